@@ -14,7 +14,7 @@ while IFS= read -r line; do
     case "$method" in
         "Initialize")
             # Respond with plugin info
-            echo "{\"id\":$id,\"result\":{\"status\":\"Initialized\",\"id\":\"example-plugin\",\"name\":\"Example Plugin\",\"version\":\"1.0.0\",\"protocol_version\":1}}"
+            echo "{\"id\":$id,\"result\":{\"status\":\"Initialized\",\"id\":\"example-plugin\",\"name\":\"Example Plugin\",\"version\":\"1.0.0\",\"protocol_version\":1,\"supported_methods\":[\"SearchTracks\"]}}"
             ;;
         "Capabilities")
             # Respond with plugin capabilities
@@ -3,7 +3,7 @@
 //! Provides metadata caching to speed up library browsing and offline mode support.
 
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::path::PathBuf;
 use std::time::{Duration, SystemTime};
 use tunez_core::models::{Album, Playlist, Track};
@@ -13,6 +13,60 @@ use tunez_core::models::{Album, Playlist, Track};
 struct CacheEntry<T> {
     data: T,
     timestamp: SystemTime,
+    /// Estimated bytes held by the entry, key included.
+    size: u64,
+    /// Recency stamp; the entry's position in `MetadataCache::order`.
+    last_used: u64,
+}
+
+/// Identifies an entry across the per-kind maps.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum CacheKey {
+    Track(PathBuf),
+    Album(String),
+    Playlist(String),
+}
+
+/// Approximate in-memory footprint, counting string contents.
+trait EstimateSize {
+    fn estimated_size(&self) -> u64;
+}
+
+fn opt_len(value: &Option<String>) -> usize {
+    value.as_ref().map_or(0, String::len)
+}
+
+impl EstimateSize for Track {
+    fn estimated_size(&self) -> u64 {
+        (std::mem::size_of::<Track>()
+            + self.id.0.len()
+            + self.provider_id.len()
+            + self.title.len()
+            + self.artist.len()
+            + opt_len(&self.album)
+            + opt_len(&self.genre)
+            + opt_len(&self.musicbrainz_id)) as u64
+    }
+}
+
+impl EstimateSize for Album {
+    fn estimated_size(&self) -> u64 {
+        (std::mem::size_of::<Album>()
+            + self.id.0.len()
+            + self.provider_id.len()
+            + self.title.len()
+            + self.artist.len()) as u64
+    }
+}
+
+impl EstimateSize for Playlist {
+    fn estimated_size(&self) -> u64 {
+        (std::mem::size_of::<Playlist>()
+            + self.id.0.len()
+            + self.provider_id.len()
+            + self.name.len()
+            + opt_len(&self.description)) as u64
+    }
 }
 
 /// Cache configuration
@@ -42,7 +96,8 @@ impl Default for CacheConfig {
     }
 }
 
-/// Metadata cache for filesystem provider
+/// Metadata cache for filesystem provider, evicting least-recently-used
+/// entries once `max_size_bytes` is exceeded.
 #[derive(Debug, Clone)]
 pub struct MetadataCache {
     /// Track cache: file path -> track metadata
@@ -51,10 +106,16 @@ pub struct MetadataCache {
     albums: HashMap<String, CacheEntry<Album>>,
     /// Playlist cache: playlist id -> playlist metadata
     playlists: HashMap<String, CacheEntry<Playlist>>,
+    /// Every entry by recency stamp, least recently used first.
+    order: BTreeMap<u64, CacheKey>,
+    /// Next recency stamp to hand out.
+    clock: u64,
     /// Configuration
     config: CacheConfig,
     /// Current size in bytes
     current_size: u64,
+    hits: u64,
+    misses: u64,
 }
 
 impl MetadataCache {
@@ -63,148 +124,136 @@ impl MetadataCache {
             tracks: HashMap::new(),
             albums: HashMap::new(),
             playlists: HashMap::new(),
+            order: BTreeMap::new(),
+            clock: 0,
             config,
             current_size: 0,
+            hits: 0,
+            misses: 0,
         }
     }
 
     /// Add a track to the cache
     pub fn add_track(&mut self, path: PathBuf, track: Track) {
-        if !self.config.cache_metadata {
-            return;
+        let size = track.estimated_size() + path.as_os_str().len() as u64;
+        if let Some(entry) = self.make_entry(CacheKey::Track(path.clone()), track, size) {
+            self.tracks.insert(path, entry);
         }
-
-        // Calculate approximate size
-        let size = std::mem::size_of_val(&track) as u64;
-
-        if self.current_size + size > self.config.max_size_bytes {
-            self.evict_old_entries();
-        }
-
-        let entry = CacheEntry {
-            data: track,
-            timestamp: SystemTime::now(),
-        };
-
-        self.current_size += size;
-        self.tracks.insert(path, entry);
     }
 
     /// Get a track from the cache if it's still valid
-    pub fn get_track(&self, path: &PathBuf) -> Option<&Track> {
-        if let Some(entry) = self.tracks.get(path) {
-            if self.is_entry_valid(entry) {
-                return Some(&entry.data);
-            }
-        }
-        None
+    pub fn get_track(&mut self, path: &PathBuf) -> Option<&Track> {
+        self.touch(CacheKey::Track(path.clone()))?;
+        self.tracks.get(path).map(|entry| &entry.data)
     }
 
     /// Add an album to the cache
     pub fn add_album(&mut self, id: String, album: Album) {
-        if !self.config.cache_metadata {
-            return;
+        let size = album.estimated_size() + id.len() as u64;
+        if let Some(entry) = self.make_entry(CacheKey::Album(id.clone()), album, size) {
+            self.albums.insert(id, entry);
         }
-
-        // Calculate approximate size
-        let size = std::mem::size_of_val(&album) as u64;
-
-        if self.current_size + size > self.config.max_size_bytes {
-            self.evict_old_entries();
-        }
-
-        let entry = CacheEntry {
-            data: album,
-            timestamp: SystemTime::now(),
-        };
-
-        self.current_size += size;
-        self.albums.insert(id, entry);
     }
 
     /// Get an album from the cache if it's still valid
-    pub fn get_album(&self, id: &str) -> Option<&Album> {
-        if let Some(entry) = self.albums.get(id) {
-            if self.is_entry_valid(entry) {
-                return Some(&entry.data);
-            }
-        }
-        None
+    pub fn get_album(&mut self, id: &str) -> Option<&Album> {
+        self.touch(CacheKey::Album(id.to_string()))?;
+        self.albums.get(id).map(|entry| &entry.data)
     }
 
     /// Add a playlist to the cache
     pub fn add_playlist(&mut self, id: String, playlist: Playlist) {
-        if !self.config.cache_metadata {
-            return;
+        let size = playlist.estimated_size() + id.len() as u64;
+        if let Some(entry) = self.make_entry(CacheKey::Playlist(id.clone()), playlist, size) {
+            self.playlists.insert(id, entry);
         }
+    }
 
-        // Calculate approximate size
-        let size = std::mem::size_of_val(&playlist) as u64;
+    /// Get a playlist from the cache if it's still valid
+    pub fn get_playlist(&mut self, id: &str) -> Option<&Playlist> {
+        self.touch(CacheKey::Playlist(id.to_string()))?;
+        self.playlists.get(id).map(|entry| &entry.data)
+    }
 
-        if self.current_size + size > self.config.max_size_bytes {
-            self.evict_old_entries();
+    /// Replace any entry under `key` and make room for `size` more bytes,
+    /// returning the entry to insert. `None` when metadata caching is off or
+    /// the entry alone exceeds the budget.
+    fn make_entry<T>(&mut self, key: CacheKey, data: T, size: u64) -> Option<CacheEntry<T>> {
+        if !self.config.cache_metadata {
+            return None;
+        }
+        self.remove(&key);
+        if size > self.config.max_size_bytes {
+            return None;
+        }
+        while self.current_size + size > self.config.max_size_bytes {
+            let Some((_, lru)) = self.order.pop_first() else {
+                break;
+            };
+            self.remove(&lru);
         }
 
-        let entry = CacheEntry {
-            data: playlist,
-            timestamp: SystemTime::now(),
-        };
-
+        let last_used = self.next_stamp();
+        self.order.insert(last_used, key);
         self.current_size += size;
-        self.playlists.insert(id, entry);
+        Some(CacheEntry {
+            data,
+            timestamp: SystemTime::now(),
+            size,
+            last_used,
+        })
     }
 
-    /// Get a playlist from the cache if it's still valid
-    pub fn get_playlist(&self, id: &str) -> Option<&Playlist> {
-        if let Some(entry) = self.playlists.get(id) {
-            if self.is_entry_valid(entry) {
-                return Some(&entry.data);
+    /// Mark `key` as just used, counting a hit, or count a miss if it is
+    /// absent. Expired entries are dropped and count as misses.
+    fn touch(&mut self, key: CacheKey) -> Option<()> {
+        let max_age = Duration::from_secs(self.config.max_age_seconds);
+        let stamp = match &key {
+            CacheKey::Track(path) => self.tracks.get(path).map(|e| fresh(e, max_age)),
+            CacheKey::Album(id) => self.albums.get(id).map(|e| fresh(e, max_age)),
+            CacheKey::Playlist(id) => self.playlists.get(id).map(|e| fresh(e, max_age)),
+        };
+        let old_stamp = match stamp {
+            Some(Some(stamp)) => stamp,
+            Some(None) => {
+                self.remove(&key);
+                self.misses += 1;
+                return None;
+            }
+            None => {
+                self.misses += 1;
+                return None;
             }
+        };
+
+        let new_stamp = self.next_stamp();
+        self.order.remove(&old_stamp);
+        self.order.insert(new_stamp, key.clone());
+        match &key {
+            CacheKey::Track(path) => set_stamp(self.tracks.get_mut(path), new_stamp),
+            CacheKey::Album(id) => set_stamp(self.albums.get_mut(id), new_stamp),
+            CacheKey::Playlist(id) => set_stamp(self.playlists.get_mut(id), new_stamp),
         }
-        None
+        self.hits += 1;
+        Some(())
     }
 
-    /// Check if a cache entry is still valid (not expired)
-    fn is_entry_valid<T>(&self, entry: &CacheEntry<T>) -> bool {
-        match entry.timestamp.elapsed() {
-            Ok(duration) => duration < Duration::from_secs(self.config.max_age_seconds),
-            Err(_) => false, // Clock went backwards
+    /// Drop the entry under `key`, if any, releasing its size.
+    fn remove(&mut self, key: &CacheKey) {
+        let removed = match key {
+            CacheKey::Track(path) => self.tracks.remove(path).map(|e| (e.size, e.last_used)),
+            CacheKey::Album(id) => self.albums.remove(id).map(|e| (e.size, e.last_used)),
+            CacheKey::Playlist(id) => self.playlists.remove(id).map(|e| (e.size, e.last_used)),
+        };
+        if let Some((size, last_used)) = removed {
+            self.current_size -= size;
+            self.order.remove(&last_used);
         }
     }
 
-    /// Evict old entries to make space
-    fn evict_old_entries(&mut self) {
-        let max_age = Duration::from_secs(self.config.max_age_seconds);
-
-        // Remove old entries
-        self.tracks.retain(|_, entry| {
-            if let Ok(duration) = entry.timestamp.elapsed() {
-                duration < max_age
-            } else {
-                false // Remove if clock went backwards
-            }
-        });
-
-        self.albums.retain(|_, entry| {
-            if let Ok(duration) = entry.timestamp.elapsed() {
-                duration < max_age
-            } else {
-                false
-            }
-        });
-
-        self.playlists.retain(|_, entry| {
-            if let Ok(duration) = entry.timestamp.elapsed() {
-                duration < max_age
-            } else {
-                false
-            }
-        });
-
-        // Recalculate size (approximate)
-        self.current_size = self.tracks.len() as u64 * 1024 + // Approximate size per entry
-                            self.albums.len() as u64 * 512 +
-                            self.playlists.len() as u64 * 512;
+    fn next_stamp(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
     }
 
     /// Clear the entire cache
@@ -212,6 +261,7 @@ impl MetadataCache {
         self.tracks.clear();
         self.albums.clear();
         self.playlists.clear();
+        self.order.clear();
         self.current_size = 0;
     }
 
@@ -223,10 +273,26 @@ impl MetadataCache {
             album_count: self.albums.len(),
             playlist_count: self.playlists.len(),
             estimated_size_bytes: self.current_size,
+            hits: self.hits,
+            misses: self.misses,
         }
     }
 }
 
+/// The entry's recency stamp if it hasn't expired.
+fn fresh<T>(entry: &CacheEntry<T>, max_age: Duration) -> Option<u64> {
+    match entry.timestamp.elapsed() {
+        Ok(age) if age < max_age => Some(entry.last_used),
+        _ => None, // Expired, or the clock went backwards
+    }
+}
+
+fn set_stamp<T>(entry: Option<&mut CacheEntry<T>>, stamp: u64) {
+    if let Some(entry) = entry {
+        entry.last_used = stamp;
+    }
+}
+
 /// Cache statistics
 #[derive(Debug, Clone)]
 #[allow(dead_code)]
@@ -235,6 +301,10 @@ pub struct CacheStats {
     pub album_count: usize,
     pub playlist_count: usize,
     pub estimated_size_bytes: u64,
+    /// Lookups answered from the cache.
+    pub hits: u64,
+    /// Lookups that found nothing, or only an expired entry.
+    pub misses: u64,
 }
 
 #[cfg(test)]
@@ -262,6 +332,11 @@ mod tests {
             album: Some("Test Album".into()),
             duration_seconds: Some(180),
             track_number: Some(1),
+            disc_number: None,
+            year: None,
+            genre: None,
+            musicbrainz_id: None,
+            replay_gain: Default::default(),
         };
 
         cache.add_track(path.clone(), track.clone());
@@ -274,8 +349,10 @@ mod tests {
         use std::thread;
         use std::time::Duration as StdDuration;
 
-        let mut config = CacheConfig::default();
-        config.max_age_seconds = 1; // 1 second for testing
+        let config = CacheConfig {
+            max_age_seconds: 1, // 1 second for testing
+            ..Default::default()
+        };
         let mut cache = MetadataCache::new(config);
 
         let path = PathBuf::from("/test/song.mp3");
@@ -287,6 +364,11 @@ mod tests {
             album: Some("Test Album".into()),
             duration_seconds: Some(180),
             track_number: Some(1),
+            disc_number: None,
+            year: None,
+            genre: None,
+            musicbrainz_id: None,
+            replay_gain: Default::default(),
         };
 
         cache.add_track(path.clone(), track);
@@ -294,5 +376,79 @@ mod tests {
 
         let retrieved = cache.get_track(&path);
         assert_eq!(retrieved, None);
+        assert_eq!(cache.stats().misses, 1);
+        assert_eq!(cache.stats().estimated_size_bytes, 0);
+    }
+
+    fn album(id: &str) -> Album {
+        Album {
+            id: id.into(),
+            provider_id: "filesystem".into(),
+            title: format!("Album {id}"),
+            artist: "Artist".into(),
+            track_count: None,
+            duration_seconds: None,
+            year: None,
+            added_at: None,
+        }
+    }
+
+    /// A cache with room for exactly `n` of the `album` entries above.
+    fn cache_for_albums(n: u64) -> MetadataCache {
+        let size = album("a").estimated_size() + 1;
+        MetadataCache::new(CacheConfig {
+            max_size_bytes: size * n,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn size_estimate_counts_strings() {
+        let short = album("a");
+        let mut long = album("a");
+        long.title = "x".repeat(1000);
+        assert_eq!(
+            long.estimated_size() - short.estimated_size(),
+            (1000 - short.title.len()) as u64
+        );
+    }
+
+    #[test]
+    fn inserting_past_budget_evicts_least_recently_added() {
+        let mut cache = cache_for_albums(2);
+        cache.add_album("a".into(), album("a"));
+        cache.add_album("b".into(), album("b"));
+        cache.add_album("c".into(), album("c"));
+
+        assert!(cache.get_album("a").is_none());
+        assert!(cache.get_album("b").is_some());
+        assert!(cache.get_album("c").is_some());
+        let stats = cache.stats();
+        assert_eq!(stats.album_count, 2);
+        assert_eq!(stats.estimated_size_bytes, cache.config.max_size_bytes);
+        assert_eq!((stats.hits, stats.misses), (2, 1));
+    }
+
+    #[test]
+    fn get_refreshes_recency() {
+        let mut cache = cache_for_albums(2);
+        cache.add_album("a".into(), album("a"));
+        cache.add_album("b".into(), album("b"));
+        assert!(cache.get_album("a").is_some());
+        cache.add_album("c".into(), album("c"));
+
+        assert!(cache.get_album("a").is_some());
+        assert!(cache.get_album("b").is_none());
+        assert!(cache.get_album("c").is_some());
+    }
+
+    #[test]
+    fn replacing_an_entry_does_not_double_count() {
+        let mut cache = cache_for_albums(2);
+        cache.add_album("a".into(), album("a"));
+        let size = cache.stats().estimated_size_bytes;
+        cache.add_album("a".into(), album("a"));
+        assert_eq!(cache.stats().estimated_size_bytes, size);
+        assert_eq!(cache.stats().album_count, 1);
     }
 }
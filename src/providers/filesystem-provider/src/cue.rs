@@ -0,0 +1,180 @@
+//! Minimal cue sheet parsing, for albums ripped to a single file.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// CD frames per second, the unit of the last field of an `INDEX` time.
+const FRAMES_PER_SECOND: u64 = 75;
+
+/// The album-level fields of a cue sheet and its audio tracks.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CueSheet {
+    pub title: Option<String>,
+    pub performer: Option<String>,
+    pub tracks: Vec<CueTrack>,
+}
+
+/// One audio track from a cue sheet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CueTrack {
+    /// The `FILE` the track is in, resolved against the sheet's directory.
+    pub path: PathBuf,
+    pub number: u32,
+    pub title: Option<String>,
+    pub performer: Option<String>,
+    /// Where `INDEX 01` puts the start of the track within its file.
+    pub start: Duration,
+}
+
+/// Parse cue sheet `contents`, resolving `FILE` names against `base_dir`.
+///
+/// Data tracks, and tracks with no `INDEX 01`, are left out. Commands other
+/// than `FILE`, `TRACK`, `TITLE`, `PERFORMER` and `INDEX` are ignored.
+pub fn parse(contents: &str, base_dir: &Path) -> CueSheet {
+    let mut sheet = CueSheet::default();
+    let mut file: Option<PathBuf> = None;
+    // The audio track being read, until the next `TRACK` or `FILE`.
+    let mut track: Option<PendingTrack> = None;
+    let mut seen_track = false;
+
+    for line in contents.trim_start_matches('\u{feff}').lines() {
+        let line = line.trim();
+        let (command, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let rest = rest.trim();
+        match command.to_ascii_uppercase().as_str() {
+            "FILE" => {
+                finish(track.take(), &file, &mut sheet);
+                // The type (WAVE, MP3, ...) follows the name.
+                let name = match rest.strip_prefix('"') {
+                    Some(quoted) => quoted.split('"').next().unwrap_or_default(),
+                    None => rest.rsplit_once(' ').map_or(rest, |(name, _)| name),
+                };
+                file = Some(base_dir.join(name));
+            }
+            "TRACK" => {
+                finish(track.take(), &file, &mut sheet);
+                seen_track = true;
+                let mut fields = rest.split_whitespace();
+                let number = fields.next().and_then(|n| n.parse().ok());
+                let audio = fields
+                    .next()
+                    .is_some_and(|kind| kind.eq_ignore_ascii_case("AUDIO"));
+                track = number.filter(|_| audio).map(|number| PendingTrack {
+                    number,
+                    ..Default::default()
+                });
+            }
+            "TITLE" | "PERFORMER" => {
+                let value = Some(unquote(rest)).filter(|v| !v.is_empty());
+                let is_title = command.eq_ignore_ascii_case("TITLE");
+                match (&mut track, is_title) {
+                    (Some(track), true) => track.title = value,
+                    (Some(track), false) => track.performer = value,
+                    // Before the first track: the album's.
+                    (None, true) if !seen_track => sheet.title = value,
+                    (None, false) if !seen_track => sheet.performer = value,
+                    (None, _) => {}
+                }
+            }
+            "INDEX" => {
+                let mut fields = rest.split_whitespace();
+                let is_start = fields.next().and_then(|n| n.parse::<u32>().ok()) == Some(1);
+                if let (true, Some(track)) = (is_start, &mut track) {
+                    track.start = fields.next().and_then(parse_time);
+                }
+            }
+            _ => {}
+        }
+    }
+    finish(track, &file, &mut sheet);
+    sheet
+}
+
+/// A track whose commands are still being read.
+#[derive(Default)]
+struct PendingTrack {
+    number: u32,
+    title: Option<String>,
+    performer: Option<String>,
+    start: Option<Duration>,
+}
+
+/// Add `track` to `sheet` if it was complete.
+fn finish(track: Option<PendingTrack>, file: &Option<PathBuf>, sheet: &mut CueSheet) {
+    let Some(track) = track else {
+        return;
+    };
+    if let (Some(start), Some(path)) = (track.start, file) {
+        sheet.tracks.push(CueTrack {
+            path: path.clone(),
+            number: track.number,
+            title: track.title,
+            performer: track.performer,
+            start,
+        });
+    }
+}
+
+/// The value of a command, without its quotes if it has them.
+fn unquote(value: &str) -> String {
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .unwrap_or(value)
+        .to_string()
+}
+
+/// Parse an `mm:ss:ff` time, where `ff` counts 1/75 s frames.
+fn parse_time(time: &str) -> Option<Duration> {
+    let mut parts = time.split(':').map(|p| p.parse::<u64>().ok());
+    let (minutes, seconds, frames) = (parts.next()??, parts.next()??, parts.next()??);
+    if parts.next().is_some() || seconds >= 60 || frames >= FRAMES_PER_SECOND {
+        return None;
+    }
+    let millis = (minutes * 60 + seconds) * 1000 + frames * 1000 / FRAMES_PER_SECOND;
+    Some(Duration::from_millis(millis))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_a_two_track_single_file_album() {
+        let contents = "\u{feff}REM GENRE Rock\r\nPERFORMER \"The Band\"\r\nTITLE \"Live Album\"\r\nFILE \"Live Album.flac\" WAVE\r\n  TRACK 01 AUDIO\r\n    TITLE \"Opener\"\r\n    INDEX 01 00:00:00\r\n  TRACK 02 AUDIO\r\n    TITLE \"Encore\"\r\n    PERFORMER \"The Band & Guest\"\r\n    INDEX 00 04:03:00\r\n    INDEX 01 04:05:25\r\n";
+        let sheet = parse(contents, Path::new("/music/live"));
+        assert_eq!(sheet.title.as_deref(), Some("Live Album"));
+        assert_eq!(sheet.performer.as_deref(), Some("The Band"));
+        assert_eq!(
+            sheet.tracks,
+            [
+                CueTrack {
+                    path: PathBuf::from("/music/live/Live Album.flac"),
+                    number: 1,
+                    title: Some("Opener".into()),
+                    performer: None,
+                    start: Duration::ZERO,
+                },
+                CueTrack {
+                    path: PathBuf::from("/music/live/Live Album.flac"),
+                    number: 2,
+                    title: Some("Encore".into()),
+                    performer: Some("The Band & Guest".into()),
+                    // The pregap (INDEX 00) belongs to the track before.
+                    start: Duration::from_millis(245_333),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn data_tracks_and_bad_times_are_skipped() {
+        let contents = "FILE disc.bin BINARY\nTRACK 01 MODE1/2352\nINDEX 01 00:00:00\nTRACK 02 AUDIO\nTITLE \"Song\"\nINDEX 01 03:00:80\nTRACK 03 AUDIO\nINDEX 01 05:00:00\n";
+        let sheet = parse(contents, Path::new("/cd"));
+        assert_eq!(sheet.title, None);
+        assert_eq!(sheet.tracks.len(), 1);
+        assert_eq!(sheet.tracks[0].number, 3);
+        assert_eq!(sheet.tracks[0].path, PathBuf::from("/cd/disc.bin"));
+        assert_eq!(sheet.tracks[0].start, Duration::from_secs(300));
+    }
+}
@@ -1,17 +1,41 @@
 mod cache;
+mod cue;
+mod m3u;
 mod scan;
 mod tags;
+mod watch;
 
 use cache::{CacheConfig, MetadataCache};
-use scan::{scan_library_with_options, LibraryIndex, ScanOptions};
-use std::sync::{Arc, RwLock};
+use scan::rescan_incremental;
+
+pub use m3u::M3uEntry;
+use path_clean::PathClean;
+pub use scan::{
+    scan_library_with_options, LibraryIndex, ScanFailure, ScanFailureKind, ScanOptions, ScanStats,
+};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+pub use tags::{ParsedTags, TagReader, TagReaders};
+use tunez_core::lyrics::Lyrics;
 use tunez_core::models::{
-    Album, AlbumId, Page, PageCursor, PageRequest, Playlist, PlaylistId, StreamUrl, Track, TrackId,
+    Album, AlbumId, Artwork, Chapter, Page, PageCursor, PageRequest, Playlist, PlaylistId,
+    StreamUrl, Track, TrackId,
 };
 use tunez_core::provider::{
     BrowseKind, CollectionItem, Provider, ProviderCapabilities, ProviderError, ProviderResult,
-    TrackSearchFilters,
+    SortOrder, TrackSearchFilters,
 };
+use tunez_core::{fuzzy_score, fuzzy_score_any, ElapsedGuard, PlayHistory};
+use watch::WatchHandle;
+
+/// A library root and the name it's listed under.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Source {
+    /// The root as configured.
+    pub root: String,
+    pub label: String,
+}
 
 #[derive(Clone, Debug)]
 pub struct FilesystemProvider {
@@ -20,8 +44,13 @@ pub struct FilesystemProvider {
     index: Arc<RwLock<LibraryIndex>>,
     capabilities: Arc<RwLock<ProviderCapabilities>>,
     roots: Vec<String>,
+    /// Labels given to roots, by root as configured.
+    labels: HashMap<String, String>,
     options: ScanOptions,
     cache: Arc<RwLock<MetadataCache>>,
+    watcher: Arc<Mutex<Option<WatchHandle>>>,
+    /// Backs `list_recently_played`; the player writes to the same file.
+    history: Option<PlayHistory>,
 }
 
 impl FilesystemProvider {
@@ -31,7 +60,7 @@ impl FilesystemProvider {
 
     pub fn with_options(roots: Vec<String>, options: ScanOptions) -> Result<Self, ProviderError> {
         let index = scan_library_with_options(roots.clone(), options.clone())?;
-        let caps = Self::capabilities_from_index(&index);
+        let caps = Self::capabilities_from_index(&index, false);
         let cache = MetadataCache::new(CacheConfig::default());
         Ok(Self {
             id: "filesystem".into(),
@@ -39,33 +68,184 @@ impl FilesystemProvider {
             index: Arc::new(RwLock::new(index)),
             capabilities: Arc::new(RwLock::new(caps)),
             roots,
+            labels: HashMap::new(),
             options,
             cache: Arc::new(RwLock::new(cache)),
+            watcher: Arc::default(),
+            history: None,
+        })
+    }
+
+    /// Answer `list_recently_played` from the local play history.
+    pub fn with_play_history(mut self, history: PlayHistory) -> Self {
+        self.history = Some(history);
+        self.capabilities
+            .write()
+            .expect("capabilities poisoned")
+            .recently_played = true;
+        self
+    }
+
+    /// List `root` as `label` in [`sources`](Self::sources) rather than by
+    /// its directory name.
+    pub fn with_label(mut self, root: &str, label: impl Into<String>) -> Self {
+        self.labels.insert(root.to_string(), label.into());
+        self
+    }
+
+    /// The library roots, in the order they're scanned.
+    pub fn sources(&self) -> Vec<Source> {
+        self.roots
+            .iter()
+            .map(|root| Source {
+                root: root.clone(),
+                label: self
+                    .labels
+                    .get(root)
+                    .cloned()
+                    .unwrap_or_else(|| default_label(root)),
+            })
+            .collect()
+    }
+
+    /// A provider that searches and browses only the source labelled
+    /// `source` (or configured as that root), sharing this one's index
+    /// as it stands. A file under overlapping roots belongs to both.
+    pub fn scoped_to(&self, source: &str) -> ProviderResult<Self> {
+        let found = self
+            .sources()
+            .into_iter()
+            .find(|s| s.label == source)
+            .or_else(|| self.sources().into_iter().find(|s| s.root == source))
+            .ok_or_else(|| ProviderError::NotFound {
+                entity: source.to_string(),
+            })?;
+        let root_path = PathBuf::from(&found.root)
+            .canonicalize()
+            .map_err(|err| ProviderError::Other {
+                message: format!("library root {} is not accessible: {err}", found.root),
+            })?
+            .clean();
+        let index = self
+            .index
+            .read()
+            .expect("index poisoned")
+            .within(&root_path);
+        let caps = Self::capabilities_from_index(&index, self.history.is_some());
+        Ok(Self {
+            index: Arc::new(RwLock::new(index)),
+            capabilities: Arc::new(RwLock::new(caps)),
+            labels: self
+                .labels
+                .get(&found.root)
+                .map(|label| (found.root.clone(), label.clone()))
+                .into_iter()
+                .collect(),
+            roots: vec![found.root],
+            cache: Arc::new(RwLock::new(MetadataCache::new(CacheConfig::default()))),
+            watcher: Arc::default(),
+            ..self.clone()
         })
     }
 
-    pub fn rescan(&self) -> Result<(), ProviderError> {
+    /// Rescan the library, re-reading tags only for new or modified files.
+    pub fn rescan(&self) -> Result<ScanStats, ProviderError> {
+        let previous = self.index.read().expect("index poisoned").clone();
+        let (new_index, stats) = rescan_incremental(&previous, self.roots.clone(), &self.options)?;
+        self.replace_index(new_index);
+        tracing::debug!(
+            parsed = stats.parsed,
+            reused = stats.reused,
+            removed = stats.removed,
+            "filesystem library rescanned"
+        );
+        Ok(stats)
+    }
+
+    /// Discard the current index and rebuild it from scratch.
+    pub fn rescan_full(&self) -> Result<(), ProviderError> {
         let new_index = scan_library_with_options(self.roots.clone(), self.options.clone())?;
+        self.replace_index(new_index);
+        Ok(())
+    }
+
+    /// Playlist entries that don't match a file in the library, with any
+    /// `#EXTINF` title/duration hints.
+    pub fn unresolved_playlist_entries(
+        &self,
+        playlist_id: &PlaylistId,
+    ) -> ProviderResult<Vec<M3uEntry>> {
+        let index = self.index.read().expect("index poisoned");
+        index
+            .playlists
+            .get(playlist_id)
+            .map(|entry| entry.unresolved.clone())
+            .ok_or_else(|| ProviderError::NotFound {
+                entity: playlist_id.0.clone(),
+            })
+    }
+
+    /// Paths the last scan couldn't read.
+    pub fn scan_failures(&self) -> Vec<ScanFailure> {
+        self.index.read().expect("index poisoned").failures.clone()
+    }
+
+    /// Watch the library roots and apply incremental rescans as files change.
+    ///
+    /// Bursts of events are debounced into a single rescan. Calling this while
+    /// already watching is a no-op. The watcher stops on [`stop_watch`] or when
+    /// the last clone of the provider is dropped.
+    ///
+    /// [`stop_watch`]: Self::stop_watch
+    pub fn watch(&self) -> Result<(), ProviderError> {
+        let mut guard = self.watcher.lock().expect("watcher poisoned");
+        if guard.is_some() {
+            return Ok(());
+        }
+        // The worker gets its own watcher slot so it doesn't keep ours alive.
+        let worker = Self {
+            watcher: Arc::default(),
+            ..self.clone()
+        };
+        *guard = Some(WatchHandle::spawn(&self.roots, move || {
+            if let Err(err) = worker.rescan() {
+                tracing::warn!(error = %err, "filesystem rescan after change failed");
+            }
+        })?);
+        Ok(())
+    }
+
+    /// Stop watching the library roots, waiting for any in-flight rescan.
+    pub fn stop_watch(&self) {
+        let handle = self.watcher.lock().expect("watcher poisoned").take();
+        drop(handle);
+    }
+
+    /// Whether a watcher started by [`watch`](Self::watch) is running.
+    pub fn is_watching(&self) -> bool {
+        self.watcher.lock().expect("watcher poisoned").is_some()
+    }
+
+    fn replace_index(&self, new_index: LibraryIndex) {
         let mut guard = self.index.write().expect("index poisoned");
         *guard = new_index;
-        let caps = Self::capabilities_from_index(&guard);
+        let caps = Self::capabilities_from_index(&guard, self.history.is_some());
         let mut caps_guard = self.capabilities.write().expect("capabilities poisoned");
         *caps_guard = caps;
 
         // Clear cache on rescan
         let mut cache_guard = self.cache.write().expect("cache poisoned");
         cache_guard.clear();
-
-        Ok(())
     }
 
-    fn capabilities_from_index(index: &LibraryIndex) -> ProviderCapabilities {
+    fn capabilities_from_index(index: &LibraryIndex, history: bool) -> ProviderCapabilities {
         ProviderCapabilities {
             playlists: !index.playlists.is_empty(),
-            lyrics: false,
-            artwork: false,
+            // Sidecar .lrc files can appear at any time, so always look.
+            lyrics: true,
+            artwork: !index.with_artwork.is_empty(),
             favorites: false,
-            recently_played: false,
+            recently_played: history,
             offline_download: true,
         }
     }
@@ -84,47 +264,56 @@ impl Provider for FilesystemProvider {
         *self.capabilities.read().expect("capabilities poisoned")
     }
 
+    #[tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(query_len = query.len(), elapsed_ms = tracing::field::Empty)
+    )]
     fn search_tracks(
         &self,
         query: &str,
-        _filters: TrackSearchFilters,
+        filters: TrackSearchFilters,
         paging: PageRequest,
     ) -> ProviderResult<Page<Track>> {
+        let _elapsed = ElapsedGuard::start();
         let index = self.index.read().expect("index poisoned");
-        let q = query.to_ascii_lowercase();
-        let mut items: Vec<Track> = index
+        // Best matches first; clone only what matched.
+        let mut matches: Vec<(u32, &Track)> = index
             .tracks
             .iter()
-            .filter(|t| {
-                t.title.to_ascii_lowercase().contains(&q)
-                    || t.artist.to_ascii_lowercase().contains(&q)
-                    || t.album
-                        .as_ref()
-                        .map(|a| a.to_ascii_lowercase().contains(&q))
-                        .unwrap_or(false)
+            .filter(|t| matches_filters(t, &filters))
+            .filter_map(|t| {
+                let fields = [Some(t.title.as_str()), Some(&t.artist), t.album.as_deref()];
+                fuzzy_score_any(query, fields.into_iter().flatten()).map(|score| (score, t))
             })
-            .cloned()
             .collect();
-        items.sort_by(|a, b| a.title.cmp(&b.title).then_with(|| a.id.0.cmp(&b.id.0)));
-        let start = paging.offset as usize;
-        let end = start.saturating_add(paging.limit as usize);
-        let next = if end < items.len() {
-            Some(PageCursor(end.to_string()))
-        } else {
-            None
-        };
-        let slice = items
-            .into_iter()
-            .skip(start)
-            .take(paging.limit as usize)
-            .collect::<Vec<_>>();
-        Ok(Page { items: slice, next })
+        matches.sort_by(|(a_score, a), (b_score, b)| {
+            b_score
+                .cmp(a_score)
+                .then_with(|| a.title.cmp(&b.title))
+                .then_with(|| a.id.0.cmp(&b.id.0))
+        });
+        let page = page_of(matches, paging);
+        Ok(Page {
+            items: page.items.into_iter().map(|(_, t)| t.clone()).collect(),
+            next: page.next,
+        })
     }
 
     fn browse(
         &self,
         kind: BrowseKind,
         paging: PageRequest,
+    ) -> ProviderResult<Page<CollectionItem>> {
+        self.browse_sorted(kind, SortOrder::Title, paging)
+    }
+
+    /// Artists are always listed by name.
+    fn browse_sorted(
+        &self,
+        kind: BrowseKind,
+        sort: SortOrder,
+        paging: PageRequest,
     ) -> ProviderResult<Page<CollectionItem>> {
         let index = self.index.read().expect("index poisoned");
         match kind {
@@ -145,37 +334,16 @@ impl Provider for FilesystemProvider {
                     ) => a.cmp(b),
                     _ => std::cmp::Ordering::Equal,
                 });
-                let start = paging.offset as usize;
-                let end = start.saturating_add(paging.limit as usize);
-                let slice = artists
-                    .into_iter()
-                    .skip(start)
-                    .take(paging.limit as usize)
-                    .collect::<Vec<_>>();
-                let next = if end < index.artists.len() {
-                    Some(PageCursor(end.to_string()))
-                } else {
-                    None
-                };
-                Ok(Page { items: slice, next })
+                Ok(page_of(artists, paging))
             }
             BrowseKind::Albums => {
                 let mut albums: Vec<Album> = index.albums.values().cloned().collect();
-                albums.sort_by(|a, b| a.title.cmp(&b.title).then_with(|| a.id.0.cmp(&b.id.0)));
-                let start = paging.offset as usize;
-                let end = start.saturating_add(paging.limit as usize);
-                let slice = albums
-                    .into_iter()
-                    .skip(start)
-                    .take(paging.limit as usize)
-                    .map(CollectionItem::Album)
-                    .collect();
-                let next = if end < index.albums.len() {
-                    Some(PageCursor(end.to_string()))
-                } else {
-                    None
-                };
-                Ok(Page { items: slice, next })
+                albums.sort_by(|a, b| sort.compare_albums(a, b));
+                let page = page_of(albums, paging);
+                Ok(Page {
+                    items: page.items.into_iter().map(CollectionItem::Album).collect(),
+                    next: page.next,
+                })
             }
             BrowseKind::Playlists | BrowseKind::Genres => Err(ProviderError::NotSupported {
                 operation: "browse".into(),
@@ -196,19 +364,7 @@ impl Provider for FilesystemProvider {
             .map(|p| p.playlist.clone())
             .collect();
         items.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.id.0.cmp(&b.id.0)));
-        let start = paging.offset as usize;
-        let end = start.saturating_add(paging.limit as usize);
-        let next = if end < items.len() {
-            Some(PageCursor(end.to_string()))
-        } else {
-            None
-        };
-        let slice = items
-            .into_iter()
-            .skip(start)
-            .take(paging.limit as usize)
-            .collect();
-        Ok(Page { items: slice, next })
+        Ok(page_of(items, paging))
     }
 
     fn search_playlists(&self, query: &str, paging: PageRequest) -> ProviderResult<Page<Playlist>> {
@@ -218,27 +374,48 @@ impl Provider for FilesystemProvider {
             });
         }
         let index = self.index.read().expect("index poisoned");
-        let q = query.to_ascii_lowercase();
-        let mut items: Vec<Playlist> = index
+        let mut matches: Vec<(u32, &Playlist)> = index
             .playlists
             .values()
-            .filter(|p| p.playlist.name.to_ascii_lowercase().contains(&q))
-            .map(|p| p.playlist.clone())
+            .filter_map(|p| fuzzy_score(query, &p.playlist.name).map(|score| (score, &p.playlist)))
             .collect();
-        items.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.id.0.cmp(&b.id.0)));
-        let start = paging.offset as usize;
-        let end = start.saturating_add(paging.limit as usize);
-        let next = if end < items.len() {
-            Some(PageCursor(end.to_string()))
-        } else {
-            None
-        };
-        let slice = items
-            .into_iter()
-            .skip(start)
-            .take(paging.limit as usize)
+        matches.sort_by(|(a_score, a), (b_score, b)| {
+            b_score
+                .cmp(a_score)
+                .then_with(|| a.name.cmp(&b.name))
+                .then_with(|| a.id.0.cmp(&b.id.0))
+        });
+        let items: Vec<Playlist> = matches.into_iter().map(|(_, p)| p.clone()).collect();
+        Ok(page_of(items, paging))
+    }
+
+    fn search_albums(&self, query: &str, paging: PageRequest) -> ProviderResult<Page<Album>> {
+        let index = self.index.read().expect("index poisoned");
+        let q = query.to_lowercase();
+        let mut items: Vec<Album> = index
+            .albums
+            .values()
+            .filter(|a| a.title.to_lowercase().contains(&q) || a.artist.to_lowercase().contains(&q))
+            .cloned()
+            .collect();
+        items.sort_by(|a, b| a.title.cmp(&b.title).then_with(|| a.id.0.cmp(&b.id.0)));
+        Ok(page_of(items, paging))
+    }
+
+    fn search_artists(&self, query: &str, paging: PageRequest) -> ProviderResult<Page<String>> {
+        let index = self.index.read().expect("index poisoned");
+        let q = query.to_lowercase();
+        // The index keeps artists sorted.
+        let items: Vec<&String> = index
+            .artists
+            .iter()
+            .filter(|name| name.to_lowercase().contains(&q))
             .collect();
-        Ok(Page { items: slice, next })
+        let page = page_of(items, paging);
+        Ok(Page {
+            items: page.items.into_iter().cloned().collect(),
+            next: page.next,
+        })
     }
 
     fn get_playlist(&self, playlist_id: &PlaylistId) -> ProviderResult<Playlist> {
@@ -250,7 +427,7 @@ impl Provider for FilesystemProvider {
 
         // Check cache first
         {
-            let cache = self.cache.read().expect("cache poisoned");
+            let mut cache = self.cache.write().expect("cache poisoned");
             if let Some(playlist) = cache.get_playlist(&playlist_id.0) {
                 return Ok(playlist.clone());
             }
@@ -293,32 +470,37 @@ impl Provider for FilesystemProvider {
             .ok_or(ProviderError::NotFound {
                 entity: playlist_id.0.clone(),
             })?;
-        let mut tracks: Vec<Track> = entry
-            .track_ids
-            .iter()
-            .filter_map(|id| index.tracks.iter().find(|t| &t.id == id))
-            .cloned()
-            .collect();
-        tracks.sort_by(|a, b| a.title.cmp(&b.title).then_with(|| a.id.0.cmp(&b.id.0)));
-        let start = paging.offset as usize;
-        let end = start.saturating_add(paging.limit as usize);
-        let next = if end < tracks.len() {
-            Some(PageCursor(end.to_string()))
-        } else {
-            None
-        };
-        let slice = tracks
-            .into_iter()
-            .skip(start)
-            .take(paging.limit as usize)
-            .collect();
-        Ok(Page { items: slice, next })
+        Ok(playlist_tracks_page(&index, entry, paging))
+    }
+
+    /// Both from one lookup in the index, bypassing the playlist cache.
+    fn get_playlist_with_tracks(
+        &self,
+        playlist_id: &PlaylistId,
+        paging: PageRequest,
+    ) -> ProviderResult<(Playlist, Page<Track>)> {
+        if !self.capabilities().supports_playlists() {
+            return Err(ProviderError::NotSupported {
+                operation: "get_playlist_with_tracks".into(),
+            });
+        }
+        let index = self.index.read().expect("index poisoned");
+        let entry = index
+            .playlists
+            .get(playlist_id)
+            .ok_or(ProviderError::NotFound {
+                entity: playlist_id.0.clone(),
+            })?;
+        Ok((
+            entry.playlist.clone(),
+            playlist_tracks_page(&index, entry, paging),
+        ))
     }
 
     fn get_album(&self, album_id: &AlbumId) -> ProviderResult<Album> {
         // Check cache first
         {
-            let cache = self.cache.read().expect("cache poisoned");
+            let mut cache = self.cache.write().expect("cache poisoned");
             if let Some(album) = cache.get_album(&album_id.0) {
                 return Ok(album.clone());
             }
@@ -343,46 +525,41 @@ impl Provider for FilesystemProvider {
         Ok(album)
     }
 
+    fn list_artist_albums(&self, artist: &str, paging: PageRequest) -> ProviderResult<Page<Album>> {
+        let artist = artist.to_lowercase();
+        let index = self.index.read().expect("index poisoned");
+        let mut albums: Vec<Album> = index
+            .albums
+            .values()
+            .filter(|a| a.artist.to_lowercase() == artist)
+            .cloned()
+            .collect();
+        albums.sort_by(|a, b| a.title.cmp(&b.title).then_with(|| a.id.0.cmp(&b.id.0)));
+        Ok(page_of(albums, paging))
+    }
+
     fn list_album_tracks(
         &self,
         album_id: &AlbumId,
         paging: PageRequest,
     ) -> ProviderResult<Page<Track>> {
+        self.get_album(album_id)?;
         let index = self.index.read().expect("index poisoned");
-        let album = self.get_album(album_id)?; // Get album to know artist and title
         let mut tracks = index
             .tracks
             .iter()
-            .filter(|t| t.artist == album.artist && t.album == Some(album.title.clone()))
-            .cloned()
-            .collect::<Vec<_>>();
-        tracks.sort_by(|a, b| match (a.track_number, b.track_number) {
-            (Some(na), Some(nb)) => na.cmp(&nb).then_with(|| a.title.cmp(&b.title)),
-            (Some(_), None) => std::cmp::Ordering::Less,
-            (None, Some(_)) => std::cmp::Ordering::Greater,
-            (None, None) => a.title.cmp(&b.title),
-        });
-        let start = paging.offset as usize;
-        let end = start.saturating_add(paging.limit as usize);
-        let slice = tracks
-            .iter()
-            .skip(start)
-            .take(paging.limit as usize)
+            .filter(|t| index.album_id_of(t).as_ref() == Some(album_id))
             .cloned()
             .collect::<Vec<_>>();
-        let next = if end < tracks.len() {
-            Some(PageCursor(end.to_string()))
-        } else {
-            None
-        };
-        Ok(Page { items: slice, next })
+        tracks.sort_by(Track::album_order);
+        Ok(page_of(tracks, paging))
     }
 
     fn get_track(&self, track_id: &TrackId) -> ProviderResult<Track> {
         // Check cache first
         let path = std::path::PathBuf::from(&track_id.0);
         {
-            let cache = self.cache.read().expect("cache poisoned");
+            let mut cache = self.cache.write().expect("cache poisoned");
             if let Some(track) = cache.get_track(&path) {
                 return Ok(track.clone());
             }
@@ -408,25 +585,173 @@ impl Provider for FilesystemProvider {
         Ok(track)
     }
 
+    fn list_recently_played(&self, paging: PageRequest) -> ProviderResult<Page<Track>> {
+        let Some(history) = &self.history else {
+            return Err(ProviderError::NotSupported {
+                operation: "list_recently_played".into(),
+            });
+        };
+        let ids = history.recent(&self.id).map_err(|e| ProviderError::Other {
+            message: format!("failed to read play history: {e}"),
+        })?;
+        let index = self.index.read().expect("index poisoned");
+        let by_id: HashMap<&TrackId, &Track> = index.tracks.iter().map(|t| (&t.id, t)).collect();
+        // Files removed since they were played drop out of the list.
+        let tracks: Vec<Track> = ids
+            .iter()
+            .filter_map(|id| by_id.get(id).map(|t| (*t).clone()))
+            .collect();
+        Ok(page_of(tracks, paging))
+    }
+
+    #[tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(track_id = %track_id.0, elapsed_ms = tracing::field::Empty)
+    )]
     fn get_stream_url(&self, track_id: &TrackId) -> ProviderResult<StreamUrl> {
+        let _elapsed = ElapsedGuard::start();
         // Validate the file still exists before returning the URL.
         let track = self.get_track(track_id)?;
-        let path = std::path::Path::new(&track.id.0);
+        let path = scan::file_path_of(&track.id);
         if !path.exists() {
             return Err(ProviderError::NotFound {
                 entity: track.id.0.clone(),
             });
         }
-        Ok(StreamUrl(format!("file://{}", track.id.0)))
+        if scan::is_cue_track(&track.id) {
+            // Already a `file://` URL with its `#t=` fragment.
+            return Ok(StreamUrl::new(track.id.0));
+        }
+        Ok(StreamUrl::file(&path))
+    }
+
+    /// Healthy while every library root can still be listed, e.g. a network
+    /// share or removable drive that is mounted.
+    fn health_check(&self) -> ProviderResult<()> {
+        for root in &self.roots {
+            std::fs::read_dir(root).map_err(|e| ProviderError::Other {
+                message: format!("library root {root} is not accessible: {e}"),
+            })?;
+        }
+        Ok(())
+    }
+
+    fn get_artwork(&self, track_id: &TrackId) -> ProviderResult<Artwork> {
+        let not_found = || ProviderError::NotFound {
+            entity: format!("artwork for track {}", track_id.0),
+        };
+        let (album_id, source) = {
+            let index = self.index.read().expect("index poisoned");
+            let track = index
+                .tracks
+                .iter()
+                .find(|t| &t.id == track_id)
+                .ok_or_else(not_found)?;
+            let album_id = index.album_id_of(track);
+            if let Some(art) = album_id.as_ref().and_then(|id| index.artwork.get(id)) {
+                return Ok(art.clone());
+            }
+            // Fall back to another track on the same album when this file has no art.
+            let source = if index.with_artwork.contains(track_id) {
+                Some(track_id.clone())
+            } else {
+                album_id.as_ref().and_then(|album_id| {
+                    index
+                        .tracks
+                        .iter()
+                        .filter(|t| index.with_artwork.contains(&t.id))
+                        .find(|t| index.album_id_of(t).as_ref() == Some(album_id))
+                        .map(|t| t.id.clone())
+                })
+            };
+            (album_id, source.ok_or_else(not_found)?)
+        };
+
+        let artwork = tags::read_artwork(&scan::file_path_of(&source)).ok_or_else(not_found)?;
+        if let Some(album_id) = album_id {
+            let mut index = self.index.write().expect("index poisoned");
+            index.artwork.insert(album_id, artwork.clone());
+        }
+        Ok(artwork)
+    }
+
+    fn get_lyrics(&self, track_id: &TrackId) -> ProviderResult<Lyrics> {
+        let track = self.get_track(track_id)?;
+        tags::read_lyrics(&scan::file_path_of(&track.id)).ok_or_else(|| ProviderError::NotFound {
+            entity: format!("lyrics for track {}", track_id.0),
+        })
+    }
+
+    fn get_chapters(&self, track_id: &TrackId) -> ProviderResult<Vec<Chapter>> {
+        let track = self.get_track(track_id)?;
+        Ok(tags::read_chapters(&scan::file_path_of(&track.id)))
     }
 }
 
+/// A page of a playlist's indexed tracks, ordered by title.
+fn playlist_tracks_page(
+    index: &LibraryIndex,
+    entry: &scan::PlaylistEntry,
+    paging: PageRequest,
+) -> Page<Track> {
+    let mut tracks: Vec<Track> = entry
+        .track_ids
+        .iter()
+        .filter_map(|id| index.tracks.iter().find(|t| &t.id == id))
+        .cloned()
+        .collect();
+    tracks.sort_by(|a, b| a.title.cmp(&b.title).then_with(|| a.id.0.cmp(&b.id.0)));
+    page_of(tracks, paging)
+}
+
+/// The page of `items` that `paging` asks for, once normalized.
+fn page_of<T>(items: Vec<T>, paging: PageRequest) -> Page<T> {
+    let paging = paging.normalized();
+    let next =
+        ((paging.end() as usize) < items.len()).then(|| PageCursor::from_offset(paging.end()));
+    let items = items
+        .into_iter()
+        .skip(paging.offset as usize)
+        .take(paging.limit as usize)
+        .collect();
+    Page { items, next }
+}
+
+/// A root's directory name, or the root itself when it has none (`/`, `..`).
+fn default_label(root: &str) -> String {
+    Path::new(root)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(root)
+        .to_string()
+}
+
+/// Whether `track` satisfies every set filter. Artist and album compare
+/// case-insensitively against the whole field.
+fn matches_filters(track: &Track, filters: &TrackSearchFilters) -> bool {
+    let same =
+        |value: &str, wanted: &str| value.trim().to_lowercase() == wanted.trim().to_lowercase();
+    filters
+        .artist
+        .as_deref()
+        .is_none_or(|artist| same(&track.artist, artist))
+        && filters.album.as_deref().is_none_or(|album| {
+            track
+                .album
+                .as_deref()
+                .is_some_and(|value| same(value, album))
+        })
+        && filters.year.is_none_or(|year| track.year == Some(year))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::fs::File;
     use std::io::Write;
     use tempfile::tempdir;
+    use tracing_subscriber::fmt::format::FmtSpan;
     use tunez_core::models::TrackId;
     use tunez_core::provider_contract::{
         run_provider_contract, ProviderContractExpectations, SearchExpectation,
@@ -451,6 +776,58 @@ mod tests {
         assert!(!page.items.is_empty());
     }
 
+    /// What `f` logs at debug level, with each span's fields as it closes.
+    fn debug_log(f: impl FnOnce()) -> String {
+        #[derive(Clone, Default)]
+        struct Buffer(Arc<Mutex<Vec<u8>>>);
+        impl std::io::Write for Buffer {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().extend_from_slice(buf);
+                Ok(buf.len())
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let buffer = Buffer::default();
+        let writer = buffer.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::DEBUG)
+            .with_span_events(FmtSpan::CLOSE)
+            .with_ansi(false)
+            .with_writer(move || writer.clone())
+            .finish();
+        tracing::subscriber::with_default(subscriber, f);
+        let log = buffer.0.lock().unwrap().clone();
+        String::from_utf8(log).unwrap()
+    }
+
+    #[test]
+    fn search_records_its_elapsed_time() {
+        let dir = tempdir().unwrap();
+        writeln!(File::create(dir.path().join("song.mp3")).unwrap(), "fake").unwrap();
+        let provider =
+            FilesystemProvider::new(vec![dir.path().to_string_lossy().to_string()]).unwrap();
+
+        let log = debug_log(|| {
+            provider
+                .search_tracks(
+                    "song",
+                    TrackSearchFilters::default(),
+                    PageRequest::first_page(10),
+                )
+                .unwrap();
+        });
+
+        let closed = log
+            .lines()
+            .find(|line| line.contains("search_tracks{") && line.contains("close"))
+            .unwrap_or_else(|| panic!("no search span closed in:\n{log}"));
+        assert!(closed.contains("query_len=4"), "{closed}");
+        assert!(closed.contains("elapsed_ms="), "{closed}");
+    }
+
     #[test]
     fn provider_contract_passes() {
         let dir = tempdir().unwrap();
@@ -459,13 +836,7 @@ mod tests {
         writeln!(f, "fake").unwrap();
         let provider =
             FilesystemProvider::new(vec![dir.path().to_string_lossy().to_string()]).unwrap();
-        let track_id = TrackId::new(
-            file_path
-                .canonicalize()
-                .unwrap()
-                .to_string_lossy()
-                .to_string(),
-        );
+        let track_id = scan::track_id_for(&file_path).unwrap();
 
         let expectations = ProviderContractExpectations {
             provider_id: "filesystem".into(),
@@ -480,4 +851,1241 @@ mod tests {
 
         run_provider_contract(&provider, &expectations).unwrap();
     }
+
+    #[test]
+    fn recently_played_follows_local_history() {
+        let dir = tempdir().unwrap();
+        let data = tempdir().unwrap();
+        write_fake(&dir.path().join("one.mp3"));
+        write_fake(&dir.path().join("two.mp3"));
+        let root = vec![dir.path().to_string_lossy().to_string()];
+
+        let plain = FilesystemProvider::new(root.clone()).unwrap();
+        assert!(!plain.capabilities().supports_recently_played());
+        assert!(matches!(
+            plain.list_recently_played(PageRequest::first_page(10)),
+            Err(ProviderError::NotSupported { .. })
+        ));
+
+        let history = PlayHistory::new(data.path());
+        let provider = FilesystemProvider::new(root)
+            .unwrap()
+            .with_play_history(history.clone());
+        assert!(provider.capabilities().supports_recently_played());
+        assert!(provider
+            .list_recently_played(PageRequest::first_page(10))
+            .unwrap()
+            .items
+            .is_empty());
+
+        let track = |name: &str| {
+            let hits = provider
+                .search_tracks(
+                    name,
+                    TrackSearchFilters::default(),
+                    PageRequest::first_page(1),
+                )
+                .unwrap();
+            hits.items[0].clone()
+        };
+        for name in ["one", "two", "one"] {
+            history.record(&track(name)).unwrap();
+        }
+        let titles = |page: Page<Track>| -> Vec<String> {
+            page.items.into_iter().map(|t| t.title).collect()
+        };
+        let first = provider
+            .list_recently_played(PageRequest::first_page(1))
+            .unwrap();
+        let next = first.next.clone().expect("second page");
+        assert_eq!(titles(first), ["one"]);
+        let rest = provider
+            .list_recently_played(PageRequest::from_cursor(&next, 1).unwrap())
+            .unwrap();
+        assert_eq!(titles(rest), ["two"]);
+
+        // Deleted files drop out, and a rescan keeps the capability.
+        std::fs::remove_file(dir.path().join("one.mp3")).unwrap();
+        provider.rescan_full().unwrap();
+        assert!(provider.capabilities().supports_recently_played());
+        let page = provider
+            .list_recently_played(PageRequest::first_page(10))
+            .unwrap();
+        assert_eq!(titles(page), ["two"]);
+    }
+
+    fn write_fake(path: &std::path::Path) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let mut f = File::create(path).unwrap();
+        writeln!(f, "fake").unwrap();
+    }
+
+    #[test]
+    fn corrupt_files_are_listed_as_scan_failures() {
+        let dir = tempdir().unwrap();
+        TestWav::new().write(&dir.path().join("Artist/Album/good.wav"));
+        let corrupt = dir.path().join("Artist/Album/corrupt.flac");
+        std::fs::write(&corrupt, b"fLaC then nothing useful").unwrap();
+        let roots = vec![dir.path().to_string_lossy().to_string()];
+
+        let index = scan_library_with_options(roots.clone(), ScanOptions::default()).unwrap();
+        assert_eq!(index.failures.len(), 1);
+        let failure = &index.failures[0];
+        assert_eq!(failure.path, corrupt.canonicalize().unwrap());
+        assert_eq!(failure.kind, ScanFailureKind::Format);
+        assert!(index.tracks.iter().any(|t| t.title == "good"));
+        assert!(!index
+            .tracks
+            .iter()
+            .any(|t| { t.title == "good" && index.unreadable.contains(&t.id) }));
+
+        // The provider keeps them, and a rescan that reuses the entry still
+        // reports it.
+        let provider = FilesystemProvider::new(roots).unwrap();
+        assert_eq!(provider.scan_failures(), index.failures);
+        provider.rescan().unwrap();
+        assert_eq!(provider.scan_failures(), index.failures);
+    }
+
+    #[test]
+    fn durations_are_read_or_computed_without_failing_the_scan() {
+        let dir = tempdir().unwrap();
+        TestWav::new()
+            .frames(3 * 8000)
+            .write(&dir.path().join("tone.wav"));
+        std::fs::write(dir.path().join("broken.wav"), b"RIFF\0\0\0\0WAVEjunk").unwrap();
+        let roots = vec![dir.path().to_string_lossy().to_string()];
+
+        let durations = |compute_durations| {
+            let options = ScanOptions {
+                compute_durations,
+                ..Default::default()
+            };
+            let index = scan_library_with_options(roots.clone(), options).unwrap();
+            let mut durations: Vec<_> = index
+                .tracks
+                .into_iter()
+                .map(|t| (t.title, t.duration_seconds))
+                .collect();
+            durations.sort();
+            durations
+        };
+        let expected = [("broken".to_string(), None), ("tone".to_string(), Some(3))];
+        assert_eq!(durations(true), expected);
+        assert_eq!(durations(false), expected);
+
+        // The fallback agrees with the header.
+        assert_eq!(
+            tags::compute_duration(&dir.path().join("tone.wav")),
+            Some(3)
+        );
+        assert_eq!(tags::compute_duration(&dir.path().join("broken.wav")), None);
+    }
+
+    #[test]
+    fn health_follows_root_accessibility() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("music");
+        write_fake(&root.join("song.mp3"));
+        let provider = FilesystemProvider::new(vec![root.to_string_lossy().to_string()]).unwrap();
+        provider.health_check().unwrap();
+
+        // As if the drive holding the library were unmounted.
+        std::fs::remove_dir_all(&root).unwrap();
+        assert!(provider.health_check().is_err());
+    }
+
+    #[test]
+    fn searched_tracks_stream_from_a_non_canonical_root() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("music/Artist/Album/song.mp3");
+        write_fake(&file);
+        let root = dir.path().join("music/Artist/../.");
+
+        let provider = FilesystemProvider::new(vec![root.to_string_lossy().to_string()]).unwrap();
+        let page = provider
+            .search_tracks(
+                "song",
+                TrackSearchFilters::default(),
+                PageRequest::first_page(10),
+            )
+            .unwrap();
+        let track = &page.items[0];
+        assert_eq!(track.artist, "Artist");
+        assert_eq!(
+            std::path::Path::new(&track.id.0),
+            file.canonicalize().unwrap()
+        );
+        assert_eq!(provider.get_track(&track.id).unwrap().id, track.id);
+        let url = provider.get_stream_url(&track.id).unwrap();
+        assert_eq!(url.0, format!("file://{}", track.id.0));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinked_files_are_indexed_once_by_target() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("song.mp3");
+        write_fake(&file);
+        std::os::unix::fs::symlink(&file, dir.path().join("alias.mp3")).unwrap();
+
+        let provider = FilesystemProvider::with_options(
+            vec![dir.path().to_string_lossy().to_string()],
+            ScanOptions {
+                follow_symlinks: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let page = provider
+            .search_tracks(
+                "",
+                TrackSearchFilters::default(),
+                PageRequest::first_page(10),
+            )
+            .unwrap();
+        assert_eq!(page.items.len(), 1);
+        assert_eq!(
+            page.items[0].id,
+            scan::track_id_for(&dir.path().join("alias.mp3")).unwrap()
+        );
+    }
+
+    #[test]
+    fn zero_and_overflowing_paging_is_normalized() {
+        let dir = tempdir().unwrap();
+        for name in ["a", "b", "c"] {
+            write_fake(&dir.path().join(format!("Band/Album/{name}.mp3")));
+        }
+        let provider =
+            FilesystemProvider::new(vec![dir.path().to_string_lossy().to_string()]).unwrap();
+        let filters = TrackSearchFilters::default;
+
+        let page = provider
+            .search_tracks("", filters(), PageRequest::new(0, 0))
+            .unwrap();
+        assert_eq!(page.items.len(), 3);
+        assert!(page.next.is_none());
+        let page = provider
+            .search_tracks("", filters(), PageRequest::new(1, u32::MAX))
+            .unwrap();
+        assert_eq!(page.items.len(), 2);
+        let page = provider
+            .search_tracks("", filters(), PageRequest::new(u32::MAX, 10))
+            .unwrap();
+        assert!(page.items.is_empty());
+        assert!(page.next.is_none());
+    }
+
+    #[test]
+    fn sources_scope_browsing_and_overlapping_roots_index_once() {
+        let dir = tempdir().unwrap();
+        write_fake(&dir.path().join("home/Band/Album/a.mp3"));
+        write_fake(&dir.path().join("nas/Jazz/Album/b.mp3"));
+        write_fake(&dir.path().join("nas/Rock/Album/c.mp3"));
+        std::fs::write(dir.path().join("nas/Jazz/list.m3u"), "Album/b.mp3\n").unwrap();
+        let root = |rel: &str| dir.path().join(rel).to_string_lossy().to_string();
+        // nas/Jazz is also inside nas.
+        let provider = FilesystemProvider::new(vec![root("home"), root("nas"), root("nas/Jazz")])
+            .unwrap()
+            .with_label(&root("nas"), "NAS");
+
+        let labels: Vec<_> = provider.sources().into_iter().map(|s| s.label).collect();
+        assert_eq!(labels, ["home", "NAS", "Jazz"]);
+        assert_eq!(search(&provider, ""), 3);
+        let paging = PageRequest::first_page(10);
+        assert_eq!(provider.list_playlists(paging).unwrap().items.len(), 1);
+
+        let nas = provider.scoped_to("NAS").unwrap();
+        assert_eq!(nas.sources()[0].label, "NAS");
+        assert_eq!(search(&nas, ""), 2);
+        assert_eq!(search(&nas, "band"), 0);
+        let albums = nas.browse(BrowseKind::Albums, paging).unwrap();
+        assert_eq!(albums.items.len(), 2);
+
+        let jazz = provider.scoped_to(&root("nas/Jazz")).unwrap();
+        assert_eq!(search(&jazz, ""), 1);
+        assert_eq!(jazz.list_playlists(paging).unwrap().items.len(), 1);
+        let home = provider.scoped_to("home").unwrap();
+        assert_eq!(search(&home, ""), 1);
+        assert!(!home.capabilities().playlists);
+        let id = &home
+            .search_tracks("", TrackSearchFilters::default(), paging)
+            .unwrap()
+            .items[0]
+            .id;
+        assert_eq!(provider.get_track(id).unwrap().id, *id);
+
+        assert!(matches!(
+            provider.scoped_to("nowhere"),
+            Err(ProviderError::NotFound { .. })
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlink_cycles_do_not_hang_the_scan() {
+        let dir = tempdir().unwrap();
+        write_fake(&dir.path().join("Artist/Album/song.mp3"));
+        std::os::unix::fs::symlink(dir.path(), dir.path().join("Artist/Album/loop")).unwrap();
+        let roots = vec![dir.path().to_string_lossy().to_string()];
+
+        for follow_symlinks in [false, true] {
+            let options = ScanOptions {
+                follow_symlinks,
+                ..Default::default()
+            };
+            let index = scan_library_with_options(roots.clone(), options).unwrap();
+            assert_eq!(index.tracks.len(), 1, "follow_symlinks = {follow_symlinks}");
+        }
+    }
+
+    #[test]
+    fn hidden_files_and_directories_are_opt_in() {
+        let dir = tempdir().unwrap();
+        write_fake(&dir.path().join("Artist/song.mp3"));
+        write_fake(&dir.path().join("Artist/.draft.mp3"));
+        write_fake(&dir.path().join(".trash/Artist/old.mp3"));
+        let roots = vec![dir.path().to_string_lossy().to_string()];
+        let titles = |include_hidden| {
+            let options = ScanOptions {
+                include_hidden,
+                ..Default::default()
+            };
+            let index = scan_library_with_options(roots.clone(), options).unwrap();
+            let mut titles: Vec<String> = index.tracks.into_iter().map(|t| t.title).collect();
+            titles.sort();
+            titles
+        };
+
+        assert_eq!(titles(false), ["song"]);
+        assert_eq!(titles(true), [".draft", "old", "song"]);
+    }
+
+    #[test]
+    fn allowlisted_files_no_reader_understands_are_named_by_file() {
+        let dir = tempdir().unwrap();
+        write_fake(&dir.path().join("Artist/Album/01 Intro.dsf"));
+        let options = ScanOptions {
+            extensions_allowlist: vec!["dsf".into()],
+            ..Default::default()
+        };
+        let roots = vec![dir.path().to_string_lossy().to_string()];
+        let index = scan_library_with_options(roots, options).unwrap();
+        assert_eq!(index.tracks.len(), 1);
+        let track = &index.tracks[0];
+        assert_eq!(track.title, "01 Intro");
+        assert_eq!(track.artist, "Artist");
+        assert_eq!(track.album.as_deref(), Some("Album"));
+    }
+
+    #[test]
+    fn artist_albums_are_listed_by_artist() {
+        let dir = tempdir().unwrap();
+        write_fake(&dir.path().join("Band/Second/a.mp3"));
+        write_fake(&dir.path().join("Band/First/b.mp3"));
+        write_fake(&dir.path().join("Other/Elsewhere/c.mp3"));
+        let provider =
+            FilesystemProvider::new(vec![dir.path().to_string_lossy().to_string()]).unwrap();
+
+        let page = provider
+            .list_artist_albums("Band", PageRequest::first_page(1))
+            .unwrap();
+        assert_eq!(page.items.len(), 1);
+        assert_eq!(page.items[0].title, "First");
+        let next = PageRequest::from_cursor(page.next.as_ref().unwrap(), 1).unwrap();
+        let page = provider.list_artist_albums("Band", next).unwrap();
+        assert_eq!(page.items[0].title, "Second");
+        assert!(page.next.is_none());
+        assert!(provider
+            .list_artist_albums("Nobody", PageRequest::first_page(10))
+            .unwrap()
+            .items
+            .is_empty());
+
+        // Any case finds the artist, and the index agrees with walking the
+        // album listing.
+        let titles = |page: Page<Album>| {
+            let mut titles: Vec<_> = page.items.into_iter().map(|a| a.title).collect();
+            titles.sort();
+            titles
+        };
+        let paging = PageRequest::first_page(10);
+        let indexed = titles(provider.list_artist_albums("bAND", paging).unwrap());
+        assert_eq!(indexed, ["First", "Second"]);
+        let browsed = titles(
+            tunez_core::provider::artist_albums_from_browse(&provider, "bAND", paging).unwrap(),
+        );
+        assert_eq!(browsed, indexed);
+    }
+
+    #[test]
+    fn incremental_rescan_only_reparses_changed_files() {
+        let dir = tempdir().unwrap();
+        let first = dir.path().join("Artist/Album/one.mp3");
+        let second = dir.path().join("Artist/Album/two.mp3");
+        write_fake(&first);
+        write_fake(&second);
+        let provider =
+            FilesystemProvider::new(vec![dir.path().to_string_lossy().to_string()]).unwrap();
+
+        let stats = provider.rescan().unwrap();
+        assert_eq!(
+            stats,
+            ScanStats {
+                parsed: 0,
+                reused: 2,
+                removed: 0
+            }
+        );
+
+        // Move the mtime backwards: still a change that must be re-read.
+        let earlier = std::time::SystemTime::now() - std::time::Duration::from_secs(3600);
+        File::options()
+            .write(true)
+            .open(&first)
+            .unwrap()
+            .set_modified(earlier)
+            .unwrap();
+        let stats = provider.rescan().unwrap();
+        assert_eq!(
+            stats,
+            ScanStats {
+                parsed: 1,
+                reused: 1,
+                removed: 0
+            }
+        );
+    }
+
+    #[test]
+    fn incremental_rescan_purges_deleted_files_from_aggregates() {
+        let dir = tempdir().unwrap();
+        let kept = dir.path().join("Kept/Album/one.mp3");
+        let gone = dir.path().join("Gone/Other/two.mp3");
+        write_fake(&kept);
+        write_fake(&gone);
+        let provider =
+            FilesystemProvider::new(vec![dir.path().to_string_lossy().to_string()]).unwrap();
+        std::fs::remove_file(&gone).unwrap();
+
+        let stats = provider.rescan().unwrap();
+        assert_eq!(stats.removed, 1);
+        let index = provider.index.read().unwrap();
+        assert_eq!(index.tracks.len(), 1);
+        assert!(!index.artists.contains("Gone"));
+        assert!(index.albums.keys().all(|id| !id.0.starts_with("Gone::")));
+    }
+
+    #[test]
+    fn full_rescan_rebuilds_index() {
+        let dir = tempdir().unwrap();
+        write_fake(&dir.path().join("one.mp3"));
+        let provider =
+            FilesystemProvider::new(vec![dir.path().to_string_lossy().to_string()]).unwrap();
+        write_fake(&dir.path().join("two.mp3"));
+
+        provider.rescan_full().unwrap();
+        assert_eq!(provider.index.read().unwrap().tracks.len(), 2);
+    }
+
+    #[test]
+    fn m3u_playlist_resolves_entries_against_its_directory() {
+        let dir = tempdir().unwrap();
+        for name in ["Artist/Album/one.mp3", "Artist/Album/two.mp3", "three.mp3"] {
+            write_fake(&dir.path().join(name));
+        }
+        let three = dir.path().join("three.mp3").canonicalize().unwrap();
+        std::fs::create_dir_all(dir.path().join("lists")).unwrap();
+        std::fs::write(
+            dir.path().join("lists/mix.m3u"),
+            format!(
+                "#EXTM3U\n#EXTINF:200,Artist - One\n../Artist/Album/one.mp3\n\n{}\n#EXTINF:95,Gone - Missing\n../missing.mp3\n",
+                three.display()
+            ),
+        )
+        .unwrap();
+
+        let provider =
+            FilesystemProvider::new(vec![dir.path().to_string_lossy().to_string()]).unwrap();
+        let id = PlaylistId::new("lists/mix.m3u");
+        let playlist = provider.get_playlist(&id).unwrap();
+        assert_eq!(playlist.track_count, Some(2));
+
+        let tracks = provider
+            .list_playlist_tracks(&id, PageRequest::first_page(10))
+            .unwrap();
+        let mut titles: Vec<_> = tracks.items.iter().map(|t| t.title.as_str()).collect();
+        titles.sort();
+        assert_eq!(titles, ["one", "three"]);
+
+        let unresolved = provider.unresolved_playlist_entries(&id).unwrap();
+        assert_eq!(unresolved.len(), 1);
+        assert_eq!(unresolved[0].title.as_deref(), Some("Gone - Missing"));
+        assert_eq!(unresolved[0].duration_seconds, Some(95));
+
+        let (with_tracks, page) = provider
+            .get_playlist_with_tracks(&id, PageRequest::first_page(10))
+            .unwrap();
+        assert_eq!(with_tracks, playlist);
+        assert_eq!(page.items, tracks.items);
+    }
+
+    #[test]
+    fn cue_sheet_splits_a_single_file_album_into_tracks() {
+        let dir = tempdir().unwrap();
+        TestWav::new()
+            .frames(10 * 8000)
+            .write(&dir.path().join("Live/live.wav"));
+        std::fs::write(
+            dir.path().join("Live/live.cue"),
+            "PERFORMER \"The Band\"\nTITLE \"Live\"\nFILE \"live.wav\" WAVE\n  TRACK 01 AUDIO\n    TITLE \"Opener\"\n    INDEX 01 00:00:00\n  TRACK 02 AUDIO\n    TITLE \"Encore\"\n    INDEX 01 00:06:00\n",
+        )
+        .unwrap();
+        let file = dir.path().join("Live/live.wav").canonicalize().unwrap();
+        let url = StreamUrl::file(&file);
+
+        let provider =
+            FilesystemProvider::new(vec![dir.path().to_string_lossy().to_string()]).unwrap();
+        let mut tracks = provider.index.read().unwrap().tracks.clone();
+        tracks.sort_by_key(|t| t.track_number);
+        let summary: Vec<_> = tracks
+            .iter()
+            .map(|t| (t.id.0.clone(), t.title.as_str(), t.duration_seconds))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (format!("{}#t=0.000,6.000", url.0), "Opener", Some(6)),
+                (format!("{}#t=6.000", url.0), "Encore", Some(4)),
+            ]
+        );
+        assert_eq!(tracks[1].album.as_deref(), Some("Live"));
+        assert_eq!(tracks[1].artist, "The Band");
+
+        let url = provider.get_stream_url(&tracks[1].id).unwrap();
+        assert_eq!(url.0, tracks[1].id.0);
+    }
+
+    #[test]
+    fn a_time_fragment_in_a_file_name_is_not_a_cue() {
+        let plain = TrackId::new("/music/live#t=123");
+        assert!(!scan::is_cue_track(&plain));
+        assert_eq!(scan::file_path_of(&plain), Path::new("/music/live#t=123"));
+
+        let cue = scan::cue_track_id(&plain, std::time::Duration::from_secs(5), None);
+        assert_eq!(cue.0, "file:///music/live%23t%3D123#t=5.000");
+        assert_eq!(scan::file_path_of(&cue), Path::new("/music/live#t=123"));
+    }
+
+    /// A silent 16-bit mono PCM WAV file at 8 kHz, with an ID3v2 tag when
+    /// any tag field is set.
+    struct TestWav<'a> {
+        frames: u32,
+        title: Option<&'a str>,
+        artist: Option<&'a str>,
+        album: Option<&'a str>,
+        album_artist: Option<&'a str>,
+        compilation: bool,
+        year: Option<u32>,
+        disc: Option<u32>,
+        track: Option<u32>,
+        cover: Option<&'a [u8]>,
+    }
+
+    impl<'a> TestWav<'a> {
+        fn new() -> Self {
+            Self {
+                frames: 800,
+                title: None,
+                artist: None,
+                album: None,
+                album_artist: None,
+                compilation: false,
+                year: None,
+                disc: None,
+                track: None,
+                cover: None,
+            }
+        }
+
+        fn frames(mut self, frames: u32) -> Self {
+            self.frames = frames;
+            self
+        }
+
+        fn title(mut self, title: &'a str) -> Self {
+            self.title = Some(title);
+            self
+        }
+
+        fn artist(mut self, artist: &'a str) -> Self {
+            self.artist = Some(artist);
+            self
+        }
+
+        fn album(mut self, album: &'a str) -> Self {
+            self.album = Some(album);
+            self
+        }
+
+        fn album_artist(mut self, album_artist: &'a str) -> Self {
+            self.album_artist = Some(album_artist);
+            self
+        }
+
+        fn compilation(mut self) -> Self {
+            self.compilation = true;
+            self
+        }
+
+        fn year(mut self, year: u32) -> Self {
+            self.year = Some(year);
+            self
+        }
+
+        fn disc(mut self, disc: u32) -> Self {
+            self.disc = Some(disc);
+            self
+        }
+
+        fn track(mut self, track: u32) -> Self {
+            self.track = Some(track);
+            self
+        }
+
+        /// A front-cover JPEG.
+        fn cover(mut self, jpeg: &'a [u8]) -> Self {
+            self.cover = Some(jpeg);
+            self
+        }
+
+        fn write(self, path: &std::path::Path) {
+            use lofty::{Accessor, ItemKey, Picture, PictureType, Tag, TagExt, TagType};
+
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            let data_len = self.frames * 2;
+            let mut wav = Vec::new();
+            wav.extend_from_slice(b"RIFF");
+            wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+            wav.extend_from_slice(b"WAVEfmt ");
+            wav.extend_from_slice(&16u32.to_le_bytes());
+            wav.extend_from_slice(&1u16.to_le_bytes()); // PCM
+            wav.extend_from_slice(&1u16.to_le_bytes()); // mono
+            wav.extend_from_slice(&8000u32.to_le_bytes());
+            wav.extend_from_slice(&16000u32.to_le_bytes());
+            wav.extend_from_slice(&2u16.to_le_bytes());
+            wav.extend_from_slice(&16u16.to_le_bytes());
+            wav.extend_from_slice(b"data");
+            wav.extend_from_slice(&data_len.to_le_bytes());
+            wav.resize(wav.len() + data_len as usize, 0);
+            std::fs::write(path, wav).unwrap();
+
+            let mut tag = Tag::new(TagType::Id3v2);
+            if let Some(title) = self.title {
+                tag.set_title(title.into());
+            }
+            if let Some(artist) = self.artist {
+                tag.set_artist(artist.into());
+            }
+            if let Some(album) = self.album {
+                tag.set_album(album.into());
+            }
+            if let Some(album_artist) = self.album_artist {
+                tag.insert_text(ItemKey::AlbumArtist, album_artist.into());
+            }
+            if self.compilation {
+                tag.insert_text(ItemKey::FlagCompilation, "1".into());
+            }
+            if let Some(year) = self.year {
+                tag.set_year(year);
+            }
+            if let Some(disc) = self.disc {
+                tag.set_disk(disc);
+            }
+            if let Some(track) = self.track {
+                tag.set_track(track);
+            }
+            if let Some(jpeg) = self.cover {
+                tag.push_picture(Picture::new_unchecked(
+                    PictureType::CoverFront,
+                    lofty::MimeType::Jpeg,
+                    None,
+                    jpeg.to_vec(),
+                ));
+            }
+            if !tag.is_empty() {
+                tag.save_to_path(path).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn extended_tags_are_mapped_to_track_fields() {
+        use lofty::{Accessor, ItemKey, ItemValue, Tag, TagExt, TagItem, TagType};
+
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("Artist/Album/song.wav");
+        TestWav::new().write(&path);
+        let mut tag = Tag::new(TagType::Id3v2);
+        tag.set_title("Song".into());
+        tag.set_year(1999);
+        tag.set_genre("Jazz".into());
+        tag.set_disk(2);
+        tag.set_track(7);
+        tag.insert_text(ItemKey::ReplayGainTrackGain, "-6.54 dB".into());
+        tag.insert_text(ItemKey::ReplayGainTrackPeak, "0.988525".into());
+        // The recording ID is stored as a UFID frame, which only the ID3v2
+        // conversion knows how to write.
+        tag.insert_unchecked(TagItem::new(
+            ItemKey::MusicBrainzRecordingId,
+            ItemValue::Text("0b3d1e5c-8f0e-4bd6-9d3a-9f1f5b4d2c11".into()),
+        ));
+        lofty::id3::v2::Id3v2Tag::from(tag)
+            .save_to_path(&path)
+            .unwrap();
+
+        let provider =
+            FilesystemProvider::new(vec![dir.path().to_string_lossy().to_string()]).unwrap();
+        let id = TrackId::new(path.canonicalize().unwrap().to_string_lossy().to_string());
+        let track = provider.get_track(&id).unwrap();
+        assert_eq!(track.year, Some(1999));
+        assert_eq!(track.genre.as_deref(), Some("Jazz"));
+        assert_eq!(track.disc_number, Some(2));
+        assert_eq!(track.track_number, Some(7));
+        assert_eq!(track.replay_gain.track_gain_db, Some(-6.54));
+        assert_eq!(track.replay_gain.track_peak, Some(0.988525));
+        assert_eq!(track.replay_gain.album_gain_db, None);
+        assert_eq!(
+            track.musicbrainz_id.as_deref(),
+            Some("0b3d1e5c-8f0e-4bd6-9d3a-9f1f5b4d2c11")
+        );
+    }
+
+    #[test]
+    fn albums_sort_by_year_with_title_ties_and_by_recently_added() {
+        let dir = tempdir().unwrap();
+        let files = [
+            ("zeta.wav", "Zeta", 1999),
+            ("alpha.wav", "Alpha", 2005),
+            ("beta.wav", "Beta", 2005),
+            // An album's year is its earliest track's.
+            ("gamma-1.wav", "Gamma", 2001),
+            ("gamma-2.wav", "Gamma", 1995),
+        ];
+        let epoch = std::time::SystemTime::UNIX_EPOCH;
+        for (i, (name, album, year)) in files.into_iter().enumerate() {
+            let path = dir.path().join(name);
+            TestWav::new()
+                .title(name)
+                .artist("Artist")
+                .album(album)
+                .year(year)
+                .write(&path);
+            let modified = epoch + std::time::Duration::from_secs(1_000_000 * (i as u64 + 1));
+            File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(modified)
+                .unwrap();
+        }
+
+        let provider =
+            FilesystemProvider::new(vec![dir.path().to_string_lossy().to_string()]).unwrap();
+        let titles = |sort| {
+            provider
+                .browse_sorted(BrowseKind::Albums, sort, PageRequest::first_page(10))
+                .unwrap()
+                .items
+                .iter()
+                .map(|item| item.name().to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(titles(SortOrder::Year), ["Gamma", "Zeta", "Alpha", "Beta"]);
+        assert_eq!(titles(SortOrder::Title), ["Alpha", "Beta", "Gamma", "Zeta"]);
+        assert_eq!(
+            titles(SortOrder::RecentlyAdded),
+            ["Gamma", "Beta", "Alpha", "Zeta"]
+        );
+
+        let page = provider
+            .browse_sorted(BrowseKind::Albums, SortOrder::Year, PageRequest::new(1, 2))
+            .unwrap();
+        assert_eq!(page.items[0].name(), "Zeta");
+        assert!(page.next.is_some());
+    }
+
+    #[test]
+    fn compilations_group_under_their_album_artist() {
+        let dir = tempdir().unwrap();
+        let hits = dir.path().join("Hits");
+        TestWav::new()
+            .artist("Alpha")
+            .album("Hits")
+            .compilation()
+            .write(&hits.join("1.wav"));
+        TestWav::new()
+            .artist("Beta")
+            .album("Hits")
+            .compilation()
+            .write(&hits.join("2.wav"));
+        let mix = dir.path().join("Mix");
+        TestWav::new()
+            .artist("Alpha")
+            .album("Mix")
+            .album_artist("DJ")
+            .write(&mix.join("1.wav"));
+        TestWav::new()
+            .artist("Gamma")
+            .album("Mix")
+            .album_artist("DJ")
+            .write(&mix.join("2.wav"));
+
+        let provider =
+            FilesystemProvider::new(vec![dir.path().to_string_lossy().to_string()]).unwrap();
+        let albums = provider
+            .search_albums("", PageRequest::first_page(10))
+            .unwrap()
+            .items;
+        let summary: Vec<(&str, &str, Option<u32>)> = albums
+            .iter()
+            .map(|a| (a.title.as_str(), a.artist.as_str(), a.track_count))
+            .collect();
+        assert_eq!(
+            summary,
+            [("Hits", "Various Artists", Some(2)), ("Mix", "DJ", Some(2))]
+        );
+
+        let tracks = provider
+            .list_album_tracks(&albums[0].id, PageRequest::first_page(10))
+            .unwrap()
+            .items;
+        let artists: Vec<&str> = tracks.iter().map(|t| t.artist.as_str()).collect();
+        assert_eq!(artists, ["Alpha", "Beta"]);
+        assert!(provider
+            .list_artist_albums("Various Artists", PageRequest::first_page(10))
+            .unwrap()
+            .items
+            .iter()
+            .any(|a| a.title == "Hits"));
+    }
+
+    #[test]
+    fn same_titled_albums_by_different_artists_stay_apart() {
+        let dir = tempdir().unwrap();
+        TestWav::new()
+            .artist("Alpha")
+            .album("Greatest Hits")
+            .write(&dir.path().join("Alpha/Greatest Hits/1.wav"));
+        TestWav::new()
+            .artist("Beta")
+            .album("Greatest Hits")
+            .album_artist("Beta")
+            .write(&dir.path().join("Beta/Greatest Hits/1.wav"));
+
+        let provider =
+            FilesystemProvider::new(vec![dir.path().to_string_lossy().to_string()]).unwrap();
+        let albums = provider
+            .search_albums("greatest", PageRequest::first_page(10))
+            .unwrap()
+            .items;
+        assert_eq!(albums.len(), 2);
+        assert_ne!(albums[0].id, albums[1].id);
+        for album in &albums {
+            let tracks = provider
+                .list_album_tracks(&album.id, PageRequest::first_page(10))
+                .unwrap()
+                .items;
+            assert_eq!(tracks.len(), 1);
+            assert_eq!(tracks[0].artist, album.artist);
+        }
+    }
+
+    fn search_titles(
+        provider: &FilesystemProvider,
+        query: &str,
+        filters: TrackSearchFilters,
+    ) -> Vec<(String, String)> {
+        provider
+            .search_tracks(query, filters, PageRequest::first_page(10))
+            .unwrap()
+            .items
+            .into_iter()
+            .map(|t| (t.title, t.artist))
+            .collect()
+    }
+
+    #[test]
+    fn album_and_artist_search_use_the_index() {
+        let dir = tempdir().unwrap();
+        TestWav::new()
+            .title("Come Together")
+            .artist("The Beatles")
+            .album("Abbey Road")
+            .year(1969)
+            .write(&dir.path().join("a.wav"));
+        TestWav::new()
+            .title("Taxman")
+            .artist("The Beatles")
+            .album("Revolver")
+            .year(1966)
+            .write(&dir.path().join("b.wav"));
+        TestWav::new()
+            .title("River")
+            .artist("Joni Mitchell")
+            .album("Blue")
+            .year(1971)
+            .write(&dir.path().join("c.wav"));
+        let provider =
+            FilesystemProvider::new(vec![dir.path().to_string_lossy().to_string()]).unwrap();
+
+        let albums = provider
+            .search_albums("Abbey", PageRequest::first_page(10))
+            .unwrap();
+        let titles: Vec<_> = albums.items.iter().map(|a| a.title.as_str()).collect();
+        assert_eq!(titles, ["Abbey Road"]);
+        assert_eq!(albums.items[0].artist, "The Beatles");
+
+        let by_artist = provider
+            .search_albums("beatles", PageRequest::first_page(1))
+            .unwrap();
+        assert_eq!(by_artist.items[0].title, "Abbey Road");
+        assert!(by_artist.next.is_some());
+
+        let artists = provider
+            .search_artists("MITCH", PageRequest::first_page(10))
+            .unwrap();
+        assert_eq!(artists.items, ["Joni Mitchell"]);
+    }
+
+    #[test]
+    fn search_filters_narrow_results() {
+        let dir = tempdir().unwrap();
+        TestWav::new()
+            .title("Home")
+            .artist("Alpha")
+            .album("First")
+            .year(1999)
+            .write(&dir.path().join("a.wav"));
+        TestWav::new()
+            .title("Home")
+            .artist("Beta")
+            .album("Second")
+            .year(2005)
+            .write(&dir.path().join("b.wav"));
+        let provider =
+            FilesystemProvider::new(vec![dir.path().to_string_lossy().to_string()]).unwrap();
+
+        assert_eq!(
+            search_titles(&provider, "home", TrackSearchFilters::default()).len(),
+            2
+        );
+        let by_artist = TrackSearchFilters {
+            artist: Some("BETA".into()),
+            ..Default::default()
+        };
+        assert_eq!(
+            search_titles(&provider, "home", by_artist),
+            [("Home".to_string(), "Beta".to_string())]
+        );
+        let by_album = TrackSearchFilters {
+            album: Some("first".into()),
+            ..Default::default()
+        };
+        assert_eq!(
+            search_titles(&provider, "home", by_album),
+            [("Home".to_string(), "Alpha".to_string())]
+        );
+        // Filters AND with each other and with the query.
+        let conflicting = TrackSearchFilters {
+            artist: Some("Alpha".into()),
+            album: Some("Second".into()),
+            year: None,
+        };
+        assert!(search_titles(&provider, "home", conflicting).is_empty());
+    }
+
+    #[test]
+    fn search_ranks_closer_matches_first_and_tolerates_typos() {
+        let dir = tempdir().unwrap();
+        TestWav::new()
+            .title("Crazy Little Thing Called Love")
+            .artist("Queen")
+            .album("The Game")
+            .year(1979)
+            .write(&dir.path().join("a.wav"));
+        TestWav::new()
+            .title("Love of My Life")
+            .artist("Queen")
+            .album("A Night at the Opera")
+            .year(1975)
+            .write(&dir.path().join("b.wav"));
+        TestWav::new()
+            .title("Bohemian Rhapsody")
+            .artist("Queen")
+            .album("A Night at the Opera")
+            .year(1975)
+            .write(&dir.path().join("c.wav"));
+        let provider =
+            FilesystemProvider::new(vec![dir.path().to_string_lossy().to_string()]).unwrap();
+        let titles = |query: &str| -> Vec<String> {
+            search_titles(&provider, query, TrackSearchFilters::default())
+                .into_iter()
+                .map(|(title, _)| title)
+                .collect()
+        };
+
+        assert_eq!(
+            titles("love"),
+            ["Love of My Life", "Crazy Little Thing Called Love"]
+        );
+        assert_eq!(titles("bohrap"), ["Bohemian Rhapsody"]);
+        assert_eq!(titles("rhapsodt"), ["Bohemian Rhapsody"]);
+        assert!(titles("zeppelin").is_empty());
+    }
+
+    #[test]
+    fn search_year_filter_excludes_other_years() {
+        let dir = tempdir().unwrap();
+        TestWav::new()
+            .title("Song")
+            .artist("Alpha")
+            .album("First")
+            .year(1999)
+            .write(&dir.path().join("a.wav"));
+        TestWav::new()
+            .title("Song")
+            .artist("Beta")
+            .album("Second")
+            .year(2005)
+            .write(&dir.path().join("b.wav"));
+        let provider =
+            FilesystemProvider::new(vec![dir.path().to_string_lossy().to_string()]).unwrap();
+
+        let filters = TrackSearchFilters {
+            year: Some(2005),
+            ..Default::default()
+        };
+        assert_eq!(
+            search_titles(&provider, "song", filters),
+            [("Song".to_string(), "Beta".to_string())]
+        );
+        let filters = TrackSearchFilters {
+            year: Some(1980),
+            ..Default::default()
+        };
+        assert!(search_titles(&provider, "song", filters).is_empty());
+    }
+
+    #[test]
+    fn album_tracks_sort_by_disc_then_track() {
+        let dir = tempdir().unwrap();
+        let album = dir.path().join("Artist/Box Set");
+        TestWav::new()
+            .title("Disc 2 Track 1")
+            .artist("Artist")
+            .album("Box Set")
+            .disc(2)
+            .track(1)
+            .write(&album.join("a.wav"));
+        TestWav::new()
+            .title("Disc 1 Track 2")
+            .artist("Artist")
+            .album("Box Set")
+            .disc(1)
+            .track(2)
+            .write(&album.join("b.wav"));
+        TestWav::new()
+            .title("Disc 1 Track 1")
+            .artist("Artist")
+            .album("Box Set")
+            .disc(1)
+            .track(1)
+            .write(&album.join("c.wav"));
+        TestWav::new()
+            .title("Disc 2 Track 2")
+            .artist("Artist")
+            .album("Box Set")
+            .disc(2)
+            .track(2)
+            .write(&album.join("d.wav"));
+        let provider =
+            FilesystemProvider::new(vec![dir.path().to_string_lossy().to_string()]).unwrap();
+
+        let tracks = provider
+            .list_album_tracks(
+                &scan::album_id_for("Artist", "Box Set"),
+                PageRequest::first_page(10),
+            )
+            .unwrap()
+            .items;
+        let titles: Vec<_> = tracks.iter().map(|t| t.title.as_str()).collect();
+        assert_eq!(
+            titles,
+            [
+                "Disc 1 Track 1",
+                "Disc 1 Track 2",
+                "Disc 2 Track 1",
+                "Disc 2 Track 2"
+            ]
+        );
+    }
+
+    #[test]
+    fn embedded_artwork_is_extracted_and_shared_by_album() {
+        let jpeg = [
+            0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10, b'J', b'F', b'I', b'F', 0xFF, 0xD9,
+        ];
+        let dir = tempdir().unwrap();
+        let with_art = dir.path().join("Artist/Album/one.wav");
+        let without_art = dir.path().join("Artist/Album/two.mp3");
+        TestWav::new().cover(&jpeg).write(&with_art);
+        write_fake(&without_art);
+        let provider =
+            FilesystemProvider::new(vec![dir.path().to_string_lossy().to_string()]).unwrap();
+        assert!(provider.capabilities().artwork);
+
+        let id = |p: &std::path::Path| {
+            TrackId::new(p.canonicalize().unwrap().to_string_lossy().to_string())
+        };
+        let art = provider.get_artwork(&id(&with_art)).unwrap();
+        assert_eq!(art.mime_type, "image/jpeg");
+        assert_eq!(art.data, jpeg);
+
+        let shared = provider.get_artwork(&id(&without_art)).unwrap();
+        assert_eq!(shared, art);
+        assert_eq!(provider.index.read().unwrap().artwork.len(), 1);
+    }
+
+    #[test]
+    fn artwork_capability_requires_embedded_art() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("plain.mp3");
+        write_fake(&path);
+        let provider =
+            FilesystemProvider::new(vec![dir.path().to_string_lossy().to_string()]).unwrap();
+        assert!(!provider.capabilities().artwork);
+        let id = TrackId::new(path.canonicalize().unwrap().to_string_lossy().to_string());
+        assert!(matches!(
+            provider.get_artwork(&id),
+            Err(ProviderError::NotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn sidecar_lrc_lyrics_are_synced() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("song.mp3");
+        write_fake(&path);
+        std::fs::write(
+            dir.path().join("song.lrc"),
+            "[ti:Song]\n[00:04.00]Second\n[00:01.50]First\n",
+        )
+        .unwrap();
+        let provider =
+            FilesystemProvider::new(vec![dir.path().to_string_lossy().to_string()]).unwrap();
+        assert!(provider.capabilities().lyrics);
+
+        let id = TrackId::new(path.canonicalize().unwrap().to_string_lossy().to_string());
+        let lyrics = provider.get_lyrics(&id).unwrap();
+        let synced = lyrics.synced.unwrap();
+        assert_eq!(synced.len(), 2);
+        assert_eq!(synced[0].0, std::time::Duration::from_millis(1_500));
+        assert_eq!(synced[0].1, "First");
+        assert_eq!(lyrics.plain.as_deref(), Some("First\nSecond"));
+    }
+
+    #[test]
+    fn embedded_sylt_lyrics_are_read() {
+        use lofty::id3::v2::{
+            Frame, FrameFlags, FrameValue, Id3v2Tag, SyncTextContentType, SynchronizedText,
+            TimestampFormat,
+        };
+        use lofty::TagExt;
+
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("Artist/Album/one.wav");
+        TestWav::new().cover(&[0xFF, 0xD8, 0xFF]).write(&path);
+        let sylt = SynchronizedText {
+            encoding: lofty::TextEncoding::UTF8,
+            language: *b"eng",
+            timestamp_format: TimestampFormat::MS,
+            content_type: SyncTextContentType::Lyrics,
+            description: None,
+            content: vec![(2_000, "Two".into()), (500, "One".into())],
+        };
+        let mut tag = Id3v2Tag::new();
+        tag.insert(
+            Frame::new(
+                "SYLT",
+                FrameValue::Binary(sylt.as_bytes().unwrap()),
+                FrameFlags::default(),
+            )
+            .unwrap(),
+        );
+        tag.save_to_path(&path).unwrap();
+
+        let provider =
+            FilesystemProvider::new(vec![dir.path().to_string_lossy().to_string()]).unwrap();
+        let id = TrackId::new(path.canonicalize().unwrap().to_string_lossy().to_string());
+        let synced = provider.get_lyrics(&id).unwrap().synced.unwrap();
+        assert_eq!(
+            synced,
+            vec![
+                (std::time::Duration::from_millis(500), "One".to_string()),
+                (std::time::Duration::from_millis(2_000), "Two".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_lyrics_are_not_found() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("plain.mp3");
+        write_fake(&path);
+        let provider =
+            FilesystemProvider::new(vec![dir.path().to_string_lossy().to_string()]).unwrap();
+        let id = TrackId::new(path.canonicalize().unwrap().to_string_lossy().to_string());
+        assert!(matches!(
+            provider.get_lyrics(&id),
+            Err(ProviderError::NotFound { .. })
+        ));
+    }
+
+    fn search(provider: &FilesystemProvider, query: &str) -> usize {
+        provider
+            .search_tracks(
+                query,
+                TrackSearchFilters::default(),
+                PageRequest::first_page(10),
+            )
+            .unwrap()
+            .items
+            .len()
+    }
+
+    #[test]
+    fn watch_picks_up_new_files() {
+        let dir = tempdir().unwrap();
+        write_fake(&dir.path().join("existing.mp3"));
+        let provider =
+            FilesystemProvider::new(vec![dir.path().to_string_lossy().to_string()]).unwrap();
+        provider.watch().unwrap();
+        assert!(provider.is_watching());
+
+        write_fake(&dir.path().join("Artist/Album/arrival.mp3"));
+
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+        while search(&provider, "arrival") == 0 {
+            assert!(
+                std::time::Instant::now() < deadline,
+                "new file never appeared in the index"
+            );
+            std::thread::sleep(std::time::Duration::from_millis(50));
+        }
+
+        provider.stop_watch();
+        assert!(!provider.is_watching());
+    }
 }
@@ -0,0 +1,88 @@
+//! Minimal M3U/M3U8 playlist parsing.
+
+use std::path::{Path, PathBuf};
+
+/// One playable entry from an M3U file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct M3uEntry {
+    /// The entry exactly as written in the playlist.
+    pub location: String,
+    /// `location` resolved against the playlist's directory.
+    pub path: PathBuf,
+    /// Title hint from a preceding `#EXTINF` line.
+    pub title: Option<String>,
+    /// Duration hint from a preceding `#EXTINF` line; `-1` (unknown) maps to `None`.
+    pub duration_seconds: Option<u32>,
+}
+
+/// Parse playlist `contents`, resolving relative entries against `base_dir`.
+///
+/// Blank lines and comments are skipped. `#EXTINF` hints attach to the next
+/// entry only.
+pub fn parse(contents: &str, base_dir: &Path) -> Vec<M3uEntry> {
+    let mut entries = Vec::new();
+    let mut hint: Option<(Option<u32>, Option<String>)> = None;
+    for line in contents.trim_start_matches('\u{feff}').lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        if let Some(info) = trimmed.strip_prefix("#EXTINF:") {
+            hint = Some(parse_extinf(info));
+            continue;
+        }
+        if trimmed.starts_with('#') {
+            continue;
+        }
+        let (duration_seconds, title) = hint.take().unwrap_or((None, None));
+        let location = Path::new(trimmed);
+        let path = if location.is_absolute() {
+            location.to_path_buf()
+        } else {
+            base_dir.join(location)
+        };
+        entries.push(M3uEntry {
+            location: trimmed.to_string(),
+            path,
+            title,
+            duration_seconds,
+        });
+    }
+    entries
+}
+
+/// Split `#EXTINF:<seconds>[ attrs],<title>` into its duration and title.
+fn parse_extinf(info: &str) -> (Option<u32>, Option<String>) {
+    let (head, title) = info.split_once(',').unwrap_or((info, ""));
+    // Extended players put key="value" attributes after the duration.
+    let duration = head
+        .split_whitespace()
+        .next()
+        .and_then(|d| d.parse::<f64>().ok())
+        .filter(|d| *d >= 0.0)
+        .map(|d| d.round() as u32);
+    let title = Some(title.trim())
+        .filter(|t| !t.is_empty())
+        .map(str::to_string);
+    (duration, title)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_entries_with_extinf_hints() {
+        let contents = "\u{feff}#EXTM3U\n\n#EXTINF:215,Artist - Song\nsub/one.mp3\n# a comment\n/abs/two.flac\n#EXTINF:-1 tvg-id=\"x\",Stream\nhttp://example.com/live\n";
+        let entries = parse(contents, Path::new("/music/lists"));
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].path, PathBuf::from("/music/lists/sub/one.mp3"));
+        assert_eq!(entries[0].title.as_deref(), Some("Artist - Song"));
+        assert_eq!(entries[0].duration_seconds, Some(215));
+        assert_eq!(entries[1].path, PathBuf::from("/abs/two.flac"));
+        assert_eq!(entries[1].title, None);
+        assert_eq!(entries[2].location, "http://example.com/live");
+        assert_eq!(entries[2].title.as_deref(), Some("Stream"));
+        assert_eq!(entries[2].duration_seconds, None);
+    }
+}
@@ -1,9 +1,15 @@
-use crate::tags::parse_tags;
+use crate::cue;
+use crate::m3u;
+use crate::tags::{compute_duration, TagReaders};
 use path_clean::PathClean;
-use std::collections::{BTreeMap, BTreeSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
-use tunez_core::models::{Album, AlbumId, Playlist, PlaylistId, Track, TrackId};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tunez_core::models::{
+    Album, AlbumId, Artwork, Playlist, PlaylistId, StreamUrl, Track, TrackId,
+};
 use tunez_core::provider::{ProviderError, ProviderResult};
 use walkdir::WalkDir;
 
@@ -13,25 +19,235 @@ pub struct LibraryIndex {
     pub albums: BTreeMap<AlbumId, Album>,
     pub artists: BTreeSet<String>,
     pub playlists: BTreeMap<PlaylistId, PlaylistEntry>,
+    /// Modification time of each track's file when its tags were last read.
+    pub mtimes: HashMap<TrackId, SystemTime>,
+    /// Tracks whose files carry embedded cover art.
+    pub with_artwork: HashSet<TrackId>,
+    /// Tracks whose tags couldn't be read, indexed from their path alone.
+    pub unreadable: HashSet<TrackId>,
+    /// Album artist of tracks tagged with one (or as compilations). Other
+    /// tracks group under their own artist.
+    pub album_artists: HashMap<TrackId, String>,
+    /// Cover art extracted so far, keyed by album.
+    pub artwork: HashMap<AlbumId, Artwork>,
+    /// Files and directories the last scan couldn't read, by path. Audio
+    /// files among them are still indexed, as `unreadable`.
+    pub failures: Vec<ScanFailure>,
+}
+
+/// A path a scan couldn't read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScanFailure {
+    pub path: PathBuf,
+    pub kind: ScanFailureKind,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScanFailureKind {
+    /// Not readable by this user.
+    PermissionDenied,
+    /// Opened, but not as audio the tag readers understand.
+    Format,
+    /// Any other I/O error.
+    Io,
+}
+
+impl ScanFailureKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            ScanFailureKind::PermissionDenied => "permission denied",
+            ScanFailureKind::Format => "unreadable format",
+            ScanFailureKind::Io => "I/O error",
+        }
+    }
+}
+
+impl ScanFailure {
+    fn from_io(path: PathBuf, err: &std::io::Error) -> Self {
+        let kind = match err.kind() {
+            std::io::ErrorKind::PermissionDenied => ScanFailureKind::PermissionDenied,
+            _ => ScanFailureKind::Io,
+        };
+        Self {
+            path,
+            kind,
+            reason: err.to_string(),
+        }
+    }
+
+    /// Why the tags of the audio file at `path` couldn't be read.
+    fn unreadable(path: &Path) -> Self {
+        match fs::File::open(path) {
+            Ok(_) => Self {
+                path: path.to_path_buf(),
+                kind: ScanFailureKind::Format,
+                reason: "not a recognised audio file, or corrupt".into(),
+            },
+            Err(err) => Self::from_io(path.to_path_buf(), &err),
+        }
+    }
+}
+
+impl LibraryIndex {
+    /// The artist `track`'s album is filed under.
+    pub fn album_artist_of<'a>(&'a self, track: &'a Track) -> &'a str {
+        self.album_artists.get(&track.id).unwrap_or(&track.artist)
+    }
+
+    /// The album `track` belongs to, if it has one.
+    pub fn album_id_of(&self, track: &Track) -> Option<AlbumId> {
+        let album = track.album.as_deref()?;
+        Some(album_id_for(self.album_artist_of(track), album))
+    }
+
+    /// The part of the index under `root` (canonical), as a scan of just
+    /// that root would have found it.
+    pub fn within(&self, root: &Path) -> LibraryIndex {
+        let inside = |id: &TrackId| file_path_of(id).starts_with(root);
+        let mut index = LibraryIndex {
+            tracks: self
+                .tracks
+                .iter()
+                .filter(|t| inside(&t.id))
+                .cloned()
+                .collect(),
+            playlists: self
+                .playlists
+                .iter()
+                .filter(|(_, entry)| entry.path.starts_with(root))
+                .map(|(id, entry)| (id.clone(), entry.clone()))
+                .collect(),
+            mtimes: self
+                .mtimes
+                .iter()
+                .filter(|(id, _)| inside(id))
+                .map(|(id, mtime)| (id.clone(), *mtime))
+                .collect(),
+            with_artwork: self
+                .with_artwork
+                .iter()
+                .filter(|id| inside(id))
+                .cloned()
+                .collect(),
+            unreadable: self
+                .unreadable
+                .iter()
+                .filter(|id| inside(id))
+                .cloned()
+                .collect(),
+            album_artists: self
+                .album_artists
+                .iter()
+                .filter(|(id, _)| inside(id))
+                .map(|(id, artist)| (id.clone(), artist.clone()))
+                .collect(),
+            failures: self
+                .failures
+                .iter()
+                .filter(|f| f.path.starts_with(root))
+                .cloned()
+                .collect(),
+            ..Default::default()
+        };
+        index.rebuild_aggregates();
+        index.artwork = self
+            .artwork
+            .iter()
+            .filter(|(id, _)| index.albums.contains_key(id))
+            .map(|(id, art)| (id.clone(), art.clone()))
+            .collect();
+        index
+    }
+
+    /// When `track`'s file was last modified, in seconds since the Unix epoch.
+    fn modified_at(&self, track: &Track) -> Option<u64> {
+        let mtime = self.mtimes.get(&track.id).or_else(|| {
+            // Cue tracks share their file's entry.
+            let file = file_path_of(&track.id);
+            self.mtimes.get(&TrackId::new(file.to_str()?))
+        })?;
+        mtime
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .ok()
+            .map(|d| d.as_secs())
+    }
+
+    /// Recompute the album and artist aggregates from `tracks`. An album's
+    /// year is its earliest track's, and it counts as added when its newest
+    /// file was.
+    fn rebuild_aggregates(&mut self) {
+        self.albums.clear();
+        self.artists.clear();
+        for track in &self.tracks {
+            self.artists.insert(track.artist.clone());
+            if let Some(album_title) = &track.album {
+                let album_artist = self.album_artist_of(track).to_string();
+                // Compilations are browsable under their album artist too.
+                self.artists.insert(album_artist.clone());
+                let album_id = album_id_for(&album_artist, album_title);
+                let added_at = self.modified_at(track);
+                let album_entry = self.albums.entry(album_id.clone()).or_insert(Album {
+                    id: album_id,
+                    provider_id: "filesystem".into(),
+                    title: album_title.clone(),
+                    artist: album_artist,
+                    track_count: Some(0),
+                    duration_seconds: None,
+                    year: None,
+                    added_at: None,
+                });
+                album_entry.track_count = Some(album_entry.track_count.unwrap_or(0) + 1);
+                album_entry.year = album_entry.year.into_iter().chain(track.year).min();
+                album_entry.added_at = album_entry.added_at.into_iter().chain(added_at).max();
+            }
+        }
+    }
+}
+
+/// What a scan did, used to report progress and verify incremental behaviour.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ScanStats {
+    /// Files whose tags were read.
+    pub parsed: usize,
+    /// Files reused from the previous index because their mtime was unchanged.
+    pub reused: usize,
+    /// Tracks dropped because their file no longer exists.
+    pub removed: usize,
 }
 
 #[derive(Debug, Clone, Default)]
 pub struct PlaylistEntry {
     pub playlist: Playlist,
+    /// The playlist file.
+    pub path: PathBuf,
     pub track_ids: Vec<TrackId>,
+    /// Entries that didn't match an indexed track, with their `#EXTINF` hints.
+    pub unresolved: Vec<m3u::M3uEntry>,
 }
 
 #[derive(Debug, Clone)]
 pub struct ScanOptions {
+    /// Descend into symlinked directories and index symlinked files. Links
+    /// that loop back to an ancestor are skipped.
     pub follow_symlinks: bool,
+    /// Index dotfiles and descend into dot-directories.
+    pub include_hidden: bool,
+    /// Demux files whose headers carry no duration to work it out. Turning
+    /// this off speeds up the first scan of a large library.
+    pub compute_durations: bool,
     pub excluded_paths: Vec<PathBuf>,
     pub extensions_allowlist: Vec<String>,
+    /// How each indexed file's tags are read.
+    pub tag_readers: Arc<TagReaders>,
 }
 
 impl Default for ScanOptions {
     fn default() -> Self {
         Self {
             follow_symlinks: false,
+            include_hidden: false,
+            compute_durations: true,
             excluded_paths: Vec::new(),
             extensions_allowlist: vec![
                 "mp3".into(),
@@ -39,25 +255,72 @@ impl Default for ScanOptions {
                 "flac".into(),
                 "wav".into(),
                 "ogg".into(),
+                "opus".into(),
+                "wv".into(),
+                "ape".into(),
             ],
+            tag_readers: Arc::new(TagReaders::default()),
         }
     }
 }
 
+/// Album artist for compilations without an `ALBUMARTIST` tag.
+pub const VARIOUS_ARTISTS: &str = "Various Artists";
+
+/// Id of `album` by `artist`, the album artist rather than each track's, so a
+/// compilation is one album and same-titled albums by different artists aren't.
 pub fn album_id_for(artist: &str, album: &str) -> AlbumId {
     AlbumId::new(format!("{}::{}", artist, album))
 }
 
-fn canonicalize_within_root(path: &Path, root: &Path) -> Option<PathBuf> {
-    let Ok(canon) = path.canonicalize() else {
-        return None;
-    };
-    let cleaned = canon.clean();
-    if cleaned.starts_with(root) {
-        Some(cleaned)
-    } else {
-        None
+/// The `TrackId` for the file at `path`: its canonical absolute path, with
+/// symlinks resolved, so search, `get_track` and `get_stream_url` all agree on
+/// one form. `None` when the file can't be resolved or its path isn't valid
+/// UTF-8, since a lossily converted id couldn't be opened again.
+pub(crate) fn track_id_for(path: &Path) -> Option<TrackId> {
+    let canon = path.canonicalize().ok()?.clean();
+    match canon.to_str() {
+        Some(id) => Some(TrackId::new(id)),
+        None => {
+            tracing::warn!(path = %canon.display(), "skipping file with a non-UTF-8 path");
+            None
+        }
+    }
+}
+
+/// Id of the part of `file` from `start` until `end`, or to the end of the
+/// file: the file's `file://` URL with a `#t=start[,end]` time fragment in
+/// seconds. That's also its stream URL, which the audio engine plays just
+/// that part of. Other ids are bare paths, so a `#t=` in a file name is
+/// never taken for a cue.
+pub(crate) fn cue_track_id(file: &TrackId, start: Duration, end: Option<Duration>) -> TrackId {
+    let url = StreamUrl::file(Path::new(&file.0));
+    let mut id = format!("{}#t={:.3}", url.0, start.as_secs_f64());
+    if let Some(end) = end {
+        id.push_str(&format!(",{:.3}", end.as_secs_f64()));
     }
+    TrackId::new(id)
+}
+
+/// Whether `id` is a part of a file cut out by a cue sheet.
+pub(crate) fn is_cue_track(id: &TrackId) -> bool {
+    id.0.starts_with("file://")
+}
+
+/// The file track `id` plays from.
+pub(crate) fn file_path_of(id: &TrackId) -> PathBuf {
+    if is_cue_track(id) {
+        if let Some(path) = StreamUrl::new(id.0.as_str()).file_path() {
+            return path;
+        }
+    }
+    PathBuf::from(&id.0)
+}
+
+/// `track_id_for`, for files that resolve inside `root` (itself canonical).
+/// A symlink pointing out of the library is skipped.
+fn track_id_within_root(path: &Path, root: &Path) -> Option<TrackId> {
+    track_id_for(path).filter(|id| Path::new(&id.0).starts_with(root))
 }
 
 #[allow(dead_code)]
@@ -69,13 +332,69 @@ pub fn scan_library_with_options(
     roots: Vec<String>,
     opts: ScanOptions,
 ) -> ProviderResult<LibraryIndex> {
+    scan(roots, &opts, None).map(|(index, _)| index)
+}
+
+/// Rescan, re-reading tags only for files that are new or whose mtime changed
+/// since `previous` was built. Tracks whose files disappeared are dropped.
+pub fn rescan_incremental(
+    previous: &LibraryIndex,
+    roots: Vec<String>,
+    opts: &ScanOptions,
+) -> ProviderResult<(LibraryIndex, ScanStats)> {
+    scan(roots, opts, Some(previous))
+}
+
+fn scan(
+    roots: Vec<String>,
+    opts: &ScanOptions,
+    previous: Option<&LibraryIndex>,
+) -> ProviderResult<(LibraryIndex, ScanStats)> {
+    let previous_tracks: HashMap<&TrackId, &Track> = previous
+        .map(|prev| prev.tracks.iter().map(|t| (&t.id, t)).collect())
+        .unwrap_or_default();
     let mut index = LibraryIndex::default();
+    let mut stats = ScanStats::default();
+    // Playlists and cue sheets are resolved once every track is indexed.
+    let mut playlist_files = Vec::new();
+    let mut cue_files = Vec::new();
+    // Files reached through more than one path (symlinks, overlapping roots)
+    // are indexed once.
+    let mut seen = HashSet::new();
+    let mut seen_lists = HashSet::new();
     for root in roots {
-        let root_path = PathBuf::from(root.clone());
-        for entry in WalkDir::new(&root_path).follow_links(opts.follow_symlinks) {
+        // Compare against the resolved root so a relative or symlinked root
+        // still contains its canonical track paths.
+        let root_path = match PathBuf::from(&root).canonicalize() {
+            Ok(path) => path.clean(),
+            Err(err) => {
+                tracing::warn!(root = %root, error = %err, "skipping library root");
+                continue;
+            }
+        };
+        let walker = WalkDir::new(&root_path)
+            .follow_links(opts.follow_symlinks)
+            .into_iter()
+            // The root itself may be hidden; only what's inside it is skipped.
+            .filter_entry(|e| opts.include_hidden || e.depth() == 0 || !is_hidden(e));
+        for entry in walker {
             let entry = match entry {
                 Ok(e) => e,
-                Err(_e) => {
+                Err(e) => {
+                    // walkdir reports a symlink cycle as an error instead of
+                    // following it again.
+                    if let Some(ancestor) = e.loop_ancestor() {
+                        tracing::warn!(
+                            path = ?e.path(),
+                            ancestor = %ancestor.display(),
+                            "skipping symlink loop"
+                        );
+                    } else if let (Some(path), Some(err)) = (e.path(), e.io_error()) {
+                        tracing::warn!(path = %path.display(), error = %err, "skipping unreadable path");
+                        index
+                            .failures
+                            .push(ScanFailure::from_io(path.to_path_buf(), err));
+                    }
                     continue;
                 }
             };
@@ -90,36 +409,88 @@ pub fn scan_library_with_options(
 
             if let Some(ext) = path.extension().and_then(|s| s.to_str()) {
                 if is_supported_extension(ext, &opts.extensions_allowlist) {
-                    if let Some(track) = parse_track(path, &root_path)? {
-                        index.artists.insert(track.artist.clone());
-                        if let Some(album_title) = &track.album {
-                            let album_id = album_id_for(&track.artist, album_title);
-                            let album_entry =
-                                index.albums.entry(album_id.clone()).or_insert(Album {
-                                    id: album_id.clone(),
-                                    provider_id: "filesystem".into(),
-                                    title: album_title.clone(),
-                                    artist: track.artist.clone(),
-                                    track_count: Some(0),
-                                    duration_seconds: None,
-                                });
-                            album_entry.track_count =
-                                Some(album_entry.track_count.unwrap_or(0) + 1);
+                    let Some(id) = track_id_within_root(path, &root_path) else {
+                        continue;
+                    };
+                    if !seen.insert(id.clone()) {
+                        continue;
+                    }
+                    let mtime = entry.metadata().ok().and_then(|m| m.modified().ok());
+                    let unchanged = previous.and_then(|prev| {
+                        let prev_mtime = prev.mtimes.get(&id)?;
+                        // Any difference counts, including clocks moving backwards.
+                        (Some(*prev_mtime) == mtime).then_some(())?;
+                        let track = previous_tracks.get(&id).map(|t| (*t).clone())?;
+                        Some(ParsedTrack {
+                            has_artwork: prev.with_artwork.contains(&id),
+                            unreadable: prev.unreadable.contains(&id),
+                            album_artist: prev.album_artists.get(&id).cloned(),
+                            track,
+                        })
+                    });
+                    let parsed = match unchanged {
+                        Some(parsed) => {
+                            stats.reused += 1;
+                            parsed
                         }
-                        index.tracks.push(track);
+                        None => {
+                            stats.parsed += 1;
+                            parse_track(path, &root_path, id, opts)?
+                        }
+                    };
+                    let track = parsed.track;
+                    if let Some(mtime) = mtime {
+                        index.mtimes.insert(track.id.clone(), mtime);
+                    }
+                    if parsed.has_artwork {
+                        index.with_artwork.insert(track.id.clone());
+                    }
+                    if parsed.unreadable {
+                        index.unreadable.insert(track.id.clone());
+                        index.failures.push(ScanFailure::unreadable(path));
+                    }
+                    if let Some(album_artist) = parsed.album_artist {
+                        index.album_artists.insert(track.id.clone(), album_artist);
                     }
-                } else if is_playlist_extension(ext) {
-                    if let Some(rel) = path.strip_prefix(&root_path).ok().and_then(|p| p.to_str()) {
-                        load_m3u_playlist(&mut index, path, rel, &root_path, &opts)?;
+                    index.tracks.push(track);
+                } else if is_playlist_extension(ext) || ext.eq_ignore_ascii_case("cue") {
+                    let canonical = path
+                        .canonicalize()
+                        .map_or_else(|_| path.to_path_buf(), |p| p.clean());
+                    if !seen_lists.insert(canonical) {
+                        continue;
+                    }
+                    if ext.eq_ignore_ascii_case("cue") {
+                        cue_files.push(path.to_path_buf());
+                    } else if let Some(rel) =
+                        path.strip_prefix(&root_path).ok().and_then(|p| p.to_str())
+                    {
+                        playlist_files.push((path.to_path_buf(), rel.to_string()));
                     }
                 }
             }
         }
     }
+    if let Some(prev) = previous {
+        let present: HashSet<&TrackId> = index.tracks.iter().map(|t| &t.id).collect();
+        stats.removed = prev
+            .tracks
+            .iter()
+            .filter(|t| !present.contains(&t.id))
+            .count();
+    }
+    for path in cue_files {
+        split_by_cue_sheet(&mut index, &path);
+    }
+    for (path, rel) in playlist_files {
+        load_m3u_playlist(&mut index, &path, &rel);
+    }
+    index.rebuild_aggregates();
+    index.failures.sort_by(|a, b| a.path.cmp(&b.path));
     index
         .tracks
         .sort_by(|a, b| a.title.cmp(&b.title).then_with(|| a.id.0.cmp(&b.id.0)));
-    Ok(index)
+    Ok((index, stats))
 }
 
 fn is_supported_extension(ext: &str, allowlist: &[String]) -> bool {
@@ -127,17 +498,33 @@ fn is_supported_extension(ext: &str, allowlist: &[String]) -> bool {
     allowlist.iter().any(|allowed| allowed == &lowered)
 }
 
+fn is_hidden(entry: &walkdir::DirEntry) -> bool {
+    entry
+        .file_name()
+        .to_str()
+        .is_some_and(|name| name.starts_with('.'))
+}
+
 fn is_playlist_extension(ext: &str) -> bool {
     matches!(ext.to_ascii_lowercase().as_str(), "m3u" | "m3u8")
 }
 
-fn parse_track(path: &Path, root: &Path) -> ProviderResult<Option<Track>> {
-    let Some(canonical) = canonicalize_within_root(path, root) else {
-        return Ok(None);
-    };
-    let id = TrackId::new(canonical.to_string_lossy().to_string());
+/// A track built from a file, with what its tags said about the file.
+struct ParsedTrack {
+    track: Track,
+    has_artwork: bool,
+    unreadable: bool,
+    /// Set when it differs from the track's artist.
+    album_artist: Option<String>,
+}
 
-    let relative = canonical
+fn parse_track(
+    path: &Path,
+    root: &Path,
+    id: TrackId,
+    opts: &ScanOptions,
+) -> ProviderResult<ParsedTrack> {
+    let relative = Path::new(&id.0)
         .strip_prefix(root)
         .map_err(|e| ProviderError::Other {
             message: e.to_string(),
@@ -172,10 +559,17 @@ fn parse_track(path: &Path, root: &Path) -> ProviderResult<Option<Track>> {
         .and_then(|s| s.to_str())
         .unwrap_or("Unknown");
 
-    let tags = parse_tags(path)?;
+    let mut tags = opts.tag_readers.read(path);
+    if tags.duration_seconds.is_none() && opts.compute_durations {
+        tags.duration_seconds = compute_duration(path);
+    }
     let artist = tags.artist.unwrap_or(inferred_artist);
     let album = tags.album.or(inferred_album);
     let title = tags.title.unwrap_or_else(|| file_stem.to_string());
+    let album_artist = tags
+        .album_artist
+        .or_else(|| tags.compilation.then(|| VARIOUS_ARTISTS.to_string()))
+        .filter(|album_artist| *album_artist != artist);
 
     let track = Track {
         id,
@@ -185,60 +579,142 @@ fn parse_track(path: &Path, root: &Path) -> ProviderResult<Option<Track>> {
         album,
         duration_seconds: tags.duration_seconds,
         track_number: tags.track_number,
+        disc_number: tags.disc_number,
+        year: tags.year,
+        genre: tags.genre,
+        musicbrainz_id: tags.musicbrainz_id,
+        replay_gain: tags.replay_gain,
     };
-    Ok(Some(track))
+    Ok(ParsedTrack {
+        track,
+        has_artwork: tags.has_artwork,
+        unreadable: tags.unreadable,
+        album_artist,
+    })
 }
 
-fn load_m3u_playlist(
-    index: &mut LibraryIndex,
-    path: &Path,
-    rel_path: &str,
-    root: &Path,
-    opts: &ScanOptions,
-) -> ProviderResult<()> {
-    let playlist_id = PlaylistId::new(rel_path);
-    let playlist = Playlist {
-        id: playlist_id.clone(),
-        provider_id: "filesystem".into(),
-        name: rel_path.to_string(),
-        description: None,
-        track_count: None,
+/// Replace each indexed file the cue sheet at `path` divides into tracks with
+/// one track per cue entry, named by the sheet and falling back to the file's
+/// tags. Files it gives a single track starting at zero are left whole.
+/// Unreadable sheets are skipped.
+fn split_by_cue_sheet(index: &mut LibraryIndex, path: &Path) {
+    let contents = match fs::read(path) {
+        Ok(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+        Err(err) => {
+            tracing::warn!(cue = %path.display(), error = %err, "failed to read cue sheet");
+            return;
+        }
     };
+    let sheet = cue::parse(&contents, path.parent().unwrap_or(Path::new("")));
 
-    let contents = fs::read_to_string(path).map_err(|e| ProviderError::Other {
-        message: e.to_string(),
-    })?;
-    let mut track_ids = Vec::new();
-    for line in contents.lines() {
-        let trimmed = line.trim();
-        if trimmed.is_empty() || trimmed.starts_with('#') {
+    // The sheet's entries for each file it names, in order.
+    let mut files: Vec<(TrackId, Vec<&cue::CueTrack>)> = Vec::new();
+    for entry in &sheet.tracks {
+        let Some(id) = track_id_for(&entry.path) else {
+            continue;
+        };
+        match files.iter_mut().find(|(file, _)| *file == id) {
+            Some((_, entries)) => entries.push(entry),
+            None => files.push((id, vec![entry])),
+        }
+    }
+
+    for (file_id, entries) in files {
+        if entries.len() == 1 && entries[0].start.is_zero() {
             continue;
         }
-        let candidate = if Path::new(trimmed).is_absolute() {
-            PathBuf::from(trimmed)
-        } else {
-            root.join(trimmed)
+        let Some(position) = index.tracks.iter().position(|t| t.id == file_id) else {
+            continue;
         };
-        if let Some(canonical) = canonicalize_within_root(&candidate, root) {
-            if opts.extensions_allowlist.iter().any(|ext| {
-                candidate
-                    .extension()
-                    .and_then(|s| s.to_str())
-                    .map(|e| e.eq_ignore_ascii_case(ext))
-                    .unwrap_or(false)
-            }) {
-                track_ids.push(TrackId::new(canonical.to_string_lossy().to_string()));
+        let file = index.tracks.remove(position);
+        let has_artwork = index.with_artwork.remove(&file_id);
+        let file_album_artist = index.album_artists.remove(&file_id);
+        let file_duration = file.duration_seconds.map(|s| Duration::from_secs(s.into()));
+
+        for (i, entry) in entries.iter().enumerate() {
+            let end = entries.get(i + 1).map(|next| next.start);
+            let length = match end {
+                Some(end) => Some(end.saturating_sub(entry.start)),
+                None => file_duration.map(|d| d.saturating_sub(entry.start)),
+            };
+            let artist = entry
+                .performer
+                .clone()
+                .or_else(|| sheet.performer.clone())
+                .unwrap_or_else(|| file.artist.clone());
+            let track = Track {
+                id: cue_track_id(&file_id, entry.start, end),
+                title: entry
+                    .title
+                    .clone()
+                    .unwrap_or_else(|| format!("Track {:02}", entry.number)),
+                album: sheet.title.clone().or_else(|| file.album.clone()),
+                duration_seconds: length.map(|d| d.as_secs_f64().round() as u32),
+                track_number: Some(entry.number),
+                artist,
+                ..file.clone()
+            };
+            let album_artist = sheet
+                .performer
+                .clone()
+                .or_else(|| file_album_artist.clone())
+                .filter(|album_artist| *album_artist != track.artist);
+            if let Some(album_artist) = album_artist {
+                index.album_artists.insert(track.id.clone(), album_artist);
+            }
+            if has_artwork {
+                index.with_artwork.insert(track.id.clone());
             }
+            index.tracks.push(track);
         }
     }
+}
 
+/// Load an M3U/M3U8 playlist, matching its entries to indexed tracks by
+/// canonical path. Unreadable playlists are skipped.
+fn load_m3u_playlist(index: &mut LibraryIndex, path: &Path, rel_path: &str) {
+    let contents = match fs::read(path) {
+        Ok(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+        Err(err) => {
+            tracing::warn!(playlist = %path.display(), error = %err, "failed to read playlist");
+            return;
+        }
+    };
+    let base_dir = path.parent().unwrap_or(Path::new(""));
+    let known: HashSet<&TrackId> = index.tracks.iter().map(|t| &t.id).collect();
+
+    let mut track_ids = Vec::new();
+    let mut unresolved = Vec::new();
+    for entry in m3u::parse(&contents, base_dir) {
+        let id = track_id_for(&entry.path).filter(|id| known.contains(id));
+        match id {
+            Some(id) => track_ids.push(id),
+            None => unresolved.push(entry),
+        }
+    }
+    if !unresolved.is_empty() {
+        tracing::debug!(
+            playlist = rel_path,
+            missing = unresolved.len(),
+            "playlist entries not found in library"
+        );
+    }
+
+    let playlist_id = PlaylistId::new(rel_path);
+    let playlist = Playlist {
+        id: playlist_id.clone(),
+        provider_id: "filesystem".into(),
+        name: rel_path.to_string(),
+        description: None,
+        track_count: Some(track_ids.len() as u32),
+    };
     index.playlists.insert(
         playlist_id,
         PlaylistEntry {
             playlist,
+            path: path.to_path_buf(),
             track_ids,
+            unresolved,
         },
     );
-
-    Ok(())
 }
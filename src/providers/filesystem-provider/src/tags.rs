@@ -1,36 +1,691 @@
-use lofty::{Accessor, AudioFile, ItemKey, Probe, TaggedFileExt};
+use lofty::id3::v2::{SyncTextContentType, SynchronizedText, TimestampFormat};
+use lofty::{
+    Accessor, AudioFile, FileType, ItemKey, ItemValue, MimeType, PictureType, Probe, TaggedFile,
+    TaggedFileExt,
+};
 use std::path::Path;
-use tunez_core::provider::ProviderResult;
+use std::time::Duration;
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+use tunez_core::lyrics::Lyrics;
+use tunez_core::models::{Artwork, Chapter};
+use tunez_core::replay_gain::{parse_gain, parse_peak, ReplayGain};
 
 #[derive(Debug, Clone, Default)]
 pub struct ParsedTags {
     pub title: Option<String>,
     pub artist: Option<String>,
     pub album: Option<String>,
+    /// `ALBUMARTIST` / `TPE2` / `aART`.
+    pub album_artist: Option<String>,
+    /// The compilation flag (`COMPILATION` / `TCMP` / `cpil`) is set.
+    pub compilation: bool,
+    /// From the format headers; `None` when they don't say.
     pub duration_seconds: Option<u32>,
     pub track_number: Option<u32>,
+    pub disc_number: Option<u32>,
+    pub year: Option<u32>,
+    pub genre: Option<String>,
+    /// MusicBrainz recording ID (`MUSICBRAINZ_TRACKID` / `UFID`).
+    pub musicbrainz_id: Option<String>,
+    pub replay_gain: ReplayGain,
+    /// Whether any tag carries an embedded picture.
+    pub has_artwork: bool,
+    /// The file couldn't be probed; every other field is empty.
+    pub unreadable: bool,
 }
 
-pub fn parse_tags(path: &Path) -> ProviderResult<ParsedTags> {
-    let tagged = match Probe::open(path).and_then(|p| p.read()) {
-        Ok(tagged) => tagged,
-        Err(_) => return Ok(ParsedTags::default()),
-    };
+/// Reads the tags of one audio format.
+pub trait TagReader: Send + Sync {
+    /// Lowercase extensions of the files this reader handles.
+    fn extensions(&self) -> &[&str];
+
+    /// The tags of `path`, or `None` when it can't be parsed as this format.
+    fn read(&self, path: &Path) -> Option<ParsedTags>;
+}
+
+/// A format lofty parses, read as that format whatever the file's contents
+/// look like.
+struct LoftyReader {
+    file_type: FileType,
+    extensions: &'static [&'static str],
+}
+
+impl TagReader for LoftyReader {
+    fn extensions(&self) -> &[&str] {
+        self.extensions
+    }
+
+    fn read(&self, path: &Path) -> Option<ParsedTags> {
+        let tagged = Probe::open(path)
+            .ok()?
+            .set_file_type(self.file_type)
+            .read()
+            .ok()?;
+        Some(tags_from(&tagged))
+    }
+}
+
+const BUILTIN_FORMATS: &[(FileType, &[&str])] = &[
+    (FileType::Mpeg, &["mp3"]),
+    (FileType::Mp4, &["m4a", "m4b", "mp4"]),
+    (FileType::Flac, &["flac"]),
+    (FileType::Wav, &["wav"]),
+    (FileType::Vorbis, &["ogg", "oga"]),
+    // Vorbis comments in an Ogg container, like Vorbis.
+    (FileType::Opus, &["opus"]),
+    // Both carry APE tags (or ID3v1) at the end of the file.
+    (FileType::WavPack, &["wv"]),
+    (FileType::Ape, &["ape"]),
+];
+
+/// Picks the [`TagReader`] for a file by its extension. A file no reader
+/// takes (an unknown extension, or an `.ogg` that is really Opus) is probed
+/// by its contents instead, and one that can't be read at all comes back
+/// `unreadable` so the scan still indexes it by file name.
+pub struct TagReaders {
+    readers: Vec<Box<dyn TagReader>>,
+}
+
+impl TagReaders {
+    /// No readers; every file is probed by its contents.
+    pub fn empty() -> Self {
+        Self {
+            readers: Vec::new(),
+        }
+    }
+
+    /// Add `reader`, taking its extensions over from any added before it.
+    pub fn register(&mut self, reader: Box<dyn TagReader>) {
+        self.readers.push(reader);
+    }
+
+    fn reader_for(&self, ext: &str) -> Option<&dyn TagReader> {
+        let ext = ext.to_ascii_lowercase();
+        self.readers
+            .iter()
+            .rev()
+            .find(|reader| reader.extensions().contains(&ext.as_str()))
+            .map(|reader| reader.as_ref())
+    }
+
+    pub fn read(&self, path: &Path) -> ParsedTags {
+        let by_extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(|ext| self.reader_for(ext))
+            .and_then(|reader| reader.read(path));
+        by_extension
+            .or_else(|| {
+                let tagged = Probe::open(path)
+                    .ok()?
+                    .guess_file_type()
+                    .ok()?
+                    .read()
+                    .ok()?;
+                Some(tags_from(&tagged))
+            })
+            .unwrap_or(ParsedTags {
+                unreadable: true,
+                ..Default::default()
+            })
+    }
+}
+
+impl Default for TagReaders {
+    /// Readers for every format lofty handles that the scanner indexes.
+    fn default() -> Self {
+        let mut readers = Self::empty();
+        for &(file_type, extensions) in BUILTIN_FORMATS {
+            readers.register(Box::new(LoftyReader {
+                file_type,
+                extensions,
+            }));
+        }
+        readers
+    }
+}
 
+impl std::fmt::Debug for TagReaders {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries(self.readers.iter().map(|reader| reader.extensions()))
+            .finish()
+    }
+}
+
+fn tags_from(tagged: &TaggedFile) -> ParsedTags {
     let tag = tagged.primary_tag().or_else(|| tagged.first_tag());
     let properties = tagged.properties();
 
     let title = tag.and_then(|t| t.get_string(&ItemKey::TrackTitle).map(|s| s.to_string()));
     let artist = tag.and_then(|t| t.artist().map(|s| s.to_string()));
     let album = tag.and_then(|t| t.album().map(|s| s.to_string()));
-    let duration_seconds = Some(properties.duration().as_secs() as u32);
+    let album_artist = tag
+        .and_then(|t| t.get_string(&ItemKey::AlbumArtist))
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_string());
+    let compilation = tag
+        .and_then(|t| t.get_string(&ItemKey::FlagCompilation))
+        .is_some_and(|flag| matches!(flag.trim(), "1" | "true"));
+    // lofty reports zero when the headers don't say how long the file is.
+    let duration_seconds = Some(properties.duration().as_secs() as u32).filter(|&secs| secs > 0);
     let track_number = tag.and_then(|t| t.track());
+    let disc_number = tag.and_then(|t| t.disk());
+    let year = tag.and_then(|t| t.year());
+    let genre = tag.and_then(|t| t.genre().map(|s| s.to_string()));
+    let musicbrainz_id = tag.and_then(|t| {
+        t.get_string(&ItemKey::MusicBrainzRecordingId)
+            .map(|s| s.to_string())
+    });
+    let tag_value = |key: &ItemKey| tag.and_then(|t| t.get_string(key));
+    let replay_gain = ReplayGain {
+        track_gain_db: tag_value(&ItemKey::ReplayGainTrackGain).and_then(parse_gain),
+        album_gain_db: tag_value(&ItemKey::ReplayGainAlbumGain).and_then(parse_gain),
+        track_peak: tag_value(&ItemKey::ReplayGainTrackPeak).and_then(parse_peak),
+        album_peak: tag_value(&ItemKey::ReplayGainAlbumPeak).and_then(parse_peak),
+    };
+    let has_artwork = tagged.tags().iter().any(|t| !t.pictures().is_empty());
 
-    Ok(ParsedTags {
+    ParsedTags {
         title,
         artist,
         album,
+        album_artist,
+        compilation,
         duration_seconds,
         track_number,
+        disc_number,
+        year,
+        genre,
+        musicbrainz_id,
+        replay_gain,
+        has_artwork,
+        unreadable: false,
+    }
+}
+
+/// Work out the duration of `path` by demuxing it: the frame count over the
+/// sample rate when the container records it, otherwise the sum of every
+/// packet's duration. Slower than reading headers, so only used when those
+/// come up empty. `None` if the file can't be demuxed.
+pub fn compute_duration(path: &Path) -> Option<u32> {
+    let file = std::fs::File::open(path).ok()?;
+    let mut hint = Hint::new();
+    if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+        hint.with_extension(ext);
+    }
+    let mss = MediaSourceStream::new(Box::new(file), Default::default());
+    let probed = symphonia::default::get_probe()
+        .format(
+            &hint,
+            mss,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .ok()?;
+    let mut format = probed.format;
+    let track = format.default_track()?;
+    let track_id = track.id;
+    let params = track.codec_params.clone();
+
+    let secs = match (params.n_frames, params.sample_rate) {
+        (Some(frames), Some(rate)) if rate > 0 => frames / u64::from(rate),
+        _ => {
+            let time_base = params.time_base?;
+            let mut total = 0u64;
+            loop {
+                match format.next_packet() {
+                    Ok(packet) if packet.track_id() == track_id => total += packet.dur,
+                    Ok(_) => {}
+                    Err(SymphoniaError::IoError(_)) => break,
+                    Err(_) => return None,
+                }
+            }
+            time_base.calc_time(total).seconds
+        }
+    };
+    u32::try_from(secs).ok().filter(|&secs| secs > 0)
+}
+
+/// Read the embedded cover (APIC/PICTURE), preferring the front cover.
+pub fn read_artwork(path: &Path) -> Option<Artwork> {
+    let tagged = Probe::open(path).and_then(|p| p.read()).ok()?;
+    let pictures: Vec<_> = tagged.tags().iter().flat_map(|t| t.pictures()).collect();
+    let picture = pictures
+        .iter()
+        .find(|p| p.pic_type() == PictureType::CoverFront)
+        .or_else(|| pictures.first())?;
+    let data = picture.data().to_vec();
+    let mime_type = match picture.mime_type() {
+        MimeType::None | MimeType::Unknown(_) => sniff_image_mime(&data)?,
+        known => known.as_str(),
+    };
+    Some(Artwork {
+        mime_type: mime_type.to_string(),
+        data,
     })
 }
+
+/// Read lyrics for `path`: a sidecar `.lrc` file next to it wins, then an
+/// embedded SYLT frame, then embedded USLT text.
+pub fn read_lyrics(path: &Path) -> Option<Lyrics> {
+    if let Ok(contents) = std::fs::read_to_string(path.with_extension("lrc")) {
+        let lyrics = Lyrics::from_text(&contents);
+        if !lyrics.is_empty() {
+            return Some(lyrics);
+        }
+    }
+
+    let tagged = Probe::open(path).and_then(|p| p.read()).ok()?;
+    let mut plain = None;
+    for tag in tagged.tags() {
+        // lofty leaves SYLT unparsed, as a raw binary item.
+        let synced = tag
+            .get_items(&ItemKey::Unknown("SYLT".to_string()))
+            .filter_map(|item| match item.value() {
+                ItemValue::Binary(data) => SynchronizedText::parse(data).ok(),
+                _ => None,
+            })
+            .find(|sylt| {
+                sylt.content_type == SyncTextContentType::Lyrics
+                    // MPEG frame offsets would need the frame rate; only milliseconds are usable.
+                    && sylt.timestamp_format == TimestampFormat::MS
+            });
+        if let Some(sylt) = synced {
+            let mut lines: Vec<_> = sylt
+                .content
+                .into_iter()
+                .map(|(ms, text)| {
+                    (
+                        Duration::from_millis(u64::from(ms)),
+                        text.trim().to_string(),
+                    )
+                })
+                .collect();
+            lines.sort_by_key(|(stamp, _)| *stamp);
+            let text = lines
+                .iter()
+                .map(|(_, line)| line.as_str())
+                .collect::<Vec<_>>()
+                .join("\n");
+            return Some(Lyrics {
+                synced: Some(lines),
+                plain: Some(text),
+            });
+        }
+        if plain.is_none() {
+            plain = tag.get_string(&ItemKey::Lyrics).map(Lyrics::from_text);
+        }
+    }
+    plain.filter(|lyrics| !lyrics.is_empty())
+}
+
+/// Read the chapter markers embedded in `path`, earliest first: ID3 `CHAP`
+/// frames or Vorbis `CHAPTERnnn` / `CHAPTERnnnNAME` comments. Empty when
+/// the file has none.
+pub fn read_chapters(path: &Path) -> Vec<Chapter> {
+    let Some(tagged) = Probe::open(path).and_then(|p| p.read()).ok() else {
+        return Vec::new();
+    };
+    let mut chapters = Vec::new();
+    for tag in tagged.tags() {
+        let mut vorbis: Vec<(String, Option<Duration>, Option<String>)> = Vec::new();
+        for item in tag.items() {
+            let ItemKey::Unknown(key) = item.key() else {
+                continue;
+            };
+            let key = key.to_ascii_uppercase();
+            match (key.as_str(), item.value()) {
+                // lofty leaves CHAP unparsed too.
+                ("CHAP", ItemValue::Binary(data)) => chapters.extend(parse_chap_frame(data)),
+                (key, ItemValue::Text(text)) if key.starts_with("CHAPTER") => {
+                    let rest = &key["CHAPTER".len()..];
+                    let (number, is_name) = match rest.strip_suffix("NAME") {
+                        Some(number) => (number, true),
+                        None => (rest, false),
+                    };
+                    if number.is_empty() || !number.bytes().all(|b| b.is_ascii_digit()) {
+                        continue;
+                    }
+                    let index = match vorbis.iter().position(|(n, _, _)| n == number) {
+                        Some(index) => index,
+                        None => {
+                            vorbis.push((number.to_string(), None, None));
+                            vorbis.len() - 1
+                        }
+                    };
+                    if is_name {
+                        vorbis[index].2 = Some(text.trim().to_string());
+                    } else {
+                        vorbis[index].1 = parse_chapter_time(text);
+                    }
+                }
+                _ => {}
+            }
+        }
+        chapters.extend(vorbis.into_iter().filter_map(|(_, start, title)| {
+            Some(Chapter {
+                start: start?,
+                title: title.filter(|t| !t.is_empty()),
+            })
+        }));
+        if !chapters.is_empty() {
+            break;
+        }
+    }
+    chapters.sort_by_key(|chapter| chapter.start);
+    chapters.dedup_by_key(|chapter| chapter.start);
+    chapters
+}
+
+/// A Vorbis chapter timestamp, `HH:MM:SS.mmm`.
+fn parse_chapter_time(text: &str) -> Option<Duration> {
+    let mut parts = text.trim().splitn(3, ':');
+    let hours: u64 = parts.next()?.parse().ok()?;
+    let minutes: u64 = parts.next()?.parse().ok()?;
+    let seconds: f64 = parts.next()?.parse().ok()?;
+    if minutes >= 60 || !(0.0..60.0).contains(&seconds) {
+        return None;
+    }
+    Some(Duration::from_secs(hours * 3600 + minutes * 60) + Duration::from_secs_f64(seconds))
+}
+
+/// The body of an ID3v2 `CHAP` frame: a NUL-terminated element ID, start
+/// and end times in milliseconds, byte offsets, then sub-frames of which
+/// `TIT2` names the chapter.
+fn parse_chap_frame(data: &[u8]) -> Option<Chapter> {
+    let id_end = data.iter().position(|&b| b == 0)?;
+    let times = data.get(id_end + 1..id_end + 17)?;
+    let start_ms = u32::from_be_bytes(times[..4].try_into().ok()?);
+    let mut rest = &data[id_end + 17..];
+    let mut title = None;
+    while rest.len() >= 10 {
+        let (header, body) = rest.split_at(10);
+        let size = frame_size(&header[4..8]);
+        let Some(body) = body.get(..size) else {
+            break;
+        };
+        if &header[..4] == b"TIT2" {
+            title = decode_text_frame(body).filter(|t| !t.is_empty());
+            break;
+        }
+        rest = &rest[10 + size..];
+    }
+    Some(Chapter {
+        start: Duration::from_millis(u64::from(start_ms)),
+        title,
+    })
+}
+
+/// A sub-frame size: synchsafe in ID3v2.4 and plain in v2.3, which agree
+/// whenever no byte has its high bit set.
+fn frame_size(bytes: &[u8]) -> usize {
+    if bytes.iter().all(|&b| b < 0x80) {
+        bytes
+            .iter()
+            .fold(0, |size, &b| (size << 7) | usize::from(b))
+    } else {
+        bytes
+            .iter()
+            .fold(0, |size, &b| (size << 8) | usize::from(b))
+    }
+}
+
+/// The text of an ID3v2 text frame, in any of its four encodings.
+fn decode_text_frame(body: &[u8]) -> Option<String> {
+    let (&encoding, text) = body.split_first()?;
+    let utf16 = |bytes: &[u8], big_endian: bool| {
+        let units: Vec<u16> = bytes
+            .chunks_exact(2)
+            .map(|pair| {
+                let pair = [pair[0], pair[1]];
+                if big_endian {
+                    u16::from_be_bytes(pair)
+                } else {
+                    u16::from_le_bytes(pair)
+                }
+            })
+            .collect();
+        String::from_utf16_lossy(&units)
+    };
+    let text = match encoding {
+        0 => text.iter().map(|&b| char::from(b)).collect(),
+        1 => match text {
+            [0xFF, 0xFE, rest @ ..] => utf16(rest, false),
+            [0xFE, 0xFF, rest @ ..] => utf16(rest, true),
+            _ => utf16(text, true),
+        },
+        2 => utf16(text, true),
+        3 => String::from_utf8_lossy(text).into_owned(),
+        _ => return None,
+    };
+    Some(text.trim_end_matches('\0').trim().to_string())
+}
+
+/// Detect an image type from its magic bytes, for pictures tagged without one.
+fn sniff_image_mime(data: &[u8]) -> Option<&'static str> {
+    match data {
+        [0xFF, 0xD8, 0xFF, ..] => Some("image/jpeg"),
+        [0x89, b'P', b'N', b'G', ..] => Some("image/png"),
+        [b'G', b'I', b'F', b'8', ..] => Some("image/gif"),
+        [b'B', b'M', ..] => Some("image/bmp"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lofty::{Tag, TagExt, TagType};
+    use tempfile::tempdir;
+
+    fn assert_tags(tags: &ParsedTags, title: &str, artist: &str, album: &str) {
+        assert!(!tags.unreadable);
+        assert_eq!(tags.title.as_deref(), Some(title));
+        assert_eq!(tags.artist.as_deref(), Some(artist));
+        assert_eq!(tags.album.as_deref(), Some(album));
+    }
+
+    /// `header` followed by an APE tag, as WavPack and Monkey's Audio store them.
+    fn write_ape_tagged(path: &Path, header: &[u8]) {
+        let mut contents = header.to_vec();
+        contents.resize(64, 0);
+        std::fs::write(path, contents).unwrap();
+        let mut tag = Tag::new(TagType::Ape);
+        tag.set_title("Lullaby".into());
+        tag.set_artist("The Cure".into());
+        tag.set_album("Disintegration".into());
+        tag.save_to_path(path).unwrap();
+    }
+
+    fn ogg_crc(data: &[u8]) -> u32 {
+        data.iter().fold(0u32, |crc, &byte| {
+            (0..8).fold(crc ^ (u32::from(byte) << 24), |crc, _| {
+                if crc & 0x8000_0000 != 0 {
+                    (crc << 1) ^ 0x04c1_1db7
+                } else {
+                    crc << 1
+                }
+            })
+        })
+    }
+
+    /// One Ogg page holding `packet` whole.
+    fn ogg_page(sequence: u32, flags: u8, granule: u64, packet: &[u8]) -> Vec<u8> {
+        let mut page = b"OggS\0".to_vec();
+        page.push(flags);
+        page.extend_from_slice(&granule.to_le_bytes());
+        page.extend_from_slice(&1u32.to_le_bytes());
+        page.extend_from_slice(&sequence.to_le_bytes());
+        page.extend_from_slice(&0u32.to_le_bytes());
+        let mut lacing = vec![255; packet.len() / 255];
+        lacing.push((packet.len() % 255) as u8);
+        page.push(lacing.len() as u8);
+        page.extend_from_slice(&lacing);
+        page.extend_from_slice(packet);
+        let crc = ogg_crc(&page);
+        page[22..26].copy_from_slice(&crc.to_le_bytes());
+        page
+    }
+
+    /// An Opus stream with Vorbis comments and one silent packet.
+    fn write_opus(path: &Path, comments: &[&str]) {
+        let mut head = b"OpusHead".to_vec();
+        head.extend_from_slice(&[1, 2]);
+        head.extend_from_slice(&312u16.to_le_bytes());
+        head.extend_from_slice(&48_000u32.to_le_bytes());
+        head.extend_from_slice(&[0, 0, 0]);
+        let field = |text: &str| [&(text.len() as u32).to_le_bytes()[..], text.as_bytes()].concat();
+        let mut tags = b"OpusTags".to_vec();
+        tags.extend(field("tunez"));
+        tags.extend_from_slice(&(comments.len() as u32).to_le_bytes());
+        for comment in comments {
+            tags.extend(field(comment));
+        }
+        let mut contents = ogg_page(0, 2, 0, &head);
+        contents.extend(ogg_page(1, 0, 0, &tags));
+        contents.extend(ogg_page(2, 4, 48_312, &[0xFC]));
+        std::fs::write(path, contents).unwrap();
+    }
+
+    #[test]
+    fn opus_vorbis_comments_are_read() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("song.opus");
+        write_opus(
+            &path,
+            &["TITLE=Teardrop", "ARTIST=Massive Attack", "ALBUM=Mezzanine"],
+        );
+        let tags = TagReaders::default().read(&path);
+        assert_tags(&tags, "Teardrop", "Massive Attack", "Mezzanine");
+    }
+
+    #[test]
+    fn vorbis_chapters_are_read_in_order() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("mix.opus");
+        write_opus(
+            &path,
+            &[
+                "TITLE=Mix",
+                "CHAPTER002=00:04:30.500",
+                "CHAPTER002NAME=Second",
+                "CHAPTER001=00:00:00.000",
+                "CHAPTER001NAME=Opening",
+                "CHAPTER003=01:02:03.000",
+                "CHAPTER004=not a time",
+            ],
+        );
+        let chapters = read_chapters(&path);
+        let found: Vec<_> = chapters
+            .iter()
+            .map(|c| (c.start, c.title.as_deref()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (Duration::ZERO, Some("Opening")),
+                (Duration::from_millis(270_500), Some("Second")),
+                (Duration::from_secs(3723), None),
+            ]
+        );
+
+        let plain = dir.path().join("plain.opus");
+        write_opus(&plain, &["TITLE=Song"]);
+        assert!(read_chapters(&plain).is_empty());
+    }
+
+    #[test]
+    fn id3_chap_frames_give_their_start_and_title() {
+        let mut frame = b"ch1\0".to_vec();
+        frame.extend_from_slice(&90_000u32.to_be_bytes());
+        frame.extend_from_slice(&180_000u32.to_be_bytes());
+        frame.extend_from_slice(&[0xFF; 8]);
+        let title = b"\x03Verse";
+        frame.extend_from_slice(b"TIT2");
+        frame.extend_from_slice(&(title.len() as u32).to_be_bytes());
+        frame.extend_from_slice(&[0, 0]);
+        frame.extend_from_slice(title);
+
+        let chapter = parse_chap_frame(&frame).unwrap();
+        assert_eq!(chapter.start, Duration::from_secs(90));
+        assert_eq!(chapter.title.as_deref(), Some("Verse"));
+
+        // Without sub-frames the chapter is untitled; a cut-off frame is no chapter.
+        assert_eq!(parse_chap_frame(&frame[..20]).unwrap().title, None);
+        assert!(parse_chap_frame(&frame[..10]).is_none());
+    }
+
+    #[test]
+    fn wavpack_and_monkeys_audio_ape_tags_are_read() {
+        let dir = tempdir().unwrap();
+        for (name, header) in [("song.wv", &b"wvpk"[..]), ("song.ape", &b"MAC "[..])] {
+            let path = dir.path().join(name);
+            write_ape_tagged(&path, header);
+            let tags = TagReaders::default().read(&path);
+            assert_tags(&tags, "Lullaby", "The Cure", "Disintegration");
+        }
+    }
+
+    #[test]
+    fn misnamed_files_are_probed_by_contents() {
+        let dir = tempdir().unwrap();
+        // Opus in an `.ogg` isn't Vorbis, and `.opx` has no reader at all.
+        for name in ["song.ogg", "song.opx"] {
+            let path = dir.path().join(name);
+            write_opus(
+                &path,
+                &["TITLE=Angel", "ARTIST=Massive Attack", "ALBUM=Mezzanine"],
+            );
+            let tags = TagReaders::default().read(&path);
+            assert_tags(&tags, "Angel", "Massive Attack", "Mezzanine");
+        }
+    }
+
+    #[test]
+    fn unknown_files_come_back_unreadable() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("notes.xyz");
+        std::fs::write(&path, b"not audio").unwrap();
+        let tags = TagReaders::default().read(&path);
+        assert!(tags.unreadable);
+        assert!(tags.title.is_none());
+    }
+
+    /// Claims `.wv` files and names every one of them after itself.
+    struct FixedReader(&'static str);
+
+    impl TagReader for FixedReader {
+        fn extensions(&self) -> &[&str] {
+            &["wv"]
+        }
+
+        fn read(&self, _path: &Path) -> Option<ParsedTags> {
+            Some(ParsedTags {
+                title: Some(self.0.to_string()),
+                ..Default::default()
+            })
+        }
+    }
+
+    #[test]
+    fn registered_readers_take_over_their_extensions() {
+        let mut readers = TagReaders::default();
+        readers.register(Box::new(FixedReader("custom")));
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("song.wv");
+        write_ape_tagged(&path, b"wvpk");
+        assert_eq!(readers.read(&path).title.as_deref(), Some("custom"));
+
+        let mut only = TagReaders::empty();
+        only.register(Box::new(FixedReader("custom")));
+        let flac = dir.path().join("song.flac");
+        std::fs::write(&flac, b"not audio").unwrap();
+        assert!(only.read(&flac).unreadable);
+    }
+}
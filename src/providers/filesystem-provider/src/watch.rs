@@ -0,0 +1,93 @@
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::Path;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use tunez_core::provider::ProviderError;
+
+/// Quiet period after the last event before a rescan is triggered.
+const DEBOUNCE: Duration = Duration::from_millis(500);
+/// Upper bound on how long a continuous stream of events can defer a rescan.
+const MAX_DEBOUNCE: Duration = Duration::from_secs(5);
+
+/// A running filesystem watcher. Dropping it stops the watcher and joins its
+/// worker thread.
+pub(crate) struct WatchHandle {
+    watcher: Option<RecommendedWatcher>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl std::fmt::Debug for WatchHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WatchHandle").finish_non_exhaustive()
+    }
+}
+
+impl WatchHandle {
+    /// Watch `roots` recursively and call `on_change` once changes settle.
+    pub(crate) fn spawn<F>(roots: &[String], on_change: F) -> Result<Self, ProviderError>
+    where
+        F: Fn() + Send + 'static,
+    {
+        let (tx, rx) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+            match res {
+                // Reads (including our own tag parsing) must not trigger a rescan.
+                Ok(event) if matches!(event.kind, EventKind::Access(_)) => {}
+                Ok(_) => {
+                    let _ = tx.send(());
+                }
+                Err(err) => tracing::warn!(error = %err, "filesystem watch error"),
+            }
+        })
+        .map_err(watch_error)?;
+
+        for root in roots {
+            watcher
+                .watch(Path::new(root), RecursiveMode::Recursive)
+                .map_err(watch_error)?;
+        }
+
+        let thread = std::thread::Builder::new()
+            .name("tunez-fs-watch".into())
+            .spawn(move || {
+                // The channel disconnects when the watcher is dropped.
+                while rx.recv().is_ok() {
+                    let started = Instant::now();
+                    loop {
+                        match rx.recv_timeout(DEBOUNCE) {
+                            Ok(()) if started.elapsed() < MAX_DEBOUNCE => continue,
+                            Ok(()) | Err(RecvTimeoutError::Timeout) => break,
+                            Err(RecvTimeoutError::Disconnected) => return,
+                        }
+                    }
+                    on_change();
+                }
+            })
+            .map_err(|err| ProviderError::Other {
+                message: format!("failed to start filesystem watcher: {err}"),
+            })?;
+
+        Ok(Self {
+            watcher: Some(watcher),
+            thread: Some(thread),
+        })
+    }
+}
+
+impl Drop for WatchHandle {
+    fn drop(&mut self) {
+        drop(self.watcher.take());
+        if let Some(thread) = self.thread.take() {
+            if thread.join().is_err() {
+                tracing::warn!("filesystem watcher thread panicked");
+            }
+        }
+    }
+}
+
+fn watch_error(err: notify::Error) -> ProviderError {
+    ProviderError::Other {
+        message: format!("failed to watch library: {err}"),
+    }
+}
@@ -0,0 +1,158 @@
+//! Access-token handling shared by the Melodee provider and scrobbler.
+
+use crate::models::{AuthenticationResponse, RefreshTokenRequest};
+use reqwest::Client;
+use std::sync::RwLock;
+use tunez_core::provider::{ProviderError, ProviderResult};
+use tunez_core::redact_error;
+use tunez_core::secrets::CredentialStore;
+
+const PROVIDER_ID: &str = "melodee";
+
+/// Holds the current access token and refreshes it when the server rejects it.
+///
+/// Tokens are cached in memory to avoid hitting the keyring on every request.
+pub struct MelodeeAuth {
+    client: Client,
+    base_url: String,
+    profile: Option<String>,
+    creds: CredentialStore,
+    access_token: RwLock<Option<String>>,
+    refresh_token: RwLock<Option<String>>,
+    /// Held for the duration of a refresh so concurrent 401s share one call.
+    refreshing: tokio::sync::Mutex<()>,
+}
+
+impl MelodeeAuth {
+    pub fn new(client: Client, base_url: impl Into<String>, profile: Option<String>) -> Self {
+        Self {
+            client,
+            base_url: base_url.into(),
+            profile,
+            creds: CredentialStore::new(),
+            access_token: RwLock::new(None),
+            refresh_token: RwLock::new(None),
+            refreshing: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Send refresh requests with `client`.
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Use `creds` instead of the default keyring-backed store.
+    pub fn with_credential_store(mut self, creds: CredentialStore) -> Self {
+        self.creds = creds;
+        self
+    }
+
+    /// Seed the in-memory tokens instead of reading them from the keyring.
+    pub fn with_tokens(self, access_token: Option<String>, refresh_token: Option<String>) -> Self {
+        *self.access_token.write().expect("token lock poisoned") = access_token;
+        *self.refresh_token.write().expect("token lock poisoned") = refresh_token;
+        self
+    }
+
+    /// The current access token, loaded from the keyring on first use.
+    pub fn access_token(&self) -> Option<String> {
+        if let Some(token) = self
+            .access_token
+            .read()
+            .expect("token lock poisoned")
+            .clone()
+        {
+            return Some(token);
+        }
+        let token = self
+            .creds
+            .get_access_token(PROVIDER_ID, self.profile.as_deref())
+            .ok()?;
+        *self.access_token.write().expect("token lock poisoned") = Some(token.clone());
+        Some(token)
+    }
+
+    fn refresh_token(&self) -> Option<String> {
+        if let Some(token) = self
+            .refresh_token
+            .read()
+            .expect("token lock poisoned")
+            .clone()
+        {
+            return Some(token);
+        }
+        self.creds
+            .get_refresh_token(PROVIDER_ID, self.profile.as_deref())
+            .ok()
+    }
+
+    /// Exchange the refresh token for a new access token after `stale` was
+    /// rejected.
+    ///
+    /// If another caller already replaced `stale` while we waited, the new
+    /// token is returned without contacting the server again.
+    pub async fn refresh(&self, stale: Option<&str>) -> ProviderResult<String> {
+        let _guard = self.refreshing.lock().await;
+        if let Some(current) = self.access_token() {
+            if Some(current.as_str()) != stale {
+                return Ok(current);
+            }
+        }
+
+        let refresh_token = self
+            .refresh_token()
+            .ok_or_else(|| unauthorized("no refresh token"))?;
+        let url = format!(
+            "{}/api/v1/auth/refresh-token",
+            self.base_url.trim_end_matches('/')
+        );
+        let response = self
+            .client
+            .post(url)
+            .json(&RefreshTokenRequest {
+                refresh_token: refresh_token.clone(),
+            })
+            .send()
+            .await
+            .map_err(|e| ProviderError::NetworkError {
+                message: redact_error(e),
+            })?;
+        if !response.status().is_success() {
+            return Err(unauthorized(&format!(
+                "token refresh failed with {}",
+                response.status()
+            )));
+        }
+        let body: AuthenticationResponse = response
+            .json()
+            .await
+            .map_err(|_| unauthorized("invalid token refresh response"))?;
+
+        let profile = self.profile.as_deref();
+        if let Err(err) = self
+            .creds
+            .store_access_token(PROVIDER_ID, profile, &body.token)
+        {
+            tracing::warn!(error = %err.user_message(), "failed to persist refreshed access token");
+        }
+        *self.access_token.write().expect("token lock poisoned") = Some(body.token.clone());
+        if let Some(rotated) = body.refresh_token.filter(|t| *t != refresh_token) {
+            if let Err(err) = self
+                .creds
+                .store_refresh_token(PROVIDER_ID, profile, &rotated)
+            {
+                tracing::warn!(error = %err.user_message(), "failed to persist refresh token");
+            }
+            *self.refresh_token.write().expect("token lock poisoned") = Some(rotated);
+        }
+        tracing::debug!("refreshed melodee access token");
+        Ok(body.token)
+    }
+}
+
+fn unauthorized(message: &str) -> ProviderError {
+    ProviderError::AuthenticationError {
+        message: message.to_string(),
+    }
+}
@@ -1,27 +1,81 @@
+mod auth;
 mod mapping;
 pub mod models;
 
+pub use auth::MelodeeAuth;
+
 use mapping::{map_album, map_playlist, map_track};
-use reqwest::blocking::{Client, Response};
-use reqwest::StatusCode;
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
 use serde::de::DeserializeOwned;
-use std::sync::{Arc, RwLock};
+use std::future::Future;
+use std::sync::{mpsc, Arc, OnceLock, RwLock};
 use std::time::Duration;
+use tokio::runtime::{Handle, Runtime};
+use tunez_core::lyrics::Lyrics;
 use tunez_core::models::{
-    Album, AlbumId, Page, PageRequest, Playlist, PlaylistId, StreamUrl, Track, TrackId,
+    Album, AlbumId, Page, PageCursor, PageRequest, Playlist, PlaylistId, StreamUrl, Track, TrackId,
 };
 use tunez_core::provider::{
     BrowseKind, CollectionItem, Provider, ProviderCapabilities, ProviderError, ProviderResult,
     TrackSearchFilters,
 };
+use tunez_core::retry::{is_transient_status, parse_retry_after, RetryError, RetryPolicy};
+use tunez_core::{fuzzy_score, redact_error, ElapsedGuard};
 use url::Url;
 
-use tunez_core::secrets::CredentialStore;
+/// Health checks give up sooner than regular requests.
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+/// Artists fetched when looking one up by name; the exact match is usually
+/// first, but others may share a prefix.
+const ARTIST_LOOKUP_LIMIT: u32 = 20;
+/// Longest `Retry-After` waited out before retrying; a longer one is
+/// returned as [`ProviderError::RateLimited`] straight away.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(10);
+
+/// How long a request may take, from connecting to reading the body, unless
+/// configured otherwise.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(20);
+/// How long connecting to the server may take unless configured otherwise.
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Runtime shared by every Melodee provider for driving HTTP requests.
+///
+/// Requests are spawned onto it and awaited over a channel, so the synchronous
+/// `Provider` methods work both from plain threads and from inside another
+/// tokio runtime without nesting one runtime in another.
+fn http_runtime() -> Handle {
+    static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+    RUNTIME
+        .get_or_init(|| {
+            tokio::runtime::Builder::new_multi_thread()
+                .worker_threads(2)
+                .thread_name("melodee-http")
+                .enable_all()
+                .build()
+                .expect("failed to start melodee http runtime")
+        })
+        .handle()
+        .clone()
+}
 
 #[derive(Clone)]
 pub struct MelodeeConfig {
     pub base_url: String,
     pub profile: Option<String>,
+    pub timeout: Duration,
+    pub connect_timeout: Duration,
+}
+
+impl MelodeeConfig {
+    /// Config for the server at `base_url` with the default timeouts.
+    pub fn new(base_url: impl Into<String>, profile: Option<String>) -> Self {
+        Self {
+            base_url: base_url.into(),
+            profile,
+            timeout: DEFAULT_TIMEOUT,
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -29,11 +83,12 @@ pub struct MelodeeProvider {
     id: String,
     name: String,
     client: Client,
+    runtime: Handle,
+    retry: RetryPolicy,
     base_url: Url,
-    profile: Option<String>,
-    creds: CredentialStore,
-    // Cache the token in memory to avoid hitting keyring on every request
-    access_token: Arc<RwLock<Option<String>>>,
+    auth: Arc<MelodeeAuth>,
+    /// The signed-in user's id, fetched once for building stream URLs.
+    user_id: Arc<RwLock<Option<String>>>,
 }
 
 impl MelodeeProvider {
@@ -42,45 +97,44 @@ impl MelodeeProvider {
             message: format!("invalid base_url: {e}"),
         })?;
         let client = Client::builder()
-            .connect_timeout(Duration::from_secs(10))
-            .timeout(Duration::from_secs(20))
+            .connect_timeout(config.connect_timeout)
+            .timeout(config.timeout)
             .build()
             .map_err(|e| ProviderError::Other {
-                message: e.to_string(),
+                message: redact_error(e),
             })?;
 
+        let auth = MelodeeAuth::new(client.clone(), base_url.as_str(), config.profile);
         Ok(Self {
             id: "melodee".into(),
             name: "Melodee".into(),
             client,
+            runtime: http_runtime(),
+            retry: RetryPolicy::default(),
             base_url,
-            profile: config.profile,
-            creds: CredentialStore::new(),
-            access_token: Arc::new(RwLock::new(None)),
+            auth: Arc::new(auth),
+            user_id: Arc::default(),
         })
     }
 
-    fn auth_header(&self) -> Option<String> {
-        // First check memory cache
-        if let Ok(guard) = self.access_token.read() {
-            if let Some(token) = guard.as_ref() {
-                return Some(token.clone());
-            }
-        }
+    /// Replace the policy used to retry transient network and server errors.
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
 
-        // Then try keyring
-        if let Ok(token) = self
-            .creds
-            .get_access_token(&self.id, self.profile.as_deref())
-        {
-            // Cache it
-            if let Ok(mut guard) = self.access_token.write() {
-                *guard = Some(token.clone());
-            }
-            return Some(token);
-        }
+    /// Replace how access tokens are obtained and refreshed. Pass an `Arc`
+    /// to share one set of tokens with the scrobbler.
+    pub fn with_auth(mut self, auth: impl Into<Arc<MelodeeAuth>>) -> Self {
+        self.auth = auth.into();
+        self.user_id = Arc::default();
+        self
+    }
 
-        None
+    /// The tokens this provider signs in with, for a scrobbler to share so a
+    /// refresh by either is seen by both.
+    pub fn auth(&self) -> Arc<MelodeeAuth> {
+        Arc::clone(&self.auth)
     }
 
     fn capabilities() -> ProviderCapabilities {
@@ -88,41 +142,205 @@ impl MelodeeProvider {
             playlists: true,
             lyrics: true,
             artwork: true,
-            favorites: false,
-            recently_played: false,
+            favorites: true,
+            recently_played: true,
             offline_download: false,
         }
     }
 
-    fn paging_query(&self, paging: PageRequest) -> Vec<(&str, String)> {
+    fn paging_query(&self, paging: PageRequest) -> Vec<(&'static str, String)> {
         vec![
-            ("page", (paging.offset / paging.limit).to_string()),
-            ("pageSize", paging.limit.to_string()),
+            ("page", paging.page_number().to_string()),
+            ("pageSize", paging.normalized().limit.to_string()),
         ]
     }
 
-    fn send_get<T: DeserializeOwned>(
+    /// [`paging_query`](Self::paging_query) for endpoints that call the page
+    /// size `limit`.
+    fn limit_query(&self, paging: PageRequest) -> Vec<(&'static str, String)> {
+        vec![
+            ("page", paging.page_number().to_string()),
+            ("limit", paging.normalized().limit.to_string()),
+        ]
+    }
+
+    /// Build a page, pointing `next` past it when the server filled the whole
+    /// page (a short page is the last one).
+    fn page<T>(paging: PageRequest, items: Vec<T>) -> Page<T> {
+        let paging = paging.normalized();
+        let next =
+            (items.len() >= paging.limit as usize).then(|| PageCursor::from_offset(paging.end()));
+        Page { items, next }
+    }
+
+    /// Run `fut` on the shared runtime and block the calling thread until it
+    /// completes.
+    fn block_on<T: Send + 'static>(
+        &self,
+        fut: impl Future<Output = ProviderResult<T>> + Send + 'static,
+    ) -> ProviderResult<T> {
+        let (tx, rx) = mpsc::channel();
+        self.runtime.spawn(async move {
+            let _ = tx.send(fut.await);
+        });
+        rx.recv().unwrap_or_else(|_| {
+            Err(ProviderError::Other {
+                message: "melodee request was cancelled".into(),
+            })
+        })
+    }
+
+    fn send_get<T: DeserializeOwned + Send + 'static>(
         &self,
         path: &str,
         query: Vec<(&str, String)>,
         not_found_entity: Option<String>,
     ) -> ProviderResult<T> {
-        let url = self.base_url.join(path).map_err(|e| ProviderError::Other {
-            message: e.to_string(),
-        })?;
-        let mut request = self.client.get(url.clone()).query(&query);
-        if let Some(token) = self.auth_header() {
-            request = request.bearer_auth(token);
+        let request = self.client.get(self.url(path)?).query(&query);
+        self.send(request, path, not_found_entity, |response| async move {
+            response
+                .json::<T>()
+                .await
+                .map_err(|e| ProviderError::Other {
+                    message: redact_error(e),
+                })
+        })
+    }
+
+    /// POST to `path` with no body, ignoring whatever the server returns.
+    fn send_post(&self, path: &str, not_found_entity: Option<String>) -> ProviderResult<()> {
+        let request = self.client.post(self.url(path)?);
+        self.send(request, path, not_found_entity, |_| async { Ok(()) })
+    }
+
+    /// `song/stream/{apiKey}/{userApiKey}/{authToken}`, for songs the server
+    /// gives no usable `streamUrl`. The token has to be in the path since the
+    /// audio engine sends no headers; log redaction knows that segment.
+    fn song_stream_url(&self, song_id: &str) -> ProviderResult<StreamUrl> {
+        let user_id = self.user_id()?;
+        let token = self
+            .auth
+            .access_token()
+            .ok_or_else(|| ProviderError::AuthenticationError {
+                message: "not signed in".into(),
+            })?;
+        let mut url = self.url("song/stream")?;
+        url.path_segments_mut()
+            .map_err(|()| ProviderError::Other {
+                message: "base_url can't have a path".into(),
+            })?
+            .extend([song_id, user_id.as_str(), token.as_str()]);
+        Ok(StreamUrl::new(url.to_string()))
+    }
+
+    /// The signed-in user's id, asking the server only the first time.
+    fn user_id(&self) -> ProviderResult<String> {
+        if let Some(id) = self.user_id.read().expect("user id lock poisoned").clone() {
+            return Ok(id);
         }
-        let response = request.send().map_err(|e| ProviderError::NetworkError {
-            message: e.to_string(),
-        })?;
-        let response = Self::map_response(response, path, not_found_entity)?;
-        response.json::<T>().map_err(|e| ProviderError::Other {
+        let user: models::CurrentUser = self.send_get("api/v1/user/me", Vec::new(), None)?;
+        *self.user_id.write().expect("user id lock poisoned") = Some(user.id.clone());
+        Ok(user.id)
+    }
+
+    fn url(&self, path: &str) -> ProviderResult<Url> {
+        self.base_url.join(path).map_err(|e| ProviderError::Other {
             message: e.to_string(),
         })
     }
 
+    /// Send `request` with the access token, refreshing it once if the server
+    /// rejects it, and hand the successful response to `read`.
+    fn send<T, F, Fut>(
+        &self,
+        request: RequestBuilder,
+        path: &str,
+        not_found_entity: Option<String>,
+        read: F,
+    ) -> ProviderResult<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(Response) -> Fut + Send + 'static,
+        Fut: Future<Output = ProviderResult<T>> + Send,
+    {
+        let path = path.to_string();
+        let retry = self.retry;
+        let auth = self.auth.clone();
+        self.block_on(async move {
+            let token = auth.access_token();
+            let result =
+                Self::execute(&retry, &request, &path, &not_found_entity, token.as_deref()).await;
+            let response = match result {
+                // The token may have expired: refresh it and try once more.
+                Err(ProviderError::AuthenticationError { .. }) => {
+                    let fresh = auth.refresh(token.as_deref()).await?;
+                    Self::execute(&retry, &request, &path, &not_found_entity, Some(&fresh)).await?
+                }
+                other => other?,
+            };
+            read(response).await
+        })
+    }
+
+    async fn execute(
+        retry: &RetryPolicy,
+        request: &RequestBuilder,
+        path: &str,
+        not_found_entity: &Option<String>,
+        token: Option<&str>,
+    ) -> ProviderResult<Response> {
+        retry
+            .run(|| {
+                let attempt = request.try_clone();
+                async move {
+                    let mut request = attempt.ok_or_else(|| {
+                        RetryError::Permanent(ProviderError::Other {
+                            message: "request cannot be retried".into(),
+                        })
+                    })?;
+                    if let Some(token) = token {
+                        request = request.bearer_auth(token);
+                    }
+                    let response = request.send().await.map_err(|e| {
+                        let error = ProviderError::NetworkError {
+                            message: redact_error(&e),
+                        };
+                        if e.is_connect() || e.is_timeout() {
+                            RetryError::transient(error)
+                        } else {
+                            RetryError::Permanent(error)
+                        }
+                    })?;
+                    let status = response.status();
+                    if is_transient_status(status.as_u16()) {
+                        let retry_after = response
+                            .headers()
+                            .get(reqwest::header::RETRY_AFTER)
+                            .and_then(|v| v.to_str().ok())
+                            .and_then(parse_retry_after);
+                        if status != StatusCode::TOO_MANY_REQUESTS {
+                            return Err(RetryError::Transient {
+                                error: ProviderError::NetworkError {
+                                    message: format!("http {} from {}", status, path),
+                                },
+                                retry_after,
+                            });
+                        }
+                        let error = ProviderError::RateLimited { retry_after };
+                        // Blocking a caller for longer than this is worse
+                        // than letting it back off on its own.
+                        if retry_after.is_some_and(|delay| delay > MAX_RETRY_AFTER) {
+                            return Err(RetryError::Permanent(error));
+                        }
+                        return Err(RetryError::Transient { error, retry_after });
+                    }
+                    Self::map_response(response, path, not_found_entity.clone())
+                        .map_err(RetryError::Permanent)
+                }
+            })
+            .await
+    }
+
     fn map_response(
         response: Response,
         path: &str,
@@ -132,6 +350,9 @@ impl MelodeeProvider {
             StatusCode::UNAUTHORIZED => Err(ProviderError::AuthenticationError {
                 message: "unauthorized".into(),
             }),
+            StatusCode::FORBIDDEN => Err(ProviderError::AuthenticationError {
+                message: "forbidden".into(),
+            }),
             StatusCode::NOT_FOUND => Err(ProviderError::NotFound {
                 entity: not_found_entity.unwrap_or_else(|| path.to_string()),
             }),
@@ -166,17 +387,20 @@ impl Provider for MelodeeProvider {
         Self::capabilities()
     }
 
+    #[tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(query_len = query.len(), elapsed_ms = tracing::field::Empty)
+    )]
     fn search_tracks(
         &self,
         query: &str,
         filters: TrackSearchFilters,
         paging: PageRequest,
     ) -> ProviderResult<Page<Track>> {
-        let mut query_params = vec![
-            ("q", query.to_string()),
-            ("page", (paging.offset / paging.limit).to_string()),
-            ("pageSize", paging.limit.to_string()),
-        ];
+        let _elapsed = ElapsedGuard::start();
+        let mut query_params = vec![("q", query.to_string())];
+        query_params.extend(self.paging_query(paging));
         if let Some(artist) = filters.artist {
             query_params.push(("filterByArtistApiKey", artist));
         }
@@ -187,7 +411,7 @@ impl Provider for MelodeeProvider {
             .into_iter()
             .map(|song| map_track(&song, &self.id))
             .collect();
-        Ok(Page { items, next: None })
+        Ok(Self::page(paging, items))
     }
 
     fn browse(
@@ -207,58 +431,75 @@ impl Provider for MelodeeProvider {
                     .into_iter()
                     .map(|a| CollectionItem::Album(map_album(&a, &self.id)))
                     .collect();
-                Ok(Page { items, next: None })
+                Ok(Self::page(paging, items))
             }
             BrowseKind::Playlists => {
-                let body: models::PlaylistPagedResponse = self.send_get(
-                    "api/v1/user/playlists",
-                    vec![
-                        ("page", (paging.offset / paging.limit).to_string()),
-                        ("limit", paging.limit.to_string()),
-                    ],
-                    None,
-                )?;
+                let body: models::PlaylistPagedResponse =
+                    self.send_get("api/v1/user/playlists", self.limit_query(paging), None)?;
                 let items = body
                     .data
                     .into_iter()
                     .map(|p| CollectionItem::Playlist(map_playlist(&p, &self.id)))
                     .collect();
-                Ok(Page { items, next: None })
+                Ok(Self::page(paging, items))
             }
         }
     }
 
-    fn list_playlists(&self, paging: PageRequest) -> ProviderResult<Page<Playlist>> {
-        let body: models::PlaylistPagedResponse = self.send_get(
-            "api/v1/user/playlists",
+    /// Looks the artist up by name, then lists their albums.
+    fn list_artist_albums(&self, artist: &str, paging: PageRequest) -> ProviderResult<Page<Album>> {
+        let found: models::ArtistPagedResponse = self.send_get(
+            "api/v1/artists",
             vec![
-                ("page", (paging.offset / paging.limit).to_string()),
-                ("limit", paging.limit.to_string()),
+                ("q", artist.to_string()),
+                ("page", "0".to_string()),
+                ("pageSize", ARTIST_LOOKUP_LIMIT.to_string()),
             ],
             None,
         )?;
+        let wanted = artist.to_lowercase();
+        let Some(artist_id) = found
+            .data
+            .into_iter()
+            .find(|a| a.name.to_lowercase() == wanted)
+            .map(|a| a.id)
+        else {
+            return Ok(Page::single_page(Vec::new()));
+        };
+        let body: models::AlbumPagedResponse = self.send_get(
+            &format!("api/v1/artists/{artist_id}/albums"),
+            self.paging_query(paging),
+            Some(artist.to_string()),
+        )?;
+        let items = body.data.iter().map(|a| map_album(a, &self.id)).collect();
+        Ok(Self::page(paging, items))
+    }
+
+    fn list_playlists(&self, paging: PageRequest) -> ProviderResult<Page<Playlist>> {
+        let body: models::PlaylistPagedResponse =
+            self.send_get("api/v1/user/playlists", self.limit_query(paging), None)?;
         let items = body
             .data
             .into_iter()
             .map(|p| map_playlist(&p, &self.id))
             .collect();
-        Ok(Page { items, next: None })
+        Ok(Self::page(paging, items))
     }
 
     fn search_playlists(&self, query: &str, paging: PageRequest) -> ProviderResult<Page<Playlist>> {
         let page = self.list_playlists(paging)?;
-        let filtered = page
+        let mut matches: Vec<(u32, Playlist)> = page
             .items
             .into_iter()
-            .filter(|p| {
-                p.name
-                    .to_ascii_lowercase()
-                    .contains(&query.to_ascii_lowercase())
-            })
+            .filter_map(|p| fuzzy_score(query, &p.name).map(|score| (score, p)))
             .collect();
+        // Best matches first within the page; the sort is stable.
+        matches.sort_by(|(a, _), (b, _)| b.cmp(a));
+        let filtered = matches.into_iter().map(|(_, p)| p).collect();
+        // Filtering is local, so paging continues through the full playlist list.
         Ok(Page {
             items: filtered,
-            next: None,
+            next: page.next,
         })
     }
 
@@ -286,7 +527,31 @@ impl Provider for MelodeeProvider {
             .into_iter()
             .map(|s| map_track(&s, &self.id))
             .collect();
-        Ok(Page { items, next: None })
+        Ok(Self::page(paging, items))
+    }
+
+    /// One request when the songs endpoint includes the playlist; older
+    /// servers that leave it out cost a second request for it.
+    fn get_playlist_with_tracks(
+        &self,
+        playlist_id: &PlaylistId,
+        paging: PageRequest,
+    ) -> ProviderResult<(Playlist, Page<Track>)> {
+        let body: models::PlaylistSongsResponse = self.send_get(
+            &format!("api/v1/playlists/{}/songs", playlist_id.0),
+            self.paging_query(paging),
+            Some(playlist_id.0.clone()),
+        )?;
+        let playlist = match &body.playlist {
+            Some(playlist) => map_playlist(playlist, &self.id),
+            None => self.get_playlist(playlist_id)?,
+        };
+        let items = body
+            .data
+            .into_iter()
+            .map(|s| map_track(&s, &self.id))
+            .collect();
+        Ok((playlist, Self::page(paging, items)))
     }
 
     fn get_album(&self, album_id: &AlbumId) -> ProviderResult<Album> {
@@ -313,7 +578,7 @@ impl Provider for MelodeeProvider {
             .into_iter()
             .map(|s| map_track(&s, &self.id))
             .collect();
-        Ok(Page { items, next: None })
+        Ok(Self::page(paging, items))
     }
 
     fn get_track(&self, track_id: &TrackId) -> ProviderResult<Track> {
@@ -321,7 +586,13 @@ impl Provider for MelodeeProvider {
         Ok(map_track(&song, &self.id))
     }
 
+    #[tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(track_id = %track_id.0, elapsed_ms = tracing::field::Empty)
+    )]
     fn get_stream_url(&self, track_id: &TrackId) -> ProviderResult<StreamUrl> {
+        let _elapsed = ElapsedGuard::start();
         let song = self.fetch_song(track_id)?;
         if song.id != track_id.0 {
             return Err(ProviderError::Other {
@@ -332,10 +603,11 @@ impl Provider for MelodeeProvider {
             .stream_url
             .as_deref()
             .map(str::trim)
-            .filter(|s| !s.is_empty())
-            .ok_or_else(|| ProviderError::Other {
-                message: format!("missing stream url for track {}", track_id.0),
-            })?;
+            // A bare id is no use to the audio engine; build the URL instead.
+            .filter(|s| s.contains('/'));
+        let Some(raw_url) = raw_url else {
+            return self.song_stream_url(&song.id);
+        };
         let resolved = Url::parse(raw_url)
             .or_else(|_| self.base_url.join(raw_url))
             .map_err(|e| ProviderError::Other {
@@ -344,13 +616,68 @@ impl Provider for MelodeeProvider {
         Ok(StreamUrl::new(resolved.to_string()))
     }
 
-    fn get_lyrics(&self, track_id: &TrackId) -> ProviderResult<String> {
+    fn get_lyrics(&self, track_id: &TrackId) -> ProviderResult<Lyrics> {
         let lyrics: models::Lyrics = self.send_get(
             &format!("api/v1/songs/{}/lyrics", track_id.0),
             Vec::new(),
             Some(track_id.0.clone()),
         )?;
-        Ok(lyrics.plain_text)
+        Ok(Lyrics::from_text(&lyrics.plain_text))
+    }
+
+    fn set_favorite(&self, track_id: &TrackId, favorite: bool) -> ProviderResult<()> {
+        self.send_post(
+            &format!("api/v1/songs/starred/{}/{}", track_id.0, favorite),
+            Some(track_id.0.clone()),
+        )
+    }
+
+    fn list_favorites(&self, paging: PageRequest) -> ProviderResult<Page<Track>> {
+        let body: models::SongPagedResponse =
+            self.send_get("api/v1/user/songs/liked", self.limit_query(paging), None)?;
+        let items = body
+            .data
+            .into_iter()
+            .map(|s| map_track(&s, &self.id))
+            .collect();
+        Ok(Self::page(paging, items))
+    }
+
+    fn list_recently_played(&self, paging: PageRequest) -> ProviderResult<Page<Track>> {
+        let body: models::SongPagedResponse = self.send_get(
+            "api/v1/user/songs/recently-played",
+            self.limit_query(paging),
+            None,
+        )?;
+        let items = body
+            .data
+            .into_iter()
+            .map(|s| map_track(&s, &self.id))
+            .collect();
+        Ok(Self::page(paging, items))
+    }
+
+    /// A single HEAD request to the server root, without retries. Any answer
+    /// short of a server error counts as reachable.
+    fn health_check(&self) -> ProviderResult<()> {
+        let request = self
+            .client
+            .head(self.base_url.clone())
+            .timeout(HEALTH_CHECK_TIMEOUT);
+        self.block_on(async move {
+            let response = request
+                .send()
+                .await
+                .map_err(|e| ProviderError::NetworkError {
+                    message: redact_error(&e),
+                })?;
+            if response.status().is_server_error() {
+                return Err(ProviderError::NetworkError {
+                    message: format!("http {} from health check", response.status()),
+                });
+            }
+            Ok(())
+        })
     }
 }
 
@@ -358,10 +685,12 @@ impl Provider for MelodeeProvider {
 mod tests {
     use super::*;
     use serde_json::json;
+    use tunez_core::models::DEFAULT_PAGE_LIMIT;
     use tunez_core::provider_contract::{
         run_provider_contract, PlaylistExpectation, ProviderContractExpectations, SearchExpectation,
     };
-    use wiremock::matchers::{method, path};
+    use tunez_core::secrets::CredentialStore;
+    use wiremock::matchers::{body_json, header, method, path, query_param};
     use wiremock::{Mock, MockServer, ResponseTemplate};
 
     #[test]
@@ -420,11 +749,60 @@ mod tests {
                 .mount(&server),
         );
 
-        let provider = MelodeeProvider::new(MelodeeConfig {
-            base_url,
-            profile: None,
-        })
-        .expect("provider constructed");
+        rt.block_on(
+            Mock::given(method("GET"))
+                .and(path("/api/v1/playlists/playlist-1"))
+                .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                    "apiKey": "playlist-1",
+                    "name": "Morning Mix",
+                    "description": "Desc",
+                    "songsCount": 1
+                })))
+                .mount(&server),
+        );
+
+        rt.block_on(
+            Mock::given(method("GET"))
+                .and(path("/api/v1/playlists/playlist-1/songs"))
+                .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                    "data": [song("song-1")],
+                    "meta": { "totalCount": 1, "pageSize": 25, "currentPage": 1 }
+                })))
+                .mount(&server),
+        );
+
+        rt.block_on(
+            Mock::given(method("GET"))
+                .and(path("/api/v1/user/songs/liked"))
+                .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                    "data": [],
+                    "meta": { "totalCount": 0, "pageSize": 25, "currentPage": 0 }
+                })))
+                .mount(&server),
+        );
+
+        rt.block_on(
+            Mock::given(method("GET"))
+                .and(path("/api/v1/user/songs/recently-played"))
+                .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                    "data": [],
+                    "meta": { "totalCount": 0, "pageSize": 25, "currentPage": 0 }
+                })))
+                .expect(1)
+                .mount(&server),
+        );
+
+        // Only a track that doesn't exist is touched.
+        rt.block_on(
+            Mock::given(method("POST"))
+                .and(path("/api/v1/songs/starred/tunez-contract-probe/false"))
+                .respond_with(ResponseTemplate::new(404))
+                .expect(1)
+                .mount(&server),
+        );
+
+        let provider =
+            MelodeeProvider::new(MelodeeConfig::new(base_url, None)).expect("provider constructed");
 
         let track_id = TrackId::new("song-1");
         let expectations = ProviderContractExpectations {
@@ -443,4 +821,683 @@ mod tests {
 
         run_provider_contract(&provider, &expectations).unwrap();
     }
+
+    fn song(id: &str) -> serde_json::Value {
+        json!({
+            "id": id,
+            "title": format!("Song {id}"),
+            "durationMs": 180000,
+            "artist": { "id": "artist-1", "name": "Artist" },
+            "album": { "id": "album-1", "name": "Album" }
+        })
+    }
+
+    #[test]
+    fn playlist_with_tracks_is_one_request_when_the_server_includes_it() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let server = rt.block_on(MockServer::start());
+        let playlist = json!({ "apiKey": "pl-1", "name": "Road Trip", "songsCount": 2 });
+        rt.block_on(
+            Mock::given(method("GET"))
+                .and(path("/api/v1/playlists/pl-1/songs"))
+                .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                    "playlist": playlist,
+                    "data": [song("song-1"), song("song-2")],
+                    "meta": { "totalCount": 2, "pageSize": 25, "currentPage": 1 }
+                })))
+                .expect(1)
+                .mount(&server),
+        );
+        rt.block_on(
+            Mock::given(method("GET"))
+                .and(path("/api/v1/playlists/pl-1"))
+                .respond_with(ResponseTemplate::new(200).set_body_json(playlist))
+                .expect(0)
+                .mount(&server),
+        );
+        // Without the playlist in the songs response it is fetched on its own.
+        rt.block_on(
+            Mock::given(method("GET"))
+                .and(path("/api/v1/playlists/old/songs"))
+                .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                    "data": [song("song-3")],
+                    "meta": { "totalCount": 1, "pageSize": 25, "currentPage": 1 }
+                })))
+                .mount(&server),
+        );
+        rt.block_on(
+            Mock::given(method("GET"))
+                .and(path("/api/v1/playlists/old"))
+                .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                    "apiKey": "old",
+                    "name": "Old Server",
+                })))
+                .expect(1)
+                .mount(&server),
+        );
+        let provider =
+            MelodeeProvider::new(MelodeeConfig::new(format!("{}/", server.uri()), None)).unwrap();
+
+        let (playlist, tracks) = provider
+            .get_playlist_with_tracks(&PlaylistId::new("pl-1"), PageRequest::first_page(25))
+            .unwrap();
+        assert_eq!(playlist.name, "Road Trip");
+        assert_eq!(playlist.track_count, Some(2));
+        let ids: Vec<_> = tracks.items.iter().map(|t| t.id.0.as_str()).collect();
+        assert_eq!(ids, ["song-1", "song-2"]);
+
+        let (playlist, tracks) = provider
+            .get_playlist_with_tracks(&PlaylistId::new("old"), PageRequest::first_page(25))
+            .unwrap();
+        assert_eq!(playlist.name, "Old Server");
+        assert_eq!(tracks.items.len(), 1);
+        rt.block_on(server.verify());
+    }
+
+    #[test]
+    fn set_favorite_stars_and_unstars_song() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let server = rt.block_on(MockServer::start());
+        for starred in ["true", "false"] {
+            rt.block_on(
+                Mock::given(method("POST"))
+                    .and(path(format!("/api/v1/songs/starred/song-1/{starred}")))
+                    .respond_with(ResponseTemplate::new(200))
+                    .expect(1)
+                    .mount(&server),
+            );
+        }
+        rt.block_on(
+            Mock::given(method("POST"))
+                .and(path("/api/v1/songs/starred/missing/true"))
+                .respond_with(ResponseTemplate::new(404))
+                .mount(&server),
+        );
+        let provider =
+            MelodeeProvider::new(MelodeeConfig::new(format!("{}/", server.uri()), None)).unwrap();
+
+        provider
+            .set_favorite(&TrackId::new("song-1"), true)
+            .unwrap();
+        provider
+            .set_favorite(&TrackId::new("song-1"), false)
+            .unwrap();
+        assert!(matches!(
+            provider.set_favorite(&TrackId::new("missing"), true),
+            Err(ProviderError::NotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn list_favorites_maps_liked_songs() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let server = rt.block_on(MockServer::start());
+        rt.block_on(
+            Mock::given(method("GET"))
+                .and(path("/api/v1/user/songs/liked"))
+                .and(query_param("page", "0"))
+                .and(query_param("limit", "2"))
+                .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                    "data": [song("song-1"), song("song-2")],
+                    "meta": { "totalCount": 3, "pageSize": 2, "currentPage": 0 }
+                })))
+                .mount(&server),
+        );
+        let provider =
+            MelodeeProvider::new(MelodeeConfig::new(format!("{}/", server.uri()), None)).unwrap();
+
+        let page = provider.list_favorites(PageRequest::first_page(2)).unwrap();
+        let ids: Vec<_> = page.items.iter().map(|t| t.id.0.as_str()).collect();
+        assert_eq!(ids, ["song-1", "song-2"]);
+        assert_eq!(page.items[0].provider_id, "melodee");
+        assert!(page.next.is_some());
+    }
+
+    #[test]
+    fn artist_albums_are_listed_by_the_artist_matching_the_name() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let server = rt.block_on(MockServer::start());
+        rt.block_on(
+            Mock::given(method("GET"))
+                .and(path("/api/v1/artists"))
+                .and(query_param("q", "the band"))
+                .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                    "data": [
+                        { "id": "artist-2", "name": "The Band Tribute" },
+                        { "id": "artist-1", "name": "The Band" }
+                    ],
+                    "meta": { "totalCount": 2, "pageSize": 20, "currentPage": 0 }
+                })))
+                .mount(&server),
+        );
+        rt.block_on(
+            Mock::given(method("GET"))
+                .and(path("/api/v1/artists/artist-1/albums"))
+                .and(query_param("page", "0"))
+                .and(query_param("pageSize", "2"))
+                .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                    "data": [{
+                        "id": "album-1",
+                        "name": "Music from Big Pink",
+                        "artist": { "id": "artist-1", "name": "The Band" },
+                        "songsCount": 11
+                    }],
+                    "meta": { "totalCount": 1, "pageSize": 2, "currentPage": 0 }
+                })))
+                .expect(1)
+                .mount(&server),
+        );
+        rt.block_on(
+            Mock::given(method("GET"))
+                .and(path("/api/v1/artists"))
+                .and(query_param("q", "nobody"))
+                .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                    "data": [],
+                    "meta": { "totalCount": 0, "pageSize": 20, "currentPage": 0 }
+                })))
+                .mount(&server),
+        );
+        let provider =
+            MelodeeProvider::new(MelodeeConfig::new(format!("{}/", server.uri()), None)).unwrap();
+
+        let page = provider
+            .list_artist_albums("the band", PageRequest::first_page(2))
+            .unwrap();
+        let titles: Vec<_> = page.items.iter().map(|a| a.title.as_str()).collect();
+        assert_eq!(titles, ["Music from Big Pink"]);
+        assert!(page.next.is_none());
+        assert!(provider
+            .list_artist_albums("nobody", PageRequest::first_page(2))
+            .unwrap()
+            .items
+            .is_empty());
+    }
+
+    #[test]
+    fn list_recently_played_maps_history() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let server = rt.block_on(MockServer::start());
+        rt.block_on(
+            Mock::given(method("GET"))
+                .and(path("/api/v1/user/songs/recently-played"))
+                .and(query_param("page", "1"))
+                .and(query_param("limit", "2"))
+                .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                    "data": [song("song-3")],
+                    "meta": { "totalCount": 3, "pageSize": 2, "currentPage": 1 }
+                })))
+                .mount(&server),
+        );
+        let provider =
+            MelodeeProvider::new(MelodeeConfig::new(format!("{}/", server.uri()), None)).unwrap();
+
+        let page = provider
+            .list_recently_played(PageRequest {
+                offset: 2,
+                limit: 2,
+            })
+            .unwrap();
+        let ids: Vec<_> = page.items.iter().map(|t| t.id.0.as_str()).collect();
+        assert_eq!(ids, ["song-3"]);
+        assert_eq!(page.items[0].provider_id, "melodee");
+        assert!(page.next.is_none());
+    }
+
+    #[test]
+    fn zero_limit_pages_use_the_default_size() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let server = rt.block_on(MockServer::start());
+        rt.block_on(
+            Mock::given(method("GET"))
+                .and(path("/api/v1/user/songs/liked"))
+                .and(query_param("page", "2"))
+                .and(query_param("limit", DEFAULT_PAGE_LIMIT.to_string()))
+                .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                    "data": [song("song-1")],
+                    "meta": { "totalCount": 101, "pageSize": 50, "currentPage": 2 }
+                })))
+                .mount(&server),
+        );
+        let provider =
+            MelodeeProvider::new(MelodeeConfig::new(format!("{}/", server.uri()), None)).unwrap();
+
+        // Offset 100 with no limit used to divide by zero.
+        let page = provider
+            .list_favorites(PageRequest {
+                offset: 100,
+                limit: 0,
+            })
+            .unwrap();
+        assert_eq!(page.items.len(), 1);
+        assert!(page.next.is_none());
+    }
+
+    #[test]
+    fn health_check_heads_the_server_root() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let server = rt.block_on(MockServer::start());
+        let provider =
+            MelodeeProvider::new(MelodeeConfig::new(format!("{}/", server.uri()), None)).unwrap();
+
+        // Nothing mounted: wiremock answers 404, which still means reachable.
+        provider.health_check().unwrap();
+
+        rt.block_on(
+            Mock::given(method("HEAD"))
+                .and(path("/"))
+                .respond_with(ResponseTemplate::new(503))
+                .mount(&server),
+        );
+        assert!(matches!(
+            provider.health_check(),
+            Err(ProviderError::NetworkError { .. })
+        ));
+    }
+
+    #[test]
+    fn health_check_fails_when_nothing_is_listening() {
+        // Take a free port, then close it so connections are refused.
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let provider = MelodeeProvider::new(MelodeeConfig::new(
+            format!("http://127.0.0.1:{port}/"),
+            None,
+        ))
+        .unwrap();
+        assert!(matches!(
+            provider.health_check(),
+            Err(ProviderError::NetworkError { .. })
+        ));
+    }
+
+    #[test]
+    fn next_cursor_round_trips_until_short_page() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let server = rt.block_on(MockServer::start());
+        for (page, songs) in [("0", vec![song("a"), song("b")]), ("1", vec![song("c")])] {
+            rt.block_on(
+                Mock::given(method("GET"))
+                    .and(path("/api/v1/albums/album-1/songs"))
+                    .and(query_param("page", page))
+                    .and(query_param("pageSize", "2"))
+                    .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                        "data": songs,
+                        "meta": { "totalCount": 3, "pageSize": 2, "currentPage": 1 }
+                    })))
+                    .expect(1)
+                    .mount(&server),
+            );
+        }
+        let provider =
+            MelodeeProvider::new(MelodeeConfig::new(format!("{}/", server.uri()), None)).unwrap();
+        let album = AlbumId::new("album-1");
+
+        let first = provider
+            .list_album_tracks(&album, PageRequest::first_page(2))
+            .unwrap();
+        assert_eq!(first.items.len(), 2);
+        let cursor = first.next.expect("full page has a next cursor");
+
+        let second = provider
+            .list_album_tracks(&album, PageRequest::from_cursor(&cursor, 2).unwrap())
+            .unwrap();
+        assert_eq!(second.items.len(), 1);
+        assert_eq!(second.items[0].id, TrackId::new("c"));
+        assert!(second.next.is_none());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn search_inside_tokio_runtime_does_not_panic() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/search/songs"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": [song("a")],
+                "meta": { "totalCount": 1, "pageSize": 10, "currentPage": 1 }
+            })))
+            .mount(&server)
+            .await;
+        let provider =
+            MelodeeProvider::new(MelodeeConfig::new(format!("{}/", server.uri()), None)).unwrap();
+
+        let page = provider
+            .search_tracks(
+                "song",
+                TrackSearchFilters::default(),
+                PageRequest::first_page(10),
+            )
+            .unwrap();
+        assert_eq!(page.items.len(), 1);
+        drop(provider);
+    }
+
+    fn fast_retry() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(10),
+        }
+    }
+
+    #[test]
+    fn configured_timeout_cuts_off_slow_responses() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let server = rt.block_on(MockServer::start());
+        rt.block_on(
+            Mock::given(method("GET"))
+                .and(path("/api/v1/songs/song-1"))
+                .respond_with(
+                    ResponseTemplate::new(200)
+                        .set_body_json(song("song-1"))
+                        .set_delay(Duration::from_secs(5)),
+                )
+                .mount(&server),
+        );
+        let config = MelodeeConfig {
+            timeout: Duration::from_millis(200),
+            ..MelodeeConfig::new(format!("{}/", server.uri()), None)
+        };
+        let provider = MelodeeProvider::new(config)
+            .unwrap()
+            .with_retry_policy(RetryPolicy::none());
+
+        let started = std::time::Instant::now();
+        let result = provider.get_track(&TrackId::new("song-1"));
+        assert!(
+            matches!(&result, Err(ProviderError::NetworkError { message }) if message.contains("timed out")),
+            "{result:?}"
+        );
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn transient_server_errors_are_retried() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let server = rt.block_on(MockServer::start());
+        rt.block_on(
+            Mock::given(method("GET"))
+                .and(path("/api/v1/songs/song-1"))
+                .respond_with(ResponseTemplate::new(503))
+                .up_to_n_times(2)
+                .expect(2)
+                .mount(&server),
+        );
+        rt.block_on(
+            Mock::given(method("GET"))
+                .and(path("/api/v1/songs/song-1"))
+                .respond_with(ResponseTemplate::new(200).set_body_json(song("song-1")))
+                .expect(1)
+                .mount(&server),
+        );
+        let provider = MelodeeProvider::new(MelodeeConfig::new(format!("{}/", server.uri()), None))
+            .unwrap()
+            .with_retry_policy(fast_retry());
+
+        let track = provider.get_track(&TrackId::new("song-1")).unwrap();
+        assert_eq!(track.title, "Song song-1");
+    }
+
+    #[test]
+    fn rate_limits_are_waited_out_as_the_server_asks() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let server = rt.block_on(MockServer::start());
+        rt.block_on(
+            Mock::given(method("GET"))
+                .and(path("/api/v1/songs/song-1"))
+                .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "2"))
+                .up_to_n_times(1)
+                .expect(1)
+                .mount(&server),
+        );
+        rt.block_on(
+            Mock::given(method("GET"))
+                .and(path("/api/v1/songs/song-1"))
+                .respond_with(ResponseTemplate::new(200).set_body_json(song("song-1")))
+                .expect(1)
+                .mount(&server),
+        );
+        let provider = MelodeeProvider::new(MelodeeConfig::new(format!("{}/", server.uri()), None))
+            .unwrap()
+            .with_retry_policy(fast_retry());
+
+        let started = std::time::Instant::now();
+        let track = provider.get_track(&TrackId::new("song-1")).unwrap();
+        assert_eq!(track.title, "Song song-1");
+        assert!(started.elapsed() >= Duration::from_secs(2));
+    }
+
+    #[test]
+    fn rate_limits_and_server_errors_surface_by_kind() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let server = rt.block_on(MockServer::start());
+        rt.block_on(
+            Mock::given(method("GET"))
+                .and(path("/api/v1/songs/busy"))
+                .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "1"))
+                .mount(&server),
+        );
+        // Too long to wait for: not retried at all.
+        rt.block_on(
+            Mock::given(method("GET"))
+                .and(path("/api/v1/songs/later"))
+                .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "3600"))
+                .expect(1)
+                .mount(&server),
+        );
+        rt.block_on(
+            Mock::given(method("GET"))
+                .and(path("/api/v1/songs/broken"))
+                .respond_with(ResponseTemplate::new(502))
+                .expect(3)
+                .mount(&server),
+        );
+        let provider = MelodeeProvider::new(MelodeeConfig::new(format!("{}/", server.uri()), None))
+            .unwrap()
+            .with_retry_policy(RetryPolicy::none());
+        assert!(matches!(
+            provider.get_track(&TrackId::new("busy")),
+            Err(ProviderError::RateLimited { retry_after: Some(delay) }) if delay == Duration::from_secs(1)
+        ));
+
+        let provider = provider.with_retry_policy(fast_retry());
+        assert!(matches!(
+            provider.get_track(&TrackId::new("later")),
+            Err(ProviderError::RateLimited { retry_after: Some(delay) }) if delay == Duration::from_secs(3600)
+        ));
+        assert!(matches!(
+            provider.get_track(&TrackId::new("broken")),
+            Err(ProviderError::NetworkError { message }) if message.contains("502")
+        ));
+    }
+
+    #[test]
+    fn not_found_is_not_retried() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let server = rt.block_on(MockServer::start());
+        rt.block_on(
+            Mock::given(method("GET"))
+                .and(path("/api/v1/songs/missing"))
+                .respond_with(ResponseTemplate::new(404))
+                .expect(1)
+                .mount(&server),
+        );
+        let provider = MelodeeProvider::new(MelodeeConfig::new(format!("{}/", server.uri()), None))
+            .unwrap()
+            .with_retry_policy(fast_retry());
+
+        assert!(matches!(
+            provider.get_track(&TrackId::new("missing")),
+            Err(ProviderError::NotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn stream_url_failures_keep_their_kind() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let server = rt.block_on(MockServer::start());
+        rt.block_on(
+            Mock::given(method("GET"))
+                .and(path("/api/v1/songs/missing"))
+                .respond_with(ResponseTemplate::new(404))
+                .mount(&server),
+        );
+        rt.block_on(
+            Mock::given(method("GET"))
+                .and(path("/api/v1/songs/locked"))
+                .respond_with(ResponseTemplate::new(403))
+                .mount(&server),
+        );
+        let provider = MelodeeProvider::new(MelodeeConfig::new(format!("{}/", server.uri()), None))
+            .unwrap()
+            .with_retry_policy(fast_retry());
+
+        assert!(matches!(
+            provider.get_stream_url(&TrackId::new("missing")),
+            Err(ProviderError::NotFound { entity }) if entity == "missing"
+        ));
+        assert!(matches!(
+            provider.get_stream_url(&TrackId::new("locked")),
+            Err(ProviderError::AuthenticationError { .. })
+        ));
+    }
+
+    #[test]
+    fn stream_url_is_built_when_the_server_gives_only_an_id() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let server = rt.block_on(MockServer::start());
+        let mut bare = song("song-1");
+        bare["streamUrl"] = json!("song-1");
+        rt.block_on(
+            Mock::given(method("GET"))
+                .and(path("/api/v1/songs/song-1"))
+                .respond_with(ResponseTemplate::new(200).set_body_json(bare))
+                .mount(&server),
+        );
+        rt.block_on(
+            Mock::given(method("GET"))
+                .and(path("/api/v1/user/me"))
+                .and(header("authorization", "Bearer tok"))
+                .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                    "id": "user-1",
+                    "username": "me"
+                })))
+                .expect(1)
+                .mount(&server),
+        );
+        let base_url = format!("{}/", server.uri());
+        let auth = MelodeeAuth::new(Client::new(), base_url.as_str(), None)
+            .with_credential_store(CredentialStore::with_service("tunez-test"))
+            .with_tokens(Some("tok".into()), None);
+        let provider = MelodeeProvider::new(MelodeeConfig::new(base_url.clone(), None))
+            .unwrap()
+            .with_auth(auth);
+
+        let url = provider.get_stream_url(&TrackId::new("song-1")).unwrap();
+        assert_eq!(url.0, format!("{base_url}song/stream/song-1/user-1/tok"));
+        assert_eq!(url.location(), tunez_core::StreamLocation::Http);
+        assert!(!tunez_core::redact_secrets(&url.0).contains("/tok"));
+
+        // The user id is remembered rather than fetched for every song.
+        provider.get_stream_url(&TrackId::new("song-1")).unwrap();
+    }
+
+    fn expiring_auth(base_url: &str) -> MelodeeAuth {
+        MelodeeAuth::new(Client::new(), base_url, None)
+            .with_credential_store(CredentialStore::with_service("tunez-test"))
+            .with_tokens(Some("old".into()), Some("refresh-1".into()))
+    }
+
+    fn mount_expired_token(rt: &tokio::runtime::Runtime, server: &MockServer, refreshes: u64) {
+        rt.block_on(
+            Mock::given(method("GET"))
+                .and(path("/api/v1/songs/song-1"))
+                .and(header("authorization", "Bearer old"))
+                .respond_with(ResponseTemplate::new(401))
+                .mount(server),
+        );
+        rt.block_on(
+            Mock::given(method("GET"))
+                .and(path("/api/v1/songs/song-1"))
+                .and(header("authorization", "Bearer new"))
+                .respond_with(ResponseTemplate::new(200).set_body_json(song("song-1")))
+                .mount(server),
+        );
+        rt.block_on(
+            Mock::given(method("POST"))
+                .and(path("/api/v1/auth/refresh-token"))
+                .and(body_json(json!({ "refreshToken": "refresh-1" })))
+                .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                    "token": "new",
+                    "expiresAt": "2030-01-01T00:00:00Z"
+                })))
+                .expect(refreshes)
+                .mount(server),
+        );
+    }
+
+    #[test]
+    fn expired_token_is_refreshed_and_request_retried() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let server = rt.block_on(MockServer::start());
+        mount_expired_token(&rt, &server, 1);
+        let base_url = format!("{}/", server.uri());
+        let provider = MelodeeProvider::new(MelodeeConfig::new(base_url.clone(), None))
+            .unwrap()
+            .with_auth(expiring_auth(&base_url));
+
+        let track = provider.get_track(&TrackId::new("song-1")).unwrap();
+        assert_eq!(track.title, "Song song-1");
+    }
+
+    #[test]
+    fn concurrent_unauthorized_requests_share_one_refresh() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let server = rt.block_on(MockServer::start());
+        mount_expired_token(&rt, &server, 1);
+        let base_url = format!("{}/", server.uri());
+        let provider = MelodeeProvider::new(MelodeeConfig::new(base_url.clone(), None))
+            .unwrap()
+            .with_auth(expiring_auth(&base_url));
+
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let provider = provider.clone();
+                std::thread::spawn(move || provider.get_track(&TrackId::new("song-1")))
+            })
+            .collect();
+        for handle in handles {
+            assert!(handle.join().unwrap().is_ok());
+        }
+    }
+
+    #[test]
+    fn failed_refresh_is_an_authentication_error() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let server = rt.block_on(MockServer::start());
+        rt.block_on(
+            Mock::given(method("GET"))
+                .and(path("/api/v1/songs/song-1"))
+                .respond_with(ResponseTemplate::new(401))
+                .mount(&server),
+        );
+        rt.block_on(
+            Mock::given(method("POST"))
+                .and(path("/api/v1/auth/refresh-token"))
+                .respond_with(ResponseTemplate::new(401))
+                .expect(1)
+                .mount(&server),
+        );
+        let base_url = format!("{}/", server.uri());
+        let provider = MelodeeProvider::new(MelodeeConfig::new(base_url.clone(), None))
+            .unwrap()
+            .with_auth(expiring_auth(&base_url));
+
+        assert!(matches!(
+            provider.get_track(&TrackId::new("song-1")),
+            Err(ProviderError::AuthenticationError { .. })
+        ));
+    }
 }
@@ -15,7 +15,12 @@ pub fn map_track(song: &Song, provider_id: &str) -> Track {
             .unwrap_or_else(|| "Unknown Artist".into()),
         album: song.album.as_ref().map(|a| a.name.clone()),
         duration_seconds: song.duration_ms.map(|d| (d / 1000) as u32),
-        track_number: None,
+        track_number: song.song_number,
+        disc_number: song.disc_number,
+        year: song.year,
+        genre: song.genre.clone(),
+        musicbrainz_id: song.musicbrainz_id.clone(),
+        replay_gain: Default::default(),
     }
 }
 
@@ -31,6 +36,8 @@ pub fn map_album(album: &Album, provider_id: &str) -> CoreAlbum {
             .unwrap_or_else(|| "Unknown Artist".into()),
         track_count: album.songs_count,
         duration_seconds: None,
+        year: None,
+        added_at: None,
     }
 }
 
@@ -1,4 +1,4 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Deserialize)]
 pub struct SongPagedResponse {
@@ -6,12 +6,28 @@ pub struct SongPagedResponse {
     pub meta: PaginationMetadata,
 }
 
+/// A page of a playlist's songs. Servers that support it describe the
+/// playlist alongside, saving a separate request for it.
+#[derive(Debug, Deserialize)]
+pub struct PlaylistSongsResponse {
+    #[serde(default)]
+    pub playlist: Option<Playlist>,
+    pub data: Vec<Song>,
+    pub meta: PaginationMetadata,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct AlbumPagedResponse {
     pub data: Vec<Album>,
     pub meta: PaginationMetadata,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct ArtistPagedResponse {
+    pub data: Vec<ArtistRef>,
+    pub meta: PaginationMetadata,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct PlaylistPagedResponse {
     pub data: Vec<Playlist>,
@@ -50,6 +66,16 @@ pub struct Song {
     pub thumbnail_url: Option<String>,
     #[serde(rename = "imageUrl", default)]
     pub image_url: Option<String>,
+    #[serde(rename = "songNumber", default)]
+    pub song_number: Option<u32>,
+    #[serde(rename = "discNumber", default)]
+    pub disc_number: Option<u32>,
+    #[serde(default)]
+    pub year: Option<u32>,
+    #[serde(default)]
+    pub genre: Option<String>,
+    #[serde(rename = "musicBrainzId", default)]
+    pub musicbrainz_id: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -84,8 +110,27 @@ pub struct Album {
     #[serde(rename = "songsCount", default)]
     pub songs_count: Option<u32>,
 }
+/// The signed-in user; their id is the `userApiKey` of stream URLs.
+#[derive(Debug, Deserialize)]
+pub struct CurrentUser {
+    pub id: String,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct Lyrics {
     #[serde(rename = "plainText")]
     pub plain_text: String,
 }
+
+#[derive(Debug, Serialize)]
+pub struct RefreshTokenRequest {
+    #[serde(rename = "refreshToken")]
+    pub refresh_token: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AuthenticationResponse {
+    pub token: String,
+    #[serde(rename = "refreshToken")]
+    pub refresh_token: Option<String>,
+}
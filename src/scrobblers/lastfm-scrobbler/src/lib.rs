@@ -0,0 +1,494 @@
+//! Last.fm scrobbler.
+//!
+//! Sends "now playing" updates when a track starts and a scrobble when it ends
+//! after being played long enough. Requests are signed with the account's API
+//! secret as described in the Last.fm API authentication spec.
+
+use async_trait::async_trait;
+use reqwest::{Client, StatusCode};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tunez_core::redact_error;
+use tunez_core::scrobbler::{
+    meets_scrobble_threshold, BatchError, PlaybackState, ScrobbleEvent, Scrobbler, ScrobblerError,
+    ScrobblerResult,
+};
+use tunez_core::secrets::{CredentialStore, SecretKind, SecretsError};
+use tunez_core::TrackId;
+
+/// Default Last.fm API endpoint.
+pub const LASTFM_API_URL: &str = "https://ws.audioscrobbler.com/2.0/";
+
+/// Credential store key under which Last.fm secrets are kept.
+pub const CREDENTIAL_KEY: &str = "lastfm";
+
+/// How long a request may take unless configured otherwise.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Tracks at or below this length are never scrobbled.
+const MIN_SCROBBLE_TRACK_SECONDS: u64 = 30;
+
+/// Most scrobbles Last.fm accepts in one `track.scrobble` call.
+pub const MAX_BATCH_SCROBBLES: usize = 50;
+
+// Last.fm API error codes we map to specific scrobbler errors.
+const ERR_INVALID_SESSION: u64 = 9;
+const ERR_INVALID_API_KEY: u64 = 10;
+const ERR_SERVICE_OFFLINE: u64 = 11;
+const ERR_TEMPORARILY_UNAVAILABLE: u64 = 16;
+const ERR_RATE_LIMITED: u64 = 29;
+
+// `ignoredMessage` code for a scrobble dropped because the account hit its
+// daily limit. Other ignore reasons are permanent, so only this one is retried.
+const IGNORED_DAILY_LIMIT: u64 = 5;
+
+/// Secrets needed to call authenticated Last.fm methods.
+#[derive(Clone)]
+pub struct LastfmCredentials {
+    pub api_key: String,
+    pub api_secret: String,
+    pub session_key: String,
+}
+
+impl std::fmt::Debug for LastfmCredentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LastfmCredentials")
+            .field("api_key", &self.api_key)
+            .finish_non_exhaustive()
+    }
+}
+
+impl LastfmCredentials {
+    /// Load the API key, API secret, and session key from the OS keyring.
+    pub fn from_store(store: &CredentialStore, profile: Option<&str>) -> ScrobblerResult<Self> {
+        let get = |kind| {
+            store
+                .get(CREDENTIAL_KEY, profile, kind)
+                .map_err(|err| match err {
+                    SecretsError::NotFound { .. } => ScrobblerError::NotConfigured,
+                    other => ScrobblerError::Other {
+                        message: other.user_message(),
+                    },
+                })
+        };
+        Ok(Self {
+            api_key: get(SecretKind::ApiKey)?,
+            api_secret: get(SecretKind::ApiSecret)?,
+            session_key: get(SecretKind::SessionKey)?,
+        })
+    }
+}
+
+/// Compute the `api_sig` for a parameter set.
+///
+/// Parameters are concatenated as `<name><value>` in name order, followed by
+/// the shared secret, and hashed with MD5. `format` and `callback` are not
+/// part of the signature.
+pub fn sign<K: AsRef<str>>(params: &BTreeMap<K, String>, api_secret: &str) -> String {
+    let mut payload = String::new();
+    for (name, value) in params {
+        let name = name.as_ref();
+        if matches!(name, "format" | "callback") {
+            continue;
+        }
+        payload.push_str(name);
+        payload.push_str(value);
+    }
+    payload.push_str(api_secret);
+    format!("{:x}", md5::compute(payload.as_bytes()))
+}
+
+/// Whether an event represents a play that Last.fm counts as a scrobble:
+/// the track ended, is longer than 30 seconds, and was played for at least
+/// half its length or four minutes, whichever comes first.
+pub fn qualifies_for_scrobble(event: &ScrobbleEvent) -> bool {
+    if event.state != PlaybackState::Ended {
+        return false;
+    }
+    let duration = event.progress.duration_seconds;
+    if duration.is_some_and(|d| d <= MIN_SCROBBLE_TRACK_SECONDS) {
+        return false;
+    }
+    meets_scrobble_threshold(event.progress.position_seconds, duration)
+}
+
+/// The track fields Last.fm takes for both now playing and scrobbles.
+fn track_params(event: &ScrobbleEvent) -> Vec<(&'static str, String)> {
+    let track = &event.track;
+    let mut params = vec![
+        ("artist", track.artist.clone()),
+        ("track", track.title.clone()),
+    ];
+    if let Some(album) = &track.album {
+        params.push(("album", album.clone()));
+    }
+    if let Some(number) = track.track_number {
+        params.push(("trackNumber", number.to_string()));
+    }
+    if let Some(mbid) = &track.musicbrainz_id {
+        params.push(("mbid", mbid.clone()));
+    }
+    if let Some(duration) = event.progress.duration_seconds {
+        params.push(("duration", duration.to_string()));
+    }
+    params
+}
+
+/// When the track started playing. Last.fm wants this rather than the time
+/// the scrobble is sent, which for a backlogged event is much later.
+fn scrobble_timestamp(event: &ScrobbleEvent) -> u64 {
+    event
+        .occurred_at_or_now()
+        .saturating_sub(event.progress.position_seconds)
+}
+
+/// How many scrobbles of a batch are settled, given the `track.scrobble`
+/// response: everything up to the first one Last.fm ignored for the daily
+/// limit. Scrobbles ignored for any other reason would be ignored again, so
+/// they count as settled.
+fn settled_scrobbles(body: &serde_json::Value, submitted: usize) -> usize {
+    let scrobbles = match body.pointer("/scrobbles/scrobble") {
+        Some(serde_json::Value::Array(items)) => items.iter().collect(),
+        Some(item @ serde_json::Value::Object(_)) => vec![item],
+        _ => return submitted,
+    };
+    for (index, scrobble) in scrobbles.into_iter().enumerate() {
+        let code = scrobble.pointer("/ignoredMessage/code").and_then(|code| {
+            code.as_u64()
+                .or_else(|| code.as_str().and_then(|s| s.parse().ok()))
+        });
+        match code {
+            Some(IGNORED_DAILY_LIMIT) => return index.min(submitted),
+            Some(code) if code != 0 => tracing::warn!(code, index, "Last.fm ignored a scrobble"),
+            _ => {}
+        }
+    }
+    submitted
+}
+
+pub struct LastfmScrobbler {
+    client: Client,
+    api_url: String,
+    credentials: LastfmCredentials,
+    /// Track most recently announced as now playing, so progress ticks don't resend it.
+    now_playing: Mutex<Option<TrackId>>,
+}
+
+impl LastfmScrobbler {
+    pub fn new(credentials: LastfmCredentials) -> Self {
+        Self::with_api_url(credentials, LASTFM_API_URL)
+    }
+
+    /// Create a scrobbler that talks to a different endpoint (used by tests).
+    pub fn with_api_url(credentials: LastfmCredentials, api_url: impl Into<String>) -> Self {
+        Self {
+            client: client(DEFAULT_TIMEOUT),
+            api_url: api_url.into(),
+            credentials,
+            now_playing: Mutex::new(None),
+        }
+    }
+
+    /// Give up on requests after `timeout`.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.client = client(timeout);
+        self
+    }
+
+    /// Create a scrobbler using credentials from the OS keyring.
+    pub fn from_credential_store(profile: Option<&str>) -> ScrobblerResult<Self> {
+        let credentials = LastfmCredentials::from_store(&CredentialStore::new(), profile)?;
+        Ok(Self::new(credentials))
+    }
+
+    /// Build the signed form parameters for a method call.
+    fn signed_params(
+        &self,
+        method: &str,
+        fields: impl IntoIterator<Item = (String, String)>,
+    ) -> BTreeMap<String, String> {
+        let mut params: BTreeMap<String, String> = fields.into_iter().collect();
+        params.insert("method".into(), method.to_string());
+        params.insert("api_key".into(), self.credentials.api_key.clone());
+        params.insert("sk".into(), self.credentials.session_key.clone());
+        let signature = sign(&params, &self.credentials.api_secret);
+        params.insert("api_sig".into(), signature);
+        params.insert("format".into(), "json".into());
+        params
+    }
+
+    async fn call(&self, params: BTreeMap<String, String>) -> ScrobblerResult<serde_json::Value> {
+        let res = self
+            .client
+            .post(&self.api_url)
+            .form(&params)
+            .send()
+            .await
+            .map_err(|e| ScrobblerError::Network {
+                message: redact_error(e),
+            })?;
+
+        let status = res.status();
+        // Last.fm reports API errors in the body, sometimes alongside a 200.
+        let body: serde_json::Value = res.json().await.unwrap_or_default();
+        if let Some(code) = body.get("error").and_then(|c| c.as_u64()) {
+            let message = body
+                .get("message")
+                .and_then(|m| m.as_str())
+                .map(redact_error)
+                .unwrap_or_else(|| "unknown error".to_string());
+            return Err(map_api_error(code, message));
+        }
+
+        match status {
+            s if s.is_success() => Ok(body),
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+                Err(ScrobblerError::Authentication {
+                    message: "Last.fm rejected the session".into(),
+                })
+            }
+            StatusCode::TOO_MANY_REQUESTS => Err(ScrobblerError::RateLimited {
+                message: "Rate limited".into(),
+            }),
+            s => Err(ScrobblerError::Other {
+                message: format!("API error: {}", s),
+            }),
+        }
+    }
+
+    async fn update_now_playing(&self, event: &ScrobbleEvent) -> ScrobblerResult<()> {
+        if event.is_replayed() {
+            tracing::debug!(track = %event.track.title, "skipping now playing for a replayed event");
+            return Ok(());
+        }
+        {
+            let mut last = self.now_playing.lock().unwrap();
+            if last.as_ref() == Some(&event.track.id) {
+                return Ok(());
+            }
+            *last = Some(event.track.id.clone());
+        }
+        let fields = track_params(event)
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value));
+        let params = self.signed_params("track.updateNowPlaying", fields);
+        self.call(params).await.map(|_| ())
+    }
+
+    async fn scrobble(&self, event: &ScrobbleEvent) -> ScrobblerResult<()> {
+        let fields = track_params(event)
+            .into_iter()
+            .chain([("timestamp", scrobble_timestamp(event).to_string())])
+            .map(|(name, value)| (name.to_string(), value));
+        let params = self.signed_params("track.scrobble", fields);
+        self.call(params).await.map(|_| ())
+    }
+
+    /// Send up to [`MAX_BATCH_SCROBBLES`] scrobbles in one call, using
+    /// Last.fm's indexed `artist[i]`, `track[i]`, ... parameters. Returns how
+    /// many of them are settled; see [`settled_scrobbles`].
+    async fn scrobble_many(&self, events: &[ScrobbleEvent]) -> ScrobblerResult<usize> {
+        debug_assert!(events.len() <= MAX_BATCH_SCROBBLES);
+        let fields = events.iter().enumerate().flat_map(|(index, event)| {
+            track_params(event)
+                .into_iter()
+                .chain([("timestamp", scrobble_timestamp(event).to_string())])
+                .map(move |(name, value)| (format!("{name}[{index}]"), value))
+        });
+        let params = self.signed_params("track.scrobble", fields);
+        let body = self.call(params).await?;
+        Ok(settled_scrobbles(&body, events.len()))
+    }
+}
+
+fn client(timeout: Duration) -> Client {
+    Client::builder().timeout(timeout).build().unwrap()
+}
+
+fn map_api_error(code: u64, message: String) -> ScrobblerError {
+    match code {
+        ERR_INVALID_SESSION | ERR_INVALID_API_KEY => ScrobblerError::Authentication { message },
+        ERR_SERVICE_OFFLINE | ERR_TEMPORARILY_UNAVAILABLE => ScrobblerError::Network { message },
+        ERR_RATE_LIMITED => ScrobblerError::RateLimited { message },
+        _ => ScrobblerError::Other {
+            message: format!("Last.fm error {code}: {message}"),
+        },
+    }
+}
+
+#[async_trait]
+impl Scrobbler for LastfmScrobbler {
+    fn id(&self) -> &str {
+        "lastfm"
+    }
+
+    async fn submit(&self, event: &ScrobbleEvent) -> ScrobblerResult<()> {
+        match event.state {
+            PlaybackState::Started | PlaybackState::Resumed => self.update_now_playing(event).await,
+            PlaybackState::Ended => {
+                *self.now_playing.lock().unwrap() = None;
+                if qualifies_for_scrobble(event) {
+                    self.scrobble(event).await
+                } else {
+                    tracing::debug!(
+                        track = %event.track.title,
+                        played = event.progress.position_seconds,
+                        "play too short to scrobble"
+                    );
+                    Ok(())
+                }
+            }
+            PlaybackState::Paused | PlaybackState::Stopped => Ok(()),
+        }
+    }
+
+    /// Scrobbles go out up to [`MAX_BATCH_SCROBBLES`] per request; anything
+    /// else in the batch is submitted on its own, in order.
+    async fn submit_batch(&self, events: &[ScrobbleEvent]) -> Result<(), BatchError> {
+        let mut sent = 0;
+        while sent < events.len() {
+            let run = events[sent..]
+                .iter()
+                .take(MAX_BATCH_SCROBBLES)
+                .take_while(|event| qualifies_for_scrobble(event))
+                .count();
+            if run == 0 {
+                self.submit(&events[sent])
+                    .await
+                    .map_err(|error| BatchError { sent, error })?;
+                sent += 1;
+                continue;
+            }
+
+            *self.now_playing.lock().unwrap() = None;
+            let settled = self
+                .scrobble_many(&events[sent..sent + run])
+                .await
+                .map_err(|error| BatchError { sent, error })?;
+            sent += settled;
+            if settled < run {
+                return Err(BatchError {
+                    sent,
+                    error: ScrobblerError::RateLimited {
+                        message: "Last.fm daily scrobble limit reached".into(),
+                    },
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tunez_core::{PlaybackProgress, Track};
+
+    fn event(state: PlaybackState, position: u64, duration: Option<u64>) -> ScrobbleEvent {
+        ScrobbleEvent {
+            track: Track {
+                id: TrackId::new("t1"),
+                provider_id: "filesystem".into(),
+                title: "Song".into(),
+                artist: "Artist".into(),
+                album: None,
+                duration_seconds: duration.map(|d| d as u32),
+                track_number: None,
+                disc_number: None,
+                year: None,
+                genre: None,
+                musicbrainz_id: None,
+                replay_gain: Default::default(),
+            },
+            progress: PlaybackProgress {
+                position_seconds: position,
+                duration_seconds: duration,
+            },
+            state,
+            player_name: "Tunez".into(),
+            device_id: None,
+            occurred_at: Some(ScrobbleEvent::now()),
+        }
+    }
+
+    #[test]
+    fn signature_uses_sorted_params_and_skips_format() {
+        let mut params = BTreeMap::new();
+        params.insert("track", "Song".to_string());
+        params.insert("method", "track.scrobble".to_string());
+        params.insert("api_key", "key".to_string());
+        params.insert("artist", "Artist".to_string());
+        params.insert("format", "json".to_string());
+
+        let expected = format!(
+            "{:x}",
+            md5::compute("api_keykeyartistArtistmethodtrack.scrobbletrackSongsecret")
+        );
+        assert_eq!(sign(&params, "secret"), expected);
+    }
+
+    #[test]
+    fn threshold_is_half_the_track_or_four_minutes() {
+        assert!(!qualifies_for_scrobble(&event(
+            PlaybackState::Ended,
+            89,
+            Some(180)
+        )));
+        assert!(qualifies_for_scrobble(&event(
+            PlaybackState::Ended,
+            90,
+            Some(180)
+        )));
+        assert!(qualifies_for_scrobble(&event(
+            PlaybackState::Ended,
+            240,
+            Some(1200)
+        )));
+        assert!(!qualifies_for_scrobble(&event(
+            PlaybackState::Ended,
+            239,
+            Some(1200)
+        )));
+        assert!(!qualifies_for_scrobble(&event(
+            PlaybackState::Ended,
+            30,
+            Some(30)
+        )));
+        assert!(!qualifies_for_scrobble(&event(
+            PlaybackState::Stopped,
+            180,
+            Some(180)
+        )));
+    }
+
+    #[test]
+    fn unknown_duration_needs_four_minutes() {
+        assert!(!qualifies_for_scrobble(&event(
+            PlaybackState::Ended,
+            200,
+            None
+        )));
+        assert!(qualifies_for_scrobble(&event(
+            PlaybackState::Ended,
+            240,
+            None
+        )));
+    }
+
+    #[test]
+    fn api_errors_map_to_scrobbler_errors() {
+        assert!(matches!(
+            map_api_error(9, "bad session".into()),
+            ScrobblerError::Authentication { .. }
+        ));
+        assert!(matches!(
+            map_api_error(29, "slow down".into()),
+            ScrobblerError::RateLimited { .. }
+        ));
+        assert!(matches!(
+            map_api_error(11, "offline".into()),
+            ScrobblerError::Network { .. }
+        ));
+    }
+}
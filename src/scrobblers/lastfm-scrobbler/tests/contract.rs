@@ -0,0 +1,242 @@
+use lastfm_scrobbler::{LastfmCredentials, LastfmScrobbler, MAX_BATCH_SCROBBLES};
+use tunez_core::models::{Track, TrackId};
+use tunez_core::scrobbler::{run_scrobbler_contract, ScrobblerContractSpec};
+use tunez_core::{PlaybackProgress, PlaybackState, ScrobbleEvent, Scrobbler, ScrobblerError};
+use wiremock::matchers::{body_string_contains, method};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn credentials() -> LastfmCredentials {
+    LastfmCredentials {
+        api_key: "key".into(),
+        api_secret: "secret".into(),
+        session_key: "session".into(),
+    }
+}
+
+fn sample_event(state: PlaybackState, position: u64) -> ScrobbleEvent {
+    ScrobbleEvent {
+        track: Track {
+            id: TrackId::new("track-1"),
+            provider_id: "filesystem".into(),
+            title: "Example".into(),
+            artist: "Artist".into(),
+            album: Some("Album".into()),
+            duration_seconds: Some(180),
+            track_number: Some(1),
+            disc_number: None,
+            year: None,
+            genre: None,
+            musicbrainz_id: None,
+            replay_gain: Default::default(),
+        },
+        progress: PlaybackProgress {
+            position_seconds: position,
+            duration_seconds: Some(180),
+        },
+        state,
+        player_name: "Tunez".into(),
+        device_id: None,
+        occurred_at: Some(ScrobbleEvent::now()),
+    }
+}
+
+#[tokio::test]
+async fn lastfm_scrobbler_contract() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(body_string_contains("method=track.updateNowPlaying"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("{}"))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(body_string_contains("method=track.scrobble"))
+        .and(body_string_contains("api_sig="))
+        .and(body_string_contains("sk=session"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("{}"))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let scrobbler = LastfmScrobbler::with_api_url(credentials(), mock_server.uri());
+    let spec = ScrobblerContractSpec {
+        scrobbler: &scrobbler,
+        events: vec![
+            sample_event(PlaybackState::Started, 0),
+            sample_event(PlaybackState::Started, 1),
+            sample_event(PlaybackState::Ended, 180),
+        ],
+        load_persisted: None,
+    };
+
+    run_scrobbler_contract(spec)
+        .await
+        .expect("contract should pass");
+}
+
+#[tokio::test]
+async fn short_play_is_not_scrobbled() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(body_string_contains("method=track.scrobble"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("{}"))
+        .expect(0)
+        .mount(&mock_server)
+        .await;
+
+    let scrobbler = LastfmScrobbler::with_api_url(credentials(), mock_server.uri());
+    scrobbler
+        .submit(&sample_event(PlaybackState::Ended, 30))
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn invalid_session_is_authentication_error() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(
+            ResponseTemplate::new(403)
+                .set_body_string(r#"{"error":9,"message":"Invalid session key"}"#),
+        )
+        .mount(&mock_server)
+        .await;
+
+    let scrobbler = LastfmScrobbler::with_api_url(credentials(), mock_server.uri());
+    let result = scrobbler
+        .submit(&sample_event(PlaybackState::Ended, 180))
+        .await;
+    assert!(matches!(result, Err(ScrobblerError::Authentication { .. })));
+}
+
+#[tokio::test]
+async fn backlogged_scrobble_is_stamped_when_it_was_played() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(body_string_contains("method=track.scrobble"))
+        .and(body_string_contains("timestamp=1699999820"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("{}"))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let scrobbler = LastfmScrobbler::with_api_url(credentials(), mock_server.uri());
+    let mut event = sample_event(PlaybackState::Ended, 180);
+    event.occurred_at = Some(1_700_000_000);
+    scrobbler.submit(&event).await.unwrap();
+}
+
+#[tokio::test]
+async fn replayed_start_sends_no_now_playing() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(body_string_contains("method=track.updateNowPlaying"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("{}"))
+        .expect(0)
+        .mount(&mock_server)
+        .await;
+
+    let scrobbler = LastfmScrobbler::with_api_url(credentials(), mock_server.uri());
+    let mut event = sample_event(PlaybackState::Started, 0);
+    event.occurred_at = Some(1_700_000_000);
+    scrobbler.submit(&event).await.unwrap();
+}
+
+#[tokio::test]
+async fn backlog_is_scrobbled_fifty_at_a_time() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(body_string_contains("method=track.updateNowPlaying"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("{}"))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    // A full batch carries indexes 0 through 49.
+    Mock::given(method("POST"))
+        .and(body_string_contains("method=track.scrobble"))
+        .and(body_string_contains("artist%5B49%5D=Artist"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("{}"))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(body_string_contains("method=track.scrobble"))
+        .and(body_string_contains("artist%5B9%5D=Artist"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("{}"))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let scrobbler = LastfmScrobbler::with_api_url(credentials(), mock_server.uri());
+    let mut events = vec![sample_event(PlaybackState::Started, 0)];
+    events.extend((0..MAX_BATCH_SCROBBLES + 10).map(|_| sample_event(PlaybackState::Ended, 180)));
+    scrobbler.submit_batch(&events).await.unwrap();
+}
+
+#[tokio::test]
+async fn daily_limit_stops_the_batch_at_the_first_ignored_scrobble() {
+    let mock_server = MockServer::start().await;
+    let body = r##"{"scrobbles":{"@attr":{"accepted":1,"ignored":2},"scrobble":[
+        {"ignoredMessage":{"code":"0","#text":""}},
+        {"ignoredMessage":{"code":"5","#text":"Daily scrobble limit exceeded"}},
+        {"ignoredMessage":{"code":"5","#text":"Daily scrobble limit exceeded"}}
+    ]}}"##;
+    Mock::given(method("POST"))
+        .and(body_string_contains("method=track.scrobble"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(body))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let scrobbler = LastfmScrobbler::with_api_url(credentials(), mock_server.uri());
+    let events = vec![sample_event(PlaybackState::Ended, 180); 3];
+    let err = scrobbler.submit_batch(&events).await.unwrap_err();
+    assert_eq!(err.sent, 1);
+    assert!(matches!(err.error, ScrobblerError::RateLimited { .. }));
+}
+
+#[tokio::test]
+async fn permanently_ignored_scrobbles_are_not_retried() {
+    let mock_server = MockServer::start().await;
+    let body = r##"{"scrobbles":{"@attr":{"accepted":1,"ignored":1},"scrobble":[
+        {"ignoredMessage":{"code":"3","#text":"Timestamp too old"}},
+        {"ignoredMessage":{"code":"0","#text":""}}
+    ]}}"##;
+    Mock::given(method("POST"))
+        .and(body_string_contains("method=track.scrobble"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(body))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let scrobbler = LastfmScrobbler::with_api_url(credentials(), mock_server.uri());
+    let events = vec![sample_event(PlaybackState::Ended, 180); 2];
+    scrobbler.submit_batch(&events).await.unwrap();
+}
+
+#[tokio::test]
+async fn failed_batch_reports_the_scrobbles_sent_before_it() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(body_string_contains("method=track.updateNowPlaying"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("{}"))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(body_string_contains("method=track.scrobble"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_string(r#"{"error":11,"message":"Service Offline"}"#),
+        )
+        .mount(&mock_server)
+        .await;
+
+    let scrobbler = LastfmScrobbler::with_api_url(credentials(), mock_server.uri());
+    let events = vec![
+        sample_event(PlaybackState::Started, 0),
+        sample_event(PlaybackState::Ended, 180),
+    ];
+    let err = scrobbler.submit_batch(&events).await.unwrap_err();
+    assert_eq!(err.sent, 1);
+    assert!(matches!(err.error, ScrobblerError::Network { .. }));
+}
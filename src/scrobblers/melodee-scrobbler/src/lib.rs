@@ -1,20 +1,28 @@
 use async_trait::async_trait;
 use reqwest::{Client, StatusCode};
 use serde_json::json;
+use std::sync::Arc;
 use std::time::Duration;
+use tunez_core::redact_error;
+use tunez_core::retry::{is_transient_status, parse_retry_after, RetryError, RetryPolicy};
 use tunez_core::scrobbler::{
     PlaybackState, ScrobbleEvent, Scrobbler, ScrobblerError, ScrobblerResult,
 };
 
-use std::sync::{Arc, RwLock};
-use tunez_core::secrets::CredentialStore;
+use melodee_provider::MelodeeAuth;
+
+/// How long a scrobble request may take unless configured otherwise.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+fn client(timeout: Duration) -> Client {
+    Client::builder().timeout(timeout).build().unwrap()
+}
 
 pub struct MelodeeScrobbler {
     client: Client,
     base_url: String,
-    profile: Option<String>,
-    creds: CredentialStore,
-    token: Arc<RwLock<Option<String>>>,
+    auth: Arc<MelodeeAuth>,
+    retry: RetryPolicy,
 }
 
 impl MelodeeScrobbler {
@@ -23,36 +31,99 @@ impl MelodeeScrobbler {
         profile: Option<String>,
         initial_token: Option<String>,
     ) -> Self {
+        let client = client(DEFAULT_TIMEOUT);
+        let base_url = base_url.into();
+        let auth = MelodeeAuth::new(client.clone(), base_url.clone(), profile)
+            .with_tokens(initial_token, None);
         Self {
-            client: Client::builder()
-                .timeout(Duration::from_secs(10))
-                .build()
-                .unwrap(),
-            base_url: base_url.into(),
-            profile,
-            creds: CredentialStore::new(),
-            token: Arc::new(RwLock::new(initial_token)),
+            client,
+            base_url,
+            auth: Arc::new(auth),
+            retry: RetryPolicy::default(),
         }
     }
 
-    fn get_token(&self) -> Option<String> {
-        if let Ok(guard) = self.token.read() {
-            if let Some(token) = guard.as_ref() {
-                return Some(token.clone());
-            }
-        }
+    /// Give up on requests after `timeout`. Token refreshes follow it too,
+    /// unless the tokens are shared, in which case their owner's client is
+    /// kept.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.client = client(timeout);
+        self.auth = match Arc::try_unwrap(self.auth) {
+            Ok(auth) => Arc::new(auth.with_client(self.client.clone())),
+            Err(shared) => shared,
+        };
+        self
+    }
 
-        if let Ok(token) = self
-            .creds
-            .get_access_token("melodee", self.profile.as_deref())
-        {
-            if let Ok(mut guard) = self.token.write() {
-                *guard = Some(token.clone());
-            }
-            return Some(token);
-        }
+    /// Replace how access tokens are obtained and refreshed. Pass the
+    /// provider's [`MelodeeProvider::auth`](melodee_provider::MelodeeProvider::auth)
+    /// so both use, and refresh, the same tokens.
+    pub fn with_auth(mut self, auth: impl Into<Arc<MelodeeAuth>>) -> Self {
+        self.auth = auth.into();
+        self
+    }
+
+    /// Replace the policy used to retry transient network and server errors.
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
 
-        None
+    /// POST a scrobble, retrying transient failures.
+    async fn post(
+        &self,
+        url: &str,
+        payload: &serde_json::Value,
+        token: Option<&str>,
+    ) -> ScrobblerResult<()> {
+        self.retry
+            .run(|| async {
+                let mut request = self.client.post(url).json(payload);
+                if let Some(token) = token {
+                    request = request.header("Authorization", format!("Bearer {}", token));
+                }
+
+                let res = request.send().await.map_err(|e| {
+                    let error = ScrobblerError::Network {
+                        message: redact_error(&e),
+                    };
+                    if e.is_connect() || e.is_timeout() {
+                        RetryError::transient(error)
+                    } else {
+                        RetryError::Permanent(error)
+                    }
+                })?;
+
+                let status = res.status();
+                let error = match status {
+                    StatusCode::OK
+                    | StatusCode::CREATED
+                    | StatusCode::ACCEPTED
+                    | StatusCode::NO_CONTENT => return Ok(()),
+                    StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+                        ScrobblerError::Authentication {
+                            message: "Invalid API token".into(),
+                        }
+                    }
+                    StatusCode::TOO_MANY_REQUESTS => ScrobblerError::RateLimited {
+                        message: "Rate limited".into(),
+                    },
+                    s => ScrobblerError::Other {
+                        message: format!("API error: {}", s),
+                    },
+                };
+                if is_transient_status(status.as_u16()) {
+                    let retry_after = res
+                        .headers()
+                        .get(reqwest::header::RETRY_AFTER)
+                        .and_then(|v| v.to_str().ok())
+                        .and_then(parse_retry_after);
+                    Err(RetryError::Transient { error, retry_after })
+                } else {
+                    Err(RetryError::Permanent(error))
+                }
+            })
+            .await
     }
 }
 
@@ -75,6 +146,8 @@ impl Scrobbler for MelodeeScrobbler {
 
         // We only scrobble on Started (NowPlaying) or Ended (Submission)
         let scrobble_type = match event.state {
+            // A replayed now-playing update is stale
+            PlaybackState::Started if event.is_replayed() => return Ok(()),
             PlaybackState::Started => "NowPlaying",
             PlaybackState::Ended => "Submission",
             _ => return Ok(()), // Ignore other states for now
@@ -109,39 +182,22 @@ impl Scrobbler for MelodeeScrobbler {
             "songId": event.track.id.0, // Assuming TrackId wraps the UUID
             "playerName": event.player_name,
             "scrobbleType": scrobble_type,
-            "timestamp": std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap_or_default()
-                .as_secs_f64(),
+            "timestamp": event.occurred_at_or_now() as f64,
             "playedDuration": event.progress.position_seconds as f64
         });
 
-        let mut request = self.client.post(&url).json(&payload);
-
-        if let Some(token) = self.get_token() {
-            request = request.header("Authorization", format!("Bearer {}", token));
-        }
-
-        let res = request.send().await.map_err(|e| ScrobblerError::Network {
-            message: e.to_string(),
-        })?;
-
-        match res.status() {
-            StatusCode::OK
-            | StatusCode::CREATED
-            | StatusCode::ACCEPTED
-            | StatusCode::NO_CONTENT => Ok(()),
-            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
-                Err(ScrobblerError::Authentication {
-                    message: "Invalid API token".into(),
-                })
+        let token = self.auth.access_token();
+        match self.post(&url, &payload, token.as_deref()).await {
+            // The token may have expired: refresh it and try once more.
+            Err(ScrobblerError::Authentication { .. }) => {
+                let fresh = self.auth.refresh(token.as_deref()).await.map_err(|e| {
+                    ScrobblerError::Authentication {
+                        message: redact_error(e),
+                    }
+                })?;
+                self.post(&url, &payload, Some(&fresh)).await
             }
-            StatusCode::TOO_MANY_REQUESTS => Err(ScrobblerError::RateLimited {
-                message: "Rate limited".into(),
-            }),
-            s => Err(ScrobblerError::Other {
-                message: format!("API error: {}", s),
-            }),
+            other => other,
         }
     }
 }
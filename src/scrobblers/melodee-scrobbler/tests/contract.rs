@@ -1,8 +1,11 @@
+use melodee_provider::{MelodeeAuth, MelodeeConfig, MelodeeProvider};
 use melodee_scrobbler::MelodeeScrobbler;
+use std::time::Duration;
 use tunez_core::models::{Track, TrackId};
 use tunez_core::scrobbler::{run_scrobbler_contract, ScrobblerContractSpec};
-use tunez_core::{PlaybackProgress, PlaybackState, ScrobbleEvent};
-use wiremock::matchers::{method, path};
+use tunez_core::secrets::CredentialStore;
+use tunez_core::{PlaybackProgress, PlaybackState, RetryPolicy, ScrobbleEvent, Scrobbler};
+use wiremock::matchers::{header, method, path};
 use wiremock::{Mock, MockServer, ResponseTemplate};
 
 fn sample_track() -> Track {
@@ -14,6 +17,11 @@ fn sample_track() -> Track {
         album: Some("Album".into()),
         duration_seconds: Some(180),
         track_number: Some(1),
+        disc_number: None,
+        year: None,
+        genre: None,
+        musicbrainz_id: None,
+        replay_gain: Default::default(),
     }
 }
 
@@ -27,6 +35,7 @@ fn sample_event(state: PlaybackState, position: u64) -> ScrobbleEvent {
         state,
         player_name: "Tunez".into(),
         device_id: Some("device-1".into()),
+        occurred_at: Some(ScrobbleEvent::now()),
     }
 }
 
@@ -42,7 +51,7 @@ async fn melodee_scrobbler_contract() {
         .mount(&mock_server)
         .await;
 
-    let scrobbler = MelodeeScrobbler::new(&mock_server.uri(), None, Some("test-token".into()));
+    let scrobbler = MelodeeScrobbler::new(mock_server.uri(), None, Some("test-token".into()));
 
     // Events to submit
     let events = vec![
@@ -61,3 +70,121 @@ async fn melodee_scrobbler_contract() {
         panic!("Contract test failed: {}", e);
     }
 }
+
+#[tokio::test]
+async fn transient_failures_are_retried() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/api/v1/scrobble"))
+        .respond_with(ResponseTemplate::new(502))
+        .up_to_n_times(2)
+        .expect(2)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/api/v1/scrobble"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let scrobbler = MelodeeScrobbler::new(mock_server.uri(), None, Some("test-token".into()))
+        .with_retry_policy(RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(10),
+        });
+    let mut event = sample_event(PlaybackState::Ended, 180);
+    event.track.provider_id = "melodee".into();
+
+    scrobbler
+        .submit(&event)
+        .await
+        .expect("third attempt succeeds");
+}
+
+#[tokio::test]
+async fn expired_token_is_refreshed_and_scrobble_retried() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/api/v1/scrobble"))
+        .and(header("authorization", "Bearer old"))
+        .respond_with(ResponseTemplate::new(401))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/api/v1/auth/refresh-token"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(serde_json::json!({ "token": "new" })),
+        )
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/api/v1/scrobble"))
+        .and(header("authorization", "Bearer new"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let auth = MelodeeAuth::new(reqwest::Client::new(), mock_server.uri(), None)
+        .with_credential_store(CredentialStore::with_service("tunez-test"))
+        .with_tokens(Some("old".into()), Some("refresh-1".into()));
+    let scrobbler = MelodeeScrobbler::new(mock_server.uri(), None, None).with_auth(auth);
+    let mut event = sample_event(PlaybackState::Ended, 180);
+    event.track.provider_id = "melodee".into();
+
+    scrobbler
+        .submit(&event)
+        .await
+        .expect("retried after refresh");
+}
+
+#[tokio::test]
+async fn refresh_is_shared_with_the_provider() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/api/v1/scrobble"))
+        .and(header("authorization", "Bearer old"))
+        .respond_with(ResponseTemplate::new(401))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/api/v1/auth/refresh-token"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(serde_json::json!({ "token": "new" })),
+        )
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/api/v1/scrobble"))
+        .and(header("authorization", "Bearer new"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&mock_server)
+        .await;
+
+    let auth = MelodeeAuth::new(reqwest::Client::new(), mock_server.uri(), None)
+        .with_credential_store(CredentialStore::with_service("tunez-test"))
+        .with_tokens(Some("old".into()), Some("refresh-1".into()));
+    let provider = MelodeeProvider::new(MelodeeConfig::new(mock_server.uri(), None))
+        .expect("provider")
+        .with_auth(auth);
+    let scrobbler = MelodeeScrobbler::new(mock_server.uri(), None, None)
+        .with_timeout(Duration::from_secs(5))
+        .with_auth(provider.auth());
+    let mut event = sample_event(PlaybackState::Ended, 180);
+    event.track.provider_id = "melodee".into();
+
+    scrobbler
+        .submit(&event)
+        .await
+        .expect("retried after refresh");
+    scrobbler
+        .submit(&event)
+        .await
+        .expect("second scrobble uses the new token");
+
+    assert_eq!(provider.auth().access_token().as_deref(), Some("new"));
+}
@@ -1,7 +1,7 @@
 use std::{
     path::PathBuf,
     sync::{
-        atomic::{AtomicBool, Ordering},
+        atomic::{AtomicBool, AtomicU64, Ordering},
         Arc, Mutex,
     },
     thread::{self, JoinHandle},
@@ -9,10 +9,16 @@ use std::{
 };
 
 use thiserror::Error;
+use tunez_core::StreamUrl;
 
-/// Type alias for audio sample callback
+/// Receives the samples being played, mixed down to mono, at
+/// [`AudioHandle::sample_rate`].
 pub type SampleCallback = Arc<dyn Fn(&[f32]) + Send + Sync>;
 
+/// Where a handle keeps its sample callback, shared with the thread that
+/// produces the samples so a callback set after playback starts is used.
+pub(crate) type SampleCallbackSlot = Arc<Mutex<Option<SampleCallback>>>;
+
 /// Audio playback errors.
 #[derive(Debug, Error)]
 pub enum AudioError {
@@ -37,10 +43,79 @@ pub enum AudioSource {
     File(PathBuf),
 }
 
+impl AudioSource {
+    /// Split a `#t=start[,end]` fragment (in seconds, as in Media Fragments)
+    /// off a URL: the source to open and the part of it to play. Tracks a cue
+    /// sheet cuts out of one file are addressed this way. File paths are left
+    /// whole, since a `#` there is part of a file name.
+    pub fn time_range(&self) -> (AudioSource, TimeRange) {
+        let AudioSource::Url(url) = self else {
+            return (self.clone(), TimeRange::default());
+        };
+        let parsed = url.split_once('#').and_then(|(base, fragment)| {
+            let fragment = fragment.strip_prefix("t=")?;
+            let (start, end) = match fragment.split_once(',') {
+                Some((start, end)) => (start, Some(end)),
+                None => (fragment, None),
+            };
+            let seconds = |s: &str| {
+                s.parse::<f64>()
+                    .ok()
+                    .and_then(|s| Duration::try_from_secs_f64(s).ok())
+            };
+            let range = TimeRange {
+                start: seconds(start)?,
+                end: match end {
+                    Some(end) => Some(seconds(end)?),
+                    None => None,
+                },
+            };
+            Some((AudioSource::Url(base.to_string()), range))
+        });
+        parsed.unwrap_or_else(|| (self.clone(), TimeRange::default()))
+    }
+}
+
+impl From<&StreamUrl> for AudioSource {
+    /// Local files (`file://` URLs and bare paths) are opened directly;
+    /// everything else is left for the engine to fetch, or to reject when it
+    /// isn't HTTP.
+    fn from(url: &StreamUrl) -> Self {
+        match url.file_path() {
+            // A fragment stays on the URL for `time_range` to split off.
+            Some(path) if url.fragment().is_none() => AudioSource::File(path),
+            _ => AudioSource::Url(url.0.clone()),
+        }
+    }
+}
+
+/// The part of a source to play: from `start` until `end`, or until the
+/// source runs out.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TimeRange {
+    pub start: Duration,
+    pub end: Option<Duration>,
+}
+
+/// A track decoded ahead of time by [`AudioEngine::prepare`], ready to follow
+/// the playing one through [`AudioHandle::queue_next`].
+#[derive(Debug)]
+#[cfg_attr(not(feature = "cpal-backend"), allow(dead_code))]
+pub struct PreparedAudio {
+    /// Interleaved at the source's own rate and channel count.
+    pub(crate) samples: Vec<f32>,
+    pub(crate) sample_rate: u32,
+    pub(crate) channels: usize,
+    pub(crate) gain: f32,
+}
+
 /// Runtime playback state for a handle.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AudioState {
     Idle,
+    /// Started, but no samples have been decoded yet (a network stream
+    /// still downloading). Moves on to `Playing` or `Error`.
+    Buffering,
     Playing,
     Paused,
     Completed,
@@ -50,9 +125,27 @@ pub enum AudioState {
 
 /// Control interface for backends to implement
 pub trait AudioControl {
-    fn pause(&self) -> AudioResult<()> { Ok(()) }
-    fn resume(&self) -> AudioResult<()> { Ok(()) }
-    fn seek(&self, _position: Duration) -> AudioResult<()> { Ok(()) }
+    fn pause(&self) -> AudioResult<()> {
+        Ok(())
+    }
+    fn resume(&self) -> AudioResult<()> {
+        Ok(())
+    }
+    fn seek(&self, _position: Duration) -> AudioResult<()> {
+        Ok(())
+    }
+    fn queue_next(&self, _next: PreparedAudio) -> AudioResult<()> {
+        Err(gapless_unsupported())
+    }
+    fn clear_next(&self) {}
+    /// How much of the decoded audio is left to play, if the backend knows.
+    fn remaining(&self) -> Option<Duration> {
+        None
+    }
+}
+
+fn gapless_unsupported() -> AudioError {
+    AudioError::Other("gapless playback is not supported".into())
 }
 
 /// Handle representing an in-flight playback operation.
@@ -69,11 +162,13 @@ pub struct AudioHandle {
     #[allow(dead_code)]
     local_keepalive: Option<Arc<Mutex<Box<dyn std::any::Any>>>>,
     /// Optional callback for streaming audio samples to visualization
-    sample_callback: Option<SampleCallback>,
+    sample_callback: SampleCallbackSlot,
     /// Number of frames (independent samples) played so far
     frames_played: Arc<std::sync::atomic::AtomicU64>,
     /// Sample rate (frames per second)
     sample_rate: u32,
+    /// Times playback moved on to a source queued with `queue_next`.
+    handoffs: Arc<AtomicU64>,
     /// Control hook for backend-specific logic
     control: Option<Arc<dyn AudioControl>>,
 }
@@ -89,7 +184,14 @@ impl std::fmt::Debug for AudioHandle {
 impl AudioHandle {
     /// Set a callback to receive audio samples for visualization
     pub fn set_sample_callback(&mut self, callback: SampleCallback) {
-        self.sample_callback = Some(callback);
+        *self.sample_callback.lock().unwrap() = Some(callback);
+    }
+
+    /// Keep the sample callback in `slot`, which the backend's playback
+    /// thread reads.
+    #[allow(dead_code)]
+    pub(crate) fn share_sample_callback(&mut self, slot: SampleCallbackSlot) {
+        self.sample_callback = slot;
     }
 
     pub fn set_control(&mut self, control: Arc<dyn AudioControl>) {
@@ -128,18 +230,68 @@ impl AudioHandle {
         Ok(())
     }
 
-    pub(crate) fn spawn_simulated(duration: Duration) -> Self {
-        let state = Arc::new(Mutex::new(AudioState::Playing));
+    /// Play `next` straight after the current source ends, without a gap,
+    /// instead of completing. Replaces anything queued before.
+    pub fn queue_next(&self, next: PreparedAudio) -> AudioResult<()> {
+        match &self.control {
+            Some(control) => control.queue_next(next),
+            None => Err(gapless_unsupported()),
+        }
+    }
+
+    /// Drop the source queued with [`queue_next`](Self::queue_next), if it
+    /// hasn't started yet.
+    pub fn clear_next(&self) {
+        if let Some(control) = &self.control {
+            control.clear_next();
+        }
+    }
+
+    /// How much of the playing source's decoded audio is left, for backends
+    /// that hold it in a buffer. `None` while a stream is still buffering.
+    pub fn remaining(&self) -> Option<Duration> {
+        self.control.as_ref()?.remaining()
+    }
+
+    /// How many times playback has moved on to a queued source. The position
+    /// restarts from zero at each one.
+    pub fn handoffs(&self) -> u64 {
+        self.handoffs.load(Ordering::SeqCst)
+    }
+
+    /// A handle that "plays" silence for `duration` and then completes, for
+    /// the null engine and tests.
+    pub fn spawn_simulated(duration: Duration) -> Self {
+        Self::spawn_simulated_stream(Some(Duration::ZERO), duration)
+    }
+
+    /// Like [`spawn_simulated`](Self::spawn_simulated), but `Buffering`
+    /// until `first_samples` has passed, or for good when it's `None`.
+    pub fn spawn_simulated_stream(first_samples: Option<Duration>, duration: Duration) -> Self {
+        let initial = match first_samples {
+            Some(Duration::ZERO) => AudioState::Playing,
+            _ => AudioState::Buffering,
+        };
+        let state = Arc::new(Mutex::new(initial));
         let stop_flag = Arc::new(AtomicBool::new(false));
         let state_clone = state.clone();
         let stop_clone = stop_flag.clone();
 
         let join = thread::spawn(move || {
             let tick = Duration::from_millis(50);
+            let started = std::time::Instant::now();
             let mut elapsed = Duration::ZERO;
             while elapsed < duration && !stop_clone.load(Ordering::SeqCst) {
                 {
-                    let guard = state_clone.lock().unwrap();
+                    let mut guard = state_clone.lock().unwrap();
+                    if *guard == AudioState::Buffering {
+                        if first_samples.is_none_or(|after| started.elapsed() < after) {
+                            drop(guard);
+                            thread::sleep(Duration::from_millis(5));
+                            continue;
+                        }
+                        *guard = AudioState::Playing;
+                    }
                     if *guard == AudioState::Paused {
                         thread::sleep(tick);
                         continue;
@@ -165,9 +317,10 @@ impl AudioHandle {
             join: Some(join),
             keepalive: None,
             local_keepalive: None,
-            sample_callback: None,
+            sample_callback: SampleCallbackSlot::default(),
             frames_played: Arc::new(std::sync::atomic::AtomicU64::new(0)),
             sample_rate: 0,
+            handoffs: Arc::default(),
             control: Some(Arc::new(MockControl)),
         }
     }
@@ -180,6 +333,7 @@ impl AudioHandle {
         keepalive: Arc<Mutex<Box<dyn std::any::Any>>>,
         frames_played: Arc<std::sync::atomic::AtomicU64>,
         sample_rate: u32,
+        handoffs: Arc<AtomicU64>,
     ) -> Self {
         Self {
             state,
@@ -187,9 +341,10 @@ impl AudioHandle {
             join: Some(join),
             keepalive: None,
             local_keepalive: Some(keepalive),
-            sample_callback: None,
+            sample_callback: SampleCallbackSlot::default(),
             frames_played,
             sample_rate,
+            handoffs,
             control: None,
         }
     }
@@ -198,6 +353,18 @@ impl AudioHandle {
         *self.state.lock().unwrap()
     }
 
+    /// Whether the source played through to its end. Playback threads only
+    /// hold the state lock to flip it, so this never waits on decoding.
+    pub fn is_finished(&self) -> bool {
+        self.state() == AudioState::Completed
+    }
+
+    /// Whether playback stopped on an error, such as the output device
+    /// going away; not the same as finishing.
+    pub fn is_failed(&self) -> bool {
+        self.state() == AudioState::Error
+    }
+
     pub fn stop(mut self) {
         self.stop_flag.store(true, Ordering::SeqCst);
         if let Some(join) = self.join.take() {
@@ -210,11 +377,17 @@ impl AudioHandle {
 
     /// Call the sample callback if set
     pub fn send_samples(&self, samples: &[f32]) {
-        if let Some(callback) = &self.sample_callback {
+        if let Some(callback) = self.sample_callback.lock().unwrap().as_ref() {
             callback(samples);
         }
     }
 
+    /// Rate of the samples sent to the output, and to the sample callback.
+    /// Zero for simulated playback.
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
     /// Get current playback position
     pub fn position(&self) -> Duration {
         let frames = self.frames_played.load(Ordering::SeqCst);
@@ -229,6 +402,31 @@ impl AudioHandle {
 /// Audio backend interface.
 pub trait AudioEngine: Send + Sync {
     fn play(&self, source: AudioSource) -> AudioResult<AudioHandle>;
+
+    /// Play with output samples scaled by the linear `gain` (e.g. from
+    /// ReplayGain). Backends without volume control ignore it.
+    fn play_with_gain(&self, source: AudioSource, gain: f32) -> AudioResult<AudioHandle> {
+        let _ = gain;
+        self.play(source)
+    }
+
+    /// Decode `source` ahead of time so it can follow the playing track
+    /// without a gap. Backends that can't do this return an error and tracks
+    /// start one after another as usual.
+    fn prepare(&self, source: AudioSource, gain: f32) -> AudioResult<PreparedAudio> {
+        let _ = (source, gain);
+        Err(gapless_unsupported())
+    }
+}
+
+/// Scale `samples` by `gain`, hard-limiting the result to full scale.
+pub fn apply_gain(samples: &mut [f32], gain: f32) {
+    if gain == 1.0 {
+        return;
+    }
+    for sample in samples {
+        *sample = (*sample * gain).clamp(-1.0, 1.0);
+    }
 }
 
 /// No-op audio engine used for tests and headless environments.
@@ -242,10 +440,199 @@ impl AudioEngine for NullAudioEngine {
     }
 }
 
+/// Audio engine that plays nothing but hands a generated tone to the sample
+/// callback at the pace of real playback, so the visualizer animates in
+/// headless demos and tests. Positions, pausing and seeking behave as they
+/// would on a device; each source "plays" for the same fixed length.
+#[derive(Debug, Clone)]
+pub struct SyntheticAudioEngine {
+    duration: Duration,
+    tone_hz: f32,
+}
+
+impl SyntheticAudioEngine {
+    /// Rate of the generated samples, which are mono.
+    pub const SAMPLE_RATE: u32 = 44_100;
+    /// Samples are handed over in chunks of this length.
+    const CHUNK: Duration = Duration::from_millis(20);
+
+    /// Play every source as `duration` of a 440 Hz tone.
+    pub fn new(duration: Duration) -> Self {
+        Self {
+            duration,
+            tone_hz: 440.0,
+        }
+    }
+
+    /// Generate a tone at `hz` instead.
+    pub fn with_tone(mut self, hz: f32) -> Self {
+        self.tone_hz = hz;
+        self
+    }
+
+    fn spawn(&self) -> AudioHandle {
+        let rate = Self::SAMPLE_RATE;
+        let total_frames = (self.duration.as_secs_f64() * f64::from(rate)) as u64;
+        let chunk_frames = (Self::CHUNK.as_secs_f64() * f64::from(rate)) as u64;
+        let step = std::f32::consts::TAU * self.tone_hz / rate as f32;
+
+        let state = Arc::new(Mutex::new(AudioState::Playing));
+        let stop_flag = Arc::new(AtomicBool::new(false));
+        let sample_callback = SampleCallbackSlot::default();
+        let frames_played = Arc::new(AtomicU64::new(0));
+        let (state_clone, stop_clone, callback_clone, frames_clone) = (
+            state.clone(),
+            stop_flag.clone(),
+            sample_callback.clone(),
+            frames_played.clone(),
+        );
+
+        let join = thread::spawn(move || {
+            let mut chunk = Vec::with_capacity(chunk_frames as usize);
+            while !stop_clone.load(Ordering::SeqCst) {
+                if *state_clone.lock().unwrap() == AudioState::Paused {
+                    thread::sleep(Self::CHUNK);
+                    continue;
+                }
+                let start = frames_clone.load(Ordering::SeqCst);
+                if start >= total_frames {
+                    break;
+                }
+                let frames = chunk_frames.min(total_frames - start);
+                chunk.clear();
+                // Wrapped to one period so the phase stays precise.
+                chunk.extend(
+                    (start..start + frames)
+                        .map(|frame| 0.5 * ((frame as f32 * step) % std::f32::consts::TAU).sin()),
+                );
+                if let Some(callback) = callback_clone.lock().unwrap().as_ref() {
+                    callback(&chunk);
+                }
+                // A seek in the meantime wins over this chunk's progress.
+                let _ = frames_clone.compare_exchange(
+                    start,
+                    start + frames,
+                    Ordering::SeqCst,
+                    Ordering::SeqCst,
+                );
+                thread::sleep(Self::CHUNK);
+            }
+            let mut guard = state_clone.lock().unwrap();
+            *guard = if stop_clone.load(Ordering::SeqCst) {
+                AudioState::Stopped
+            } else {
+                AudioState::Completed
+            };
+        });
+
+        struct SyntheticControl {
+            frames_played: Arc<AtomicU64>,
+            total_frames: u64,
+        }
+        impl AudioControl for SyntheticControl {
+            fn seek(&self, position: Duration) -> AudioResult<()> {
+                let frame = position.as_secs_f64() * f64::from(SyntheticAudioEngine::SAMPLE_RATE);
+                self.frames_played
+                    .store((frame as u64).min(self.total_frames), Ordering::SeqCst);
+                Ok(())
+            }
+        }
+
+        AudioHandle {
+            state,
+            stop_flag,
+            join: Some(join),
+            keepalive: None,
+            local_keepalive: None,
+            sample_callback,
+            frames_played: frames_played.clone(),
+            sample_rate: rate,
+            handoffs: Arc::default(),
+            control: Some(Arc::new(SyntheticControl {
+                frames_played,
+                total_frames,
+            })),
+        }
+    }
+}
+
+impl AudioEngine for SyntheticAudioEngine {
+    fn play(&self, _source: AudioSource) -> AudioResult<AudioHandle> {
+        Ok(self.spawn())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn apply_gain_scales_and_limits() {
+        let mut samples = [0.5, -0.25, 0.9];
+        apply_gain(&mut samples, 2.0);
+        assert_eq!(samples, [1.0, -0.5, 1.0]);
+    }
+
+    #[test]
+    fn time_fragments_select_part_of_a_url() {
+        let (source, range) =
+            AudioSource::Url("file:///music/album.flac#t=245.5,300".into()).time_range();
+        assert!(matches!(source, AudioSource::Url(url) if url == "file:///music/album.flac"));
+        assert_eq!(
+            range,
+            TimeRange {
+                start: Duration::from_millis(245_500),
+                end: Some(Duration::from_secs(300)),
+            }
+        );
+
+        let (_, range) = AudioSource::Url("file:///a.flac#t=12".into()).time_range();
+        assert_eq!(range.start, Duration::from_secs(12));
+        assert_eq!(range.end, None);
+
+        // Anything else is part of the name.
+        for url in ["file:///#1 Hits/a.flac", "http://h/a.mp3#t=soon"] {
+            let (source, range) = AudioSource::Url(url.into()).time_range();
+            assert!(matches!(source, AudioSource::Url(u) if u == url));
+            assert_eq!(range, TimeRange::default());
+        }
+    }
+
+    #[test]
+    fn file_paths_are_never_cut() {
+        let path = PathBuf::from("/music/live#t=245.5");
+        let (source, range) = AudioSource::File(path.clone()).time_range();
+        assert!(matches!(source, AudioSource::File(p) if p == path));
+        assert_eq!(range, TimeRange::default());
+    }
+
+    #[test]
+    fn stream_urls_map_to_files_or_urls() {
+        let source = |url: &str| AudioSource::from(&StreamUrl::new(url));
+        assert!(matches!(
+            source("file:///music/a.flac"),
+            AudioSource::File(path) if path == std::path::Path::new("/music/a.flac")
+        ));
+        assert!(matches!(
+            source("/music/a b.flac"),
+            AudioSource::File(path) if path == std::path::Path::new("/music/a b.flac")
+        ));
+        assert!(matches!(
+            source("file:///music/%231%20live%23t=10.flac"),
+            AudioSource::File(path) if path == std::path::Path::new("/music/#1 live#t=10.flac")
+        ));
+        // The cue fragment survives for `time_range`.
+        assert!(matches!(
+            source("file:///music/live.flac#t=10"),
+            AudioSource::Url(url) if url == "file:///music/live.flac#t=10"
+        ));
+        for url in ["https://host/stream/42?token=x", "HTTP://host/a.mp3"] {
+            assert!(matches!(source(url), AudioSource::Url(u) if u == url));
+        }
+        // Not playable, but passed on for the engine to report.
+        assert!(matches!(source("42"), AudioSource::Url(u) if u == "42"));
+    }
+
     #[test]
     fn null_engine_completes() {
         let engine = NullAudioEngine;
@@ -256,6 +643,79 @@ mod tests {
         assert_eq!(handle.state(), AudioState::Completed);
     }
 
+    #[test]
+    fn simulated_streams_buffer_until_their_first_samples() {
+        let handle = AudioHandle::spawn_simulated_stream(
+            Some(Duration::from_millis(100)),
+            Duration::from_secs(5),
+        );
+        assert_eq!(handle.state(), AudioState::Buffering);
+        thread::sleep(Duration::from_millis(200));
+        assert_eq!(handle.state(), AudioState::Playing);
+        handle.stop();
+
+        let stalled = AudioHandle::spawn_simulated_stream(None, Duration::from_secs(5));
+        thread::sleep(Duration::from_millis(100));
+        assert_eq!(stalled.state(), AudioState::Buffering);
+        stalled.stop();
+    }
+
+    #[test]
+    fn synthetic_engine_sends_a_tone_while_playing() {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let sink = received.clone();
+        let mut handle = SyntheticAudioEngine::new(Duration::from_millis(200))
+            .play(AudioSource::Url("demo".into()))
+            .unwrap();
+        // Set after playback started, as the player does.
+        handle.set_sample_callback(Arc::new(move |samples: &[f32]| {
+            sink.lock().unwrap().extend_from_slice(samples);
+        }));
+        assert_eq!(handle.sample_rate(), SyntheticAudioEngine::SAMPLE_RATE);
+
+        thread::sleep(Duration::from_millis(400));
+        assert_eq!(handle.state(), AudioState::Completed);
+        assert!(handle.position() > Duration::from_millis(150));
+        let received = received.lock().unwrap();
+        assert!(!received.is_empty());
+        assert!(received.iter().any(|s| s.abs() > 0.25));
+        assert!(received.iter().all(|s| s.abs() <= 0.5));
+        // One mono sample per frame: 200 ms at 44.1 kHz.
+        assert!(received.len() <= 8_820, "{}", received.len());
+    }
+
+    #[test]
+    fn short_sources_finish_once_their_samples_run_out() {
+        let handle = SyntheticAudioEngine::new(Duration::from_millis(60))
+            .play(AudioSource::Url("demo".into()))
+            .unwrap();
+        assert!(!handle.is_finished());
+        thread::sleep(Duration::from_millis(250));
+        assert!(handle.is_finished());
+        assert!(!handle.is_failed());
+
+        let failed = AudioHandle::spawn_simulated(Duration::from_secs(5));
+        *failed.state.lock().unwrap() = AudioState::Error;
+        assert!(failed.is_failed());
+        assert!(!failed.is_finished());
+        failed.stop();
+    }
+
+    #[test]
+    fn synthetic_engine_pauses_and_seeks() {
+        let handle = SyntheticAudioEngine::new(Duration::from_secs(30))
+            .play(AudioSource::Url("demo".into()))
+            .unwrap();
+        handle.seek(Duration::from_secs(10)).unwrap();
+        handle.pause().unwrap();
+        thread::sleep(Duration::from_millis(60));
+        let paused_at = handle.position();
+        assert!(paused_at >= Duration::from_secs(10), "{paused_at:?}");
+        thread::sleep(Duration::from_millis(100));
+        assert_eq!(handle.position(), paused_at);
+        handle.stop();
+    }
+
     #[test]
     fn handle_can_stop_early() {
         let engine = NullAudioEngine;
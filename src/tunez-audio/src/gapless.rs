@@ -0,0 +1,127 @@
+//! Output buffers that run one track straight into the next.
+
+use crate::engine::apply_gain;
+
+/// Interleaved output samples for the playing track and, once queued, the
+/// track after it. Filling past the end of the first carries on into the
+/// second within the same output chunk, so no silence is played between them.
+#[derive(Debug, Default)]
+pub(crate) struct TrackBuffers {
+    current: Vec<f32>,
+    gain: f32,
+    /// Read position into `current`.
+    cursor: usize,
+    next: Option<(Vec<f32>, f32)>,
+    /// Times playback moved on to a queued track.
+    handoffs: u64,
+}
+
+impl TrackBuffers {
+    pub(crate) fn new(samples: Vec<f32>, gain: f32) -> Self {
+        Self {
+            current: samples,
+            gain,
+            ..Default::default()
+        }
+    }
+
+    /// Play `samples` once the current track runs out, replacing any track
+    /// queued before.
+    pub(crate) fn queue_next(&mut self, samples: Vec<f32>, gain: f32) {
+        self.next = Some((samples, gain));
+    }
+
+    pub(crate) fn clear_next(&mut self) {
+        self.next = None;
+    }
+
+    /// Copy the next samples into `out` with their track's gain applied,
+    /// padding with silence once everything has played.
+    pub(crate) fn fill(&mut self, out: &mut [f32]) {
+        let mut written = 0;
+        while written < out.len() {
+            if self.cursor >= self.current.len() {
+                let Some((samples, gain)) = self.next.take() else {
+                    break;
+                };
+                self.current = samples;
+                self.gain = gain;
+                self.cursor = 0;
+                self.handoffs += 1;
+                continue;
+            }
+            let n = (out.len() - written).min(self.current.len() - self.cursor);
+            let chunk = &mut out[written..written + n];
+            chunk.copy_from_slice(&self.current[self.cursor..self.cursor + n]);
+            apply_gain(chunk, self.gain);
+            self.cursor += n;
+            written += n;
+        }
+        out[written..].fill(0.0);
+    }
+
+    /// Move the read position within the current track, clamped to its end.
+    pub(crate) fn seek(&mut self, sample: usize) {
+        self.cursor = sample.min(self.current.len());
+    }
+
+    pub(crate) fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Samples of the current track still to play.
+    pub(crate) fn remaining(&self) -> usize {
+        self.current.len().saturating_sub(self.cursor)
+    }
+
+    /// Whether the current track has played out with nothing queued after it.
+    pub(crate) fn is_finished(&self) -> bool {
+        self.cursor >= self.current.len() && self.next.is_none()
+    }
+
+    pub(crate) fn handoffs(&self) -> u64 {
+        self.handoffs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn queued_track_follows_without_silence() {
+        let mut buffers = TrackBuffers::new(vec![0.1; 6], 1.0);
+        let mut out = [9.0; 4];
+        buffers.fill(&mut out);
+        assert_eq!(out, [0.1; 4]);
+
+        // Queued before the current track completes: the chunk that crosses
+        // the boundary carries on into it, at its own gain.
+        buffers.queue_next(vec![0.2; 6], 2.0);
+        assert!(!buffers.is_finished());
+        buffers.fill(&mut out);
+        assert_eq!(out, [0.1, 0.1, 0.4, 0.4]);
+        assert_eq!(buffers.handoffs(), 1);
+        assert_eq!(buffers.cursor(), 2);
+        assert_eq!(buffers.remaining(), 4);
+
+        // Nothing queued after it: the rest is silence.
+        let mut out = [9.0; 6];
+        buffers.fill(&mut out);
+        assert_eq!(out, [0.4, 0.4, 0.4, 0.4, 0.0, 0.0]);
+        assert!(buffers.is_finished());
+        assert_eq!(buffers.handoffs(), 1);
+    }
+
+    #[test]
+    fn cleared_track_is_not_played() {
+        let mut buffers = TrackBuffers::new(vec![0.1; 2], 1.0);
+        buffers.queue_next(vec![0.2; 2], 1.0);
+        buffers.clear_next();
+        let mut out = [9.0; 4];
+        buffers.fill(&mut out);
+        assert_eq!(out, [0.1, 0.1, 0.0, 0.0]);
+        assert!(buffers.is_finished());
+        assert_eq!(buffers.handoffs(), 0);
+    }
+}
@@ -1,10 +1,12 @@
 mod engine;
 #[cfg(feature = "cpal-backend")]
+mod gapless;
+#[cfg(feature = "cpal-backend")]
 mod real;
 
 pub use engine::{
-    AudioEngine, AudioError, AudioHandle, AudioResult, AudioSource, AudioState, NullAudioEngine,
-    SampleCallback,
+    apply_gain, AudioControl, AudioEngine, AudioError, AudioHandle, AudioResult, AudioSource,
+    AudioState, NullAudioEngine, PreparedAudio, SampleCallback, SyntheticAudioEngine, TimeRange,
 };
 #[cfg(feature = "cpal-backend")]
-pub use real::CpalAudioEngine;
+pub use real::{list_output_devices, CpalAudioEngine};
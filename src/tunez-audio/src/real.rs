@@ -1,38 +1,118 @@
 use std::{
     fs::File,
-    path::{Path, PathBuf},
+    path::PathBuf,
     sync::{
-        atomic::{AtomicBool, Ordering},
+        atomic::{AtomicBool, AtomicU64, Ordering},
         Arc, Mutex,
     },
     thread,
+    time::Duration,
 };
 
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 use symphonia::{
     core::{
-        audio::SampleBuffer, codecs::DecoderOptions, formats::FormatOptions, meta::MetadataOptions,
+        audio::SampleBuffer,
+        codecs::DecoderOptions,
+        formats::FormatOptions,
+        io::{MediaSource, MediaSourceStream, ReadOnlySource},
+        meta::MetadataOptions,
         probe::Hint,
     },
     default,
 };
 
-use crate::engine::SampleCallback;
-use crate::{AudioEngine, AudioError, AudioHandle, AudioResult, AudioSource, AudioState};
+use crate::engine::SampleCallbackSlot;
+use crate::gapless::TrackBuffers;
+use crate::{
+    AudioEngine, AudioError, AudioHandle, AudioResult, AudioSource, AudioState, PreparedAudio,
+    TimeRange,
+};
+use tunez_core::{ElapsedGuard, StreamLocation, StreamUrl};
+
+/// How long to wait for a stream server to accept the connection.
+const HTTP_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long a single read from a stream server may stall.
+const HTTP_READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Audio engine backed by cpal + symphonia (local files and HTTP(S) streams).
+#[derive(Debug, Default, Clone)]
+pub struct CpalAudioEngine {
+    /// Output device name; the system default when unset.
+    device: Option<String>,
+}
+
+/// Names of the output devices the default host can play to. The default
+/// device comes first when the host doesn't enumerate it (ALSA's `default`).
+pub fn list_output_devices() -> Vec<String> {
+    let host = cpal::default_host();
+    let mut names: Vec<String> = match host.output_devices() {
+        Ok(devices) => devices.filter_map(|d| d.name().ok()).collect(),
+        Err(e) => {
+            tracing::warn!("Cannot list output devices: {}", e);
+            Vec::new()
+        }
+    };
+    if let Some(default) = host.default_output_device().and_then(|d| d.name().ok()) {
+        if !names.contains(&default) {
+            names.insert(0, default);
+        }
+    }
+    names
+}
 
-/// Audio engine backed by cpal + symphonia (local files only).
-#[derive(Debug, Default, Clone, Copy)]
-pub struct CpalAudioEngine;
+/// Where the encoded audio for a source comes from.
+#[derive(Debug, PartialEq, Eq)]
+enum ResolvedSource {
+    File(PathBuf),
+    Http(String),
+}
 
 impl CpalAudioEngine {
-    fn resolve_path(source: AudioSource) -> AudioResult<PathBuf> {
+    /// Play to the output device called `name`, or the default device when
+    /// it isn't connected.
+    pub fn with_device(name: impl Into<String>) -> Self {
+        Self {
+            device: Some(name.into()),
+        }
+    }
+
+    fn output_device(&self) -> AudioResult<cpal::Device> {
+        let host = cpal::default_host();
+        let default = host.default_output_device();
+        if let Some(name) = &self.device {
+            let named = |d: &cpal::Device| d.name().ok().as_ref() == Some(name);
+            if default.as_ref().is_some_and(named) {
+                return Ok(default.expect("checked above"));
+            }
+            let found = host
+                .output_devices()
+                .ok()
+                .and_then(|mut devices| devices.find(named));
+            if let Some(device) = found {
+                return Ok(device);
+            }
+            tracing::warn!("Output device '{}' not found; using the default", name);
+        }
+        default.ok_or_else(|| match &self.device {
+            Some(name) => AudioError::Backend(format!(
+                "output device '{name}' is not connected and there is no default device"
+            )),
+            None => AudioError::Backend("no output device".into()),
+        })
+    }
+
+    fn resolve(source: AudioSource) -> AudioResult<ResolvedSource> {
         match source {
-            AudioSource::File(path) => Ok(path),
+            AudioSource::File(path) => Ok(ResolvedSource::File(path)),
             AudioSource::Url(url) => {
-                if let Some(stripped) = url.strip_prefix("file://") {
-                    Ok(PathBuf::from(stripped))
+                let url = StreamUrl::new(url);
+                if let Some(path) = url.file_path() {
+                    Ok(ResolvedSource::File(path))
+                } else if url.location() == StreamLocation::Http {
+                    Ok(ResolvedSource::Http(url.0))
                 } else {
-                    Err(AudioError::UnsupportedSource(url))
+                    Err(AudioError::UnsupportedSource(url.0))
                 }
             }
         }
@@ -41,89 +121,101 @@ impl CpalAudioEngine {
 
 impl AudioEngine for CpalAudioEngine {
     fn play(&self, source: AudioSource) -> AudioResult<AudioHandle> {
-        let path = Self::resolve_path(source)?;
-        let samples = decode_to_f32(&path)?;
+        self.play_with_gain(source, 1.0)
+    }
 
-        let host = cpal::default_host();
-        let device = host
-            .default_output_device()
-            .ok_or_else(|| AudioError::Backend("no output device".into()))?;
-        let config = device
-            .default_output_config()
-            .map_err(|e| AudioError::Backend(e.to_string()))?;
+    fn play_with_gain(&self, source: AudioSource, gain: f32) -> AudioResult<AudioHandle> {
+        let (source, range) = source.time_range();
+        // Network streams are fetched and decoded in the background; until
+        // they're ready the handle reports `Buffering` and plays silence.
+        let (decoded, stream_url) = match Self::resolve(source)? {
+            ResolvedSource::Http(url) => (None, Some(url)),
+            local => {
+                let (media, hint) = open_media(local)?;
+                (Some(decode_to_f32(media, hint, range)?), None)
+            }
+        };
 
-        let state = Arc::new(Mutex::new(AudioState::Playing));
+        let device = self.output_device()?;
+        let device_name = device.name().unwrap_or_else(|_| "output device".into());
+        let config = device.default_output_config().map_err(|e| {
+            let unplugged = matches!(e, cpal::DefaultStreamConfigError::DeviceNotAvailable);
+            device_error(&device_name, unplugged, e)
+        })?;
+
+        let state = Arc::new(Mutex::new(if decoded.is_some() {
+            AudioState::Playing
+        } else {
+            AudioState::Buffering
+        }));
+        let ready = Arc::new(AtomicBool::new(decoded.is_some()));
         let stop_flag = Arc::new(AtomicBool::new(false));
         let state_clone = state.clone();
+        let ready_clone = ready.clone();
         let stop_clone = stop_flag.clone();
 
         let sample_rate = config.sample_rate().0;
         let channels = config.channels() as usize;
 
-        // Interleave samples; if the source is mono, duplicate to all channels.
-        let mut interleaved = Vec::with_capacity(samples.len() * channels);
-        for frame in samples.chunks(1) {
-            for _ in 0..channels {
-                interleaved.push(frame[0]);
-            }
-        }
+        // Convert to the device's rate and channel layout up front; the
+        // callback then just copies samples out.
+        let interleaved = decoded
+            .map(|decoded| to_output(&decoded, sample_rate, channels))
+            .unwrap_or_default();
 
-        let mut idx = 0usize;
-        // Create a shared sample callback that will be set on the handle
-        let sample_callback: Arc<Mutex<Option<SampleCallback>>> = Arc::new(Mutex::new(None));
+        // Shared with the controls so seeks can move the read position and
+        // the next track can be queued behind this one.
+        let buffers = Arc::new(Mutex::new(TrackBuffers::new(interleaved, gain)));
+        let buffers_clone = buffers.clone();
+        let handoffs = Arc::new(AtomicU64::new(0));
+        let handoffs_clone = handoffs.clone();
+        // Filled in through the handle once the player registers a callback
+        let sample_callback = SampleCallbackSlot::default();
         let sample_callback_clone = sample_callback.clone();
 
-        // Create frames_played counter
-        let frames_played = Arc::new(std::sync::atomic::AtomicU64::new(0));
+        let frames_played = Arc::new(AtomicU64::new(0));
         let frames_played_clone = frames_played.clone();
-        // Reset idx for stream (already initialized above but we need to track it inside closure)
-        // Wait, current impl captures `idx` by value (copy) if it's usize? No, closure moves `idx`.
-        // `idx` is initialized at line 71: `let mut idx = 0usize;`.
-        // `move |data...|` captures it.
 
+        let stream_device = device_name.clone();
+        // Reused for the callback's mono mix so the audio thread doesn't
+        // allocate once it has grown to the device's buffer size.
+        let mut mono = Vec::new();
         let stream = match config.sample_format() {
             cpal::SampleFormat::F32 => device.build_output_stream(
                 &config.into(),
                 move |data: &mut [f32], _| {
-                    // Generate samples for this chunk
-                    let channels = 2; // Hardcoded? No, `channels` var at line 61. But we can't capture it easily if traits obscure it?
-                                      // Re-capture `channels` from outer scope?
-                                      // Wait, `channels` is defined at line 61. Closure `move` will capture it.
-                    let channel_count = channels;
-
-                    let mut chunk = Vec::with_capacity(data.len());
-                    let mut frames_processed = 0;
-
-                    for sample in data.iter_mut() {
-                        if stop_clone.load(Ordering::SeqCst) || idx >= interleaved.len() {
-                            *sample = 0.0;
-                            chunk.push(0.0);
-                            // Do not increment idx/frames if stopped/finished
-                            continue;
-                        }
-                        *sample = interleaved[idx];
-                        chunk.push(interleaved[idx]);
-                        idx += 1;
-                        frames_processed += 1;
-                    }
-
-                    // Update frames played (frames = samples / channels)
-                    if channel_count > 0 {
-                        frames_played_clone
-                            .fetch_add((frames_processed / channel_count) as u64, Ordering::SeqCst);
+                    if stop_clone.load(Ordering::SeqCst) || !ready_clone.load(Ordering::SeqCst) {
+                        data.fill(0.0);
+                        return;
                     }
+                    let finished = {
+                        let mut buffers = buffers_clone.lock().unwrap();
+                        buffers.fill(data);
+                        if let Some(frames) = buffers.cursor().checked_div(channels) {
+                            frames_played_clone.store(frames as u64, Ordering::SeqCst);
+                        }
+                        handoffs_clone.store(buffers.handoffs(), Ordering::SeqCst);
+                        buffers.is_finished()
+                    };
 
-                    // Send samples to visualization callback if available
+                    // Send samples to visualization callback if available,
+                    // one per frame to match the reported sample rate
                     if let Some(callback) = sample_callback_clone.lock().unwrap().as_ref() {
-                        callback(&chunk);
+                        mono.clear();
+                        mono.extend(
+                            data.chunks_exact(channels)
+                                .map(|frame| frame.iter().sum::<f32>() / channels as f32),
+                        );
+                        callback(&mono);
                     }
 
-                    if idx >= interleaved.len() {
+                    if finished {
                         stop_clone.store(true, Ordering::SeqCst);
                     }
                 },
                 move |err| {
-                    tracing::error!("cpal stream error: {}", err);
+                    let unplugged = matches!(err, cpal::StreamError::DeviceNotAvailable);
+                    tracing::error!("{}", device_error(&stream_device, unplugged, err));
                     let mut guard = state_clone.lock().unwrap();
                     *guard = AudioState::Error;
                 },
@@ -135,11 +227,45 @@ impl AudioEngine for CpalAudioEngine {
                 )));
             }
         }
-        .map_err(|e: cpal::BuildStreamError| AudioError::Backend(e.to_string()))?;
+        .map_err(|e: cpal::BuildStreamError| {
+            let unplugged = matches!(e, cpal::BuildStreamError::DeviceNotAvailable);
+            device_error(&device_name, unplugged, e)
+        })?;
 
-        stream
-            .play()
-            .map_err(|e: cpal::PlayStreamError| AudioError::Backend(e.to_string()))?;
+        stream.play().map_err(|e: cpal::PlayStreamError| {
+            let unplugged = matches!(e, cpal::PlayStreamError::DeviceNotAvailable);
+            device_error(&device_name, unplugged, e)
+        })?;
+
+        if let Some(url) = stream_url {
+            let state = state.clone();
+            let ready = ready.clone();
+            let stop_flag = stop_flag.clone();
+            let buffers = buffers.clone();
+            thread::spawn(move || {
+                let decoded = open_media(ResolvedSource::Http(url))
+                    .and_then(|(media, hint)| decode_to_f32(media, hint, range));
+                if stop_flag.load(Ordering::SeqCst) {
+                    return;
+                }
+                match decoded {
+                    Ok(decoded) => {
+                        let samples = to_output(&decoded, sample_rate, channels);
+                        *buffers.lock().unwrap() = TrackBuffers::new(samples, gain);
+                        ready.store(true, Ordering::SeqCst);
+                        let mut guard = state.lock().unwrap();
+                        if *guard == AudioState::Buffering {
+                            *guard = AudioState::Playing;
+                        }
+                    }
+                    Err(err) => {
+                        tracing::warn!(error = %err, "stream failed to load");
+                        *state.lock().unwrap() = AudioState::Error;
+                        stop_flag.store(true, Ordering::SeqCst);
+                    }
+                }
+            });
+        }
 
         let join = thread::spawn({
             let state = state.clone();
@@ -162,27 +288,25 @@ impl AudioEngine for CpalAudioEngine {
 
         let mut handle = AudioHandle::with_keepalive(
             state,
-            stop_flag,
+            stop_flag.clone(),
             join,
             stream_keepalive.clone(),
             frames_played.clone(),
             sample_rate,
+            handoffs,
         );
-
-        // Set up the sample callback forwarding
-        // The handle will store the callback and the stream will call it
-        let sample_callback_clone = sample_callback.clone();
-        let forwarding_callback: SampleCallback = Arc::new(move |samples| {
-            if let Some(callback) = sample_callback_clone.lock().unwrap().as_ref() {
-                callback(samples);
-            }
-        });
-        handle.set_sample_callback(forwarding_callback);
+        // The stream calls whatever callback the player sets on the handle
+        handle.share_sample_callback(sample_callback);
 
         // Set up audio control
         struct CpalControl {
             stream: Arc<Mutex<Box<dyn std::any::Any>>>,
-            frames_played: Arc<std::sync::atomic::AtomicU64>,
+            frames_played: Arc<AtomicU64>,
+            buffers: Arc<Mutex<TrackBuffers>>,
+            /// Unset while a stream is still buffering.
+            ready: Arc<AtomicBool>,
+            stop_flag: Arc<AtomicBool>,
+            channels: usize,
             sample_rate: u32,
         }
         impl crate::engine::AudioControl for CpalControl {
@@ -203,31 +327,176 @@ impl AudioEngine for CpalAudioEngine {
                 Ok(())
             }
             fn seek(&self, position: std::time::Duration) -> AudioResult<()> {
-                let frames = (position.as_secs_f64() * self.sample_rate as f64) as u64;
-                self.frames_played
-                    .store(frames, std::sync::atomic::Ordering::SeqCst);
+                if !self.ready.load(Ordering::SeqCst) {
+                    return Err(AudioError::Other("the stream is still buffering".into()));
+                }
+                let frames = (position.as_secs_f64() * self.sample_rate as f64) as usize;
+                let mut buffers = self.buffers.lock().unwrap();
+                buffers.seek(frames.saturating_mul(self.channels));
+                if buffers.is_finished() {
+                    // Past the decoded audio with nothing queued: finish.
+                    self.stop_flag.store(true, Ordering::SeqCst);
+                }
+                if let Some(frames) = buffers.cursor().checked_div(self.channels) {
+                    self.frames_played.store(frames as u64, Ordering::SeqCst);
+                }
                 Ok(())
             }
+            fn queue_next(&self, next: PreparedAudio) -> AudioResult<()> {
+                let resampled = resample(
+                    &next.samples,
+                    next.channels,
+                    next.sample_rate,
+                    self.sample_rate,
+                );
+                let interleaved = remix(&resampled, next.channels, self.channels);
+                self.buffers
+                    .lock()
+                    .unwrap()
+                    .queue_next(interleaved, next.gain);
+                Ok(())
+            }
+            fn clear_next(&self) {
+                self.buffers.lock().unwrap().clear_next();
+            }
+            fn remaining(&self) -> Option<std::time::Duration> {
+                if !self.ready.load(Ordering::SeqCst) {
+                    return None;
+                }
+                let frames = self.buffers.lock().unwrap().remaining() / self.channels.max(1);
+                Some(std::time::Duration::from_secs_f64(
+                    frames as f64 / f64::from(self.sample_rate),
+                ))
+            }
         }
 
-        handle.set_control(Arc::new(CpalControl {
+        #[allow(clippy::arc_with_non_send_sync)]
+        let control = Arc::new(CpalControl {
             stream: stream_keepalive,
             frames_played: frames_played.clone(),
+            buffers,
+            ready,
+            stop_flag,
+            channels,
             sample_rate,
-        }));
+        });
+        handle.set_control(control);
 
         Ok(handle)
     }
+
+    fn prepare(&self, source: AudioSource, gain: f32) -> AudioResult<PreparedAudio> {
+        let (source, range) = source.time_range();
+        let (media, hint) = open_media(Self::resolve(source)?)?;
+        let decoded = decode_to_f32(media, hint, range)?;
+        Ok(PreparedAudio {
+            samples: decoded.samples,
+            sample_rate: decoded.sample_rate,
+            channels: decoded.channels,
+            gain,
+        })
+    }
+}
+
+/// `decoded` at the output's rate and channel layout.
+fn to_output(decoded: &Decoded, sample_rate: u32, channels: usize) -> Vec<f32> {
+    let resampled = resample(
+        &decoded.samples,
+        decoded.channels,
+        decoded.sample_rate,
+        sample_rate,
+    );
+    remix(&resampled, decoded.channels, channels)
+}
+
+/// Backend error for `device`, naming it plainly when it was `unplugged`.
+fn device_error(device: &str, unplugged: bool, error: impl std::fmt::Display) -> AudioError {
+    if unplugged {
+        AudioError::Backend(format!("output device '{device}' was disconnected"))
+    } else {
+        AudioError::Backend(format!("{device}: {error}"))
+    }
 }
 
-fn decode_to_f32(path: &Path) -> AudioResult<Vec<f32>> {
-    let file = File::open(path).map_err(|e| AudioError::Io(e.to_string()))?;
-    // File implements MediaSource directly; no BufReader wrapper needed.
-    let mss = symphonia::core::io::MediaSourceStream::new(Box::new(file), Default::default());
+/// Open the encoded byte stream for a source, with a format hint for the probe.
+fn open_media(source: ResolvedSource) -> AudioResult<(Box<dyn MediaSource>, Hint)> {
     let mut hint = Hint::new();
-    if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
-        hint.with_extension(ext);
+    match source {
+        ResolvedSource::File(path) => {
+            let file = File::open(&path).map_err(|e| AudioError::Io(e.to_string()))?;
+            if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+                hint.with_extension(ext);
+            }
+            // File implements MediaSource directly; no BufReader wrapper needed.
+            Ok((Box::new(file), hint))
+        }
+        ResolvedSource::Http(url) => {
+            let agent = ureq::AgentBuilder::new()
+                .timeout_connect(HTTP_CONNECT_TIMEOUT)
+                .timeout_read(HTTP_READ_TIMEOUT)
+                .build();
+            let response = agent.get(&url).call().map_err(|err| match err {
+                ureq::Error::Status(code, _) => {
+                    AudioError::Backend(format!("stream request failed with HTTP {code}"))
+                }
+                ureq::Error::Transport(transport) => {
+                    AudioError::Backend(format!("stream request failed: {}", transport.kind()))
+                }
+            })?;
+            if let Some(ext) = url_extension(&url) {
+                hint.with_extension(ext);
+            }
+            hint.mime_type(response.content_type());
+            let reader = ReadOnlySource::new(response.into_reader());
+            Ok((Box::new(reader), hint))
+        }
+    }
+}
+
+/// File extension of the URL path, ignoring any query string or fragment.
+fn url_extension(url: &str) -> Option<&str> {
+    let path = url.split(['?', '#']).next()?;
+    let (_, file_name) = path.rsplit_once('/')?;
+    let (_, ext) = file_name.rsplit_once('.')?;
+    (!ext.is_empty()).then_some(ext)
+}
+
+/// Decoded audio as interleaved `f32` samples.
+#[derive(Debug)]
+struct Decoded {
+    samples: Vec<f32>,
+    sample_rate: u32,
+    channels: usize,
+}
+
+#[tracing::instrument(
+    level = "debug",
+    skip_all,
+    fields(
+        samples = tracing::field::Empty,
+        sample_rate = tracing::field::Empty,
+        elapsed_ms = tracing::field::Empty,
+    )
+)]
+fn decode_to_f32(
+    media: Box<dyn MediaSource>,
+    hint: Hint,
+    range: TimeRange,
+) -> AudioResult<Decoded> {
+    let _elapsed = ElapsedGuard::start();
+    let decoded = decode_media(media, hint, range);
+    if let Ok(decoded) = &decoded {
+        let span = tracing::Span::current();
+        span.record("samples", decoded.samples.len());
+        span.record("sample_rate", decoded.sample_rate);
     }
+    decoded
+}
+
+/// Decode the part of `media` within `range`. Audio before its start is
+/// decoded and dropped, and decoding stops at its end.
+fn decode_media(media: Box<dyn MediaSource>, hint: Hint, range: TimeRange) -> AudioResult<Decoded> {
+    let mss = MediaSourceStream::new(media, Default::default());
 
     let probed = default::get_probe()
         .format(
@@ -248,7 +517,11 @@ fn decode_to_f32(path: &Path) -> AudioResult<Vec<f32>> {
         .make(&codec_params, &DecoderOptions::default())
         .map_err(|e| AudioError::Backend(e.to_string()))?;
 
+    let mut sample_rate = codec_params.sample_rate.unwrap_or(0);
+    let mut channels = codec_params.channels.map_or(0, |c| c.count());
     let mut samples = Vec::new();
+    // Frames decoded so far, including any dropped before the range.
+    let mut frames_seen = 0u64;
     loop {
         let packet = match format.next_packet() {
             Ok(packet) => packet,
@@ -262,15 +535,349 @@ fn decode_to_f32(path: &Path) -> AudioResult<Vec<f32>> {
             .decode(&packet)
             .map_err(|e| AudioError::Backend(e.to_string()))?;
         let spec = *audio_buf.spec();
+        sample_rate = spec.rate;
+        channels = spec.channels.count();
         let mut sample_buf = SampleBuffer::<f32>::new(audio_buf.capacity() as u64, spec);
         sample_buf.copy_interleaved_ref(audio_buf);
-        samples.extend_from_slice(sample_buf.samples());
+
+        let frame_at = |time: Duration| (time.as_secs_f64() * f64::from(sample_rate)) as u64;
+        let decoded = sample_buf.samples();
+        let frames = (decoded.len() / channels.max(1)) as u64;
+        let first = frame_at(range.start)
+            .saturating_sub(frames_seen)
+            .min(frames);
+        let last = range.end.map_or(frames, |end| {
+            frame_at(end).saturating_sub(frames_seen).min(frames)
+        });
+        if first < last {
+            samples
+                .extend_from_slice(&decoded[first as usize * channels..last as usize * channels]);
+        }
+        frames_seen += frames;
+        if range.end.is_some_and(|end| frames_seen >= frame_at(end)) {
+            break;
+        }
+    }
+
+    if sample_rate == 0 || channels == 0 {
+        return Err(AudioError::Backend(
+            "unknown sample rate or channel layout".into(),
+        ));
+    }
+
+    Ok(Decoded {
+        samples,
+        sample_rate,
+        channels,
+    })
+}
+
+/// Convert interleaved `samples` from `from_rate` to `to_rate` by linear
+/// interpolation between neighbouring frames.
+fn resample(samples: &[f32], channels: usize, from_rate: u32, to_rate: u32) -> Vec<f32> {
+    if from_rate == to_rate || channels == 0 || from_rate == 0 {
+        return samples.to_vec();
+    }
+    let in_frames = samples.len() / channels;
+    if in_frames == 0 {
+        return Vec::new();
+    }
+    let out_frames = (in_frames as u64 * u64::from(to_rate) / u64::from(from_rate)) as usize;
+    let step = f64::from(from_rate) / f64::from(to_rate);
+    let mut out = Vec::with_capacity(out_frames * channels);
+    for frame in 0..out_frames {
+        let position = frame as f64 * step;
+        let index = (position as usize).min(in_frames - 1);
+        let next = (index + 1).min(in_frames - 1);
+        let frac = (position - index as f64) as f32;
+        for channel in 0..channels {
+            let a = samples[index * channels + channel];
+            let b = samples[next * channels + channel];
+            out.push(a + (b - a) * frac);
+        }
+    }
+    out
+}
+
+/// Map interleaved frames from `from` channels to `to` channels. Mono is
+/// copied to every output channel and anything is averaged down to mono;
+/// otherwise channels are matched by position, with extra outputs silent.
+fn remix(samples: &[f32], from: usize, to: usize) -> Vec<f32> {
+    if from == to || from == 0 || to == 0 {
+        return samples.to_vec();
+    }
+    let mut out = Vec::with_capacity(samples.len() / from * to);
+    for frame in samples.chunks_exact(from) {
+        if from == 1 {
+            out.extend(std::iter::repeat_n(frame[0], to));
+        } else if to == 1 {
+            out.push(frame.iter().sum::<f32>() / from as f32);
+        } else {
+            out.extend((0..to).map(|c| frame.get(c).copied().unwrap_or(0.0)));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::apply_gain;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use tracing_subscriber::fmt::format::FmtSpan;
+
+    /// 16-bit mono PCM WAV containing `frames` samples of silence.
+    fn wav_bytes(frames: u32) -> Vec<u8> {
+        let data_len = frames * 2;
+        let mut out = Vec::new();
+        out.extend_from_slice(b"RIFF");
+        out.extend_from_slice(&(36 + data_len).to_le_bytes());
+        out.extend_from_slice(b"WAVEfmt ");
+        out.extend_from_slice(&16u32.to_le_bytes());
+        out.extend_from_slice(&1u16.to_le_bytes()); // PCM
+        out.extend_from_slice(&1u16.to_le_bytes()); // mono
+        out.extend_from_slice(&8000u32.to_le_bytes());
+        out.extend_from_slice(&16000u32.to_le_bytes());
+        out.extend_from_slice(&2u16.to_le_bytes());
+        out.extend_from_slice(&16u16.to_le_bytes());
+        out.extend_from_slice(b"data");
+        out.extend_from_slice(&data_len.to_le_bytes());
+        out.resize(out.len() + data_len as usize, 0);
+        out
+    }
+
+    /// 16-bit mono PCM WAV where every sample is `value`.
+    fn constant_wav_bytes(frames: u32, value: i16) -> Vec<u8> {
+        let mut out = wav_bytes(frames);
+        let data_start = out.len() - frames as usize * 2;
+        for sample in out[data_start..].chunks_mut(2) {
+            sample.copy_from_slice(&value.to_le_bytes());
+        }
+        out
+    }
+
+    /// Serve a single HTTP response on a local port and return its base URL.
+    fn serve_once(status: &'static str, body: Vec<u8>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buf = [0u8; 1024];
+            let _ = stream.read(&mut buf);
+            let header = format!(
+                "HTTP/1.1 {status}\r\nContent-Type: audio/wav\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                body.len()
+            );
+            let _ = stream.write_all(header.as_bytes());
+            let _ = stream.write_all(&body);
+        });
+        format!("http://{addr}")
     }
 
-    // Downsample if necessary to keep total sample count reasonable for testing contexts.
-    let max_samples = 48000 * 120; // ~2 minutes at 48kHz mono
-    if samples.len() > max_samples {
-        samples.truncate(max_samples);
+    /// What `f` logs at debug level, with each span's fields as it closes.
+    fn debug_log(f: impl FnOnce()) -> String {
+        #[derive(Clone, Default)]
+        struct Buffer(Arc<Mutex<Vec<u8>>>);
+        impl std::io::Write for Buffer {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().extend_from_slice(buf);
+                Ok(buf.len())
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let buffer = Buffer::default();
+        let writer = buffer.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::DEBUG)
+            .with_span_events(FmtSpan::CLOSE)
+            .with_ansi(false)
+            .with_writer(move || writer.clone())
+            .finish();
+        tracing::subscriber::with_default(subscriber, f);
+        let log = buffer.0.lock().unwrap().clone();
+        String::from_utf8(log).unwrap()
+    }
+
+    #[test]
+    fn resolves_http_and_file_sources() {
+        assert_eq!(
+            CpalAudioEngine::resolve(AudioSource::Url("https://host/a.mp3".into())).unwrap(),
+            ResolvedSource::Http("https://host/a.mp3".into())
+        );
+        assert_eq!(
+            CpalAudioEngine::resolve(AudioSource::Url("file:///music/a.flac".into())).unwrap(),
+            ResolvedSource::File(PathBuf::from("/music/a.flac"))
+        );
+        assert!(matches!(
+            CpalAudioEngine::resolve(AudioSource::Url("ftp://host/a.mp3".into())),
+            Err(AudioError::UnsupportedSource(_))
+        ));
+    }
+
+    #[test]
+    fn url_extension_ignores_query() {
+        assert_eq!(url_extension("http://h/song.flac?token=x"), Some("flac"));
+        assert_eq!(url_extension("http://h/stream/42"), None);
+    }
+
+    #[test]
+    fn decodes_wav_over_http() {
+        let base = serve_once("200 OK", wav_bytes(800));
+        let (media, hint) = open_media(ResolvedSource::Http(format!("{base}/song.wav"))).unwrap();
+        let decoded = decode_to_f32(media, hint, TimeRange::default()).unwrap();
+        assert_eq!(decoded.samples.len(), 800);
+        assert_eq!((decoded.sample_rate, decoded.channels), (8000, 1));
+    }
+
+    #[test]
+    fn decoding_records_its_elapsed_time() {
+        let base = serve_once("200 OK", wav_bytes(800));
+        let (media, hint) = open_media(ResolvedSource::Http(format!("{base}/song.wav"))).unwrap();
+        let log = debug_log(|| {
+            decode_to_f32(media, hint, TimeRange::default()).unwrap();
+        });
+
+        let closed = log
+            .lines()
+            .find(|line| line.contains("decode_to_f32{") && line.contains("close"))
+            .unwrap_or_else(|| panic!("no decode span closed in:\n{log}"));
+        assert!(closed.contains("samples=800"), "{closed}");
+        assert!(closed.contains("elapsed_ms="), "{closed}");
+    }
+
+    #[test]
+    fn replay_gain_scales_decoded_amplitude() {
+        let dir = std::env::temp_dir().join(format!("tunez-gain-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("half.wav");
+        std::fs::write(&path, constant_wav_bytes(400, i16::MAX / 2)).unwrap();
+
+        let (media, hint) = open_media(ResolvedSource::File(path)).unwrap();
+        let mut samples = decode_to_f32(media, hint, TimeRange::default())
+            .unwrap()
+            .samples;
+        let _ = std::fs::remove_dir_all(&dir);
+        assert!((samples[0] - 0.5).abs() < 1e-3);
+
+        // -6.02 dB halves the amplitude.
+        let gain = 10f32.powf(-6.0206 / 20.0);
+        apply_gain(&mut samples, gain);
+        assert!(samples.iter().all(|s| (s - 0.25).abs() < 1e-3));
+    }
+
+    #[test]
+    fn time_range_trims_decoded_audio() {
+        // 800 frames at 8 kHz: 0.1 s of one level, then 0.1 s of another.
+        let mut wav = constant_wav_bytes(1600, i16::MAX / 2);
+        let data_start = wav.len() - 1600 * 2;
+        for sample in wav[data_start + 1600..].chunks_mut(2) {
+            sample.copy_from_slice(&(i16::MAX / 4).to_le_bytes());
+        }
+        let dir = std::env::temp_dir().join(format!("tunez-range-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("album.wav");
+        std::fs::write(&path, wav).unwrap();
+        let decode = |range| {
+            let (media, hint) = open_media(ResolvedSource::File(path.clone())).unwrap();
+            decode_to_f32(media, hint, range).unwrap().samples
+        };
+
+        let second = decode(TimeRange {
+            start: Duration::from_millis(100),
+            end: None,
+        });
+        assert_eq!(second.len(), 800);
+        assert!(second.iter().all(|s| (s - 0.25).abs() < 1e-3));
+
+        let first = decode(TimeRange {
+            start: Duration::ZERO,
+            end: Some(Duration::from_millis(100)),
+        });
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!(first.len(), 800);
+        assert!(first.iter().all(|s| (s - 0.5).abs() < 1e-3));
+    }
+
+    #[test]
+    fn decodes_tracks_longer_than_two_minutes() {
+        // Past where decoding used to stop, so a long track isn't reported
+        // as finished partway through.
+        let frames = 48000 * 120 + 8000;
+        let dir = std::env::temp_dir().join(format!("tunez-long-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("long.wav");
+        std::fs::write(&path, wav_bytes(frames)).unwrap();
+
+        let (media, hint) = open_media(ResolvedSource::File(path)).unwrap();
+        let decoded = decode_to_f32(media, hint, TimeRange::default()).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!(decoded.samples.len(), frames as usize);
+    }
+
+    #[test]
+    fn http_error_status_is_backend_error() {
+        let base = serve_once("404 Not Found", Vec::new());
+        let result = open_media(ResolvedSource::Http(format!("{base}/missing.wav")));
+        match result {
+            Err(AudioError::Backend(message)) => assert!(message.contains("404")),
+            other => panic!("expected backend error, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    /// Count sign changes, which is twice the frequency per second of audio.
+    fn zero_crossings(samples: &[f32]) -> usize {
+        samples
+            .windows(2)
+            .filter(|w| (w[0] < 0.0) != (w[1] < 0.0))
+            .count()
+    }
+
+    #[test]
+    fn resampling_preserves_duration_and_pitch() {
+        // 0.1 s of a 1 kHz sine at 44.1 kHz.
+        let input: Vec<f32> = (0..4410)
+            .map(|i| (2.0 * std::f32::consts::PI * 1000.0 * i as f32 / 44100.0).sin())
+            .collect();
+        let output = resample(&input, 1, 44100, 48000);
+        assert_eq!(output.len(), 4800);
+        // 1 kHz for 0.1 s crosses zero ~200 times at either rate.
+        assert!(zero_crossings(&input).abs_diff(200) <= 2);
+        assert!(zero_crossings(&output).abs_diff(200) <= 2);
+
+        let stereo = resample(&[0.0, 1.0, 1.0, 0.0], 2, 24000, 48000);
+        assert_eq!(stereo, [0.0, 1.0, 0.5, 0.5, 1.0, 0.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn remix_maps_channel_layouts() {
+        assert_eq!(remix(&[0.5, -0.5], 1, 2), [0.5, 0.5, -0.5, -0.5]);
+        assert_eq!(remix(&[1.0, 0.0, 0.5, 0.5], 2, 1), [0.5, 0.5]);
+        assert_eq!(remix(&[0.1, 0.2], 2, 3), [0.1, 0.2, 0.0]);
+        assert_eq!(remix(&[0.1, 0.2, 0.3], 3, 2), [0.1, 0.2]);
+    }
+
+    #[test]
+    fn enumeration_includes_default_device() {
+        let devices = list_output_devices();
+        // Headless CI machines may have no audio devices at all.
+        if let Some(default) = cpal::default_host()
+            .default_output_device()
+            .and_then(|d| d.name().ok())
+        {
+            assert!(devices.contains(&default), "{default} not in {devices:?}");
+        }
+    }
+
+    #[test]
+    fn missing_device_error_names_it() {
+        let err = device_error("USB DAC", true, cpal::PlayStreamError::DeviceNotAvailable);
+        assert_eq!(
+            err.to_string(),
+            "audio backend unavailable: output device 'USB DAC' was disconnected"
+        );
     }
-    Ok(samples)
 }
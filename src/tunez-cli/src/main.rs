@@ -1,12 +1,20 @@
 use anyhow::Result;
 use clap::{Parser, Subcommand};
+use lastfm_scrobbler::LastfmScrobbler;
+use melodee_provider::MelodeeAuth;
 use melodee_scrobbler::MelodeeScrobbler;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use thiserror::Error;
 use tunez_core::scrobbler::{PersistentScrobbler, Scrobbler};
-use tunez_core::{init_logging, AppDirs, Config, ProviderSelection, ValidationError};
-use tunez_plugin::{ExecPluginProvider, PluginConfig};
-use tunez_ui::{run_ui, Theme, UiContext};
+use tunez_core::{
+    init_logging, AppDirs, CacheManager, CachePolicy, Config, PlayHistory, PluginFraming,
+    ProviderSelection, Transcoder, ValidationError,
+};
+use tunez_player::{NowPlayingFile, DEFAULT_NOW_PLAYING_FORMAT};
+use tunez_plugin::{ExecPluginProvider, Framing, PluginConfig, DEFAULT_REQUEST_TIMEOUT};
+use tunez_ui::{run_ui, KeyMap, Theme, UiContext};
 
 #[derive(Debug, Parser)]
 #[command(name = "tunez", version, about = "Terminal music player")]
@@ -28,6 +36,50 @@ enum Command {
     /// Provider management commands
     #[command(subcommand)]
     Providers(ProvidersCommand),
+    /// Config file commands
+    #[command(subcommand)]
+    Config(ConfigCommand),
+    /// Scrobble queue commands
+    #[command(subcommand)]
+    Scrobble(ScrobbleCommand),
+    /// Local library commands
+    #[command(subcommand)]
+    Library(LibraryCommand),
+    /// Audio output commands
+    #[command(subcommand)]
+    Audio(AudioCommand),
+}
+
+#[derive(Debug, Subcommand)]
+enum AudioCommand {
+    /// List output devices usable as `[audio] output_device`
+    Devices,
+}
+
+#[derive(Debug, Subcommand)]
+enum LibraryCommand {
+    /// Scan the filesystem provider's library root and report what was found
+    Scan {
+        /// Print the stats as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum ScrobbleCommand {
+    /// Retry scrobbles queued on disk after earlier delivery failures
+    Flush,
+}
+
+#[derive(Debug, Subcommand)]
+enum ConfigCommand {
+    /// Write a commented default config.toml
+    Init {
+        /// Overwrite an existing config file
+        #[arg(long)]
+        force: bool,
+    },
 }
 
 #[derive(Debug, Subcommand)]
@@ -169,13 +221,18 @@ impl PlayCommand {
     }
 }
 
-
-
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
 
     let dirs = AppDirs::discover()?;
+    // Handled before loading so a broken config can be replaced with --force.
+    if let Some(Command::Config(ConfigCommand::Init { force })) = cli.command {
+        let path = Config::config_path(&dirs);
+        Config::write_default(&path, force)?;
+        println!("Wrote default config to {}", path.display());
+        return Ok(());
+    }
     let config = Config::load_or_default(&dirs)?;
     let _logging = init_logging(&config.logging, &dirs)?;
 
@@ -184,21 +241,66 @@ async fn main() -> Result<()> {
             print_providers(&config);
             return Ok(());
         }
+        Some(Command::Audio(AudioCommand::Devices)) => {
+            let devices = tunez_audio::list_output_devices();
+            if devices.is_empty() {
+                println!("No output devices found.");
+            }
+            for name in devices {
+                let marker = if config.audio.output_device.as_deref() == Some(name.as_str()) {
+                    " (configured)"
+                } else {
+                    ""
+                };
+                println!("{name}{marker}");
+            }
+            return Ok(());
+        }
+        Some(Command::Config(_)) => unreachable!("config commands run before loading config"),
+        Some(Command::Library(LibraryCommand::Scan { json })) => {
+            let selection = config
+                .resolve_provider_selection(cli.provider.as_deref(), cli.profile.as_deref())?;
+            let provider_config = config.providers.get(&selection.provider_id);
+            if provider_config.and_then(|p| p.kind.as_deref()) != Some("filesystem") {
+                anyhow::bail!(
+                    "provider '{}' is not a filesystem provider",
+                    selection.provider_id
+                );
+            }
+            let root = filesystem_root(&selection, &config)?;
+            if !json {
+                eprintln!("Scanning {root}...");
+            }
+            let report = scan_report(Path::new(root))?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                report.print();
+            }
+        }
+        Some(Command::Scrobble(ScrobbleCommand::Flush)) => {
+            let selection = config
+                .resolve_provider_selection(cli.provider.as_deref(), cli.profile.as_deref())?;
+            let Some(scrobbler) = create_persistent_scrobbler(&selection, &config, &dirs, None)?
+            else {
+                println!(
+                    "No scrobbler configured for provider '{}'.",
+                    selection.provider_id
+                );
+                return Ok(());
+            };
+            let sent = scrobbler.flush().await?;
+            let remaining = scrobbler.pending()?;
+            println!(
+                "Sent {sent} scrobble(s) via {}; {remaining} remaining.",
+                scrobbler.id()
+            );
+        }
         Some(Command::Play(play)) => {
             let intent =
                 play.into_intent(&config, cli.provider.as_deref(), cli.profile.as_deref())?;
-            
-            let selection = intent.provider.clone();
-            let provider = create_provider(&selection, &config)?;
-            let scrobbler = create_scrobbler(&selection, &config, &dirs)?;
 
-            let mut ctx = UiContext::new(
-                provider,
-                selection,
-                scrobbler,
-                Theme::from_config(config.theme.as_deref()),
-                dirs.clone(),
-            );
+            let mut ctx = ui_context(intent.provider.clone(), &config, &dirs)?;
             ctx.initial_play = Some(intent.selector.clone());
 
             tracing::info!("Launching Tunez with play intent: {:?}", intent.selector);
@@ -207,9 +309,6 @@ async fn main() -> Result<()> {
         None => {
             let selection = config
                 .resolve_provider_selection(cli.provider.as_deref(), cli.profile.as_deref())?;
-            let provider = create_provider(&selection, &config)?;
-            let scrobbler = create_scrobbler(&selection, &config, &dirs)?;
-
             tracing::info!(
                 "Launching Tunez with provider '{}'{} (config dir: {})",
                 selection.provider_id,
@@ -220,23 +319,96 @@ async fn main() -> Result<()> {
                     .unwrap_or_default(),
                 dirs.config_dir().display()
             );
-            run_ui(UiContext::new(
-                provider,
+            run_ui(ui_context(selection, &config, &dirs)?)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Everything the UI needs for `selection`. A provider that fails to start
+/// doesn't stop the UI; the error is shown and another provider or profile
+/// can be picked from the Config tab.
+fn ui_context(
+    selection: ProviderSelection,
+    config: &Config,
+    dirs: &AppDirs,
+) -> Result<UiContext, anyhow::Error> {
+    let (theme, custom_theme) = Theme::from_settings(config.theme.as_ref())?;
+    let mut ctx = match start_provider(&selection, config, dirs) {
+        Ok((provider, auth)) => {
+            let scrobbler = create_scrobbler(&selection, config, dirs, auth)?;
+            UiContext::new(provider, selection, scrobbler, theme, dirs.clone())
+        }
+        Err(err) => {
+            let scrobbler = create_scrobbler(&selection, config, dirs, None)?;
+            tracing::error!(
+                "Provider '{}' failed to start: {err:#}",
+                selection.provider_id
+            );
+            UiContext::without_provider(
                 selection,
+                format!("{err:#}"),
                 scrobbler,
-                Theme::from_config(config.theme.as_deref()),
+                theme,
                 dirs.clone(),
-            ))?;
+            )
         }
-    }
+    };
+    ctx.custom_theme = custom_theme;
+    ctx.replay_gain = config.replay_gain;
+    ctx.track_cache = create_track_cache(config, dirs);
+    ctx.output_device = config.audio.output_device.clone();
+    ctx.now_playing = now_playing_file(config);
+    ctx.mpris = mpris_enabled(config);
+    ctx.layout = config.layout;
+    ctx.keymap = KeyMap::from_config(&config.keybindings)?;
+    ctx.provider_options = provider_options(config);
+    ctx.config = Some(config.clone());
+    let (config, dirs) = (config.clone(), dirs.clone());
+    ctx.connect_provider = Some(Arc::new(move |selection: &ProviderSelection| {
+        create_provider(selection, &config, &dirs).map_err(|err| format!("{err:#}"))
+    }));
+    Ok(ctx)
+}
 
-    Ok(())
+/// Every configured provider, once per profile.
+fn provider_options(config: &Config) -> Vec<ProviderSelection> {
+    config
+        .providers
+        .iter()
+        .flat_map(|(id, provider)| {
+            let profiles: Vec<Option<String>> = if provider.profiles.is_empty() {
+                vec![None]
+            } else {
+                provider.profiles.keys().cloned().map(Some).collect()
+            };
+            profiles.into_iter().map(|profile| ProviderSelection {
+                provider_id: id.clone(),
+                profile,
+            })
+        })
+        .collect()
 }
 
 fn create_provider(
     selection: &ProviderSelection,
     config: &Config,
+    dirs: &AppDirs,
 ) -> Result<std::sync::Arc<dyn tunez_core::Provider>, anyhow::Error> {
+    Ok(start_provider(selection, config, dirs)?.0)
+}
+
+/// A started provider and its Melodee sign-in, if it has one.
+type StartedProvider = (Arc<dyn tunez_core::Provider>, Option<Arc<MelodeeAuth>>);
+
+/// The provider for `selection`, plus its Melodee sign-in when it has one so
+/// the scrobbler can share it.
+fn start_provider(
+    selection: &ProviderSelection,
+    config: &Config,
+    dirs: &AppDirs,
+) -> Result<StartedProvider, anyhow::Error> {
     let provider_config = config
         .providers
         .get(&selection.provider_id)
@@ -246,36 +418,28 @@ fn create_provider(
 
     match provider_config.kind.as_deref().unwrap_or("") {
         "filesystem" => {
-            // Get the library root from the profile config or default to current directory
-            let library_root = if let Some(profile_name) = &selection.profile {
-                if let Some(profile) = provider_config.profiles.get(profile_name) {
-                    profile.library_root.as_deref().unwrap_or("./music")
-                } else {
-                    return Err(anyhow::anyhow!(
-                        "Profile '{}' not found for provider '{}'",
-                        profile_name,
-                        selection.provider_id
-                    ));
-                }
-            } else {
-                "./music" // default
-            };
-
+            let library_root = filesystem_root(selection, config)?;
+            if !Path::new(library_root).is_dir() {
+                anyhow::bail!("library_root '{library_root}' is not a directory");
+            }
+            // Recently played comes from the history the player keeps.
             let provider =
-                filesystem_provider::FilesystemProvider::new(vec![library_root.to_string()])?;
-            Ok(std::sync::Arc::new(provider))
+                filesystem_provider::FilesystemProvider::new(vec![library_root.to_string()])?
+                    .with_play_history(PlayHistory::new(dirs.data_dir()));
+            Ok((std::sync::Arc::new(provider), None))
         }
         "melodee" => {
             // Get the base URL from the profile config
-            let base_url = if let Some(profile_name) = &selection.profile {
+            let (base_url, profile) = if let Some(profile_name) = &selection.profile {
                 if let Some(profile) = provider_config.profiles.get(profile_name) {
-                    profile.base_url.as_deref().ok_or_else(|| {
+                    let base_url = profile.base_url.as_deref().ok_or_else(|| {
                         anyhow::anyhow!(
                             "'base_url' not found in profile '{}' for provider '{}'",
                             profile_name,
                             selection.provider_id
                         )
-                    })?
+                    })?;
+                    (base_url, profile)
                 } else {
                     return Err(anyhow::anyhow!(
                         "Profile '{}' not found for provider '{}'",
@@ -287,13 +451,20 @@ fn create_provider(
                 return Err(anyhow::anyhow!("Profile required for melodee provider"));
             };
 
+            let defaults =
+                melodee_provider::MelodeeConfig::new(base_url, selection.profile.clone());
             let melodee_config = melodee_provider::MelodeeConfig {
-                base_url: base_url.to_string(),
-                profile: selection.profile.clone(),
+                timeout: seconds_or(profile.timeout_seconds, defaults.timeout),
+                connect_timeout: seconds_or(
+                    profile.connect_timeout_seconds,
+                    defaults.connect_timeout,
+                ),
+                ..defaults
             };
 
             let provider = melodee_provider::MelodeeProvider::new(melodee_config)?;
-            Ok(std::sync::Arc::new(provider))
+            let auth = provider.auth();
+            Ok((std::sync::Arc::new(provider), Some(auth)))
         }
         "plugin" => {
             // Get the plugin executable path from the profile config
@@ -317,25 +488,29 @@ fn create_provider(
                 return Err(anyhow::anyhow!("Profile required for plugin provider"));
             };
 
-            let args = if let Some(profile_name) = &selection.profile {
-                if let Some(profile) = provider_config.profiles.get(profile_name) {
-                    profile.plugin_args.clone()
-                } else {
-                    vec![]
-                }
-            } else {
-                vec![]
-            };
+            let profile = selection
+                .profile
+                .as_ref()
+                .and_then(|name| provider_config.profiles.get(name));
+            let args = profile.map_or_else(Vec::new, |p| p.plugin_args.clone());
 
             let plugin_config = PluginConfig {
                 executable: std::path::PathBuf::from(executable),
                 args,
                 working_dir: None,
                 env: vec![],
+                request_timeout: seconds_or(
+                    profile.and_then(|p| p.timeout_seconds),
+                    DEFAULT_REQUEST_TIMEOUT,
+                ),
+                framing: match profile.map(|p| p.plugin_framing).unwrap_or_default() {
+                    PluginFraming::Lines => Framing::Lines,
+                    PluginFraming::LengthPrefixed => Framing::LengthPrefixed,
+                },
             };
 
             let provider = ExecPluginProvider::new(plugin_config)?;
-            Ok(std::sync::Arc::new(provider))
+            Ok((std::sync::Arc::new(provider), None))
         }
         _ => Err(anyhow::anyhow!(
             "Unknown provider kind: '{}'",
@@ -344,11 +519,203 @@ fn create_provider(
     }
 }
 
+/// A configured number of seconds, or `default` when unset.
+fn seconds_or(seconds: Option<u64>, default: std::time::Duration) -> std::time::Duration {
+    seconds.map_or(default, std::time::Duration::from_secs)
+}
+
+/// The selected profile's `library_root`, or `./music` without one.
+fn filesystem_root<'a>(
+    selection: &ProviderSelection,
+    config: &'a Config,
+) -> Result<&'a str, anyhow::Error> {
+    let provider_config = config
+        .providers
+        .get(&selection.provider_id)
+        .ok_or_else(|| {
+            anyhow::anyhow!("Provider '{}' not found in config", selection.provider_id)
+        })?;
+    let Some(profile_name) = &selection.profile else {
+        return Ok("./music");
+    };
+    let profile = provider_config.profiles.get(profile_name).ok_or_else(|| {
+        anyhow::anyhow!(
+            "Profile '{}' not found for provider '{}'",
+            profile_name,
+            selection.provider_id
+        )
+    })?;
+    Ok(profile.library_root.as_deref().unwrap_or("./music"))
+}
+
+/// Counts from `tunez library scan`.
+#[derive(Debug, Serialize)]
+struct ScanReport {
+    root: String,
+    tracks: usize,
+    albums: usize,
+    artists: usize,
+    playlists: usize,
+    /// Paths the scan couldn't read, and why.
+    failures: Vec<ScanFailureLine>,
+}
+
+#[derive(Debug, Serialize)]
+struct ScanFailureLine {
+    path: String,
+    /// `permission denied`, `unreadable format` or `I/O error`.
+    kind: &'static str,
+    reason: String,
+}
+
+impl ScanReport {
+    fn print(&self) {
+        println!("Library: {}", self.root);
+        println!("  tracks:    {}", self.tracks);
+        println!("  albums:    {}", self.albums);
+        println!("  artists:   {}", self.artists);
+        println!("  playlists: {}", self.playlists);
+        if !self.failures.is_empty() {
+            println!("{} file(s) could not be read:", self.failures.len());
+            for failure in &self.failures {
+                println!("  {} ({})", failure.path, failure.kind);
+            }
+        }
+    }
+}
+
+fn scan_report(root: &Path) -> Result<ScanReport, anyhow::Error> {
+    if !root.is_dir() {
+        anyhow::bail!("library root {} does not exist", root.display());
+    }
+    let root = root.display().to_string();
+    let index = filesystem_provider::scan_library_with_options(
+        vec![root.clone()],
+        filesystem_provider::ScanOptions::default(),
+    )?;
+    let failures = index
+        .failures
+        .iter()
+        .map(|failure| ScanFailureLine {
+            path: failure.path.display().to_string(),
+            kind: failure.kind.label(),
+            reason: failure.reason.clone(),
+        })
+        .collect();
+    Ok(ScanReport {
+        root,
+        tracks: index.tracks.len(),
+        albums: index.albums.len(),
+        artists: index.artists.len(),
+        playlists: index.playlists.len(),
+        failures,
+    })
+}
+
+fn now_playing_file(config: &Config) -> Option<NowPlayingFile> {
+    let path = config.now_playing_output.clone()?;
+    let format = config
+        .now_playing_format
+        .as_deref()
+        .unwrap_or(DEFAULT_NOW_PLAYING_FORMAT);
+    Some(NowPlayingFile::new(path, format))
+}
+
+/// The track cache when `[cache] cache_tracks` or `[audio] transcode` is on,
+/// cleaned up first if `auto_cleanup` is set.
+fn create_track_cache(config: &Config, dirs: &AppDirs) -> Option<Arc<CacheManager>> {
+    let cache = &config.cache;
+    let transcoder = config.audio.transcode.then(find_transcoder).flatten();
+    if !cache.cache_tracks && transcoder.is_none() {
+        return None;
+    }
+    let download_dir = cache
+        .download_dir
+        .as_ref()
+        .map(PathBuf::from)
+        .unwrap_or_else(|| dirs.download_dir().to_path_buf());
+    let mut manager = CacheManager::new(
+        download_dir,
+        CachePolicy {
+            max_size_bytes: cache.max_size_bytes,
+            max_age_seconds: cache.max_age_seconds,
+            enabled: cache.auto_cleanup,
+        },
+    );
+    if let Some(transcoder) = transcoder {
+        manager = manager.with_transcoder(transcoder);
+    }
+    if manager.download_dir().exists() {
+        match manager.enforce_policy() {
+            Ok(removed) if !removed.is_empty() => {
+                tracing::info!("Removed {} expired cached track(s)", removed.len())
+            }
+            Ok(_) => {}
+            Err(e) => tracing::warn!("Track cache cleanup failed: {}", e),
+        }
+    }
+    Some(Arc::new(manager))
+}
+
+/// ffmpeg for `[audio] transcode`, or a warning that streams will play as
+/// they are.
+fn find_transcoder() -> Option<Transcoder> {
+    let transcoder = Transcoder::detect();
+    if transcoder.is_none() {
+        tracing::warn!("transcode is on but ffmpeg wasn't found; playing streams directly");
+    }
+    transcoder
+}
+
+/// `mpris = true`, or a warning that this build can't serve it.
+fn mpris_enabled(config: &Config) -> bool {
+    if config.mpris && !cfg!(feature = "mpris") {
+        tracing::warn!(
+            "mpris is on but this build has no MPRIS support; rebuild with --features mpris"
+        );
+        return false;
+    }
+    config.mpris
+}
+
 fn create_scrobbler(
     selection: &ProviderSelection,
     config: &Config,
     dirs: &AppDirs,
+    melodee_auth: Option<Arc<MelodeeAuth>>,
 ) -> Result<Option<Arc<dyn Scrobbler>>, anyhow::Error> {
+    Ok(
+        create_persistent_scrobbler(selection, config, dirs, melodee_auth)?
+            .map(|scrobbler| Arc::new(scrobbler) as Arc<dyn Scrobbler>),
+    )
+}
+
+/// The configured scrobbler wrapped in its on-disk retry queue. A Melodee
+/// scrobbler uses `melodee_auth` when given, so it and the provider refresh
+/// the same tokens.
+fn create_persistent_scrobbler(
+    selection: &ProviderSelection,
+    config: &Config,
+    dirs: &AppDirs,
+    melodee_auth: Option<Arc<MelodeeAuth>>,
+) -> Result<Option<PersistentScrobbler<Arc<dyn Scrobbler>>>, anyhow::Error> {
+    if config.scrobbling.default.as_deref() == Some("lastfm") {
+        return match LastfmScrobbler::from_credential_store(None) {
+            Ok(remote) => {
+                let remote = remote.with_timeout(seconds_or(
+                    config.scrobbling.timeout_seconds,
+                    lastfm_scrobbler::DEFAULT_TIMEOUT,
+                ));
+                let path = dirs.data_dir().join("scrobbles.lastfm.jsonl");
+                Ok(Some(PersistentScrobbler::new(Arc::new(remote), path, 1000)))
+            }
+            Err(err) => {
+                tracing::warn!(error = %err, "Last.fm scrobbling disabled");
+                Ok(None)
+            }
+        };
+    }
+
     let provider_config = config.providers.get(&selection.provider_id);
     // If provider config missing, create_provider would handle it, here we just return None
     let provider_config = match provider_config {
@@ -370,48 +737,80 @@ fn create_scrobbler(
             return Ok(None);
         };
 
-        let remote = MelodeeScrobbler::new(base_url, selection.profile.clone(), None);
+        let timeout = seconds_or(
+            config.scrobbling.timeout_seconds,
+            melodee_scrobbler::DEFAULT_TIMEOUT,
+        );
+        let mut remote =
+            MelodeeScrobbler::new(base_url, selection.profile.clone(), None).with_timeout(timeout);
+        if let Some(auth) = melodee_auth {
+            remote = remote.with_auth(auth);
+        }
         let path = dirs.data_dir().join("scrobbles.jsonl");
-
-        // PersistentScrobbler new(id, path, batch_size, player_name, device_id, wrapped)
-        // Check PersistentScrobbler::new signature.
-        // It wraps a wrapped scrobbler? No, wait.
-        // Previously FileScrobbler was standalone.
-        // Refactor in scrobbler.rs introduced PersistentScrobbler<S: Scrobbler>.
-        // Constructor: PersistentScrobbler::new(wrapped: S, path: PathBuf).
-        // I need to verify PersistentScrobbler::new signature.
-
-        let persistent = PersistentScrobbler::new(remote, path, 1000);
-
-        Ok(Some(Arc::new(persistent)))
+        Ok(Some(PersistentScrobbler::new(Arc::new(remote), path, 1000)))
     } else {
         Ok(None)
     }
 }
 
+/// A configured provider as `tunez providers` lists it.
+#[derive(Debug, PartialEq, Eq)]
+struct ProviderLine {
+    id: String,
+    is_default: bool,
+    /// In name order.
+    profiles: Vec<ProfileLine>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct ProfileLine {
+    name: String,
+    is_default: bool,
+}
+
+/// Configured providers in id order, marking the ones started without
+/// `--provider`/`--profile`. The default profile only applies to the
+/// default provider.
+fn provider_listing(config: &Config) -> Vec<ProviderLine> {
+    config
+        .providers
+        .iter()
+        .map(|(id, provider)| {
+            let is_default = config.default_provider.as_deref() == Some(id.as_str());
+            ProviderLine {
+                id: id.clone(),
+                is_default,
+                profiles: provider
+                    .profiles
+                    .keys()
+                    .map(|name| ProfileLine {
+                        name: name.clone(),
+                        is_default: is_default && config.profile.as_deref() == Some(name.as_str()),
+                    })
+                    .collect(),
+            }
+        })
+        .collect()
+}
+
 fn print_providers(config: &Config) {
-    if config.providers.is_empty() {
-        println!("No providers configured. Set providers.<id> in config.toml.");
+    let listing = provider_listing(config);
+    if listing.is_empty() {
+        println!(
+            "No providers configured. Set providers.<id> in config.toml \
+             (run `tunez config init` to create one)."
+        );
         return;
     }
 
-    for (id, provider) in &config.providers {
-        let default_marker = if config.default_provider.as_deref() == Some(id) {
-            " (default)"
-        } else {
-            ""
-        };
-
-        println!("Provider: {}{}", id, default_marker);
+    let marker = |is_default: bool| if is_default { " (default)" } else { "" };
+    for provider in listing {
+        println!("Provider: {}{}", provider.id, marker(provider.is_default));
         if provider.profiles.is_empty() {
             println!("  profiles: (none configured)");
-        } else {
-            for profile in provider.profiles.keys() {
-                let is_default_profile = config.profile.as_deref() == Some(profile)
-                    && config.default_provider.as_deref() == Some(id);
-                let marker = if is_default_profile { " (default)" } else { "" };
-                println!("  - {}{}", profile, marker);
-            }
+        }
+        for profile in provider.profiles {
+            println!("  - {}{}", profile.name, marker(profile.is_default));
         }
     }
 }
@@ -434,10 +833,103 @@ mod tests {
             },
         );
 
-        let mut config = Config::default();
-        config.default_provider = Some(provider_id.to_string());
-        config.providers = providers;
-        config
+        Config {
+            default_provider: Some(provider_id.to_string()),
+            providers,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn provider_listing_marks_the_default_provider_and_profile() {
+        let mut config = config_with_provider("melodee", "work");
+        config.profile = Some("work".into());
+        let melodee = config.providers.get_mut("melodee").unwrap();
+        melodee
+            .profiles
+            .insert("home".into(), ProviderProfile::default());
+        // Same profile name under a provider that isn't the default.
+        let mut profiles = BTreeMap::new();
+        profiles.insert("work".to_string(), ProviderProfile::default());
+        config.providers.insert(
+            "backup".into(),
+            ProviderConfig {
+                kind: Some("melodee".into()),
+                profiles,
+            },
+        );
+        config.providers.insert(
+            "filesystem".into(),
+            ProviderConfig {
+                kind: Some("filesystem".into()),
+                profiles: BTreeMap::new(),
+            },
+        );
+
+        let profile = |name: &str, is_default| ProfileLine {
+            name: name.into(),
+            is_default,
+        };
+        assert_eq!(
+            provider_listing(&config),
+            vec![
+                ProviderLine {
+                    id: "backup".into(),
+                    is_default: false,
+                    profiles: vec![profile("work", false)],
+                },
+                ProviderLine {
+                    id: "filesystem".into(),
+                    is_default: false,
+                    profiles: vec![],
+                },
+                ProviderLine {
+                    id: "melodee".into(),
+                    is_default: true,
+                    profiles: vec![profile("home", false), profile("work", true)],
+                },
+            ]
+        );
+
+        assert!(provider_listing(&Config::default()).is_empty());
+    }
+
+    #[test]
+    fn mpris_follows_the_config_only_in_builds_that_have_it() {
+        let config = Config {
+            mpris: true,
+            ..Config::default()
+        };
+        assert_eq!(mpris_enabled(&config), cfg!(feature = "mpris"));
+        assert!(!mpris_enabled(&Config::default()));
+    }
+
+    #[test]
+    fn scan_report_counts_library_and_flags_unreadable_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let album = dir.path().join("Artist").join("Album");
+        std::fs::create_dir_all(&album).unwrap();
+        std::fs::write(album.join("01 - One.mp3"), b"not really audio").unwrap();
+        std::fs::write(album.join("02 - Two.flac"), b"nor this").unwrap();
+        std::fs::write(album.join("cover.jpg"), b"ignored").unwrap();
+        std::fs::write(dir.path().join("mix.m3u"), "Artist/Album/01 - One.mp3\n").unwrap();
+
+        let report = scan_report(dir.path()).unwrap();
+        assert_eq!(report.tracks, 2);
+        assert_eq!(report.albums, 1);
+        assert_eq!(report.artists, 1);
+        assert_eq!(report.playlists, 1);
+        assert_eq!(report.failures.len(), 2);
+        assert!(report
+            .failures
+            .iter()
+            .all(|failure| failure.kind == "unreadable format"));
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["tracks"], 2);
+        assert_eq!(json["failures"][0]["kind"], "unreadable format");
+
+        assert!(scan_report(&dir.path().join("missing")).is_err());
     }
 
     #[test]
@@ -539,4 +1031,41 @@ mod tests {
         assert_eq!(intent.selector.describe(), "track=\"song\"");
         assert!(intent.autoplay);
     }
+
+    #[test]
+    fn missing_library_root_starts_the_ui_without_a_provider() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = config_with_provider("filesystem", "home");
+        let library = dir.path().join("missing");
+        config
+            .providers
+            .get_mut("filesystem")
+            .unwrap()
+            .profiles
+            .insert(
+                "home".into(),
+                ProviderProfile {
+                    library_root: Some(library.display().to_string()),
+                    ..Default::default()
+                },
+            );
+        let selection = config
+            .resolve_provider_selection(None, Some("home"))
+            .unwrap();
+        let dirs = AppDirs::under(dir.path());
+
+        let ctx = ui_context(selection.clone(), &config, &dirs).expect("UI still starts");
+        let error = ctx
+            .provider_error
+            .as_deref()
+            .expect("error kept for the UI");
+        assert!(error.contains("not a directory"), "{error}");
+        assert!(ctx.provider.health_check().is_err());
+        assert_eq!(ctx.provider_options, vec![selection.clone()]);
+
+        // Once the directory exists the Config tab can start it.
+        std::fs::create_dir(&library).unwrap();
+        let connect = ctx.connect_provider.expect("connector for the Config tab");
+        assert!(connect(&selection).is_ok());
+    }
 }
@@ -2,11 +2,19 @@
 //!
 //! Handles offline download storage and automatic cleanup based on size/age policies.
 
+use crate::models::{StreamLocation, StreamUrl, TrackId};
+use crate::transcode::Transcoder;
+use std::collections::{HashMap, HashSet};
 use std::fs;
+use std::io;
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
 use std::time::{Duration, SystemTime};
 use thiserror::Error;
 
+const DOWNLOAD_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const DOWNLOAD_READ_TIMEOUT: Duration = Duration::from_secs(30);
+
 #[derive(Debug, Error)]
 pub enum CacheError {
     #[error("failed to read cache directory: {0}")]
@@ -20,6 +28,14 @@ pub enum CacheError {
     },
     #[error("cache directory not found")]
     NotFound,
+    /// Only the host is kept: stream URLs may carry credentials in their
+    /// path or query.
+    #[error("failed to download from {host}: {message}")]
+    Download { host: String, message: String },
+    #[error("failed to write cache file {path}: {error}")]
+    Write { path: PathBuf, error: io::Error },
+    #[error("failed to transcode {path}: {error}")]
+    Transcode { path: PathBuf, error: io::Error },
 }
 
 pub type CacheResult<T> = Result<T, CacheError>;
@@ -49,6 +65,13 @@ impl Default for CachePolicy {
 pub struct CacheManager {
     download_dir: PathBuf,
     policy: CachePolicy,
+    /// Converts downloads to FLAC before they are kept.
+    transcoder: Option<Transcoder>,
+    /// Cached file per cache key, read from the directory on first use and
+    /// kept current as tracks are cached and evicted.
+    index: Mutex<Option<HashMap<String, PathBuf>>>,
+    /// Cache keys being downloaded right now.
+    in_flight: Mutex<HashSet<String>>,
 }
 
 impl CacheManager {
@@ -56,11 +79,24 @@ impl CacheManager {
         Self {
             download_dir,
             policy,
+            transcoder: None,
+            index: Mutex::new(None),
+            in_flight: Mutex::new(HashSet::new()),
         }
     }
 
+    /// Keep downloads as FLAC converted by `transcoder`.
+    pub fn with_transcoder(mut self, transcoder: Transcoder) -> Self {
+        self.transcoder = Some(transcoder);
+        self
+    }
+
     /// Enforce cache eviction policy
     pub fn enforce_policy(&self) -> CacheResult<Vec<PathBuf>> {
+        self.enforce_policy_keeping(None)
+    }
+
+    fn enforce_policy_keeping(&self, keep: Option<&Path>) -> CacheResult<Vec<PathBuf>> {
         if !self.policy.enabled {
             return Ok(Vec::new());
         }
@@ -70,14 +106,21 @@ impl CacheManager {
         }
 
         let mut removed = Vec::new();
+        let downloading = self
+            .in_flight
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
 
         // Get all files in download directory
         let entries: Vec<_> = fs::read_dir(&self.download_dir)
             .map_err(CacheError::ReadDir)?
             .filter_map(|entry| entry.ok())
             .filter(|entry| {
-                // Only consider files, not directories
-                entry.path().is_file()
+                // Only consider files, not directories, and leave the
+                // `.part` and `-source` files of running downloads alone.
+                let path = entry.path();
+                path.is_file() && !is_being_written(&path, &downloading)
             })
             .filter_map(|entry| {
                 let path = entry.path();
@@ -119,9 +162,9 @@ impl CacheManager {
                 // Sort by modification time (oldest first)
                 let mut to_remove: Vec<_> = entries
                     .into_iter()
-                    .filter(|(path, _, _)| !removed.contains(path))
+                    .filter(|(path, _, _)| !removed.contains(path) && Some(path.as_path()) != keep)
                     .collect();
-                to_remove.sort_by(|a, b| a.1.cmp(&b.1));
+                to_remove.sort_by_key(|entry| entry.1);
 
                 let size_to_free = total_size - self.policy.max_size_bytes;
                 let mut freed = 0u64;
@@ -142,6 +185,11 @@ impl CacheManager {
             }
         }
 
+        if !removed.is_empty() {
+            if let Some(index) = self.index().as_mut() {
+                index.retain(|_, path| !removed.contains(path));
+            }
+        }
         Ok(removed)
     }
 
@@ -180,6 +228,273 @@ impl CacheManager {
     pub fn download_dir(&self) -> &Path {
         &self.download_dir
     }
+
+    /// The cached copy of `track_id` as a `file://` URL, if one exists. A hit
+    /// refreshes the file's modification time so eviction treats it as recent.
+    pub fn cached_url(&self, track_id: &TrackId) -> Option<StreamUrl> {
+        let path = self.cached_path(track_id)?;
+        if let Ok(file) = fs::File::options().write(true).open(&path) {
+            let _ = file.set_modified(SystemTime::now());
+        }
+        Some(StreamUrl::file(&path))
+    }
+
+    /// Download `stream_url` into the cache unless `track_id` is already
+    /// cached, returning a `file://` URL for the local copy. Non-HTTP URLs are
+    /// returned unchanged, as is `stream_url` while another call is already
+    /// downloading the same track. With a transcoder the download is
+    /// converted to FLAC and only that is kept. When the policy is enabled it
+    /// is enforced after a download, never evicting the file just written.
+    pub fn cache_track(
+        &self,
+        track_id: &TrackId,
+        stream_url: &StreamUrl,
+    ) -> CacheResult<StreamUrl> {
+        if let Some(url) = self.cached_url(track_id) {
+            return Ok(url);
+        }
+        if stream_url.location() != StreamLocation::Http {
+            return Ok(stream_url.clone());
+        }
+        let key = cache_key(track_id);
+        let Some(_downloading) = InFlight::begin(&self.in_flight, &key) else {
+            return Ok(stream_url.clone());
+        };
+        // The other download may have finished between the lookup and now.
+        if let Some(url) = self.cached_url(track_id) {
+            return Ok(url);
+        }
+        let url = stream_url.as_ref();
+
+        fs::create_dir_all(&self.download_dir).map_err(|error| CacheError::Write {
+            path: self.download_dir.clone(),
+            error,
+        })?;
+        let path = match (&self.transcoder, url_extension(url)) {
+            (Some(_), _) => self.download_dir.join(format!("{key}.flac")),
+            (None, Some(ext)) => self.download_dir.join(format!("{key}.{ext}")),
+            (None, None) => self.download_dir.join(&key),
+        };
+        match &self.transcoder {
+            Some(transcoder) => {
+                // Named off the key so a leftover is never taken for the track.
+                let source = self.download_dir.join(format!("{key}-source"));
+                download(url, &source)?;
+                let result = transcode(transcoder, &source, &path);
+                let _ = fs::remove_file(&source);
+                result?;
+            }
+            None => download(url, &path)?,
+        }
+        tracing::info!("Cached {} at {}", track_id.0, path.display());
+        if let Some(index) = self.index().as_mut() {
+            index.insert(key, path.clone());
+        }
+
+        if self.policy.enabled {
+            let removed = self.enforce_policy_keeping(Some(&path))?;
+            if !removed.is_empty() {
+                tracing::debug!("Evicted {} cached file(s)", removed.len());
+            }
+        }
+        Ok(StreamUrl::file(&path))
+    }
+
+    /// Fill the cache with `track_id` from `stream_url` on a thread of its
+    /// own, so the caller can stream the remote URL meanwhile. Does nothing
+    /// when the track is cached or already downloading; failures are logged.
+    pub fn cache_in_background(self: &Arc<Self>, track_id: &TrackId, stream_url: &StreamUrl) {
+        if stream_url.location() != StreamLocation::Http
+            || self.cached_path(track_id).is_some()
+            || self.is_downloading(track_id)
+        {
+            return;
+        }
+        let cache = Arc::clone(self);
+        let track_id = track_id.clone();
+        let stream_url = stream_url.clone();
+        std::thread::spawn(move || {
+            if let Err(e) = cache.cache_track(&track_id, &stream_url) {
+                tracing::warn!("Failed to cache {}: {}", track_id.0, e);
+            }
+        });
+    }
+
+    /// Whether a download of `track_id` into the cache is under way.
+    pub fn is_downloading(&self, track_id: &TrackId) -> bool {
+        self.in_flight
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .contains(&cache_key(track_id))
+    }
+
+    fn cached_path(&self, track_id: &TrackId) -> Option<PathBuf> {
+        let key = cache_key(track_id);
+        let mut index = self.index();
+        let index = index.get_or_insert_with(|| self.scan());
+        let path = index.get(&key)?;
+        if path.is_file() {
+            return Some(path.clone());
+        }
+        // Deleted behind our back.
+        index.remove(&key);
+        None
+    }
+
+    fn index(&self) -> MutexGuard<'_, Option<HashMap<String, PathBuf>>> {
+        self.index.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Cached files in the download directory by cache key, skipping
+    /// unfinished `.part` files.
+    fn scan(&self) -> HashMap<String, PathBuf> {
+        let Ok(entries) = fs::read_dir(&self.download_dir) else {
+            return HashMap::new();
+        };
+        entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.is_file() && path.extension().and_then(|ext| ext.to_str()) != Some("part")
+            })
+            .filter_map(|path| {
+                let key = path.file_stem()?.to_str()?.to_string();
+                Some((key, path))
+            })
+            .collect()
+    }
+}
+
+/// Marks a cache key as downloading until dropped, so two callers never
+/// write the same `.part` file.
+struct InFlight<'a> {
+    keys: &'a Mutex<HashSet<String>>,
+    key: String,
+}
+
+impl<'a> InFlight<'a> {
+    /// `None` when `key` is already downloading.
+    fn begin(keys: &'a Mutex<HashSet<String>>, key: &str) -> Option<Self> {
+        let inserted = keys
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(key.to_string());
+        inserted.then(|| Self {
+            keys,
+            key: key.to_string(),
+        })
+    }
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.keys
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&self.key);
+    }
+}
+
+/// Whether `path` is a `.part` or `-source` file that one of the
+/// `downloading` cache keys is still writing. A leftover from an earlier run
+/// has no download behind it and is fair game.
+fn is_being_written(path: &Path, downloading: &HashSet<String>) -> bool {
+    let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+        return false;
+    };
+    let temporary = name.ends_with(".part") || name.contains("-source");
+    temporary
+        && name
+            .split(['.', '-'])
+            .next()
+            .is_some_and(|key| downloading.contains(key))
+}
+
+/// Stable file name for a track. Track ids may be paths or other strings
+/// that are not valid file names, so they are hashed (64-bit FNV-1a).
+fn cache_key(track_id: &TrackId) -> String {
+    let hash = track_id
+        .0
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        });
+    format!("{hash:016x}")
+}
+
+/// Short alphanumeric extension of the URL's last path segment, kept so the
+/// decoder can use it as a format hint.
+fn url_extension(url: &str) -> Option<&str> {
+    let path = url.split(['?', '#']).next()?;
+    let (_, file_name) = path.rsplit_once('/')?;
+    let (_, ext) = file_name.rsplit_once('.')?;
+    (!ext.is_empty() && ext.len() <= 5 && ext.chars().all(|c| c.is_ascii_alphanumeric()))
+        .then_some(ext)
+}
+
+/// Host (and port) of `url`, without any user info.
+fn url_host(url: &str) -> &str {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host)
+}
+
+/// Fetch `url` into `path` via a `.part` file so an interrupted download never
+/// looks like a cached track.
+fn download(url: &str, path: &Path) -> CacheResult<()> {
+    let agent = ureq::AgentBuilder::new()
+        .timeout_connect(DOWNLOAD_CONNECT_TIMEOUT)
+        .timeout_read(DOWNLOAD_READ_TIMEOUT)
+        .build();
+    let response = agent.get(url).call().map_err(|err| CacheError::Download {
+        host: url_host(url).to_string(),
+        message: match err {
+            ureq::Error::Status(code, _) => format!("HTTP {code}"),
+            ureq::Error::Transport(transport) => transport.kind().to_string(),
+        },
+    })?;
+
+    let partial = path.with_extension("part");
+    let write_error = |error| CacheError::Write {
+        path: partial.clone(),
+        error,
+    };
+    let result = fs::File::create(&partial)
+        .and_then(|mut file| io::copy(&mut response.into_reader(), &mut file))
+        .map_err(write_error)
+        .and_then(|_| {
+            fs::rename(&partial, path).map_err(|error| CacheError::Write {
+                path: path.to_path_buf(),
+                error,
+            })
+        });
+    if result.is_err() {
+        let _ = fs::remove_file(&partial);
+    }
+    result
+}
+
+/// Convert `source` to FLAC at `path`, via a `.part` file like `download`.
+fn transcode(transcoder: &Transcoder, source: &Path, path: &Path) -> CacheResult<()> {
+    let partial = path.with_extension("part");
+    let result = transcoder
+        .to_flac(source, &partial)
+        .map_err(|error| CacheError::Transcode {
+            path: source.to_path_buf(),
+            error,
+        })
+        .and_then(|()| {
+            fs::rename(&partial, path).map_err(|error| CacheError::Write {
+                path: path.to_path_buf(),
+                error,
+            })
+        });
+    if result.is_err() {
+        let _ = fs::remove_file(&partial);
+    }
+    result
 }
 
 #[derive(Debug, Default)]
@@ -194,9 +509,34 @@ pub struct CacheStats {
 mod tests {
     use super::*;
     use std::fs::File;
-    use std::io::Write;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
     use tempfile::tempdir;
 
+    /// Serve a single HTTP 200 response with `body` and return its base URL.
+    fn serve_once(body: Vec<u8>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buf = [0u8; 1024];
+            let _ = stream.read(&mut buf);
+            let header = format!(
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                body.len()
+            );
+            let _ = stream.write_all(header.as_bytes());
+            let _ = stream.write_all(&body);
+        });
+        format!("http://{addr}")
+    }
+
+    fn write_aged(path: &Path, len: usize, age: Duration) {
+        let file = File::create(path).unwrap();
+        file.set_len(len as u64).unwrap();
+        file.set_modified(SystemTime::now() - age).unwrap();
+    }
+
     #[test]
     fn test_cache_stats() {
         let dir = tempdir().unwrap();
@@ -247,4 +587,256 @@ mod tests {
         assert_eq!(removed.len(), 1);
         assert!(!file_path.exists());
     }
+
+    #[test]
+    fn cache_track_downloads_into_cache_dir() {
+        let dir = tempdir().unwrap();
+        let manager = CacheManager::new(dir.path().join("downloads"), CachePolicy::default());
+        let track = TrackId::new("remote-1");
+        assert!(manager.cached_url(&track).is_none());
+
+        let base = serve_once(b"audio bytes".to_vec());
+        let url = manager
+            .cache_track(&track, &StreamUrl::new(format!("{base}/song.mp3?token=x")))
+            .unwrap();
+
+        let path = PathBuf::from(url.0.strip_prefix("file://").unwrap());
+        assert!(path.starts_with(dir.path().join("downloads")));
+        assert_eq!(path.extension().unwrap(), "mp3");
+        assert_eq!(fs::read(&path).unwrap(), b"audio bytes");
+        assert_eq!(manager.cached_url(&track), Some(url.clone()));
+
+        // A second request is served from the cache without hitting the network.
+        let again = manager
+            .cache_track(&track, &StreamUrl::new("http://127.0.0.1:9/unreachable"))
+            .unwrap();
+        assert_eq!(again, url);
+    }
+
+    /// A mono 16-bit WAV file of a short tone.
+    fn wav_tone() -> Vec<u8> {
+        let samples: Vec<i16> = (0..8000)
+            .map(|i| ((i as f32 * 0.06).sin() * 8000.0) as i16)
+            .collect();
+        let data_len = (samples.len() * 2) as u32;
+        let mut wav = b"RIFF".to_vec();
+        wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+        wav.extend_from_slice(b"WAVEfmt ");
+        wav.extend_from_slice(&16u32.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&8000u32.to_le_bytes());
+        wav.extend_from_slice(&16000u32.to_le_bytes());
+        wav.extend_from_slice(&2u16.to_le_bytes());
+        wav.extend_from_slice(&16u16.to_le_bytes());
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&data_len.to_le_bytes());
+        wav.extend(samples.iter().flat_map(|s| s.to_le_bytes()));
+        wav
+    }
+
+    #[test]
+    #[ignore = "needs ffmpeg on PATH"]
+    fn cache_track_transcodes_with_ffmpeg() {
+        let ffmpeg = Transcoder::detect().expect("ffmpeg on PATH");
+        let dir = tempdir().unwrap();
+        let manager = CacheManager::new(dir.path().to_path_buf(), CachePolicy::default())
+            .with_transcoder(ffmpeg);
+        let base = serve_once(wav_tone());
+        let url = manager
+            .cache_track(
+                &TrackId::new("tone"),
+                &StreamUrl::new(format!("{base}/a.wav")),
+            )
+            .unwrap();
+
+        let path = PathBuf::from(url.0.strip_prefix("file://").unwrap());
+        assert_eq!(path.extension().unwrap(), "flac");
+        assert!(fs::read(&path).unwrap().starts_with(b"fLaC"));
+        assert_eq!(manager.get_stats().unwrap().file_count, 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn transcoded_downloads_replace_the_source() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempdir().unwrap();
+        // Stands in for ffmpeg: answers -version and "converts" by copying
+        // its input after a FLAC marker.
+        let script = dir.path().join("fake-ffmpeg");
+        fs::write(
+            &script,
+            "#!/bin/sh\n[ \"$1\" = -version ] && exit 0\n\
+             for a; do out=$a; done\n\
+             while [ \"$1\" != -i ]; do shift; done\n\
+             { printf fLaC; cat \"$2\"; } > \"$out\"\n",
+        )
+        .unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+        let transcoder = Transcoder::find(&script).unwrap();
+
+        let cache_dir = dir.path().join("cache");
+        let manager = CacheManager::new(cache_dir.clone(), CachePolicy::default())
+            .with_transcoder(transcoder);
+        let base = serve_once(b"opus bytes".to_vec());
+        let url = manager
+            .cache_track(
+                &TrackId::new("t"),
+                &StreamUrl::new(format!("{base}/a.opus")),
+            )
+            .unwrap();
+
+        let path = PathBuf::from(url.0.strip_prefix("file://").unwrap());
+        assert_eq!(path.extension().unwrap(), "flac");
+        assert_eq!(fs::read(&path).unwrap(), b"fLaCopus bytes");
+        let files: Vec<_> = fs::read_dir(&cache_dir).unwrap().collect();
+        assert_eq!(files.len(), 1, "only the FLAC is kept");
+        assert_eq!(manager.cached_url(&TrackId::new("t")), Some(url));
+    }
+
+    #[test]
+    fn missing_ffmpeg_leaves_streams_as_they_are() {
+        let dir = tempdir().unwrap();
+        assert_eq!(Transcoder::find(dir.path().join("no-such-ffmpeg")), None);
+
+        // Without a transcoder, downloads are kept in their own format.
+        let manager = CacheManager::new(dir.path().to_path_buf(), CachePolicy::default());
+        let base = serve_once(b"opus bytes".to_vec());
+        let url = manager
+            .cache_track(
+                &TrackId::new("t"),
+                &StreamUrl::new(format!("{base}/a.opus")),
+            )
+            .unwrap();
+        assert!(url.0.ends_with(".opus"));
+    }
+
+    #[test]
+    fn cache_track_leaves_local_urls_alone() {
+        let dir = tempdir().unwrap();
+        let manager = CacheManager::new(dir.path().to_path_buf(), CachePolicy::default());
+        let url = StreamUrl::new("file:///music/a.flac");
+        assert_eq!(manager.cache_track(&TrackId::new("a"), &url).unwrap(), url);
+        assert_eq!(manager.get_stats().unwrap().file_count, 0);
+    }
+
+    #[test]
+    fn download_errors_name_only_the_host() {
+        let dir = tempdir().unwrap();
+        let manager = CacheManager::new(dir.path().to_path_buf(), CachePolicy::default());
+        let url = StreamUrl::new("http://me:pw@127.0.0.1:9/song/stream/1/u/secret");
+        let message = manager
+            .cache_track(&TrackId::new("t"), &url)
+            .unwrap_err()
+            .to_string();
+        assert!(message.contains("127.0.0.1:9"), "{message}");
+        assert!(
+            !message.contains("secret") && !message.contains("pw"),
+            "{message}"
+        );
+    }
+
+    #[test]
+    fn a_track_already_downloading_is_streamed_uncached() {
+        let dir = tempdir().unwrap();
+        let manager = CacheManager::new(dir.path().to_path_buf(), CachePolicy::default());
+        let track = TrackId::new("t");
+        let _downloading = InFlight::begin(&manager.in_flight, &cache_key(&track)).unwrap();
+        assert!(manager.is_downloading(&track));
+
+        // Nothing listens here: a second download would fail.
+        let url = StreamUrl::new("http://127.0.0.1:9/a.flac");
+        assert_eq!(manager.cache_track(&track, &url).unwrap(), url);
+        assert_eq!(manager.get_stats().unwrap().file_count, 0);
+    }
+
+    #[test]
+    fn background_caching_fills_the_cache() {
+        let dir = tempdir().unwrap();
+        let manager = Arc::new(CacheManager::new(
+            dir.path().to_path_buf(),
+            CachePolicy::default(),
+        ));
+        let track = TrackId::new("t");
+        let base = serve_once(b"flac bytes".to_vec());
+        manager.cache_in_background(&track, &StreamUrl::new(format!("{base}/a.flac")));
+
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        let url = loop {
+            if let Some(url) = manager.cached_url(&track) {
+                break url;
+            }
+            assert!(std::time::Instant::now() < deadline, "never cached");
+            std::thread::sleep(Duration::from_millis(10));
+        };
+        assert!(url.0.ends_with(".flac"));
+        assert!(!manager.is_downloading(&track));
+    }
+
+    #[test]
+    fn cache_lookups_notice_files_deleted_behind_their_back() {
+        let dir = tempdir().unwrap();
+        let manager = CacheManager::new(dir.path().to_path_buf(), CachePolicy::default());
+        let track = TrackId::new("t");
+        let path = dir.path().join(format!("{}.flac", cache_key(&track)));
+        fs::write(&path, b"flac bytes").unwrap();
+        assert_eq!(manager.cached_url(&track), Some(StreamUrl::file(&path)));
+
+        fs::remove_file(&path).unwrap();
+        assert_eq!(manager.cached_url(&track), None);
+    }
+
+    #[test]
+    fn eviction_leaves_running_downloads_alone() {
+        let dir = tempdir().unwrap();
+        let policy = CachePolicy {
+            max_size_bytes: 100,
+            max_age_seconds: 60,
+            enabled: true,
+        };
+        let manager = CacheManager::new(dir.path().to_path_buf(), policy);
+        let key = cache_key(&TrackId::new("t"));
+        let _downloading = InFlight::begin(&manager.in_flight, &key).unwrap();
+        let part = dir.path().join(format!("{key}.part"));
+        let source = dir.path().join(format!("{key}-source.part"));
+        let leftover = dir
+            .path()
+            .join(format!("{}.part", cache_key(&TrackId::new("u"))));
+        for path in [&part, &source, &leftover] {
+            write_aged(path, 400, Duration::from_secs(300));
+        }
+
+        let removed = manager.enforce_policy().unwrap();
+        assert_eq!(removed, vec![leftover.clone()]);
+        assert!(part.exists());
+        assert!(source.exists());
+    }
+
+    #[test]
+    fn oversize_cache_evicts_oldest_files() {
+        let dir = tempdir().unwrap();
+        let policy = CachePolicy {
+            max_size_bytes: 1000,
+            max_age_seconds: 0,
+            enabled: true,
+        };
+        let manager = CacheManager::new(dir.path().to_path_buf(), policy);
+        let oldest = dir.path().join("oldest");
+        let older = dir.path().join("older");
+        write_aged(&oldest, 400, Duration::from_secs(300));
+        write_aged(&older, 400, Duration::from_secs(200));
+
+        let base = serve_once(vec![0u8; 500]);
+        let url = manager
+            .cache_track(
+                &TrackId::new("new"),
+                &StreamUrl::new(format!("{base}/new.flac")),
+            )
+            .unwrap();
+
+        assert!(!oldest.exists());
+        assert!(older.exists());
+        assert!(Path::new(url.0.strip_prefix("file://").unwrap()).exists());
+        assert_eq!(manager.get_stats().unwrap().total_size, 900);
+    }
 }
@@ -1,11 +1,41 @@
 use crate::paths::AppDirs;
+use crate::replay_gain::ReplayGainMode;
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use thiserror::Error;
 
-const CURRENT_CONFIG_VERSION: u32 = 1;
+const CURRENT_CONFIG_VERSION: u32 = 2;
+
+/// Upgrades a raw config table from version `n` to `n + 1`, where `n` is the
+/// index plus one.
+const MIGRATIONS: &[fn(&mut toml::Table)] = &[migrate_v1_to_v2];
+
+/// Commented starting config written by `tunez config init`.
+pub const DEFAULT_CONFIG_TEMPLATE: &str = include_str!("default_config.toml");
+
+/// Action names accepted as keys of the `[keybindings]` section, in the order
+/// the UI's key map declares its actions; the key map takes its names from here.
+pub const KEYBINDING_ACTIONS: &[&str] = &[
+    "quit",
+    "help",
+    "play_pause",
+    "next",
+    "prev",
+    "search",
+    "cycle_viz",
+    "cycle_theme",
+    "cycle_repeat",
+    "toggle_shuffle",
+    "seek_forward",
+    "seek_back",
+    "seek_start",
+    "seek_end",
+    "next_chapter",
+    "prev_chapter",
+    "toggle_favorite",
+];
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
@@ -16,15 +46,37 @@ pub struct Config {
     #[serde(default)]
     pub profile: Option<String>,
     #[serde(default)]
-    pub default_scrobbler: Option<String>,
+    pub scrobbling: ScrobblingConfig,
     #[serde(default)]
-    pub theme: Option<String>,
+    pub theme: Option<ThemeSetting>,
     #[serde(default)]
     pub logging: LoggingConfig,
     #[serde(default)]
     pub providers: BTreeMap<String, ProviderConfig>,
     #[serde(default)]
     pub cache: CacheConfig,
+    #[serde(default)]
+    pub replay_gain: ReplayGainConfig,
+    #[serde(default)]
+    pub audio: AudioConfig,
+    #[serde(default)]
+    pub layout: LayoutConfig,
+    /// Action name to key string (e.g. `next = "ctrl+n"`); unset actions keep
+    /// their default keys.
+    #[serde(default)]
+    pub keybindings: BTreeMap<String, String>,
+    /// File the playing track is written to for other programs, such as a
+    /// streaming overlay, and emptied on stop.
+    #[serde(default)]
+    pub now_playing_output: Option<PathBuf>,
+    /// Template for that file, with `{artist}`, `{title}` and `{album}`
+    /// placeholders.
+    #[serde(default)]
+    pub now_playing_format: Option<String>,
+    /// Offer playback controls over MPRIS (D-Bus) on Linux desktops, in
+    /// builds with the `mpris` feature.
+    #[serde(default)]
+    pub mpris: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -41,6 +93,10 @@ pub struct CacheConfig {
     /// Whether to automatically clean up old files on startup
     #[serde(default = "default_auto_cleanup")]
     pub auto_cleanup: bool,
+    /// Keep a local copy of streamed tracks and play it instead of the
+    /// remote stream on later plays
+    #[serde(default)]
+    pub cache_tracks: bool,
 }
 
 impl Default for CacheConfig {
@@ -50,6 +106,7 @@ impl Default for CacheConfig {
             max_size_bytes: default_max_cache_size(),
             max_age_seconds: default_max_cache_age(),
             auto_cleanup: default_auto_cleanup(),
+            cache_tracks: false,
         }
     }
 }
@@ -60,11 +117,175 @@ impl Default for Config {
             config_version: default_config_version(),
             default_provider: None,
             profile: None,
-            default_scrobbler: None,
+            scrobbling: ScrobblingConfig::default(),
             theme: None,
             logging: LoggingConfig::default(),
             providers: BTreeMap::new(),
             cache: CacheConfig::default(),
+            replay_gain: ReplayGainConfig::default(),
+            audio: AudioConfig::default(),
+            layout: LayoutConfig::default(),
+            keybindings: BTreeMap::new(),
+            now_playing_output: None,
+            now_playing_format: None,
+            mpris: false,
+        }
+    }
+}
+
+/// `theme = "<name>"`, or a `[theme]` table that may define a custom theme:
+///
+/// ```toml
+/// [theme]
+/// name = "custom"
+///
+/// [theme.custom]
+/// primary = "#ff8800"
+/// text = "white"
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ThemeSetting {
+    Name(String),
+    Table {
+        #[serde(default)]
+        name: Option<String>,
+        #[serde(default)]
+        custom: Option<CustomThemeColors>,
+    },
+}
+
+impl ThemeSetting {
+    /// The selected theme name. A table with a custom theme and no name
+    /// selects `"custom"`.
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            ThemeSetting::Name(name) => Some(name),
+            ThemeSetting::Table {
+                name: Some(name), ..
+            } => Some(name),
+            ThemeSetting::Table {
+                name: None,
+                custom: Some(_),
+            } => Some("custom"),
+            ThemeSetting::Table { .. } => None,
+        }
+    }
+
+    /// Select `name`, keeping any custom colors.
+    pub fn set_name(&mut self, new_name: impl Into<String>) {
+        match self {
+            ThemeSetting::Name(name) => *name = new_name.into(),
+            ThemeSetting::Table { name, .. } => *name = Some(new_name.into()),
+        }
+    }
+
+    pub fn custom(&self) -> Option<&CustomThemeColors> {
+        match self {
+            ThemeSetting::Table { custom, .. } => custom.as_ref(),
+            ThemeSetting::Name(_) => None,
+        }
+    }
+}
+
+/// Colors of a custom theme, as `#rrggbb` hex or color names. Unset colors
+/// keep the default theme's.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CustomThemeColors {
+    #[serde(default)]
+    pub primary: Option<String>,
+    #[serde(default)]
+    pub secondary: Option<String>,
+    #[serde(default)]
+    pub accent: Option<String>,
+    #[serde(default)]
+    pub error: Option<String>,
+    #[serde(default)]
+    pub success: Option<String>,
+    #[serde(default)]
+    pub text: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplayGainConfig {
+    /// `off`, `track` or `album`.
+    #[serde(default)]
+    pub mode: ReplayGainMode,
+    /// Lower the gain when the tagged peak would clip.
+    #[serde(default = "default_prevent_clipping")]
+    pub prevent_clipping: bool,
+}
+
+impl Default for ReplayGainConfig {
+    fn default() -> Self {
+        Self {
+            mode: ReplayGainMode::default(),
+            prevent_clipping: default_prevent_clipping(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AudioConfig {
+    /// Output device name as listed by `tunez audio devices`; the system
+    /// default when unset or not connected.
+    #[serde(default)]
+    pub output_device: Option<String>,
+    /// Convert remote streams to FLAC with ffmpeg in the track cache before
+    /// playing them; streams play as they are when ffmpeg isn't installed.
+    #[serde(default)]
+    pub transcode: bool,
+}
+
+/// Smallest terminal the UI draws in. Terminals between this and the size
+/// of the full layout get a compact one without the tab sidebar; smaller
+/// ones only show a prompt to resize.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LayoutConfig {
+    #[serde(default = "default_min_width")]
+    pub min_width: u16,
+    #[serde(default = "default_min_height")]
+    pub min_height: u16,
+}
+
+impl LayoutConfig {
+    /// The smallest `min_width` that leaves room for a list.
+    pub const SMALLEST_WIDTH: u16 = 20;
+    /// The smallest `min_height` that leaves room for a list.
+    pub const SMALLEST_HEIGHT: u16 = 6;
+}
+
+impl Default for LayoutConfig {
+    fn default() -> Self {
+        Self {
+            min_width: default_min_width(),
+            min_height: default_min_height(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScrobblingConfig {
+    /// Send scrobbles when a scrobbler is configured; toggled from the
+    /// Config tab.
+    #[serde(default = "default_scrobbling_enabled")]
+    pub enabled: bool,
+    /// Scrobbler to use (e.g. `"lastfm"`); the provider's own scrobbler is
+    /// used when unset. Was the top-level `default_scrobbler` in version 1.
+    #[serde(default)]
+    pub default: Option<String>,
+    /// Seconds before a scrobble request gives up; the scrobbler's own
+    /// default when unset.
+    #[serde(default)]
+    pub timeout_seconds: Option<u64>,
+}
+
+impl Default for ScrobblingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_scrobbling_enabled(),
+            default: None,
+            timeout_seconds: None,
         }
     }
 }
@@ -109,6 +330,17 @@ pub enum LogLevel {
 }
 
 impl LogLevel {
+    /// The next level from most to least verbose, wrapping around.
+    pub fn next(self) -> Self {
+        match self {
+            LogLevel::Trace => LogLevel::Debug,
+            LogLevel::Debug => LogLevel::Info,
+            LogLevel::Info => LogLevel::Warn,
+            LogLevel::Warn => LogLevel::Error,
+            LogLevel::Error => LogLevel::Trace,
+        }
+    }
+
     pub fn as_filter_directive(&self) -> &'static str {
         match self {
             LogLevel::Trace => "trace",
@@ -132,6 +364,15 @@ pub enum ConfigError {
         path: PathBuf,
         source: toml::de::Error,
     },
+    #[error("failed to write config to {path}: {source}")]
+    Write {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("config already exists at {path} (use --force to overwrite)")]
+    AlreadyExists { path: PathBuf },
+    #[error("failed to serialize config: {0}")]
+    Serialize(#[from] toml::ser::Error),
     #[error("config validation failed: {0}")]
     Validation(ValidationError),
     #[error("failed to prepare configuration directories: {0}")]
@@ -153,6 +394,12 @@ pub enum ValidationError {
     },
     #[error("provider selection is required (set default_provider or pass --provider)")]
     MissingProviderSelection,
+    #[error("unknown keybinding action '{action}'")]
+    UnknownKeyAction { action: String },
+    #[error("'{setting}' must be a positive number of seconds")]
+    InvalidTimeout { setting: String },
+    #[error("'{setting}' must be at least {smallest}")]
+    LayoutTooSmall { setting: String, smallest: u16 },
 }
 
 impl Config {
@@ -162,16 +409,46 @@ impl Config {
         if !path.exists() {
             return Ok(Self::default());
         }
+        Self::load_from(&path)
+    }
 
-        let contents = fs::read_to_string(&path).map_err(|source| ConfigError::Io {
-            path: path.clone(),
+    /// Load the config at `path`, migrating it to the current version first.
+    /// A migrated file that loads and validates is rewritten in place after
+    /// the original is copied to `config.toml.v<N>.bak`; one that doesn't is
+    /// left untouched.
+    fn load_from(path: &Path) -> Result<Self, ConfigError> {
+        let contents = fs::read_to_string(path).map_err(|source| ConfigError::Io {
+            path: path.to_path_buf(),
             source,
         })?;
-        let config: Config = toml::from_str(&contents).map_err(|source| ConfigError::Parse {
-            path: path.clone(),
+        let parse_error = |source| ConfigError::Parse {
+            path: path.to_path_buf(),
             source,
-        })?;
+        };
+        let mut table: toml::Table = toml::from_str(&contents).map_err(parse_error)?;
+        let migration = match migrate(&mut table) {
+            Some(from) => Some((from, toml::to_string(&table)?)),
+            None => None,
+        };
+
+        let config: Config = table.try_into().map_err(parse_error)?;
         config.validate().map_err(ConfigError::Validation)?;
+
+        if let Some((from, migrated)) = migration {
+            let write_error = |source| ConfigError::Write {
+                path: path.to_path_buf(),
+                source,
+            };
+            let backup = path.with_extension(format!("toml.v{from}.bak"));
+            fs::write(&backup, &contents).map_err(write_error)?;
+            fs::write(path, migrated).map_err(write_error)?;
+            tracing::info!(
+                from,
+                to = CURRENT_CONFIG_VERSION,
+                backup = %backup.display(),
+                "migrated config file"
+            );
+        }
         Ok(config)
     }
 
@@ -179,6 +456,41 @@ impl Config {
         dirs.config_dir().join("config.toml")
     }
 
+    /// Validate and write this config to `config.toml`. Nothing is written
+    /// when validation fails. Comments in the existing file are not kept.
+    pub fn save(&self, dirs: &AppDirs) -> Result<(), ConfigError> {
+        self.validate().map_err(ConfigError::Validation)?;
+        dirs.ensure_exists()?;
+        let path = Self::config_path(dirs);
+        let contents = toml::to_string_pretty(self)?;
+        // Written beside it and renamed so a failed write can't truncate it.
+        let staged = path.with_extension("toml.tmp");
+        let write_error = |source| ConfigError::Write {
+            path: path.clone(),
+            source,
+        };
+        fs::write(&staged, contents).map_err(write_error)?;
+        fs::rename(&staged, &path).map_err(write_error)
+    }
+
+    /// Write [`DEFAULT_CONFIG_TEMPLATE`] to `path`, creating its directory.
+    /// An existing file is only replaced when `force` is set.
+    pub fn write_default(path: &Path, force: bool) -> Result<(), ConfigError> {
+        if path.exists() && !force {
+            return Err(ConfigError::AlreadyExists {
+                path: path.to_path_buf(),
+            });
+        }
+        let write_error = |source| ConfigError::Write {
+            path: path.to_path_buf(),
+            source,
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(write_error)?;
+        }
+        fs::write(path, DEFAULT_CONFIG_TEMPLATE).map_err(write_error)
+    }
+
     pub fn validate(&self) -> Result<(), ValidationError> {
         if self.config_version != CURRENT_CONFIG_VERSION {
             return Err(ValidationError::UnsupportedVersion {
@@ -210,6 +522,60 @@ impl Config {
             return Err(ValidationError::MissingProviderSelection);
         }
 
+        if let Some(action) = self
+            .keybindings
+            .keys()
+            .find(|action| !KEYBINDING_ACTIONS.contains(&action.as_str()))
+        {
+            return Err(ValidationError::UnknownKeyAction {
+                action: action.clone(),
+            });
+        }
+
+        let timeouts = self.providers.iter().flat_map(|(provider_id, provider)| {
+            provider
+                .profiles
+                .iter()
+                .flat_map(move |(profile, settings)| {
+                    let prefix = format!("providers.{provider_id}.profiles.{profile}");
+                    [
+                        ("timeout_seconds", settings.timeout_seconds),
+                        ("connect_timeout_seconds", settings.connect_timeout_seconds),
+                    ]
+                    .map(|(name, value)| (format!("{prefix}.{name}"), value))
+                })
+        });
+        let scrobbling = (
+            "scrobbling.timeout_seconds".to_string(),
+            self.scrobbling.timeout_seconds,
+        );
+        if let Some((setting, _)) = timeouts
+            .chain([scrobbling])
+            .find(|(_, value)| *value == Some(0))
+        {
+            return Err(ValidationError::InvalidTimeout { setting });
+        }
+
+        for (setting, value, smallest) in [
+            (
+                "layout.min_width",
+                self.layout.min_width,
+                LayoutConfig::SMALLEST_WIDTH,
+            ),
+            (
+                "layout.min_height",
+                self.layout.min_height,
+                LayoutConfig::SMALLEST_HEIGHT,
+            ),
+        ] {
+            if value < smallest {
+                return Err(ValidationError::LayoutTooSmall {
+                    setting: setting.to_string(),
+                    smallest,
+                });
+            }
+        }
+
         Ok(())
     }
 
@@ -276,6 +642,27 @@ pub struct ProviderProfile {
     /// Arguments to pass to the plugin executable.
     #[serde(default)]
     pub plugin_args: Vec<String>,
+    /// How messages to and from the plugin are delimited.
+    #[serde(default)]
+    pub plugin_framing: PluginFraming,
+    /// Seconds before a request to the provider gives up; the provider's own
+    /// default when unset.
+    #[serde(default)]
+    pub timeout_seconds: Option<u64>,
+    /// Seconds to wait for a connection to a remote provider.
+    #[serde(default)]
+    pub connect_timeout_seconds: Option<u64>,
+}
+
+/// Message framing a plugin speaks on stdin and stdout.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum PluginFraming {
+    /// One JSON message per line.
+    #[default]
+    Lines,
+    /// Each message preceded by its 4-byte big-endian length.
+    LengthPrefixed,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -284,10 +671,47 @@ pub struct ProviderSelection {
     pub profile: Option<String>,
 }
 
+/// Bring `table` up to [`CURRENT_CONFIG_VERSION`], returning the version it
+/// started at if anything changed. A missing `config_version` is treated as
+/// version 1; unknown or newer versions are left for validation to reject.
+fn migrate(table: &mut toml::Table) -> Option<u32> {
+    let from = match table.get("config_version") {
+        None => 1,
+        Some(value) => value
+            .as_integer()
+            .and_then(|v| u32::try_from(v).ok())
+            .filter(|v| (1..CURRENT_CONFIG_VERSION).contains(v))?,
+    };
+    for step in &MIGRATIONS[from as usize - 1..] {
+        step(table);
+    }
+    table.insert(
+        "config_version".into(),
+        toml::Value::Integer(CURRENT_CONFIG_VERSION.into()),
+    );
+    Some(from)
+}
+
+/// v2 moved the top-level `default_scrobbler` into `[scrobbling] default`.
+fn migrate_v1_to_v2(table: &mut toml::Table) {
+    if let Some(scrobbler) = table.remove("default_scrobbler") {
+        let scrobbling = table
+            .entry("scrobbling")
+            .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+        if let Some(scrobbling) = scrobbling.as_table_mut() {
+            scrobbling.entry("default").or_insert(scrobbler);
+        }
+    }
+}
+
 fn default_config_version() -> u32 {
     CURRENT_CONFIG_VERSION
 }
 
+fn default_scrobbling_enabled() -> bool {
+    true
+}
+
 fn default_log_level() -> LogLevel {
     LogLevel::Info
 }
@@ -317,6 +741,18 @@ fn default_auto_cleanup() -> bool {
     true
 }
 
+fn default_min_width() -> u16 {
+    40
+}
+
+fn default_min_height() -> u16 {
+    12
+}
+
+fn default_prevent_clipping() -> bool {
+    true
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -333,8 +769,10 @@ mod tests {
 
     #[test]
     fn invalid_version_rejected() {
-        let mut config = Config::default();
-        config.config_version = CURRENT_CONFIG_VERSION + 1;
+        let config = Config {
+            config_version: CURRENT_CONFIG_VERSION + 1,
+            ..Default::default()
+        };
         let result = config.validate();
         assert!(matches!(
             result,
@@ -342,10 +780,136 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn v1_config_is_migrated_and_rewritten() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        let v1 = "config_version = 1\ndefault_scrobbler = \"lastfm\"\ntheme = \"afterdark\"\n";
+        fs::write(&path, v1).unwrap();
+
+        let config = Config::load_from(&path).unwrap();
+        assert_eq!(config.config_version, CURRENT_CONFIG_VERSION);
+        assert_eq!(config.scrobbling.default.as_deref(), Some("lastfm"));
+        assert!(config.validate().is_ok());
+
+        let rewritten: toml::Table = toml::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(
+            rewritten.get("config_version").and_then(|v| v.as_integer()),
+            Some(CURRENT_CONFIG_VERSION.into())
+        );
+        assert!(!rewritten.contains_key("default_scrobbler"));
+        assert_eq!(
+            fs::read_to_string(dir.path().join("config.toml.v1.bak")).unwrap(),
+            v1
+        );
+
+        // Loading again is a no-op.
+        let again = Config::load_from(&path).unwrap();
+        assert_eq!(again.scrobbling.default.as_deref(), Some("lastfm"));
+    }
+
+    #[test]
+    fn invalid_v1_config_is_not_rewritten() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        let v1 = "config_version = 1\ndefault_provider = \"missing\"\n";
+        fs::write(&path, v1).unwrap();
+
+        assert!(matches!(
+            Config::load_from(&path),
+            Err(ConfigError::Validation(_))
+        ));
+        assert_eq!(fs::read_to_string(&path).unwrap(), v1);
+        assert!(!dir.path().join("config.toml.v1.bak").exists());
+    }
+
+    #[test]
+    fn current_and_newer_versions_are_not_migrated() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        let current = format!("config_version = {CURRENT_CONFIG_VERSION}\n");
+        fs::write(&path, &current).unwrap();
+        Config::load_from(&path).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), current);
+
+        let newer = format!("config_version = {}\n", CURRENT_CONFIG_VERSION + 1);
+        fs::write(&path, &newer).unwrap();
+        assert!(matches!(
+            Config::load_from(&path),
+            Err(ConfigError::Validation(
+                ValidationError::UnsupportedVersion { .. }
+            ))
+        ));
+        assert_eq!(fs::read_to_string(&path).unwrap(), newer);
+    }
+
+    #[test]
+    fn default_template_is_written_once_and_loads() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tunez").join("config.toml");
+
+        Config::write_default(&path, false).unwrap();
+        let config = Config::load_from(&path).unwrap();
+        assert_eq!(config.config_version, CURRENT_CONFIG_VERSION);
+        assert!(config.providers.is_empty());
+        assert_eq!(fs::read_to_string(&path).unwrap(), DEFAULT_CONFIG_TEMPLATE);
+
+        fs::write(&path, "config_version = 2\n").unwrap();
+        assert!(matches!(
+            Config::write_default(&path, false),
+            Err(ConfigError::AlreadyExists { .. })
+        ));
+        Config::write_default(&path, true).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), DEFAULT_CONFIG_TEMPLATE);
+    }
+
+    #[test]
+    fn saved_settings_load_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let dirs = AppDirs::under(dir.path());
+        let mut config = Config::load_or_default(&dirs).unwrap();
+        config.theme = Some(ThemeSetting::Table {
+            name: None,
+            custom: Some(CustomThemeColors {
+                primary: Some("#ff8800".into()),
+                ..Default::default()
+            }),
+        });
+        config.theme.as_mut().unwrap().set_name("afterdark");
+        config.logging.level = config.logging.level.next();
+        config.scrobbling.enabled = false;
+        config.save(&dirs).unwrap();
+
+        let loaded = Config::load_or_default(&dirs).unwrap();
+        let theme = loaded.theme.as_ref().unwrap();
+        assert_eq!(theme.name(), Some("afterdark"));
+        assert_eq!(theme.custom().unwrap().primary.as_deref(), Some("#ff8800"));
+        assert_eq!(loaded.logging.level, LogLevel::Warn);
+        assert!(!loaded.scrobbling.enabled);
+
+        // An invalid config leaves the saved one alone.
+        let invalid = Config {
+            default_provider: Some("missing".into()),
+            ..loaded
+        };
+        assert!(matches!(
+            invalid.save(&dirs),
+            Err(ConfigError::Validation(
+                ValidationError::NoProvidersConfigured
+            ))
+        ));
+        assert_eq!(
+            Config::load_or_default(&dirs).unwrap().logging.level,
+            LogLevel::Warn
+        );
+    }
+
     #[test]
     fn missing_provider_when_default_set_is_invalid() {
-        let mut config = Config::default();
-        config.default_provider = Some("melodee".into());
+        let config = Config {
+            default_provider: Some("melodee".into()),
+            ..Default::default()
+        };
         let result = config.validate();
         assert!(matches!(
             result,
@@ -358,10 +922,12 @@ mod tests {
         let mut providers = BTreeMap::new();
         providers.insert("filesystem".into(), ProviderConfig::default());
 
-        let mut config = Config::default();
-        config.default_provider = Some("filesystem".into());
-        config.profile = Some("home".into());
-        config.providers = providers;
+        let config = Config {
+            default_provider: Some("filesystem".into()),
+            profile: Some("home".into()),
+            providers,
+            ..Default::default()
+        };
 
         let result = config.validate();
         assert!(matches!(
@@ -370,6 +936,141 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn keybindings_default_to_empty_and_reject_unknown_actions() {
+        let config: Config = toml::from_str("config_version = 1").unwrap();
+        assert!(config.keybindings.is_empty());
+
+        let config: Config =
+            toml::from_str("[keybindings]\nnext = \"ctrl+n\"\nquit = \"q esc\"").unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.keybindings["next"], "ctrl+n");
+
+        let config: Config = toml::from_str("[keybindings]\nfly = \"f\"").unwrap();
+        assert!(matches!(
+            config.validate(),
+            Err(ValidationError::UnknownKeyAction { action }) if action == "fly"
+        ));
+    }
+
+    #[test]
+    fn timeouts_must_be_positive() {
+        let config: Config = toml::from_str(
+            "[providers.melodee.profiles.home]\ntimeout_seconds = 60\nconnect_timeout_seconds = 3\n\
+             [scrobbling]\ntimeout_seconds = 5",
+        )
+        .unwrap();
+        assert!(config.validate().is_ok());
+        let profile = &config.providers["melodee"].profiles["home"];
+        assert_eq!(profile.timeout_seconds, Some(60));
+        assert_eq!(profile.connect_timeout_seconds, Some(3));
+        assert_eq!(config.scrobbling.timeout_seconds, Some(5));
+
+        let config: Config =
+            toml::from_str("[providers.melodee.profiles.home]\nconnect_timeout_seconds = 0")
+                .unwrap();
+        assert!(matches!(
+            config.validate(),
+            Err(ValidationError::InvalidTimeout { setting })
+                if setting == "providers.melodee.profiles.home.connect_timeout_seconds"
+        ));
+
+        let config: Config = toml::from_str("[scrobbling]\ntimeout_seconds = 0").unwrap();
+        assert!(matches!(
+            config.validate(),
+            Err(ValidationError::InvalidTimeout { setting }) if setting == "scrobbling.timeout_seconds"
+        ));
+        assert!(toml::from_str::<Config>("[scrobbling]\ntimeout_seconds = -1").is_err());
+    }
+
+    #[test]
+    fn plugin_framing_defaults_to_lines_and_rejects_unknown_names() {
+        let config: Config = toml::from_str(
+            "[providers.mine.profiles.a]\n\
+             [providers.mine.profiles.b]\nplugin_framing = \"length-prefixed\"",
+        )
+        .unwrap();
+        let profiles = &config.providers["mine"].profiles;
+        assert_eq!(profiles["a"].plugin_framing, PluginFraming::Lines);
+        assert_eq!(profiles["b"].plugin_framing, PluginFraming::LengthPrefixed);
+
+        assert!(toml::from_str::<Config>(
+            "[providers.mine.profiles.a]\nplugin_framing = \"netstring\""
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn layout_minimum_is_configurable_down_to_a_floor() {
+        let config: Config = toml::from_str("").unwrap();
+        assert_eq!(config.layout, LayoutConfig::default());
+
+        let config: Config = toml::from_str("[layout]\nmin_width = 30").unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.layout.min_width, 30);
+        assert_eq!(config.layout.min_height, LayoutConfig::default().min_height);
+
+        let config: Config = toml::from_str("[layout]\nmin_height = 3").unwrap();
+        assert!(matches!(
+            config.validate(),
+            Err(ValidationError::LayoutTooSmall { setting, smallest: 6 })
+                if setting == "layout.min_height"
+        ));
+    }
+
+    #[test]
+    fn theme_accepts_name_or_custom_table() {
+        let config: Config = toml::from_str("theme = \"afterdark\"").unwrap();
+        assert_eq!(config.theme.unwrap().name(), Some("afterdark"));
+
+        let config: Config =
+            toml::from_str("[theme.custom]\nprimary = \"#ff8800\"\ntext = \"white\"").unwrap();
+        let theme = config.theme.unwrap();
+        assert_eq!(theme.name(), Some("custom"));
+        let custom = theme.custom().unwrap();
+        assert_eq!(custom.primary.as_deref(), Some("#ff8800"));
+        assert_eq!(custom.secondary, None);
+    }
+
+    #[test]
+    fn audio_output_device_is_optional() {
+        let config: Config = toml::from_str("").unwrap();
+        assert_eq!(config.audio.output_device, None);
+
+        let config: Config = toml::from_str("[audio]\noutput_device = \"USB DAC\"").unwrap();
+        assert_eq!(config.audio.output_device.as_deref(), Some("USB DAC"));
+        assert!(!config.audio.transcode);
+
+        let config: Config = toml::from_str("[audio]\ntranscode = true").unwrap();
+        assert!(config.audio.transcode);
+    }
+
+    #[test]
+    fn now_playing_output_is_optional() {
+        let config: Config = toml::from_str("").unwrap();
+        assert_eq!(config.now_playing_output, None);
+
+        let config: Config = toml::from_str(
+            "now_playing_output = \"/tmp/np.txt\"\nnow_playing_format = \"{title}\"",
+        )
+        .unwrap();
+        assert_eq!(
+            config.now_playing_output,
+            Some(PathBuf::from("/tmp/np.txt"))
+        );
+        assert_eq!(config.now_playing_format.as_deref(), Some("{title}"));
+    }
+
+    #[test]
+    fn replay_gain_defaults_off_and_parses_mode() {
+        let config: Config = toml::from_str("").unwrap();
+        assert_eq!(config.replay_gain.mode, ReplayGainMode::Off);
+        assert!(config.replay_gain.prevent_clipping);
+
+        let config: Config = toml::from_str("[replay_gain]\nmode = \"album\"").unwrap();
+        assert_eq!(config.replay_gain.mode, ReplayGainMode::Album);
+    }
+
     #[test]
     fn resolve_provider_prefers_cli_over_default() {
         let mut profiles = BTreeMap::new();
@@ -384,9 +1085,11 @@ mod tests {
             },
         );
 
-        let mut config = Config::default();
-        config.default_provider = Some("filesystem".into());
-        config.providers = providers;
+        let config = Config {
+            default_provider: Some("filesystem".into()),
+            providers,
+            ..Default::default()
+        };
 
         let selection = config
             .resolve_provider_selection(Some("filesystem"), Some("home"))
@@ -0,0 +1,187 @@
+//! Fuzzy matching for local searches.
+//!
+//! A query scores highest as a case-insensitive substring, ranked by where
+//! it appears: the whole text, then a prefix, then the start of a word, then
+//! anywhere. Failing that, its letters may appear in order with gaps
+//! ("bohrap" in "Bohemian Rhapsody"), and longer queries may have one letter
+//! wrong, missing or swapped. Nothing is allocated per comparison, so whole
+//! libraries can be scored on every keystroke.
+
+/// Queries shorter than this (ignoring spaces) only match as substrings;
+/// shorter ones would match almost anything letter by letter.
+const MIN_SCATTERED_LEN: usize = 3;
+/// Queries at least this long may contain one typo.
+const MIN_TYPO_LEN: usize = 4;
+
+/// How well `query` matches `text`, higher being better, or `None` when it
+/// doesn't. An empty query matches everything with a score of zero.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<u32> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Some(0);
+    }
+    if let Some((pos, word_start)) = find_substring(query, text) {
+        let score = if pos == 0 && query.chars().count() == text.chars().count() {
+            1000
+        } else if pos == 0 {
+            900
+        } else if word_start {
+            700 - pos.min(99) as u32
+        } else {
+            500 - pos.min(99) as u32
+        };
+        return Some(score);
+    }
+
+    let len = query.chars().filter(|c| !c.is_whitespace()).count();
+    if len < MIN_SCATTERED_LEN {
+        return None;
+    }
+    if let Some(score) = scattered_score(query, text, None) {
+        return Some(score);
+    }
+    if len < MIN_TYPO_LEN {
+        return None;
+    }
+    // Leaving out one query letter covers a wrong, extra or swapped letter.
+    (0..len)
+        .filter_map(|skip| scattered_score(query, text, Some(skip)))
+        .max()
+        .map(|score| (score / 2).max(1))
+}
+
+/// The best of [`fuzzy_score`] over several fields of one item, such as a
+/// track's title, artist and album.
+pub fn fuzzy_score_any<'a>(query: &str, fields: impl IntoIterator<Item = &'a str>) -> Option<u32> {
+    fields
+        .into_iter()
+        .filter_map(|field| fuzzy_score(query, field))
+        .max()
+}
+
+fn chars_eq(a: char, b: char) -> bool {
+    a == b || a.to_lowercase().eq(b.to_lowercase())
+}
+
+fn is_word_start(previous: Option<char>) -> bool {
+    previous.is_none_or(|c| !c.is_alphanumeric())
+}
+
+/// Char offset of the first occurrence of `query` in `text` that starts a
+/// word, or else of the first one anywhere, and whether it starts a word.
+fn find_substring(query: &str, text: &str) -> Option<(usize, bool)> {
+    let mut first = None;
+    let mut previous = None;
+    for (pos, (byte, c)) in text.char_indices().enumerate() {
+        let word_start = is_word_start(previous);
+        previous = Some(c);
+        if first.is_some() && !word_start {
+            continue;
+        }
+        let mut rest = text[byte..].chars();
+        if query
+            .chars()
+            .all(|q| rest.next().is_some_and(|c| chars_eq(q, c)))
+        {
+            if word_start {
+                return Some((pos, true));
+            }
+            first = Some(pos);
+        }
+    }
+    first.map(|pos| (pos, false))
+}
+
+/// Score for the query's letters appearing in order in `text`, leaving out
+/// the `skip`th one. Runs of adjacent letters and letters starting words
+/// score more; gaps between them less.
+fn scattered_score(query: &str, text: &str, skip: Option<usize>) -> Option<u32> {
+    let mut wanted = query
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .enumerate()
+        .filter(|(i, _)| Some(*i) != skip)
+        .map(|(_, c)| c)
+        .peekable();
+    let mut score: u32 = 100;
+    let mut previous = None;
+    let mut in_run = false;
+    for c in text.chars() {
+        let Some(&q) = wanted.peek() else {
+            break;
+        };
+        let word_start = is_word_start(previous);
+        previous = Some(c);
+        if chars_eq(q, c) {
+            wanted.next();
+            if in_run {
+                score += 10;
+            }
+            if word_start {
+                score += 15;
+            }
+            in_run = true;
+        } else {
+            if in_run {
+                score = score.saturating_sub(5);
+            }
+            in_run = false;
+        }
+    }
+    wanted.peek().is_none().then_some(score.clamp(1, 399))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn earlier_and_whole_word_matches_rank_higher() {
+        let exact = fuzzy_score("bohemian rhapsody", "Bohemian Rhapsody").unwrap();
+        let prefix = fuzzy_score("bohem", "Bohemian Rhapsody").unwrap();
+        let word = fuzzy_score("rhap", "Bohemian Rhapsody").unwrap();
+        let inside = fuzzy_score("emian", "Bohemian Rhapsody").unwrap();
+        let scattered = fuzzy_score("bohrap", "Bohemian Rhapsody").unwrap();
+        assert!(exact > prefix, "{exact} > {prefix}");
+        assert!(prefix > word, "{prefix} > {word}");
+        assert!(word > inside, "{word} > {inside}");
+        assert!(inside > scattered, "{inside} > {scattered}");
+
+        // Of two titles containing the query, the one starting with it wins.
+        assert!(
+            fuzzy_score("love", "Love Me Do").unwrap()
+                > fuzzy_score("love", "Crazy Little Thing Called Love").unwrap()
+        );
+    }
+
+    #[test]
+    fn one_typo_still_matches() {
+        for (typo, text) in [
+            ("rhapsodt", "Bohemian Rhapsody"),
+            ("bohemain", "Bohemian Rhapsody"),
+            ("rhaspody", "Bohemian Rhapsody"),
+            ("queeen", "Queen"),
+        ] {
+            assert!(fuzzy_score(typo, text).is_some(), "{typo}");
+        }
+        assert!(
+            fuzzy_score("rhapsody", "Bohemian Rhapsody").unwrap()
+                > fuzzy_score("rhapsodt", "Bohemian Rhapsody").unwrap()
+        );
+    }
+
+    #[test]
+    fn unrelated_and_short_scattered_queries_do_not_match() {
+        assert_eq!(fuzzy_score("xyzzy", "Bohemian Rhapsody"), None);
+        assert_eq!(fuzzy_score("br", "Bohemian Rhapsody"), None);
+        assert_eq!(fuzzy_score("  ", "Anything"), Some(0));
+        assert_eq!(
+            fuzzy_score_any(
+                "queen",
+                ["Bohemian Rhapsody", "Queen", "A Night at the Opera"]
+            ),
+            fuzzy_score("queen", "Queen")
+        );
+        assert_eq!(fuzzy_score_any("queen", ["Imagine", "John Lennon"]), None);
+    }
+}
@@ -0,0 +1,173 @@
+//! Local play history.
+//!
+//! The player appends each track it starts to a JSON-lines file in the data
+//! directory. Providers without a server-side history (the filesystem
+//! provider) read it back to answer `list_recently_played`.
+
+use crate::models::{Track, TrackId};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// File name of the history inside the data directory.
+pub const HISTORY_FILE: &str = "history.jsonl";
+
+/// Entries kept on disk; older plays are dropped as new ones arrive.
+const MAX_ENTRIES: usize = 500;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct HistoryEntry {
+    provider_id: String,
+    track_id: TrackId,
+    /// Seconds since the Unix epoch.
+    played_at: u64,
+}
+
+/// Play history stored as JSON lines, oldest first.
+#[derive(Debug, Clone)]
+pub struct PlayHistory {
+    path: PathBuf,
+}
+
+impl PlayHistory {
+    /// History kept in `data_dir`.
+    pub fn new(data_dir: &Path) -> Self {
+        Self::at(data_dir.join(HISTORY_FILE))
+    }
+
+    /// History kept in the file at `path`.
+    pub fn at(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Record that `track` started playing now.
+    pub fn record(&self, track: &Track) -> io::Result<()> {
+        let mut entries = self.load()?;
+        entries.push(HistoryEntry {
+            provider_id: track.provider_id.clone(),
+            track_id: track.id.clone(),
+            played_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+        });
+        if entries.len() > MAX_ENTRIES {
+            entries.drain(..entries.len() - MAX_ENTRIES);
+        }
+        self.persist(&entries)
+    }
+
+    /// Tracks played from `provider_id`, most recent first, each listed once.
+    pub fn recent(&self, provider_id: &str) -> io::Result<Vec<TrackId>> {
+        let mut seen = HashSet::new();
+        Ok(self
+            .load()?
+            .into_iter()
+            .rev()
+            .filter(|entry| entry.provider_id == provider_id)
+            .filter_map(|entry| {
+                seen.insert(entry.track_id.clone())
+                    .then_some(entry.track_id)
+            })
+            .collect())
+    }
+
+    fn load(&self) -> io::Result<Vec<HistoryEntry>> {
+        let file = match fs::File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        let mut entries = Vec::new();
+        for line in BufReader::new(file).lines() {
+            // A torn or hand-edited line shouldn't cost the rest of the history.
+            match serde_json::from_str(&line?) {
+                Ok(entry) => entries.push(entry),
+                Err(e) => tracing::debug!(error = %e, "skipping bad play history line"),
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Rewrite the file through a temporary so readers never see it half written.
+    fn persist(&self, entries: &[HistoryEntry]) -> io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let tmp = self.path.with_extension("jsonl.tmp");
+        let mut file = io::BufWriter::new(fs::File::create(&tmp)?);
+        for entry in entries {
+            serde_json::to_writer(&mut file, entry)?;
+            file.write_all(b"\n")?;
+        }
+        file.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        fs::rename(&tmp, &self.path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn track(provider: &str, id: &str) -> Track {
+        Track {
+            id: TrackId::new(id),
+            provider_id: provider.into(),
+            title: id.into(),
+            artist: "Artist".into(),
+            album: None,
+            duration_seconds: None,
+            track_number: None,
+            disc_number: None,
+            year: None,
+            genre: None,
+            musicbrainz_id: None,
+            replay_gain: Default::default(),
+        }
+    }
+
+    #[test]
+    fn recent_lists_newest_first_without_repeats() {
+        let dir = tempdir().unwrap();
+        let history = PlayHistory::new(dir.path());
+        assert!(history.recent("filesystem").unwrap().is_empty());
+
+        for (provider, id) in [
+            ("filesystem", "a"),
+            ("filesystem", "b"),
+            ("melodee", "x"),
+            ("filesystem", "a"),
+        ] {
+            history.record(&track(provider, id)).unwrap();
+        }
+
+        let recent = history.recent("filesystem").unwrap();
+        assert_eq!(recent, vec![TrackId::new("a"), TrackId::new("b")]);
+        assert_eq!(history.recent("melodee").unwrap(), vec![TrackId::new("x")]);
+    }
+
+    #[test]
+    fn history_is_capped_and_skips_bad_lines() {
+        let dir = tempdir().unwrap();
+        let history = PlayHistory::new(dir.path());
+        fs::write(history.path(), "not json\n").unwrap();
+
+        for n in 0..MAX_ENTRIES + 5 {
+            history
+                .record(&track("filesystem", &n.to_string()))
+                .unwrap();
+        }
+
+        let recent = history.recent("filesystem").unwrap();
+        assert_eq!(recent.len(), MAX_ENTRIES);
+        assert_eq!(recent[0], TrackId::new((MAX_ENTRIES + 4).to_string()));
+        assert_eq!(recent.last(), Some(&TrackId::new("5")));
+    }
+}
@@ -1,26 +1,42 @@
 pub mod cache;
 pub mod config;
+pub mod fuzzy;
+pub mod history;
 pub mod logging;
+pub mod lyrics;
 pub mod models;
 pub mod paths;
+pub mod play_counts;
 pub mod provider;
 pub mod provider_contract;
 pub mod redact;
+pub mod replay_gain;
+pub mod retry;
 pub mod scrobbler;
 pub mod secrets;
+pub mod transcode;
 
 pub use cache::{CacheError, CacheManager, CachePolicy, CacheResult, CacheStats};
 pub use config::{
-    CacheConfig, Config, ConfigError, LogLevel, LoggingConfig, ProviderConfig, ProviderProfile,
-    ProviderSelection, ValidationError,
+    AudioConfig, CacheConfig, Config, ConfigError, CustomThemeColors, LayoutConfig, LogLevel,
+    LoggingConfig, PluginFraming, ProviderConfig, ProviderProfile, ProviderSelection,
+    ReplayGainConfig, ScrobblingConfig, ThemeSetting, ValidationError, DEFAULT_CONFIG_TEMPLATE,
+    KEYBINDING_ACTIONS,
 };
-pub use logging::{init_logging, LoggingError, LoggingGuard};
+pub use fuzzy::{fuzzy_score, fuzzy_score_any};
+pub use history::PlayHistory;
+pub use logging::{init_logging, set_log_level, ElapsedGuard, LoggingError, LoggingGuard};
+pub use lyrics::Lyrics;
 pub use models::*;
 pub use paths::{AppDirs, DirsError};
+pub use play_counts::{PlayCount, PlayCounts, PlayedTrack};
 pub use provider::*;
-pub use redact::{contains_sensitive, redact_secrets};
+pub use redact::{contains_sensitive, redact_error, redact_secrets};
+pub use replay_gain::{ReplayGain, ReplayGainMode};
+pub use retry::{RetryError, RetryPolicy};
 pub use scrobbler::*;
 pub use secrets::{CredentialStore, SecretKind, SecretsError, SecretsResult};
+pub use transcode::Transcoder;
 
 pub const APP_NAME: &str = "tunez";
 pub const APP_AUTHOR: &str = "Tunez";
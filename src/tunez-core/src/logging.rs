@@ -1,15 +1,47 @@
-use crate::{config::LoggingConfig, paths::AppDirs};
+use crate::config::{LogLevel, LoggingConfig};
+use crate::paths::AppDirs;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Instant;
 use thiserror::Error;
 use tracing_appender::non_blocking::{NonBlocking, WorkerGuard};
 use tracing_subscriber::fmt::writer::{BoxMakeWriter, MakeWriterExt};
-use tracing_subscriber::{fmt, EnvFilter};
+use tracing_subscriber::{fmt, reload, EnvFilter};
+
+/// Swaps the installed subscriber's filter; set by [`init_logging`].
+type ReloadFilter = Box<dyn Fn(EnvFilter) -> Result<(), reload::Error> + Send + Sync>;
+
+static RELOAD_FILTER: OnceLock<ReloadFilter> = OnceLock::new();
 
 pub struct LoggingGuard {
     _file_guard: Option<WorkerGuard>,
 }
 
+/// Records how long the current span's work took into its `elapsed_ms`
+/// field when dropped. The span has to declare the field, e.g. with
+/// `#[tracing::instrument(fields(elapsed_ms = tracing::field::Empty))]`.
+pub struct ElapsedGuard {
+    span: tracing::Span,
+    started: Instant,
+}
+
+impl ElapsedGuard {
+    pub fn start() -> Self {
+        Self {
+            span: tracing::Span::current(),
+            started: Instant::now(),
+        }
+    }
+}
+
+impl Drop for ElapsedGuard {
+    fn drop(&mut self) {
+        let elapsed_ms = self.started.elapsed().as_millis() as u64;
+        self.span.record("elapsed_ms", elapsed_ms);
+    }
+}
+
 pub fn init_logging(config: &LoggingConfig, dirs: &AppDirs) -> Result<LoggingGuard, LoggingError> {
     let log_dir = dirs.log_dir().to_path_buf();
     fs::create_dir_all(&log_dir).map_err(|source| LoggingError::CreateDirectory {
@@ -36,19 +68,38 @@ pub fn init_logging(config: &LoggingConfig, dirs: &AppDirs) -> Result<LoggingGua
         (false, None) => BoxMakeWriter::new(std::io::stdout), // fallback sink to avoid dropping logs silently
     };
 
-    fmt()
+    let builder = fmt()
         .with_env_filter(env_filter)
         .with_target(false)
         .with_ansi(config.stdout)
         .with_writer(writer)
+        .with_filter_reloading();
+    let handle = builder.reload_handle();
+    builder
         .try_init()
         .map_err(LoggingError::SubscriberInstall)?;
+    let _ = RELOAD_FILTER.set(Box::new(move |filter| handle.reload(filter)));
 
     Ok(LoggingGuard {
         _file_guard: file_guard,
     })
 }
 
+/// Change the level of the logging set up by [`init_logging`] while running.
+/// Does nothing before logging is initialized.
+pub fn set_log_level(level: LogLevel) -> Result<(), LoggingError> {
+    let Some(reload) = RELOAD_FILTER.get() else {
+        return Ok(());
+    };
+    let filter = EnvFilter::try_new(level.as_filter_directive()).map_err(|source| {
+        LoggingError::ParseLevel {
+            level: level.as_filter_directive().to_string(),
+            source,
+        }
+    })?;
+    reload(filter).map_err(LoggingError::Reload)
+}
+
 fn build_file_writer(
     config: &LoggingConfig,
     log_dir: &Path,
@@ -143,6 +194,8 @@ pub enum LoggingError {
     },
     #[error("failed to install tracing subscriber: {0}")]
     SubscriberInstall(Box<dyn std::error::Error + Send + Sync>),
+    #[error("failed to change log level: {0}")]
+    Reload(reload::Error),
     #[error("failed to list log directory {path}: {source}")]
     ReadDir {
         path: PathBuf,
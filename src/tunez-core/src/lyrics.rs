@@ -0,0 +1,167 @@
+//! Track lyrics and LRC (timestamped lyrics) parsing.
+
+use std::time::Duration;
+
+/// Lyrics for a track, either time-synced, plain, or both.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Lyrics {
+    /// Lines with their start time, sorted by time.
+    pub synced: Option<Vec<(Duration, String)>>,
+    /// Untimed lyrics text.
+    pub plain: Option<String>,
+}
+
+impl Lyrics {
+    /// Build lyrics from raw text, treating it as LRC when it contains any
+    /// timestamped lines and as plain text otherwise.
+    pub fn from_text(text: &str) -> Self {
+        let synced = parse_lrc(text);
+        if synced.is_empty() {
+            let plain = text.trim();
+            Self {
+                synced: None,
+                plain: (!plain.is_empty()).then(|| plain.to_string()),
+            }
+        } else {
+            let plain = synced
+                .iter()
+                .map(|(_, line)| line.as_str())
+                .collect::<Vec<_>>()
+                .join("\n");
+            Self {
+                synced: Some(synced),
+                plain: Some(plain),
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.synced.as_ref().is_none_or(Vec::is_empty)
+            && self.plain.as_ref().is_none_or(|p| p.trim().is_empty())
+    }
+
+    /// Index of the synced line active at `position`: the last line starting
+    /// at or before it.
+    pub fn active_line(&self, position: Duration) -> Option<usize> {
+        let synced = self.synced.as_ref()?;
+        synced
+            .partition_point(|(start, _)| *start <= position)
+            .checked_sub(1)
+    }
+}
+
+/// Parse LRC `contents` into `(start, line)` pairs sorted by start time.
+///
+/// A line may carry several `[mm:ss.xx]` stamps and is emitted once per
+/// stamp. Metadata tags such as `[ar:...]` and lines whose stamps are
+/// malformed are skipped.
+pub fn parse_lrc(contents: &str) -> Vec<(Duration, String)> {
+    let mut lines = Vec::new();
+    for raw in contents.trim_start_matches('\u{feff}').lines() {
+        let mut rest = raw.trim();
+        let mut stamps = Vec::new();
+        while let Some(tag) = rest.strip_prefix('[') {
+            let Some((inner, after)) = tag.split_once(']') else {
+                break;
+            };
+            match parse_timestamp(inner) {
+                Some(stamp) => stamps.push(stamp),
+                None => break,
+            }
+            rest = after.trim_start();
+        }
+        let text = rest.trim_end().to_string();
+        lines.extend(stamps.into_iter().map(|stamp| (stamp, text.clone())));
+    }
+    // Stable, so lines sharing a stamp keep their file order.
+    lines.sort_by_key(|(stamp, _)| *stamp);
+    lines
+}
+
+/// Parse `mm:ss`, `mm:ss.xx` or `mm:ss.xxx`.
+fn parse_timestamp(stamp: &str) -> Option<Duration> {
+    let (minutes, rest) = stamp.split_once(':')?;
+    let (seconds, fraction) = match rest.split_once(['.', ':']) {
+        Some((seconds, fraction)) => (seconds, Some(fraction)),
+        None => (rest, None),
+    };
+    let all_digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    if !all_digits(minutes) || !all_digits(seconds) || seconds.len() > 2 {
+        return None;
+    }
+    let minutes: u64 = minutes.parse().ok()?;
+    let seconds: u64 = seconds.parse().ok()?;
+    if seconds >= 60 {
+        return None;
+    }
+    let millis = match fraction {
+        None => 0,
+        Some(f) if all_digits(f) && f.len() <= 3 => {
+            let value: u64 = f.parse().ok()?;
+            value * 10u64.pow(3 - f.len() as u32)
+        }
+        Some(_) => return None,
+    };
+    Some(Duration::from_millis(
+        (minutes * 60 + seconds) * 1000 + millis,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn parses_timestamps_and_metadata() {
+        let lrc = "[ar:Artist]\n[ti:Title]\n[00:01.50]First\n[00:03]Second\n[01:02.345] Third \n";
+        let lines = parse_lrc(lrc);
+        assert_eq!(
+            lines,
+            vec![
+                (ms(1_500), "First".to_string()),
+                (ms(3_000), "Second".to_string()),
+                (ms(62_345), "Third".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn skips_malformed_timestamps() {
+        let lrc = "[00:xx.10]Bad\n[0:75.00]Too many seconds\n[00:01.1234]Too precise\n[00:02.00]Good\nno stamp at all\n";
+        assert_eq!(parse_lrc(lrc), vec![(ms(2_000), "Good".to_string())]);
+    }
+
+    #[test]
+    fn sorts_out_of_order_and_repeated_lines() {
+        let lrc = "[00:10.00]Later\n[00:05.00][00:20.00]Chorus\n[00:01.00]Start\n";
+        let lines = parse_lrc(lrc);
+        let stamps: Vec<_> = lines
+            .iter()
+            .map(|(t, l)| (t.as_secs(), l.as_str()))
+            .collect();
+        assert_eq!(
+            stamps,
+            vec![(1, "Start"), (5, "Chorus"), (10, "Later"), (20, "Chorus")]
+        );
+    }
+
+    #[test]
+    fn plain_text_falls_back() {
+        let lyrics = Lyrics::from_text("Just some words\nand more\n");
+        assert!(lyrics.synced.is_none());
+        assert_eq!(lyrics.plain.as_deref(), Some("Just some words\nand more"));
+        assert!(Lyrics::from_text("  \n").is_empty());
+    }
+
+    #[test]
+    fn active_line_tracks_position() {
+        let lyrics = Lyrics::from_text("[00:01.00]One\n[00:05.00]Two\n");
+        assert_eq!(lyrics.active_line(ms(500)), None);
+        assert_eq!(lyrics.active_line(ms(1_000)), Some(0));
+        assert_eq!(lyrics.active_line(ms(9_000)), Some(1));
+    }
+}
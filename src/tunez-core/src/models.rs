@@ -1,4 +1,7 @@
+use crate::replay_gain::ReplayGain;
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
 
 /// A provider-scoped track identifier.
 ///
@@ -87,6 +90,37 @@ pub struct Track {
     pub duration_seconds: Option<u32>,
     /// Track number within album when known.
     pub track_number: Option<u32>,
+    /// Disc number within a multi-disc album when known.
+    #[serde(default)]
+    pub disc_number: Option<u32>,
+    /// Release year when known.
+    #[serde(default)]
+    pub year: Option<u32>,
+    #[serde(default)]
+    pub genre: Option<String>,
+    /// MusicBrainz recording ID when tagged.
+    #[serde(default)]
+    pub musicbrainz_id: Option<String>,
+    #[serde(default)]
+    pub replay_gain: ReplayGain,
+}
+
+impl Track {
+    /// Album order: disc number, then track number, then title. Tracks
+    /// missing a number sort after those that have one.
+    pub fn album_order(&self, other: &Track) -> std::cmp::Ordering {
+        fn known_first(a: Option<u32>, b: Option<u32>) -> std::cmp::Ordering {
+            match (a, b) {
+                (Some(a), Some(b)) => a.cmp(&b),
+                (Some(_), None) => std::cmp::Ordering::Less,
+                (None, Some(_)) => std::cmp::Ordering::Greater,
+                (None, None) => std::cmp::Ordering::Equal,
+            }
+        }
+        known_first(self.disc_number, other.disc_number)
+            .then_with(|| known_first(self.track_number, other.track_number))
+            .then_with(|| self.title.cmp(&other.title))
+    }
 }
 
 /// Minimal album metadata to support browse/detail views.
@@ -98,6 +132,13 @@ pub struct Album {
     pub artist: String,
     pub track_count: Option<u32>,
     pub duration_seconds: Option<u32>,
+    /// Release year when known.
+    #[serde(default)]
+    pub year: Option<u32>,
+    /// When the album was added to the library, in seconds since the Unix
+    /// epoch, when known.
+    #[serde(default)]
+    pub added_at: Option<u64>,
 }
 
 /// Minimal playlist metadata to support browse/detail views.
@@ -122,6 +163,23 @@ impl Default for Playlist {
     }
 }
 
+/// Cover art image bytes as stored by the provider.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Artwork {
+    /// MIME type of `data`, e.g. `image/jpeg`.
+    pub mime_type: String,
+    pub data: Vec<u8>,
+}
+
+/// A chapter marker embedded in a track, such as a section of a DJ mix or
+/// an audiobook chapter.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Chapter {
+    /// Offset from the start of the track.
+    pub start: std::time::Duration,
+    pub title: Option<String>,
+}
+
 /// Stream URL returned by a provider. Providers MUST return a URL/handle; Tunez
 /// is responsible for reading/decoding the stream.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -131,6 +189,120 @@ impl StreamUrl {
     pub fn new(url: impl Into<String>) -> Self {
         Self(url.into())
     }
+
+    /// `file://` URL of a local file. The path is percent-encoded, so a
+    /// `#`, `%` or space in a file name stays part of it.
+    pub fn file(path: &Path) -> Self {
+        let mut url = String::from("file://");
+        for &byte in path_bytes(path).iter() {
+            if byte.is_ascii_alphanumeric() || b"/-._~:".contains(&byte) {
+                url.push(char::from(byte));
+            } else {
+                url.push_str(&format!("%{byte:02X}"));
+            }
+        }
+        Self(url)
+    }
+
+    /// Whether this is a local file, something to fetch over HTTP, or
+    /// neither (a bare id or an unknown scheme) and so can't be played.
+    pub fn location(&self) -> StreamLocation {
+        let url = self.0.as_str();
+        let has_scheme = |scheme: &str| {
+            url.get(..scheme.len())
+                .is_some_and(|prefix| prefix.eq_ignore_ascii_case(scheme))
+        };
+        if has_scheme("file://") || url.starts_with('/') {
+            StreamLocation::File
+        } else if has_scheme("http://") || has_scheme("https://") {
+            StreamLocation::Http
+        } else {
+            StreamLocation::Unsupported
+        }
+    }
+
+    /// The local file this points at: a `file://` URL's decoded path without
+    /// its fragment, or a bare absolute path as it is.
+    pub fn file_path(&self) -> Option<PathBuf> {
+        if self.location() != StreamLocation::File {
+            return None;
+        }
+        if self.0.starts_with('/') {
+            return Some(PathBuf::from(&self.0));
+        }
+        let url = &self.0["file://".len()..];
+        let encoded = url.split_once('#').map_or(url, |(path, _)| path);
+        Some(path_from_bytes(percent_decode(encoded)))
+    }
+
+    /// What follows the `#` of a URL, such as a cue track's `t=start,end`.
+    /// A bare path has none: any `#` in it belongs to a file name.
+    pub fn fragment(&self) -> Option<&str> {
+        if self.0.starts_with('/') {
+            return None;
+        }
+        self.0.split_once('#').map(|(_, fragment)| fragment)
+    }
+}
+
+#[cfg(unix)]
+fn path_bytes(path: &Path) -> Cow<'_, [u8]> {
+    use std::os::unix::ffi::OsStrExt;
+    Cow::Borrowed(path.as_os_str().as_bytes())
+}
+
+#[cfg(not(unix))]
+fn path_bytes(path: &Path) -> Cow<'_, [u8]> {
+    match path.to_string_lossy() {
+        Cow::Borrowed(path) => Cow::Borrowed(path.as_bytes()),
+        Cow::Owned(path) => Cow::Owned(path.into_bytes()),
+    }
+}
+
+#[cfg(unix)]
+fn path_from_bytes(bytes: Vec<u8>) -> PathBuf {
+    use std::os::unix::ffi::OsStringExt;
+    PathBuf::from(std::ffi::OsString::from_vec(bytes))
+}
+
+#[cfg(not(unix))]
+fn path_from_bytes(bytes: Vec<u8>) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// `%XX` escapes decoded; anything else, including a stray `%`, kept as is.
+fn percent_decode(encoded: &str) -> Vec<u8> {
+    let bytes = encoded.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| encoded.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                out.push(byte);
+                i += 3;
+            }
+            None => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+/// What kind of resource a [`StreamUrl`] refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamLocation {
+    /// A `file://` URL or an absolute path.
+    File,
+    /// An `http://` or `https://` URL.
+    Http,
+    /// Anything else, such as a bare track id.
+    Unsupported,
 }
 
 impl AsRef<str> for StreamUrl {
@@ -151,6 +323,11 @@ impl From<String> for StreamUrl {
     }
 }
 
+/// Items per page when a request asks for none.
+pub const DEFAULT_PAGE_LIMIT: u32 = 50;
+/// The most items one page may ask for.
+pub const MAX_PAGE_LIMIT: u32 = 500;
+
 /// Paging request represented as offset/limit.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct PageRequest {
@@ -166,12 +343,53 @@ impl PageRequest {
     pub fn first_page(limit: u32) -> Self {
         Self { offset: 0, limit }
     }
+
+    /// Request the page a cursor points at. Returns `None` for cursors that
+    /// don't encode an offset.
+    pub fn from_cursor(cursor: &PageCursor, limit: u32) -> Option<Self> {
+        cursor.offset().map(|offset| Self { offset, limit })
+    }
+
+    /// This request as providers should serve it: a zero limit asks for
+    /// [`DEFAULT_PAGE_LIMIT`] items and a larger one than
+    /// [`MAX_PAGE_LIMIT`] is capped to it.
+    pub fn normalized(self) -> Self {
+        let limit = match self.limit {
+            0 => DEFAULT_PAGE_LIMIT,
+            limit => limit.min(MAX_PAGE_LIMIT),
+        };
+        Self { limit, ..self }
+    }
+
+    /// Offset just past this page, saturating rather than overflowing.
+    pub fn end(self) -> u32 {
+        self.offset.saturating_add(self.limit)
+    }
+
+    /// Zero-based number of the page holding `offset`, for servers that page
+    /// by number rather than offset.
+    pub fn page_number(self) -> u32 {
+        let paging = self.normalized();
+        paging.offset / paging.limit
+    }
 }
 
 /// Cursor returned from a paged provider call.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct PageCursor(pub String);
 
+impl PageCursor {
+    /// Cursor for the page starting at item `offset`.
+    pub fn from_offset(offset: u32) -> Self {
+        Self(offset.to_string())
+    }
+
+    /// The item offset encoded by [`PageCursor::from_offset`].
+    pub fn offset(&self) -> Option<u32> {
+        self.0.parse().ok()
+    }
+}
+
 /// A single page of items plus an optional cursor for continuation.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Page<T> {
@@ -240,3 +458,70 @@ impl PlaySelector {
         artist.map(|name| format!("artist=\"{name}\""))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_urls_round_trip_any_file_name() {
+        for path in [
+            "/music/a.flac",
+            "/music/#1 Hits/100% live#t=123.flac",
+            "/música/東京.mp3",
+        ] {
+            let url = StreamUrl::file(Path::new(path));
+            assert_eq!(url.location(), StreamLocation::File);
+            assert_eq!(url.fragment(), None, "{}", url.0);
+            assert_eq!(url.file_path(), Some(PathBuf::from(path)));
+        }
+        assert_eq!(
+            StreamUrl::file(Path::new("/a b/#1.flac")).0,
+            "file:///a%20b/%231.flac"
+        );
+    }
+
+    #[test]
+    fn only_an_encoded_url_has_a_fragment() {
+        let url = StreamUrl::new("file:///music/live%20set.flac#t=10,20");
+        assert_eq!(url.fragment(), Some("t=10,20"));
+        assert_eq!(url.file_path(), Some(PathBuf::from("/music/live set.flac")));
+
+        let bare = StreamUrl::new("/music/live#t=10.flac");
+        assert_eq!(bare.fragment(), None);
+        assert_eq!(
+            bare.file_path(),
+            Some(PathBuf::from("/music/live#t=10.flac"))
+        );
+    }
+
+    #[test]
+    fn zero_and_huge_limits_are_normalized() {
+        assert_eq!(
+            PageRequest::new(10, 0).normalized(),
+            PageRequest::new(10, DEFAULT_PAGE_LIMIT)
+        );
+        assert_eq!(
+            PageRequest::new(0, u32::MAX).normalized(),
+            PageRequest::first_page(MAX_PAGE_LIMIT)
+        );
+        assert_eq!(
+            PageRequest::new(5, 20).normalized(),
+            PageRequest::new(5, 20)
+        );
+    }
+
+    #[test]
+    fn page_numbers_and_ends_never_panic() {
+        assert_eq!(
+            PageRequest::new(100, 0).page_number(),
+            100 / DEFAULT_PAGE_LIMIT
+        );
+        assert_eq!(PageRequest::new(40, 20).page_number(), 2);
+        assert_eq!(PageRequest::new(u32::MAX, 10).end(), u32::MAX);
+        assert_eq!(
+            PageRequest::new(u32::MAX, u32::MAX).page_number(),
+            u32::MAX / MAX_PAGE_LIMIT
+        );
+    }
+}
@@ -27,6 +27,18 @@ impl AppDirs {
         })
     }
 
+    /// Every directory beneath `root`, for tests that mustn't touch the
+    /// user's real config.
+    pub fn under(root: &Path) -> Self {
+        Self {
+            config_dir: root.join("config"),
+            data_dir: root.join("data"),
+            cache_dir: root.join("cache"),
+            log_dir: root.join("data").join("logs"),
+            download_dir: root.join("data").join("downloads"),
+        }
+    }
+
     pub fn ensure_exists(&self) -> Result<(), DirsError> {
         for dir in [
             &self.config_dir,
@@ -0,0 +1,285 @@
+//! Local play counts.
+//!
+//! The player counts a play once a track has played past the scrobble
+//! threshold, whether or not a scrobbler is set up. Each counted track is
+//! kept as a JSON line in the data directory with the metadata needed to
+//! show and play it, so the "Most Played" list can be shown without asking
+//! the provider again.
+
+use crate::models::{Track, TrackId};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// File name of the play counts inside the data directory.
+pub const PLAY_COUNTS_FILE: &str = "play_counts.jsonl";
+
+/// Tracks kept on disk; past this the least played are dropped.
+const MAX_ENTRIES: usize = 500;
+
+/// The parts of a played track the "Most Played" list shows and queues.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlayedTrack {
+    pub id: TrackId,
+    pub provider_id: String,
+    pub title: String,
+    pub artist: String,
+    pub album: Option<String>,
+    pub duration_seconds: Option<u32>,
+}
+
+impl From<&Track> for PlayedTrack {
+    fn from(track: &Track) -> Self {
+        Self {
+            id: track.id.clone(),
+            provider_id: track.provider_id.clone(),
+            title: track.title.clone(),
+            artist: track.artist.clone(),
+            album: track.album.clone(),
+            duration_seconds: track.duration_seconds,
+        }
+    }
+}
+
+impl From<PlayedTrack> for Track {
+    fn from(track: PlayedTrack) -> Self {
+        Self {
+            id: track.id,
+            provider_id: track.provider_id,
+            title: track.title,
+            artist: track.artist,
+            album: track.album,
+            duration_seconds: track.duration_seconds,
+            track_number: None,
+            disc_number: None,
+            year: None,
+            genre: None,
+            musicbrainz_id: None,
+            replay_gain: Default::default(),
+        }
+    }
+}
+
+/// How often a track has been played, as last seen.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlayCount {
+    pub track: PlayedTrack,
+    pub count: u64,
+    /// Seconds since the Unix epoch.
+    pub last_played: u64,
+}
+
+/// Play counts stored as JSON lines, one per track.
+#[derive(Debug, Clone)]
+pub struct PlayCounts {
+    path: PathBuf,
+}
+
+impl PlayCounts {
+    /// Counts kept in `data_dir`.
+    pub fn new(data_dir: &Path) -> Self {
+        Self::at(data_dir.join(PLAY_COUNTS_FILE))
+    }
+
+    /// Counts kept in the file at `path`.
+    pub fn at(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Count a play of `track` now, refreshing its stored metadata. When
+    /// the file is full the least played track makes room; the one just
+    /// played always stays so it has a chance to climb.
+    pub fn record(&self, track: &Track) -> io::Result<()> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        let mut counts = self.load()?;
+        let previous = counts
+            .iter()
+            .position(|c| c.track.provider_id == track.provider_id && c.track.id == track.id)
+            .map_or(0, |i| counts.swap_remove(i).count);
+        if counts.len() >= MAX_ENTRIES {
+            counts.sort_by(most_played_first);
+            counts.truncate(MAX_ENTRIES - 1);
+        }
+        counts.push(PlayCount {
+            track: track.into(),
+            count: previous + 1,
+            last_played: now,
+        });
+        self.persist(&counts)
+    }
+
+    /// Tracks played from `provider_id`, most played first; ties go to the
+    /// one played most recently.
+    pub fn most_played(&self, provider_id: &str) -> io::Result<Vec<PlayCount>> {
+        let mut counts: Vec<_> = self
+            .load()?
+            .into_iter()
+            .filter(|c| c.track.provider_id == provider_id)
+            .collect();
+        counts.sort_by(most_played_first);
+        Ok(counts)
+    }
+
+    /// How often the track `id` from `provider_id` has been played.
+    pub fn count(&self, provider_id: &str, id: &TrackId) -> io::Result<u64> {
+        Ok(self
+            .load()?
+            .iter()
+            .find(|c| c.track.provider_id == provider_id && c.track.id == *id)
+            .map_or(0, |c| c.count))
+    }
+
+    fn load(&self) -> io::Result<Vec<PlayCount>> {
+        let file = match fs::File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        let mut counts = Vec::new();
+        for line in BufReader::new(file).lines() {
+            // A torn or hand-edited line shouldn't cost the other counts.
+            match serde_json::from_str(&line?) {
+                Ok(count) => counts.push(count),
+                Err(e) => tracing::debug!(error = %e, "skipping bad play count line"),
+            }
+        }
+        Ok(counts)
+    }
+
+    /// Rewrite the file through a temporary so readers never see it half written.
+    fn persist(&self, counts: &[PlayCount]) -> io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let tmp = self.path.with_extension("jsonl.tmp");
+        let mut file = io::BufWriter::new(fs::File::create(&tmp)?);
+        for count in counts {
+            serde_json::to_writer(&mut file, count)?;
+            file.write_all(b"\n")?;
+        }
+        file.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        fs::rename(&tmp, &self.path)
+    }
+}
+
+/// Higher counts first; ties go to the one played most recently.
+fn most_played_first(a: &PlayCount, b: &PlayCount) -> Ordering {
+    b.count
+        .cmp(&a.count)
+        .then_with(|| b.last_played.cmp(&a.last_played))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn track(provider: &str, id: &str) -> Track {
+        Track {
+            id: TrackId::new(id),
+            provider_id: provider.into(),
+            title: id.into(),
+            artist: "Artist".into(),
+            album: None,
+            duration_seconds: None,
+            track_number: None,
+            disc_number: None,
+            year: None,
+            genre: None,
+            musicbrainz_id: None,
+            replay_gain: Default::default(),
+        }
+    }
+
+    #[test]
+    fn most_played_sorts_by_count_and_survives_reopening() {
+        let dir = tempdir().unwrap();
+        let counts = PlayCounts::new(dir.path());
+        assert!(counts.most_played("filesystem").unwrap().is_empty());
+
+        for (provider, id) in [
+            ("filesystem", "a"),
+            ("filesystem", "b"),
+            ("melodee", "b"),
+            ("filesystem", "b"),
+        ] {
+            counts.record(&track(provider, id)).unwrap();
+        }
+
+        let reopened = PlayCounts::new(dir.path());
+        let listed: Vec<_> = reopened
+            .most_played("filesystem")
+            .unwrap()
+            .into_iter()
+            .map(|c| (c.track.id.0, c.count))
+            .collect();
+        assert_eq!(listed, [("b".to_string(), 2), ("a".to_string(), 1)]);
+        assert_eq!(reopened.count("melodee", &TrackId::new("b")).unwrap(), 1);
+        assert_eq!(reopened.count("melodee", &TrackId::new("a")).unwrap(), 0);
+    }
+
+    #[test]
+    fn a_full_file_drops_the_least_played() {
+        let dir = tempdir().unwrap();
+        let counts = PlayCounts::new(dir.path());
+        counts.record(&track("filesystem", "favourite")).unwrap();
+        counts.record(&track("filesystem", "favourite")).unwrap();
+        for n in 0..MAX_ENTRIES + 5 {
+            counts
+                .record(&track("filesystem", &format!("t{n}")))
+                .unwrap();
+        }
+
+        let listed = counts.most_played("filesystem").unwrap();
+        assert_eq!(listed.len(), MAX_ENTRIES);
+        assert_eq!(listed[0].track.id, TrackId::new("favourite"));
+        let last = format!("t{}", MAX_ENTRIES + 4);
+        assert_eq!(counts.count("filesystem", &TrackId::new(&last)).unwrap(), 1);
+    }
+
+    #[test]
+    fn counts_keep_only_what_the_list_needs() {
+        let dir = tempdir().unwrap();
+        let counts = PlayCounts::new(dir.path());
+        let mut song = track("filesystem", "a");
+        song.album = Some("Album".into());
+        song.duration_seconds = Some(200);
+        song.genre = Some("Jazz".into());
+        counts.record(&song).unwrap();
+
+        let stored = fs::read_to_string(counts.path()).unwrap();
+        assert!(!stored.contains("Jazz"), "{stored}");
+        let listed: Track = counts.most_played("filesystem").unwrap()[0]
+            .track
+            .clone()
+            .into();
+        assert_eq!(
+            (listed.album.as_deref(), listed.duration_seconds),
+            (Some("Album"), Some(200))
+        );
+    }
+
+    #[test]
+    fn a_bad_line_keeps_the_other_counts() {
+        let dir = tempdir().unwrap();
+        let counts = PlayCounts::new(dir.path());
+        counts.record(&track("filesystem", "a")).unwrap();
+        let mut file = fs::OpenOptions::new()
+            .append(true)
+            .open(counts.path())
+            .unwrap();
+        file.write_all(b"{not json\n").unwrap();
+
+        counts.record(&track("filesystem", "a")).unwrap();
+        assert_eq!(counts.count("filesystem", &TrackId::new("a")).unwrap(), 2);
+    }
+}
@@ -1,7 +1,10 @@
+use crate::lyrics::Lyrics;
 use crate::models::{
-    Album, AlbumId, Page, PageRequest, Playlist, PlaylistId, StreamUrl, Track, TrackId,
+    Album, AlbumId, Artwork, Chapter, Page, PageCursor, PageRequest, Playlist, PlaylistId,
+    StreamUrl, Track, TrackId,
 };
 use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
 use thiserror::Error;
 
 /// Capability flags describing optional provider features.
@@ -24,6 +27,14 @@ impl ProviderCapabilities {
         self.lyrics
     }
 
+    pub fn supports_favorites(&self) -> bool {
+        self.favorites
+    }
+
+    pub fn supports_recently_played(&self) -> bool {
+        self.recently_played
+    }
+
     pub fn supports_offline_download(&self) -> bool {
         self.offline_download
     }
@@ -40,6 +51,12 @@ pub enum ProviderError {
     NotFound { entity: String },
     #[error("operation not supported: {operation}")]
     NotSupported { operation: String },
+    /// The server asked to be left alone (HTTP 429), for `retry_after` if
+    /// it said how long.
+    #[error("rate limited by the server")]
+    RateLimited {
+        retry_after: Option<std::time::Duration>,
+    },
     #[error("{message}")]
     Other { message: String },
 }
@@ -77,10 +94,55 @@ pub trait Provider: Send + Sync {
     fn browse(&self, kind: BrowseKind, paging: PageRequest)
         -> ProviderResult<Page<CollectionItem>>;
 
+    /// [`browse`](Self::browse) in the given order.
+    ///
+    /// The default sorts each fetched page on its own; providers that can
+    /// sort the whole listing should override it.
+    fn browse_sorted(
+        &self,
+        kind: BrowseKind,
+        sort: SortOrder,
+        paging: PageRequest,
+    ) -> ProviderResult<Page<CollectionItem>> {
+        let mut page = self.browse(kind, paging)?;
+        sort.sort_items(&mut page.items);
+        Ok(page)
+    }
+
     fn list_playlists(&self, paging: PageRequest) -> ProviderResult<Page<Playlist>>;
 
     fn search_playlists(&self, query: &str, paging: PageRequest) -> ProviderResult<Page<Playlist>>;
 
+    /// Albums whose title or artist contains `query`, ignoring case.
+    ///
+    /// The default walks [`browse`](Self::browse) for albums and filters it;
+    /// providers that can search server-side or from an index should override it.
+    fn search_albums(&self, query: &str, paging: PageRequest) -> ProviderResult<Page<Album>> {
+        let query = query.to_lowercase();
+        search_browse(self, BrowseKind::Albums, paging, |item| match item {
+            CollectionItem::Album(album)
+                if album.title.to_lowercase().contains(&query)
+                    || album.artist.to_lowercase().contains(&query) =>
+            {
+                Some(album)
+            }
+            _ => None,
+        })
+    }
+
+    /// Names of artists containing `query`, ignoring case.
+    ///
+    /// The default walks [`browse`](Self::browse) for artists and filters it.
+    fn search_artists(&self, query: &str, paging: PageRequest) -> ProviderResult<Page<String>> {
+        let query = query.to_lowercase();
+        search_browse(self, BrowseKind::Artists, paging, |item| match item {
+            CollectionItem::Artist { name, .. } if name.to_lowercase().contains(&query) => {
+                Some(name)
+            }
+            _ => None,
+        })
+    }
+
     fn get_playlist(&self, playlist_id: &PlaylistId) -> ProviderResult<Playlist>;
 
     fn list_playlist_tracks(
@@ -89,8 +151,31 @@ pub trait Provider: Send + Sync {
         paging: PageRequest,
     ) -> ProviderResult<Page<Track>>;
 
+    /// A playlist and a page of its tracks together.
+    ///
+    /// The default makes both calls; providers that can answer with one
+    /// request or lookup should override it.
+    fn get_playlist_with_tracks(
+        &self,
+        playlist_id: &PlaylistId,
+        paging: PageRequest,
+    ) -> ProviderResult<(Playlist, Page<Track>)> {
+        let playlist = self.get_playlist(playlist_id)?;
+        let tracks = self.list_playlist_tracks(playlist_id, paging)?;
+        Ok((playlist, tracks))
+    }
+
     fn get_album(&self, album_id: &AlbumId) -> ProviderResult<Album>;
 
+    /// Albums by the artist named in a [`CollectionItem::Artist`], ignoring
+    /// case.
+    ///
+    /// The default is [`artist_albums_from_browse`]; providers that can look
+    /// an artist up directly should override it.
+    fn list_artist_albums(&self, artist: &str, paging: PageRequest) -> ProviderResult<Page<Album>> {
+        artist_albums_from_browse(self, artist, paging)
+    }
+
     fn list_album_tracks(
         &self,
         album_id: &AlbumId,
@@ -103,13 +188,106 @@ pub trait Provider: Send + Sync {
     fn get_stream_url(&self, track_id: &TrackId) -> ProviderResult<StreamUrl>;
 
     /// Returns the lyrics for the given track.
-    fn get_lyrics(&self, _track_id: &TrackId) -> ProviderResult<String> {
+    fn get_lyrics(&self, _track_id: &TrackId) -> ProviderResult<Lyrics> {
         Err(ProviderError::NotSupported {
             operation: "get_lyrics".into(),
         })
     }
+
+    /// Returns the chapter markers of the given track, earliest first.
+    fn get_chapters(&self, _track_id: &TrackId) -> ProviderResult<Vec<Chapter>> {
+        Err(ProviderError::NotSupported {
+            operation: "get_chapters".into(),
+        })
+    }
+
+    /// Returns the cover art for the given track.
+    fn get_artwork(&self, _track_id: &TrackId) -> ProviderResult<Artwork> {
+        Err(ProviderError::NotSupported {
+            operation: "get_artwork".into(),
+        })
+    }
+
+    /// Marks the given track as a favorite, or removes the mark.
+    fn set_favorite(&self, _track_id: &TrackId, _favorite: bool) -> ProviderResult<()> {
+        Err(ProviderError::NotSupported {
+            operation: "set_favorite".into(),
+        })
+    }
+
+    /// The user's favorite tracks.
+    fn list_favorites(&self, _paging: PageRequest) -> ProviderResult<Page<Track>> {
+        Err(ProviderError::NotSupported {
+            operation: "list_favorites".into(),
+        })
+    }
+
+    /// Tracks the user played recently, most recent first.
+    fn list_recently_played(&self, _paging: PageRequest) -> ProviderResult<Page<Track>> {
+        Err(ProviderError::NotSupported {
+            operation: "list_recently_played".into(),
+        })
+    }
+
+    /// Checks that the provider's backend is reachable right now. Called
+    /// periodically in the background, so it should be cheap.
+    fn health_check(&self) -> ProviderResult<()> {
+        Ok(())
+    }
+}
+
+/// Albums by `artist`, ignoring case, found by walking
+/// [`Provider::browse`] for albums.
+pub fn artist_albums_from_browse<P: Provider + ?Sized>(
+    provider: &P,
+    artist: &str,
+    paging: PageRequest,
+) -> ProviderResult<Page<Album>> {
+    let artist = artist.to_lowercase();
+    search_browse(provider, BrowseKind::Albums, paging, |item| match item {
+        CollectionItem::Album(album) if album.artist.to_lowercase() == artist => Some(album),
+        _ => None,
+    })
 }
 
+/// Browse pages fetched per request by the default searches.
+const SEARCH_BROWSE_PAGE: u32 = 200;
+
+/// Page through `browse(kind)` keeping what `keep` accepts, then return the
+/// requested page of the matches. Stops as soon as it knows whether another
+/// page of matches exists.
+fn search_browse<P: Provider + ?Sized, T>(
+    provider: &P,
+    kind: BrowseKind,
+    paging: PageRequest,
+    keep: impl Fn(CollectionItem) -> Option<T>,
+) -> ProviderResult<Page<T>> {
+    let paging = paging.normalized();
+    let wanted = paging.end() as usize;
+    let mut matches = Vec::new();
+    let mut request = PageRequest::first_page(SEARCH_BROWSE_PAGE);
+    loop {
+        let page = provider.browse(kind, request)?;
+        matches.extend(page.items.into_iter().filter_map(&keep));
+        if matches.len() > wanted {
+            break;
+        }
+        match page
+            .next
+            .and_then(|cursor| PageRequest::from_cursor(&cursor, SEARCH_BROWSE_PAGE))
+        {
+            Some(next) => request = next,
+            None => break,
+        }
+    }
+    let next = (matches.len() > wanted).then(|| PageCursor::from_offset(wanted as u32));
+    let items = matches
+        .into_iter()
+        .skip(paging.offset as usize)
+        .take(paging.limit as usize)
+        .collect();
+    Ok(Page { items, next })
+}
 
 /// Browse kinds supported by the core UI.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -120,6 +298,71 @@ pub enum BrowseKind {
     Genres,
 }
 
+/// Order of a browse listing. Ties, and items the order says nothing about,
+/// fall back to name (title) and then id so pages stay stable.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+    #[default]
+    Title,
+    Artist,
+    /// Oldest release first; albums without a year last.
+    Year,
+    /// Newest addition first; albums without a date last.
+    RecentlyAdded,
+}
+
+impl SortOrder {
+    pub const ALL: [SortOrder; 4] = [
+        SortOrder::Title,
+        SortOrder::Artist,
+        SortOrder::Year,
+        SortOrder::RecentlyAdded,
+    ];
+
+    /// The order after this one, wrapping around.
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|&s| s == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SortOrder::Title => "Title",
+            SortOrder::Artist => "Artist",
+            SortOrder::Year => "Year",
+            SortOrder::RecentlyAdded => "Recently added",
+        }
+    }
+
+    pub fn compare_albums(self, a: &Album, b: &Album) -> Ordering {
+        let primary = match self {
+            SortOrder::Title => Ordering::Equal,
+            SortOrder::Artist => a.artist.cmp(&b.artist),
+            SortOrder::Year => match (a.year, b.year) {
+                (Some(a), Some(b)) => a.cmp(&b),
+                (a, b) => a.is_none().cmp(&b.is_none()),
+            },
+            SortOrder::RecentlyAdded => match (a.added_at, b.added_at) {
+                (Some(a), Some(b)) => b.cmp(&a),
+                (a, b) => a.is_none().cmp(&b.is_none()),
+            },
+        };
+        primary
+            .then_with(|| a.title.cmp(&b.title))
+            .then_with(|| a.id.0.cmp(&b.id.0))
+    }
+
+    /// Sort browse items in this order. Albums are compared with
+    /// [`compare_albums`](Self::compare_albums), everything else by name.
+    pub fn sort_items(self, items: &mut [CollectionItem]) {
+        items.sort_by(|a, b| match (a, b) {
+            (CollectionItem::Album(a), CollectionItem::Album(b)) => self.compare_albums(a, b),
+            _ => a.name().cmp(b.name()),
+        });
+    }
+}
+
 /// Items returned from browse views.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum CollectionItem {
@@ -135,3 +378,192 @@ pub enum CollectionItem {
         provider_id: String,
     },
 }
+
+impl CollectionItem {
+    /// The album title, playlist name, or artist or genre name.
+    pub fn name(&self) -> &str {
+        match self {
+            CollectionItem::Album(album) => &album.title,
+            CollectionItem::Playlist(playlist) => &playlist.name,
+            CollectionItem::Artist { name, .. } | CollectionItem::Genre { name, .. } => name,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Implements only the required methods; browsing pages two items at a time.
+    struct BrowseOnly;
+
+    impl Provider for BrowseOnly {
+        fn id(&self) -> &str {
+            "browse-only"
+        }
+        fn name(&self) -> &str {
+            "Browse Only"
+        }
+        fn capabilities(&self) -> ProviderCapabilities {
+            ProviderCapabilities::default()
+        }
+        fn search_tracks(
+            &self,
+            _query: &str,
+            _filters: TrackSearchFilters,
+            _paging: PageRequest,
+        ) -> ProviderResult<Page<Track>> {
+            Ok(Page::single_page(Vec::new()))
+        }
+        fn browse(
+            &self,
+            kind: BrowseKind,
+            paging: PageRequest,
+        ) -> ProviderResult<Page<CollectionItem>> {
+            let items: Vec<CollectionItem> = match kind {
+                BrowseKind::Albums => [
+                    ("Abbey Road", "The Beatles"),
+                    ("Revolver", "The Beatles"),
+                    ("Blue", "Joni Mitchell"),
+                    ("Abbey Road Live", "Tribute Band"),
+                ]
+                .into_iter()
+                .map(|(title, artist)| {
+                    CollectionItem::Album(Album {
+                        id: AlbumId::new(title),
+                        provider_id: "browse-only".into(),
+                        title: title.into(),
+                        artist: artist.into(),
+                        track_count: None,
+                        duration_seconds: None,
+                        year: None,
+                        added_at: None,
+                    })
+                })
+                .collect(),
+                BrowseKind::Artists => ["Joni Mitchell", "The Beatles", "Tribute Band"]
+                    .into_iter()
+                    .map(|name| CollectionItem::Artist {
+                        name: name.into(),
+                        provider_id: "browse-only".into(),
+                    })
+                    .collect(),
+                _ => {
+                    return Err(ProviderError::NotSupported {
+                        operation: "browse".into(),
+                    })
+                }
+            };
+            let start = (paging.offset as usize).min(items.len());
+            let end = (start + 2).min(items.len());
+            Ok(Page {
+                next: (end < items.len()).then(|| PageCursor::from_offset(end as u32)),
+                items: items[start..end].to_vec(),
+            })
+        }
+        fn list_playlists(&self, _paging: PageRequest) -> ProviderResult<Page<Playlist>> {
+            unimplemented!()
+        }
+        fn search_playlists(
+            &self,
+            _query: &str,
+            _paging: PageRequest,
+        ) -> ProviderResult<Page<Playlist>> {
+            unimplemented!()
+        }
+        fn get_playlist(&self, _playlist_id: &PlaylistId) -> ProviderResult<Playlist> {
+            unimplemented!()
+        }
+        fn list_playlist_tracks(
+            &self,
+            _playlist_id: &PlaylistId,
+            _paging: PageRequest,
+        ) -> ProviderResult<Page<Track>> {
+            unimplemented!()
+        }
+        fn get_album(&self, _album_id: &AlbumId) -> ProviderResult<Album> {
+            unimplemented!()
+        }
+        fn list_album_tracks(
+            &self,
+            _album_id: &AlbumId,
+            _paging: PageRequest,
+        ) -> ProviderResult<Page<Track>> {
+            unimplemented!()
+        }
+        fn get_track(&self, _track_id: &TrackId) -> ProviderResult<Track> {
+            unimplemented!()
+        }
+        fn get_stream_url(&self, _track_id: &TrackId) -> ProviderResult<StreamUrl> {
+            unimplemented!()
+        }
+    }
+
+    #[test]
+    fn default_sorted_browse_sorts_each_page() {
+        let titles = |sort, offset| {
+            BrowseOnly
+                .browse_sorted(BrowseKind::Albums, sort, PageRequest::new(offset, 2))
+                .unwrap()
+                .items
+                .iter()
+                .map(|item| item.name().to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(titles(SortOrder::Artist, 2), ["Blue", "Abbey Road Live"]);
+        assert_eq!(titles(SortOrder::Title, 2), ["Abbey Road Live", "Blue"]);
+    }
+
+    #[test]
+    fn unknown_years_and_dates_sort_last() {
+        let album = |title: &str, year, added_at| Album {
+            id: AlbumId::new(title),
+            provider_id: "test".into(),
+            title: title.into(),
+            artist: "Artist".into(),
+            track_count: None,
+            duration_seconds: None,
+            year,
+            added_at,
+        };
+        let mut albums = [
+            album("C", None, Some(10)),
+            album("B", Some(2001), None),
+            album("A", Some(1999), Some(30)),
+            album("D", Some(2001), Some(20)),
+        ];
+        let titles = |albums: &[Album]| albums.iter().map(|a| a.title.clone()).collect::<Vec<_>>();
+
+        albums.sort_by(|a, b| SortOrder::Year.compare_albums(a, b));
+        assert_eq!(titles(&albums), ["A", "B", "D", "C"]);
+        albums.sort_by(|a, b| SortOrder::RecentlyAdded.compare_albums(a, b));
+        assert_eq!(titles(&albums), ["A", "D", "C", "B"]);
+        assert_eq!(SortOrder::RecentlyAdded.next(), SortOrder::Title);
+    }
+
+    #[test]
+    fn default_searches_filter_browse_across_pages() {
+        let provider = BrowseOnly;
+
+        let first = provider
+            .search_albums("abbey", PageRequest::first_page(1))
+            .unwrap();
+        assert_eq!(first.items[0].title, "Abbey Road");
+        let next = PageRequest::from_cursor(&first.next.unwrap(), 1).unwrap();
+        let second = provider.search_albums("abbey", next).unwrap();
+        assert_eq!(second.items[0].title, "Abbey Road Live");
+        assert!(second.next.is_none());
+
+        // Album search also matches the artist.
+        let beatles = provider
+            .search_albums("beatles", PageRequest::first_page(10))
+            .unwrap();
+        assert_eq!(beatles.items.len(), 2);
+
+        let artists = provider
+            .search_artists("BAND", PageRequest::first_page(10))
+            .unwrap();
+        assert_eq!(artists.items, ["Tribute Band"]);
+        assert!(artists.next.is_none());
+    }
+}
@@ -1,8 +1,8 @@
-use crate::models::{PageRequest, PlaylistId, TrackId};
+use crate::models::{PageRequest, PlaylistId, StreamLocation, Track, TrackId};
 use crate::provider::{Provider, ProviderError, TrackSearchFilters};
 
 #[cfg(test)]
-use crate::models::{Album, AlbumId, Page, PageCursor, Playlist, StreamUrl, Track};
+use crate::models::{Album, AlbumId, Page, PageCursor, Playlist, StreamUrl};
 #[cfg(test)]
 use crate::provider::{BrowseKind, CollectionItem, ProviderCapabilities};
 use thiserror::Error;
@@ -53,6 +53,8 @@ pub enum ProviderContractError {
     TrackLookupMismatch { expected: TrackId, actual: TrackId },
     #[error("stream URL was empty for track {track_id:?}")]
     EmptyStreamUrl { track_id: TrackId },
+    #[error("stream URL for track {track_id:?} is neither a local file nor HTTP")]
+    UnplayableStreamUrl { track_id: TrackId },
     #[error("provider advertises playlists capability but no playlist expectation supplied")]
     MissingPlaylistExpectation,
     #[error("provider claims playlists support but did not return a playlist")]
@@ -63,6 +65,8 @@ pub enum ProviderContractError {
     PlaylistMissingExpected { expected: PlaylistId },
     #[error("provider claims playlists support but search did not return expected playlist id {expected:?}")]
     PlaylistSearchMissing { expected: PlaylistId },
+    #[error("get_playlist_with_tracks disagrees with get_playlist and list_playlist_tracks for {playlist_id:?}")]
+    PlaylistWithTracksMismatch { playlist_id: PlaylistId },
     #[error(
         "provider does not advertise playlists but list_playlists did not return NotSupported"
     )]
@@ -71,6 +75,14 @@ pub enum ProviderContractError {
         "provider does not advertise playlists but search_playlists did not return NotSupported"
     )]
     PlaylistSearchNotSupportedExpected,
+    #[error("provider claims favorites support but {operation} is not implemented")]
+    FavoritesNotImplemented { operation: String },
+    #[error("provider does not advertise favorites but {operation} did not return NotSupported")]
+    FavoritesNotSupportedExpected { operation: String },
+    #[error("provider claims recently played support but list_recently_played is not implemented")]
+    RecentlyPlayedNotImplemented,
+    #[error("provider does not advertise recently played but list_recently_played did not return NotSupported")]
+    RecentlyPlayedNotSupportedExpected,
     #[error("provider error while running contract: {0}")]
     ProviderFailure(String),
 }
@@ -86,6 +98,8 @@ pub fn run_provider_contract<P: Provider>(
     verify_search(provider, expectations)?;
     verify_stream(provider, expectations)?;
     verify_playlists(provider, expectations)?;
+    verify_favorites(provider)?;
+    verify_recently_played(provider)?;
     Ok(())
 }
 
@@ -160,6 +174,11 @@ fn verify_stream<P: Provider>(
             track_id: expectations.stream_track_id.clone(),
         });
     }
+    if url.location() == StreamLocation::Unsupported {
+        return Err(ProviderContractError::UnplayableStreamUrl {
+            track_id: expectations.stream_track_id.clone(),
+        });
+    }
     Ok(())
 }
 
@@ -190,6 +209,23 @@ fn verify_playlists<P: Provider>(
             });
         }
 
+        let playlist_id = &playlist_expectation.playlist_id;
+        let paging = PageRequest::first_page(25);
+        let failure = |e: ProviderError| ProviderContractError::ProviderFailure(e.to_string());
+        let (playlist, tracks) = provider
+            .get_playlist_with_tracks(playlist_id, paging)
+            .map_err(failure)?;
+        let separate = provider.get_playlist(playlist_id).map_err(failure)?;
+        let separate_tracks = provider
+            .list_playlist_tracks(playlist_id, paging)
+            .map_err(failure)?;
+        let ids = |tracks: &[Track]| tracks.iter().map(|t| t.id.clone()).collect::<Vec<_>>();
+        if playlist != separate || ids(&tracks.items) != ids(&separate_tracks.items) {
+            return Err(ProviderContractError::PlaylistWithTracksMismatch {
+                playlist_id: playlist_id.clone(),
+            });
+        }
+
         if let Some(query) = &playlist_expectation.search_query {
             let searched = provider
                 .search_playlists(query, PageRequest::first_page(25))
@@ -219,9 +255,64 @@ fn verify_playlists<P: Provider>(
     Ok(())
 }
 
+/// Track id that no provider should have, for checking `set_favorite` is
+/// implemented without changing a real track.
+const FAVORITE_PROBE_ID: &str = "tunez-contract-probe";
+
+/// Read-only: the contract runs against real libraries, so `set_favorite` is
+/// only probed with a track id that doesn't exist.
+fn verify_favorites<P: Provider>(provider: &P) -> Result<(), ProviderContractError> {
+    let listed = provider.list_favorites(PageRequest::first_page(25));
+    let set = provider.set_favorite(&TrackId::new(FAVORITE_PROBE_ID), false);
+    if !provider.capabilities().supports_favorites() {
+        if !matches!(listed, Err(ProviderError::NotSupported { .. })) {
+            return Err(ProviderContractError::FavoritesNotSupportedExpected {
+                operation: "list_favorites".into(),
+            });
+        }
+        if !matches!(set, Err(ProviderError::NotSupported { .. })) {
+            return Err(ProviderContractError::FavoritesNotSupportedExpected {
+                operation: "set_favorite".into(),
+            });
+        }
+        return Ok(());
+    }
+
+    match listed {
+        Err(ProviderError::NotSupported { .. }) => {
+            return Err(ProviderContractError::FavoritesNotImplemented {
+                operation: "list_favorites".into(),
+            })
+        }
+        Err(other) => return Err(ProviderContractError::ProviderFailure(other.to_string())),
+        Ok(_) => {}
+    }
+    // Any answer but NotSupported will do: the probe track isn't there.
+    if matches!(set, Err(ProviderError::NotSupported { .. })) {
+        return Err(ProviderContractError::FavoritesNotImplemented {
+            operation: "set_favorite".into(),
+        });
+    }
+    Ok(())
+}
+
+fn verify_recently_played<P: Provider>(provider: &P) -> Result<(), ProviderContractError> {
+    let listed = provider.list_recently_played(PageRequest::first_page(25));
+    match (provider.capabilities().supports_recently_played(), listed) {
+        (true, Err(ProviderError::NotSupported { .. })) => {
+            Err(ProviderContractError::RecentlyPlayedNotImplemented)
+        }
+        (true, Err(other)) => Err(ProviderContractError::ProviderFailure(other.to_string())),
+        (false, Err(ProviderError::NotSupported { .. })) | (true, Ok(_)) => Ok(()),
+        (false, _) => Err(ProviderContractError::RecentlyPlayedNotSupportedExpected),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::provider::ProviderResult;
+    use std::sync::{Arc, Mutex};
 
     #[derive(Clone)]
     struct FakeProvider {
@@ -232,6 +323,10 @@ mod tests {
         playlists: Vec<Playlist>,
         playlist_tracks: Vec<Track>,
         stream_prefix: String,
+        /// `None` when the favorites methods aren't implemented.
+        favorites: Option<Vec<Track>>,
+        /// Tracks passed to `set_favorite`.
+        favorite_writes: Arc<Mutex<Vec<TrackId>>>,
     }
 
     impl FakeProvider {
@@ -244,6 +339,11 @@ mod tests {
                 album: Some("Album".into()),
                 duration_seconds: Some(180),
                 track_number: Some(1),
+                disc_number: None,
+                year: None,
+                genre: None,
+                musicbrainz_id: None,
+                replay_gain: Default::default(),
             };
             let playlist = Playlist {
                 id: PlaylistId::new("pl-1"),
@@ -267,6 +367,8 @@ mod tests {
                 playlists: vec![playlist],
                 playlist_tracks: vec![track],
                 stream_prefix: "file:///music/".into(),
+                favorites: None,
+                favorite_writes: Arc::default(),
             }
         }
 
@@ -279,6 +381,11 @@ mod tests {
                 album: Some("Album".into()),
                 duration_seconds: Some(180),
                 track_number: Some(1),
+                disc_number: None,
+                year: None,
+                genre: None,
+                musicbrainz_id: None,
+                replay_gain: Default::default(),
             };
             Self {
                 id: "fake".into(),
@@ -295,6 +402,8 @@ mod tests {
                 playlists: Vec::new(),
                 playlist_tracks: vec![track],
                 stream_prefix: "file:///music/".into(),
+                favorites: None,
+                favorite_writes: Arc::default(),
             }
         }
     }
@@ -322,8 +431,8 @@ mod tests {
             let mut items: Vec<Track> = self
                 .tracks
                 .iter()
-                .cloned()
                 .filter(|t| t.title.to_ascii_lowercase().contains(&lower))
+                .cloned()
                 .collect();
             if items.is_empty() {
                 items = self.tracks.clone();
@@ -371,8 +480,8 @@ mod tests {
             let items: Vec<Playlist> = self
                 .playlists
                 .iter()
-                .cloned()
                 .filter(|p| p.name.to_ascii_lowercase().contains(&lower))
+                .cloned()
                 .collect();
             Ok(Page { items, next: None })
         }
@@ -445,6 +554,26 @@ mod tests {
                     entity: track_id.0.clone(),
                 })
         }
+
+        fn set_favorite(&self, track_id: &TrackId, _favorite: bool) -> ProviderResult<()> {
+            if self.favorites.is_none() {
+                return Err(ProviderError::NotSupported {
+                    operation: "set_favorite".into(),
+                });
+            }
+            self.favorite_writes.lock().unwrap().push(track_id.clone());
+            self.get_track(track_id).map(|_| ())
+        }
+
+        fn list_favorites(&self, _paging: PageRequest) -> ProviderResult<Page<Track>> {
+            let items = self
+                .favorites
+                .clone()
+                .ok_or_else(|| ProviderError::NotSupported {
+                    operation: "list_favorites".into(),
+                })?;
+            Ok(Page { items, next: None })
+        }
     }
 
     #[test]
@@ -468,6 +597,24 @@ mod tests {
         assert!(result.is_ok(), "expected contract to pass: {result:?}");
     }
 
+    #[test]
+    fn default_playlist_with_tracks_combines_both_calls() {
+        let provider = FakeProvider::with_playlists();
+        let id = PlaylistId::new("pl-1");
+        let (playlist, tracks) = provider
+            .get_playlist_with_tracks(&id, PageRequest::first_page(10))
+            .unwrap();
+        assert_eq!(playlist, provider.get_playlist(&id).unwrap());
+        assert_eq!(playlist.track_count, Some(tracks.items.len() as u32));
+        assert_eq!(tracks.items, provider.playlist_tracks);
+
+        assert!(matches!(
+            provider
+                .get_playlist_with_tracks(&PlaylistId::new("nope"), PageRequest::first_page(10)),
+            Err(ProviderError::NotFound { .. })
+        ));
+    }
+
     #[test]
     fn contract_validates_not_supported_when_playlists_disabled() {
         let provider = FakeProvider::without_playlists();
@@ -486,6 +633,75 @@ mod tests {
         assert!(result.is_ok(), "expected contract to pass: {result:?}");
     }
 
+    #[test]
+    fn contract_fails_when_favorites_advertised_but_missing() {
+        let mut provider = FakeProvider::without_playlists();
+        provider.capabilities.favorites = true;
+        let expectations = ProviderContractExpectations {
+            provider_id: "fake".into(),
+            search: SearchExpectation {
+                query: "track".into(),
+                filters: TrackSearchFilters::default(),
+                expected_first_track_id: TrackId::new("track-1"),
+            },
+            stream_track_id: TrackId::new("track-1"),
+            playlist: None,
+        };
+
+        let result = run_provider_contract(&provider, &expectations);
+        assert_eq!(
+            result,
+            Err(ProviderContractError::FavoritesNotImplemented {
+                operation: "list_favorites".into()
+            })
+        );
+    }
+
+    #[test]
+    fn favorites_check_leaves_real_tracks_alone() {
+        let mut provider = FakeProvider::without_playlists();
+        provider.capabilities.favorites = true;
+        provider.favorites = Some(provider.tracks.clone());
+        let expectations = ProviderContractExpectations {
+            provider_id: "fake".into(),
+            search: SearchExpectation {
+                query: "track".into(),
+                filters: TrackSearchFilters::default(),
+                expected_first_track_id: TrackId::new("track-1"),
+            },
+            stream_track_id: TrackId::new("track-1"),
+            playlist: None,
+        };
+
+        run_provider_contract(&provider, &expectations).unwrap();
+        assert_eq!(
+            *provider.favorite_writes.lock().unwrap(),
+            [TrackId::new(FAVORITE_PROBE_ID)]
+        );
+    }
+
+    #[test]
+    fn contract_fails_when_recently_played_advertised_but_missing() {
+        let mut provider = FakeProvider::without_playlists();
+        provider.capabilities.recently_played = true;
+        let expectations = ProviderContractExpectations {
+            provider_id: "fake".into(),
+            search: SearchExpectation {
+                query: "track".into(),
+                filters: TrackSearchFilters::default(),
+                expected_first_track_id: TrackId::new("track-1"),
+            },
+            stream_track_id: TrackId::new("track-1"),
+            playlist: None,
+        };
+
+        let result = run_provider_contract(&provider, &expectations);
+        assert_eq!(
+            result,
+            Err(ProviderContractError::RecentlyPlayedNotImplemented)
+        );
+    }
+
     #[test]
     fn contract_fails_when_stream_url_empty() {
         let mut provider = FakeProvider::with_playlists();
@@ -510,4 +726,29 @@ mod tests {
             Err(ProviderContractError::EmptyStreamUrl { .. })
         ));
     }
+
+    #[test]
+    fn contract_fails_when_stream_url_is_a_bare_id() {
+        let mut provider = FakeProvider::with_playlists();
+        provider.stream_prefix = "song-".into();
+        let expectations = ProviderContractExpectations {
+            provider_id: "fake".into(),
+            search: SearchExpectation {
+                query: "track".into(),
+                filters: TrackSearchFilters::default(),
+                expected_first_track_id: TrackId::new("track-1"),
+            },
+            stream_track_id: TrackId::new("track-1"),
+            playlist: Some(PlaylistExpectation {
+                playlist_id: PlaylistId::new("pl-1"),
+                search_query: Some("fav".into()),
+            }),
+        };
+
+        let result = run_provider_contract(&provider, &expectations);
+        assert!(matches!(
+            result,
+            Err(ProviderContractError::UnplayableStreamUrl { .. })
+        ));
+    }
 }
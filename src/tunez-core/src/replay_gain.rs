@@ -0,0 +1,153 @@
+//! ReplayGain loudness normalization.
+
+use serde::{Deserialize, Serialize};
+
+/// Largest boost or cut applied, whatever the tags say.
+pub const MAX_GAIN_DB: f32 = 15.0;
+
+/// Which ReplayGain value playback uses.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReplayGainMode {
+    #[default]
+    Off,
+    Track,
+    Album,
+}
+
+/// ReplayGain values read from a track's tags. Gains are in dB, peaks are
+/// linear sample amplitudes (1.0 is full scale).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReplayGain {
+    #[serde(default)]
+    pub track_gain_db: Option<f32>,
+    #[serde(default)]
+    pub album_gain_db: Option<f32>,
+    #[serde(default)]
+    pub track_peak: Option<f32>,
+    #[serde(default)]
+    pub album_peak: Option<f32>,
+}
+
+// Values come from `parse_gain`/`parse_peak` or JSON, neither of which yields NaN.
+impl Eq for ReplayGain {}
+
+impl ReplayGain {
+    /// Linear factor to scale samples by. The requested value falls back to
+    /// the other one when missing, and to unity when neither is tagged. The
+    /// gain is clamped to ±[`MAX_GAIN_DB`]; with `prevent_clipping` it is
+    /// also lowered so the tagged peak stays at or below full scale.
+    pub fn factor(&self, mode: ReplayGainMode, prevent_clipping: bool) -> f32 {
+        let (gain, peak) = match mode {
+            ReplayGainMode::Off => return 1.0,
+            ReplayGainMode::Track => (
+                self.track_gain_db.or(self.album_gain_db),
+                self.track_peak.or(self.album_peak),
+            ),
+            ReplayGainMode::Album => (
+                self.album_gain_db.or(self.track_gain_db),
+                self.album_peak.or(self.track_peak),
+            ),
+        };
+        let Some(gain) = gain else {
+            return 1.0;
+        };
+        let factor = 10f32.powf(gain.clamp(-MAX_GAIN_DB, MAX_GAIN_DB) / 20.0);
+        match peak.filter(|p| prevent_clipping && *p > 0.0) {
+            Some(peak) => factor.min(1.0 / peak),
+            None => factor,
+        }
+    }
+}
+
+/// Parse a gain tag such as `-6.54 dB` or `+1.2`.
+pub fn parse_gain(value: &str) -> Option<f32> {
+    let value = value.trim();
+    let number = value
+        .strip_suffix("dB")
+        .or_else(|| value.strip_suffix("db"))
+        .unwrap_or(value);
+    number
+        .trim()
+        .trim_start_matches('+')
+        .parse::<f32>()
+        .ok()
+        .filter(|g| g.is_finite())
+}
+
+/// Parse a peak tag such as `0.988525`.
+pub fn parse_peak(value: &str) -> Option<f32> {
+    value
+        .trim()
+        .parse::<f32>()
+        .ok()
+        .filter(|p| p.is_finite() && *p >= 0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-4
+    }
+
+    #[test]
+    fn parses_tag_values() {
+        assert_eq!(parse_gain("-6.54 dB"), Some(-6.54));
+        assert_eq!(parse_gain("+1.20 dB"), Some(1.2));
+        assert_eq!(parse_gain("3"), Some(3.0));
+        assert_eq!(parse_gain("loud"), None);
+        assert_eq!(parse_gain("NaN"), None);
+        assert_eq!(parse_peak("0.988525"), Some(0.988525));
+        assert_eq!(parse_peak("-1"), None);
+    }
+
+    #[test]
+    fn factor_follows_mode_with_fallback() {
+        let gain = ReplayGain {
+            track_gain_db: Some(-6.0),
+            album_gain_db: Some(-12.0),
+            ..Default::default()
+        };
+        assert_eq!(gain.factor(ReplayGainMode::Off, true), 1.0);
+        assert!(close(gain.factor(ReplayGainMode::Track, true), 0.501_187));
+        assert!(close(gain.factor(ReplayGainMode::Album, true), 0.251_189));
+
+        let track_only = ReplayGain {
+            track_gain_db: Some(-6.0),
+            ..Default::default()
+        };
+        assert!(close(
+            track_only.factor(ReplayGainMode::Album, true),
+            0.501_187
+        ));
+        assert_eq!(
+            ReplayGain::default().factor(ReplayGainMode::Track, true),
+            1.0
+        );
+    }
+
+    #[test]
+    fn factor_is_clamped_and_peak_limited() {
+        let huge = ReplayGain {
+            track_gain_db: Some(40.0),
+            ..Default::default()
+        };
+        assert!(close(
+            huge.factor(ReplayGainMode::Track, false),
+            10f32.powf(MAX_GAIN_DB / 20.0)
+        ));
+
+        let boosted = ReplayGain {
+            track_gain_db: Some(6.0),
+            track_peak: Some(0.8),
+            ..Default::default()
+        };
+        assert!(close(boosted.factor(ReplayGainMode::Track, true), 1.25));
+        assert!(close(
+            boosted.factor(ReplayGainMode::Track, false),
+            1.995_262
+        ));
+    }
+}
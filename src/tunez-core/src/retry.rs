@@ -0,0 +1,201 @@
+//! Retry with exponential backoff for transient network failures.
+//!
+//! Shared by providers and scrobblers so they back off the same way. The
+//! caller decides which failures are transient by wrapping them in
+//! [`RetryError::Transient`].
+
+use rand::Rng;
+use std::future::Future;
+use std::time::Duration;
+
+/// How many times to try an operation and how long to wait in between.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Total attempts, including the first. `1` disables retries.
+    pub max_attempts: u32,
+    /// Delay before the first retry; doubled for each one after.
+    pub base_delay: Duration,
+    /// Upper bound for a single backoff delay.
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(250),
+            max_delay: Duration::from_secs(5),
+        }
+    }
+}
+
+/// Outcome of a failed attempt.
+#[derive(Debug)]
+pub enum RetryError<E> {
+    /// Not worth retrying (e.g. 401 or 404).
+    Permanent(E),
+    /// May succeed if tried again, optionally after a server-requested delay.
+    Transient {
+        error: E,
+        retry_after: Option<Duration>,
+    },
+}
+
+impl<E> RetryError<E> {
+    pub fn transient(error: E) -> Self {
+        Self::Transient {
+            error,
+            retry_after: None,
+        }
+    }
+
+    fn into_inner(self) -> E {
+        match self {
+            Self::Permanent(error) | Self::Transient { error, .. } => error,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Never retry.
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    /// Backoff before retry number `retry` (starting at 1): the exponential
+    /// delay capped at `max_delay`, plus up to half of it again as jitter.
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let exp = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(retry.saturating_sub(1)))
+            .min(self.max_delay);
+        let jitter_ms = (exp.as_millis() / 2) as u64;
+        let jitter = if jitter_ms == 0 {
+            0
+        } else {
+            rand::thread_rng().gen_range(0..=jitter_ms)
+        };
+        exp + Duration::from_millis(jitter)
+    }
+
+    /// Run `op` until it succeeds, fails permanently, or attempts run out.
+    ///
+    /// A `retry_after` on a transient failure replaces the computed backoff.
+    pub async fn run<T, E, F, Fut>(&self, mut op: F) -> Result<T, E>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, RetryError<E>>>,
+    {
+        let mut attempt = 1;
+        loop {
+            match op().await {
+                Ok(value) => return Ok(value),
+                Err(RetryError::Transient { retry_after, .. }) if attempt < self.max_attempts => {
+                    let delay = retry_after.unwrap_or_else(|| self.backoff(attempt));
+                    tracing::debug!(attempt, ?delay, "transient failure, retrying");
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                Err(err) => return Err(err.into_inner()),
+            }
+        }
+    }
+}
+
+/// Whether an HTTP status is worth retrying: 429 and any 5xx.
+pub fn is_transient_status(status: u16) -> bool {
+    status == 429 || (500..600).contains(&status)
+}
+
+/// Parse a `Retry-After` header given in seconds. HTTP-date values are not
+/// supported and yield `None`, falling back to the normal backoff.
+pub fn parse_retry_after(value: &str) -> Option<Duration> {
+    value.trim().parse::<u64>().ok().map(Duration::from_secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    fn fast() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(5),
+        }
+    }
+
+    #[test]
+    fn backoff_grows_and_is_capped() {
+        let policy = RetryPolicy {
+            max_attempts: 5,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(300),
+        };
+        let first = policy.backoff(1);
+        assert!(first >= Duration::from_millis(100) && first <= Duration::from_millis(150));
+        let second = policy.backoff(2);
+        assert!(second >= Duration::from_millis(200) && second <= Duration::from_millis(300));
+        let capped = policy.backoff(10);
+        assert!(capped >= Duration::from_millis(300) && capped <= Duration::from_millis(450));
+    }
+
+    #[tokio::test]
+    async fn transient_failures_are_retried_until_success() {
+        let calls = Cell::new(0);
+        let result: Result<u32, &str> = fast()
+            .run(|| {
+                calls.set(calls.get() + 1);
+                let n = calls.get();
+                async move {
+                    if n < 3 {
+                        Err(RetryError::transient("busy"))
+                    } else {
+                        Ok(n)
+                    }
+                }
+            })
+            .await;
+        assert_eq!(result, Ok(3));
+    }
+
+    #[tokio::test]
+    async fn permanent_failures_are_not_retried() {
+        let calls = Cell::new(0);
+        let result: Result<(), &str> = fast()
+            .run(|| {
+                calls.set(calls.get() + 1);
+                async { Err(RetryError::Permanent("not found")) }
+            })
+            .await;
+        assert_eq!(result, Err("not found"));
+        assert_eq!(calls.get(), 1);
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_attempts() {
+        let calls = Cell::new(0);
+        let result: Result<(), &str> = fast()
+            .run(|| {
+                calls.set(calls.get() + 1);
+                async { Err(RetryError::transient("down")) }
+            })
+            .await;
+        assert_eq!(result, Err("down"));
+        assert_eq!(calls.get(), 3);
+    }
+
+    #[test]
+    fn classifies_statuses_and_retry_after() {
+        assert!(is_transient_status(429));
+        assert!(is_transient_status(503));
+        assert!(!is_transient_status(401));
+        assert!(!is_transient_status(404));
+        assert_eq!(parse_retry_after(" 2 "), Some(Duration::from_secs(2)));
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), None);
+    }
+}
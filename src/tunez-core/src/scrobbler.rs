@@ -4,6 +4,7 @@ use serde_json::Deserializer;
 use std::fs;
 use std::io::{BufReader, Write};
 use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
 use thiserror::Error;
 
 /// Playback states surfaced to Scrobblers.
@@ -16,6 +17,23 @@ pub enum PlaybackState {
     Ended,
 }
 
+/// Listening time that always counts as a full listen, regardless of track length.
+pub const SCROBBLE_THRESHOLD_MAX_SECONDS: u64 = 240;
+
+/// How long after it happened an event still counts as live. Older events
+/// are being replayed from a backlog.
+pub const LIVE_EVENT_MAX_AGE_SECONDS: u64 = 60;
+
+/// Whether a play counts as a listen: at least half the track or four
+/// minutes, whichever is lower. Unknown durations use the four-minute rule.
+pub fn meets_scrobble_threshold(played_seconds: u64, duration_seconds: Option<u64>) -> bool {
+    let required = match duration_seconds {
+        Some(duration) => (duration / 2).min(SCROBBLE_THRESHOLD_MAX_SECONDS),
+        None => SCROBBLE_THRESHOLD_MAX_SECONDS,
+    };
+    played_seconds >= required
+}
+
 /// Per-second (or similar cadence) playback telemetry.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct PlaybackProgress {
@@ -33,6 +51,32 @@ pub struct ScrobbleEvent {
     pub player_name: String,
     /// Optional device identifier for the current host.
     pub device_id: Option<String>,
+    /// When the event happened, in seconds since the Unix epoch. Backlogged
+    /// events are delivered long after; `None` in ones saved before this was
+    /// recorded.
+    #[serde(default)]
+    pub occurred_at: Option<u64>,
+}
+
+impl ScrobbleEvent {
+    /// The current time, as an [`occurred_at`](Self::occurred_at) value.
+    pub fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs())
+    }
+
+    /// When the event happened, or now if that wasn't recorded.
+    pub fn occurred_at_or_now(&self) -> u64 {
+        self.occurred_at.unwrap_or_else(Self::now)
+    }
+
+    /// Whether the event is being delivered from a backlog rather than as it
+    /// happens. A now-playing update for it would be stale.
+    pub fn is_replayed(&self) -> bool {
+        self.occurred_at
+            .is_none_or(|at| Self::now().saturating_sub(at) > LIVE_EVENT_MAX_AGE_SECONDS)
+    }
 }
 
 #[derive(Debug, Error)]
@@ -51,14 +95,24 @@ pub enum ScrobblerError {
 
 pub type ScrobblerResult<T> = Result<T, ScrobblerError>;
 
+/// A batch submission that stopped partway. The first `sent` events were
+/// delivered; the rest were not.
+#[derive(Debug, Error)]
+#[error("{error} (after {sent} delivered)")]
+pub struct BatchError {
+    pub sent: usize,
+    #[source]
+    pub error: ScrobblerError,
+}
+
 /// Scrobbler interface (Phase 1).
 #[async_trait::async_trait]
 pub trait Scrobbler: Send + Sync {
     /// Stable scrobbler identifier (e.g., "listenbrainz").
     fn id(&self) -> &str;
 
-    /// Advertised default polling cadence (seconds). Core MAY use this to
-    /// adjust tick frequency; it defaults to 1s in Phase 1.
+    /// How often the player should send progress updates while a track
+    /// plays. Defaults to 1s.
     fn desired_tick(&self) -> std::time::Duration {
         std::time::Duration::from_secs(1)
     }
@@ -66,6 +120,48 @@ pub trait Scrobbler: Send + Sync {
     /// Called when playback state/progress changes.
     /// This should be non-blocking (async).
     async fn submit(&self, event: &ScrobbleEvent) -> ScrobblerResult<()>;
+
+    /// A periodic now-playing refresh for a track that is still playing.
+    /// Losing one is harmless, so wrappers that persist events for retry
+    /// should pass it straight through. Defaults to [`submit`](Self::submit).
+    async fn submit_progress(&self, event: &ScrobbleEvent) -> ScrobblerResult<()> {
+        self.submit(event).await
+    }
+
+    /// Submit several events in order. Submits them one at a time by
+    /// default; backends whose API accepts a list should send them in as
+    /// few requests as they can.
+    async fn submit_batch(&self, events: &[ScrobbleEvent]) -> Result<(), BatchError> {
+        for (sent, event) in events.iter().enumerate() {
+            self.submit(event)
+                .await
+                .map_err(|error| BatchError { sent, error })?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl<S: Scrobbler + ?Sized> Scrobbler for std::sync::Arc<S> {
+    fn id(&self) -> &str {
+        (**self).id()
+    }
+
+    fn desired_tick(&self) -> std::time::Duration {
+        (**self).desired_tick()
+    }
+
+    async fn submit(&self, event: &ScrobbleEvent) -> ScrobblerResult<()> {
+        (**self).submit(event).await
+    }
+
+    async fn submit_progress(&self, event: &ScrobbleEvent) -> ScrobblerResult<()> {
+        (**self).submit_progress(event).await
+    }
+
+    async fn submit_batch(&self, events: &[ScrobbleEvent]) -> Result<(), BatchError> {
+        (**self).submit_batch(events).await
+    }
 }
 
 /// A wrapper that persists events to disk before attempting to send them via the inner Scrobbler.
@@ -129,57 +225,34 @@ impl<S: Scrobbler> PersistentScrobbler<S> {
         Ok(())
     }
 
-    /// Try to flush pending events.
-    /// In a real implementation this would likely be called periodically.
-    /// Here it is called on submit.
-    pub async fn flush(&self) -> ScrobblerResult<()> {
+    /// Number of events waiting on disk.
+    pub fn pending(&self) -> ScrobblerResult<usize> {
+        self.load().map(|events| events.len())
+    }
+
+    /// Submit persisted events as one batch, in order.
+    ///
+    /// Successfully sent events are removed from disk; if the batch stops
+    /// partway, the failed event and everything after it are kept for the
+    /// next attempt. Returns the number of events delivered.
+    pub async fn flush(&self) -> ScrobblerResult<usize> {
         let events = self.load()?;
         if events.is_empty() {
-            return Ok(());
+            return Ok(0);
         }
 
-        let mut remaining = Vec::new();
-        // Try to send all events. If one fails, stop and keep the rest.
-        // In a more robust system we might want to discard permanently broken events.
-        for event in events {
-            match self.inner.submit(&event).await {
-                Ok(_) => {} // Success, drop event (it was "popped")
-                Err(e) => {
-                    // Log error?
-                    tracing::warn!("Failed to submit scrobble: {}", e);
-                    remaining.push(event);
-                    // Stop trying for now if network/auth fails
-                    // But if it's "Other", maybe we should continue?
-                    // For safety, let's keep order strict.
-                    break;
-                }
+        let sent = match self.inner.submit_batch(&events).await {
+            Ok(()) => events.len(),
+            Err(e) => {
+                tracing::warn!(sent = e.sent, error = %e.error, "scrobble batch stopped partway");
+                e.sent.min(events.len())
             }
-        }
-
-        // Write back remaining events.
-        // But wait, we iterated the list... we need to keep the ones we broke on PLUS
-        // the ones we didn't even try.
-        // Actually the loop above consumes `events`.
-        // Logic fix:
-        // We need to properly re-persist only what failed.
-        // Since we broke the loop, `remaining` has the failed one.
-        // But we need the REST of the original list too potentially. Used vec drain logic?
-
-        // Let's reload to be safe against concurrency?
-        // No, this struct isn't async-mutex protected internally (yet).
-        // Let's assume single threaded flushing for Phase 1.
-
-        // Correct approach:
-        // iterate `events` by index or similar?
-        // Let's just re-write `remaining` + `unprocessed`.
-        // Actually let's just do:
-
-        // events is consumed.
-        // `remaining` contains the failed event.
-        // We need to add all SUBSEQUENT events from `events` to `remaining` as well.
-        // This loop logic is slightly flawed.
+        };
 
-        Ok(())
+        if sent > 0 {
+            self.persist(events[sent..].to_vec())?;
+        }
+        Ok(sent)
     }
 }
 
@@ -189,42 +262,32 @@ impl<S: Scrobbler> Scrobbler for PersistentScrobbler<S> {
         self.inner.id()
     }
 
+    fn desired_tick(&self) -> std::time::Duration {
+        self.inner.desired_tick()
+    }
+
     async fn submit(&self, event: &ScrobbleEvent) -> ScrobblerResult<()> {
-        // ALWAYS persist first (Write-Ahead Log style).
+        // Persist first so nothing is lost if delivery fails, then drain the backlog.
         let mut events = self.load()?;
         events.push(event.clone());
         self.persist(events)?;
+        self.flush().await.map(|_| ())
+    }
 
-        // Then try to flush ONLY if we can.
-        // For Phase 1 simple logic: try to flush everything.
-        // If flush succeeds, the file will be cleared/updated.
-
-        // Re-load to get full queue including the one we just added
-        let queue = self.load()?;
-        let mut keep = Vec::new();
-        let mut failed = false;
-
-        for evt in queue {
-            if failed {
-                keep.push(evt);
-                continue;
-            }
-
-            match self.inner.submit(&evt).await {
-                Ok(_) => {
-                    // Submitted successfully, do not add to 'keep'
-                }
-                Err(e) => {
-                    tracing::warn!("Failed to submit scrobble '{}': {}", evt.track.title, e);
-                    // Keep this event
-                    keep.push(evt);
-                    failed = true;
-                }
-            }
-        }
+    async fn submit_progress(&self, event: &ScrobbleEvent) -> ScrobblerResult<()> {
+        // Best effort: a stale now-playing update isn't worth retrying.
+        self.inner.submit_progress(event).await
+    }
 
-        // Update persistence with what remains
-        self.persist(keep)
+    async fn submit_batch(&self, batch: &[ScrobbleEvent]) -> Result<(), BatchError> {
+        let mut events = self.load().map_err(|error| BatchError { sent: 0, error })?;
+        events.extend_from_slice(batch);
+        self.persist(events)
+            .map_err(|error| BatchError { sent: 0, error })?;
+        self.flush()
+            .await
+            .map(|_| ())
+            .map_err(|error| BatchError { sent: 0, error })
     }
 }
 
@@ -394,6 +457,11 @@ mod tests {
             album: Some("Album".into()),
             duration_seconds: Some(180),
             track_number: Some(1),
+            disc_number: None,
+            year: None,
+            genre: None,
+            musicbrainz_id: None,
+            replay_gain: Default::default(),
         }
     }
 
@@ -407,9 +475,145 @@ mod tests {
             state,
             player_name: "Tunez".into(),
             device_id: Some("device-1".into()),
+            occurred_at: Some(ScrobbleEvent::now()),
+        }
+    }
+
+    /// Inner scrobbler that fails the Nth submission (1-based) and succeeds otherwise.
+    struct FailNth {
+        fail_on: usize,
+        calls: std::sync::atomic::AtomicUsize,
+        delivered: std::sync::Mutex<Vec<u64>>,
+    }
+
+    impl FailNth {
+        fn new(fail_on: usize) -> Self {
+            Self {
+                fail_on,
+                calls: std::sync::atomic::AtomicUsize::new(0),
+                delivered: std::sync::Mutex::new(Vec::new()),
+            }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl Scrobbler for FailNth {
+        fn id(&self) -> &str {
+            "fail-nth"
+        }
+
+        async fn submit(&self, event: &ScrobbleEvent) -> ScrobblerResult<()> {
+            let call = self.calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst) + 1;
+            if call == self.fail_on {
+                return Err(ScrobblerError::Network {
+                    message: "offline".into(),
+                });
+            }
+            self.delivered
+                .lock()
+                .unwrap()
+                .push(event.progress.position_seconds);
+            Ok(())
         }
     }
 
+    #[tokio::test]
+    async fn flush_keeps_failed_and_unattempted_events_in_order() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("pending.jsonl");
+        let scrobbler = PersistentScrobbler::new(FailNth::new(2), &path, 10);
+        let events = (1..=4)
+            .map(|n| sample_event(PlaybackState::Started, n))
+            .collect();
+        scrobbler.persist(events).unwrap();
+
+        let sent = scrobbler.flush().await.unwrap();
+
+        assert_eq!(sent, 1);
+        assert_eq!(*scrobbler.inner.delivered.lock().unwrap(), vec![1]);
+        let remaining: Vec<u64> = scrobbler
+            .load()
+            .unwrap()
+            .iter()
+            .map(|e| e.progress.position_seconds)
+            .collect();
+        assert_eq!(remaining, vec![2, 3, 4]);
+    }
+
+    #[tokio::test]
+    async fn flush_clears_file_when_all_events_sent() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("pending.jsonl");
+        let scrobbler = PersistentScrobbler::new(FailNth::new(usize::MAX), &path, 10);
+        let events = (1..=3)
+            .map(|n| sample_event(PlaybackState::Started, n))
+            .collect();
+        scrobbler.persist(events).unwrap();
+
+        assert_eq!(scrobbler.flush().await.unwrap(), 3);
+        assert!(scrobbler.load().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn progress_updates_are_not_persisted() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("pending.jsonl");
+        let scrobbler = PersistentScrobbler::new(FailNth::new(1), &path, 10);
+
+        let progress = sample_event(PlaybackState::Started, 30);
+        assert!(scrobbler.submit_progress(&progress).await.is_err());
+        assert!(scrobbler.load().unwrap().is_empty());
+
+        scrobbler.submit_progress(&progress).await.unwrap();
+        assert!(scrobbler.load().unwrap().is_empty());
+        assert_eq!(*scrobbler.inner.delivered.lock().unwrap(), vec![30]);
+    }
+
+    #[tokio::test]
+    async fn submit_retries_backlog_before_new_event() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("pending.jsonl");
+        let scrobbler = PersistentScrobbler::new(FailNth::new(1), &path, 10);
+
+        scrobbler
+            .submit(&sample_event(PlaybackState::Started, 1))
+            .await
+            .unwrap();
+        assert_eq!(scrobbler.load().unwrap().len(), 1);
+
+        scrobbler
+            .submit(&sample_event(PlaybackState::Ended, 2))
+            .await
+            .unwrap();
+        assert!(scrobbler.load().unwrap().is_empty());
+        assert_eq!(*scrobbler.inner.delivered.lock().unwrap(), vec![1, 2]);
+    }
+
+    #[test]
+    fn events_without_a_recent_timestamp_are_replayed() {
+        let mut event = sample_event(PlaybackState::Started, 0);
+        assert!(!event.is_replayed());
+        event.occurred_at = Some(ScrobbleEvent::now() - LIVE_EVENT_MAX_AGE_SECONDS - 1);
+        assert!(event.is_replayed());
+
+        // Saved before events carried a timestamp
+        let mut json = serde_json::to_value(&event).unwrap();
+        json.as_object_mut().unwrap().remove("occurred_at");
+        let old: ScrobbleEvent = serde_json::from_value(json).unwrap();
+        assert_eq!(old.occurred_at, None);
+        assert!(old.is_replayed());
+    }
+
+    #[test]
+    fn scrobble_threshold_is_half_or_four_minutes() {
+        assert!(!meets_scrobble_threshold(89, Some(180)));
+        assert!(meets_scrobble_threshold(90, Some(180)));
+        assert!(!meets_scrobble_threshold(239, Some(1200)));
+        assert!(meets_scrobble_threshold(240, Some(1200)));
+        assert!(!meets_scrobble_threshold(200, None));
+        assert!(meets_scrobble_threshold(240, None));
+    }
+
     #[tokio::test]
     async fn file_scrobbler_persists_events_and_trims() {
         let dir = tempdir().unwrap();
@@ -5,12 +5,24 @@
 //!
 //! Secrets are stored with a service name of "tunez" and user-specific keys that
 //! include provider, profile, and secret type information.
+//!
+//! When the keyring is unavailable (for example a headless Linux box without
+//! Secret Service), or Tunez is built without the `keyring` feature, secrets
+//! fall back to an encrypted file in the data directory.
+
+mod file_store;
 
+use crate::paths::AppDirs;
+use file_store::FileStore;
+use std::path::PathBuf;
 use thiserror::Error;
 
 /// Service name used for all Tunez credentials in the OS keyring.
 const SERVICE_NAME: &str = "tunez";
 
+/// Name of the encrypted fallback file in the data directory.
+const CREDENTIALS_FILE: &str = "credentials.enc";
+
 /// Errors that can occur when accessing the credential store.
 #[derive(Debug, Error)]
 pub enum SecretsError {
@@ -20,6 +32,9 @@ pub enum SecretsError {
     #[error("keyring access denied: {0}")]
     AccessDenied(String),
 
+    #[error("keyring is locked: {0}")]
+    Locked(String),
+
     #[error("keyring unavailable: {0}")]
     Unavailable(String),
 
@@ -27,13 +42,14 @@ pub enum SecretsError {
     Other(String),
 }
 
+#[cfg(feature = "keyring")]
 impl From<keyring::Error> for SecretsError {
     fn from(err: keyring::Error) -> Self {
         match err {
             keyring::Error::NoEntry => SecretsError::NotFound {
                 key: "unknown".into(),
             },
-            keyring::Error::NoStorageAccess(e) => SecretsError::AccessDenied(e.to_string()),
+            keyring::Error::NoStorageAccess(e) => SecretsError::Locked(e.to_string()),
             keyring::Error::PlatformFailure(e) => SecretsError::Unavailable(e.to_string()),
             other => SecretsError::Other(other.to_string()),
         }
@@ -42,6 +58,32 @@ impl From<keyring::Error> for SecretsError {
 
 pub type SecretsResult<T> = Result<T, SecretsError>;
 
+/// Without the `keyring` feature the keyring operations still type-check
+/// against this stand-in, but are never called.
+#[cfg(not(feature = "keyring"))]
+mod keyring {
+    pub type Result<T> = std::result::Result<T, Error>;
+
+    #[derive(Debug)]
+    pub struct Error;
+
+    pub struct Entry;
+
+    impl Entry {
+        pub fn set_password(&self, _password: &str) -> Result<()> {
+            Err(Error)
+        }
+
+        pub fn get_password(&self) -> Result<String> {
+            Err(Error)
+        }
+
+        pub fn delete_credential(&self) -> Result<()> {
+            Err(Error)
+        }
+    }
+}
+
 /// Key types for different kinds of secrets.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SecretKind {
@@ -51,6 +93,10 @@ pub enum SecretKind {
     RefreshToken,
     /// API key
     ApiKey,
+    /// Shared secret paired with an API key (used for request signing)
+    ApiSecret,
+    /// Long-lived session key issued after user authorization
+    SessionKey,
 }
 
 impl SecretKind {
@@ -59,17 +105,22 @@ impl SecretKind {
             SecretKind::AccessToken => "access_token",
             SecretKind::RefreshToken => "refresh_token",
             SecretKind::ApiKey => "api_key",
+            SecretKind::ApiSecret => "api_secret",
+            SecretKind::SessionKey => "session_key",
         }
     }
 }
 
-/// Credential store backed by the OS keyring.
+/// Credential store backed by the OS keyring, with an optional encrypted
+/// file used while the keyring is unavailable.
 ///
 /// Provides secure storage for provider credentials without exposing
 /// them in config files or logs.
 #[derive(Debug, Clone)]
 pub struct CredentialStore {
     service: String,
+    use_keyring: bool,
+    fallback: Option<FileStore>,
 }
 
 impl Default for CredentialStore {
@@ -79,10 +130,75 @@ impl Default for CredentialStore {
 }
 
 impl CredentialStore {
-    /// Create a new credential store using the default service name.
+    /// Create a new credential store using the default service name, falling
+    /// back to `credentials.enc` in the data directory.
     pub fn new() -> Self {
         Self {
             service: SERVICE_NAME.into(),
+            use_keyring: true,
+            fallback: AppDirs::discover()
+                .ok()
+                .map(|dirs| FileStore::new(dirs.data_dir().join(CREDENTIALS_FILE))),
+        }
+    }
+
+    /// Create a store under a different keyring service name, e.g. to keep
+    /// tests away from real credentials. It has no file fallback.
+    pub fn with_service(service: impl Into<String>) -> Self {
+        Self {
+            service: service.into(),
+            use_keyring: true,
+            fallback: None,
+        }
+    }
+
+    /// A store that only uses the encrypted file at `path`, never the keyring.
+    pub fn file(path: impl Into<PathBuf>) -> Self {
+        Self {
+            service: SERVICE_NAME.into(),
+            use_keyring: false,
+            fallback: Some(FileStore::new(path.into())),
+        }
+    }
+
+    /// Use the encrypted file at `path` whenever the keyring is unavailable.
+    pub fn with_file_fallback(mut self, path: impl Into<PathBuf>) -> Self {
+        self.fallback = Some(FileStore::new(path.into()));
+        self
+    }
+
+    /// Run `op` against the keyring entry for `key`, or `fallback_op` against
+    /// the file store when the keyring is unavailable. Lock and permission
+    /// failures are returned rather than silently writing elsewhere.
+    fn with_backend<T>(
+        &self,
+        key: &str,
+        op: impl FnOnce(&keyring::Entry) -> keyring::Result<T>,
+        fallback_op: impl FnOnce(&FileStore) -> SecretsResult<T>,
+    ) -> SecretsResult<T> {
+        #[cfg(feature = "keyring")]
+        if self.use_keyring {
+            let result = keyring::Entry::new(&self.service, key)
+                .and_then(|entry| op(&entry))
+                .map_err(|err| match err {
+                    keyring::Error::NoEntry => SecretsError::NotFound { key: key.into() },
+                    other => other.into(),
+                });
+            return match (result, &self.fallback) {
+                (Err(SecretsError::Unavailable(reason)), Some(file)) => {
+                    tracing::debug!(%reason, "keyring unavailable, using credential file");
+                    fallback_op(file)
+                }
+                (result, _) => result,
+            };
+        }
+        #[cfg(not(feature = "keyring"))]
+        let _ = (key, op, &self.service, self.use_keyring);
+        match &self.fallback {
+            Some(file) => fallback_op(file),
+            None => Err(SecretsError::Unavailable(
+                "no keyring support and no credential file configured".into(),
+            )),
         }
     }
 
@@ -109,9 +225,12 @@ impl CredentialStore {
         secret: &str,
     ) -> SecretsResult<()> {
         let key = Self::build_key(provider, profile, kind);
-        let entry = keyring::Entry::new(&self.service, &key)?;
-        entry.set_password(secret)?;
-        tracing::debug!(provider = provider, kind = ?kind, "stored credential in keyring");
+        self.with_backend(
+            &key,
+            |entry| entry.set_password(secret),
+            |file| file.set(&key, secret),
+        )?;
+        tracing::debug!(provider = provider, kind = ?kind, "stored credential");
         Ok(())
     }
 
@@ -125,12 +244,14 @@ impl CredentialStore {
         kind: SecretKind,
     ) -> SecretsResult<String> {
         let key = Self::build_key(provider, profile, kind);
-        let entry = keyring::Entry::new(&self.service, &key)?;
-        match entry.get_password() {
-            Ok(secret) => Ok(secret),
-            Err(keyring::Error::NoEntry) => Err(SecretsError::NotFound { key }),
-            Err(e) => Err(e.into()),
-        }
+        self.with_backend(
+            &key,
+            |entry| entry.get_password(),
+            |file| {
+                file.get(&key)?
+                    .ok_or_else(|| SecretsError::NotFound { key: key.clone() })
+            },
+        )
     }
 
     /// Delete a secret from the keyring.
@@ -143,14 +264,17 @@ impl CredentialStore {
         kind: SecretKind,
     ) -> SecretsResult<()> {
         let key = Self::build_key(provider, profile, kind);
-        let entry = keyring::Entry::new(&self.service, &key)?;
-        match entry.delete_credential() {
+        match self.with_backend(
+            &key,
+            |entry| entry.delete_credential(),
+            |file| file.delete(&key),
+        ) {
             Ok(()) => {
-                tracing::debug!(provider = provider, kind = ?kind, "deleted credential from keyring");
+                tracing::debug!(provider = provider, kind = ?kind, "deleted credential");
                 Ok(())
             }
-            Err(keyring::Error::NoEntry) => Ok(()), // Already gone, not an error
-            Err(e) => Err(e.into()),
+            Err(SecretsError::NotFound { .. }) => Ok(()), // Already gone, not an error
+            Err(e) => Err(e),
         }
     }
 
@@ -223,6 +347,8 @@ impl CredentialStore {
         let _ = self.delete(provider, profile, SecretKind::AccessToken);
         let _ = self.delete(provider, profile, SecretKind::RefreshToken);
         let _ = self.delete(provider, profile, SecretKind::ApiKey);
+        let _ = self.delete(provider, profile, SecretKind::ApiSecret);
+        let _ = self.delete(provider, profile, SecretKind::SessionKey);
         Ok(())
     }
 
@@ -233,7 +359,11 @@ impl CredentialStore {
     /// and to provide appropriate warnings to users on headless systems.
     pub fn is_available(&self) -> bool {
         // Try to create a test entry - if this fails, keyring is not available
-        keyring::Entry::new(&self.service, "tunez_availability_test").is_ok()
+        #[cfg(feature = "keyring")]
+        if self.use_keyring {
+            return keyring::Entry::new(&self.service, "tunez_availability_test").is_ok();
+        }
+        false
     }
 
     /// Check keyring availability and log a warning if unavailable.
@@ -258,7 +388,7 @@ impl SecretsError {
     pub fn is_keyring_unavailable(&self) -> bool {
         matches!(
             self,
-            SecretsError::Unavailable(_) | SecretsError::AccessDenied(_)
+            SecretsError::Unavailable(_) | SecretsError::AccessDenied(_) | SecretsError::Locked(_)
         )
     }
 
@@ -278,6 +408,13 @@ impl SecretsError {
                     msg
                 )
             }
+            SecretsError::Locked(msg) => {
+                format!(
+                    "Credential storage is locked: {}. \
+                     Unlock your keychain or keyring and try again.",
+                    msg
+                )
+            }
             SecretsError::Unavailable(msg) => {
                 format!(
                     "Secure credential storage is not available: {}. \
@@ -313,12 +450,15 @@ mod tests {
         assert_eq!(SecretKind::AccessToken.as_str(), "access_token");
         assert_eq!(SecretKind::RefreshToken.as_str(), "refresh_token");
         assert_eq!(SecretKind::ApiKey.as_str(), "api_key");
+        assert_eq!(SecretKind::ApiSecret.as_str(), "api_secret");
+        assert_eq!(SecretKind::SessionKey.as_str(), "session_key");
     }
 
     #[test]
     fn error_is_keyring_unavailable() {
         assert!(SecretsError::Unavailable("test".into()).is_keyring_unavailable());
         assert!(SecretsError::AccessDenied("test".into()).is_keyring_unavailable());
+        assert!(SecretsError::Locked("test".into()).is_keyring_unavailable());
         assert!(!SecretsError::NotFound { key: "test".into() }.is_keyring_unavailable());
         assert!(!SecretsError::Other("test".into()).is_keyring_unavailable());
     }
@@ -333,4 +473,71 @@ mod tests {
         let unavailable = SecretsError::Unavailable("no dbus".into());
         assert!(unavailable.user_message().contains("headless"));
     }
+
+    #[test]
+    fn file_store_round_trips_token() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("credentials.enc");
+        let store = CredentialStore::file(&path);
+
+        assert!(matches!(
+            store.get_access_token("melodee", Some("home")),
+            Err(SecretsError::NotFound { .. })
+        ));
+        store
+            .store_access_token("melodee", Some("home"), "s3cret-token")
+            .unwrap();
+        store.store_api_key("lastfm", None, "api-key").unwrap();
+
+        // A fresh store reads what the first one wrote; the file is not plain text.
+        let reopened = CredentialStore::file(&path);
+        assert_eq!(
+            reopened.get_access_token("melodee", Some("home")).unwrap(),
+            "s3cret-token"
+        );
+        let raw = std::fs::read(&path).unwrap();
+        assert!(!String::from_utf8_lossy(&raw).contains("s3cret-token"));
+
+        reopened.clear_provider("melodee", Some("home")).unwrap();
+        assert!(!store
+            .exists("melodee", Some("home"), SecretKind::AccessToken)
+            .unwrap());
+        assert_eq!(store.get_api_key("lastfm", None).unwrap(), "api-key");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn file_store_is_private_and_rejects_tampering() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("credentials.enc");
+        let store = CredentialStore::file(&path);
+        store.store_access_token("melodee", None, "token").unwrap();
+        // Left behind by a crash mid-write, readable by anyone.
+        let leftover = dir.path().join("credentials.enc.tmp");
+        std::fs::write(&leftover, b"stale").unwrap();
+        std::fs::set_permissions(&leftover, std::fs::Permissions::from_mode(0o644)).unwrap();
+        store.store_api_key("lastfm", None, "api-key").unwrap();
+
+        for file in [&path, &path.with_extension("key")] {
+            let mode = std::fs::metadata(file).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        // Writes go through a temporary file that never outlives them.
+        let mut names: Vec<_> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        names.sort();
+        assert_eq!(names, ["credentials.enc", "credentials.key"]);
+
+        let mut raw = std::fs::read(&path).unwrap();
+        *raw.last_mut().unwrap() ^= 0xff;
+        std::fs::write(&path, raw).unwrap();
+        assert!(matches!(
+            store.get_access_token("melodee", None),
+            Err(SecretsError::Other(_))
+        ));
+    }
 }
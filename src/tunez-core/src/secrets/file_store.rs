@@ -0,0 +1,151 @@
+//! Encrypted-file credential store used when the OS keyring is unavailable.
+//!
+//! Secrets are kept as a JSON map encrypted with AES-256-GCM. The key lives
+//! in a sibling `.key` file; both files are created readable by the owner
+//! only. This keeps credentials out of plain-text config and logs, but is
+//! weaker than a real keyring since anyone who can read both files can
+//! decrypt them.
+
+use super::{SecretsError, SecretsResult};
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use rand::RngCore;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+const KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+
+#[derive(Debug, Clone)]
+pub(super) struct FileStore {
+    path: PathBuf,
+}
+
+impl FileStore {
+    pub(super) fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    pub(super) fn get(&self, key: &str) -> SecretsResult<Option<String>> {
+        Ok(self.load()?.remove(key))
+    }
+
+    pub(super) fn set(&self, key: &str, secret: &str) -> SecretsResult<()> {
+        let mut secrets = self.load()?;
+        secrets.insert(key.to_string(), secret.to_string());
+        self.save(&secrets)
+    }
+
+    pub(super) fn delete(&self, key: &str) -> SecretsResult<()> {
+        let mut secrets = self.load()?;
+        if secrets.remove(key).is_some() {
+            self.save(&secrets)?;
+        }
+        Ok(())
+    }
+
+    fn key_path(&self) -> PathBuf {
+        self.path.with_extension("key")
+    }
+
+    fn cipher(&self, create: bool) -> SecretsResult<Option<Aes256Gcm>> {
+        let key_path = self.key_path();
+        let key = match fs::read(&key_path) {
+            Ok(key) => key,
+            Err(e) if e.kind() == io::ErrorKind::NotFound && create => {
+                let mut key = vec![0u8; KEY_LEN];
+                rand::thread_rng().fill_bytes(&mut key);
+                write_private(&key_path, &key)?;
+                key
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(io_error(&key_path, e)),
+        };
+        if key.len() != KEY_LEN {
+            return Err(SecretsError::Other(format!(
+                "credential key file {} is malformed",
+                key_path.display()
+            )));
+        }
+        Ok(Some(Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key))))
+    }
+
+    fn load(&self) -> SecretsResult<BTreeMap<String, String>> {
+        let data = match fs::read(&self.path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+            Err(e) => return Err(io_error(&self.path, e)),
+        };
+        let unreadable = || {
+            SecretsError::Other(format!(
+                "credential file {} could not be decrypted",
+                self.path.display()
+            ))
+        };
+        let cipher = self.cipher(false)?.ok_or_else(unreadable)?;
+        if data.len() < NONCE_LEN {
+            return Err(unreadable());
+        }
+        let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+        let plaintext = cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| unreadable())?;
+        serde_json::from_slice(&plaintext).map_err(|_| unreadable())
+    }
+
+    fn save(&self, secrets: &BTreeMap<String, String>) -> SecretsResult<()> {
+        let cipher = self.cipher(true)?.expect("key is created on demand");
+        let plaintext = serde_json::to_vec(secrets)
+            .map_err(|e| SecretsError::Other(format!("failed to encode credentials: {e}")))?;
+        let mut nonce = [0u8; NONCE_LEN];
+        rand::thread_rng().fill_bytes(&mut nonce);
+        let ciphertext = cipher
+            .encrypt(Nonce::from_slice(&nonce), plaintext.as_slice())
+            .map_err(|_| SecretsError::Other("failed to encrypt credentials".into()))?;
+        write_private(&self.path, &[&nonce[..], &ciphertext].concat())
+    }
+}
+
+/// Replace `path` with `data`, readable and writable by the owner only.
+/// The data is synced to a temporary file beside it and renamed over it,
+/// so a crash mid-write never leaves the secrets half written.
+fn write_private(path: &Path, data: &[u8]) -> SecretsResult<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| io_error(parent, e))?;
+    }
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+    // A leftover from an earlier crash keeps whatever mode it was made with.
+    let _ = fs::remove_file(&tmp);
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let result = options
+        .open(&tmp)
+        .and_then(|mut file| {
+            file.write_all(data)?;
+            file.sync_all()
+        })
+        .and_then(|()| fs::rename(&tmp, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    result.map_err(|e| io_error(path, e))
+}
+
+fn io_error(path: &Path, error: io::Error) -> SecretsError {
+    match error.kind() {
+        io::ErrorKind::PermissionDenied => {
+            SecretsError::AccessDenied(format!("{}: {}", path.display(), error))
+        }
+        _ => SecretsError::Other(format!("{}: {}", path.display(), error)),
+    }
+}
@@ -0,0 +1,63 @@
+//! Converting downloaded streams to FLAC with ffmpeg.
+//!
+//! Some servers hand out formats the decoder can't read. With `[audio]
+//! transcode` on, the track cache runs each download through ffmpeg, when one
+//! is installed, and keeps the FLAC it writes instead.
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// An ffmpeg executable known to run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transcoder {
+    program: PathBuf,
+}
+
+impl Transcoder {
+    /// `ffmpeg` from `PATH`, if it's installed.
+    pub fn detect() -> Option<Self> {
+        Self::find("ffmpeg")
+    }
+
+    /// `program`, if it runs as ffmpeg does (`program -version` succeeds).
+    pub fn find(program: impl Into<PathBuf>) -> Option<Self> {
+        let program = program.into();
+        let runs = Command::new(&program)
+            .arg("-version")
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success());
+        runs.then_some(Self { program })
+    }
+
+    pub fn program(&self) -> &Path {
+        &self.program
+    }
+
+    /// Convert the audio in `input` to FLAC at `output`, whatever `output`'s
+    /// extension.
+    pub fn to_flac(&self, input: &Path, output: &Path) -> io::Result<()> {
+        let result = Command::new(&self.program)
+            .args(["-nostdin", "-hide_banner", "-loglevel", "error", "-y", "-i"])
+            .arg(input)
+            .args(["-vn", "-c:a", "flac", "-f", "flac"])
+            .arg(output)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .output()?;
+        if result.status.success() {
+            return Ok(());
+        }
+        let stderr = String::from_utf8_lossy(&result.stderr);
+        Err(io::Error::other(format!(
+            "{} exited with {}: {}",
+            self.program.display(),
+            result.status,
+            stderr.trim()
+        )))
+    }
+}
@@ -0,0 +1,181 @@
+use std::sync::{Arc, Mutex};
+use tunez_core::models::{Track, TrackId};
+use tunez_core::scrobbler::PersistentScrobbler;
+use tunez_core::{
+    BatchError, PlaybackProgress, PlaybackState, ScrobbleEvent, Scrobbler, ScrobblerError,
+    ScrobblerResult,
+};
+
+fn sample_event(title: &str) -> ScrobbleEvent {
+    ScrobbleEvent {
+        track: Track {
+            id: TrackId::new(title),
+            provider_id: "filesystem".into(),
+            title: title.into(),
+            artist: "Artist".into(),
+            album: Some("Album".into()),
+            duration_seconds: Some(180),
+            track_number: Some(1),
+            disc_number: None,
+            year: None,
+            genre: None,
+            musicbrainz_id: None,
+            replay_gain: Default::default(),
+        },
+        progress: PlaybackProgress {
+            position_seconds: 180,
+            duration_seconds: Some(180),
+        },
+        state: PlaybackState::Ended,
+        player_name: "Tunez".into(),
+        device_id: None,
+        occurred_at: Some(ScrobbleEvent::now()),
+    }
+}
+
+fn seed(path: &std::path::Path, titles: &[&str]) {
+    let lines: String = titles
+        .iter()
+        .map(|title| serde_json::to_string(&sample_event(title)).unwrap() + "\n")
+        .collect();
+    std::fs::write(path, lines).unwrap();
+}
+
+/// Records delivered titles; fails every submission while `offline` is set.
+#[derive(Default)]
+struct MockScrobbler {
+    offline: bool,
+    delivered: Mutex<Vec<String>>,
+}
+
+#[async_trait::async_trait]
+impl Scrobbler for MockScrobbler {
+    fn id(&self) -> &str {
+        "mock"
+    }
+
+    async fn submit(&self, event: &ScrobbleEvent) -> ScrobblerResult<()> {
+        if self.offline {
+            return Err(ScrobblerError::Network {
+                message: "offline".into(),
+            });
+        }
+        self.delivered
+            .lock()
+            .unwrap()
+            .push(event.track.title.clone());
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn flush_drains_seeded_queue() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("scrobbles.jsonl");
+    seed(&path, &["one", "two", "three"]);
+
+    let mock = Arc::new(MockScrobbler::default());
+    let scrobbler = PersistentScrobbler::new(mock.clone(), &path, 1000);
+    assert_eq!(scrobbler.pending().unwrap(), 3);
+
+    assert_eq!(scrobbler.flush().await.unwrap(), 3);
+    assert_eq!(scrobbler.pending().unwrap(), 0);
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "");
+    assert_eq!(*mock.delivered.lock().unwrap(), ["one", "two", "three"]);
+}
+
+#[tokio::test]
+async fn flush_keeps_queue_when_offline() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("scrobbles.jsonl");
+    seed(&path, &["one", "two"]);
+
+    let scrobbler = PersistentScrobbler::new(
+        MockScrobbler {
+            offline: true,
+            ..Default::default()
+        },
+        &path,
+        1000,
+    );
+
+    assert_eq!(scrobbler.flush().await.unwrap(), 0);
+    assert_eq!(scrobbler.pending().unwrap(), 2);
+}
+
+/// Sends a whole batch in one request, the way list-accepting APIs do.
+#[derive(Default)]
+struct BatchScrobbler {
+    reject: bool,
+    requests: Mutex<Vec<Vec<String>>>,
+}
+
+#[async_trait::async_trait]
+impl Scrobbler for BatchScrobbler {
+    fn id(&self) -> &str {
+        "batch"
+    }
+
+    async fn submit(&self, event: &ScrobbleEvent) -> ScrobblerResult<()> {
+        self.submit_batch(std::slice::from_ref(event))
+            .await
+            .map_err(|e| e.error)
+    }
+
+    async fn submit_batch(&self, events: &[ScrobbleEvent]) -> Result<(), BatchError> {
+        self.requests
+            .lock()
+            .unwrap()
+            .push(events.iter().map(|e| e.track.title.clone()).collect());
+        if self.reject {
+            return Err(BatchError {
+                sent: 0,
+                error: ScrobblerError::Other {
+                    message: "batch rejected".into(),
+                },
+            });
+        }
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn flush_sends_backlog_in_one_batch() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("scrobbles.jsonl");
+    seed(&path, &["one", "two", "three"]);
+
+    let mock = Arc::new(BatchScrobbler::default());
+    let scrobbler = PersistentScrobbler::new(mock.clone(), &path, 1000);
+
+    assert_eq!(scrobbler.flush().await.unwrap(), 3);
+    assert_eq!(*mock.requests.lock().unwrap(), [["one", "two", "three"]]);
+    assert_eq!(scrobbler.pending().unwrap(), 0);
+}
+
+#[tokio::test]
+async fn rejected_batch_stays_persisted() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("scrobbles.jsonl");
+    seed(&path, &["one", "two", "three"]);
+
+    let mock = Arc::new(BatchScrobbler {
+        reject: true,
+        ..Default::default()
+    });
+    let scrobbler = PersistentScrobbler::new(mock.clone(), &path, 1000);
+
+    assert_eq!(scrobbler.flush().await.unwrap(), 0);
+    assert_eq!(mock.requests.lock().unwrap().len(), 1);
+    let titles: Vec<String> = std::fs::read_to_string(&path)
+        .unwrap()
+        .lines()
+        .map(|line| {
+            serde_json::from_str::<ScrobbleEvent>(line)
+                .unwrap()
+                .track
+                .title
+        })
+        .collect();
+    assert_eq!(titles, ["one", "two", "three"]);
+}
@@ -1,9 +1,15 @@
+mod now_playing;
 mod player;
 mod queue;
 mod queue_persistence;
+mod recorder;
 mod scrobbler_integration;
 
+pub use now_playing::{NowPlayingFile, DEFAULT_NOW_PLAYING_FORMAT};
 pub use player::{Player, PlayerState};
-pub use queue::{Queue, QueueId, QueueItem};
-pub use queue_persistence::{QueuePersistence, QueuePersistenceError, QueuePersistenceResult};
+pub use queue::{Queue, QueueId, QueueItem, QueuePosition, RepeatMode};
+pub use queue_persistence::{
+    QueuePersistence, QueuePersistenceError, QueuePersistenceResult, QueueSaver,
+    DEFAULT_SAVE_INTERVAL,
+};
 pub use scrobbler_integration::ScrobblerManager;
@@ -0,0 +1,190 @@
+//! The playing track written to a file, for stream overlays and the like.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use tunez_core::Track;
+
+/// Template used when the config doesn't set one.
+pub const DEFAULT_NOW_PLAYING_FORMAT: &str = "{artist} - {title} - {album}";
+
+/// A file holding one line about the playing track, rendered from a template
+/// with `{artist}`, `{title}` and `{album}` placeholders. Empty while stopped.
+///
+/// A placeholder with nothing to show takes the separator (spaces and
+/// punctuation such as ` - `) joining it to its neighbour with it, so a
+/// track without an album doesn't end in a dangling dash.
+#[derive(Debug, Clone)]
+pub struct NowPlayingFile {
+    path: PathBuf,
+    format: String,
+}
+
+impl NowPlayingFile {
+    pub fn new(path: impl Into<PathBuf>, format: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            format: format.into(),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The template filled in for `track`. Placeholders are replaced in one
+    /// pass, so braces in the track's own text are left as they are.
+    pub fn render(&self, track: &Track) -> String {
+        let fields = [
+            ("artist", track.artist.as_str()),
+            ("title", track.title.as_str()),
+            ("album", track.album.as_deref().unwrap_or_default()),
+        ];
+        let mut pieces = parse(&self.format, &fields);
+
+        let mut i = 0;
+        while i < pieces.len() {
+            if pieces[i] != Piece::Field("") {
+                i += 1;
+                continue;
+            }
+            pieces.remove(i);
+            let joins_previous = i >= 2
+                && matches!(pieces[i - 2], Piece::Field(_))
+                && matches!(pieces[i - 1], Piece::Text(text) if is_separator(text));
+            let joins_next = matches!(pieces.get(i), Some(Piece::Text(text)) if is_separator(text))
+                && matches!(pieces.get(i + 1), Some(Piece::Field(_)));
+            if joins_previous {
+                pieces.remove(i - 1);
+                i -= 1;
+            } else if joins_next {
+                pieces.remove(i);
+            }
+        }
+
+        pieces
+            .into_iter()
+            .map(|piece| match piece {
+                Piece::Text(text) | Piece::Field(text) => text,
+            })
+            .collect()
+    }
+
+    /// Replace the file's contents with the line for `track`.
+    pub fn write(&self, track: &Track) -> io::Result<()> {
+        self.replace(&self.render(track))
+    }
+
+    /// Empty the file.
+    pub fn clear(&self) -> io::Result<()> {
+        self.replace("")
+    }
+
+    /// Write through a temporary so readers never see the file half written.
+    fn replace(&self, contents: &str) -> io::Result<()> {
+        if let Some(parent) = self.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(".tmp");
+        fs::write(&tmp, contents)?;
+        fs::rename(&tmp, &self.path)
+    }
+}
+
+/// Part of a rendered template: literal text or a placeholder's value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Piece<'a> {
+    Text(&'a str),
+    Field(&'a str),
+}
+
+/// Split `format` into literal text and the values of the `fields` it names.
+/// Unknown placeholders stay as text.
+fn parse<'a>(format: &'a str, fields: &[(&str, &'a str)]) -> Vec<Piece<'a>> {
+    let mut pieces = Vec::new();
+    let mut text_start = 0;
+    let mut search = 0;
+    while let Some(offset) = format[search..].find('{') {
+        let start = search + offset;
+        let rest = &format[start + 1..];
+        let field = fields.iter().find(|(name, _)| {
+            rest.strip_prefix(name)
+                .is_some_and(|after| after.starts_with('}'))
+        });
+        match field {
+            Some((name, value)) => {
+                if text_start < start {
+                    pieces.push(Piece::Text(&format[text_start..start]));
+                }
+                pieces.push(Piece::Field(value));
+                search = start + name.len() + 2;
+                text_start = search;
+            }
+            None => search = start + 1,
+        }
+    }
+    if text_start < format.len() {
+        pieces.push(Piece::Text(&format[text_start..]));
+    }
+    pieces
+}
+
+fn is_separator(text: &str) -> bool {
+    text.chars()
+        .all(|c| c.is_whitespace() || "-–—|·•,/:".contains(c))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tunez_core::TrackId;
+
+    fn track(title: &str, album: Option<&str>) -> Track {
+        Track {
+            id: TrackId::new("t"),
+            provider_id: "test".into(),
+            title: title.into(),
+            artist: "Band".into(),
+            album: album.map(Into::into),
+            duration_seconds: None,
+            track_number: None,
+            disc_number: None,
+            year: None,
+            genre: None,
+            musicbrainz_id: None,
+            replay_gain: Default::default(),
+        }
+    }
+
+    #[test]
+    fn placeholders_are_filled_and_unknown_text_kept() {
+        let file = NowPlayingFile::new("np.txt", "♪ {title} by {artist} [{album}] {year}");
+        assert_eq!(
+            file.render(&track("Song", None)),
+            "♪ Song by Band [] {year}"
+        );
+    }
+
+    #[test]
+    fn placeholders_in_track_text_are_not_expanded() {
+        let file = NowPlayingFile::new("np.txt", DEFAULT_NOW_PLAYING_FORMAT);
+        assert_eq!(
+            file.render(&track("{artist} {album}", Some("{title}"))),
+            "Band - {artist} {album} - {title}"
+        );
+    }
+
+    #[test]
+    fn missing_album_takes_its_separator_with_it() {
+        let song = track("Song", None);
+        let file = NowPlayingFile::new("np.txt", DEFAULT_NOW_PLAYING_FORMAT);
+        assert_eq!(file.render(&song), "Band - Song");
+        let file = NowPlayingFile::new("np.txt", "{album} | {artist} - {title}");
+        assert_eq!(file.render(&song), "Band - Song");
+        assert_eq!(
+            file.render(&track("Song", Some("Album"))),
+            "Album | Band - Song"
+        );
+    }
+}
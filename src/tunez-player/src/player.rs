@@ -1,9 +1,66 @@
-use crate::{Queue, QueueId, QueueItem};
+use crate::recorder::PlayRecorder;
+use crate::{NowPlayingFile, Queue, QueueId, QueueItem, RepeatMode};
+use std::collections::VecDeque;
 use std::sync::Arc;
-use tunez_audio::{AudioEngine, AudioHandle, AudioSource};
+use std::time::{Duration, Instant};
+use tunez_audio::{
+    AudioEngine, AudioError, AudioHandle, AudioSource, AudioState, PreparedAudio, SampleCallback,
+};
+use tunez_core::{
+    meets_scrobble_threshold, Chapter, PlayCounts, PlayHistory, ReplayGainConfig, Track, TrackId,
+};
 
-/// Type alias for player sample callback
-pub type PlayerSampleCallback = Box<dyn Fn(&[f32]) + Send + Sync>;
+/// Maximum number of previously played queue entries remembered for `skip_previous`.
+const HISTORY_LIMIT: usize = 100;
+/// How long before the end of a track the next one is prepared for gapless
+/// playback; long enough to fetch and decode it.
+const GAPLESS_LEAD: Duration = Duration::from_secs(20);
+/// How long a network stream may buffer before playback gives up on it.
+/// Streams are fetched whole before they start, so this is generous.
+const DEFAULT_BUFFERING_TIMEOUT: Duration = Duration::from_secs(60);
+/// How far into a chapter "previous chapter" restarts it rather than going
+/// back to the one before, like previous track in most players.
+const CHAPTER_RESTART_AFTER: Duration = Duration::from_secs(3);
+
+/// `fraction` of `duration`, clamped to `0.0..=1.0` and rounded to the
+/// millisecond; `None` for NaN.
+fn fraction_of(duration: Duration, fraction: f32) -> Option<Duration> {
+    if fraction.is_nan() {
+        return None;
+    }
+    // Whole milliseconds, so 0.9 of three minutes is 162s and not a hair less.
+    let millis = duration.as_millis() as f64 * f64::from(fraction.clamp(0.0, 1.0));
+    Some(Duration::from_millis(millis.round() as u64))
+}
+
+/// Start of the first chapter after `position`.
+fn chapter_after(chapters: &[Chapter], position: Duration) -> Option<Duration> {
+    chapters
+        .iter()
+        .map(|chapter| chapter.start)
+        .find(|&start| start > position)
+}
+
+/// Where "previous chapter" goes from `position`: the start of the chapter
+/// playing, or of the one before it within `CHAPTER_RESTART_AFTER` of that
+/// start. Before the first chapter, the start of the track.
+fn chapter_before(chapters: &[Chapter], position: Duration) -> Option<Duration> {
+    if chapters.is_empty() {
+        return None;
+    }
+    let playing = chapters.partition_point(|chapter| chapter.start <= position);
+    let Some(current) = playing.checked_sub(1) else {
+        return Some(Duration::ZERO);
+    };
+    let start = chapters[current].start;
+    if position.saturating_sub(start) > CHAPTER_RESTART_AFTER {
+        return Some(start);
+    }
+    Some(match current.checked_sub(1) {
+        Some(previous) => chapters[previous].start,
+        None => Duration::ZERO,
+    })
+}
 
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
 pub enum PlayerState {
@@ -29,7 +86,32 @@ pub struct Player {
     queue: Queue,
     state: PlayerState,
     audio: Option<AudioHandle>,
-    sample_callback: Option<PlayerSampleCallback>,
+    /// Handed to every track's audio handle.
+    sample_callback: Option<SampleCallback>,
+    history: VecDeque<QueueId>,
+    replay_gain: ReplayGainConfig,
+    /// Where started tracks are recorded for "recently played".
+    play_history: Option<PlayRecorder>,
+    /// Where the playing track is shown to other programs.
+    now_playing: Option<PlayRecorder<Option<Track>>>,
+    /// Where plays are counted once they pass the scrobble threshold.
+    play_counts: Option<PlayRecorder>,
+    /// Whether the current play has been counted.
+    play_counted: bool,
+    /// Entry handed out by `gapless_candidate`, to follow the current one
+    /// without a gap once it is prepared.
+    gapless_next: Option<QueueId>,
+    /// `AudioHandle::handoffs` already moved past.
+    handoffs_seen: u64,
+    /// Why the last `play_with_audio` failed, until someone takes it.
+    audio_error: Option<AudioError>,
+    /// When the current stream started buffering.
+    buffering_since: Option<Instant>,
+    /// Overrides `DEFAULT_BUFFERING_TIMEOUT`.
+    buffering_timeout: Option<Duration>,
+    /// Chapter markers and the track they belong to; ignored once another
+    /// track is current.
+    chapters: Option<(TrackId, Vec<Chapter>)>,
 }
 
 impl std::fmt::Debug for Player {
@@ -38,6 +120,10 @@ impl std::fmt::Debug for Player {
             .field("queue", &self.queue)
             .field("state", &self.state)
             .field("audio", &self.audio)
+            .field("history", &self.history)
+            .field("replay_gain", &self.replay_gain)
+            .field("play_history", &self.play_history)
+            .field("now_playing", &self.now_playing)
             .finish_non_exhaustive()
     }
 }
@@ -63,6 +149,20 @@ impl Player {
         self.queue.current()
     }
 
+    /// Replace the queue with a freshly loaded one, stopping playback and
+    /// forgetting the play history of the old queue.
+    pub fn load_queue(&mut self, queue: Queue) {
+        self.stop_audio();
+        self.queue = queue;
+        self.history.clear();
+        self.state = PlayerState::Stopped;
+    }
+
+    /// Queue entries played before the current one, oldest first.
+    pub fn history(&self) -> impl Iterator<Item = QueueId> + '_ {
+        self.history.iter().copied()
+    }
+
     /// Get current playback position
     pub fn position(&self) -> std::time::Duration {
         if let Some(audio) = &self.audio {
@@ -72,28 +172,146 @@ impl Player {
         }
     }
 
-    /// Seek by relative offset from current position
-    /// TODO: Implement seek when audio engine supports it
-    pub fn seek(&mut self, position: std::time::Duration) {
-        if let Some(audio) = &self.audio {
-            let _ = audio.seek(position);
+    /// Output sample rate of the playing audio, if the backend reports one.
+    pub fn sample_rate(&self) -> Option<u32> {
+        self.audio
+            .as_ref()
+            .map(|audio| audio.sample_rate())
+            .filter(|&rate| rate > 0)
+    }
+
+    /// Seek to an absolute position, clamped to the current track's duration.
+    ///
+    /// Returns the position actually sought to, or `None` if nothing is loaded
+    /// or the backend rejected the seek.
+    pub fn seek(&mut self, position: std::time::Duration) -> Option<std::time::Duration> {
+        let audio = self.audio.as_ref()?;
+        let target = match self.queue.current().and_then(|c| c.track.duration_seconds) {
+            Some(secs) => position.min(std::time::Duration::from_secs(secs.into())),
+            None => position,
+        };
+        match audio.seek(target) {
+            Ok(()) => Some(target),
+            Err(err) => {
+                tracing::warn!(error = %err, "seek failed");
+                None
+            }
+        }
+    }
+
+    /// Seek to `fraction` of the current track, clamped to `0.0..=1.0`.
+    ///
+    /// Returns the position sought to, or `None` if the track's duration is
+    /// unknown, `fraction` is NaN, or the seek itself failed.
+    pub fn seek_fraction(&mut self, fraction: f32) -> Option<Duration> {
+        let secs = self.queue.current()?.track.duration_seconds?;
+        let target = fraction_of(Duration::from_secs(secs.into()), fraction)?;
+        self.seek(target)
+    }
+
+    /// Chapter markers of `track_id`, earliest first, used by the chapter
+    /// jumps while it is the current track.
+    pub fn set_chapters(&mut self, track_id: TrackId, mut chapters: Vec<Chapter>) {
+        chapters.sort_by_key(|chapter| chapter.start);
+        self.chapters = Some((track_id, chapters));
+    }
+
+    /// Chapters of the current track; empty when it has none or they
+    /// haven't been loaded.
+    pub fn chapters(&self) -> &[Chapter] {
+        match (&self.chapters, self.queue.current()) {
+            (Some((id, chapters)), Some(current)) if *id == current.track.id => chapters,
+            _ => &[],
         }
     }
 
+    /// Seek to the start of the next chapter. `None` without chapters or
+    /// in the last one.
+    pub fn next_chapter(&mut self) -> Option<Duration> {
+        let target = chapter_after(self.chapters(), self.position())?;
+        self.seek(target)
+    }
+
+    /// Seek back to the start of the current chapter, or of the previous
+    /// one when the current one has only just begun. `None` without
+    /// chapters.
+    pub fn previous_chapter(&mut self) -> Option<Duration> {
+        let target = chapter_before(self.chapters(), self.position())?;
+        self.seek(target)
+    }
+
+    /// How ReplayGain tags are applied to tracks started after this call.
+    pub fn set_replay_gain(&mut self, config: ReplayGainConfig) {
+        self.replay_gain = config;
+    }
+
+    /// Record every track that starts playing in `history`, written off
+    /// the playback path.
+    pub fn set_play_history(&mut self, history: PlayHistory) {
+        self.play_history = Some(PlayRecorder::spawn(move |track| {
+            if let Err(err) = history.record(track) {
+                tracing::warn!(error = %err, "failed to record play history");
+            }
+        }));
+    }
+
+    /// Count plays in `store`, written off the playback path.
+    pub fn set_play_counts(&mut self, store: PlayCounts) {
+        self.play_counts = Some(PlayRecorder::spawn(move |track| {
+            if let Err(err) = store.record(track) {
+                tracing::warn!(path = %store.path().display(), error = %err, "failed to record play count");
+            }
+        }));
+    }
+
+    /// Count a play of the current track the first time `position` reaches
+    /// the scrobble threshold after it started. Returns whether this call
+    /// counted it.
+    pub fn record_progress(&mut self, position: Duration) -> bool {
+        if self.play_counted || !matches!(self.state, PlayerState::Playing { .. }) {
+            return false;
+        }
+        let (Some(recorder), Some(current)) = (&self.play_counts, self.queue.current()) else {
+            return false;
+        };
+        let duration = current.track.duration_seconds.map(u64::from);
+        if !meets_scrobble_threshold(position.as_secs(), duration) {
+            return false;
+        }
+        recorder.record(current.track.clone());
+        self.play_counted = true;
+        true
+    }
+
+    /// Write each track that starts to `file`, and empty it on stop, off
+    /// the playback path.
+    pub fn set_now_playing_file(&mut self, file: NowPlayingFile) {
+        self.now_playing = Some(PlayRecorder::spawn(move |track: &Option<Track>| {
+            let result = match track {
+                Some(track) => file.write(track),
+                None => file.clear(),
+            };
+            if let Err(err) = result {
+                tracing::warn!(path = %file.path().display(), error = %err, "failed to update now playing file");
+            }
+        }));
+    }
+
     /// Set a callback to receive audio samples for visualization
     pub fn set_sample_callback<F>(&mut self, callback: F)
     where
         F: Fn(&[f32]) + Send + Sync + 'static,
     {
-        self.sample_callback = Some(Box::new(callback));
+        self.sample_callback = Some(Arc::new(callback));
     }
 
     pub fn play(&mut self) -> Option<&QueueItem> {
         if self.queue.current().is_none() {
             self.queue.select_first()?;
         }
-        let current = self.queue.current()?;
-        self.state = PlayerState::Playing { id: current.id };
+        let id = self.queue.current()?.id;
+        self.remember_active(id);
+        self.state = PlayerState::Playing { id };
         self.stop_audio();
         self.queue.current()
     }
@@ -109,17 +327,21 @@ impl Player {
         source: AudioSource,
     ) -> Option<&QueueItem> {
         self.play()?;
-        let current_id = self.queue.current().map(|c| c.id)?;
-        match engine.play(source) {
+        let current = self.queue.current()?;
+        let current_id = current.id;
+        let gain = self.gain_for(&current.track);
+        match engine.play_with_gain(source, gain) {
             Ok(mut handle) => {
                 // Set up sample callback if one has been registered
-                if let Some(callback) = self.sample_callback.take() {
-                    // Convert Box to Arc for the AudioHandle
-                    type CallbackType = dyn Fn(&[f32]) + Send + Sync;
-                    let arc_callback: Arc<CallbackType> = Arc::new(callback);
-                    handle.set_sample_callback(arc_callback);
+                if let Some(callback) = &self.sample_callback {
+                    handle.set_sample_callback(callback.clone());
+                }
+                if handle.state() == AudioState::Buffering {
+                    self.state = PlayerState::Buffering { id: current_id };
+                    self.buffering_since = Some(Instant::now());
                 }
                 self.audio = Some(handle);
+                self.record_play();
                 self.queue.current()
             }
             Err(err) => {
@@ -127,11 +349,170 @@ impl Player {
                     id: Some(current_id),
                     message: err.to_string(),
                 };
+                self.audio_error = Some(err);
                 None
             }
         }
     }
 
+    /// How long a stream may buffer before `poll_buffering` fails it.
+    pub fn set_buffering_timeout(&mut self, timeout: Duration) {
+        self.buffering_timeout = Some(timeout);
+    }
+
+    /// Follow a buffering stream: `Playing` once the engine has its first
+    /// samples, `Error` if it fails to load or stalls past the timeout. The
+    /// cause of an error can be had from `take_audio_error`.
+    pub fn poll_buffering(&mut self) {
+        let PlayerState::Buffering { id } = self.state else {
+            return;
+        };
+        let Some(audio) = &self.audio else {
+            return;
+        };
+        let error = match audio.state() {
+            AudioState::Buffering => {
+                let timeout = self.buffering_timeout.unwrap_or(DEFAULT_BUFFERING_TIMEOUT);
+                if self
+                    .buffering_since
+                    .is_none_or(|since| since.elapsed() < timeout)
+                {
+                    return;
+                }
+                AudioError::Io("the stream stalled while buffering".into())
+            }
+            AudioState::Error => AudioError::Other("the stream failed to load".into()),
+            _ => {
+                self.buffering_since = None;
+                self.state = PlayerState::Playing { id };
+                return;
+            }
+        };
+        self.set_error(error.to_string());
+        self.audio_error = Some(error);
+    }
+
+    /// The linear ReplayGain factor `track` plays at.
+    pub fn gain_for(&self, track: &Track) -> f32 {
+        track
+            .replay_gain
+            .factor(self.replay_gain.mode, self.replay_gain.prevent_clipping)
+    }
+
+    /// The next entry to prepare for gapless playback, handed out once: when
+    /// the current track's decoded audio has `GAPLESS_LEAD` or less left to
+    /// play and the next entry continues the same album. Shuffle and
+    /// repeat-one play tracks one after another as usual.
+    ///
+    /// A handed-out entry that no longer follows the current one (the queue
+    /// was edited or shuffle turned on) is taken back off the audio first.
+    pub fn gapless_candidate(&mut self) -> Option<QueueItem> {
+        if let Some(id) = self.gapless_next {
+            if self.gapless_follower().map(|item| item.id) == Some(id) {
+                return None;
+            }
+            if let Some(audio) = &self.audio {
+                audio.clear_next();
+            }
+            self.gapless_next = None;
+        }
+        let audio = self.audio.as_ref()?;
+        let remaining = match audio.remaining() {
+            Some(remaining) => remaining,
+            // Backends without a buffer to measure: go by the tagged length.
+            None => {
+                let duration = self.queue.current()?.track.duration_seconds?;
+                Duration::from_secs(duration.into()).saturating_sub(audio.position())
+            }
+        };
+        if remaining > GAPLESS_LEAD {
+            return None;
+        }
+        let next = self.gapless_follower()?.clone();
+        self.gapless_next = Some(next.id);
+        Some(next)
+    }
+
+    /// Queue `prepared` audio for entry `id`, from `gapless_candidate`, to
+    /// start the moment the current track ends. Returns `false` if `id` no
+    /// longer follows the current track or the backend can't do gapless.
+    pub fn queue_gapless(&mut self, id: QueueId, prepared: PreparedAudio) -> bool {
+        if self.gapless_next != Some(id) || self.gapless_follower().map(|i| i.id) != Some(id) {
+            return false;
+        }
+        let Some(audio) = &self.audio else {
+            return false;
+        };
+        match audio.queue_next(prepared) {
+            Ok(()) => true,
+            Err(err) => {
+                tracing::debug!(error = %err, "gapless playback unavailable");
+                false
+            }
+        }
+    }
+
+    /// Whether playback has moved on to the entry queued with
+    /// `queue_gapless`; `finish_gapless` makes it the current one.
+    pub fn gapless_started(&self) -> bool {
+        self.audio
+            .as_ref()
+            .is_some_and(|audio| audio.handoffs() > self.handoffs_seen)
+    }
+
+    /// Make the entry that started without a gap the current one.
+    pub fn finish_gapless(&mut self) -> Option<&QueueItem> {
+        let handoffs = self.audio.as_ref()?.handoffs();
+        if handoffs <= self.handoffs_seen {
+            return None;
+        }
+        self.handoffs_seen = handoffs;
+        let id = self.gapless_next.take()?;
+        let Some(index) = self.queue.index_of(id) else {
+            // Removed just as it started: there's no entry left to show.
+            self.stop();
+            return None;
+        };
+        self.remember_active(id);
+        self.queue.select_index(index);
+        self.state = PlayerState::Playing { id };
+        self.record_play();
+        self.queue.current()
+    }
+
+    /// The entry after the current one, if it can follow without a gap.
+    fn gapless_follower(&self) -> Option<&QueueItem> {
+        if !matches!(
+            self.state,
+            PlayerState::Playing { .. } | PlayerState::Paused { .. }
+        ) || self.queue.is_shuffled()
+            || self.queue.repeat_mode() == RepeatMode::One
+        {
+            return None;
+        }
+        let next = self.queue.peek_next()?;
+        continues_album(&self.queue.current()?.track, &next.track).then_some(next)
+    }
+
+    fn record_play(&mut self) {
+        self.play_counted = false;
+        let Some(current) = self.queue.current() else {
+            return;
+        };
+        if let Some(history) = &self.play_history {
+            history.record(current.track.clone());
+        }
+        if let Some(now_playing) = &self.now_playing {
+            now_playing.record(Some(current.track.clone()));
+        }
+    }
+
+    fn clear_now_playing(&self) {
+        if let Some(now_playing) = &self.now_playing {
+            now_playing.record(None);
+        }
+    }
+
     /// Get mutable access to audio handle for setting up callbacks
     pub fn audio_mut(&mut self) -> Option<&mut AudioHandle> {
         self.audio.as_mut()
@@ -165,26 +546,86 @@ impl Player {
         self.stop_audio();
         self.queue.reset_current();
         self.state = PlayerState::Stopped;
+        self.clear_now_playing();
     }
 
+    /// Move to the next entry. Repeat-One doesn't replay the current track
+    /// on a skip; only a track playing to its end does.
     pub fn skip_next(&mut self) -> Option<&QueueItem> {
-        self.queue.advance()?;
-        let next_id = self.queue.current().map(|c| c.id)?;
-        self.state = PlayerState::Buffering { id: next_id };
+        let next_id = self.queue.advance()?.id;
+        self.start_next(next_id)
+    }
+
+    fn start_next(&mut self, next_id: QueueId) -> Option<&QueueItem> {
+        self.remember_active(next_id);
         self.state = PlayerState::Playing { id: next_id };
         self.stop_audio();
         self.queue.current()
     }
 
+    /// Whether the current track has played to its end, for the caller to
+    /// move on. Returns `true` once per track: the finished audio is
+    /// released, so later calls return `false` until another track starts.
+    ///
+    /// Audio that fails mid-track is not a finish: the player moves to
+    /// `Error` and the cause can be had from `take_audio_error`.
+    pub fn poll_completion(&mut self) -> bool {
+        let PlayerState::Playing { id } = self.state else {
+            return false;
+        };
+        let Some(audio) = &self.audio else {
+            return false;
+        };
+        if audio.is_failed() {
+            let error = AudioError::Backend("playback stopped unexpectedly".into());
+            self.stop_audio();
+            self.state = PlayerState::Error {
+                id: Some(id),
+                message: error.to_string(),
+            };
+            self.audio_error = Some(error);
+            return false;
+        }
+        let finished = audio.is_finished();
+        if finished {
+            self.stop_audio();
+        }
+        finished
+    }
+
+    /// Move on after the current track finished, following the queue's
+    /// repeat and shuffle order. Stops at the end of the queue.
+    pub fn advance_after_end(&mut self) -> Option<&QueueItem> {
+        let Some(next_id) = self.queue.advance_after_end().map(|next| next.id) else {
+            self.state = PlayerState::Stopped;
+            self.clear_now_playing();
+            return None;
+        };
+        self.start_next(next_id)
+    }
+
+    /// Return to the most recently played entry that is still in the queue.
+    ///
+    /// Returns `None` without changing state when there is no history left.
     pub fn skip_previous(&mut self) -> Option<&QueueItem> {
-        self.queue.previous()?;
-        let prev_id = self.queue.current().map(|c| c.id)?;
-        self.state = PlayerState::Buffering { id: prev_id };
+        let index = loop {
+            let id = self.history.pop_back()?;
+            if let Some(index) = self.queue.index_of(id) {
+                break index;
+            }
+        };
+        let prev_id = self.queue.select_index(index)?.id;
         self.state = PlayerState::Playing { id: prev_id };
         self.stop_audio();
         self.queue.current()
     }
 
+    /// The engine error behind the last failed `play_with_audio`, if it
+    /// hasn't been taken yet.
+    pub fn take_audio_error(&mut self) -> Option<AudioError> {
+        self.audio_error.take()
+    }
+
     pub fn set_error(&mut self, message: impl Into<String>) {
         let id = self.queue.current().map(|item| item.id);
         self.state = PlayerState::Error {
@@ -231,8 +672,8 @@ impl Player {
         self.stop_audio();
 
         // Try to advance to next track
-        if let Some(next) = self.queue.advance() {
-            let next_id = next.id;
+        if let Some(next_id) = self.skip_failed() {
+            self.remember_active(next_id);
             self.state = PlayerState::Buffering { id: next_id };
             self.queue.current()
         } else {
@@ -278,7 +719,7 @@ impl Player {
         self.stop_audio();
 
         // Try to advance to next track and play it
-        if self.queue.advance().is_some() {
+        if self.skip_failed().is_some() {
             let current = self.queue.current()?;
             let source = source_fn(current);
             self.play_with_audio(engine, source)
@@ -289,13 +730,61 @@ impl Player {
         }
     }
 
+    /// Move past the current entry after it failed to play. Stops instead of
+    /// wrapping round to the same entry, which would only fail again.
+    fn skip_failed(&mut self) -> Option<QueueId> {
+        let failed = self.queue.current().map(|item| item.id);
+        let next = self.queue.advance().map(|item| item.id);
+        if next.is_some() && next == failed {
+            self.queue.reset_current();
+            return None;
+        }
+        next
+    }
+
+    /// Push the entry that was active before `next` onto the history stack.
+    fn remember_active(&mut self, next: QueueId) {
+        let active = match &self.state {
+            PlayerState::Buffering { id }
+            | PlayerState::Playing { id }
+            | PlayerState::Paused { id } => Some(*id),
+            PlayerState::Error { id, .. } => *id,
+            PlayerState::Stopped => None,
+        };
+        if let Some(active) = active.filter(|active| *active != next) {
+            if self.history.len() == HISTORY_LIMIT {
+                self.history.pop_front();
+            }
+            self.history.push_back(active);
+        }
+    }
+
     fn stop_audio(&mut self) {
+        self.gapless_next = None;
+        self.buffering_since = None;
+        self.handoffs_seen = 0;
         if let Some(handle) = self.audio.take() {
             handle.stop();
         }
     }
 }
 
+/// Whether `next` is the track after `current` on the same album. Without
+/// track numbers, being on the same album is enough.
+fn continues_album(current: &Track, next: &Track) -> bool {
+    if current.provider_id != next.provider_id || current.album.is_none() {
+        return false;
+    }
+    if current.album != next.album {
+        return false;
+    }
+    match (current.track_number, next.track_number) {
+        (Some(a), Some(b)) if current.disc_number == next.disc_number => b == a + 1,
+        (Some(_), Some(b)) => b == 1 && next.disc_number > current.disc_number,
+        _ => true,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use tunez_core::{Track, TrackId};
@@ -311,6 +800,11 @@ mod tests {
             album: None,
             duration_seconds: None,
             track_number: None,
+            disc_number: None,
+            year: None,
+            genre: None,
+            musicbrainz_id: None,
+            replay_gain: Default::default(),
         }
     }
 
@@ -337,6 +831,98 @@ mod tests {
         assert!(matches!(player.state(), PlayerState::Playing { .. }));
     }
 
+    #[test]
+    fn started_tracks_are_recorded_in_play_history() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut player = Player::new();
+        player.set_play_history(PlayHistory::new(dir.path()));
+        player.queue_mut().enqueue_back(track("one"));
+        player.queue_mut().enqueue_back(track("two"));
+        player.queue_mut().enqueue_back(track("three"));
+        let engine = tunez_audio::NullAudioEngine;
+
+        player.play_with_audio(&engine, AudioSource::Url("test".into()));
+        player.skip_next();
+        player.play_with_audio(&engine, AudioSource::Url("test".into()));
+        // Queued but not started: not part of the history.
+        player.skip_next();
+        // Dropping the player waits for the history to be written.
+        drop(player);
+        assert_eq!(
+            PlayHistory::new(dir.path()).recent("test").unwrap(),
+            vec![TrackId::new("two"), TrackId::new("one")]
+        );
+    }
+
+    #[test]
+    fn a_play_is_counted_once_it_passes_the_threshold() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut player = Player::new();
+        player.set_play_counts(PlayCounts::new(dir.path()));
+        let mut song = track("one");
+        song.duration_seconds = Some(100);
+        player.queue_mut().enqueue_back(song);
+        let engine = tunez_audio::NullAudioEngine;
+
+        player.play_with_audio(&engine, AudioSource::Url("test".into()));
+        assert!(!player.record_progress(Duration::from_secs(49)));
+        assert!(player.record_progress(Duration::from_secs(50)));
+        // The rest of the same play doesn't count again.
+        assert!(!player.record_progress(Duration::from_secs(60)));
+        assert!(!player.record_progress(Duration::from_secs(99)));
+
+        // Playing it again is another play.
+        player.play_with_audio(&engine, AudioSource::Url("test".into()));
+        assert!(player.record_progress(Duration::from_secs(50)));
+        // Dropping the player waits for the counts to be written.
+        drop(player);
+
+        let counts = PlayCounts::new(dir.path());
+        assert_eq!(counts.count("test", &TrackId::new("one")).unwrap(), 2);
+    }
+
+    #[test]
+    fn now_playing_file_follows_the_playing_track() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("obs/now-playing.txt");
+        let engine = tunez_audio::NullAudioEngine;
+        let player_for = |titles: &[&str]| {
+            let mut player = Player::new();
+            player.set_now_playing_file(NowPlayingFile::new(&path, "{artist} - {title} - {album}"));
+            for title in titles {
+                let mut track = track(title);
+                track.album = Some("Album".into());
+                player.queue_mut().enqueue_back(track);
+            }
+            player
+        };
+
+        // Dropping the player waits for the file to be written.
+        let mut player = player_for(&["one", "two"]);
+        player.play_with_audio(&engine, AudioSource::Url("test".into()));
+        drop(player);
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "artist - one - Album"
+        );
+
+        let mut player = player_for(&["one", "two"]);
+        player.play_with_audio(&engine, AudioSource::Url("test".into()));
+        player.skip_next();
+        player.play_with_audio(&engine, AudioSource::Url("test".into()));
+        drop(player);
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "artist - two - Album"
+        );
+
+        let mut player = player_for(&["one"]);
+        player.play_with_audio(&engine, AudioSource::Url("test".into()));
+        player.stop();
+        drop(player);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "");
+    }
+
     #[test]
     fn skip_advances_queue_and_state() {
         let mut player = Player::new();
@@ -349,6 +935,98 @@ mod tests {
         assert!(matches!(player.state(), PlayerState::Playing { .. }));
     }
 
+    #[test]
+    fn skip_previous_walks_history_in_reverse() {
+        let mut player = Player::new();
+        for title in ["one", "two", "three"] {
+            player.queue_mut().enqueue_back(track(title));
+        }
+        player.play();
+        player.skip_next();
+        player.skip_next();
+
+        let prev = player.skip_previous().expect("should return to two");
+        assert_eq!(prev.track.title, "two");
+        assert!(matches!(player.state(), PlayerState::Playing { .. }));
+        let prev = player.skip_previous().expect("should return to one");
+        assert_eq!(prev.track.title, "one");
+        assert!(player.skip_previous().is_none());
+        assert_eq!(player.current().unwrap().track.title, "one");
+    }
+
+    #[test]
+    fn skip_previous_follows_play_order_not_queue_order() {
+        let mut player = Player::new();
+        for title in ["one", "two", "three"] {
+            player.queue_mut().enqueue_back(track(title));
+        }
+        player.play_index(2);
+        player.play_index(0);
+
+        let prev = player.skip_previous().expect("should return to three");
+        assert_eq!(prev.track.title, "three");
+    }
+
+    #[test]
+    fn skip_previous_without_history_keeps_state() {
+        let mut player = Player::new();
+        player.queue_mut().enqueue_back(track("one"));
+        player.queue_mut().enqueue_back(track("two"));
+        player.play_index(1);
+
+        assert!(player.skip_previous().is_none());
+        assert_eq!(player.current().unwrap().track.title, "two");
+        assert!(matches!(player.state(), PlayerState::Playing { .. }));
+    }
+
+    #[test]
+    fn skip_previous_skips_removed_entries() {
+        let mut player = Player::new();
+        let one = player.queue_mut().enqueue_back(track("one"));
+        let two = player.queue_mut().enqueue_back(track("two"));
+        player.queue_mut().enqueue_back(track("three"));
+        player.play();
+        player.skip_next();
+        player.skip_next();
+        player.queue_mut().remove(two);
+
+        let prev = player.skip_previous().expect("should fall back to one");
+        assert_eq!(prev.id, one);
+    }
+
+    #[test]
+    fn history_survives_stop_but_not_queue_load() {
+        let mut player = Player::new();
+        player.queue_mut().enqueue_back(track("one"));
+        player.queue_mut().enqueue_back(track("two"));
+        player.play();
+        player.skip_next();
+
+        player.stop();
+        let prev = player.skip_previous().expect("history survives stop");
+        assert_eq!(prev.track.title, "one");
+
+        player.skip_next();
+        let mut fresh = Queue::new();
+        fresh.enqueue_back(track("other"));
+        player.load_queue(fresh);
+        assert_eq!(player.history().count(), 0);
+        assert!(player.skip_previous().is_none());
+        assert!(matches!(player.state(), PlayerState::Stopped));
+    }
+
+    #[test]
+    fn history_is_bounded() {
+        let mut player = Player::new();
+        for i in 0..=HISTORY_LIMIT + 5 {
+            player.queue_mut().enqueue_back(track(&i.to_string()));
+        }
+        player.play();
+        while player.skip_next().is_some() {}
+
+        assert_eq!(player.history().count(), HISTORY_LIMIT);
+    }
+
     #[test]
     fn stop_clears_current_selection() {
         let mut player = Player::new();
@@ -388,6 +1066,471 @@ mod tests {
         assert!(matches!(player.state(), PlayerState::Playing { .. }));
     }
 
+    fn album_track(title: &str, album: &str, number: u32) -> Track {
+        Track {
+            album: Some(album.into()),
+            track_number: Some(number),
+            duration_seconds: Some(10),
+            ..track(title)
+        }
+    }
+
+    #[test]
+    fn gapless_candidate_is_the_next_album_track_near_the_end() {
+        let mut player = Player::new();
+        player
+            .queue_mut()
+            .enqueue_back(album_track("one", "Album", 1));
+        player
+            .queue_mut()
+            .enqueue_back(album_track("two", "Album", 2));
+        player
+            .queue_mut()
+            .enqueue_back(album_track("other", "Other", 1));
+        let engine = tunez_audio::NullAudioEngine;
+        assert!(player.gapless_candidate().is_none());
+
+        // Ten-second tracks are always within the lead of their end.
+        player.play_with_audio(&engine, AudioSource::Url("test".into()));
+        let next = player.gapless_candidate().expect("two follows one");
+        assert_eq!(next.track.title, "two");
+        // Handed out once.
+        assert!(player.gapless_candidate().is_none());
+
+        // Shuffle takes it back; it isn't offered while shuffled.
+        player.queue_mut().set_shuffle(true);
+        assert!(player.gapless_candidate().is_none());
+        player.queue_mut().set_shuffle(false);
+        assert_eq!(player.gapless_candidate().unwrap().track.title, "two");
+
+        // A manual skip drops it; the next album doesn't continue this one.
+        player.skip_next();
+        player.play_with_audio(&engine, AudioSource::Url("test".into()));
+        assert!(player.gapless_candidate().is_none());
+        assert!(!player.gapless_started());
+    }
+
+    /// Plays silence that always has the given decoded audio left.
+    struct BufferedEngine(Duration);
+
+    impl AudioEngine for BufferedEngine {
+        fn play(&self, _source: AudioSource) -> tunez_audio::AudioResult<AudioHandle> {
+            struct Remaining(Duration);
+            impl tunez_audio::AudioControl for Remaining {
+                fn remaining(&self) -> Option<Duration> {
+                    Some(self.0)
+                }
+            }
+            let mut handle = AudioHandle::spawn_simulated(Duration::from_secs(60));
+            handle.set_control(Arc::new(Remaining(self.0)));
+            Ok(handle)
+        }
+    }
+
+    #[test]
+    fn gapless_lead_follows_the_decoded_audio_left() {
+        let mut first = album_track("one", "Album", 1);
+        // Tags can be wrong, or audio shorter than they say.
+        first.duration_seconds = Some(600);
+        let mut player = Player::new();
+        player.queue_mut().enqueue_back(first);
+        player
+            .queue_mut()
+            .enqueue_back(album_track("two", "Album", 2));
+
+        player.play_with_audio(
+            &BufferedEngine(GAPLESS_LEAD + Duration::from_secs(1)),
+            AudioSource::Url("test".into()),
+        );
+        assert!(player.gapless_candidate().is_none());
+
+        player.play_with_audio(
+            &BufferedEngine(GAPLESS_LEAD),
+            AudioSource::Url("test".into()),
+        );
+        assert_eq!(player.gapless_candidate().unwrap().track.title, "two");
+    }
+
+    #[test]
+    fn albums_continue_across_discs_but_not_gaps() {
+        let first = album_track("a", "Album", 9);
+        let mut next_disc = album_track("b", "Album", 1);
+        next_disc.disc_number = Some(2);
+        let mut first_disc = first.clone();
+        first_disc.disc_number = Some(1);
+        assert!(continues_album(&first_disc, &next_disc));
+        assert!(continues_album(&first, &album_track("c", "Album", 10)));
+        assert!(!continues_album(&first, &album_track("d", "Album", 11)));
+        assert!(!continues_album(&track("x"), &track("y")));
+    }
+
+    /// Plays every source as an instantly finishing track.
+    struct InstantEngine;
+
+    impl AudioEngine for InstantEngine {
+        fn play(&self, _source: AudioSource) -> tunez_audio::AudioResult<AudioHandle> {
+            Ok(AudioHandle::spawn_simulated(std::time::Duration::ZERO))
+        }
+    }
+
+    /// Poll until the current track reports its end, as the UI tick does.
+    fn wait_for_end(player: &mut Player) -> bool {
+        for _ in 0..100 {
+            if player.poll_completion() {
+                return true;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        false
+    }
+
+    #[test]
+    fn finished_track_advances_exactly_once() {
+        let mut player = Player::new();
+        for title in ["one", "two", "three"] {
+            player.queue_mut().enqueue_back(track(title));
+        }
+        player.play_with_audio(&InstantEngine, AudioSource::Url("test".into()));
+
+        assert!(wait_for_end(&mut player));
+        // The same completion is not reported again.
+        assert!(!player.poll_completion());
+        let next = player.advance_after_end().expect("should move to two");
+        assert_eq!(next.track.title, "two");
+        assert!(!player.poll_completion());
+        assert_eq!(player.current().unwrap().track.title, "two");
+    }
+
+    #[test]
+    fn short_synthetic_tracks_complete_on_their_own() {
+        let mut player = Player::new();
+        player.queue_mut().enqueue_back(track("short"));
+        let engine = tunez_audio::SyntheticAudioEngine::new(Duration::from_millis(60));
+        player.play_with_audio(&engine, AudioSource::Url("demo".into()));
+        assert!(!player.poll_completion());
+
+        assert!(wait_for_end(&mut player));
+        assert!(player.take_audio_error().is_none());
+        assert!(player.advance_after_end().is_none());
+        assert_eq!(player.state(), &PlayerState::Stopped);
+    }
+
+    #[test]
+    fn finished_track_respects_repeat_and_queue_end() {
+        let mut player = Player::new();
+        player.queue_mut().enqueue_back(track("one"));
+        player.queue_mut().set_repeat_mode(crate::RepeatMode::One);
+        player.play_with_audio(&InstantEngine, AudioSource::Url("test".into()));
+        assert!(wait_for_end(&mut player));
+        assert_eq!(player.advance_after_end().unwrap().track.title, "one");
+
+        player.queue_mut().set_repeat_mode(crate::RepeatMode::Off);
+        player.play_with_audio(&InstantEngine, AudioSource::Url("test".into()));
+        assert!(wait_for_end(&mut player));
+        assert!(player.advance_after_end().is_none());
+        assert!(matches!(player.state(), PlayerState::Stopped));
+    }
+
+    /// Has no output device.
+    struct NoDeviceEngine;
+
+    impl AudioEngine for NoDeviceEngine {
+        fn play(&self, _source: AudioSource) -> tunez_audio::AudioResult<AudioHandle> {
+            Err(AudioError::Backend("no output device".into()))
+        }
+    }
+
+    #[test]
+    fn failed_playback_keeps_the_engine_error_once() {
+        let mut player = Player::new();
+        player.queue_mut().enqueue_back(track("one"));
+        assert!(player
+            .play_with_audio(&NoDeviceEngine, AudioSource::Url("test".into()))
+            .is_none());
+        assert!(matches!(player.state(), PlayerState::Error { .. }));
+        assert!(matches!(
+            player.take_audio_error(),
+            Some(AudioError::Backend(_))
+        ));
+        assert!(player.take_audio_error().is_none());
+    }
+
+    /// A network stream whose first samples arrive after the delay, or
+    /// never when it's `None`.
+    struct SlowStreamEngine(Option<Duration>);
+
+    impl AudioEngine for SlowStreamEngine {
+        fn play(&self, _source: AudioSource) -> tunez_audio::AudioResult<AudioHandle> {
+            Ok(AudioHandle::spawn_simulated_stream(
+                self.0,
+                Duration::from_secs(5),
+            ))
+        }
+    }
+
+    #[test]
+    fn streams_buffer_until_their_first_samples_arrive() {
+        let mut player = Player::new();
+        player.queue_mut().enqueue_back(track("one"));
+        let engine = SlowStreamEngine(Some(Duration::from_millis(100)));
+        let id = player
+            .play_with_audio(&engine, AudioSource::Url("http://host/one.mp3".into()))
+            .unwrap()
+            .id;
+
+        let mut seen = vec![player.state().clone()];
+        for _ in 0..100 {
+            player.poll_buffering();
+            if seen.last() != Some(player.state()) {
+                seen.push(player.state().clone());
+            }
+            if matches!(player.state(), PlayerState::Playing { .. }) {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(
+            seen,
+            [PlayerState::Buffering { id }, PlayerState::Playing { id }]
+        );
+        player.stop();
+    }
+
+    #[test]
+    fn a_stalled_stream_times_out_into_an_error() {
+        let mut player = Player::new();
+        player.set_buffering_timeout(Duration::from_millis(50));
+        player.queue_mut().enqueue_back(track("one"));
+        player.play_with_audio(
+            &SlowStreamEngine(None),
+            AudioSource::Url("http://host/one.mp3".into()),
+        );
+        player.poll_buffering();
+        assert!(matches!(player.state(), PlayerState::Buffering { .. }));
+        assert!(player.take_audio_error().is_none());
+
+        std::thread::sleep(Duration::from_millis(80));
+        player.poll_buffering();
+        assert!(matches!(player.state(), PlayerState::Error { .. }));
+        assert!(matches!(player.take_audio_error(), Some(AudioError::Io(_))));
+    }
+
+    /// Records the gain it was asked to play at.
+    #[derive(Default)]
+    struct GainProbe(std::sync::Mutex<Option<f32>>);
+
+    impl AudioEngine for GainProbe {
+        fn play(&self, source: AudioSource) -> tunez_audio::AudioResult<AudioHandle> {
+            self.play_with_gain(source, 1.0)
+        }
+
+        fn play_with_gain(
+            &self,
+            source: AudioSource,
+            gain: f32,
+        ) -> tunez_audio::AudioResult<AudioHandle> {
+            *self.0.lock().unwrap() = Some(gain);
+            tunez_audio::NullAudioEngine.play(source)
+        }
+    }
+
+    #[test]
+    fn replay_gain_mode_sets_playback_gain() {
+        let mut tagged = track("one");
+        tagged.replay_gain.track_gain_db = Some(-6.0);
+        tagged.replay_gain.album_gain_db = Some(-12.0);
+        let mut player = Player::new();
+        player.queue_mut().enqueue_back(tagged);
+        let engine = GainProbe::default();
+
+        player.play_with_audio(&engine, AudioSource::Url("test".into()));
+        assert_eq!(*engine.0.lock().unwrap(), Some(1.0));
+
+        player.set_replay_gain(ReplayGainConfig {
+            mode: tunez_core::ReplayGainMode::Album,
+            prevent_clipping: true,
+        });
+        player.play_with_audio(&engine, AudioSource::Url("test".into()));
+        let gain = engine.0.lock().unwrap().unwrap();
+        assert!((gain - 0.251_189).abs() < 1e-4);
+    }
+
+    /// Records the part of each source it was asked to play.
+    #[derive(Default)]
+    struct RangeProbe(std::sync::Mutex<Vec<tunez_audio::TimeRange>>);
+
+    impl AudioEngine for RangeProbe {
+        fn play(&self, source: AudioSource) -> tunez_audio::AudioResult<AudioHandle> {
+            let (source, range) = source.time_range();
+            self.0.lock().unwrap().push(range);
+            tunez_audio::NullAudioEngine.play(source)
+        }
+    }
+
+    #[test]
+    fn cue_track_starts_at_its_encoded_offset() {
+        let mut player = Player::new();
+        for id in [
+            "/music/live.flac#t=0.000,245.333",
+            "/music/live.flac#t=245.333",
+        ] {
+            player.queue_mut().enqueue_back(track(id));
+        }
+        let engine = RangeProbe::default();
+        let second = player.play_index(1).unwrap().track.id.clone();
+        let url = format!("file://{}", second.0);
+        player.play_with_audio(&engine, AudioSource::Url(url));
+        assert_eq!(player.current().unwrap().track.id, second);
+
+        let ranges = engine.0.lock().unwrap();
+        assert_eq!(
+            ranges.as_slice(),
+            [tunez_audio::TimeRange {
+                start: std::time::Duration::from_millis(245_333),
+                end: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn seek_clamps_to_track_duration() {
+        let mut player = Player::new();
+        let mut long = track("one");
+        long.duration_seconds = Some(30);
+        player.queue_mut().enqueue_back(long);
+        let engine = tunez_audio::NullAudioEngine;
+        player.play_with_audio(&engine, AudioSource::Url("test".into()));
+
+        let target = player.seek(std::time::Duration::from_secs(90));
+        assert_eq!(target, Some(std::time::Duration::from_secs(30)));
+        let target = player.seek(std::time::Duration::from_secs(12));
+        assert_eq!(target, Some(std::time::Duration::from_secs(12)));
+    }
+
+    #[test]
+    fn fractions_map_onto_the_track_duration() {
+        let minutes = Duration::from_secs(240);
+        assert_eq!(fraction_of(minutes, 0.0), Some(Duration::ZERO));
+        assert_eq!(fraction_of(minutes, 0.5), Some(Duration::from_secs(120)));
+        assert_eq!(fraction_of(minutes, 0.25), Some(Duration::from_secs(60)));
+        assert_eq!(fraction_of(minutes, 1.0), Some(minutes));
+        assert_eq!(fraction_of(minutes, 1.7), Some(minutes));
+        assert_eq!(fraction_of(minutes, -0.3), Some(Duration::ZERO));
+        assert_eq!(fraction_of(minutes, f32::NAN), None);
+
+        let mut player = Player::new();
+        let mut known = track("known");
+        known.duration_seconds = Some(200);
+        player.queue_mut().enqueue_back(known);
+        player.play_with_audio(
+            &tunez_audio::NullAudioEngine,
+            AudioSource::Url("test".into()),
+        );
+        assert_eq!(player.seek_fraction(0.9), Some(Duration::from_secs(180)));
+        assert_eq!(player.seek_fraction(2.0), Some(Duration::from_secs(200)));
+
+        // Without a duration there's nothing to take a fraction of.
+        let mut player = Player::new();
+        player.queue_mut().enqueue_back(track("unknown"));
+        player.play_with_audio(
+            &tunez_audio::NullAudioEngine,
+            AudioSource::Url("test".into()),
+        );
+        assert_eq!(player.seek_fraction(0.5), None);
+    }
+
+    fn chapters_at(starts: &[u64]) -> Vec<Chapter> {
+        starts
+            .iter()
+            .map(|&secs| Chapter {
+                start: Duration::from_secs(secs),
+                title: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn chapter_jumps_stop_at_the_boundaries() {
+        let secs = Duration::from_secs;
+        let chapters = chapters_at(&[10, 60, 120]);
+
+        assert_eq!(chapter_after(&chapters, secs(0)), Some(secs(10)));
+        assert_eq!(chapter_after(&chapters, secs(10)), Some(secs(60)));
+        assert_eq!(chapter_after(&chapters, secs(90)), Some(secs(120)));
+        assert_eq!(chapter_after(&chapters, secs(120)), None);
+
+        // Well into a chapter restarts it; just after its start goes back one.
+        assert_eq!(chapter_before(&chapters, secs(90)), Some(secs(60)));
+        assert_eq!(chapter_before(&chapters, secs(61)), Some(secs(10)));
+        assert_eq!(chapter_before(&chapters, secs(11)), Some(secs(0)));
+        assert_eq!(chapter_before(&chapters, secs(5)), Some(secs(0)));
+        assert_eq!(chapter_before(&chapters, secs(500)), Some(secs(120)));
+
+        assert_eq!(chapter_after(&[], secs(5)), None);
+        assert_eq!(chapter_before(&[], secs(5)), None);
+    }
+
+    #[test]
+    fn chapters_only_apply_to_their_track() {
+        let mut player = Player::new();
+        let mut one = track("one");
+        one.duration_seconds = Some(300);
+        let one_id = one.id.clone();
+        player.queue_mut().enqueue_back(one);
+        player.queue_mut().enqueue_back(track("two"));
+        player.play_with_audio(
+            &tunez_audio::NullAudioEngine,
+            AudioSource::Url("test".into()),
+        );
+        assert_eq!(player.next_chapter(), None);
+
+        player.set_chapters(one_id, chapters_at(&[120, 30]));
+        assert_eq!(player.chapters().len(), 2);
+        assert_eq!(player.next_chapter(), Some(Duration::from_secs(30)));
+
+        player.skip_next();
+        assert!(player.chapters().is_empty());
+        assert_eq!(player.next_chapter(), None);
+        assert_eq!(player.previous_chapter(), None);
+    }
+
+    #[test]
+    fn synthetic_playback_feeds_the_visualizer() {
+        let visualizer = Arc::new(std::sync::Mutex::new(
+            tunez_viz::Visualizer::with_buffer_capacity(4096),
+        ));
+        let viz = visualizer.clone();
+        let mut player = Player::new();
+        player.set_sample_callback(move |samples: &[f32]| {
+            viz.lock().unwrap().add_samples(samples);
+        });
+        player.queue_mut().enqueue_back(track("one"));
+        player.queue_mut().enqueue_back(track("two"));
+        let engine = tunez_audio::SyntheticAudioEngine::new(Duration::from_secs(5));
+
+        // Every track gets the callback, not just the first.
+        for _ in 0..2 {
+            visualizer.lock().unwrap().add_samples(&[0.0; 4096]);
+            player.play_with_audio(&engine, AudioSource::Url("demo".into()));
+            let deadline = Instant::now() + Duration::from_secs(2);
+            while visualizer.lock().unwrap().rms() == 0.0 && Instant::now() < deadline {
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            assert!(visualizer.lock().unwrap().rms() > 0.1);
+            assert_eq!(player.sample_rate(), Some(44_100));
+            player.skip_next();
+        }
+    }
+
+    #[test]
+    fn seek_without_audio_is_noop() {
+        let mut player = Player::new();
+        player.queue_mut().enqueue_back(track("one"));
+        player.play();
+
+        assert!(player.seek(std::time::Duration::from_secs(5)).is_none());
+    }
+
     #[test]
     fn handle_track_error_skips_to_next() {
         let mut player = Player::new();
@@ -423,6 +1566,40 @@ mod tests {
         assert!(matches!(player.state(), PlayerState::Stopped));
     }
 
+    #[test]
+    fn repeat_one_skips_move_to_the_next_track() {
+        let mut player = Player::new();
+        player.queue_mut().enqueue_back(track("one"));
+        player.queue_mut().enqueue_back(track("two"));
+        player.queue_mut().set_repeat_mode(crate::RepeatMode::One);
+        player.play();
+
+        assert_eq!(player.skip_next().unwrap().track.title, "two");
+
+        // Picking a track to play now queues it next and skips to it.
+        player.queue_mut().enqueue_next(track("picked"));
+        assert_eq!(player.skip_next().unwrap().track.title, "picked");
+    }
+
+    #[test]
+    fn repeat_one_does_not_retry_a_failing_track() {
+        let mut player = Player::new();
+        player.queue_mut().enqueue_back(track("one"));
+        player.queue_mut().enqueue_back(track("two"));
+        player.queue_mut().set_repeat_mode(crate::RepeatMode::One);
+        player.play();
+
+        let next = player.handle_track_error("decode failed", |_| {});
+        assert_eq!(next.unwrap().track.title, "two");
+
+        let mut single = Player::new();
+        single.queue_mut().enqueue_back(track("only"));
+        single.queue_mut().set_repeat_mode(crate::RepeatMode::One);
+        single.play();
+        assert!(single.handle_track_error("decode failed", |_| {}).is_none());
+        assert!(matches!(single.state(), PlayerState::Stopped));
+    }
+
     #[test]
     fn handle_track_error_does_not_panic_on_empty_queue() {
         let mut player = Player::new();
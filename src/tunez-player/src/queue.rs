@@ -1,4 +1,4 @@
-use rand::{seq::SliceRandom, thread_rng};
+use rand::{rngs::StdRng, seq::SliceRandom, thread_rng, SeedableRng};
 use serde::{Deserialize, Serialize};
 use tunez_core::Track;
 
@@ -17,6 +17,50 @@ impl QueueId {
     }
 }
 
+/// How `Queue::advance` behaves once the current item finishes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RepeatMode {
+    /// Stop after the last item.
+    #[default]
+    Off,
+    /// Repeat the current item.
+    One,
+    /// Wrap around to the first item after the last.
+    All,
+}
+
+impl RepeatMode {
+    /// The mode that follows this one when cycling (Off → All → One → Off).
+    pub fn cycle(self) -> Self {
+        match self {
+            RepeatMode::Off => RepeatMode::All,
+            RepeatMode::All => RepeatMode::One,
+            RepeatMode::One => RepeatMode::Off,
+        }
+    }
+
+    /// Short label for status displays.
+    pub fn label(self) -> &'static str {
+        match self {
+            RepeatMode::Off => "Off",
+            RepeatMode::One => "One",
+            RepeatMode::All => "All",
+        }
+    }
+}
+
+/// Where [`Queue::enqueue_all`] puts the tracks it's given.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueuePosition {
+    /// After the last item.
+    Back,
+    /// Straight after the current item, or at the front if none is selected.
+    Next,
+    /// In place of everything queued, with nothing selected.
+    Replace,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct QueueItem {
     pub id: QueueId,
@@ -28,6 +72,11 @@ pub struct Queue {
     items: Vec<QueueItem>,
     current: Option<usize>,
     next_id: u64,
+    repeat: RepeatMode,
+    shuffle: bool,
+    /// Play order over `items` indices while shuffle is enabled.
+    shuffle_order: Vec<usize>,
+    shuffle_seed: Option<u64>,
 }
 
 impl Queue {
@@ -51,9 +100,56 @@ impl Queue {
         &self.items
     }
 
+    pub fn repeat_mode(&self) -> RepeatMode {
+        self.repeat
+    }
+
+    pub fn set_repeat_mode(&mut self, mode: RepeatMode) {
+        self.repeat = mode;
+    }
+
+    pub fn is_shuffled(&self) -> bool {
+        self.shuffle
+    }
+
+    /// Enable or disable shuffled play order.
+    ///
+    /// Enabling computes a fresh order anchored on the current item; disabling
+    /// returns to insertion order. The current item stays selected either way.
+    pub fn set_shuffle(&mut self, enabled: bool) {
+        self.shuffle = enabled;
+        if enabled {
+            self.reshuffle();
+        } else {
+            self.shuffle_order.clear();
+        }
+    }
+
+    /// Fix the seed used for shuffling so orders are reproducible.
+    pub fn set_shuffle_seed(&mut self, seed: Option<u64>) {
+        self.shuffle_seed = seed;
+    }
+
+    /// Item indices in the order they will be played.
+    pub fn play_order(&self) -> Vec<usize> {
+        if self.shuffle {
+            self.shuffle_order.clone()
+        } else {
+            (0..self.items.len()).collect()
+        }
+    }
+
+    /// Position of the entry with the given id, if it is still queued.
+    pub fn index_of(&self, id: QueueId) -> Option<usize> {
+        self.items.iter().position(|item| item.id == id)
+    }
+
     pub fn enqueue_back(&mut self, track: Track) -> QueueId {
         let id = QueueId::next(&mut self.next_id);
         self.items.push(QueueItem { id, track });
+        if self.shuffle {
+            self.shuffle_order.push(self.items.len() - 1);
+        }
         id
     }
 
@@ -61,6 +157,19 @@ impl Queue {
         let id = QueueId::next(&mut self.next_id);
         let insert_at = self.current.map(|idx| idx + 1).unwrap_or(0);
         self.items.insert(insert_at, QueueItem { id, track });
+        if self.shuffle {
+            let order_at = self
+                .current
+                .and_then(|current| self.order_position(current))
+                .map(|pos| pos + 1)
+                .unwrap_or(0);
+            for idx in &mut self.shuffle_order {
+                if *idx >= insert_at {
+                    *idx += 1;
+                }
+            }
+            self.shuffle_order.insert(order_at, insert_at);
+        }
         if let Some(current) = self.current.as_mut() {
             if insert_at <= *current {
                 *current += 1;
@@ -69,9 +178,63 @@ impl Queue {
         id
     }
 
+    /// Queue `tracks` at `position`, keeping their order, and return their ids.
+    ///
+    /// While shuffled, `Back` and `Next` place them in play order as the
+    /// single-track methods do; `Replace` shuffles the new items afresh.
+    pub fn enqueue_all(
+        &mut self,
+        tracks: impl IntoIterator<Item = Track>,
+        position: QueuePosition,
+    ) -> Vec<QueueId> {
+        match position {
+            QueuePosition::Back => tracks
+                .into_iter()
+                .map(|track| self.enqueue_back(track))
+                .collect(),
+            QueuePosition::Next => {
+                // Each lands straight after the current item, so going
+                // backwards leaves them in order.
+                let tracks: Vec<_> = tracks.into_iter().collect();
+                let mut ids: Vec<_> = tracks
+                    .into_iter()
+                    .rev()
+                    .map(|track| self.enqueue_next(track))
+                    .collect();
+                ids.reverse();
+                ids
+            }
+            QueuePosition::Replace => {
+                self.clear();
+                let ids = self.enqueue_all(tracks, QueuePosition::Back);
+                if self.shuffle {
+                    self.reshuffle();
+                }
+                ids
+            }
+        }
+    }
+
     pub fn remove(&mut self, id: QueueId) -> Option<QueueItem> {
-        let idx = self.items.iter().position(|item| item.id == id)?;
+        let idx = self.index_of(id)?;
         let removed = self.items.remove(idx);
+        if self.shuffle {
+            let pos = self.order_position(idx);
+            self.shuffle_order.retain(|&i| i != idx);
+            for i in &mut self.shuffle_order {
+                if *i > idx {
+                    *i -= 1;
+                }
+            }
+            if self.current == Some(idx) {
+                // Continue with whatever followed the removed item in play order
+                let pos = pos
+                    .unwrap_or(0)
+                    .min(self.shuffle_order.len().saturating_sub(1));
+                self.current = self.shuffle_order.get(pos).copied();
+                return Some(removed);
+            }
+        }
         match self.current {
             Some(current_idx) if idx < current_idx => self.current = Some(current_idx - 1),
             Some(current_idx) if idx == current_idx => {
@@ -88,13 +251,66 @@ impl Queue {
         Some(removed)
     }
 
+    /// Remove the item at `index`. Returns `None` if out of bounds.
+    pub fn remove_at(&mut self, index: usize) -> Option<QueueItem> {
+        let id = self.items.get(index)?.id;
+        self.remove(id)
+    }
+
+    /// Insert `track` at `index`, shifting later items back.
+    ///
+    /// Returns `None` if `index` is past the end. While shuffled the new item
+    /// plays last, as with [`Queue::enqueue_back`].
+    pub fn insert_at(&mut self, index: usize, track: Track) -> Option<QueueId> {
+        if index > self.items.len() {
+            return None;
+        }
+        let id = QueueId::next(&mut self.next_id);
+        self.items.insert(index, QueueItem { id, track });
+        for idx in self.current.iter_mut().chain(&mut self.shuffle_order) {
+            if *idx >= index {
+                *idx += 1;
+            }
+        }
+        if self.shuffle {
+            self.shuffle_order.push(index);
+        }
+        Some(id)
+    }
+
+    /// Move the item at `from` to `to`. The current item stays selected and
+    /// the shuffled play order is unchanged. Returns `false` if either index is
+    /// out of bounds.
+    pub fn move_item(&mut self, from: usize, to: usize) -> bool {
+        if from >= self.items.len() || to >= self.items.len() {
+            return false;
+        }
+        let item = self.items.remove(from);
+        self.items.insert(to, item);
+        for idx in self.current.iter_mut().chain(&mut self.shuffle_order) {
+            *idx = if *idx == from {
+                to
+            } else if from < *idx && *idx <= to {
+                *idx - 1
+            } else if to <= *idx && *idx < from {
+                *idx + 1
+            } else {
+                *idx
+            };
+        }
+        true
+    }
+
     pub fn clear(&mut self) {
         self.items.clear();
+        self.shuffle_order.clear();
         self.current = None;
     }
 
+    /// Select the first item in play order.
     pub fn select_first(&mut self) -> Option<&QueueItem> {
-        self.select_index(0)
+        let first = self.first_index()?;
+        self.select_index(first)
     }
 
     pub fn select_index(&mut self, index: usize) -> Option<&QueueItem> {
@@ -106,27 +322,30 @@ impl Queue {
         }
     }
 
+    /// Move to the next item, as for a skip. Repeat-One doesn't hold a skip
+    /// on the current item: it wraps at the end like Repeat-All.
     pub fn advance(&mut self) -> Option<&QueueItem> {
-        match self.current {
-            Some(idx) if idx + 1 < self.items.len() => {
-                self.current = Some(idx + 1);
-                self.current()
-            }
-            _ => {
-                self.current = None;
-                None
-            }
-        }
+        self.current = self.following_index(false);
+        self.current()
+    }
+
+    /// Move on after the current item played to its end, which Repeat-One
+    /// replays.
+    pub fn advance_after_end(&mut self) -> Option<&QueueItem> {
+        self.current = self.following_index(true);
+        self.current()
     }
 
+    /// The item `advance_after_end` would move to, without moving.
+    pub fn peek_next(&self) -> Option<&QueueItem> {
+        self.items.get(self.following_index(true)?)
+    }
+
+    /// Move to the item before the current one in play order.
     pub fn previous(&mut self) -> Option<&QueueItem> {
-        match self.current {
-            Some(idx) if idx > 0 => {
-                self.current = Some(idx - 1);
-                self.current()
-            }
-            _ => None,
-        }
+        let prev = self.prev_index(self.current?)?;
+        self.current = Some(prev);
+        self.current()
     }
 
     pub fn reset_current(&mut self) {
@@ -148,6 +367,71 @@ impl Queue {
             let mut rng = thread_rng();
             self.items.shuffle(&mut rng);
         }
+        if self.shuffle {
+            self.reshuffle();
+        }
+    }
+
+    fn reshuffle(&mut self) {
+        let mut rng = match self.shuffle_seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        let mut order: Vec<usize> = (0..self.items.len())
+            .filter(|&idx| Some(idx) != self.current)
+            .collect();
+        order.shuffle(&mut rng);
+        if let Some(current) = self.current {
+            order.insert(0, current);
+        }
+        self.shuffle_order = order;
+    }
+
+    fn order_position(&self, idx: usize) -> Option<usize> {
+        self.shuffle_order.iter().position(|&i| i == idx)
+    }
+
+    /// The item after the current one; `ended` when it played to its end
+    /// rather than being skipped.
+    fn following_index(&self, ended: bool) -> Option<usize> {
+        match (self.current, self.repeat) {
+            (Some(idx), RepeatMode::One) if ended => Some(idx),
+            (Some(idx), RepeatMode::All | RepeatMode::One) => {
+                self.next_index(idx).or_else(|| self.first_index())
+            }
+            (Some(idx), RepeatMode::Off) => self.next_index(idx),
+            (None, _) => None,
+        }
+    }
+
+    fn first_index(&self) -> Option<usize> {
+        if self.shuffle {
+            self.shuffle_order.first().copied()
+        } else if self.items.is_empty() {
+            None
+        } else {
+            Some(0)
+        }
+    }
+
+    fn next_index(&self, idx: usize) -> Option<usize> {
+        if self.shuffle {
+            let pos = self.order_position(idx)?;
+            self.shuffle_order.get(pos + 1).copied()
+        } else if idx + 1 < self.items.len() {
+            Some(idx + 1)
+        } else {
+            None
+        }
+    }
+
+    fn prev_index(&self, idx: usize) -> Option<usize> {
+        if self.shuffle {
+            let pos = self.order_position(idx)?;
+            pos.checked_sub(1).map(|pos| self.shuffle_order[pos])
+        } else {
+            idx.checked_sub(1)
+        }
     }
 
     /// Get the next_id value (for persistence).
@@ -171,6 +455,7 @@ impl Queue {
             items,
             current,
             next_id,
+            ..Self::default()
         }
     }
 }
@@ -190,6 +475,11 @@ mod tests {
             album: None,
             duration_seconds: None,
             track_number: None,
+            disc_number: None,
+            year: None,
+            genre: None,
+            musicbrainz_id: None,
+            replay_gain: Default::default(),
         }
     }
 
@@ -216,6 +506,60 @@ mod tests {
         assert_eq!(queue.items()[1].track.title, "inserted");
     }
 
+    #[test]
+    fn enqueue_all_keeps_order_at_each_position() {
+        let mut queue = Queue::new();
+        queue.enqueue_back(track("one"));
+        queue.enqueue_back(track("two"));
+        queue.enqueue_back(track("three"));
+        queue.select_index(1);
+
+        let ids = queue.enqueue_all([track("a"), track("b")], QueuePosition::Next);
+        assert_eq!(titles(&queue), ["one", "two", "a", "b", "three"]);
+        assert_eq!(
+            ids,
+            [queue.items()[2].id, queue.items()[3].id],
+            "ids follow the given order"
+        );
+        assert_eq!(queue.current().unwrap().track.title, "two");
+        assert_eq!(queue.advance().unwrap().track.title, "a");
+
+        queue.enqueue_all([track("c"), track("d")], QueuePosition::Back);
+        assert_eq!(titles(&queue), ["one", "two", "a", "b", "three", "c", "d"]);
+    }
+
+    #[test]
+    fn enqueue_all_next_goes_after_current_while_shuffled() {
+        let mut queue = Queue::new();
+        queue.set_shuffle_seed(Some(7));
+        for title in ["one", "two", "three"] {
+            queue.enqueue_back(track(title));
+        }
+        queue.set_shuffle(true);
+        let playing = queue.select_first().unwrap().track.title.clone();
+
+        queue.enqueue_all([track("a"), track("b")], QueuePosition::Next);
+        assert_eq!(queue.current().unwrap().track.title, playing);
+        assert_eq!(queue.advance().unwrap().track.title, "a");
+        assert_eq!(queue.advance().unwrap().track.title, "b");
+    }
+
+    #[test]
+    fn enqueue_all_replace_clears_queue_and_selection() {
+        let mut queue = Queue::new();
+        let old = queue.enqueue_back(track("old"));
+        queue.enqueue_back(track("older"));
+        queue.select_index(1);
+
+        let ids = queue.enqueue_all([track("x"), track("y")], QueuePosition::Replace);
+        assert_eq!(titles(&queue), ["x", "y"]);
+        assert!(queue.current().is_none());
+        assert!(queue.index_of(old).is_none());
+        // Fresh ids, never reusing the replaced items' ones.
+        assert!(ids.iter().all(|id| id.0 > old.0 + 1));
+        assert_eq!(queue.select_first().unwrap().id, ids[0]);
+    }
+
     #[test]
     fn remove_updates_current_pointer() {
         let mut queue = Queue::new();
@@ -228,6 +572,90 @@ mod tests {
         assert_eq!(current.id, second);
     }
 
+    fn titles(queue: &Queue) -> Vec<&str> {
+        queue
+            .items()
+            .iter()
+            .map(|item| item.track.title.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn remove_at_current_selects_following_item() {
+        let mut queue = Queue::new();
+        queue.enqueue_back(track("one"));
+        queue.enqueue_back(track("two"));
+        queue.enqueue_back(track("three"));
+        queue.select_index(1);
+
+        assert_eq!(queue.remove_at(1).unwrap().track.title, "two");
+        assert_eq!(queue.current().unwrap().track.title, "three");
+        assert_eq!(queue.remove_at(1).unwrap().track.title, "three");
+        assert_eq!(queue.current().unwrap().track.title, "one");
+        assert!(queue.remove_at(5).is_none());
+        queue.remove_at(0);
+        assert!(queue.current().is_none());
+    }
+
+    #[test]
+    fn insert_at_shifts_current() {
+        let mut queue = Queue::new();
+        queue.enqueue_back(track("one"));
+        queue.enqueue_back(track("two"));
+        queue.select_index(1);
+
+        queue.insert_at(0, track("zero")).unwrap();
+        queue.insert_at(3, track("end")).unwrap();
+        assert_eq!(titles(&queue), ["zero", "one", "two", "end"]);
+        assert_eq!(queue.current().unwrap().track.title, "two");
+        assert!(queue.insert_at(9, track("nope")).is_none());
+        assert_eq!(queue.len(), 4);
+    }
+
+    #[test]
+    fn move_item_across_current_keeps_it_selected() {
+        let mut queue = Queue::new();
+        for name in ["a", "b", "c", "d"] {
+            queue.enqueue_back(track(name));
+        }
+        queue.select_index(1);
+
+        assert!(queue.move_item(0, 3));
+        assert_eq!(titles(&queue), ["b", "c", "d", "a"]);
+        assert_eq!(queue.current().unwrap().track.title, "b");
+
+        assert!(queue.move_item(3, 0));
+        assert_eq!(titles(&queue), ["a", "b", "c", "d"]);
+        assert_eq!(queue.current().unwrap().track.title, "b");
+
+        assert!(queue.move_item(1, 2));
+        assert_eq!(queue.current().unwrap().track.title, "b");
+        assert_eq!(queue.advance().unwrap().track.title, "d");
+
+        assert!(!queue.move_item(0, 4));
+        assert!(!queue.move_item(4, 0));
+    }
+
+    #[test]
+    fn move_item_preserves_shuffled_play_order() {
+        let mut queue = shuffled_queue(5);
+        queue.select_first();
+        queue.set_shuffle(true);
+        let before: Vec<_> = queue
+            .play_order()
+            .into_iter()
+            .map(|idx| queue.items()[idx].id)
+            .collect();
+
+        assert!(queue.move_item(4, 1));
+        let after: Vec<_> = queue
+            .play_order()
+            .into_iter()
+            .map(|idx| queue.items()[idx].id)
+            .collect();
+        assert_eq!(before, after);
+    }
+
     #[test]
     fn shuffle_keeps_current_at_front() {
         let mut queue = Queue::new();
@@ -255,4 +683,157 @@ mod tests {
         assert!(queue.advance().is_none());
         assert!(queue.current().is_none());
     }
+
+    #[test]
+    fn repeat_off_stops_at_end() {
+        let mut queue = Queue::new();
+        queue.enqueue_back(track("one"));
+        queue.select_first();
+
+        assert_eq!(queue.repeat_mode(), RepeatMode::Off);
+        assert!(queue.advance().is_none());
+    }
+
+    #[test]
+    fn repeat_one_reselects_current() {
+        let mut queue = Queue::new();
+        queue.enqueue_back(track("one"));
+        queue.enqueue_back(track("two"));
+        queue.select_first();
+        queue.set_repeat_mode(RepeatMode::One);
+
+        assert_eq!(queue.advance_after_end().unwrap().track.title, "one");
+        assert_eq!(queue.advance_after_end().unwrap().track.title, "one");
+    }
+
+    #[test]
+    fn repeat_one_lets_skips_move_on() {
+        let mut queue = Queue::new();
+        queue.enqueue_back(track("one"));
+        queue.enqueue_back(track("two"));
+        queue.select_first();
+        queue.set_repeat_mode(RepeatMode::One);
+
+        assert_eq!(queue.advance().unwrap().track.title, "two");
+        assert_eq!(queue.advance().unwrap().track.title, "one");
+    }
+
+    #[test]
+    fn repeat_all_wraps_to_first() {
+        let mut queue = Queue::new();
+        queue.enqueue_back(track("one"));
+        queue.enqueue_back(track("two"));
+        queue.select_first();
+        queue.set_repeat_mode(RepeatMode::All);
+
+        assert_eq!(queue.advance().unwrap().track.title, "two");
+        assert_eq!(queue.advance().unwrap().track.title, "one");
+    }
+
+    #[test]
+    fn repeat_modes_cycle() {
+        let mode = RepeatMode::Off.cycle();
+        assert_eq!(mode, RepeatMode::All);
+        assert_eq!(mode.cycle(), RepeatMode::One);
+        assert_eq!(mode.cycle().cycle(), RepeatMode::Off);
+    }
+
+    fn shuffled_queue(count: usize) -> Queue {
+        let mut queue = Queue::new();
+        for i in 0..count {
+            queue.enqueue_back(track(&i.to_string()));
+        }
+        queue.set_shuffle_seed(Some(42));
+        queue
+    }
+
+    #[test]
+    fn shuffle_visits_every_item_once() {
+        let mut queue = shuffled_queue(10);
+        queue.select_index(3);
+        queue.set_shuffle(true);
+
+        let mut seen = vec![queue.current().unwrap().id];
+        while let Some(item) = queue.advance() {
+            seen.push(item.id);
+        }
+        assert_eq!(seen[0], QueueId(3));
+        let mut sorted = seen.clone();
+        sorted.sort_by_key(|id| id.0);
+        sorted.dedup();
+        assert_eq!(sorted.len(), 10);
+        assert_eq!(seen.len(), 10);
+    }
+
+    #[test]
+    fn shuffle_with_repeat_all_cycles_same_order() {
+        let mut queue = shuffled_queue(5);
+        queue.set_repeat_mode(RepeatMode::All);
+        queue.set_shuffle(true);
+        queue.select_first();
+
+        let mut first_cycle = vec![queue.current().unwrap().id];
+        for _ in 0..4 {
+            first_cycle.push(queue.advance().unwrap().id);
+        }
+        let mut second_cycle = Vec::new();
+        for _ in 0..5 {
+            second_cycle.push(queue.advance().unwrap().id);
+        }
+        assert_eq!(first_cycle, second_cycle);
+    }
+
+    #[test]
+    fn shuffle_is_reproducible_with_seed() {
+        let mut a = shuffled_queue(8);
+        let mut b = shuffled_queue(8);
+        a.set_shuffle(true);
+        b.set_shuffle(true);
+        assert_eq!(a.play_order(), b.play_order());
+    }
+
+    #[test]
+    fn previous_walks_shuffle_order() {
+        let mut queue = shuffled_queue(6);
+        queue.select_first();
+        queue.set_shuffle(true);
+        let start = queue.current().unwrap().id;
+        let next = queue.advance().unwrap().id;
+
+        assert_ne!(start, next);
+        assert_eq!(queue.previous().unwrap().id, start);
+        assert!(queue.previous().is_none());
+    }
+
+    #[test]
+    fn disabling_shuffle_restores_natural_order() {
+        let mut queue = shuffled_queue(6);
+        queue.select_index(2);
+        queue.set_shuffle(true);
+        queue.advance();
+        let current = queue.current().unwrap().id;
+
+        queue.set_shuffle(false);
+        assert_eq!(queue.current().unwrap().id, current);
+        assert_eq!(queue.play_order(), (0..6).collect::<Vec<_>>());
+        let idx = queue.index_of(current).unwrap();
+        let expected = queue.items().get(idx + 1).map(|item| item.id);
+        assert_eq!(queue.advance().map(|item| item.id), expected);
+    }
+
+    #[test]
+    fn shuffle_order_tracks_queue_edits() {
+        let mut queue = shuffled_queue(4);
+        queue.select_first();
+        queue.set_shuffle(true);
+        let inserted = queue.enqueue_next(track("next"));
+        queue.enqueue_back(track("last"));
+
+        assert_eq!(queue.advance().unwrap().id, inserted);
+        let removed = queue.items()[1].id;
+        queue.remove(removed);
+        let mut order = queue.play_order();
+        order.sort_unstable();
+        assert_eq!(order, (0..queue.len()).collect::<Vec<_>>());
+    }
 }
@@ -3,11 +3,12 @@
 //! Handles saving and loading the playback queue state to survive restarts.
 //! Includes corruption handling and last-known-good backups.
 
-use crate::queue::{Queue, QueueId, QueueItem};
+use crate::queue::{Queue, QueueId, QueueItem, RepeatMode};
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::io::{self, BufReader, BufWriter};
 use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 use thiserror::Error;
 use tunez_core::Track;
 
@@ -22,6 +23,9 @@ const MAX_QUEUE_ITEMS: usize = 10_000;
 /// Prevents loading extremely large files that could exhaust memory.
 const MAX_QUEUE_FILE_SIZE: u64 = 10 * 1024 * 1024;
 
+/// Default for how long [`QueueSaver`] lets changes collect before writing.
+pub const DEFAULT_SAVE_INTERVAL: Duration = Duration::from_secs(5);
+
 /// Queue persistence errors.
 #[derive(Debug, Error)]
 pub enum QueuePersistenceError {
@@ -56,6 +60,12 @@ struct PersistedQueue {
     items: Vec<PersistedQueueItem>,
     current_index: Option<usize>,
     next_id: u64,
+    /// Added after version 1 shipped; missing in older files means `Off`.
+    #[serde(default)]
+    repeat_mode: RepeatMode,
+    /// Only the flag is kept; a fresh order is drawn on load.
+    #[serde(default)]
+    shuffle: bool,
 }
 
 /// Serialized queue item.
@@ -261,6 +271,8 @@ impl QueuePersistence {
             items,
             current_index,
             next_id: queue.next_id(),
+            repeat_mode: queue.repeat_mode(),
+            shuffle: queue.is_shuffled(),
         }
     }
 
@@ -275,7 +287,10 @@ impl QueuePersistence {
             })
             .collect();
 
-        Queue::from_persisted(items, persisted.current_index, persisted.next_id)
+        let mut queue = Queue::from_persisted(items, persisted.current_index, persisted.next_id);
+        queue.set_repeat_mode(persisted.repeat_mode);
+        queue.set_shuffle(persisted.shuffle);
+        queue
     }
 
     /// Check if a persisted queue exists.
@@ -298,6 +313,64 @@ impl QueuePersistence {
     }
 }
 
+/// Saves the queue at most once per interval.
+///
+/// Every skip or enqueue changes the queue, and each save rewrites the whole
+/// file plus its backup. Changes only mark the queue dirty; `tick` writes it
+/// once the oldest unsaved change is `interval` old, and `flush` writes it
+/// right away (when the UI exits), so the last change is never lost.
+#[derive(Debug)]
+pub struct QueueSaver {
+    persistence: QueuePersistence,
+    interval: Duration,
+    /// When the queue first changed since the last write.
+    dirty_since: Option<Instant>,
+}
+
+impl QueueSaver {
+    pub fn new(persistence: QueuePersistence, interval: Duration) -> Self {
+        Self {
+            persistence,
+            interval,
+            dirty_since: None,
+        }
+    }
+
+    pub fn persistence(&self) -> &QueuePersistence {
+        &self.persistence
+    }
+
+    /// Note that the queue changed and needs saving.
+    pub fn mark_dirty(&mut self, now: Instant) {
+        self.dirty_since.get_or_insert(now);
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.dirty_since.is_some()
+    }
+
+    /// Write `queue` if it has had unsaved changes for a full interval. A
+    /// failed write is tried again an interval later.
+    pub fn tick(&mut self, queue: &Queue, now: Instant) -> QueuePersistenceResult<()> {
+        match self.dirty_since {
+            Some(since) if now.duration_since(since) >= self.interval => self
+                .flush(queue)
+                .inspect_err(|_| self.dirty_since = Some(now)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Write `queue` now if it has unsaved changes.
+    pub fn flush(&mut self, queue: &Queue) -> QueuePersistenceResult<()> {
+        if self.dirty_since.is_none() {
+            return Ok(());
+        }
+        self.persistence.save(queue)?;
+        self.dirty_since = None;
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -313,7 +386,55 @@ mod tests {
             album: Some("Test Album".into()),
             duration_seconds: Some(180),
             track_number: Some(1),
+            disc_number: None,
+            year: None,
+            genre: None,
+            musicbrainz_id: None,
+            replay_gain: Default::default(),
+        }
+    }
+
+    #[test]
+    fn rapid_changes_are_written_once_with_the_latest_queue() {
+        let dir = tempdir().unwrap();
+        let mut saver = QueueSaver::new(QueuePersistence::new(dir.path()), DEFAULT_SAVE_INTERVAL);
+        let start = Instant::now();
+
+        let mut queue = Queue::new();
+        for (i, id) in ["1", "2", "3"].into_iter().enumerate() {
+            queue.enqueue_back(test_track(id));
+            let now = start + Duration::from_millis(100 * i as u64);
+            saver.mark_dirty(now);
+            saver.tick(&queue, now).unwrap();
         }
+        assert!(!saver.persistence().exists());
+
+        saver.tick(&queue, start + DEFAULT_SAVE_INTERVAL).unwrap();
+        assert!(!saver.is_dirty());
+        assert_eq!(saver.persistence().load().unwrap().len(), 3);
+
+        // Nothing changed since, so ticking and flushing don't write again.
+        saver.persistence().clear().unwrap();
+        saver
+            .tick(&queue, start + DEFAULT_SAVE_INTERVAL * 3)
+            .unwrap();
+        saver.flush(&queue).unwrap();
+        assert!(!saver.persistence().exists());
+    }
+
+    #[test]
+    fn flush_writes_a_change_inside_the_interval() {
+        let dir = tempdir().unwrap();
+        let mut saver = QueueSaver::new(QueuePersistence::new(dir.path()), DEFAULT_SAVE_INTERVAL);
+        let mut queue = Queue::new();
+        queue.enqueue_back(test_track("1"));
+        let now = Instant::now();
+        saver.mark_dirty(now);
+        saver.tick(&queue, now).unwrap();
+        assert!(!saver.persistence().exists());
+
+        saver.flush(&queue).unwrap();
+        assert_eq!(saver.persistence().load().unwrap().len(), 1);
     }
 
     #[test]
@@ -431,6 +552,8 @@ mod tests {
             items,
             current_index: None,
             next_id: (MAX_QUEUE_ITEMS + 1) as u64,
+            repeat_mode: RepeatMode::Off,
+            shuffle: false,
         };
         let json = serde_json::to_string(&persisted).unwrap();
         fs::write(&persistence.queue_path, json).unwrap();
@@ -439,4 +562,86 @@ mod tests {
         let loaded = persistence.load().unwrap();
         assert!(loaded.is_empty());
     }
+
+    #[test]
+    fn repeat_mode_roundtrips() {
+        let dir = tempdir().unwrap();
+        let persistence = QueuePersistence::new(dir.path());
+
+        let mut queue = Queue::new();
+        queue.enqueue_back(test_track("1"));
+        queue.set_repeat_mode(RepeatMode::All);
+        persistence.save(&queue).unwrap();
+
+        let loaded = persistence.load().unwrap();
+        assert_eq!(loaded.repeat_mode(), RepeatMode::All);
+    }
+
+    #[test]
+    fn shuffle_flag_roundtrips_and_keeps_current() {
+        let dir = tempdir().unwrap();
+        let persistence = QueuePersistence::new(dir.path());
+
+        let mut queue = Queue::new();
+        for id in ["1", "2", "3"] {
+            queue.enqueue_back(test_track(id));
+        }
+        queue.select_index(1);
+        queue.set_shuffle(true);
+        persistence.save(&queue).unwrap();
+
+        let loaded = persistence.load().unwrap();
+        assert!(loaded.is_shuffled());
+        assert_eq!(loaded.current().unwrap().track.id.0, "2");
+        assert_eq!(loaded.play_order()[0], 1);
+    }
+
+    #[test]
+    fn playback_modes_roundtrip_together() {
+        let dir = tempdir().unwrap();
+        let persistence = QueuePersistence::new(dir.path());
+
+        let mut queue = Queue::new();
+        queue.enqueue_back(test_track("1"));
+        queue.enqueue_back(test_track("2"));
+        queue.set_repeat_mode(RepeatMode::One);
+        queue.set_shuffle(true);
+        persistence.save(&queue).unwrap();
+
+        let loaded = QueuePersistence::new(dir.path()).load().unwrap();
+        assert_eq!(loaded.repeat_mode(), RepeatMode::One);
+        assert!(loaded.is_shuffled());
+    }
+
+    #[test]
+    fn version_one_file_without_repeat_mode_loads() {
+        let dir = tempdir().unwrap();
+        let persistence = QueuePersistence::new(dir.path());
+        fs::write(
+            &persistence.queue_path,
+            r#"{"version":1,"items":[],"current_index":null,"next_id":0}"#,
+        )
+        .unwrap();
+
+        let loaded = persistence.try_load(&persistence.queue_path).unwrap();
+        assert_eq!(loaded.repeat_mode(), RepeatMode::Off);
+        assert!(!loaded.is_shuffled());
+    }
+
+    #[test]
+    fn tracks_saved_before_extended_metadata_load() {
+        let dir = tempdir().unwrap();
+        let persistence = QueuePersistence::new(dir.path());
+        fs::write(
+            &persistence.queue_path,
+            r#"{"version":1,"items":[{"id":0,"track":{"id":"t1","provider_id":"filesystem","title":"Old","artist":"Artist","album":null,"duration_seconds":null,"track_number":3}}],"current_index":0,"next_id":1}"#,
+        )
+        .unwrap();
+
+        let loaded = persistence.try_load(&persistence.queue_path).unwrap();
+        let track = &loaded.items()[0].track;
+        assert_eq!(track.track_number, Some(3));
+        assert_eq!(track.disc_number, None);
+        assert_eq!(track.genre, None);
+    }
 }
@@ -0,0 +1,50 @@
+//! Writing plays to disk without holding up playback.
+
+use std::sync::mpsc;
+use std::thread::JoinHandle;
+use tunez_core::Track;
+
+/// Hands tracks (or whatever else is recorded) to a writer on its own
+/// thread, one at a time so no write is lost or reordered. Dropping it waits
+/// for the writes already handed over.
+pub(crate) struct PlayRecorder<T = Track> {
+    tx: Option<mpsc::Sender<T>>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl<T: Send + 'static> PlayRecorder<T> {
+    pub(crate) fn spawn(mut write: impl FnMut(&T) + Send + 'static) -> Self {
+        let (tx, rx) = mpsc::channel::<T>();
+        let worker = std::thread::spawn(move || {
+            for item in rx {
+                write(&item);
+            }
+        });
+        Self {
+            tx: Some(tx),
+            worker: Some(worker),
+        }
+    }
+
+    /// Record `item`; returns straight away.
+    pub(crate) fn record(&self, item: T) {
+        if let Some(tx) = &self.tx {
+            let _ = tx.send(item);
+        }
+    }
+}
+
+impl<T> Drop for PlayRecorder<T> {
+    fn drop(&mut self) {
+        drop(self.tx.take());
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for PlayRecorder<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PlayRecorder").finish_non_exhaustive()
+    }
+}
@@ -7,11 +7,15 @@ use crate::{Player, PlayerState, QueueItem};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tunez_core::{
-    PlaybackProgress, PlaybackState as ScrobblePlaybackState, ScrobbleEvent, Scrobbler,
+    meets_scrobble_threshold, PlaybackProgress, PlaybackState as ScrobblePlaybackState,
+    ScrobbleEvent, Scrobbler, ScrobblerError, TrackId,
 };
 
+/// How often an unchanged track's now-playing update is repeated.
+const NOW_PLAYING_REFRESH: Duration = Duration::from_secs(30);
+
 /// Type alias for error callbacks.
-pub type ErrorCallback = Arc<dyn Fn(&str) + Send + Sync>;
+pub type ErrorCallback = Arc<dyn Fn(&ScrobblerError) + Send + Sync>;
 
 /// Manages scrobbling for a player, ensuring failures don't interrupt playback.
 pub struct ScrobblerManager {
@@ -21,6 +25,11 @@ pub struct ScrobblerManager {
     tick_interval: Duration,
     last_tick: Option<Instant>,
     last_position: u64,
+    /// Seconds of the current track actually heard: the position's advance
+    /// between ticks, not counting seeks.
+    listened: u64,
+    /// Track of the last now-playing update, and when it was sent.
+    now_playing: Option<(TrackId, Instant)>,
     /// Whether scrobbling is enabled for the current session
     enabled: bool,
     /// Callback for error notifications
@@ -45,6 +54,8 @@ impl std::fmt::Debug for ScrobblerManager {
 impl ScrobblerManager {
     /// Create a new scrobbler manager.
     ///
+    /// Progress updates are sent at the scrobbler's [`Scrobbler::desired_tick`].
+    ///
     /// # Arguments
     /// * `scrobbler` - Optional scrobbler implementation (None = disabled)
     /// * `player_name` - Name of the player (e.g., "Tunez")
@@ -54,13 +65,18 @@ impl ScrobblerManager {
         player_name: impl Into<String>,
         device_id: Option<String>,
     ) -> Self {
+        let tick_interval = scrobbler
+            .as_ref()
+            .map_or(Duration::from_secs(1), |s| s.desired_tick());
         Self {
             scrobbler,
             player_name: player_name.into(),
             device_id,
-            tick_interval: Duration::from_secs(1),
+            tick_interval,
             last_tick: None,
             last_position: 0,
+            listened: 0,
+            now_playing: None,
             enabled: false,
             error_callback: None,
         }
@@ -69,7 +85,7 @@ impl ScrobblerManager {
     /// Set a callback for error notifications.
     pub fn set_error_callback<F>(&mut self, callback: F)
     where
-        F: Fn(&str) + Send + Sync + 'static,
+        F: Fn(&ScrobblerError) + Send + Sync + 'static,
     {
         self.error_callback = Some(Arc::new(callback));
     }
@@ -81,7 +97,12 @@ impl ScrobblerManager {
 
     /// Check if scrobbling is enabled and configured.
     pub fn is_active(&self) -> bool {
-        self.enabled && self.scrobbler.is_some()
+        self.enabled && self.is_configured()
+    }
+
+    /// Whether there is a scrobbler to send plays to at all.
+    pub fn is_configured(&self) -> bool {
+        self.scrobbler.is_some()
     }
 
     /// Notify the scrobbler of a playback state transition.
@@ -92,30 +113,51 @@ impl ScrobblerManager {
     /// - Playback is paused (Paused)
     /// - Playback is stopped (Stopped)
     /// - Track ends naturally (Ended)
+    ///
+    /// A stop, which includes skipping to another track, scrobbles the track
+    /// if enough of it was heard.
     pub fn on_state_change(&mut self, player: &Player, state: ScrobblePlaybackState) {
         if !self.is_active() {
             return;
         }
 
         if let Some(current) = player.current() {
-            self.submit_event(current, state, self.last_position);
+            self.submit_event(current, state, self.last_position, false);
         }
 
-        // Reset tick tracking on state changes
-        if matches!(state, ScrobblePlaybackState::Started) {
-            self.last_tick = Some(Instant::now());
-            self.last_position = 0;
+        match state {
+            // Reset tick tracking, and the time heard if the track changed
+            ScrobblePlaybackState::Started => {
+                let now = Instant::now();
+                let current = player.current().map(|c| c.track.id.clone());
+                if self.now_playing.as_ref().map(|(id, _)| id) != current.as_ref() {
+                    self.listened = 0;
+                }
+                self.last_tick = Some(now);
+                self.last_position = player.position().as_secs();
+                self.now_playing = current.map(|id| (id, now));
+            }
+            ScrobblePlaybackState::Stopped => self.finish_listen(player, 0),
+            _ => {}
         }
     }
 
-    /// Process a playback tick (called at ~1 second intervals during playback).
+    /// Process a playback tick (called at least every [`tick_interval`] during playback).
     ///
     /// This method:
-    /// 1. Checks if enough time has passed since the last scrobble update
-    /// 2. If so, submits a progress update to the scrobbler
+    /// 1. Records the playback position once per tick interval
+    /// 2. Repeats the now-playing update if the track changed or
+    ///    [`NOW_PLAYING_REFRESH`] has passed since the last one
     ///
-    /// Returns true if a scrobble was submitted (or attempted).
+    /// Returns true if a now-playing update was submitted (or attempted).
+    ///
+    /// [`tick_interval`]: Self::tick_interval
     pub fn tick(&mut self, player: &Player, position_seconds: u64) -> bool {
+        self.tick_at(player, position_seconds, Instant::now())
+    }
+
+    /// [`tick`](Self::tick) with the current time supplied by the caller.
+    fn tick_at(&mut self, player: &Player, position_seconds: u64, now: Instant) -> bool {
         if !self.is_active() {
             return false;
         }
@@ -126,7 +168,6 @@ impl ScrobblerManager {
         }
 
         // Check if we should submit based on tick interval
-        let now = Instant::now();
         let should_tick = match self.last_tick {
             Some(last) => now.duration_since(last) >= self.tick_interval,
             None => true,
@@ -136,34 +177,112 @@ impl ScrobblerManager {
             return false;
         }
 
+        let heard = self.heard_until(position_seconds, now);
         self.last_tick = Some(now);
         self.last_position = position_seconds;
 
-        // Submit progress update (the scrobbler decides what to do with it)
-        if let Some(current) = player.current() {
-            // For periodic ticks during playback, we don't change state
-            // The scrobbler will receive position updates to track progress
-            self.submit_event(current, ScrobblePlaybackState::Started, position_seconds);
-            return true;
+        let Some(current) = player.current() else {
+            return false;
+        };
+        let new_track = self
+            .now_playing
+            .as_ref()
+            .is_none_or(|(id, _)| *id != current.track.id);
+        if new_track {
+            self.listened = 0;
+        } else {
+            self.listened += heard;
+        }
+        let due = match &self.now_playing {
+            Some((id, sent)) => {
+                *id != current.track.id || now.duration_since(*sent) >= NOW_PLAYING_REFRESH
+            }
+            None => true,
+        };
+        if !due {
+            return false;
         }
+        self.now_playing = Some((current.track.id.clone(), now));
+        self.submit_event(
+            current,
+            ScrobblePlaybackState::Started,
+            position_seconds,
+            true,
+        );
+        true
+    }
+
+    /// Record a seek so the next progress update reports the new position.
+    /// The jump itself doesn't count as time heard.
+    pub fn on_seek(&mut self, position_seconds: u64) {
+        self.last_position = position_seconds;
+    }
 
-        false
+    /// Seconds heard between the last sampled position and `position` at
+    /// `now`. Bounded by the time that actually passed, so a jump nobody
+    /// reported through [`on_seek`](Self::on_seek) isn't counted either.
+    fn heard_until(&self, position: u64, now: Instant) -> u64 {
+        let Some(last) = self.last_tick else {
+            return 0;
+        };
+        let elapsed = now.duration_since(last).as_secs() + 1;
+        position.saturating_sub(self.last_position).min(elapsed)
     }
 
-    /// Notify the scrobbler that a track has ended (reached its natural end).
+    /// Notify the scrobbler that a track played to its end, as the audio
+    /// engine reports it through [`Player::poll_completion`] or a gapless
+    /// handoff.
+    ///
+    /// The listen is only submitted if the time actually heard meets the
+    /// scrobble threshold (half the track or four minutes).
     pub fn on_track_ended(&mut self, player: &Player) {
+        self.on_track_ended_at(player, Instant::now());
+    }
+
+    /// [`on_track_ended`](Self::on_track_ended) with the current time
+    /// supplied by the caller.
+    fn on_track_ended_at(&mut self, player: &Player, now: Instant) {
         if !self.is_active() {
             return;
         }
+        // Ticks only sample the position now and then; count the stretch
+        // from the last sample to the end too.
+        let tail = player
+            .current()
+            .and_then(|c| c.track.duration_seconds)
+            .map_or(0, |duration| self.heard_until(u64::from(duration), now));
+        self.finish_listen(player, tail);
+    }
 
-        if let Some(current) = player.current() {
-            let duration = current.track.duration_seconds.unwrap_or(0) as u64;
-            self.submit_event(current, ScrobblePlaybackState::Ended, duration);
+    /// Scrobble the current track if, with `tail` more seconds, enough of it
+    /// was heard, and start counting afresh.
+    fn finish_listen(&mut self, player: &Player, tail: u64) {
+        let played = std::mem::take(&mut self.listened) + tail;
+        let Some(current) = player.current() else {
+            return;
+        };
+        let duration = current.track.duration_seconds.map(u64::from);
+        if !meets_scrobble_threshold(played, duration) {
+            tracing::debug!(
+                track = %current.track.title,
+                played,
+                duration = ?duration,
+                "play below scrobble threshold; not submitting"
+            );
+            return;
         }
+        self.submit_event(current, ScrobblePlaybackState::Ended, played, false);
     }
 
-    /// Submit a scrobble event, handling errors gracefully.
-    fn submit_event(&self, item: &QueueItem, state: ScrobblePlaybackState, position: u64) {
+    /// Submit a scrobble event, handling errors gracefully. `progress` marks
+    /// a periodic refresh rather than a playback transition.
+    fn submit_event(
+        &self,
+        item: &QueueItem,
+        state: ScrobblePlaybackState,
+        position: u64,
+        progress: bool,
+    ) {
         let Some(scrobbler) = &self.scrobbler else {
             return;
         };
@@ -177,6 +296,7 @@ impl ScrobblerManager {
             state,
             player_name: self.player_name.clone(),
             device_id: self.device_id.clone(),
+            occurred_at: Some(ScrobbleEvent::now()),
         };
 
         // Submit via background task, never interrupt playback
@@ -185,7 +305,12 @@ impl ScrobblerManager {
         let track_title = item.track.title.clone(); // Clone for logging inside async block
 
         tokio::spawn(async move {
-            if let Err(e) = scrobbler.submit(&event).await {
+            let result = if progress {
+                scrobbler.submit_progress(&event).await
+            } else {
+                scrobbler.submit(&event).await
+            };
+            if let Err(e) = result {
                 tracing::warn!(
                     scrobbler_id = scrobbler.id(),
                     error = %e,
@@ -195,7 +320,7 @@ impl ScrobblerManager {
 
                 // Notify via callback (for UI indicator)
                 if let Some(cb) = callback {
-                    cb(&format!("Scrobble failed: {}", e));
+                    cb(&e);
                 }
             }
         });
@@ -218,13 +343,19 @@ mod tests {
     struct MockScrobbler {
         submissions: Mutex<Vec<ScrobbleEvent>>,
         fail_count: AtomicUsize,
+        tick: Duration,
     }
 
     impl MockScrobbler {
         fn new() -> Self {
+            Self::with_tick(Duration::from_secs(1))
+        }
+
+        fn with_tick(tick: Duration) -> Self {
             Self {
                 submissions: Mutex::new(Vec::new()),
                 fail_count: AtomicUsize::new(0),
+                tick,
             }
         }
 
@@ -243,6 +374,10 @@ mod tests {
             "mock"
         }
 
+        fn desired_tick(&self) -> Duration {
+            self.tick
+        }
+
         async fn submit(&self, event: &ScrobbleEvent) -> ScrobblerResult<()> {
             let fail = self.fail_count.load(Ordering::SeqCst);
             if fail > 0 {
@@ -265,6 +400,11 @@ mod tests {
             album: None,
             duration_seconds: Some(180),
             track_number: None,
+            disc_number: None,
+            year: None,
+            genre: None,
+            musicbrainz_id: None,
+            replay_gain: Default::default(),
         }
     }
 
@@ -320,6 +460,213 @@ mod tests {
         // No panic is the main success criterion
     }
 
+    #[tokio::test]
+    async fn progress_updates_follow_desired_tick() {
+        let scrobbler = Arc::new(MockScrobbler::with_tick(Duration::from_secs(5)));
+        let mut manager = ScrobblerManager::new(Some(scrobbler.clone()), "Tunez", None);
+        manager.set_enabled(true);
+        assert_eq!(manager.tick_interval(), Duration::from_secs(5));
+
+        let mut player = Player::new();
+        player.queue_mut().enqueue_back(test_track("Test Song"));
+        player.play();
+
+        let start = Instant::now();
+        assert!(manager.tick_at(&player, 0, start));
+        for secs in 1..5 {
+            manager.tick_at(&player, secs, start + Duration::from_secs(secs));
+            assert_eq!(manager.last_position, 0, "sampled after only {secs}s");
+        }
+        manager.tick_at(&player, 5, start + Duration::from_secs(5));
+        assert_eq!(manager.last_position, 5);
+        manager.tick_at(&player, 6, start + Duration::from_secs(6));
+        assert_eq!(manager.last_position, 5);
+
+        // The 30s now-playing refresh is measured in whole ticks too.
+        assert!(!manager.tick_at(&player, 29, start + Duration::from_secs(29)));
+        assert!(manager.tick_at(&player, 35, start + Duration::from_secs(35)));
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let positions: Vec<u64> = scrobbler
+            .submissions()
+            .iter()
+            .map(|e| e.progress.position_seconds)
+            .collect();
+        assert_eq!(positions, [0, 35]);
+    }
+
+    #[tokio::test]
+    async fn repeated_ticks_do_not_repeat_now_playing() {
+        let scrobbler = Arc::new(MockScrobbler::new());
+        let mut manager = ScrobblerManager::new(Some(scrobbler.clone()), "Tunez", None);
+        manager.set_enabled(true);
+        let mut player = Player::new();
+        player.queue_mut().enqueue_back(test_track("One"));
+        player.queue_mut().enqueue_back(test_track("Two"));
+        player.play();
+
+        manager.on_state_change(&player, ScrobblePlaybackState::Started);
+        let start = Instant::now();
+        for secs in 1..=10 {
+            manager.tick_at(&player, secs, start + Duration::from_secs(secs));
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(
+            scrobbler.submissions().len() <= 2,
+            "ten ticks sent {} events",
+            scrobbler.submissions().len()
+        );
+        assert_eq!(manager.last_position, 10);
+
+        // A new track is announced on the next tick even without a Started event.
+        player.skip_next();
+        assert!(manager.tick_at(&player, 0, start + Duration::from_secs(11)));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let titles: Vec<String> = scrobbler
+            .submissions()
+            .into_iter()
+            .map(|e| e.track.title)
+            .collect();
+        assert_eq!(titles.last().map(String::as_str), Some("Two"));
+    }
+
+    fn ended_submissions(scrobbler: &MockScrobbler) -> usize {
+        scrobbler
+            .submissions()
+            .iter()
+            .filter(|e| e.state == ScrobblePlaybackState::Ended)
+            .count()
+    }
+
+    fn four_minute_player() -> Player {
+        let mut track = test_track("Long Song");
+        track.duration_seconds = Some(240);
+        let mut player = Player::new();
+        player.queue_mut().enqueue_back(track);
+        player.play();
+        player
+    }
+
+    fn ended_positions(scrobbler: &MockScrobbler) -> Vec<u64> {
+        scrobbler
+            .submissions()
+            .iter()
+            .filter(|e| e.state == ScrobblePlaybackState::Ended)
+            .map(|e| e.progress.position_seconds)
+            .collect()
+    }
+
+    /// Tick once a second from `from` to `to` seconds into the track.
+    fn play_through(
+        manager: &mut ScrobblerManager,
+        player: &Player,
+        start: Instant,
+        from: u64,
+        to: u64,
+    ) {
+        for secs in from..=to {
+            manager.tick_at(player, secs, start + Duration::from_secs(secs));
+        }
+    }
+
+    #[tokio::test]
+    async fn short_play_is_not_scrobbled() {
+        let scrobbler = Arc::new(MockScrobbler::new());
+        let mut manager = ScrobblerManager::new(Some(scrobbler.clone()), "Tunez", None);
+        manager.set_enabled(true);
+        let player = four_minute_player();
+
+        manager.tick(&player, 30);
+        manager.on_track_ended(&player);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        assert_eq!(ended_submissions(&scrobbler), 0);
+    }
+
+    #[tokio::test]
+    async fn short_play_of_unknown_length_is_not_scrobbled() {
+        let scrobbler = Arc::new(MockScrobbler::new());
+        let mut manager = ScrobblerManager::new(Some(scrobbler.clone()), "Tunez", None);
+        manager.set_enabled(true);
+        let mut jingle = test_track("Jingle");
+        jingle.duration_seconds = None;
+        let mut player = Player::new();
+        player.queue_mut().enqueue_back(jingle);
+        player.play();
+
+        let start = Instant::now();
+        play_through(&mut manager, &player, start, 0, 30);
+        manager.on_track_ended_at(&player, start + Duration::from_secs(31));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        assert_eq!(ended_submissions(&scrobbler), 0);
+    }
+
+    #[tokio::test]
+    async fn play_past_half_is_scrobbled() {
+        let scrobbler = Arc::new(MockScrobbler::new());
+        let mut manager = ScrobblerManager::new(Some(scrobbler.clone()), "Tunez", None);
+        manager.set_enabled(true);
+        let player = four_minute_player();
+
+        let start = Instant::now();
+        play_through(&mut manager, &player, start, 0, 150);
+        // Skipping on counts what was heard too.
+        manager.on_state_change(&player, ScrobblePlaybackState::Stopped);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        assert_eq!(ended_positions(&scrobbler), [150]);
+    }
+
+    #[tokio::test]
+    async fn a_track_that_plays_out_counts_the_time_after_the_last_tick() {
+        let scrobbler = Arc::new(MockScrobbler::with_tick(Duration::from_secs(5)));
+        let mut manager = ScrobblerManager::new(Some(scrobbler.clone()), "Tunez", None);
+        manager.set_enabled(true);
+        let player = four_minute_player();
+
+        let start = Instant::now();
+        play_through(&mut manager, &player, start, 0, 238);
+        assert_eq!(manager.last_position, 235);
+        manager.on_track_ended_at(&player, start + Duration::from_secs(240));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        assert_eq!(ended_positions(&scrobbler), [240]);
+    }
+
+    #[tokio::test]
+    async fn seeking_to_the_end_is_not_scrobbled() {
+        let scrobbler = Arc::new(MockScrobbler::new());
+        let mut manager = ScrobblerManager::new(Some(scrobbler.clone()), "Tunez", None);
+        manager.set_enabled(true);
+        let player = four_minute_player();
+
+        let start = Instant::now();
+        play_through(&mut manager, &player, start, 0, 10);
+        manager.on_seek(230);
+        for secs in 231..=239 {
+            manager.tick_at(&player, secs, start + Duration::from_secs(secs - 220));
+        }
+        manager.on_track_ended_at(&player, start + Duration::from_secs(20));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        assert_eq!(ended_submissions(&scrobbler), 0);
+    }
+
+    #[tokio::test]
+    async fn an_unreported_jump_is_not_counted_as_heard() {
+        let scrobbler = Arc::new(MockScrobbler::new());
+        let mut manager = ScrobblerManager::new(Some(scrobbler.clone()), "Tunez", None);
+        manager.set_enabled(true);
+        let player = four_minute_player();
+
+        // The position leaps two minutes in one second, as when audio is cut off.
+        let start = Instant::now();
+        play_through(&mut manager, &player, start, 0, 10);
+        manager.tick_at(&player, 130, start + Duration::from_secs(11));
+        assert!(manager.listened <= 12, "counted {}s", manager.listened);
+    }
+
     #[tokio::test]
     async fn disabled_scrobbler_does_not_submit() {
         let scrobbler = Arc::new(MockScrobbler::new());
@@ -1,15 +1,17 @@
 //! Plugin provider adapter that implements the Provider trait for external plugins.
 
 use crate::host::{ExecPluginHost, PluginConfig, PluginHostError};
-use crate::protocol::{PluginMethod, PluginResult};
+use crate::protocol::{PluginError, PluginInfo, PluginMethod, PluginResult};
+use base64::Engine;
 use std::sync::RwLock;
 use tunez_core::models::{
-    Album, AlbumId, Page, PageRequest, Playlist, PlaylistId, StreamUrl, Track, TrackId,
+    Album, AlbumId, Artwork, Page, PageRequest, Playlist, PlaylistId, StreamUrl, Track, TrackId,
 };
 use tunez_core::provider::{
     BrowseKind, CollectionItem, Provider, ProviderCapabilities, ProviderError, ProviderResult,
     TrackSearchFilters,
 };
+use tunez_core::redact_error;
 
 /// A provider backed by an external plugin process.
 ///
@@ -19,6 +21,7 @@ pub struct ExecPluginProvider {
     host: ExecPluginHost,
     id: String,
     name: String,
+    info: PluginInfo,
     capabilities: RwLock<Option<ProviderCapabilities>>,
 }
 
@@ -30,8 +33,9 @@ impl ExecPluginProvider {
 
         Ok(Self {
             host,
-            id: info.id,
-            name: info.name,
+            id: info.id.clone(),
+            name: info.name.clone(),
+            info,
             capabilities: RwLock::new(None),
         })
     }
@@ -44,7 +48,8 @@ impl ExecPluginProvider {
         Ok(Self {
             host,
             id,
-            name: info.name,
+            name: info.name.clone(),
+            info,
             capabilities: RwLock::new(None),
         })
     }
@@ -59,14 +64,33 @@ impl ExecPluginProvider {
         self.host.is_running()
     }
 
+    /// Send `method` to the plugin, or fail with `NotSupported` without
+    /// asking when the plugin didn't list it at initialization.
+    fn request(&self, method: PluginMethod) -> ProviderResult<PluginResult> {
+        if !self.info.supports(&method) {
+            return Err(ProviderError::NotSupported {
+                operation: method.name().to_string(),
+            });
+        }
+        self.host.send_request(method).map_err(Self::map_host_error)
+    }
+
     fn map_host_error(err: PluginHostError) -> ProviderError {
         match err {
-            PluginHostError::PluginError(msg) => ProviderError::Other { message: msg },
+            PluginHostError::PluginError(err) => PluginError {
+                message: redact_error(&err.message),
+                ..err
+            }
+            .into(),
+            PluginHostError::NotSupported(operation) => ProviderError::NotSupported { operation },
             PluginHostError::ProcessTerminated => ProviderError::NetworkError {
                 message: "plugin process terminated".to_string(),
             },
+            err @ PluginHostError::Timeout(_) => ProviderError::NetworkError {
+                message: err.to_string(),
+            },
             other => ProviderError::Other {
-                message: other.to_string(),
+                message: redact_error(other),
             },
         }
     }
@@ -114,14 +138,11 @@ impl Provider for ExecPluginProvider {
         filters: TrackSearchFilters,
         paging: PageRequest,
     ) -> ProviderResult<Page<Track>> {
-        let result = self
-            .host
-            .send_request(PluginMethod::SearchTracks {
-                query: query.to_string(),
-                filters,
-                paging,
-            })
-            .map_err(Self::map_host_error)?;
+        let result = self.request(PluginMethod::SearchTracks {
+            query: query.to_string(),
+            filters,
+            paging: paging.normalized(),
+        })?;
 
         match result {
             PluginResult::Tracks(page) => Ok(page),
@@ -137,10 +158,10 @@ impl Provider for ExecPluginProvider {
         kind: BrowseKind,
         paging: PageRequest,
     ) -> ProviderResult<Page<CollectionItem>> {
-        let result = self
-            .host
-            .send_request(PluginMethod::Browse { kind, paging })
-            .map_err(Self::map_host_error)?;
+        let result = self.request(PluginMethod::Browse {
+            kind,
+            paging: paging.normalized(),
+        })?;
 
         match result {
             PluginResult::CollectionItems(page) => Ok(page),
@@ -152,10 +173,9 @@ impl Provider for ExecPluginProvider {
     }
 
     fn list_playlists(&self, paging: PageRequest) -> ProviderResult<Page<Playlist>> {
-        let result = self
-            .host
-            .send_request(PluginMethod::ListPlaylists { paging })
-            .map_err(Self::map_host_error)?;
+        let result = self.request(PluginMethod::ListPlaylists {
+            paging: paging.normalized(),
+        })?;
 
         match result {
             PluginResult::Playlists(page) => Ok(page),
@@ -167,13 +187,10 @@ impl Provider for ExecPluginProvider {
     }
 
     fn search_playlists(&self, query: &str, paging: PageRequest) -> ProviderResult<Page<Playlist>> {
-        let result = self
-            .host
-            .send_request(PluginMethod::SearchPlaylists {
-                query: query.to_string(),
-                paging,
-            })
-            .map_err(Self::map_host_error)?;
+        let result = self.request(PluginMethod::SearchPlaylists {
+            query: query.to_string(),
+            paging: paging.normalized(),
+        })?;
 
         match result {
             PluginResult::Playlists(page) => Ok(page),
@@ -185,12 +202,9 @@ impl Provider for ExecPluginProvider {
     }
 
     fn get_playlist(&self, playlist_id: &PlaylistId) -> ProviderResult<Playlist> {
-        let result = self
-            .host
-            .send_request(PluginMethod::GetPlaylist {
-                playlist_id: playlist_id.clone(),
-            })
-            .map_err(Self::map_host_error)?;
+        let result = self.request(PluginMethod::GetPlaylist {
+            playlist_id: playlist_id.clone(),
+        })?;
 
         match result {
             PluginResult::Playlist(playlist) => Ok(playlist),
@@ -206,13 +220,10 @@ impl Provider for ExecPluginProvider {
         playlist_id: &PlaylistId,
         paging: PageRequest,
     ) -> ProviderResult<Page<Track>> {
-        let result = self
-            .host
-            .send_request(PluginMethod::ListPlaylistTracks {
-                playlist_id: playlist_id.clone(),
-                paging,
-            })
-            .map_err(Self::map_host_error)?;
+        let result = self.request(PluginMethod::ListPlaylistTracks {
+            playlist_id: playlist_id.clone(),
+            paging: paging.normalized(),
+        })?;
 
         match result {
             PluginResult::Tracks(page) => Ok(page),
@@ -224,12 +235,9 @@ impl Provider for ExecPluginProvider {
     }
 
     fn get_album(&self, album_id: &AlbumId) -> ProviderResult<Album> {
-        let result = self
-            .host
-            .send_request(PluginMethod::GetAlbum {
-                album_id: album_id.clone(),
-            })
-            .map_err(Self::map_host_error)?;
+        let result = self.request(PluginMethod::GetAlbum {
+            album_id: album_id.clone(),
+        })?;
 
         match result {
             PluginResult::Album(album) => Ok(album),
@@ -245,13 +253,10 @@ impl Provider for ExecPluginProvider {
         album_id: &AlbumId,
         paging: PageRequest,
     ) -> ProviderResult<Page<Track>> {
-        let result = self
-            .host
-            .send_request(PluginMethod::ListAlbumTracks {
-                album_id: album_id.clone(),
-                paging,
-            })
-            .map_err(Self::map_host_error)?;
+        let result = self.request(PluginMethod::ListAlbumTracks {
+            album_id: album_id.clone(),
+            paging: paging.normalized(),
+        })?;
 
         match result {
             PluginResult::Tracks(page) => Ok(page),
@@ -263,12 +268,9 @@ impl Provider for ExecPluginProvider {
     }
 
     fn get_track(&self, track_id: &TrackId) -> ProviderResult<Track> {
-        let result = self
-            .host
-            .send_request(PluginMethod::GetTrack {
-                track_id: track_id.clone(),
-            })
-            .map_err(Self::map_host_error)?;
+        let result = self.request(PluginMethod::GetTrack {
+            track_id: track_id.clone(),
+        })?;
 
         match result {
             PluginResult::Track(track) => Ok(track),
@@ -280,12 +282,9 @@ impl Provider for ExecPluginProvider {
     }
 
     fn get_stream_url(&self, track_id: &TrackId) -> ProviderResult<StreamUrl> {
-        let result = self
-            .host
-            .send_request(PluginMethod::GetStreamUrl {
-                track_id: track_id.clone(),
-            })
-            .map_err(Self::map_host_error)?;
+        let result = self.request(PluginMethod::GetStreamUrl {
+            track_id: track_id.clone(),
+        })?;
 
         match result {
             PluginResult::StreamUrl(url) => Ok(url),
@@ -295,15 +294,190 @@ impl Provider for ExecPluginProvider {
             }),
         }
     }
+
+    fn get_artwork(&self, track_id: &TrackId) -> ProviderResult<Artwork> {
+        let result = self.request(PluginMethod::GetArtwork {
+            track_id: track_id.clone(),
+        })?;
+
+        match result {
+            PluginResult::Artwork { mime, data_base64 } => {
+                if !mime.starts_with("image/") {
+                    return Err(ProviderError::Other {
+                        message: format!("plugin returned artwork of type '{mime}'"),
+                    });
+                }
+                let data = base64::engine::general_purpose::STANDARD
+                    .decode(data_base64.trim())
+                    .map_err(|e| ProviderError::Other {
+                        message: format!("invalid artwork data: {e}"),
+                    })?;
+                Ok(Artwork {
+                    mime_type: mime,
+                    data,
+                })
+            }
+            PluginResult::Error(err) => Err(err.into()),
+            _ => Err(ProviderError::Other {
+                message: "unexpected response type".to_string(),
+            }),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::protocol::PluginErrorKind;
+
+    /// A plugin that only implements `SearchTracks` and logs every request
+    /// line to the file named by its first argument.
+    #[cfg(unix)]
+    fn create_search_only_plugin_script() -> tempfile::TempPath {
+        use std::io::Write;
+        use std::os::unix::fs::PermissionsExt;
+
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        writeln!(
+            file,
+            r#"#!/bin/bash
+while IFS= read -r line; do
+    echo "$line" >> "$1"
+    id=$(echo "$line" | grep -o '"id":[0-9]*' | head -1 | cut -d: -f2)
+    case "$line" in
+    *'"Initialize"'*)
+        echo '{{"id":'$id',"result":{{"status":"Initialized","id":"test","name":"Test","version":"1.0.0","protocol_version":1,"supported_methods":["SearchTracks"]}}}}' ;;
+    *'"SearchTracks"'*)
+        echo '{{"id":'$id',"result":{{"status":"Tracks","items":[],"next":null}}}}' ;;
+    *)
+        echo '{{"id":'$id',"result":{{"status":"ShutdownAck"}}}}' ;;
+    esac
+done
+"#
+        )
+        .unwrap();
+        file.flush().unwrap();
+        std::fs::set_permissions(file.path(), std::fs::Permissions::from_mode(0o755)).unwrap();
+        file.into_temp_path()
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn unsupported_methods_fail_without_a_request() {
+        let script = create_search_only_plugin_script();
+        let log = tempfile::NamedTempFile::new().unwrap();
+        let provider = ExecPluginProvider::new(PluginConfig {
+            executable: script.to_path_buf(),
+            args: vec![log.path().display().to_string()],
+            working_dir: None,
+            env: vec![],
+            request_timeout: crate::DEFAULT_REQUEST_TIMEOUT,
+            framing: crate::Framing::Lines,
+        })
+        .expect("failed to start plugin");
+
+        let page = provider
+            .search_tracks(
+                "x",
+                TrackSearchFilters::default(),
+                PageRequest::first_page(5),
+            )
+            .unwrap();
+        assert!(page.items.is_empty());
+        assert!(matches!(
+            provider.browse(BrowseKind::Artists, PageRequest::first_page(5)),
+            Err(ProviderError::NotSupported { operation }) if operation == "Browse"
+        ));
+        assert!(matches!(
+            provider.get_track(&TrackId::new("t")),
+            Err(ProviderError::NotSupported { .. })
+        ));
+
+        let sent = std::fs::read_to_string(log.path()).unwrap();
+        let methods: Vec<_> = sent
+            .lines()
+            .map(|line| {
+                let request: crate::PluginRequest = serde_json::from_str(line).unwrap();
+                request.method.name()
+            })
+            .collect();
+        assert_eq!(methods, ["Initialize", "SearchTracks"]);
+        provider.stop().unwrap();
+    }
+
+    /// A plugin serving artwork by track id: a PNG for `png`, text for
+    /// `text`, and `not_supported` for anything else.
+    #[cfg(unix)]
+    fn create_artwork_plugin_script() -> tempfile::TempPath {
+        use std::io::Write;
+        use std::os::unix::fs::PermissionsExt;
+
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        writeln!(
+            file,
+            r#"#!/bin/bash
+while IFS= read -r line; do
+    id=$(echo "$line" | grep -o '"id":[0-9]*' | head -1 | cut -d: -f2)
+    case "$line" in
+    *'"Initialize"'*)
+        echo '{{"id":'$id',"result":{{"status":"Initialized","id":"test","name":"Test","version":"1.0.0","protocol_version":1}}}}' ;;
+    *'"track_id":"png"'*)
+        echo '{{"id":'$id',"result":{{"status":"Artwork","mime":"image/png","data_base64":"iVBORw0KGgo="}}}}' ;;
+    *'"track_id":"text"'*)
+        echo '{{"id":'$id',"result":{{"status":"Artwork","mime":"text/plain","data_base64":"aGk="}}}}' ;;
+    *'"GetArtwork"'*)
+        echo '{{"id":'$id',"result":{{"status":"Error","kind":"not_supported","message":"no art here"}}}}' ;;
+    *)
+        echo '{{"id":'$id',"result":{{"status":"ShutdownAck"}}}}' ;;
+    esac
+done
+"#
+        )
+        .unwrap();
+        file.flush().unwrap();
+        std::fs::set_permissions(file.path(), std::fs::Permissions::from_mode(0o755)).unwrap();
+        file.into_temp_path()
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn artwork_is_decoded_from_base64() {
+        let script = create_artwork_plugin_script();
+        let provider = ExecPluginProvider::new(PluginConfig {
+            executable: script.to_path_buf(),
+            args: vec![],
+            working_dir: None,
+            env: vec![],
+            request_timeout: crate::DEFAULT_REQUEST_TIMEOUT,
+            framing: crate::Framing::Lines,
+        })
+        .expect("failed to start plugin");
+
+        let artwork = provider.get_artwork(&TrackId::new("png")).unwrap();
+        assert_eq!(artwork.mime_type, "image/png");
+        assert_eq!(artwork.data, b"\x89PNG\r\n\x1a\n");
+
+        assert!(matches!(
+            provider.get_artwork(&TrackId::new("text")),
+            Err(ProviderError::Other { message }) if message.contains("text/plain")
+        ));
+        assert!(matches!(
+            provider.get_artwork(&TrackId::new("other")),
+            Err(ProviderError::NotSupported { operation }) if operation == "GetArtwork"
+        ));
+        provider.stop().unwrap();
+    }
+
+    fn plugin_error(kind: PluginErrorKind, message: &str) -> PluginHostError {
+        PluginHostError::PluginError(PluginError {
+            kind,
+            message: message.to_string(),
+        })
+    }
 
     #[test]
     fn map_host_error_converts_correctly() {
-        let err = PluginHostError::PluginError("test error".to_string());
+        let err = plugin_error(PluginErrorKind::Internal, "test error");
         let provider_err = ExecPluginProvider::map_host_error(err);
         match provider_err {
             ProviderError::Other { message } => assert_eq!(message, "test error"),
@@ -311,6 +485,35 @@ mod tests {
         }
     }
 
+    #[test]
+    fn map_host_error_keeps_the_plugin_error_kind() {
+        let err = plugin_error(PluginErrorKind::NotFound, "track 42");
+        assert!(matches!(
+            ExecPluginProvider::map_host_error(err),
+            ProviderError::NotFound { entity } if entity == "track 42"
+        ));
+        let err = plugin_error(PluginErrorKind::Authentication, "token expired");
+        assert!(matches!(
+            ExecPluginProvider::map_host_error(err),
+            ProviderError::AuthenticationError { .. }
+        ));
+    }
+
+    #[test]
+    fn map_host_error_redacts_plugin_secrets() {
+        let err = plugin_error(
+            PluginErrorKind::Internal,
+            "login failed: Authorization: Bearer abc.def.ghi for user bob",
+        );
+        match ExecPluginProvider::map_host_error(err) {
+            ProviderError::Other { message } => assert_eq!(
+                message,
+                "login failed: Authorization: Bearer [REDACTED] for user bob"
+            ),
+            _ => panic!("expected Other error"),
+        }
+    }
+
     #[test]
     fn map_terminated_to_network_error() {
         let err = PluginHostError::ProcessTerminated;
@@ -0,0 +1,156 @@
+//! How messages are delimited on a plugin's stdin and stdout.
+
+use std::io::{self, BufRead, Write};
+
+/// Largest length-prefixed message accepted from a plugin.
+const MAX_FRAME_LEN: usize = 64 * 1024 * 1024;
+
+/// Message framing used in both directions.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Framing {
+    /// One JSON message per line.
+    #[default]
+    Lines,
+    /// A 4-byte big-endian length followed by that many bytes of JSON, so
+    /// messages may contain newlines.
+    LengthPrefixed,
+}
+
+impl Framing {
+    /// Write one message and flush it.
+    pub(crate) fn write(self, out: &mut impl Write, message: &[u8]) -> io::Result<()> {
+        match self {
+            Framing::Lines => {
+                out.write_all(message)?;
+                out.write_all(b"\n")?;
+            }
+            Framing::LengthPrefixed => {
+                let len = u32::try_from(message.len()).map_err(|_| {
+                    io::Error::new(io::ErrorKind::InvalidInput, "message too large to frame")
+                })?;
+                out.write_all(&len.to_be_bytes())?;
+                out.write_all(message)?;
+            }
+        }
+        out.flush()
+    }
+
+    /// Read the next message into `buf`, returning `false` once the stream
+    /// has ended.
+    pub(crate) fn read(self, input: &mut impl BufRead, buf: &mut Vec<u8>) -> io::Result<bool> {
+        buf.clear();
+        match self {
+            Framing::Lines => Ok(input.read_until(b'\n', buf)? > 0),
+            Framing::LengthPrefixed => {
+                let mut len = [0; 4];
+                match input.read_exact(&mut len) {
+                    Ok(()) => {}
+                    Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(false),
+                    Err(err) => return Err(err),
+                }
+                let len = u32::from_be_bytes(len) as usize;
+                if len > MAX_FRAME_LEN {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("plugin message of {len} bytes exceeds the limit"),
+                    ));
+                }
+                buf.resize(len, 0);
+                input.read_exact(buf)?;
+                Ok(true)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::{PluginMethod, PluginRequest, PluginResponse, PluginResult};
+    use std::io::Cursor;
+    use tunez_core::models::{PageRequest, Track, TrackId};
+    use tunez_core::provider::TrackSearchFilters;
+
+    #[test]
+    fn length_prefixed_messages_keep_embedded_newlines() {
+        let request = PluginRequest {
+            id: 7,
+            method: PluginMethod::SearchTracks {
+                query: "line one\nline two".into(),
+                filters: TrackSearchFilters::default(),
+                paging: PageRequest::first_page(10),
+            },
+        };
+        let response = PluginResponse {
+            id: 7,
+            result: PluginResult::Track(Track {
+                id: TrackId::new("t"),
+                provider_id: "test".into(),
+                title: "Verse\nChorus".into(),
+                artist: "A".into(),
+                album: None,
+                duration_seconds: None,
+                track_number: None,
+                disc_number: None,
+                year: None,
+                genre: None,
+                musicbrainz_id: None,
+                replay_gain: Default::default(),
+            }),
+        };
+
+        let mut stream = Vec::new();
+        let framing = Framing::LengthPrefixed;
+        framing
+            .write(&mut stream, &serde_json::to_vec(&request).unwrap())
+            .unwrap();
+        // Pretty-printed, so the JSON itself spans several lines.
+        framing
+            .write(&mut stream, &serde_json::to_vec_pretty(&response).unwrap())
+            .unwrap();
+
+        let mut input = Cursor::new(stream);
+        let mut buf = Vec::new();
+        assert!(framing.read(&mut input, &mut buf).unwrap());
+        let read: PluginRequest = serde_json::from_slice(&buf).unwrap();
+        assert!(matches!(
+            read.method,
+            PluginMethod::SearchTracks { query, .. } if query == "line one\nline two"
+        ));
+
+        assert!(framing.read(&mut input, &mut buf).unwrap());
+        assert!(buf.contains(&b'\n'));
+        let read: PluginResponse = serde_json::from_slice(&buf).unwrap();
+        assert!(matches!(
+            read.result,
+            PluginResult::Track(track) if track.title == "Verse\nChorus"
+        ));
+        assert!(!framing.read(&mut input, &mut buf).unwrap());
+    }
+
+    #[test]
+    fn truncated_or_oversized_frames_are_errors() {
+        let framing = Framing::LengthPrefixed;
+        let mut buf = Vec::new();
+
+        let mut input = Cursor::new([0, 0, 0, 5, b'{'].to_vec());
+        assert!(framing.read(&mut input, &mut buf).is_err());
+
+        let mut input = Cursor::new(u32::MAX.to_be_bytes().to_vec());
+        let err = framing.read(&mut input, &mut buf).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn lines_are_the_default() {
+        let mut stream = Vec::new();
+        Framing::default().write(&mut stream, b"{}").unwrap();
+        assert_eq!(stream, b"{}\n");
+
+        let mut buf = Vec::new();
+        let mut input = Cursor::new(stream);
+        assert!(Framing::Lines.read(&mut input, &mut buf).unwrap());
+        assert_eq!(buf, b"{}\n");
+        assert!(!Framing::Lines.read(&mut input, &mut buf).unwrap());
+    }
+}
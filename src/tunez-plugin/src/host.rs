@@ -1,14 +1,20 @@
 //! Exec-based plugin host that communicates with external processes via JSON over stdio.
 
+use crate::framing::Framing;
 use crate::protocol::{
-    PluginInfo, PluginMethod, PluginRequest, PluginResponse, PluginResult, PROTOCOL_VERSION,
+    PluginError, PluginErrorKind, PluginInfo, PluginMethod, PluginRequest, PluginResponse,
+    PluginResult, PROTOCOL_VERSION,
 };
-use std::io::{BufRead, BufReader, Write};
+use std::collections::{HashMap, HashSet};
+use std::io::BufReader;
 use std::path::PathBuf;
 use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::Mutex;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
 use thiserror::Error;
+use tunez_core::ElapsedGuard;
 
 /// Errors from plugin host operations.
 #[derive(Debug, Error)]
@@ -25,8 +31,11 @@ pub enum PluginHostError {
     ReadError(std::io::Error),
     #[error("failed to parse plugin response: {0}")]
     ParseError(serde_json::Error),
-    #[error("plugin returned error: {0}")]
-    PluginError(String),
+    /// The plugin answered with an error, kept whole so its kind survives.
+    #[error("plugin returned error: {}", .0.message)]
+    PluginError(PluginError),
+    #[error("plugin does not support {0}")]
+    NotSupported(String),
     #[error("protocol version mismatch: expected {expected}, got {actual}")]
     ProtocolMismatch { expected: u32, actual: u32 },
     #[error("unexpected response type for method")]
@@ -35,8 +44,13 @@ pub enum PluginHostError {
     IdMismatch { sent: u64, received: u64 },
     #[error("plugin process terminated unexpectedly")]
     ProcessTerminated,
+    #[error("plugin did not respond within {0:?}")]
+    Timeout(Duration),
 }
 
+/// How long to wait for a plugin response unless configured otherwise.
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
 /// Configuration for an external plugin.
 #[derive(Debug, Clone)]
 pub struct PluginConfig {
@@ -48,14 +62,36 @@ pub struct PluginConfig {
     pub working_dir: Option<PathBuf>,
     /// Environment variables to set for the plugin.
     pub env: Vec<(String, String)>,
+    /// How long to wait for each response before giving up on the request.
+    pub request_timeout: Duration,
+    /// How messages are delimited; the plugin must use the same.
+    pub framing: Framing,
+}
+
+type ResponseSender = mpsc::Sender<Result<PluginResponse, PluginHostError>>;
+
+/// Requests the reader thread knows about.
+#[derive(Default)]
+struct Inflight {
+    /// Callers waiting for a response, keyed by request id.
+    pending: HashMap<u64, ResponseSender>,
+    /// Requests that timed out; their late responses are dropped.
+    abandoned: HashSet<u64>,
 }
 
+type PendingRequests = Arc<Mutex<Inflight>>;
+
 /// Host for an external plugin process.
+///
+/// Requests may be sent from several threads at once. A reader thread routes
+/// each response line to the caller waiting on its `id`, so plugins are free
+/// to answer out of order.
 pub struct ExecPluginHost {
     config: PluginConfig,
     child: Mutex<Option<Child>>,
     stdin: Mutex<Option<ChildStdin>>,
-    stdout: Mutex<Option<BufReader<ChildStdout>>>,
+    reader: Mutex<Option<JoinHandle<()>>>,
+    pending: PendingRequests,
     request_id: AtomicU64,
     info: Mutex<Option<PluginInfo>>,
 }
@@ -67,7 +103,8 @@ impl ExecPluginHost {
             config,
             child: Mutex::new(None),
             stdin: Mutex::new(None),
-            stdout: Mutex::new(None),
+            reader: Mutex::new(None),
+            pending: Arc::default(),
             request_id: AtomicU64::new(1),
             info: Mutex::new(None),
         }
@@ -96,7 +133,13 @@ impl ExecPluginHost {
 
         *self.child.lock().unwrap() = Some(child);
         *self.stdin.lock().unwrap() = Some(stdin);
-        *self.stdout.lock().unwrap() = Some(BufReader::new(stdout));
+        let pending = Arc::clone(&self.pending);
+        let framing = self.config.framing;
+        let reader = std::thread::Builder::new()
+            .name("tunez-plugin-reader".into())
+            .spawn(move || read_responses(BufReader::new(stdout), framing, pending))
+            .map_err(PluginHostError::SpawnFailed)?;
+        *self.reader.lock().unwrap() = Some(reader);
 
         // Initialize the plugin
         let info = self.initialize()?;
@@ -119,7 +162,10 @@ impl ExecPluginHost {
         }
 
         *self.stdin.lock().unwrap() = None;
-        *self.stdout.lock().unwrap() = None;
+        // The reader exits once the process's stdout closes.
+        if let Some(reader) = self.reader.lock().unwrap().take() {
+            let _ = reader.join();
+        }
         *self.info.lock().unwrap() = None;
 
         Ok(())
@@ -141,51 +187,74 @@ impl ExecPluginHost {
     }
 
     /// Send a request to the plugin and receive a response.
+    #[tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(
+            method = method.name(),
+            request_bytes = tracing::field::Empty,
+            elapsed_ms = tracing::field::Empty,
+        )
+    )]
     pub fn send_request(&self, method: PluginMethod) -> Result<PluginResult, PluginHostError> {
+        let _elapsed = ElapsedGuard::start();
         let id = self.request_id.fetch_add(1, Ordering::SeqCst);
+        let name = method.name();
         let request = PluginRequest { id, method };
 
         // Serialize and write request
         let json = serde_json::to_string(&request).map_err(PluginHostError::ParseError)?;
+        // Only the size: the payload can be large.
+        tracing::Span::current().record("request_bytes", json.len());
+
+        // Register before writing so a fast response can't be missed.
+        let (tx, rx) = mpsc::channel();
+        self.pending.lock().unwrap().pending.insert(id, tx);
 
-        {
+        let written = {
             let mut stdin_guard = self.stdin.lock().unwrap();
-            let stdin = stdin_guard
-                .as_mut()
-                .ok_or(PluginHostError::ProcessTerminated)?;
-            writeln!(stdin, "{}", json).map_err(PluginHostError::WriteError)?;
-            stdin.flush().map_err(PluginHostError::WriteError)?;
+            match stdin_guard.as_mut() {
+                None => Err(PluginHostError::ProcessTerminated),
+                Some(stdin) => self
+                    .config
+                    .framing
+                    .write(stdin, json.as_bytes())
+                    .map_err(PluginHostError::WriteError),
+            }
+        };
+        if let Err(err) = written {
+            self.pending.lock().unwrap().pending.remove(&id);
+            return Err(err);
         }
 
-        // Read response
-        let response_line = {
-            let mut stdout_guard = self.stdout.lock().unwrap();
-            let stdout = stdout_guard
-                .as_mut()
-                .ok_or(PluginHostError::ProcessTerminated)?;
-            let mut line = String::new();
-            stdout
-                .read_line(&mut line)
-                .map_err(PluginHostError::ReadError)?;
-            if line.is_empty() {
-                return Err(PluginHostError::ProcessTerminated);
+        // The reader drops every sender when the plugin's stdout closes.
+        let timeout = self.config.request_timeout;
+        let response = match rx.recv_timeout(timeout) {
+            Ok(response) => response?,
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                return Err(PluginHostError::ProcessTerminated)
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                let mut inflight = self.pending.lock().unwrap();
+                if inflight.pending.remove(&id).is_some() {
+                    inflight.abandoned.insert(id);
+                    drop(inflight);
+                    tracing::warn!(id, ?timeout, "plugin request timed out");
+                    return Err(PluginHostError::Timeout(timeout));
+                }
+                // The response landed just as we gave up.
+                drop(inflight);
+                rx.try_recv()
+                    .map_err(|_| PluginHostError::ProcessTerminated)??
             }
-            line
         };
 
-        let response: PluginResponse =
-            serde_json::from_str(&response_line).map_err(PluginHostError::ParseError)?;
-
-        if response.id != id {
-            return Err(PluginHostError::IdMismatch {
-                sent: id,
-                received: response.id,
-            });
-        }
-
         // Check for error results
         if let PluginResult::Error(err) = &response.result {
-            return Err(PluginHostError::PluginError(err.message.clone()));
+            if err.kind == PluginErrorKind::NotSupported {
+                return Err(PluginHostError::NotSupported(name.to_string()));
+            }
+            return Err(PluginHostError::PluginError(err.clone()));
         }
 
         Ok(response.result)
@@ -214,6 +283,50 @@ impl ExecPluginHost {
     }
 }
 
+/// Read responses until stdout closes, handing each to its waiting caller.
+fn read_responses(mut stdout: BufReader<ChildStdout>, framing: Framing, pending: PendingRequests) {
+    let mut message = Vec::new();
+    loop {
+        match framing.read(&mut stdout, &mut message) {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(err) => {
+                tracing::warn!(error = %err, "failed to read from plugin");
+                break;
+            }
+        }
+        let response: PluginResponse = match serde_json::from_slice(&message) {
+            Ok(response) => response,
+            Err(err) => {
+                tracing::warn!(error = %err, "discarding unparseable plugin response");
+                continue;
+            }
+        };
+        let mut inflight = pending.lock().unwrap();
+        if let Some(tx) = inflight.pending.remove(&response.id) {
+            let _ = tx.send(Ok(response));
+        } else if inflight.abandoned.remove(&response.id) {
+            tracing::debug!(
+                id = response.id,
+                "discarding late response to timed-out request"
+            );
+        } else if inflight.pending.len() == 1 {
+            // With a single request in flight the stray response can only be
+            // meant for it, so report the mismatch rather than hang.
+            let sent = *inflight.pending.keys().next().unwrap();
+            let tx = inflight.pending.remove(&sent).unwrap();
+            let _ = tx.send(Err(PluginHostError::IdMismatch {
+                sent,
+                received: response.id,
+            }));
+        } else {
+            tracing::warn!(id = response.id, "discarding response for unknown request");
+        }
+    }
+    // Wake any callers still waiting; they'll see the process as terminated.
+    *pending.lock().unwrap() = Inflight::default();
+}
+
 impl Drop for ExecPluginHost {
     fn drop(&mut self) {
         let _ = self.stop();
@@ -256,6 +369,8 @@ done
             args: vec!["--config".to_string(), "test.toml".to_string()],
             working_dir: None,
             env: vec![("PLUGIN_DEBUG".to_string(), "1".to_string())],
+            request_timeout: DEFAULT_REQUEST_TIMEOUT,
+            framing: Framing::Lines,
         };
         assert_eq!(config.args.len(), 2);
         assert_eq!(config.env.len(), 1);
@@ -270,6 +385,8 @@ done
             args: vec![],
             working_dir: None,
             env: vec![],
+            request_timeout: DEFAULT_REQUEST_TIMEOUT,
+            framing: Framing::Lines,
         };
 
         let host = ExecPluginHost::new(config);
@@ -280,4 +397,199 @@ done
 
         host.stop().expect("failed to stop");
     }
+
+    /// A plugin that answers the two requests after `Initialize` in reverse order.
+    #[cfg(unix)]
+    fn create_reordering_plugin_script() -> tempfile::TempPath {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(
+            file,
+            r#"#!/bin/bash
+field() {{ echo "$1" | grep -o "\"$2\":[^,}}]*" | head -1 | cut -d: -f2 | tr -d '"'; }}
+track() {{ echo '{{"id":'$1',"result":{{"status":"Track","id":"'$2'","provider_id":"test","title":"T","artist":"A"}}}}'; }}
+read -r line
+echo '{{"id":'$(field "$line" id)',"result":{{"status":"Initialized","id":"test","name":"Test","version":"1.0.0","protocol_version":1}}}}'
+read -r first
+read -r second
+track "$(field "$second" id)" "$(field "$second" track_id)"
+track "$(field "$first" id)" "$(field "$first" track_id)"
+while IFS= read -r line; do
+    echo '{{"id":'$(field "$line" id)',"result":{{"status":"ShutdownAck"}}}}'
+done
+"#
+        )
+        .unwrap();
+        file.flush().unwrap();
+
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(file.path(), std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        file.into_temp_path()
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn concurrent_requests_are_matched_by_id() {
+        use tunez_core::models::TrackId;
+
+        let script = create_reordering_plugin_script();
+        let host = Arc::new(ExecPluginHost::new(PluginConfig {
+            executable: script.to_path_buf(),
+            args: vec![],
+            working_dir: None,
+            env: vec![],
+            request_timeout: DEFAULT_REQUEST_TIMEOUT,
+            framing: Framing::Lines,
+        }));
+        host.start().expect("failed to start plugin");
+
+        let handles: Vec<_> = ["one", "two"]
+            .into_iter()
+            .map(|name| {
+                let host = Arc::clone(&host);
+                std::thread::spawn(move || {
+                    let result = host.send_request(PluginMethod::GetTrack {
+                        track_id: TrackId::new(name),
+                    });
+                    (name, result)
+                })
+            })
+            .collect();
+        for handle in handles {
+            let (name, result) = handle.join().unwrap();
+            match result.expect("request should succeed") {
+                PluginResult::Track(track) => assert_eq!(track.id.0, name),
+                other => panic!("unexpected result: {other:?}"),
+            }
+        }
+
+        host.stop().expect("failed to stop");
+    }
+
+    /// A plugin that takes a second to answer the first request after `Initialize`.
+    #[cfg(unix)]
+    fn create_slow_plugin_script() -> tempfile::TempPath {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(
+            file,
+            r#"#!/bin/bash
+field() {{ echo "$1" | grep -o "\"$2\":[^,}}]*" | head -1 | cut -d: -f2 | tr -d '"'; }}
+read -r line
+echo '{{"id":'$(field "$line" id)',"result":{{"status":"Initialized","id":"test","name":"Test","version":"1.0.0","protocol_version":1}}}}'
+slow=1
+while IFS= read -r line; do
+    if [ -n "$slow" ]; then sleep 1; slow=; fi
+    echo '{{"id":'$(field "$line" id)',"result":{{"status":"Track","id":"'$(field "$line" track_id)'","provider_id":"test","title":"T","artist":"A"}}}}'
+done
+"#
+        )
+        .unwrap();
+        file.flush().unwrap();
+
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(file.path(), std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        file.into_temp_path()
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn slow_response_times_out_and_is_discarded() {
+        use tunez_core::models::TrackId;
+
+        let script = create_slow_plugin_script();
+        let host = ExecPluginHost::new(PluginConfig {
+            executable: script.to_path_buf(),
+            args: vec![],
+            working_dir: None,
+            env: vec![],
+            request_timeout: Duration::from_millis(800),
+            framing: Framing::Lines,
+        });
+        host.start().expect("failed to start plugin");
+        let get = |name: &str| {
+            host.send_request(PluginMethod::GetTrack {
+                track_id: TrackId::new(name),
+            })
+        };
+
+        assert!(matches!(get("slow"), Err(PluginHostError::Timeout(_))));
+
+        // The late answer to "slow" arrives first and must not be taken for this one.
+        match get("next").expect("second request should succeed") {
+            PluginResult::Track(track) => assert_eq!(track.id.0, "next"),
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+
+    /// A plugin speaking length-prefixed frames that answers every request
+    /// after `Initialize` with a pretty-printed, multi-line track.
+    #[cfg(unix)]
+    fn create_length_prefixed_plugin_script() -> tempfile::TempPath {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(
+            file,
+            r#"#!/bin/bash
+read_frame() {{
+    len=$(dd bs=1 count=4 status=none | od -An -tu1 | awk 'NF == 4 {{ print $1 * 16777216 + $2 * 65536 + $3 * 256 + $4 }}')
+    [ -n "$len" ] || exit 0
+    frame=$(dd bs=1 count="$len" status=none)
+}}
+write_frame() {{
+    len=${{#1}}
+    printf "\\x$(printf %02x $((len >> 24 & 255)))\\x$(printf %02x $((len >> 16 & 255)))\\x$(printf %02x $((len >> 8 & 255)))\\x$(printf %02x $((len & 255)))"
+    printf '%s' "$1"
+}}
+read_frame
+id=$(echo "$frame" | grep -o '"id":[0-9]*' | head -1 | cut -d: -f2)
+write_frame '{{"id":'$id',"result":{{"status":"Initialized","id":"test","name":"Test","version":"1.0.0","protocol_version":1}}}}'
+while read_frame; do
+    id=$(echo "$frame" | grep -o '"id":[0-9]*' | head -1 | cut -d: -f2)
+    write_frame '{{
+  "id": '$id',
+  "result": {{
+    "status": "Track",
+    "id": "t",
+    "provider_id": "test",
+    "title": "Verse\nChorus",
+    "artist": "A"
+  }}
+}}'
+done
+"#
+        )
+        .unwrap();
+        file.flush().unwrap();
+
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(file.path(), std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        file.into_temp_path()
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn length_prefixed_framing_round_trips_multiline_messages() {
+        use tunez_core::models::TrackId;
+
+        let script = create_length_prefixed_plugin_script();
+        let host = ExecPluginHost::new(PluginConfig {
+            executable: script.to_path_buf(),
+            args: vec![],
+            working_dir: None,
+            env: vec![],
+            request_timeout: DEFAULT_REQUEST_TIMEOUT,
+            framing: Framing::LengthPrefixed,
+        });
+        host.start().expect("failed to start plugin");
+
+        for name in ["first\nsecond", "third"] {
+            match host.send_request(PluginMethod::GetTrack {
+                track_id: TrackId::new(name),
+            }) {
+                Ok(PluginResult::Track(track)) => assert_eq!(track.title, "Verse\nChorus"),
+                other => panic!("unexpected result: {other:?}"),
+            }
+        }
+    }
 }
@@ -11,6 +11,9 @@
 //! - Tunez sends [`PluginRequest`] messages (one per line) to the plugin's stdin
 //! - The plugin responds with [`PluginResponse`] messages (one per line) on stdout
 //!
+//! With [`Framing::LengthPrefixed`] each message is instead preceded by its
+//! length as a 4-byte big-endian integer, and may span several lines.
+//!
 //! # Example Plugin (pseudocode)
 //!
 //! ```text
@@ -24,7 +27,9 @@
 //!                 "id": "my-plugin",
 //!                 "name": "My Plugin",
 //!                 "version": "1.0.0",
-//!                 "protocol_version": 1
+//!                 "protocol_version": 1,
+//!                 # Optional; methods left out fail without being sent
+//!                 "supported_methods": ["SearchTracks"]
 //!             }
 //!         }
 //!     elif request.method == "SearchTracks":
@@ -35,7 +40,7 @@
 //! # Usage
 //!
 //! ```rust,ignore
-//! use tunez_plugin::{ExecPluginProvider, PluginConfig};
+//! use tunez_plugin::{ExecPluginProvider, Framing, PluginConfig, DEFAULT_REQUEST_TIMEOUT};
 //! use std::path::PathBuf;
 //!
 //! let config = PluginConfig {
@@ -43,6 +48,8 @@
 //!     args: vec![],
 //!     working_dir: None,
 //!     env: vec![],
+//!     request_timeout: DEFAULT_REQUEST_TIMEOUT,
+//!     framing: Framing::Lines,
 //! };
 //!
 //! let provider = ExecPluginProvider::new(config)?;
@@ -50,11 +57,13 @@
 //! ```
 
 mod adapter;
+mod framing;
 mod host;
 pub mod protocol;
 
 pub use adapter::ExecPluginProvider;
-pub use host::{ExecPluginHost, PluginConfig, PluginHostError};
+pub use framing::Framing;
+pub use host::{ExecPluginHost, PluginConfig, PluginHostError, DEFAULT_REQUEST_TIMEOUT};
 pub use protocol::{
     PluginError, PluginErrorKind, PluginInfo, PluginMethod, PluginRequest, PluginResponse,
     PluginResult, PROTOCOL_VERSION,
@@ -71,10 +71,34 @@ pub enum PluginMethod {
     GetTrack { track_id: TrackId },
     /// Get the stream URL for a track.
     GetStreamUrl { track_id: TrackId },
+    /// Get the cover art for a track.
+    GetArtwork { track_id: TrackId },
     /// Shutdown the plugin gracefully.
     Shutdown,
 }
 
+impl PluginMethod {
+    /// The method's name on the wire, safe to log without its parameters.
+    pub fn name(&self) -> &'static str {
+        match self {
+            PluginMethod::Initialize => "Initialize",
+            PluginMethod::Capabilities => "Capabilities",
+            PluginMethod::SearchTracks { .. } => "SearchTracks",
+            PluginMethod::Browse { .. } => "Browse",
+            PluginMethod::ListPlaylists { .. } => "ListPlaylists",
+            PluginMethod::SearchPlaylists { .. } => "SearchPlaylists",
+            PluginMethod::GetPlaylist { .. } => "GetPlaylist",
+            PluginMethod::ListPlaylistTracks { .. } => "ListPlaylistTracks",
+            PluginMethod::GetAlbum { .. } => "GetAlbum",
+            PluginMethod::ListAlbumTracks { .. } => "ListAlbumTracks",
+            PluginMethod::GetTrack { .. } => "GetTrack",
+            PluginMethod::GetStreamUrl { .. } => "GetStreamUrl",
+            PluginMethod::GetArtwork { .. } => "GetArtwork",
+            PluginMethod::Shutdown => "Shutdown",
+        }
+    }
+}
+
 /// Result of a plugin method invocation.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "status")]
@@ -97,6 +121,8 @@ pub enum PluginResult {
     Track(Track),
     /// Stream URL.
     StreamUrl(StreamUrl),
+    /// Cover art image, base64-encoded.
+    Artwork { mime: String, data_base64: String },
     /// Shutdown acknowledged.
     ShutdownAck,
     /// Error response.
@@ -114,6 +140,24 @@ pub struct PluginInfo {
     pub version: String,
     /// Protocol version the plugin supports.
     pub protocol_version: u32,
+    /// Names of the methods the plugin implements (e.g. `"SearchTracks"`).
+    /// Plugins that leave it out are assumed to implement them all.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub supported_methods: Option<Vec<String>>,
+}
+
+impl PluginInfo {
+    /// Whether `method` may be sent to the plugin. `Initialize`,
+    /// `Capabilities` and `Shutdown` always may.
+    pub fn supports(&self, method: &PluginMethod) -> bool {
+        match (method, &self.supported_methods) {
+            (PluginMethod::Initialize | PluginMethod::Capabilities | PluginMethod::Shutdown, _) => {
+                true
+            }
+            (_, None) => true,
+            (method, Some(names)) => names.iter().any(|name| name == method.name()),
+        }
+    }
 }
 
 /// Error returned by a plugin.
@@ -173,6 +217,10 @@ impl From<tunez_core::provider::ProviderError> for PluginError {
                 kind: PluginErrorKind::Network,
                 message,
             },
+            err @ ProviderError::RateLimited { .. } => Self {
+                kind: PluginErrorKind::Network,
+                message: err.to_string(),
+            },
             ProviderError::AuthenticationError { message } => Self {
                 kind: PluginErrorKind::Authentication,
                 message,
@@ -222,6 +270,26 @@ mod tests {
         }
     }
 
+    #[test]
+    fn supported_methods_default_to_all() {
+        let json = r#"{"id":"test","name":"Test","version":"1.0.0","protocol_version":1}"#;
+        let info: PluginInfo = serde_json::from_str(json).unwrap();
+        assert_eq!(info.supported_methods, None);
+        assert!(info.supports(&PluginMethod::ListPlaylists {
+            paging: PageRequest::first_page(10)
+        }));
+
+        let json = r#"{"id":"test","name":"Test","version":"1.0.0","protocol_version":1,"supported_methods":["GetTrack"]}"#;
+        let info: PluginInfo = serde_json::from_str(json).unwrap();
+        assert!(info.supports(&PluginMethod::GetTrack {
+            track_id: TrackId::new("t")
+        }));
+        assert!(!info.supports(&PluginMethod::ListPlaylists {
+            paging: PageRequest::first_page(10)
+        }));
+        assert!(info.supports(&PluginMethod::Capabilities));
+    }
+
     #[test]
     fn error_converts_to_provider_error() {
         let err = PluginError {
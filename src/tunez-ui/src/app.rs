@@ -1,36 +1,63 @@
-use std::io::stdout;
+use std::collections::HashSet;
+use std::io::{stdout, Stdout};
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
 use crossterm::{
-    event::{self, Event, KeyCode, KeyEvent},
+    event::{
+        self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyModifiers,
+        MouseButton, MouseEvent, MouseEventKind,
+    },
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use ratatui::{
     backend::CrosstermBackend,
-    layout::{Constraint, Direction, Layout, Rect},
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span, Text},
-    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap},
+    widgets::{Block, Borders, Clear, Gauge, List, ListItem, ListState, Paragraph, Wrap},
     Frame, Terminal,
 };
 use thiserror::Error;
-use tunez_core::{AppDirs, Provider, ProviderSelection};
-use tunez_player::{Player, PlayerState, QueuePersistence};
+use tunez_core::{AppDirs, Provider, ProviderCapabilities, ProviderSelection};
+use tunez_player::{
+    Player, PlayerState, QueuePersistence, QueuePosition, QueueSaver, DEFAULT_SAVE_INTERVAL,
+};
 use tunez_viz::VizMode;
 
+use crate::artwork::ArtworkCache;
+use crate::filter::ListFilter;
+use crate::health::{HealthMonitor, NetStatus};
 use crate::help::HelpContent;
-use crate::theme::Theme;
+use crate::keymap::{Action, KeyMap};
+use crate::paging::{ListPaging, PAGE_SIZE};
+use crate::requests::LatestRequest;
+use crate::resolve::{collect_pages, resolve_selector, ResolveError};
+use crate::text::{marquee, truncate};
+use crate::theme::{ColorDepth, Theme};
+use crate::toast::{Severity, Toast};
+use crate::unavailable::UnavailableProvider;
 use std::sync::mpsc;
 use tunez_viz::Visualizer;
 
-use tunez_audio::CpalAudioEngine;
+use tunez_audio::{AudioEngine, CpalAudioEngine};
 
-const MIN_WIDTH: u16 = 60;
-const MIN_HEIGHT: u16 = 18;
+/// Smaller terminals get the compact layout.
+const FULL_WIDTH: u16 = 60;
+const FULL_HEIGHT: u16 = 18;
 const HELP_WIDTH: u16 = 80;
 const HELP_HEIGHT: u16 = 70;
+const SEEK_STEP_SECS: i64 = 5;
+/// Animation frames per character a long Now Playing line scrolls by.
+const MARQUEE_FRAMES_PER_STEP: u64 = 4;
+/// Below this many cells the cover shows as a placeholder.
+const MIN_ART_WIDTH: u16 = 8;
+const MIN_ART_HEIGHT: u16 = 4;
+
+/// Starts the provider for a selection picked in the Config tab.
+pub type ProviderConnector =
+    Arc<dyn Fn(&ProviderSelection) -> Result<Arc<dyn Provider>, String> + Send + Sync>;
 
 #[derive(Clone)]
 pub struct UiContext {
@@ -40,6 +67,30 @@ pub struct UiContext {
     pub theme: Theme,
     pub dirs: AppDirs,
     pub initial_play: Option<tunez_core::models::PlaySelector>,
+    pub keymap: KeyMap,
+    /// The `custom` theme from config, offered when cycling themes.
+    pub custom_theme: Option<Theme>,
+    pub replay_gain: tunez_core::ReplayGainConfig,
+    /// Where streamed tracks are cached; cached copies are played first.
+    pub track_cache: Option<Arc<tunez_core::CacheManager>>,
+    /// Output device name; the system default when unset.
+    pub output_device: Option<String>,
+    /// Colors the terminal can show; theme colors are reduced to fit.
+    pub color_depth: ColorDepth,
+    /// File kept up to date with the playing track.
+    pub now_playing: Option<tunez_player::NowPlayingFile>,
+    /// Serve MPRIS on D-Bus; needs the `mpris` feature.
+    pub mpris: bool,
+    /// Smallest terminal to draw in.
+    pub layout: tunez_core::LayoutConfig,
+    /// Why the selected provider failed to start, when it did.
+    pub provider_error: Option<String>,
+    /// Configured providers and profiles the Config tab can switch to.
+    pub provider_options: Vec<ProviderSelection>,
+    pub connect_provider: Option<ProviderConnector>,
+    /// The loaded configuration, shown in the Config tab and saved when a
+    /// setting is changed there. Without one, changes last until exit.
+    pub config: Option<tunez_core::Config>,
 }
 
 impl UiContext {
@@ -57,6 +108,39 @@ impl UiContext {
             theme,
             dirs,
             initial_play: None,
+            keymap: KeyMap::default(),
+            custom_theme: None,
+            replay_gain: tunez_core::ReplayGainConfig::default(),
+            track_cache: None,
+            output_device: None,
+            color_depth: ColorDepth::detect(),
+            now_playing: None,
+            mpris: false,
+            layout: tunez_core::LayoutConfig::default(),
+            provider_error: None,
+            provider_options: Vec::new(),
+            connect_provider: None,
+            config: None,
+        }
+    }
+
+    /// A context for when the selected provider couldn't be constructed. The
+    /// UI still starts, showing `error` until another provider is picked.
+    pub fn without_provider(
+        provider_selection: ProviderSelection,
+        error: impl Into<String>,
+        scrobbler: Option<Arc<dyn tunez_core::Scrobbler>>,
+        theme: Theme,
+        dirs: AppDirs,
+    ) -> Self {
+        let error = error.into();
+        let provider = Arc::new(UnavailableProvider::new(
+            provider_selection.provider_id.clone(),
+            error.clone(),
+        ));
+        Self {
+            provider_error: Some(error),
+            ..Self::new(provider, provider_selection, scrobbler, theme, dirs)
         }
     }
 }
@@ -72,7 +156,7 @@ struct TerminalGuard;
 impl TerminalGuard {
     fn enter() -> Result<Self, UiError> {
         enable_raw_mode()?;
-        execute!(stdout(), EnterAlternateScreen)?;
+        execute!(stdout(), EnterAlternateScreen, EnableMouseCapture)?;
         Ok(Self)
     }
 }
@@ -80,7 +164,7 @@ impl TerminalGuard {
 impl Drop for TerminalGuard {
     fn drop(&mut self) {
         let _ = disable_raw_mode();
-        let _ = execute!(stdout(), LeaveAlternateScreen);
+        let _ = execute!(stdout(), DisableMouseCapture, LeaveAlternateScreen);
     }
 }
 
@@ -91,6 +175,18 @@ pub fn run_ui(context: UiContext) -> Result<(), UiError> {
     terminal.clear()?;
 
     let mut app = App::new(context);
+    let result = run_loop(&mut terminal, &mut app);
+    // However the loop ended, keep the queue changes made since the last save.
+    if let Err(e) = app.queue_saver.flush(app.player.queue()) {
+        tracing::warn!(error = %e, "couldn't save the queue on exit");
+    }
+    result
+}
+
+fn run_loop(
+    terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    app: &mut App,
+) -> Result<(), UiError> {
     let mut last_tick = Instant::now();
 
     loop {
@@ -103,17 +199,21 @@ pub fn run_ui(context: UiContext) -> Result<(), UiError> {
         } else {
             20 // Default fallback
         };
-        let tick_rate = Duration::from_millis(1000 / fps as u64);
+        let mut tick_rate = Duration::from_millis(1000 / fps as u64);
+        // A slow visualizer mustn't delay scrobble progress updates.
+        if app.scrobbler_manager.is_active() {
+            tick_rate = tick_rate.min(app.scrobbler_manager.tick_interval());
+        }
 
         let timeout = tick_rate
             .checked_sub(last_tick.elapsed())
             .unwrap_or_else(|| Duration::from_millis(0));
 
         if event::poll(timeout)? {
-            if let Event::Key(key) = event::read()? {
-                if app.handle_key(key) {
-                    break;
-                }
+            match event::read()? {
+                Event::Key(key) if app.handle_key(key) => break,
+                Event::Mouse(mouse) => app.handle_mouse(mouse),
+                _ => {}
             }
         }
 
@@ -130,59 +230,201 @@ struct App {
     #[allow(dead_code)] // Will be used for UI-provider integration
     provider: Arc<dyn Provider>,
     provider_selection: ProviderSelection,
+    /// What the provider supports, asked once when it's put in use.
+    capabilities: ProviderCapabilities,
     player: Player,
     tabs: Vec<Tab>,
     active_tab: usize,
     show_help: bool,
     help: HelpContent,
     visualizer: Arc<Mutex<tunez_viz::Visualizer>>,
-    error_rx: mpsc::Receiver<String>,
-    error_message: Option<String>,
-    error_timeout: Option<Instant>,
+    error_rx: mpsc::Receiver<Toast>,
+    toast: Option<Toast>,
+    toast_timeout: Option<Instant>,
     scrobbler_manager: tunez_player::ScrobblerManager,
-    queue_persistence: QueuePersistence,
+    queue_saver: QueueSaver,
     theme: Theme,
+    custom_theme: Option<Theme>,
+    color_depth: ColorDepth,
+    track_cache: Option<Arc<tunez_core::CacheManager>>,
     use_color: bool,
+    keymap: KeyMap,
+    layout: tunez_core::LayoutConfig,
     // Queue state
     queue_state: ratatui::widgets::ListState,
     // Search state
     search_query: String,
+    search_scope: SearchScope,
     search_results: Vec<tunez_core::Track>,
+    /// Album or artist results, when `search_scope` isn't `Tracks`.
+    search_collections: Vec<tunez_core::CollectionItem>,
     search_state: ratatui::widgets::ListState,
     is_searching: bool,
-    search_rx: Option<mpsc::Receiver<tunez_core::ProviderResult<Vec<tunez_core::Track>>>>,
+    search_request:
+        LatestRequest<SearchKey, tunez_core::ProviderResult<tunez_core::Page<tunez_core::Track>>>,
+    search_collections_request: LatestRequest<
+        SearchKey,
+        tunez_core::ProviderResult<tunez_core::Page<tunez_core::CollectionItem>>,
+    >,
+    /// Query and scope of the last search, used to fetch further pages.
+    search_submitted: (String, SearchScope),
+    search_paging: ListPaging,
+    /// The submitted search was abandoned before its first page arrived by
+    /// leaving the tab; it's sent again when the tab is shown.
+    search_interrupted: bool,
     // Library state
+    library_view: LibraryView,
+    /// Levels above `library_view`, restored on Backspace/Esc.
+    library_stack: Vec<LibraryLevel>,
+    /// Shown in place of the list when a level can't be browsed.
+    library_status: Option<String>,
     library_items: Vec<tunez_core::CollectionItem>,
     library_state: ratatui::widgets::ListState,
-    library_rx: Option<
-        mpsc::Receiver<tunez_core::ProviderResult<tunez_core::Page<tunez_core::CollectionItem>>>,
+    library_request: LatestRequest<
+        (LibraryView, tunez_core::SortOrder, tunez_core::PageRequest),
+        tunez_core::ProviderResult<tunez_core::Page<tunez_core::CollectionItem>>,
     >,
+    library_paging: ListPaging,
+    /// Order of the artist and album listings; `o` cycles it.
+    library_sort: tunez_core::SortOrder,
     // Album tracks view state
     album_tracks: Vec<tunez_core::Track>,
     album_tracks_state: ratatui::widgets::ListState,
-    album_tracks_rx:
-        Option<mpsc::Receiver<tunez_core::ProviderResult<tunez_core::Page<tunez_core::Track>>>>,
+    /// Tracks for the open album or playlist, with the playlist itself when
+    /// it was fetched alongside its first page.
+    album_tracks_request: LatestRequest<
+        (TrackListSource, tunez_core::PageRequest),
+        tunez_core::ProviderResult<TrackPage>,
+    >,
+    /// Where `album_tracks` come from, used to fetch further pages.
+    album_tracks_source: Option<TrackListSource>,
+    album_tracks_paging: ListPaging,
     viewing_album_tracks: bool,
     current_album_id: Option<tunez_core::AlbumId>,
     current_album_name: Option<String>,
     // Playlist state
     playlist_items: Vec<tunez_core::Playlist>,
     playlist_state: ratatui::widgets::ListState,
-    playlist_rx:
-        Option<mpsc::Receiver<tunez_core::ProviderResult<tunez_core::Page<tunez_core::Playlist>>>>,
+    playlist_request: LatestRequest<
+        tunez_core::PageRequest,
+        tunez_core::ProviderResult<tunez_core::Page<tunez_core::Playlist>>,
+    >,
+    playlist_paging: ListPaging,
     stream_url_rx: Option<mpsc::Receiver<tunez_core::ProviderResult<tunez_core::StreamUrl>>>,
+    /// The next album track, decoded ahead for gapless playback.
+    gapless_rx: Option<
+        mpsc::Receiver<(
+            tunez_player::QueueId,
+            Result<tunez_audio::PreparedAudio, String>,
+        )>,
+    >,
     // Lyrics state
-    lyrics: Option<String>,
-    lyrics_rx: Option<mpsc::Receiver<tunez_core::ProviderResult<String>>>,
+    lyrics: Option<tunez_core::Lyrics>,
+    /// Shown instead of lyrics while loading or when none are available.
+    lyrics_status: Option<String>,
+    lyrics_rx: Option<mpsc::Receiver<tunez_core::ProviderResult<tunez_core::Lyrics>>>,
     current_lyrics_id: Option<tunez_core::models::TrackId>,
+    /// Chapter markers being fetched for the track that just started.
+    chapters_rx: Option<
+        mpsc::Receiver<(
+            tunez_core::TrackId,
+            tunez_core::ProviderResult<Vec<tunez_core::Chapter>>,
+        )>,
+    >,
+    // Artwork state: cover for the current track, keyed by album
+    artwork: Option<(String, tunez_core::Artwork)>,
+    artwork_rx: Option<mpsc::Receiver<(String, tunez_core::ProviderResult<tunez_core::Artwork>)>>,
+    artwork_cache: ArtworkCache,
     audio_engine: CpalAudioEngine,
     // Config state
     config_state: ListState,
     config_items: Vec<&'static str>,
-    // Pending initial play states
-    pending_search_play: bool,
-    pending_playlist_play: Option<String>,
-    pending_view_play: bool,
+    /// Favorite track ids, loaded at startup when the provider supports them.
+    favorites: HashSet<tunez_core::TrackId>,
+    favorites_rx: Option<mpsc::Receiver<tunez_core::ProviderResult<Vec<tunez_core::Track>>>>,
+    /// Outcome of the last favorite toggle: the track and its new state.
+    favorite_rx: Option<mpsc::Receiver<(tunez_core::Track, bool, tunez_core::ProviderResult<()>)>>,
+    /// Tracks for the `tunez play` selector, resolved in the background.
+    initial_play_rx: Option<mpsc::Receiver<Result<Vec<tunez_core::Track>, ResolveError>>>,
+    /// The whole album or playlist being fetched to replace the queue, with
+    /// the row to start playing from.
+    track_list_play_rx:
+        Option<mpsc::Receiver<(usize, tunez_core::ProviderResult<Vec<tunez_core::Track>>)>>,
+    /// Where the last frame drew clickable widgets.
+    click_areas: ClickAreas,
+    /// Reachability of the provider, shown as `Net:` in the header.
+    net_health: HealthMonitor,
+    /// Type-ahead filter over the active tab's list, while one is open.
+    list_filter: Option<ListFilter>,
+    /// Why the current provider couldn't be started, shown until another
+    /// one is picked from the Config tab.
+    provider_error: Option<String>,
+    provider_options: Vec<ProviderSelection>,
+    connect_provider: Option<ProviderConnector>,
+    config: Option<tunez_core::Config>,
+    dirs: AppDirs,
+    /// Why the last change in the Config tab couldn't be saved.
+    config_error: Option<String>,
+    /// Desktop media controls, when enabled.
+    #[cfg(feature = "mpris")]
+    mpris: Option<crate::mpris::Mpris>,
+}
+
+/// One-line description of the track cache settings for the Config tab.
+fn cache_summary(cache: &tunez_core::CacheConfig) -> String {
+    const GIB: u64 = 1024 * 1024 * 1024;
+    const MIB: u64 = 1024 * 1024;
+    let tracks = if cache.cache_tracks { "on" } else { "off" };
+    let size = match cache.max_size_bytes {
+        0 => "no size limit".to_string(),
+        bytes if bytes >= GIB => format!("max {} GB", bytes / GIB),
+        bytes => format!("max {} MB", bytes / MIB),
+    };
+    let age = match cache.max_age_seconds {
+        0 => "kept forever".to_string(),
+        seconds => format!("kept {} days", seconds / (24 * 60 * 60)),
+    };
+    format!("tracks {tracks}, {size}, {age}")
+}
+
+/// One-line list of what the provider supports, for the Config tab.
+fn capability_summary(caps: &ProviderCapabilities) -> String {
+    let supported: Vec<&str> = [
+        (caps.playlists, "playlists"),
+        (caps.lyrics, "lyrics"),
+        (caps.artwork, "artwork"),
+        (caps.favorites, "favorites"),
+        (caps.recently_played, "recently played"),
+        (caps.offline_download, "offline download"),
+    ]
+    .into_iter()
+    .filter_map(|(supported, name)| supported.then_some(name))
+    .collect();
+    if supported.is_empty() {
+        "none".to_string()
+    } else {
+        supported.join(", ")
+    }
+}
+
+/// Cells left for a row's text in a bordered list drawn in `area`, after
+/// the two-cell highlight symbol.
+fn row_width(area: Rect) -> usize {
+    usize::from(area.width.saturating_sub(4))
+}
+
+/// A list row showing `text`, shortened to `width` cells.
+fn fit_row(text: &str, width: usize) -> ListItem<'static> {
+    ListItem::new(truncate(text, width).into_owned())
+}
+
+/// Screen regions from the last render, used to hit-test mouse clicks.
+#[derive(Debug, Default, Clone, Copy)]
+struct ClickAreas {
+    nav: Rect,
+    /// The active tab's list, if it shows one.
+    list: Option<Rect>,
+    progress: Option<Rect>,
 }
 
 impl App {
@@ -192,26 +434,33 @@ impl App {
         // Initialize scrobbler manager
         let mut scrobbler_manager =
             tunez_player::ScrobblerManager::new(ctx.scrobbler.clone(), "Tunez", None);
-        // Enable scrobbling if a scrobbler was configured and provided
-        scrobbler_manager.set_enabled(ctx.scrobbler.is_some());
+        // Enable scrobbling if a scrobbler was configured and not switched off
+        let scrobbling = ctx.config.as_ref().is_none_or(|c| c.scrobbling.enabled);
+        scrobbler_manager.set_enabled(ctx.scrobbler.is_some() && scrobbling);
         // Hook up error callback
         {
             let tx_clone = tx.clone();
-            scrobbler_manager.set_error_callback(move |msg: &str| {
-                let _ = tx_clone.send(msg.to_string());
+            scrobbler_manager.set_error_callback(move |error| {
+                let _ = tx_clone.send(Toast::scrobbler(error));
             });
         }
 
         let queue_persistence = QueuePersistence::new(ctx.dirs.data_dir());
         let mut player = Player::new();
+        player.set_replay_gain(ctx.replay_gain);
+        if let Some(file) = ctx.now_playing.clone() {
+            player.set_now_playing_file(file);
+        }
+        player.set_play_history(tunez_core::PlayHistory::new(ctx.dirs.data_dir()));
+        player.set_play_counts(tunez_core::PlayCounts::new(ctx.dirs.data_dir()));
 
         // Load persisted queue
         match queue_persistence.load() {
             Ok(queue) => {
-                *player.queue_mut() = queue;
+                player.load_queue(queue);
             }
             Err(e) => {
-                let _ = tx.send(format!("Failed to load queue: {}", e));
+                let _ = tx.send(Toast::failure("Couldn't restore the saved queue", e));
             }
         }
 
@@ -228,53 +477,115 @@ impl App {
             }
         });
 
+        let net_health = HealthMonitor::new(ctx.provider.clone());
+        let capabilities = ctx.provider.capabilities();
         let mut app = Self {
             provider: ctx.provider,
             provider_selection: ctx.provider_selection,
+            tabs: Tab::for_capabilities(&capabilities),
+            capabilities,
             player,
-            tabs: Tab::all(),
             active_tab: 0,
             show_help: false,
             visualizer,
             error_rx: rx,
-            error_message: None,
-            error_timeout: None,
+            toast: None,
+            toast_timeout: None,
             scrobbler_manager,
-            queue_persistence,
+            queue_saver: QueueSaver::new(queue_persistence, DEFAULT_SAVE_INTERVAL),
             help: HelpContent::new(),
-            theme: ctx.theme,
-            use_color: ctx.theme.is_color,
+            theme: ctx.theme.for_depth(ctx.color_depth),
+            custom_theme: ctx.custom_theme,
+            color_depth: ctx.color_depth,
+            track_cache: ctx.track_cache.clone(),
+            use_color: ctx.theme.for_depth(ctx.color_depth).is_color,
+            keymap: ctx.keymap.clone(),
             queue_state: ratatui::widgets::ListState::default(),
             search_query: String::new(),
+            search_scope: SearchScope::Tracks,
             search_results: Vec::new(),
+            search_collections: Vec::new(),
             search_state: ratatui::widgets::ListState::default(),
             is_searching: false,
-            search_rx: None,
+            search_collections_request: LatestRequest::new(),
+            search_submitted: (String::new(), SearchScope::Tracks),
+            search_paging: ListPaging::default(),
+            search_interrupted: false,
+            search_request: LatestRequest::new(),
+            library_view: LibraryView::Root,
+            library_stack: Vec::new(),
+            library_status: None,
             library_items: Vec::new(),
             library_state: ratatui::widgets::ListState::default(),
-            library_rx: None,
+            library_request: LatestRequest::new(),
+            library_paging: ListPaging::default(),
+            library_sort: tunez_core::SortOrder::default(),
             album_tracks: Vec::new(),
             album_tracks_state: ratatui::widgets::ListState::default(),
-            album_tracks_rx: None,
+            album_tracks_request: LatestRequest::new(),
+            album_tracks_source: None,
+            album_tracks_paging: ListPaging::default(),
             viewing_album_tracks: false,
             current_album_id: None,
             current_album_name: None,
             playlist_items: Vec::new(),
             playlist_state: ratatui::widgets::ListState::default(),
-            playlist_rx: None,
+            playlist_request: LatestRequest::new(),
+            playlist_paging: ListPaging::default(),
             stream_url_rx: None,
+            gapless_rx: None,
             lyrics: None,
+            lyrics_status: None,
             lyrics_rx: None,
+            artwork: None,
+            artwork_rx: None,
+            chapters_rx: None,
+            artwork_cache: ArtworkCache::default(),
             current_lyrics_id: None,
-            audio_engine: CpalAudioEngine,
+            audio_engine: match &ctx.output_device {
+                Some(name) => CpalAudioEngine::with_device(name.as_str()),
+                None => CpalAudioEngine::default(),
+            },
             config_state: ListState::default(),
-
-            config_items: vec!["Theme", "Visualizer Mode", "Scrobbling"],
-            pending_search_play: false,
-            pending_playlist_play: None,
-            pending_view_play: false,
+            config_items: vec![
+                "Provider",
+                "Theme",
+                "Log Level",
+                "Visualizer Mode",
+                "Scrobbling",
+                "Cache",
+                "Capabilities",
+            ],
+            favorites: HashSet::new(),
+            favorites_rx: None,
+            favorite_rx: None,
+            initial_play_rx: None,
+            track_list_play_rx: None,
+            click_areas: ClickAreas::default(),
+            net_health,
+            list_filter: None,
+            provider_error: ctx.provider_error,
+            provider_options: ctx.provider_options,
+            connect_provider: ctx.connect_provider,
+            config: ctx.config,
+            layout: ctx.layout,
+            dirs: ctx.dirs.clone(),
+            config_error: None,
+            #[cfg(feature = "mpris")]
+            mpris: ctx.mpris.then(crate::mpris::Mpris::start),
         };
 
+        if app.provider_error.is_some() {
+            // Land on the tab that can fix it.
+            if let Some(i) = app.tabs.iter().position(|&tab| tab == Tab::Config) {
+                app.active_tab = i;
+            }
+        }
+
+        if app.capabilities.supports_favorites() {
+            app.load_favorites();
+        }
+
         // Handle initial play intent if provided
         if let Some(selector) = ctx.initial_play {
             app.handle_initial_play(selector);
@@ -283,93 +594,276 @@ impl App {
         app
     }
 
+    /// Resolve the `tunez play` selector off the UI thread; `tick` queues
+    /// and plays the result.
     fn handle_initial_play(&mut self, selector: tunez_core::models::PlaySelector) {
-        match selector {
-            tunez_core::models::PlaySelector::Id { id } => {
-                let track_id = tunez_core::models::TrackId::new(id);
-                self.fetch_track_and_play(track_id);
-            }
-            tunez_core::models::PlaySelector::TrackSearch {
-                track,
-                artist,
-                album,
-            } => {
-                let mut query = track;
-                if let Some(a) = artist {
-                    query.push_str(&format!(" {}", a));
-                }
-                if let Some(a) = album {
-                    query.push_str(&format!(" {}", a));
-                }
+        let provider = self.provider.clone();
+        let (tx, rx) = mpsc::channel();
+        self.initial_play_rx = Some(rx);
 
-                // Switch to search tab, set query, trigger search, and set flag to play first result
-                if let Some(search_idx) = self.tabs.iter().position(|t| matches!(t, Tab::Search)) {
-                    self.active_tab = search_idx;
-                }
-                self.search_query = query;
-                self.is_searching = false; // Not interactive typing
-                self.pending_search_play = true;
-                self.perform_search();
-            }
-            tunez_core::models::PlaySelector::Playlist { name } => {
-                // Trigger playlist load and set flag to find and play
-                self.pending_playlist_play = Some(name);
-                self.load_playlists();
-            }
-            _ => {
-                // For now, simpler ones could be added later
-                tracing::warn!("Initial play for selector {:?} not yet implemented", selector);
-            }
-        }
+        tokio::task::spawn_blocking(move || {
+            let _ = tx.send(resolve_selector(provider.as_ref(), &selector));
+        });
     }
 
-    fn fetch_track_and_play(&mut self, track_id: tunez_core::models::TrackId) {
+    fn load_favorites(&mut self) {
         let provider = self.provider.clone();
         let (tx, rx) = mpsc::channel();
-        self.stream_url_rx = Some(rx);
+        self.favorites_rx = Some(rx);
 
-        // We also need to get the track metadata to enqueue it
-        let track_id_clone = track_id.clone();
-        let provider_clone = provider.clone();
         tokio::task::spawn_blocking(move || {
-            if let Ok(_track) = provider_clone.get_track(&track_id_clone) {
-                // If successful, start getting stream URL
-                let result = provider_clone.get_stream_url(&track_id_clone);
-                let _ = tx.send(result);
-                // We should also find a way to update the player queue here, 
-                // but that requires access to the player in a non-blocking context.
-            }
+            let _ = tx.send(collect_pages(|paging| provider.list_favorites(paging)));
         });
     }
 
-    fn load_library(&mut self) {
+    /// Flip the favorite mark on the highlighted track, or the playing one
+    /// on tabs without a track list.
+    fn toggle_favorite(&mut self) {
+        if !self.capabilities.supports_favorites() {
+            self.show_toast(Toast::info(format!(
+                "{} has no favorites",
+                self.provider.name()
+            )));
+            return;
+        }
+        let Some(track) = self.focused_track() else {
+            return;
+        };
+        let favorite = !self.favorites.contains(&track.id);
         let provider = self.provider.clone();
         let (tx, rx) = mpsc::channel();
-        self.library_rx = Some(rx);
+        self.favorite_rx = Some(rx);
 
         tokio::task::spawn_blocking(move || {
-            let result = provider.browse(
-                tunez_core::BrowseKind::Albums,
-                tunez_core::PageRequest::first_page(50),
-            );
-            let _ = tx.send(result);
+            let result = provider.set_favorite(&track.id, favorite);
+            let _ = tx.send((track, favorite, result));
         });
     }
 
-    fn load_album_tracks(&mut self, album_id: tunez_core::AlbumId, album_name: String) {
+    /// The track under the cursor in the active tab's track list, falling
+    /// back to the playing track.
+    fn focused_track(&self) -> Option<tunez_core::Track> {
+        let selected = |tracks: &[tunez_core::Track], state: &ListState| {
+            state.selected().and_then(|i| tracks.get(i)).cloned()
+        };
+        let focused = match self.tabs[self.active_tab] {
+            Tab::Search if self.search_scope == SearchScope::Tracks => {
+                selected(&self.search_results, &self.search_state)
+            }
+            Tab::Library | Tab::Playlists if self.viewing_album_tracks => {
+                selected(&self.album_tracks, &self.album_tracks_state)
+            }
+            Tab::Queue => self
+                .queue_state
+                .selected()
+                .and_then(|i| self.player.queue().items().get(i))
+                .map(|item| item.track.clone()),
+            _ => None,
+        };
+        focused.or_else(|| self.player.current().map(|item| item.track.clone()))
+    }
+
+    fn load_library(&mut self) {
+        self.library_status = None;
+        let paging = self.library_paging.first(PAGE_SIZE);
+        self.fetch_library(paging);
+    }
+
+    fn fetch_library(&mut self, paging: tunez_core::PageRequest) {
         let provider = self.provider.clone();
-        let (tx, rx) = mpsc::channel();
-        self.album_tracks_rx = Some(rx);
+        let sort = self.library_sort;
+        let key = (self.library_view.clone(), sort, paging);
+
+        match self.library_view.clone() {
+            LibraryView::Root => self.library_request.start(key, move || {
+                // Providers without artist browsing start at their albums.
+                match provider.browse_sorted(tunez_core::BrowseKind::Artists, sort, paging) {
+                    Err(tunez_core::ProviderError::NotSupported { .. }) => {
+                        provider.browse_sorted(tunez_core::BrowseKind::Albums, sort, paging)
+                    }
+                    other => other,
+                }
+            }),
+            LibraryView::Artist(artist) => self.library_request.start(key, move || {
+                provider.list_artist_albums(&artist, paging).map(|page| {
+                    let mut items: Vec<_> = page
+                        .items
+                        .into_iter()
+                        .map(tunez_core::CollectionItem::Album)
+                        .collect();
+                    sort.sort_items(&mut items);
+                    tunez_core::Page {
+                        items,
+                        next: page.next,
+                    }
+                })
+            }),
+            // Tracks arrive through `album_tracks_request`.
+            LibraryView::Album { .. } | LibraryView::RecentlyPlayed | LibraryView::MostPlayed => {
+                false
+            }
+        };
+    }
+
+    /// Switch the artist and album listings to the next sort order and
+    /// reload the current level in it.
+    fn cycle_library_sort(&mut self) {
+        self.library_sort = self.library_sort.next();
+        self.library_state = ListState::default();
+        self.library_request.cancel();
+        self.load_library();
+    }
+
+    /// Descend into `view`, remembering the current level for `library_pop`.
+    fn library_push(&mut self, view: LibraryView) {
+        let level = LibraryLevel {
+            view: std::mem::replace(&mut self.library_view, view.clone()),
+            items: std::mem::take(&mut self.library_items),
+            selected: self.library_state.selected(),
+            paging: std::mem::take(&mut self.library_paging),
+        };
+        self.library_stack.push(level);
+        self.list_filter = None;
+        self.library_state = ListState::default();
+        self.library_status = None;
+        self.library_request.cancel();
+        match view {
+            LibraryView::Album { id, title } => {
+                self.album_tracks.clear();
+                self.album_tracks_state = ListState::default();
+                self.viewing_album_tracks = true;
+                self.load_album_tracks(id, title);
+            }
+            LibraryView::RecentlyPlayed | LibraryView::MostPlayed => {
+                self.album_tracks.clear();
+                self.album_tracks_state = ListState::default();
+                self.viewing_album_tracks = true;
+                self.load_play_list(view);
+            }
+            _ => self.load_library(),
+        }
+    }
+
+    /// Return to the previous level. Returns `false` at the top.
+    fn library_pop(&mut self) -> bool {
+        let Some(level) = self.library_stack.pop() else {
+            return false;
+        };
+        self.list_filter = None;
+        if matches!(
+            self.library_view,
+            LibraryView::Album { .. } | LibraryView::RecentlyPlayed | LibraryView::MostPlayed
+        ) {
+            self.close_track_list();
+        }
+        self.library_view = level.view;
+        self.library_items = level.items;
+        self.library_state.select(level.selected);
+        self.library_paging = level.paging;
+        // Anything in flight was for the level below.
+        self.library_paging.cancel();
+        self.library_request.cancel();
+        self.library_status = None;
+        // The level was left before its items arrived.
+        if self.library_items.is_empty() {
+            self.load_library();
+        }
+        true
+    }
+
+    /// Block title showing the path to the current level.
+    fn library_breadcrumb(&self) -> String {
+        self.library_stack
+            .iter()
+            .map(|level| &level.view)
+            .chain(std::iter::once(&self.library_view))
+            .map(LibraryView::label)
+            .collect::<Vec<_>>()
+            .join(" › ")
+    }
+
+    fn load_album_tracks(&mut self, album_id: tunez_core::AlbumId, album_name: String) {
         self.current_album_id = Some(album_id.clone());
         self.current_album_name = Some(album_name);
+        self.album_tracks_source = Some(TrackListSource::Album(album_id));
+        let paging = self.album_tracks_paging.first(PAGE_SIZE);
+        self.fetch_album_tracks(paging);
+    }
 
-        tokio::task::spawn_blocking(move || {
-            let result =
-                provider.list_album_tracks(&album_id, tunez_core::PageRequest::first_page(50));
-            let _ = tx.send(result);
+    /// Open the Recently Played or Most Played list.
+    fn load_play_list(&mut self, view: LibraryView) {
+        self.current_album_id = None;
+        self.current_album_name = Some(view.label().to_string());
+        self.album_tracks_source = Some(match view {
+            LibraryView::MostPlayed => TrackListSource::MostPlayed,
+            _ => TrackListSource::RecentlyPlayed,
+        });
+        let paging = self.album_tracks_paging.first(PAGE_SIZE);
+        self.fetch_album_tracks(paging);
+    }
+
+    fn fetch_album_tracks(&mut self, paging: tunez_core::PageRequest) {
+        let Some(source) = self.album_tracks_source.clone() else {
+            return;
+        };
+        let provider = self.provider.clone();
+        let play_counts = tunez_core::PlayCounts::new(self.dirs.data_dir());
+        let key = (source.clone(), paging);
+        self.album_tracks_request.start(key, move || {
+            match source {
+                TrackListSource::Album(id) => {
+                    provider.list_album_tracks(&id, paging).map(|p| (None, p))
+                }
+                // Opening a playlist refreshes its name and track count.
+                TrackListSource::Playlist(id) if paging.offset == 0 => provider
+                    .get_playlist_with_tracks(&id, paging)
+                    .map(|(playlist, page)| (Some(playlist), page)),
+                TrackListSource::Playlist(id) => provider
+                    .list_playlist_tracks(&id, paging)
+                    .map(|p| (None, p)),
+                TrackListSource::RecentlyPlayed => {
+                    provider.list_recently_played(paging).map(|p| (None, p))
+                }
+                TrackListSource::MostPlayed => {
+                    most_played_page(&play_counts, provider.id(), paging).map(|p| (None, p))
+                }
+            }
         });
     }
 
+    /// Leave the album or playlist track list, abandoning any page of it
+    /// still loading.
+    fn close_track_list(&mut self) {
+        self.viewing_album_tracks = false;
+        self.album_tracks.clear();
+        self.album_tracks_state = ListState::default();
+        self.album_tracks_request.cancel();
+        self.album_tracks_paging = ListPaging::default();
+        self.album_tracks_source = None;
+        self.current_album_id = None;
+        self.current_album_name = None;
+    }
+
+    /// Rows leading the Library's top level, ahead of its items.
+    fn library_shortcuts(&self) -> Vec<LibraryView> {
+        if self.library_view != LibraryView::Root {
+            return Vec::new();
+        }
+        let recent = self
+            .capabilities
+            .supports_recently_played()
+            .then_some(LibraryView::RecentlyPlayed);
+        recent
+            .into_iter()
+            .chain(std::iter::once(LibraryView::MostPlayed))
+            .collect()
+    }
+
+    /// Rows in the Library list, counting the shortcut rows.
+    fn library_len(&self) -> usize {
+        self.library_items.len() + self.library_shortcuts().len()
+    }
+
     fn play_track(&mut self, track: tunez_core::Track) {
         self.player.queue_mut().enqueue_next(track.clone());
         if self.player.current().is_none() {
@@ -378,39 +872,131 @@ impl App {
             self.player.skip_next();
         }
 
-        if let Some(current) = self.player.current() {
-            let provider = self.provider.clone();
-            let track_id = current.track.id.clone();
-            let (tx, rx) = mpsc::channel();
-            self.stream_url_rx = Some(rx);
-
-            tokio::task::spawn_blocking(move || {
-                let result = provider.get_stream_url(&track_id);
-                let _ = tx.send(result);
-            });
-        }
+        self.request_stream_for_current();
 
         if let Some(np_idx) = self.tabs.iter().position(|t| matches!(t, Tab::NowPlaying)) {
             self.active_tab = np_idx;
         }
     }
 
+    /// Play row `index` of the album or playlist being viewed, with the
+    /// whole list replacing the queue so the rest plays on after it. Pages
+    /// not loaded yet are fetched first. Recently Played rows are queued one
+    /// at a time instead.
+    fn play_from_track_list(&mut self, index: usize) {
+        let Some(track) = self.album_tracks.get(index).cloned() else {
+            return;
+        };
+        let source = match self.album_tracks_source.clone() {
+            Some(source @ (TrackListSource::Album(_) | TrackListSource::Playlist(_))) => source,
+            _ => {
+                self.play_track(track);
+                return;
+            }
+        };
+        if self.album_tracks_paging.is_complete() {
+            self.play_track_list(self.album_tracks.clone(), index);
+            return;
+        }
+        let provider = self.provider.clone();
+        let (tx, rx) = mpsc::channel();
+        self.track_list_play_rx = Some(rx);
+        tokio::task::spawn_blocking(move || {
+            let tracks = collect_pages(|paging| match &source {
+                TrackListSource::Album(id) => provider.list_album_tracks(id, paging),
+                TrackListSource::Playlist(id) => provider.list_playlist_tracks(id, paging),
+                TrackListSource::RecentlyPlayed | TrackListSource::MostPlayed => {
+                    Ok(tunez_core::Page::single_page(Vec::new()))
+                }
+            });
+            let _ = tx.send((index, tracks));
+        });
+    }
+
+    /// Replace the queue with `tracks` and play the one at `index`.
+    fn play_track_list(&mut self, tracks: Vec<tunez_core::Track>, index: usize) {
+        self.player
+            .queue_mut()
+            .enqueue_all(tracks, QueuePosition::Replace);
+        self.queue_state.select(Some(index));
+        self.play_queue_item(index);
+    }
+
     fn play_queue_item(&mut self, index: usize) {
-        if let Some(item) = self.player.play_index(index) {
+        if self.player.play_index(index).is_some() {
+            self.request_stream_for_current();
+
+            if let Some(np_idx) = self.tabs.iter().position(|t| matches!(t, Tab::NowPlaying)) {
+                self.active_tab = np_idx;
+            }
+        }
+    }
+
+    /// Resolve a stream URL for the current queue item, preferring the
+    /// track cache; playback starts in `tick`.
+    fn request_stream_for_current(&mut self) {
+        if let Some(current) = self.player.current() {
             let provider = self.provider.clone();
-            let track_id = item.track.id.clone();
+            let cache = self.track_cache.clone();
+            let track_id = current.track.id.clone();
             let (tx, rx) = mpsc::channel();
             self.stream_url_rx = Some(rx);
 
             tokio::task::spawn_blocking(move || {
-                let result = provider.get_stream_url(&track_id);
-                let _ = tx.send(result);
+                let _ = tx.send(stream_url(provider.as_ref(), cache.as_ref(), &track_id));
             });
+        }
+    }
 
-            if let Some(np_idx) = self.tabs.iter().position(|t| matches!(t, Tab::NowPlaying)) {
-                self.active_tab = np_idx;
+    /// Fetch and decode the next album track ahead of its turn so it
+    /// follows the current one without a gap; `tick` queues it.
+    fn preload_gapless(&mut self) {
+        if self.gapless_rx.is_some() {
+            return;
+        }
+        let Some(next) = self.player.gapless_candidate() else {
+            return;
+        };
+        let gain = self.player.gain_for(&next.track);
+        let provider = self.provider.clone();
+        let cache = self.track_cache.clone();
+        let engine = self.audio_engine.clone();
+        let (tx, rx) = mpsc::channel();
+        self.gapless_rx = Some(rx);
+
+        tokio::task::spawn_blocking(move || {
+            let result = stream_url(provider.as_ref(), cache.as_ref(), &next.track.id)
+                .map_err(|e| e.to_string())
+                .and_then(|url| {
+                    engine
+                        .prepare(tunez_audio::AudioSource::from(&url), gain)
+                        .map_err(|e| e.to_string())
+                });
+            let _ = tx.send((next.id, result));
+        });
+    }
+
+    /// Scrobble, artwork and lyrics updates for a track that just started.
+    fn on_track_started(&mut self) {
+        self.scrobbler_manager
+            .on_state_change(&self.player, tunez_core::PlaybackState::Started);
+
+        if let Some(rate) = self.player.sample_rate() {
+            if let Ok(mut viz) = self.visualizer.lock() {
+                viz.set_sample_rate(rate);
             }
         }
+
+        self.load_artwork();
+        self.load_chapters();
+
+        // Clear lyrics if it's a new track and we're not on lyrics tab
+        if self.tabs[self.active_tab] != Tab::Lyrics {
+            self.lyrics = None;
+            self.lyrics_status = None;
+        } else {
+            self.load_lyrics();
+        }
     }
 
     fn tick(&mut self) {
@@ -419,10 +1005,15 @@ impl App {
             viz.update_animation();
         }
 
+        #[cfg(feature = "mpris")]
+        self.sync_mpris();
+
         // Update scrobbler progress
         // Note: we cast Duration to u64 seconds, losing sub-second precision which is fine for scrobbling interval checks
         self.scrobbler_manager
             .tick(&self.player, self.player.position().as_secs());
+        // Count the play locally once it passes the same threshold
+        self.player.record_progress(self.player.position());
 
         // Check for stream URL results
         if let Some(rx) = &self.stream_url_rx {
@@ -430,181 +1021,285 @@ impl App {
                 match result {
                     Ok(url) => {
                         // Start playback
-                        let source = tunez_audio::AudioSource::Url(url.0);
+                        let source = tunez_audio::AudioSource::from(&url);
                         self.player.play_with_audio(&self.audio_engine, source);
-
-                        // Notify scrobbler
-                        self.scrobbler_manager
-                            .on_state_change(&self.player, tunez_core::PlaybackState::Started);
-                        
-                        // Clear lyrics if it's a new track and we're not on lyrics tab
-                        if self.tabs[self.active_tab] != Tab::Lyrics {
-                            self.lyrics = None;
-                        } else {
-                            self.load_lyrics();
+                        if let Some(e) = self.player.take_audio_error() {
+                            self.show_toast(Toast::audio(&e));
                         }
+                        self.on_track_started();
                     }
                     Err(e) => {
-                        self.error_message = Some(format!("Failed to get stream URL: {}", e));
-                        self.error_timeout = Some(Instant::now() + Duration::from_secs(5));
+                        self.show_toast(Toast::provider("Track", &e));
                         self.player.set_error(e.to_string());
                     }
                 }
             }
         }
 
+        // A network stream moves on from buffering, or gives up
+        self.player.poll_buffering();
+        if let Some(e) = self.player.take_audio_error() {
+            self.show_toast(Toast::audio(&e));
+        }
+
+        // The next album track was queued behind this one and has started
+        if self.player.gapless_started() {
+            self.scrobbler_manager.on_track_ended(&self.player);
+            if self.player.finish_gapless().is_some() {
+                self.on_track_started();
+            }
+            self.save_queue();
+        }
+        if let Some(rx) = &self.gapless_rx {
+            if let Ok((id, result)) = rx.try_recv() {
+                self.gapless_rx = None;
+                match result {
+                    Ok(prepared) => {
+                        self.player.queue_gapless(id, prepared);
+                    }
+                    Err(e) => tracing::debug!("Next track not prepared for gapless: {}", e),
+                }
+            }
+        }
+        self.preload_gapless();
+
+        // Move on when the current track plays out
+        if self.player.poll_completion() {
+            self.scrobbler_manager.on_track_ended(&self.player);
+            if self.player.advance_after_end().is_some() {
+                self.request_stream_for_current();
+            }
+            self.save_queue();
+        } else if let Some(e) = self.player.take_audio_error() {
+            self.show_toast(Toast::audio(&e));
+        }
+
         // Check for lyrics results
         if let Some(rx) = &self.lyrics_rx {
             if let Ok(result) = rx.try_recv() {
                 match result {
-                    Ok(lyrics) => {
+                    Ok(lyrics) if !lyrics.is_empty() => {
                         self.lyrics = Some(lyrics);
+                        self.lyrics_status = None;
                     }
-                    Err(_) => {
-                        self.lyrics = Some("No lyrics found for this track".to_string());
+                    _ => {
+                        self.lyrics = None;
+                        self.lyrics_status = Some("No lyrics found for this track".to_string());
                     }
                 }
                 self.lyrics_rx = None;
             }
         }
 
-        // Check for playlist results
-        if let Some(rx) = &self.playlist_rx {
-            if let Ok(result) = rx.try_recv() {
+        // Check for chapter markers
+        if let Some(rx) = &self.chapters_rx {
+            if let Ok((track_id, result)) = rx.try_recv() {
+                self.chapters_rx = None;
                 match result {
-                    Ok(page) => {
-                        self.playlist_items = page.items;
-                        if !self.playlist_items.is_empty() {
-                            self.playlist_state.select(Some(0));
-                        }
+                    Ok(chapters) => self.player.set_chapters(track_id, chapters),
+                    Err(e) => tracing::debug!("No chapters for {}: {}", track_id.0, e),
+                }
+            }
+        }
 
-                        // Handle pending playlist play
-                        if let Some(name) = self.pending_playlist_play.take() {
-                            if let Some(playlist) =
-                                self.playlist_items.iter().find(|p| p.name == name)
-                            {
-                                self.pending_view_play = true;
-                                self.load_playlist_tracks(playlist.id.clone(), playlist.name.clone());
-                            } else {
-                                self.error_message =
-                                    Some(format!("Playlist '{}' not found", name));
-                                self.error_timeout =
-                                    Some(Instant::now() + Duration::from_secs(5));
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        // Only show error if playlists are supported
-                        // If NotSupported, we just show empty list or "Not supported" message in render
-                        // But here we just log/toast
-                        self.error_message = Some(format!("Playlist load failed: {}", e));
-                        self.error_timeout = Some(Instant::now() + Duration::from_secs(5));
-                        self.pending_playlist_play = None;
+        // Check for artwork results
+        if let Some(rx) = &self.artwork_rx {
+            if let Ok((album_key, result)) = rx.try_recv() {
+                self.artwork = result.ok().map(|artwork| (album_key, artwork));
+                self.artwork_rx = None;
+            }
+        }
+
+        // Check for playlist results
+        if let Some(result) = self.playlist_request.poll() {
+            match result {
+                Ok(page) => {
+                    let first = self.playlist_paging.loading_first();
+                    self.playlist_paging.receive(&mut self.playlist_items, page);
+                    if first && !self.playlist_items.is_empty() {
+                        self.playlist_state.select(Some(0));
                     }
                 }
+                Err(e) => {
+                    self.playlist_paging.fail();
+                    // Only show error if playlists are supported
+                    // If NotSupported, we just show empty list or "Not supported" message in render
+                    // But here we just log/toast
+                    self.show_toast(Toast::provider("Playlists", &e));
+                }
             }
         }
 
         // Check for library results
-        if let Some(rx) = &self.library_rx {
-            if let Ok(result) = rx.try_recv() {
-                match result {
-                    Ok(page) => {
-                        self.library_items = page.items;
-                        if !self.library_items.is_empty() {
-                            self.library_state.select(Some(0));
-                        }
-                    }
-                    Err(e) => {
-                        self.error_message = Some(format!("Library load failed: {}", e));
-                        self.error_timeout = Some(Instant::now() + Duration::from_secs(5));
+        if let Some(result) = self.library_request.poll() {
+            match result {
+                Ok(page) => {
+                    let first = self.library_paging.loading_first();
+                    self.library_paging.receive(&mut self.library_items, page);
+                    if first && !self.library_items.is_empty() {
+                        self.library_state.select(Some(0));
                     }
                 }
+                Err(tunez_core::ProviderError::NotSupported { .. }) => {
+                    self.library_paging.fail();
+                    self.library_items.clear();
+                    self.library_status = Some(format!(
+                        "{} can't be browsed with this provider",
+                        self.library_view.label()
+                    ));
+                }
+                Err(e) => {
+                    self.library_paging.fail();
+                    self.show_toast(Toast::provider("Library", &e));
+                }
             }
         }
 
         // Check for search results
-        if let Some(rx) = &self.search_rx {
-            if let Ok(result) = rx.try_recv() {
-                match result {
-                    Ok(tracks) => {
-                        self.search_results = tracks;
-                        if !self.search_results.is_empty() {
-                            self.search_state.select(Some(0));
-                            // Handle pending search play
-                            if self.pending_search_play {
-                                self.pending_search_play = false;
-                                let track = self.search_results[0].clone();
-                                self.play_track(track);
-                            }
-                        } else if self.pending_search_play {
-                             self.pending_search_play = false;
-                             self.error_message = Some("No tracks found".to_string());
-                             self.error_timeout = Some(Instant::now() + Duration::from_secs(5));
-                        }
+        if let Some(result) = self.search_request.poll() {
+            match result {
+                Ok(page) => {
+                    let first = self.search_paging.loading_first();
+                    self.search_paging.receive(&mut self.search_results, page);
+                    if first && !self.search_results.is_empty() {
+                        self.search_state.select(Some(0));
                     }
-                    Err(e) => {
-                        self.error_message = Some(format!("Search failed: {}", e));
-                        self.error_timeout = Some(Instant::now() + Duration::from_secs(5));
-                        self.pending_search_play = false;
+                }
+                Err(e) => {
+                    self.search_paging.fail();
+                    self.show_toast(Toast::provider("Search", &e));
+                }
+            }
+        }
+
+        if let Some(result) = self.search_collections_request.poll() {
+            match result {
+                Ok(page) => {
+                    let first = self.search_paging.loading_first();
+                    self.search_paging
+                        .receive(&mut self.search_collections, page);
+                    if first && !self.search_collections.is_empty() {
+                        self.search_state.select(Some(0));
                     }
                 }
-                // Clear the receiver as we're done with this search
-                // We can't easily clear it here due to borrow checker if we iterate.
-                // But we are not iterating.
+                Err(e) => {
+                    self.search_paging.fail();
+                    self.show_toast(Toast::provider("Search", &e));
+                }
             }
         }
 
         // Check for album tracks results
-        if let Some(rx) = &self.album_tracks_rx {
-            if let Ok(result) = rx.try_recv() {
-                match result {
-                    Ok(page) => {
-                        self.album_tracks = page.items;
+        if let Some(result) = self.album_tracks_request.poll() {
+            match result {
+                Ok((playlist, page)) => {
+                    if let Some(playlist) = playlist {
+                        self.current_album_name = Some(playlist.name.clone());
+                        if let Some(listed) =
+                            self.playlist_items.iter_mut().find(|p| p.id == playlist.id)
+                        {
+                            *listed = playlist;
+                        }
+                    }
+                    let first = self.album_tracks_paging.loading_first();
+                    self.album_tracks_paging
+                        .receive(&mut self.album_tracks, page);
+                    if first {
                         if !self.album_tracks.is_empty() {
                             self.album_tracks_state.select(Some(0));
+                        } else if matches!(
+                            self.library_view,
+                            LibraryView::RecentlyPlayed | LibraryView::MostPlayed
+                        ) {
+                            self.library_status = Some("Nothing played yet".into());
                         }
-                        self.viewing_album_tracks = true;
-
-                        // Handle pending play (for playlists or albums if implemented)
-                        if self.pending_view_play {
-                            self.pending_view_play = false;
-                            
-                            // Replace queue with these tracks
-                            self.player.stop();
-                            self.player.queue_mut().clear();
-                            for track in &self.album_tracks {
-                                self.player.queue_mut().enqueue_back(track.clone());
-                            }
-                            // Play first
-                            if !self.album_tracks.is_empty() {
-                                self.play_queue_item(0);
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        self.error_message = Some(format!("Album tracks load failed: {}", e));
-                        self.error_timeout = Some(Instant::now() + Duration::from_secs(5));
-                        self.pending_view_play = false;
                     }
+                    self.viewing_album_tracks = true;
+                }
+                Err(e) => {
+                    self.album_tracks_paging.fail();
+                    self.show_toast(Toast::provider("Tracks", &e));
                 }
             }
         }
 
-        // Check for error messages
-        while let Ok(msg) = self.error_rx.try_recv() {
-            self.error_message = Some(msg);
-            self.error_timeout = Some(Instant::now() + Duration::from_secs(5));
+        // Check for favorites
+        if let Some(rx) = &self.favorites_rx {
+            if let Ok(result) = rx.try_recv() {
+                self.favorites_rx = None;
+                match result {
+                    Ok(tracks) => self.favorites = tracks.into_iter().map(|t| t.id).collect(),
+                    Err(e) => tracing::warn!("Failed to load favorites: {}", e),
+                }
+            }
         }
-
-        // Clear error message if timeout expired
-        if let Some(timeout) = self.error_timeout {
-            if Instant::now() > timeout {
-                self.error_message = None;
-                self.error_timeout = None;
+        if let Some(rx) = &self.favorite_rx {
+            if let Ok((track, favorite, result)) = rx.try_recv() {
+                self.favorite_rx = None;
+                let toast = match result {
+                    Ok(()) if favorite => {
+                        let toast = Toast::info(format!("Added '{}' to favorites", track.title));
+                        self.favorites.insert(track.id);
+                        toast
+                    }
+                    Ok(()) => {
+                        self.favorites.remove(&track.id);
+                        Toast::info(format!("Removed '{}' from favorites", track.title))
+                    }
+                    Err(e) => Toast::provider("Favorite", &e),
+                };
+                self.show_toast(toast);
             }
         }
-    }
+
+        if let Some(rx) = &self.track_list_play_rx {
+            if let Ok((index, result)) = rx.try_recv() {
+                self.track_list_play_rx = None;
+                match result {
+                    Ok(tracks) if index < tracks.len() => self.play_track_list(tracks, index),
+                    Ok(_) => self.show_toast(Toast::info("The track list changed; try again")),
+                    Err(e) => self.show_toast(Toast::provider("Tracks", &e)),
+                }
+            }
+        }
+
+        // Check for the resolved `tunez play` selector
+        if let Some(rx) = &self.initial_play_rx {
+            if let Ok(result) = rx.try_recv() {
+                self.initial_play_rx = None;
+                match result {
+                    Ok(tracks) => {
+                        let mut queue = tunez_player::Queue::new();
+                        queue.enqueue_all(tracks, QueuePosition::Back);
+                        self.player.load_queue(queue);
+                        self.play_queue_item(0);
+                    }
+                    Err(ResolveError::Provider(e)) => {
+                        self.show_toast(Toast::provider("Nothing to play", &e));
+                    }
+                    Err(e) => self.show_toast(Toast::failure(format!("Nothing to play: {e}"), &e)),
+                }
+            }
+        }
+
+        // Check for error messages
+        while let Ok(toast) = self.error_rx.try_recv() {
+            self.show_toast(toast);
+        }
+
+        // Clear the toast once its time is up
+        if let Some(timeout) = self.toast_timeout {
+            if Instant::now() > timeout {
+                self.toast = None;
+                self.toast_timeout = None;
+            }
+        }
+        self.net_health.poll(Instant::now());
+        if let Err(e) = self.queue_saver.tick(self.player.queue(), Instant::now()) {
+            self.show_toast(Toast::failure("Couldn't save the queue", e));
+        }
+        self.load_more();
+    }
 
     fn style_fg(&self, color: Color) -> Style {
         if self.use_color {
@@ -614,11 +1309,19 @@ impl App {
         }
     }
 
+    /// Mark the queue for saving; `tick` writes it once changes settle.
     fn save_queue(&mut self) {
-        if let Err(e) = self.queue_persistence.save(self.player.queue()) {
-            self.error_message = Some(format!("Failed to save queue: {}", e));
-            self.error_timeout = Some(Instant::now() + Duration::from_secs(5));
+        self.queue_saver.mark_dirty(Instant::now());
+    }
+
+    /// Show `toast` in the player bar for as long as its severity calls for,
+    /// logging the error behind it.
+    fn show_toast(&mut self, toast: Toast) {
+        if let Some(detail) = &toast.detail {
+            tracing::warn!(error = %detail, "{}", toast.message);
         }
+        self.toast_timeout = Some(Instant::now() + toast.severity.duration());
+        self.toast = Some(toast);
     }
 
     fn handle_key(&mut self, key: KeyEvent) -> bool {
@@ -645,6 +1348,9 @@ impl App {
                 KeyCode::Backspace => {
                     self.search_query.pop();
                 }
+                KeyCode::Tab => {
+                    self.search_scope = self.search_scope.next();
+                }
                 KeyCode::Char(c) => {
                     self.search_query.push(c);
                 }
@@ -653,19 +1359,63 @@ impl App {
             return false;
         }
 
-        match key.code {
-            KeyCode::Char('q') | KeyCode::Esc => {
-                self.save_queue();
-                return true;
+        // Handle list filter input; Esc clears the filter even once applied
+        if let Some(filter) = self.list_filter.as_mut() {
+            let typing = filter.is_typing();
+            match key.code {
+                KeyCode::Esc => {
+                    self.close_filter();
+                    return false;
+                }
+                KeyCode::Enter if typing => {
+                    filter.set_typing(false);
+                    return false;
+                }
+                KeyCode::Backspace if typing => {
+                    filter.pop();
+                    self.refilter();
+                    return false;
+                }
+                KeyCode::Char(c) if typing => {
+                    filter.push(c);
+                    self.refilter();
+                    return false;
+                }
+                KeyCode::Up | KeyCode::Down => {}
+                _ if typing => return false,
+                _ => {}
+            }
+            let delta = match key.code {
+                KeyCode::Char('j') | KeyCode::Down => Some(1),
+                KeyCode::Char('k') | KeyCode::Up => Some(-1),
+                _ => None,
+            };
+            if let Some(delta) = delta {
+                self.with_filter(|filter, state, visible| filter.step(state, visible, delta, true));
+                return false;
             }
-            KeyCode::Char('?') => self.show_help = !self.show_help,
+        }
+
+        if matches!(key.code, KeyCode::Backspace | KeyCode::Esc)
+            && self.tabs[self.active_tab] == Tab::Library
+            && !self.library_stack.is_empty()
+        {
+            self.library_pop();
+            return false;
+        }
+
+        if let Some(action) = self.keymap.action(&key) {
+            return self.run_action(action);
+        }
+
+        match key.code {
             KeyCode::Char('j') | KeyCode::Down => {
                 let tab = self.tabs[self.active_tab];
                 let mut handled = false;
-                if tab == Tab::Search && !self.search_results.is_empty() {
+                if tab == Tab::Search && self.search_len() > 0 {
                     let i = match self.search_state.selected() {
                         Some(i) => {
-                            if i >= self.search_results.len() - 1 {
+                            if i >= self.search_len() - 1 {
                                 0
                             } else {
                                 i + 1
@@ -702,10 +1452,10 @@ impl App {
                         };
                         self.album_tracks_state.select(Some(i));
                         handled = true;
-                    } else if !self.library_items.is_empty() {
+                    } else if self.library_len() > 0 {
                         let i = match self.library_state.selected() {
                             Some(i) => {
-                                if i >= self.library_items.len() - 1 {
+                                if i >= self.library_len() - 1 {
                                     0
                                 } else {
                                     i + 1
@@ -751,11 +1501,11 @@ impl App {
             KeyCode::Char('k') | KeyCode::Up => {
                 let tab = self.tabs[self.active_tab];
                 let mut handled = false;
-                if tab == Tab::Search && !self.search_results.is_empty() {
+                if tab == Tab::Search && self.search_len() > 0 {
                     let i = match self.search_state.selected() {
                         Some(i) => {
                             if i == 0 {
-                                self.search_results.len() - 1
+                                self.search_len() - 1
                             } else {
                                 i - 1
                             }
@@ -791,11 +1541,11 @@ impl App {
                         };
                         self.album_tracks_state.select(Some(i));
                         handled = true;
-                    } else if !self.library_items.is_empty() {
+                    } else if self.library_len() > 0 {
                         let i = match self.library_state.selected() {
                             Some(i) => {
                                 if i == 0 {
-                                    self.library_items.len() - 1
+                                    self.library_len() - 1
                                 } else {
                                     i - 1
                                 }
@@ -839,28 +1589,17 @@ impl App {
             }
             KeyCode::Char('h') | KeyCode::BackTab => self.previous_tab(),
             KeyCode::Char('l') | KeyCode::Tab => self.next_tab(),
+            // Alt+digit seeks to that tenth of the track; alt+0 is the start
+            KeyCode::Char(c) if c.is_ascii_digit() && key.modifiers.contains(KeyModifiers::ALT) => {
+                let tenths = c.to_digit(10).unwrap_or(0);
+                self.seek_to_fraction(tenths as f32 / 10.0);
+            }
             KeyCode::Char(c) if c.is_ascii_digit() => self.jump_to_tab(c),
             // Backspace - go back from album tracks view
-            KeyCode::Backspace => {
-                if self.viewing_album_tracks {
-                    self.viewing_album_tracks = false;
-                    self.album_tracks.clear();
-                    self.album_tracks_state = ratatui::widgets::ListState::default();
-                    self.album_tracks_rx = None;
-                    self.current_album_id = None;
-                    self.current_album_name = None;
-                }
-            }
-            // Search mode
-            KeyCode::Char('/') => {
-                // Switch to search tab
-                if let Some(search_idx) = self.tabs.iter().position(|t| matches!(t, Tab::Search)) {
-                    self.active_tab = search_idx;
-                    self.is_searching = true;
-                    self.search_query.clear();
-                }
+            KeyCode::Backspace if self.viewing_album_tracks => {
+                self.list_filter = None;
+                self.close_track_list();
             }
-
             KeyCode::Enter => {
                 let tab = self.tabs[self.active_tab];
                 match tab {
@@ -868,25 +1607,20 @@ impl App {
                         if let Some(i) = self.config_state.selected() {
                             if i < self.config_items.len() {
                                 match self.config_items[i] {
+                                    "Provider" => self.cycle_provider(),
                                     "Theme" => {
-                                        // Cycle theme
-                                        let themes = Theme::available_themes();
-                                        let current_theme_name = match self.theme.primary {
-                                            Color::Cyan => "default",
-                                            Color::White => "monochrome",
-                                            Color::LightMagenta => "afterdark",
-                                            _ => "default",
-                                        };
-                                        let current_idx = themes
-                                            .iter()
-                                            .position(|&t| t == current_theme_name)
-                                            .unwrap_or(0);
-                                        let next_idx = (current_idx + 1) % themes.len();
-                                        if let Some(new_theme) = Theme::parse(themes[next_idx]) {
-                                            self.theme = new_theme;
-                                            self.use_color = new_theme.is_color;
-                                        }
+                                        self.cycle_theme();
+                                        let name = self.theme.name;
+                                        self.save_config(|config| match &mut config.theme {
+                                            Some(theme) => theme.set_name(name),
+                                            None => {
+                                                config.theme = Some(tunez_core::ThemeSetting::Name(
+                                                    name.into(),
+                                                ))
+                                            }
+                                        });
                                     }
+                                    "Log Level" => self.cycle_log_level(),
                                     "Visualizer Mode" => {
                                         if let Ok(mut viz_guard) = self.visualizer.lock() {
                                             let current_mode = viz_guard.mode();
@@ -899,71 +1633,78 @@ impl App {
                                             viz_guard.set_mode(all_modes[next_idx]);
                                         }
                                     }
-                                    "Scrobbling" => {
-                                        // Toggle if allowed? For now just log intent or toggle enabled.
-                                        let is_active = self.scrobbler_manager.is_active();
-                                        // Note: ScrobblerManager doesn't expose enable toggling easily if we don't track it,
-                                        // but we can call set_enabled.
-                                        // But wait, is_active check checks internal atomic boolean.
-                                        // We can toggle it.
-                                        self.scrobbler_manager.set_enabled(!is_active);
-                                    }
+                                    "Scrobbling" => self.toggle_scrobbling(),
                                     _ => {}
                                 }
                             }
                         }
                     }
                     Tab::Search => {
-                        // Play selected track
-                        if let Some(i) = self.search_state.selected() {
-                            if i < self.search_results.len() {
-                                let track = self.search_results[i].clone();
+                        let Some(i) = self.search_state.selected() else {
+                            return false;
+                        };
+                        if self.search_scope == SearchScope::Tracks {
+                            // Play selected track
+                            if let Some(track) = self.search_results.get(i).cloned() {
                                 self.play_track(track);
                             }
+                        } else {
+                            // Open the album or artist in the Library tab
+                            let view = match self.search_collections.get(i) {
+                                Some(tunez_core::CollectionItem::Album(album)) => {
+                                    LibraryView::Album {
+                                        id: album.id.clone(),
+                                        title: album.title.clone(),
+                                    }
+                                }
+                                Some(tunez_core::CollectionItem::Artist { name, .. }) => {
+                                    LibraryView::Artist(name.clone())
+                                }
+                                _ => return false,
+                            };
+                            if let Some(idx) = self.tabs.iter().position(|t| *t == Tab::Library) {
+                                self.active_tab = idx;
+                                self.on_tab_changed();
+                                self.library_push(view);
+                            }
                         }
                     }
                     Tab::Library => {
                         if self.viewing_album_tracks {
-                            // Play selected track from album
                             if let Some(i) = self.album_tracks_state.selected() {
-                                if i < self.album_tracks.len() {
-                                    let track = self.album_tracks[i].clone();
-                                    self.play_track(track);
-                                }
+                                self.play_from_track_list(i);
                             }
+                        } else if let Some(shortcut) = self
+                            .library_state
+                            .selected()
+                            .and_then(|i| self.library_shortcuts().get(i).cloned())
+                        {
+                            self.library_push(shortcut);
                         } else {
-                            // Browse selected item
+                            // Browse selected item, past the shortcut rows
+                            let offset = self.library_shortcuts().len();
                             if let Some(i) = self.library_state.selected() {
+                                let i = i - offset;
                                 if i < self.library_items.len() {
                                     match &self.library_items[i] {
                                         tunez_core::CollectionItem::Album(album) => {
-                                            // Load album tracks
-                                            self.load_album_tracks(
-                                                album.id.clone(),
-                                                album.title.clone(),
-                                            );
+                                            self.library_push(LibraryView::Album {
+                                                id: album.id.clone(),
+                                                title: album.title.clone(),
+                                            });
                                         }
                                         tunez_core::CollectionItem::Playlist(playlist) => {
                                             // For now, show a message - playlist browsing is handled in Playlists tab
-                                            self.error_message = Some(format!(
+                                            self.show_toast(Toast::info(format!(
                                                 "Use Playlists tab for playlist: {}",
                                                 playlist.name
-                                            ));
-                                            self.error_timeout =
-                                                Some(Instant::now() + Duration::from_secs(3));
+                                            )));
                                         }
                                         tunez_core::CollectionItem::Artist {
                                             name,
                                             provider_id: _,
                                         } => {
-                                            // Search for tracks by this artist
-                                            self.search_query = format!("artist:{}", name);
-                                            self.perform_search();
-                                            if let Some(idx) =
-                                                self.tabs.iter().position(|t| matches!(t, Tab::Search))
-                                            {
-                                                self.active_tab = idx;
-                                            }
+                                            self.library_push(LibraryView::Artist(name.clone()));
                                         }
                                         tunez_core::CollectionItem::Genre {
                                             name,
@@ -971,6 +1712,7 @@ impl App {
                                         } => {
                                             // Same for genre
                                             self.search_query = format!("genre:{}", name);
+                                            self.search_scope = SearchScope::Tracks;
                                             self.perform_search();
                                             if let Some(idx) =
                                                 self.tabs.iter().position(|t| matches!(t, Tab::Search))
@@ -991,10 +1733,7 @@ impl App {
                     Tab::Playlists => {
                         if self.viewing_album_tracks {
                             if let Some(i) = self.album_tracks_state.selected() {
-                                if i < self.album_tracks.len() {
-                                    let track = self.album_tracks[i].clone();
-                                    self.play_track(track);
-                                }
+                                self.play_from_track_list(i);
                             }
                         } else if let Some(i) = self.playlist_state.selected() {
                             if i < self.playlist_items.len() {
@@ -1009,9 +1748,7 @@ impl App {
             // Queue specific physical actions
             KeyCode::Char('d') if self.tabs[self.active_tab] == Tab::Queue => {
                 if let Some(i) = self.queue_state.selected() {
-                    if let Some(item) = self.player.queue().items().get(i) {
-                        let id = item.id;
-                        self.player.queue_mut().remove(id);
+                    if self.player.queue_mut().remove_at(i).is_some() {
                         let len = self.player.queue().len();
                         if len == 0 {
                             self.queue_state.select(None);
@@ -1022,14 +1759,73 @@ impl App {
                     }
                 }
             }
+            KeyCode::Char('K') | KeyCode::Char('J') if self.tabs[self.active_tab] == Tab::Queue => {
+                if let Some(i) = self.queue_state.selected() {
+                    let target = if key.code == KeyCode::Char('K') {
+                        i.checked_sub(1)
+                    } else {
+                        Some(i + 1)
+                    };
+                    if let Some(target) = target {
+                        if self.player.queue_mut().move_item(i, target) {
+                            self.queue_state.select(Some(target));
+                            self.save_queue();
+                        }
+                    }
+                }
+            }
+            KeyCode::Char('o')
+                if self.tabs[self.active_tab] == Tab::Library && !self.viewing_album_tracks =>
+            {
+                self.cycle_library_sort();
+            }
             KeyCode::Char('c') if self.tabs[self.active_tab] == Tab::Queue => {
                 self.player.queue_mut().clear();
                 self.queue_state.select(None);
                 self.save_queue();
             }
 
-            // Visualization mode switching (global shortcut)
-            KeyCode::Char('v') => {
+            // Seek backward/forward: arrows on Now Playing, brackets anywhere
+            KeyCode::Left if self.tabs[self.active_tab] == Tab::NowPlaying => {
+                self.seek_by(-SEEK_STEP_SECS)
+            }
+            KeyCode::Right if self.tabs[self.active_tab] == Tab::NowPlaying => {
+                self.seek_by(SEEK_STEP_SECS)
+            }
+            _ => {}
+        }
+        false
+    }
+
+    /// Run a key-bound global action. Returns `true` when the app should exit.
+    fn run_action(&mut self, action: Action) -> bool {
+        match action {
+            Action::Quit => return true,
+            Action::Help => self.show_help = !self.show_help,
+            // Search mode
+            // On list tabs, narrow the loaded rows instead
+            Action::Search
+                if matches!(
+                    self.tabs[self.active_tab],
+                    Tab::Library | Tab::Playlists | Tab::Queue
+                ) =>
+            {
+                match &mut self.list_filter {
+                    Some(filter) => filter.set_typing(true),
+                    None => self.list_filter = Some(ListFilter::new()),
+                }
+            }
+            Action::Search => {
+                // Switch to search tab
+                if let Some(search_idx) = self.tabs.iter().position(|t| matches!(t, Tab::Search)) {
+                    self.active_tab = search_idx;
+                    self.is_searching = true;
+                    self.search_query.clear();
+                }
+            }
+
+            // Visualization mode switching
+            Action::CycleViz => {
                 // Cycle through visualization modes
                 if let Ok(mut viz_guard) = self.visualizer.lock() {
                     let current_mode = viz_guard.mode();
@@ -1043,32 +1839,16 @@ impl App {
                 }
             }
             // Theme switching
-            KeyCode::Char('t') => {
-                // Cycle through available themes
-                let themes = Theme::available_themes();
-                let current_theme_name = match self.theme.primary {
-                    Color::Cyan => "default",
-                    Color::White => "monochrome",
-                    Color::LightMagenta => "afterdark",
-                    _ => "default",
-                };
-                let current_idx = themes
-                    .iter()
-                    .position(|&t| t == current_theme_name)
-                    .unwrap_or(0);
-                let next_idx = (current_idx + 1) % themes.len();
-                if let Some(new_theme) = Theme::parse(themes[next_idx]) {
-                    self.theme = new_theme;
-                    tracing::info!("Switched to theme: {}", themes[next_idx]);
-                }
-            }
+            Action::CycleTheme => self.cycle_theme(),
             // Playback controls
-            KeyCode::Char(' ') => match self.player.state() {
+            Action::PlayPause => match self.player.state() {
                 tunez_player::PlayerState::Playing { .. } => {
                     self.player.pause();
                     self.scrobbler_manager
                         .on_state_change(&self.player, tunez_core::PlaybackState::Paused);
                 }
+                // Starting over would throw away the stream being fetched.
+                tunez_player::PlayerState::Buffering { .. } => {}
                 _ => {
                     self.player.play();
                     if let tunez_player::PlayerState::Playing { .. } = self.player.state() {
@@ -1091,62 +1871,491 @@ impl App {
                     }
                 }
             },
-            KeyCode::Char('n') => {
+            Action::Next => {
                 // Scrobble stop for current track before skipping
                 self.scrobbler_manager
                     .on_state_change(&self.player, tunez_core::PlaybackState::Stopped);
                 self.player.skip_next();
+                self.request_stream_for_current();
                 // Scrobble start for new track
                 self.scrobbler_manager
                     .on_state_change(&self.player, tunez_core::PlaybackState::Started);
                 self.save_queue();
             }
-            KeyCode::Char('p') => {
-                // Scrobble stop for current track before skipping
-                if self.player.current().is_some() {
-                    self.scrobbler_manager
-                        .on_state_change(&self.player, tunez_core::PlaybackState::Stopped);
+            Action::Prev => {
+                // Nothing to go back to: leave the current track untouched
+                if self.player.history().next().is_none() {
+                    return false;
                 }
-                self.player.skip_previous();
-                // Scrobble start for previous track
-                if self.player.current().is_some() {
+                // Scrobble stop for current track before skipping
+                self.scrobbler_manager
+                    .on_state_change(&self.player, tunez_core::PlaybackState::Stopped);
+                if self.player.skip_previous().is_some() {
+                    self.request_stream_for_current();
+                    // Scrobble start for previous track
                     self.scrobbler_manager
                         .on_state_change(&self.player, tunez_core::PlaybackState::Started);
                 }
                 self.save_queue();
             }
-            // Seek backward/forward with arrow keys
-            KeyCode::Left => {
-                let current_pos = self.player.position();
-                let new_pos = current_pos.saturating_sub(Duration::from_secs(5));
-                self.player.seek(new_pos);
+            Action::CycleRepeat => {
+                let mode = self.player.queue().repeat_mode().cycle();
+                self.player.queue_mut().set_repeat_mode(mode);
+                self.save_queue();
             }
-            KeyCode::Right => {
-                let current_pos = self.player.position();
-                let new_pos = current_pos + Duration::from_secs(5);
-                self.player.seek(new_pos);
+            Action::ToggleShuffle => {
+                let shuffle = !self.player.queue().is_shuffled();
+                self.player.queue_mut().set_shuffle(shuffle);
+                self.save_queue();
             }
-            _ => {}
+            Action::SeekBack => self.seek_by(-SEEK_STEP_SECS),
+            Action::SeekForward => self.seek_by(SEEK_STEP_SECS),
+            Action::SeekStart => self.seek_to_fraction(0.0),
+            Action::SeekEnd => self.seek_to_fraction(1.0),
+            Action::NextChapter => {
+                if self.player.chapters().is_empty() {
+                    self.show_toast(Toast::info("This track has no chapters"));
+                } else if let Some(position) = self.player.next_chapter() {
+                    self.scrobbler_manager.on_seek(position.as_secs());
+                }
+            }
+            Action::PrevChapter => {
+                if self.player.chapters().is_empty() {
+                    self.show_toast(Toast::info("This track has no chapters"));
+                } else if let Some(position) = self.player.previous_chapter() {
+                    self.scrobbler_manager.on_seek(position.as_secs());
+                }
+            }
+            Action::ToggleFavorite => self.toggle_favorite(),
         }
         false
     }
 
+    /// Carry out media-key requests from D-Bus and publish the player state.
+    #[cfg(feature = "mpris")]
+    fn sync_mpris(&mut self) {
+        use crate::mpris::MprisCommand;
+
+        let Some(mpris) = &self.mpris else {
+            return;
+        };
+        let commands: Vec<_> = mpris.commands().collect();
+        for command in commands {
+            let playing = matches!(
+                self.player.state(),
+                tunez_player::PlayerState::Playing { .. }
+            );
+            match command {
+                MprisCommand::PlayPause => {
+                    self.run_action(Action::PlayPause);
+                }
+                MprisCommand::Play if !playing => {
+                    self.run_action(Action::PlayPause);
+                }
+                MprisCommand::Pause if playing => {
+                    self.run_action(Action::PlayPause);
+                }
+                MprisCommand::Play | MprisCommand::Pause => {}
+                MprisCommand::Stop => {
+                    self.scrobbler_manager
+                        .on_state_change(&self.player, tunez_core::PlaybackState::Stopped);
+                    self.player.stop();
+                }
+                MprisCommand::Next => {
+                    self.run_action(Action::Next);
+                }
+                MprisCommand::Previous => {
+                    self.run_action(Action::Prev);
+                }
+                MprisCommand::Seek(offset_us) => {
+                    let step = Duration::from_micros(offset_us.unsigned_abs());
+                    let current = self.player.position();
+                    let target = if offset_us < 0 {
+                        current.saturating_sub(step)
+                    } else {
+                        current + step
+                    };
+                    self.seek_to(target);
+                }
+                MprisCommand::SetPosition(entry, position) => {
+                    if self.player.current().is_some_and(|item| item.id == entry) {
+                        self.seek_to(position);
+                    }
+                }
+            }
+        }
+        if let Some(mpris) = &mut self.mpris {
+            mpris.update(&self.player);
+        }
+    }
+
+    fn cycle_theme(&mut self) {
+        self.theme = self
+            .theme
+            .next(self.custom_theme.as_ref())
+            .for_depth(self.color_depth);
+        self.use_color = self.theme.is_color;
+        tracing::info!("Switched to theme: {}", self.theme.name);
+    }
+
+    /// Switch logging to the next level now and in the saved config.
+    fn cycle_log_level(&mut self) {
+        let Some(level) = self.config.as_ref().map(|c| c.logging.level.next()) else {
+            return;
+        };
+        if self.save_config(|config| config.logging.level = level) {
+            if let Err(e) = tunez_core::set_log_level(level) {
+                self.config_error = Some(e.to_string());
+            }
+        }
+    }
+
+    /// Turn scrobbling on or off now and in the saved config. Without a
+    /// scrobbler there is nothing to turn on, so the Config tab says so and
+    /// nothing is saved.
+    fn toggle_scrobbling(&mut self) {
+        if !self.scrobbler_manager.is_configured() {
+            self.config_error =
+                Some("No scrobbler configured; set [scrobbling] default in config.toml".into());
+            return;
+        }
+        let enabled = !self.scrobbler_manager.is_active();
+        self.scrobbler_manager.set_enabled(enabled);
+        self.save_config(|config| config.scrobbling.enabled = enabled);
+    }
+
+    /// Apply `change` to a copy of the config and write it out if it still
+    /// validates. Returns whether it was saved; otherwise the error is shown
+    /// in the Config tab and the loaded config is left as it was.
+    fn save_config(&mut self, change: impl FnOnce(&mut tunez_core::Config)) -> bool {
+        let Some(config) = &self.config else {
+            return false;
+        };
+        let mut updated = config.clone();
+        change(&mut updated);
+        match updated.save(&self.dirs) {
+            Ok(()) => {
+                self.config = Some(updated);
+                self.config_error = None;
+                true
+            }
+            Err(e) => {
+                tracing::warn!("config not saved: {e}");
+                self.config_error = Some(e.to_string());
+                false
+            }
+        }
+    }
+
+    /// Start the configured provider or profile after the current one. One
+    /// that fails to start is still selected, so the next Enter moves past it.
+    fn cycle_provider(&mut self) {
+        let Some(connect) = self.connect_provider.clone() else {
+            return;
+        };
+        if self.provider_options.is_empty() {
+            return;
+        }
+        let next = self
+            .provider_options
+            .iter()
+            .position(|option| *option == self.provider_selection)
+            .map_or(0, |i| (i + 1) % self.provider_options.len());
+        let selection = self.provider_options[next].clone();
+        let provider: Arc<dyn Provider> = match connect(&selection) {
+            Ok(provider) => {
+                self.provider_error = None;
+                provider
+            }
+            Err(error) => {
+                tracing::warn!(provider = %selection.provider_id, %error, "provider failed to start");
+                self.provider_error = Some(error.clone());
+                Arc::new(UnavailableProvider::new(
+                    selection.provider_id.clone(),
+                    error,
+                ))
+            }
+        };
+        self.use_provider(provider, selection);
+    }
+
+    /// Swap in `provider`, dropping everything loaded from the previous one.
+    fn use_provider(&mut self, provider: Arc<dyn Provider>, selection: ProviderSelection) {
+        tracing::info!(provider = %selection.provider_id, profile = ?selection.profile, "switched provider");
+        self.net_health = HealthMonitor::new(provider.clone());
+        self.capabilities = provider.capabilities();
+        self.provider = provider;
+        self.provider_selection = selection;
+        self.list_filter = None;
+
+        let active = self.tabs[self.active_tab];
+        self.tabs = Tab::for_capabilities(&self.capabilities);
+        self.active_tab = self.tabs.iter().position(|&tab| tab == active).unwrap_or(0);
+
+        self.library_view = LibraryView::Root;
+        self.library_stack.clear();
+        self.library_status = None;
+        self.library_items.clear();
+        self.library_state = ListState::default();
+        self.library_request.cancel();
+        self.library_paging = ListPaging::default();
+        self.close_track_list();
+
+        self.playlist_items.clear();
+        self.playlist_state = ListState::default();
+        self.playlist_request.cancel();
+        self.playlist_paging = ListPaging::default();
+
+        self.search_results.clear();
+        self.search_collections.clear();
+        self.search_state = ListState::default();
+        self.search_request.cancel();
+        self.search_collections_request.cancel();
+        self.search_paging = ListPaging::default();
+        self.search_interrupted = false;
+
+        self.favorites.clear();
+        self.favorites_rx = None;
+        if self.capabilities.supports_favorites() {
+            self.load_favorites();
+        }
+    }
+
+    fn seek_by(&mut self, delta_secs: i64) {
+        let current = self.player.position();
+        let step = Duration::from_secs(delta_secs.unsigned_abs());
+        let target = if delta_secs < 0 {
+            current.saturating_sub(step)
+        } else {
+            current + step
+        };
+        self.seek_to(target);
+    }
+
+    fn seek_to(&mut self, target: Duration) {
+        if let Some(position) = self.player.seek(target) {
+            self.scrobbler_manager.on_seek(position.as_secs());
+        }
+    }
+
+    /// Seek to `fraction` of the current track, or say why not when its
+    /// length is unknown.
+    fn seek_to_fraction(&mut self, fraction: f32) {
+        let Some(current) = self.player.current() else {
+            return;
+        };
+        if current.track.duration_seconds.is_none() {
+            self.show_toast(Toast::info(
+                "Track length unknown; seek with [ and ] instead",
+            ));
+            return;
+        }
+        if let Some(position) = self.player.seek_fraction(fraction) {
+            self.scrobbler_manager.on_seek(position.as_secs());
+        }
+    }
+
+    fn handle_mouse(&mut self, mouse: MouseEvent) {
+        if self.show_help || self.is_searching {
+            return;
+        }
+        let (column, row) = (mouse.column, mouse.row);
+        match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                let areas = self.click_areas;
+                if let Some(index) = tab_at(areas.nav, self.tabs.len(), column, row) {
+                    if index != self.active_tab {
+                        self.active_tab = index;
+                        self.on_tab_changed();
+                    }
+                } else if let Some(fraction) = areas
+                    .progress
+                    .and_then(|area| seek_fraction(area, column, row))
+                {
+                    if let Some(position) = self.player.seek_fraction(fraction) {
+                        self.scrobbler_manager.on_seek(position.as_secs());
+                    }
+                } else if let (Some(area), Some(visible)) = (areas.list, self.filtered_rows()) {
+                    let offset = self.list_filter.as_ref().map_or(0, ListFilter::offset);
+                    let index = list_row_at(area, offset, column, row).and_then(|i| visible.get(i));
+                    if let (Some(&index), Some((state, _))) = (index, self.active_list()) {
+                        state.select(Some(index));
+                    }
+                } else if let (Some(area), Some((state, len))) = (areas.list, self.active_list()) {
+                    if let Some(index) =
+                        list_row_at(area, state.offset(), column, row).filter(|i| *i < len)
+                    {
+                        state.select(Some(index));
+                    }
+                }
+            }
+            MouseEventKind::ScrollDown => self.scroll_selection(1),
+            MouseEventKind::ScrollUp => self.scroll_selection(-1),
+            _ => {}
+        }
+    }
+
+    /// The active tab's list selection and its length, if it has a non-empty list.
+    fn active_list(&mut self) -> Option<(&mut ListState, usize)> {
+        let list = match self.tabs[self.active_tab] {
+            Tab::Search => {
+                let len = self.search_len();
+                (&mut self.search_state, len)
+            }
+            Tab::Library | Tab::Playlists if self.viewing_album_tracks => {
+                (&mut self.album_tracks_state, self.album_tracks.len())
+            }
+            Tab::Library => {
+                let len = self.library_len();
+                (&mut self.library_state, len)
+            }
+            Tab::Playlists => (&mut self.playlist_state, self.playlist_items.len()),
+            Tab::Queue => (&mut self.queue_state, self.player.queue().len()),
+            Tab::Config => (&mut self.config_state, self.config_items.len()),
+            _ => return None,
+        };
+        (list.1 > 0).then_some(list)
+    }
+
+    /// Move the active list's selection by `delta` rows, stopping at the ends.
+    fn scroll_selection(&mut self, delta: isize) {
+        if self.list_filter.is_some() {
+            self.with_filter(|filter, state, visible| filter.step(state, visible, delta, false));
+        } else if let Some((state, len)) = self.active_list() {
+            let current = state.selected().unwrap_or(0);
+            state.select(Some(current.saturating_add_signed(delta).min(len - 1)));
+        }
+    }
+
+    /// Names the type-ahead filter matches, one per row of the active tab's
+    /// list, or `None` on tabs that can't be filtered.
+    fn filter_names(&self) -> Option<Vec<String>> {
+        let track_name = |track: &tunez_core::Track| format!("{} - {}", track.artist, track.title);
+        let names = match self.tabs[self.active_tab] {
+            Tab::Library | Tab::Playlists if self.viewing_album_tracks => {
+                self.album_tracks.iter().map(track_name).collect()
+            }
+            Tab::Library => {
+                let shortcuts = self.library_shortcuts();
+                shortcuts
+                    .iter()
+                    .map(|view| view.label().to_string())
+                    .chain(
+                        self.library_items
+                            .iter()
+                            .map(|item| collection_name(item).to_string()),
+                    )
+                    .collect()
+            }
+            Tab::Playlists => self.playlist_items.iter().map(|p| p.name.clone()).collect(),
+            Tab::Queue => self
+                .player
+                .queue()
+                .items()
+                .iter()
+                .map(|item| track_name(&item.track))
+                .collect(),
+            _ => return None,
+        };
+        Some(names)
+    }
+
+    /// Rows of the active list the filter shows, or `None` when unfiltered.
+    fn filtered_rows(&self) -> Option<Vec<usize>> {
+        let filter = self.list_filter.as_ref()?;
+        Some(filter.visible(&self.filter_names()?))
+    }
+
+    /// Run `f` with the filter, the active list's selection and the rows shown.
+    fn with_filter(&mut self, f: impl FnOnce(&mut ListFilter, &mut ListState, &[usize])) {
+        let Some(visible) = self.filtered_rows() else {
+            return;
+        };
+        let Some(mut filter) = self.list_filter.take() else {
+            return;
+        };
+        if let Some((state, _)) = self.active_list() {
+            f(&mut filter, state, &visible);
+        }
+        self.list_filter = Some(filter);
+    }
+
+    /// Keep the selection on a shown row after the filter's query changed.
+    fn refilter(&mut self) {
+        self.with_filter(|filter, state, visible| {
+            filter.set_offset(0);
+            filter.reselect(state, visible);
+        });
+    }
+
+    /// Show the whole list again, keeping the selection where possible.
+    fn close_filter(&mut self) {
+        if let Some(filter) = self.list_filter.take() {
+            if let Some((state, _)) = self.active_list() {
+                filter.restore(state);
+            }
+        }
+    }
+
     fn perform_search(&mut self) {
+        self.search_state = ListState::default();
+        self.search_submitted = (self.search_query.clone(), self.search_scope);
+        self.search_interrupted = false;
+        let paging = self.search_paging.first(PAGE_SIZE);
+        self.fetch_search(paging);
+    }
+
+    fn fetch_search(&mut self, paging: tunez_core::PageRequest) {
         let provider = self.provider.clone();
-        let query = self.search_query.clone();
-        let (tx, rx) = mpsc::channel();
-        self.search_rx = Some(rx);
+        let (query, scope) = self.search_submitted.clone();
+        let key = (query.clone(), scope, paging);
 
-        tokio::task::spawn_blocking(move || {
-            let result = provider
-                .search_tracks(
-                    &query,
-                    tunez_core::TrackSearchFilters::default(),
-                    tunez_core::PageRequest::first_page(50),
-                )
-                .map(|page| page.items);
-            let _ = tx.send(result);
-        });
+        if scope == SearchScope::Tracks {
+            self.search_collections_request.cancel();
+            self.search_request.start(key, move || {
+                provider.search_tracks(&query, tunez_core::TrackSearchFilters::default(), paging)
+            });
+            return;
+        }
+
+        self.search_request.cancel();
+        self.search_collections_request
+            .start(key, move || match scope {
+                SearchScope::Albums => {
+                    provider
+                        .search_albums(&query, paging)
+                        .map(|page| tunez_core::Page {
+                            items: page
+                                .items
+                                .into_iter()
+                                .map(tunez_core::CollectionItem::Album)
+                                .collect(),
+                            next: page.next,
+                        })
+                }
+                _ => provider.search_artists(&query, paging).map(|page| {
+                    let provider_id = provider.id().to_string();
+                    tunez_core::Page {
+                        items: page
+                            .items
+                            .into_iter()
+                            .map(|name| tunez_core::CollectionItem::Artist {
+                                name,
+                                provider_id: provider_id.clone(),
+                            })
+                            .collect(),
+                        next: page.next,
+                    }
+                }),
+            });
+    }
+
+    /// Rows in the Search results list for the current scope.
+    fn search_len(&self) -> usize {
+        match self.search_scope {
+            SearchScope::Tracks => self.search_results.len(),
+            SearchScope::Albums | SearchScope::Artists => self.search_collections.len(),
+        }
     }
 
     fn next_tab(&mut self) {
@@ -1177,52 +2386,114 @@ impl App {
     }
 
     fn load_playlists(&mut self) {
-        let provider = self.provider.clone();
-        let (tx, rx) = mpsc::channel();
-        self.playlist_rx = Some(rx);
+        let paging = self.playlist_paging.first(PAGE_SIZE);
+        self.fetch_playlists(paging);
+    }
 
-        tokio::task::spawn_blocking(move || {
-            let result = provider.list_playlists(tunez_core::PageRequest::first_page(50));
-            let _ = tx.send(result);
-        });
+    fn fetch_playlists(&mut self, paging: tunez_core::PageRequest) {
+        let provider = self.provider.clone();
+        self.playlist_request
+            .start(paging, move || provider.list_playlists(paging));
     }
 
     fn load_playlist_tracks(&mut self, playlist_id: tunez_core::PlaylistId, playlist_name: String) {
-        let provider = self.provider.clone();
-        let (tx, rx) = mpsc::channel();
-        self.album_tracks_rx = Some(rx);
+        self.list_filter = None;
         self.viewing_album_tracks = true;
         self.album_tracks.clear();
         self.album_tracks_state = ratatui::widgets::ListState::default();
         self.current_album_id = None; // Not an album
         self.current_album_name = Some(playlist_name);
+        self.album_tracks_source = Some(TrackListSource::Playlist(playlist_id));
+        let paging = self.album_tracks_paging.first(PAGE_SIZE);
+        self.fetch_album_tracks(paging);
+    }
 
-        tokio::task::spawn_blocking(move || {
-            let result = provider
-                .list_playlist_tracks(&playlist_id, tunez_core::PageRequest::first_page(100));
-            let _ = tx.send(result);
-        });
+    /// Fetch the next page of the active list once its last row is selected.
+    fn load_more(&mut self) {
+        let Some((state, len)) = self.active_list() else {
+            return;
+        };
+        if state.selected() != Some(len - 1) {
+            return;
+        }
+        match self.tabs[self.active_tab] {
+            Tab::Library | Tab::Playlists if self.viewing_album_tracks => {
+                if let Some(paging) = self.album_tracks_paging.more() {
+                    self.fetch_album_tracks(paging);
+                }
+            }
+            Tab::Library => {
+                if let Some(paging) = self.library_paging.more() {
+                    self.fetch_library(paging);
+                }
+            }
+            Tab::Playlists => {
+                if let Some(paging) = self.playlist_paging.more() {
+                    self.fetch_playlists(paging);
+                }
+            }
+            Tab::Search => {
+                if let Some(paging) = self.search_paging.more() {
+                    self.fetch_search(paging);
+                }
+            }
+            _ => {}
+        }
     }
 
     fn on_tab_changed(&mut self) {
+        self.list_filter = None;
+        self.cancel_hidden_loads();
         if self.tabs[self.active_tab] == Tab::Library {
             // Reset album tracks view when switching to library tab
-            self.viewing_album_tracks = false;
-            self.album_tracks.clear();
-            self.album_tracks_state = ratatui::widgets::ListState::default();
-            self.album_tracks_rx = None;
-            self.current_album_id = None;
-            self.current_album_name = None;
+            while self.library_pop() {}
+            self.close_track_list();
 
             if self.library_items.is_empty() {
                 self.load_library();
             }
         } else if self.tabs[self.active_tab] == Tab::Playlists && self.playlist_items.is_empty() {
             self.load_playlists();
-        } else if self.tabs[self.active_tab] == Tab::Lyrics {
-            if self.lyrics.is_none() || self.current_player_track_id() != self.current_lyrics_id {
-                self.load_lyrics();
-            }
+        } else if self.tabs[self.active_tab] == Tab::Search && self.search_interrupted {
+            self.search_interrupted = false;
+            let paging = self.search_paging.first(PAGE_SIZE);
+            self.fetch_search(paging);
+        } else if self.tabs[self.active_tab] == Tab::Lyrics
+            && ((self.lyrics.is_none() && self.lyrics_status.is_none())
+                || self.current_player_track_id() != self.current_lyrics_id)
+        {
+            self.load_lyrics();
+        }
+    }
+
+    /// Abandon loads for the tabs that aren't shown, so a late answer can't
+    /// land in a view the user has left. Each tab fetches what it's missing
+    /// when it's shown again.
+    fn cancel_hidden_loads(&mut self) {
+        let tab = self.tabs[self.active_tab];
+        let tracks_tab = match self.album_tracks_source {
+            Some(TrackListSource::Playlist(_)) => Tab::Playlists,
+            Some(_) => Tab::Library,
+            None => tab,
+        };
+        if tab != tracks_tab {
+            self.close_track_list();
+        }
+        if tab != Tab::Library && self.library_request.is_pending() {
+            self.library_request.cancel();
+            self.library_paging.cancel();
+        }
+        if tab != Tab::Playlists && self.playlist_request.is_pending() {
+            self.playlist_request.cancel();
+            self.playlist_paging.cancel();
+        }
+        if tab != Tab::Search
+            && (self.search_request.is_pending() || self.search_collections_request.is_pending())
+        {
+            self.search_interrupted |= self.search_paging.loading_first();
+            self.search_request.cancel();
+            self.search_collections_request.cancel();
+            self.search_paging.cancel();
         }
     }
 
@@ -1230,29 +2501,78 @@ impl App {
         self.player.current().map(|c| c.track.id.clone())
     }
 
+    /// Fetch cover art for the current track, if the provider has any.
+    fn load_artwork(&mut self) {
+        self.artwork = None;
+        self.artwork_rx = None;
+        let Some(current) = self.player.current() else {
+            return;
+        };
+        if !self.capabilities.artwork {
+            return;
+        }
+        let track = &current.track;
+        let album_key = match &track.album {
+            Some(album) => format!("{}\u{1f}{}", track.artist, album),
+            None => track.id.0.clone(),
+        };
+        let track_id = track.id.clone();
+        let provider = self.provider.clone();
+        let (tx, rx) = mpsc::channel();
+        self.artwork_rx = Some(rx);
+
+        tokio::task::spawn_blocking(move || {
+            let result = provider.get_artwork(&track_id);
+            let _ = tx.send((album_key, result));
+        });
+    }
+
+    /// Fetch the chapter markers of the current track for the chapter jumps.
+    fn load_chapters(&mut self) {
+        let Some(track_id) = self.current_player_track_id() else {
+            self.chapters_rx = None;
+            return;
+        };
+        let provider = self.provider.clone();
+        let (tx, rx) = mpsc::channel();
+        self.chapters_rx = Some(rx);
+
+        tokio::task::spawn_blocking(move || {
+            let result = provider.get_chapters(&track_id);
+            let _ = tx.send((track_id, result));
+        });
+    }
+
     fn load_lyrics(&mut self) {
         if let Some(track_id) = self.current_player_track_id() {
             let provider = self.provider.clone();
             let (tx, rx) = mpsc::channel();
             self.lyrics_rx = Some(rx);
             self.current_lyrics_id = Some(track_id.clone());
-            self.lyrics = Some("Loading lyrics...".to_string());
+            self.lyrics = None;
+            self.lyrics_status = Some("Loading lyrics...".to_string());
 
             tokio::task::spawn_blocking(move || {
                 let result = provider.get_lyrics(&track_id);
                 let _ = tx.send(result);
             });
         } else {
-            self.lyrics = Some("No track playing".to_string());
+            self.lyrics = None;
+            self.lyrics_status = Some("No track playing".to_string());
             self.current_lyrics_id = None;
         }
     }
 
     fn render(&mut self, frame: &mut Frame) {
+        self.click_areas = ClickAreas::default();
         let area = frame.size();
-        if area.width < MIN_WIDTH || area.height < MIN_HEIGHT {
+        let tunez_core::LayoutConfig {
+            min_width,
+            min_height,
+        } = self.layout;
+        if area.width < min_width || area.height < min_height {
             let message = format!(
-                "Resize terminal to at least {MIN_WIDTH}x{MIN_HEIGHT} (current: {}x{})",
+                "Resize terminal to at least {min_width}x{min_height} (current: {}x{})",
                 area.width, area.height
             );
             let paragraph = Paragraph::new(message)
@@ -1261,6 +2581,10 @@ impl App {
             frame.render_widget(paragraph, area);
             return;
         }
+        if area.width < FULL_WIDTH || area.height < FULL_HEIGHT {
+            self.render_compact(frame, area);
+            return;
+        }
 
         let layout = Layout::default()
             .direction(Direction::Vertical)
@@ -1289,6 +2613,26 @@ impl App {
         } else {
             format!("Provider: {}", self.provider_selection.provider_id)
         };
+        let net = self.net_health.status();
+        let net_color = match net {
+            NetStatus::Ok => self.theme.success,
+            NetStatus::Degraded => self.theme.accent,
+            NetStatus::Offline => self.theme.error,
+        };
+
+        let (provider, provider_color) = if self.provider_error.is_some() {
+            (
+                format!("{provider} UNAVAILABLE (see Config)"),
+                self.theme.error,
+            )
+        } else {
+            (provider, self.theme.success)
+        };
+        let (scrobble, scrobble_color) = if self.scrobbler_manager.is_active() {
+            ("ON", self.theme.success)
+        } else {
+            ("OFF", self.theme.text)
+        };
 
         let status = Line::from(vec![
             Span::styled(
@@ -1297,8 +2641,12 @@ impl App {
                     .add_modifier(Modifier::BOLD),
             ),
             Span::raw("▸ "),
-            Span::styled(provider, self.style_fg(self.theme.success)),
-            Span::raw("  Net: OK  Scrobble: OFF (text labels shown for accessibility)"),
+            Span::styled(provider, self.style_fg(provider_color)),
+            Span::raw("  Net: "),
+            Span::styled(net.label(), self.style_fg(net_color)),
+            Span::raw("  Scrobble: "),
+            Span::styled(scrobble, self.style_fg(scrobble_color)),
+            Span::raw(" (text labels shown for accessibility)"),
         ]);
 
         let paragraph = Paragraph::new(status)
@@ -1314,10 +2662,46 @@ impl App {
             .split(area);
 
         self.render_nav(frame, chunks[0]);
-        self.render_main(frame, chunks[1]);
+        self.render_main(frame, chunks[1], true);
+    }
+
+    /// One-line header and footer around the active tab, with no sidebar or
+    /// visualizer, for terminals too small for the full layout.
+    fn render_compact(&mut self, frame: &mut Frame, area: Rect) {
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(1),
+                Constraint::Min(3),
+                Constraint::Length(1),
+            ])
+            .split(area);
+
+        let tab = self.tabs.get(self.active_tab).unwrap_or(&Tab::NowPlaying);
+        let header = Line::from(vec![
+            Span::styled(
+                "Tunez ",
+                self.style_fg(self.theme.primary)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(format!(
+                "▸ {} ({}/{})",
+                tab.display_name(),
+                self.active_tab + 1,
+                self.tabs.len()
+            )),
+        ]);
+        frame.render_widget(Paragraph::new(header), layout[0]);
+        self.render_main(frame, layout[1], false);
+        frame.render_widget(Paragraph::new(self.footer_line()), layout[2]);
+
+        if self.show_help {
+            self.render_help(frame, area);
+        }
     }
 
     fn render_nav(&mut self, frame: &mut Frame, area: Rect) {
+        self.click_areas.nav = area;
         let items: Vec<ListItem> = self
             .tabs
             .iter()
@@ -1339,18 +2723,21 @@ impl App {
         frame.render_stateful_widget(list, area, &mut state);
     }
 
-    fn render_main(&mut self, frame: &mut Frame, area: Rect) {
+    fn render_main(&mut self, frame: &mut Frame, area: Rect, visualizer: bool) {
         let tab = self.tabs.get(self.active_tab).unwrap_or(&Tab::NowPlaying);
 
+        let visualizer_height = if visualizer { 6 } else { 0 };
         let chunks = Layout::default()
             .direction(Direction::Vertical)
-            .constraints([Constraint::Min(5), Constraint::Length(6)])
+            .constraints([Constraint::Min(5), Constraint::Length(visualizer_height)])
             .split(area);
 
         match tab {
             Tab::NowPlaying => {
                 self.render_now_playing(frame, chunks[0]);
-                self.render_visualizer(frame, chunks[1]);
+                if visualizer {
+                    self.render_visualizer(frame, chunks[1]);
+                }
             }
             Tab::Search => {
                 self.render_search(frame, chunks[0]);
@@ -1369,6 +2756,7 @@ impl App {
             }
             Tab::Config => {
                 self.render_config(frame, chunks[0]);
+                self.click_areas.list = Some(chunks[0]);
             }
             Tab::Help => {
                 self.render_help_main(frame, chunks[0]);
@@ -1376,13 +2764,31 @@ impl App {
         }
     }
 
-    fn render_now_playing(&self, frame: &mut Frame, area: Rect) {
+    fn render_now_playing(&mut self, frame: &mut Frame, area: Rect) {
         let title = format!("{} (Phase 1D shell)", Tab::NowPlaying.display_name());
         let hints = vec![
             Line::from("Navigation: j/k or ↑/↓ | h/l or ←/→ | Tab/Shift+Tab | 1-8"),
             Line::from("Help: ?   Quit: q or Esc   Tabs: Now Playing, Search, Library, Playlists, Queue, Lyrics, Config, Help"),
         ];
 
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(3)])
+            .split(area);
+
+        // Cover art sits to the right, about as wide as it is tall.
+        let info_area = if self.artwork.is_some() {
+            let art_width = (chunks[0].height.saturating_sub(2) * 2 + 2).min(chunks[0].width / 2);
+            let columns = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Min(0), Constraint::Length(art_width)])
+                .split(chunks[0]);
+            self.render_artwork(frame, columns[1]);
+            columns[0]
+        } else {
+            chunks[0]
+        };
+
         let mut lines = Vec::new();
         lines.push(Line::from(Span::styled(
             title,
@@ -1393,10 +2799,27 @@ impl App {
 
         // Show current track info if available
         if let Some(current) = self.player.current() {
+            let star = if self.favorites.contains(&current.track.id) {
+                " ★"
+            } else {
+                ""
+            };
+            let label = "Now Playing: ";
+            let width =
+                (info_area.width.saturating_sub(2) as usize).saturating_sub(label.chars().count());
+            let frame_count = self
+                .visualizer
+                .lock()
+                .map_or(0, |viz| viz.animation_frame());
+            let text = format!("{} - {}{}", current.track.artist, current.track.title, star);
             lines.push(Line::from(Span::styled(
                 format!(
-                    "Now Playing: {} - {}",
-                    current.track.artist, current.track.title
+                    "{label}{}",
+                    marquee(
+                        &text,
+                        width,
+                        (frame_count / MARQUEE_FRAMES_PER_STEP) as usize
+                    )
                 ),
                 self.style_fg(self.theme.success)
                     .add_modifier(Modifier::BOLD),
@@ -1404,9 +2827,6 @@ impl App {
             if let Some(album) = &current.track.album {
                 lines.push(Line::from(format!("Album: {}", album)));
             }
-            if let Some(duration) = current.track.duration_seconds {
-                lines.push(Line::from(format!("Duration: {}s", duration)));
-            }
         } else {
             lines.push(Line::from("No track playing"));
         }
@@ -1417,13 +2837,83 @@ impl App {
         let paragraph = Paragraph::new(Text::from(lines))
             .block(Block::default().borders(Borders::ALL))
             .wrap(Wrap { trim: true });
-        frame.render_widget(paragraph, area);
+        frame.render_widget(paragraph, info_area);
+        self.render_progress(frame, chunks[1]);
+        self.click_areas.progress = Some(chunks[1]);
+    }
+
+    fn render_artwork(&mut self, frame: &mut Frame, area: Rect) {
+        let block = Block::default().borders(Borders::ALL).title("Cover");
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+        let Some((album_key, artwork)) = &self.artwork else {
+            return;
+        };
+
+        // Cover art needs 24-bit color to be recognisable.
+        let cells = if self.use_color
+            && self.color_depth == ColorDepth::TrueColor
+            && inner.width >= MIN_ART_WIDTH
+            && inner.height >= MIN_ART_HEIGHT
+        {
+            self.artwork_cache
+                .get_or_render(album_key, inner.width, inner.height, &artwork.data)
+        } else {
+            None
+        };
+        match cells {
+            Some(cells) => {
+                // Center the scaled image in the panel.
+                let x = inner.x + (inner.width - cells.width) / 2;
+                let y = inner.y + (inner.height - cells.height) / 2;
+                let target = Rect::new(x, y, cells.width, cells.height);
+                frame.render_widget(Paragraph::new(cells.lines().to_vec()), target);
+            }
+            None => {
+                let placeholder = Paragraph::new("[ cover ]")
+                    .alignment(Alignment::Center)
+                    .style(Style::default().add_modifier(Modifier::DIM));
+                let y = inner.y + inner.height / 2;
+                frame.render_widget(
+                    placeholder,
+                    Rect::new(inner.x, y, inner.width, inner.height.min(1)),
+                );
+            }
+        }
+    }
+
+    fn render_progress(&self, frame: &mut Frame, area: Rect) {
+        let total = self
+            .player
+            .current()
+            .and_then(|c| c.track.duration_seconds)
+            .map(u64::from);
+        let (label, percent) = format_progress(self.player.position(), total);
+        let block = Block::default().borders(Borders::ALL).title("Progress");
+        let gauge = if total.is_some() {
+            Gauge::default()
+                .gauge_style(self.style_fg(self.theme.accent))
+                .percent(percent)
+                .label(format!("{label}  {percent}%"))
+        } else {
+            // Unknown length: show elapsed time over an empty, dimmed bar.
+            Gauge::default()
+                .gauge_style(Style::default().add_modifier(Modifier::DIM))
+                .percent(0)
+                .label(label)
+        };
+        frame.render_widget(gauge.block(block), area);
     }
 
     fn render_search(&mut self, frame: &mut Frame, area: Rect) {
         let title = format!("{} (Phase 1D shell)", Tab::Search.display_name());
         let hints = vec![
-            Line::from("Navigation: j/k or ↑/↓ | Enter to play | / to search"),
+            Line::from(match self.search_scope {
+                SearchScope::Tracks => {
+                    "Navigation: j/k or ↑/↓ | Enter to play | / to search (Tab: scope)"
+                }
+                _ => "Navigation: j/k or ↑/↓ | Enter to open | / to search (Tab: scope)",
+            }),
             Line::from("Help: ?   Quit: q or Esc"),
         ];
 
@@ -1436,14 +2926,15 @@ impl App {
             Line::from(""),
         ];
 
+        let prompt = format!("Search {}: ", self.search_scope.label());
         if self.is_searching {
             lines.push(Line::from(vec![
-                Span::raw("Search: "),
+                Span::raw(prompt),
                 Span::styled(&self.search_query, Style::default().fg(Color::Yellow)),
                 Span::raw("█"), // Cursor
             ]));
         } else {
-            lines.push(Line::from(format!("Search: {}", self.search_query)));
+            lines.push(Line::from(format!("{}{}", prompt, self.search_query)));
         }
         lines.push(Line::from(""));
 
@@ -1461,19 +2952,35 @@ impl App {
         frame.render_widget(header, chunks[0]);
 
         // Results list
-        if !self.search_results.is_empty() {
-            let items: Vec<ListItem> = self
-                .search_results
-                .iter()
-                .map(|track| ListItem::new(format!("{} - {}", track.artist, track.title)))
-                .collect();
+        if self.search_len() > 0 {
+            let width = row_width(chunks[1]);
+            let items: Vec<ListItem> = if self.search_scope == SearchScope::Tracks {
+                self.search_results
+                    .iter()
+                    .map(|track| fit_row(&format!("{} - {}", track.artist, track.title), width))
+                    .collect()
+            } else {
+                self.search_collections
+                    .iter()
+                    .map(|item| match item {
+                        tunez_core::CollectionItem::Album(a) => {
+                            fit_row(&format!("{} - {}", a.artist, a.title), width)
+                        }
+                        tunez_core::CollectionItem::Artist { name, .. }
+                        | tunez_core::CollectionItem::Genre { name, .. } => fit_row(name, width),
+                        tunez_core::CollectionItem::Playlist(p) => fit_row(&p.name, width),
+                    })
+                    .collect()
+            };
 
+            let results_title = format!("Results: {}", self.search_scope.label());
             let list = List::new(items)
-                .block(Block::default().borders(Borders::ALL).title("Results"))
+                .block(Block::default().borders(Borders::ALL).title(results_title))
                 .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
                 .highlight_symbol("▶ ");
 
             frame.render_stateful_widget(list, chunks[1], &mut self.search_state);
+            self.click_areas.list = Some(chunks[1]);
         } else {
             let msg = Paragraph::new("No results").block(Block::default().borders(Borders::ALL));
             frame.render_widget(msg, chunks[1]);
@@ -1485,35 +2992,44 @@ impl App {
 
     fn render_library(&mut self, frame: &mut Frame, area: Rect) {
         let title = format!("{} (Phase 1D shell)", Tab::Library.display_name());
+        let breadcrumb = self.library_breadcrumb();
 
         if self.viewing_album_tracks {
             // Render album tracks view
             let hints = vec![
-                Line::from("Navigation: j/k or ↑/↓ | Enter to play | Backspace to return"),
+                Line::from(
+                    "Navigation: j/k or ↑/↓ | Enter to play | / to filter | Backspace/Esc to go back",
+                ),
                 Line::from("Help: ?   Quit: q or Esc"),
             ];
 
-            let mut lines = vec![
-                Line::from(Span::styled(
-                    title,
-                    self.style_fg(self.theme.primary)
-                        .add_modifier(Modifier::BOLD),
-                )),
-                Line::from(""),
-            ];
+            let mut lines = vec![Line::from(Span::styled(
+                title,
+                self.style_fg(self.theme.primary)
+                    .add_modifier(Modifier::BOLD),
+            ))];
 
             if let Some(album_name) = &self.current_album_name {
+                let heading = match self.current_album_id {
+                    Some(_) if !self.album_tracks.is_empty() => format!(
+                        "Album: {} — {}",
+                        album_name,
+                        album_summary(&self.album_tracks, self.album_tracks_paging.is_complete())
+                            .label()
+                    ),
+                    Some(_) => format!("Album: {}", album_name),
+                    None => album_name.clone(),
+                };
                 lines.push(Line::from(Span::styled(
-                    format!("Album: {}", album_name),
+                    heading,
                     Style::default().add_modifier(Modifier::BOLD),
                 )));
             }
-            lines.push(Line::from(""));
 
             let chunks = Layout::default()
                 .direction(Direction::Vertical)
                 .constraints([
-                    Constraint::Length(3),
+                    Constraint::Length(4),
                     Constraint::Min(0),
                     Constraint::Length(2),
                 ])
@@ -1523,36 +3039,22 @@ impl App {
                 Paragraph::new(Text::from(lines)).block(Block::default().borders(Borders::ALL));
             frame.render_widget(header, chunks[0]);
 
-            if !self.album_tracks.is_empty() {
-                let items: Vec<ListItem> = self
-                    .album_tracks
-                    .iter()
-                    .map(|track| {
-                        let duration = track
-                            .duration_seconds
-                            .map_or(String::new(), |d| format!(" ({})", d));
-                        ListItem::new(format!("{} - {}{}", track.artist, track.title, duration))
-                    })
-                    .collect();
-
-                let list = List::new(items)
-                    .block(Block::default().borders(Borders::ALL).title("Tracks"))
-                    .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
-                    .highlight_symbol("▶ ");
-
-                frame.render_stateful_widget(list, chunks[1], &mut self.album_tracks_state);
-            } else {
-                let msg = Paragraph::new("Loading tracks...")
-                    .block(Block::default().borders(Borders::ALL));
-                frame.render_widget(msg, chunks[1]);
-            }
+            let status = self
+                .library_status
+                .clone()
+                .unwrap_or_else(|| "Loading tracks...".into());
+            self.render_track_list(frame, chunks[1], &breadcrumb, &status);
 
             let footer = Paragraph::new(Text::from(hints)).wrap(Wrap { trim: true });
             frame.render_widget(footer, chunks[2]);
         } else {
             // Render main library view
             let hints = vec![
-                Line::from("Navigation: j/k or ↑/↓ | Enter to browse albums"),
+                Line::from(if self.library_stack.is_empty() {
+                    "Navigation: j/k or ↑/↓ | Enter to browse | / to filter | o to sort"
+                } else {
+                    "Navigation: j/k or ↑/↓ | Enter to browse | / to filter | o to sort | Backspace/Esc to go back"
+                }),
                 Line::from("Help: ?   Quit: q or Esc"),
             ];
 
@@ -1578,30 +3080,43 @@ impl App {
                 Paragraph::new(Text::from(lines)).block(Block::default().borders(Borders::ALL));
             frame.render_widget(header, chunks[0]);
 
-            if !self.library_items.is_empty() {
-                let items: Vec<ListItem> = self
-                    .library_items
+            let breadcrumb = match self.library_sort {
+                tunez_core::SortOrder::Title => breadcrumb,
+                sort => format!("{breadcrumb} · by {}", sort.label().to_lowercase()),
+            };
+            let shortcuts = self.library_shortcuts();
+            if !self.library_items.is_empty() || !shortcuts.is_empty() {
+                let width = row_width(chunks[1]);
+                let items: Vec<ListItem> = shortcuts
                     .iter()
-                    .map(|item| {
-                        let name = match item {
-                            tunez_core::CollectionItem::Album(a) => &a.title,
-                            tunez_core::CollectionItem::Playlist(p) => &p.name,
-                            tunez_core::CollectionItem::Artist { name, .. } => name,
-                            tunez_core::CollectionItem::Genre { name, .. } => name,
-                        };
-                        ListItem::new(name.clone())
+                    .map(|view| {
+                        ListItem::new(view.label().to_string())
+                            .style(self.style_fg(self.theme.accent))
                     })
+                    .chain(
+                        self.library_items
+                            .iter()
+                            .map(|item| fit_row(collection_name(item), width)),
+                    )
                     .collect();
 
-                let list = List::new(items)
-                    .block(Block::default().borders(Borders::ALL).title("Library"))
+                let list = List::default()
+                    .block(
+                        Block::default()
+                            .borders(Borders::ALL)
+                            .title(self.filtered_title(&breadcrumb)),
+                    )
                     .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
                     .highlight_symbol("▶ ");
 
-                frame.render_stateful_widget(list, chunks[1], &mut self.library_state);
+                self.render_list(frame, chunks[1], list, items, |app| &mut app.library_state);
             } else {
-                let msg = Paragraph::new("Loading library or empty...")
-                    .block(Block::default().borders(Borders::ALL));
+                let msg = Paragraph::new(
+                    self.library_status
+                        .as_deref()
+                        .unwrap_or("Loading library or empty..."),
+                )
+                .block(Block::default().borders(Borders::ALL).title(breadcrumb));
                 frame.render_widget(msg, chunks[1]);
 
                 // Trigger load if empty and not loading (simple check)
@@ -1615,10 +3130,52 @@ impl App {
         }
     }
 
+    /// Draw the open album or playlist's tracks, or `status` until it has some.
+    fn render_track_list(&mut self, frame: &mut Frame, area: Rect, title: &str, status: &str) {
+        if self.album_tracks.is_empty() {
+            let msg = Paragraph::new(status.to_string()).block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(title.to_string()),
+            );
+            frame.render_widget(msg, area);
+            return;
+        }
+        let width = row_width(area);
+        let items: Vec<ListItem> = self
+            .album_tracks
+            .iter()
+            .map(|track| {
+                let duration = track
+                    .duration_seconds
+                    .map_or(String::new(), |d| format!(" ({})", clock(u64::from(d))));
+                // Shorten the name so the duration stays in view.
+                let name = format!("{} - {}", track.artist, track.title);
+                let name = truncate(&name, width.saturating_sub(duration.len()));
+                ListItem::new(format!("{name}{duration}"))
+            })
+            .collect();
+
+        let list = List::default()
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(self.filtered_title(title)),
+            )
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+            .highlight_symbol("▶ ");
+
+        self.render_list(frame, area, list, items, |app| &mut app.album_tracks_state);
+    }
+
     fn render_playlists(&mut self, frame: &mut Frame, area: Rect) {
         let title = format!("{} (Phase 1D shell)", Tab::Playlists.display_name());
         let hints = vec![
-            Line::from("Navigation: j/k or ↑/↓ | Enter to open"),
+            Line::from(if self.viewing_album_tracks {
+                "Navigation: j/k or ↑/↓ | Enter to play | / to filter | Backspace to go back"
+            } else {
+                "Navigation: j/k or ↑/↓ | Enter to open | / to filter"
+            }),
             Line::from("Help: ?   Quit: q or Esc"),
         ];
 
@@ -1644,19 +3201,35 @@ impl App {
             Paragraph::new(Text::from(lines)).block(Block::default().borders(Borders::ALL));
         frame.render_widget(header, chunks[0]);
 
-        if !self.playlist_items.is_empty() {
+        if self.viewing_album_tracks {
+            let name = self
+                .current_album_name
+                .clone()
+                .unwrap_or_else(|| "Playlist".into());
+            let status = if self.album_tracks_request.is_pending() {
+                "Loading tracks..."
+            } else {
+                "No tracks"
+            };
+            self.render_track_list(frame, chunks[1], &name, status);
+        } else if !self.playlist_items.is_empty() {
+            let width = row_width(chunks[1]);
             let items: Vec<ListItem> = self
                 .playlist_items
                 .iter()
-                .map(|item| ListItem::new(item.name.clone()))
+                .map(|item| fit_row(&item.name, width))
                 .collect();
 
-            let list = List::new(items)
-                .block(Block::default().borders(Borders::ALL).title("Playlists"))
+            let list = List::default()
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .title(self.filtered_title("Playlists")),
+                )
                 .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
                 .highlight_symbol("▶ ");
 
-            frame.render_stateful_widget(list, chunks[1], &mut self.playlist_state);
+            self.render_list(frame, chunks[1], list, items, |app| &mut app.playlist_state);
         } else {
             let msg = Paragraph::new("No playlists or loading...")
                 .block(Block::default().borders(Borders::ALL));
@@ -1670,17 +3243,20 @@ impl App {
     fn render_queue(&mut self, frame: &mut Frame, area: Rect) {
         let title = format!("{} (Phase 1E functional)", Tab::Queue.display_name());
         let hints = vec![
-            Line::from("Navigation: j/k or ↑/↓ | Enter to play | d to remove | c to clear"),
+            Line::from(
+                "Navigation: j/k or ↑/↓ | Enter to play | d to remove | c to clear | / to filter",
+            ),
             Line::from("Help: ?   Quit: q or Esc"),
         ];
 
-        let mut lines = Vec::new();
-        lines.push(Line::from(Span::styled(
-            title,
-            self.style_fg(self.theme.primary)
-                .add_modifier(Modifier::BOLD),
-        )));
-        lines.push(Line::from(""));
+        let lines = vec![
+            Line::from(Span::styled(
+                title,
+                self.style_fg(self.theme.primary)
+                    .add_modifier(Modifier::BOLD),
+            )),
+            Line::from(""),
+        ];
 
         let chunks = Layout::default()
             .direction(Direction::Vertical)
@@ -1697,6 +3273,7 @@ impl App {
 
         // Render queue list
         let current_id = self.player.current().map(|c| c.id);
+        let width = row_width(chunks[1]);
         let items: Vec<ListItem> = self
             .player
             .queue()
@@ -1708,7 +3285,13 @@ impl App {
                 } else {
                     "  "
                 };
-                ListItem::new(format!("{}{}. {} - {}", prefix, item.id.0, item.track.artist, item.track.title))
+                fit_row(
+                    &format!(
+                        "{}{}. {} - {}",
+                        prefix, item.id.0, item.track.artist, item.track.title
+                    ),
+                    width,
+                )
             })
             .collect();
 
@@ -1716,24 +3299,63 @@ impl App {
             let msg = Paragraph::new("Queue is empty").block(Block::default().borders(Borders::ALL));
             frame.render_widget(msg, chunks[1]);
         } else {
-            let list = List::new(items)
-                .block(Block::default().borders(Borders::ALL).title("Tracks"))
+            let list = List::default()
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .title(self.filtered_title("Tracks")),
+                )
                 .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
                 .highlight_symbol("> ");
 
-            frame.render_stateful_widget(list, chunks[1], &mut self.queue_state);
+            self.render_list(frame, chunks[1], list, items, |app| &mut app.queue_state);
         }
 
         let footer = Paragraph::new(Text::from(hints)).wrap(Wrap { trim: true });
         frame.render_widget(footer, chunks[2]);
     }
 
+    /// Draw `list` with `rows`, narrowed to those the type-ahead filter shows.
+    fn render_list<'a>(
+        &mut self,
+        frame: &mut Frame,
+        area: Rect,
+        list: List<'a>,
+        rows: Vec<ListItem<'a>>,
+        state: fn(&mut Self) -> &mut ListState,
+    ) {
+        self.click_areas.list = Some(area);
+        let Some(visible) = self.filtered_rows() else {
+            frame.render_stateful_widget(list.items(rows), area, state(self));
+            return;
+        };
+        let mut rows: Vec<Option<ListItem>> = rows.into_iter().map(Some).collect();
+        let shown: Vec<ListItem> = visible
+            .iter()
+            .filter_map(|&i| rows.get_mut(i)?.take())
+            .collect();
+        let selection = state(self).clone();
+        if let Some(filter) = self.list_filter.as_mut() {
+            let mut display = filter.display_state(&selection, &visible);
+            frame.render_stateful_widget(list.items(shown), area, &mut display);
+            filter.set_offset(display.offset());
+        }
+    }
+
+    /// `title` followed by the filter's query, while one is open.
+    fn filtered_title(&self, title: &str) -> String {
+        match &self.list_filter {
+            Some(filter) => format!(
+                "{title} /{}{}",
+                filter.query(),
+                if filter.is_typing() { "_" } else { "" }
+            ),
+            None => title.to_string(),
+        }
+    }
+
     fn render_lyrics(&self, frame: &mut Frame, area: Rect) {
         let title = format!("{} (Phase 1G functional)", Tab::Lyrics.display_name());
-        let content = self
-            .lyrics
-            .as_deref()
-            .unwrap_or("No lyrics available for this track");
 
         let mut lines = Vec::new();
         lines.push(Line::from(Span::styled(
@@ -1751,30 +3373,64 @@ impl App {
             lines.push(Line::from(""));
         }
 
-        for line in content.lines() {
-            lines.push(Line::from(line));
+        let mut scroll = 0;
+        match &self.lyrics {
+            Some(
+                lyrics @ tunez_core::Lyrics {
+                    synced: Some(synced),
+                    ..
+                },
+            ) => {
+                let active = lyrics.active_line(self.player.position());
+                // Keep the active line roughly centred once it passes the middle.
+                if let Some(active) = active {
+                    let visible = area.height.saturating_sub(2) as usize;
+                    let row = lines.len() + active;
+                    scroll = row.saturating_sub(visible / 2) as u16;
+                }
+                for (i, (_, text)) in synced.iter().enumerate() {
+                    let style = if Some(i) == active {
+                        self.style_fg(self.theme.accent)
+                            .add_modifier(Modifier::BOLD)
+                    } else if active.is_none_or(|a| i < a) {
+                        Style::default().add_modifier(Modifier::DIM)
+                    } else {
+                        Style::default()
+                    };
+                    lines.push(Line::from(Span::styled(text.clone(), style)));
+                }
+            }
+            Some(tunez_core::Lyrics {
+                plain: Some(plain), ..
+            }) => {
+                for line in plain.lines() {
+                    lines.push(Line::from(line.to_string()));
+                }
+            }
+            _ => {
+                let status = self
+                    .lyrics_status
+                    .as_deref()
+                    .unwrap_or("No lyrics available for this track");
+                lines.push(Line::from(status));
+            }
         }
 
         let paragraph = Paragraph::new(Text::from(lines))
             .block(Block::default().borders(Borders::ALL))
-            .wrap(Wrap { trim: true });
+            .wrap(Wrap { trim: true })
+            .scroll((scroll, 0));
         frame.render_widget(paragraph, area);
     }
 
     fn render_config(&self, frame: &mut Frame, area: Rect) {
-        let title = format!("{} (Phase 3 Shell)", Tab::Config.display_name());
-
-        // Define settings values to display alongside names
-        let theme_name = if self.use_color {
-            if self.theme.background == Color::Black {
-                "Afterdark"
-            } else if self.theme.secondary == Color::Yellow {
-                "Solarized"
-            } else {
-                "Default"
-            }
+        let title = if self.config.is_some() {
+            format!(
+                "{} (Enter changes; saved to config.toml)",
+                Tab::Config.display_name()
+            )
         } else {
-            "Monochrome"
+            format!("{} (Enter changes until exit)", Tab::Config.display_name())
         };
 
         let viz_mode = if let Ok(viz) = self.visualizer.lock() {
@@ -1783,22 +3439,42 @@ impl App {
             "Unknown"
         };
 
-        let scrobbler_status = if self.scrobbler_manager.is_active() {
+        let scrobbler_status = if !self.scrobbler_manager.is_configured() {
+            "Not configured"
+        } else if self.scrobbler_manager.is_active() {
             "Enabled"
         } else {
             "Disabled (Opt-in)"
         };
 
+        let provider = match &self.provider_selection.profile {
+            Some(profile) => format!(
+                "{} (profile: {})",
+                self.provider_selection.provider_id, profile
+            ),
+            None => self.provider_selection.provider_id.clone(),
+        };
+
         let items: Vec<ListItem> = self
             .config_items
             .iter()
             .enumerate()
             .map(|(i, item)| {
+                // Only settings without secrets are shown.
                 let value = match *item {
-                    "Theme" => theme_name,
-                    "Visualizer Mode" => viz_mode,
-                    "Scrobbling" => scrobbler_status,
-                    _ => "",
+                    "Provider" => provider.clone(),
+                    "Theme" => self.theme.name.to_string(),
+                    "Log Level" => self.config.as_ref().map_or("-".to_string(), |c| {
+                        c.logging.level.as_filter_directive().to_string()
+                    }),
+                    "Visualizer Mode" => viz_mode.to_string(),
+                    "Scrobbling" => scrobbler_status.to_string(),
+                    "Cache" => self
+                        .config
+                        .as_ref()
+                        .map_or("-".to_string(), |c| cache_summary(&c.cache)),
+                    "Capabilities" => capability_summary(&self.capabilities),
+                    _ => String::new(),
                 };
 
                 let content = format!("{}: {}", item, value);
@@ -1821,22 +3497,53 @@ impl App {
                     .add_modifier(Modifier::BOLD),
             );
 
-        // Use a mutable state ref here is tricky because render takes &self.
-        // We have to use internal mutability or pass state.
-        // Typically render shouldn't mutate state.
-        // But List requires &mut ListState.
-        // We can clone the state, but we need to persist it.
-        // App::render takes &mut self in main loop? No, it takes &self?
-        // Let's check App::render signature.
-        // It matches `fn render(&self, frame: &mut Frame)`. This is a problem for ListState which needs mutation for scrolling calculations if we passed it.
-        // However, we can just render the list with proper styling manually or change render signature.
-        // Wait, `List::new` doesn't take state. `frame.render_stateful_widget` does.
-        // `frame.render_stateful_widget(list, area, &mut self.config_state)` requires `&mut self`.
-        // I need to check render signature.
-
-        // CHECK: Loop in main calls `app.render(frame)`.
-        // `fn render(&mut self, ...)` is standard for ratatui apps if state is mutating.
-        // I will check App::render signature in file.
+        let area = match &self.provider_error {
+            Some(error) => {
+                let chunks = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints([Constraint::Length(5), Constraint::Min(3)])
+                    .split(area);
+                let remedy = if self.connect_provider.is_some() {
+                    "Fix it in config.toml and restart, or select Provider and press Enter to switch."
+                } else {
+                    "Fix it in config.toml and restart Tunez."
+                };
+                let banner = Paragraph::new(vec![
+                    Line::from(Span::styled(
+                        error.as_str(),
+                        self.style_fg(self.theme.error),
+                    )),
+                    Line::from(remedy),
+                ])
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .title("Provider failed to start"),
+                )
+                .wrap(Wrap { trim: true });
+                frame.render_widget(banner, chunks[0]);
+                chunks[1]
+            }
+            None => area,
+        };
+
+        let area = match &self.config_error {
+            Some(error) => {
+                let chunks = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints([Constraint::Min(3), Constraint::Length(3)])
+                    .split(area);
+                let message = Paragraph::new(Span::styled(
+                    error.as_str(),
+                    self.style_fg(self.theme.error),
+                ))
+                .block(Block::default().borders(Borders::ALL).title("Not saved"))
+                .wrap(Wrap { trim: true });
+                frame.render_widget(message, chunks[1]);
+                chunks[0]
+            }
+            None => area,
+        };
 
         frame.render_widget(list, area);
     }
@@ -1869,20 +3576,51 @@ impl App {
     }
 
     fn render_footer(&self, frame: &mut Frame, area: Rect) {
+        let footer = Paragraph::new(self.footer_line())
+            .block(Block::default().borders(Borders::ALL).title("Player"));
+        frame.render_widget(footer, area);
+    }
+
+    /// Player state, progress and the current toast.
+    fn footer_line(&self) -> Line<'static> {
         let player_state_str = match self.player.state() {
-            PlayerState::Stopped => "⏹  Stopped",
-            PlayerState::Buffering { .. } => "⏳ Buffering",
-            PlayerState::Playing { .. } => "⏵  Playing",
-            PlayerState::Paused { .. } => "⏸  Paused",
-            PlayerState::Error { message, .. } => &format!("⚠️  Error: {}", message),
+            PlayerState::Stopped => "⏹  Stopped".to_string(),
+            PlayerState::Buffering { .. } => "⏳ Buffering".to_string(),
+            PlayerState::Playing { .. } => "⏵  Playing".to_string(),
+            PlayerState::Paused { .. } => "⏸  Paused".to_string(),
+            PlayerState::Error { message, .. } => format!("⚠️  Error: {}", message),
         };
 
-        let footer = Paragraph::new(Line::from(vec![
+        let total = self
+            .player
+            .current()
+            .and_then(|c| c.track.duration_seconds)
+            .map(u64::from);
+        let (progress, _) = format_progress(self.player.position(), total);
+
+        let mut spans = vec![
             Span::raw(player_state_str),
-            Span::raw("   ▓▓▓▓░░░░░░  Vol: 72%  Rep:Off"),
-        ]))
-        .block(Block::default().borders(Borders::ALL).title("Player"));
-        frame.render_widget(footer, area);
+            Span::raw(format!(
+                "   {progress}  Vol: 72%  Rep:{}  Shuf:{}",
+                self.player.queue().repeat_mode().label(),
+                if self.player.queue().is_shuffled() {
+                    "On"
+                } else {
+                    "Off"
+                }
+            )),
+        ];
+        if let Some(toast) = &self.toast {
+            let style = match toast.severity {
+                Severity::Info => self.style_fg(self.theme.success),
+                Severity::Transient => self.style_fg(self.theme.accent),
+                Severity::Persistent => {
+                    self.style_fg(self.theme.error).add_modifier(Modifier::BOLD)
+                }
+            };
+            spans.push(Span::styled(format!("   {}", toast.message), style));
+        }
+        Line::from(spans)
     }
 
     fn render_visualizer(&self, frame: &mut Frame, area: Rect) {
@@ -1924,30 +3662,252 @@ impl App {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum Tab {
-    NowPlaying,
-    Search,
-    Library,
-    Playlists,
-    Queue,
-    Lyrics,
-    Config,
-    Help,
+/// Stream URL for `track_id`, preferring the track cache. A miss streams
+/// the provider's URL while the cache fills in the background, so playback
+/// never waits on a whole download.
+fn stream_url(
+    provider: &dyn Provider,
+    cache: Option<&Arc<tunez_core::CacheManager>>,
+    track_id: &tunez_core::TrackId,
+) -> tunez_core::ProviderResult<tunez_core::StreamUrl> {
+    let Some(cache) = cache else {
+        return provider.get_stream_url(track_id);
+    };
+    if let Some(url) = cache.cached_url(track_id) {
+        return Ok(url);
+    }
+    let url = provider.get_stream_url(track_id)?;
+    cache.cache_in_background(track_id, &url);
+    Ok(url)
 }
 
-impl Tab {
-    fn all() -> Vec<Tab> {
-        vec![
-            Tab::NowPlaying,
-            Tab::Search,
-            Tab::Library,
-            Tab::Playlists,
-            Tab::Queue,
-            Tab::Lyrics,
-            Tab::Config,
-            Tab::Help,
-        ]
+/// One page of the "Most Played" list for `provider_id`.
+fn most_played_page(
+    counts: &tunez_core::PlayCounts,
+    provider_id: &str,
+    paging: tunez_core::PageRequest,
+) -> tunez_core::ProviderResult<tunez_core::Page<tunez_core::Track>> {
+    let tracks = counts
+        .most_played(provider_id)
+        .map_err(|e| tunez_core::ProviderError::Other {
+            message: format!("failed to read play counts: {e}"),
+        })?;
+    let start = paging.offset as usize;
+    let end = start.saturating_add(paging.limit as usize);
+    let next = (end < tracks.len()).then(|| tunez_core::PageCursor(end.to_string()));
+    let items = tracks
+        .into_iter()
+        .skip(start)
+        .take(paging.limit as usize)
+        .map(|c| c.track.into())
+        .collect();
+    Ok(tunez_core::Page { items, next })
+}
+
+/// Display name of a library row.
+fn collection_name(item: &tunez_core::CollectionItem) -> &str {
+    match item {
+        tunez_core::CollectionItem::Album(a) => &a.title,
+        tunez_core::CollectionItem::Playlist(p) => &p.name,
+        tunez_core::CollectionItem::Artist { name, .. } => name,
+        tunez_core::CollectionItem::Genre { name, .. } => name,
+    }
+}
+
+/// Elapsed/total label and completed percentage for a progress bar.
+///
+/// With an unknown `total` (in seconds) the total shows as `--:--` and the
+/// percentage is 0.
+fn format_progress(pos: Duration, total: Option<u64>) -> (String, u16) {
+    let elapsed = pos.as_secs();
+    match total {
+        Some(total) if total > 0 => {
+            let elapsed = elapsed.min(total);
+            let percent = (pos.as_millis().min(u128::from(total) * 1000) * 100
+                / (u128::from(total) * 1000)) as u16;
+            (format!("{} / {}", clock(elapsed), clock(total)), percent)
+        }
+        _ => (format!("{} / --:--", clock(elapsed)), 0),
+    }
+}
+
+/// `mm:ss`, or `h:mm:ss` from an hour up.
+fn clock(secs: u64) -> String {
+    if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+    } else {
+        format!("{:02}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+/// Totals shown above an album's track list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct AlbumSummary {
+    tracks: usize,
+    /// Runtime of the tracks whose duration is known.
+    total_seconds: u64,
+    /// Tracks left out of the total for lack of a duration.
+    unknown_durations: usize,
+    multi_disc: bool,
+    /// Later pages haven't loaded, so the totals cover only the first ones.
+    partial: bool,
+}
+
+impl AlbumSummary {
+    fn label(&self) -> String {
+        let mut label = if self.partial {
+            format!(
+                "{}+ tracks · {} so far",
+                self.tracks,
+                clock(self.total_seconds)
+            )
+        } else {
+            format!(
+                "{} track{} · {}",
+                self.tracks,
+                if self.tracks == 1 { "" } else { "s" },
+                clock(self.total_seconds)
+            )
+        };
+        if self.unknown_durations > 0 {
+            label.push_str(&format!(" (+{} unknown)", self.unknown_durations));
+        }
+        if self.multi_disc {
+            label.push_str(" · multi-disc");
+        }
+        label
+    }
+}
+
+/// Totals for the loaded `tracks`; `complete` says whether they are all of
+/// the album's.
+fn album_summary(tracks: &[tunez_core::Track], complete: bool) -> AlbumSummary {
+    let durations = tracks.iter().filter_map(|t| t.duration_seconds);
+    let mut discs = tracks.iter().filter_map(|t| t.disc_number);
+    let first_disc = discs.next();
+    AlbumSummary {
+        tracks: tracks.len(),
+        total_seconds: durations.clone().map(u64::from).sum(),
+        unknown_durations: tracks.len() - durations.count(),
+        multi_disc: discs.any(|disc| Some(disc) != first_disc),
+        partial: !complete,
+    }
+}
+
+/// A level of the Library tab's drill-down.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum LibraryView {
+    /// Artists, or albums for providers that can't browse artists.
+    Root,
+    Artist(String),
+    Album {
+        id: tunez_core::AlbumId,
+        title: String,
+    },
+    /// The provider's recently played tracks.
+    RecentlyPlayed,
+    /// Tracks from the provider by local play count.
+    MostPlayed,
+}
+
+impl LibraryView {
+    fn label(&self) -> &str {
+        match self {
+            LibraryView::Root => "Library",
+            LibraryView::Artist(name) => name,
+            LibraryView::Album { title, .. } => title,
+            LibraryView::RecentlyPlayed => "Recently Played",
+            LibraryView::MostPlayed => "Most Played",
+        }
+    }
+}
+
+/// Query, scope and page of a search request.
+type SearchKey = (String, SearchScope, tunez_core::PageRequest);
+
+/// What the Search tab looks for; Tab cycles it while typing a query.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SearchScope {
+    Tracks,
+    Albums,
+    Artists,
+}
+
+impl SearchScope {
+    fn label(self) -> &'static str {
+        match self {
+            SearchScope::Tracks => "Tracks",
+            SearchScope::Albums => "Albums",
+            SearchScope::Artists => "Artists",
+        }
+    }
+
+    fn next(self) -> Self {
+        match self {
+            SearchScope::Tracks => SearchScope::Albums,
+            SearchScope::Albums => SearchScope::Artists,
+            SearchScope::Artists => SearchScope::Tracks,
+        }
+    }
+}
+
+/// A level left behind by `App::library_push`, with its items and selection.
+#[derive(Debug)]
+struct LibraryLevel {
+    view: LibraryView,
+    items: Vec<tunez_core::CollectionItem>,
+    selected: Option<usize>,
+    paging: ListPaging,
+}
+
+/// What the tracks in `App::album_tracks` were listed from.
+#[derive(Debug, Clone, PartialEq)]
+enum TrackListSource {
+    Album(tunez_core::AlbumId),
+    Playlist(tunez_core::PlaylistId),
+    RecentlyPlayed,
+    MostPlayed,
+}
+
+/// A page of `App::album_tracks`, and the playlist they belong to when it
+/// came with them.
+type TrackPage = (
+    Option<tunez_core::Playlist>,
+    tunez_core::Page<tunez_core::Track>,
+);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Tab {
+    NowPlaying,
+    Search,
+    Library,
+    Playlists,
+    Queue,
+    Lyrics,
+    Config,
+    Help,
+}
+
+impl Tab {
+    fn all() -> Vec<Tab> {
+        vec![
+            Tab::NowPlaying,
+            Tab::Search,
+            Tab::Library,
+            Tab::Playlists,
+            Tab::Queue,
+            Tab::Lyrics,
+            Tab::Config,
+            Tab::Help,
+        ]
+    }
+
+    /// The tabs worth showing for a provider with `caps`.
+    fn for_capabilities(caps: &ProviderCapabilities) -> Vec<Tab> {
+        Self::all()
+            .into_iter()
+            .filter(|&tab| tab != Tab::Playlists || caps.playlists)
+            .collect()
     }
 
     fn display_name(&self) -> &'static str {
@@ -1964,6 +3924,29 @@ impl Tab {
     }
 }
 
+/// Index of the row under (`column`, `row`) in a bordered list drawn in
+/// `area` and scrolled down by `offset` rows.
+fn list_row_at(area: Rect, offset: usize, column: u16, row: u16) -> Option<usize> {
+    let inside = column > area.x
+        && column < area.right().saturating_sub(1)
+        && row > area.y
+        && row < area.bottom().saturating_sub(1);
+    inside.then(|| offset + usize::from(row - area.y - 1))
+}
+
+/// Index of the tab under (`column`, `row`) in the nav list.
+fn tab_at(nav: Rect, tab_count: usize, column: u16, row: u16) -> Option<usize> {
+    list_row_at(nav, 0, column, row).filter(|index| *index < tab_count)
+}
+
+/// How far along a bordered progress gauge drawn in `area` a click lands, from
+/// 0.0 at the left edge to 1.0 at the right.
+fn seek_fraction(area: Rect, column: u16, row: u16) -> Option<f32> {
+    let width = area.width.checked_sub(2).filter(|w| *w > 0)?;
+    list_row_at(area, 0, column, row)?;
+    Some(f32::from(column - area.x - 1) / f32::from(width.saturating_sub(1).max(1)))
+}
+
 fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
     let horizontal = Layout::default()
         .direction(Direction::Horizontal)
@@ -1990,6 +3973,14 @@ mod tests {
     use std::sync::Arc;
     use tunez_core::provider::ProviderCapabilities;
 
+    /// Directories in a fresh temporary directory, so no test reads or
+    /// overwrites the user's queue, history or play counts.
+    fn test_dirs() -> (tempfile::TempDir, AppDirs) {
+        let tmp = tempfile::tempdir().unwrap();
+        let dirs = AppDirs::under(tmp.path());
+        (tmp, dirs)
+    }
+
     // Mock provider for testing
     struct MockProvider;
 
@@ -2001,16 +3992,46 @@ mod tests {
             "Mock"
         }
         fn capabilities(&self) -> tunez_core::ProviderCapabilities {
-            ProviderCapabilities::default()
+            ProviderCapabilities {
+                playlists: true,
+                recently_played: true,
+                ..Default::default()
+            }
+        }
+        fn list_recently_played(
+            &self,
+            _paging: tunez_core::PageRequest,
+        ) -> tunez_core::ProviderResult<tunez_core::Page<tunez_core::Track>> {
+            Ok(tunez_core::Page {
+                items: vec![],
+                next: None,
+            })
         }
         fn search_tracks(
             &self,
-            _query: &str,
+            query: &str,
             _filters: tunez_core::TrackSearchFilters,
             _paging: tunez_core::PageRequest,
         ) -> tunez_core::ProviderResult<tunez_core::Page<tunez_core::Track>> {
+            // Lets a test have one search answer after a later one.
+            if query.starts_with("slow") {
+                std::thread::sleep(Duration::from_millis(200));
+            }
             Ok(tunez_core::Page {
-                items: vec![],
+                items: vec![tunez_core::Track {
+                    title: query.into(),
+                    ..album_track(None, None)
+                }],
+                next: None,
+            })
+        }
+        fn search_artists(
+            &self,
+            _query: &str,
+            _paging: tunez_core::PageRequest,
+        ) -> tunez_core::ProviderResult<tunez_core::Page<String>> {
+            Ok(tunez_core::Page {
+                items: vec!["Band".into()],
                 next: None,
             })
         }
@@ -2024,6 +4045,26 @@ mod tests {
                 next: None,
             })
         }
+        fn list_artist_albums(
+            &self,
+            artist: &str,
+            _paging: tunez_core::PageRequest,
+        ) -> tunez_core::ProviderResult<tunez_core::Page<tunez_core::Album>> {
+            let album = |title: &str, year| tunez_core::Album {
+                id: tunez_core::AlbumId::new(title),
+                provider_id: "mock".into(),
+                title: title.into(),
+                artist: artist.into(),
+                track_count: None,
+                duration_seconds: None,
+                year,
+                added_at: None,
+            };
+            Ok(tunez_core::Page {
+                items: vec![album("Zulu", Some(1990)), album("Alpha", Some(2001))],
+                next: None,
+            })
+        }
         fn list_playlists(
             &self,
             _paging: tunez_core::PageRequest,
@@ -2065,14 +4106,23 @@ mod tests {
         ) -> tunez_core::ProviderResult<tunez_core::Album> {
             unimplemented!()
         }
+        /// The album "long" has more tracks than fit on one page.
         fn list_album_tracks(
             &self,
-            _album_id: &tunez_core::AlbumId,
-            _paging: tunez_core::PageRequest,
+            album_id: &tunez_core::AlbumId,
+            paging: tunez_core::PageRequest,
         ) -> tunez_core::ProviderResult<tunez_core::Page<tunez_core::Track>> {
+            let total = if album_id.0 == "long" { 120 } else { 0 };
+            let end = paging.end().min(total);
             Ok(tunez_core::Page {
-                items: vec![],
-                next: None,
+                items: (paging.offset.min(end)..end)
+                    .map(|n| tunez_core::Track {
+                        id: tunez_core::TrackId::new(n.to_string()),
+                        title: format!("Track {n}"),
+                        ..album_track(None, None)
+                    })
+                    .collect(),
+                next: (end < total).then(|| tunez_core::PageCursor::from_offset(end)),
             })
         }
         fn get_track(
@@ -2085,10 +4135,104 @@ mod tests {
             &self,
             _track_id: &tunez_core::TrackId,
         ) -> tunez_core::ProviderResult<tunez_core::StreamUrl> {
-            unimplemented!()
+            Err(tunez_core::ProviderError::NotSupported {
+                operation: "get_stream_url".into(),
+            })
         }
     }
 
+    #[tokio::test]
+    async fn failed_provider_opens_config_and_can_be_switched() {
+        let broken = ProviderSelection {
+            provider_id: "filesystem".into(),
+            profile: Some("home".into()),
+        };
+        let working = ProviderSelection {
+            provider_id: "mock".into(),
+            profile: None,
+        };
+        let (_tmp, dirs) = test_dirs();
+        let mut context = UiContext::without_provider(
+            broken.clone(),
+            "library_root './nowhere' is not a directory",
+            None,
+            Theme::default(),
+            dirs,
+        );
+        context.provider_options = vec![broken, working.clone()];
+        context.connect_provider = Some(Arc::new(|selection: &ProviderSelection| {
+            if selection.provider_id == "mock" {
+                Ok(Arc::new(MockProvider) as Arc<dyn Provider>)
+            } else {
+                Err("still broken".into())
+            }
+        }));
+        let mut app = App::new(context);
+        assert_eq!(app.tabs[app.active_tab], Tab::Config);
+        assert!(app.provider_error.is_some());
+        assert_eq!(app.config_items[0], "Provider");
+        assert!(!app.tabs.contains(&Tab::Playlists));
+
+        app.cycle_provider();
+        assert_eq!(app.provider_selection, working);
+        assert_eq!(app.provider.id(), "mock");
+        assert!(app.provider_error.is_none());
+        // The mock has playlists, and the Config tab stays in view.
+        assert!(app.tabs.contains(&Tab::Playlists));
+        assert_eq!(app.tabs[app.active_tab], Tab::Config);
+
+        app.cycle_provider();
+        assert_eq!(app.provider_error.as_deref(), Some("still broken"));
+    }
+
+    #[test]
+    fn playlists_tab_follows_the_capability() {
+        let none = ProviderCapabilities::default();
+        assert!(!Tab::for_capabilities(&none).contains(&Tab::Playlists));
+        assert_eq!(Tab::for_capabilities(&none).len(), Tab::all().len() - 1);
+        assert_eq!(capability_summary(&none), "none");
+
+        let some = ProviderCapabilities {
+            playlists: true,
+            favorites: true,
+            ..Default::default()
+        };
+        assert_eq!(Tab::for_capabilities(&some), Tab::all());
+        assert_eq!(capability_summary(&some), "playlists, favorites");
+    }
+
+    #[tokio::test]
+    async fn failed_search_shows_a_user_message() {
+        let selection = ProviderSelection {
+            provider_id: "filesystem".into(),
+            profile: None,
+        };
+        let (_tmp, dirs) = test_dirs();
+        let context = UiContext::without_provider(
+            selection,
+            "connection refused",
+            None,
+            Theme::default(),
+            dirs,
+        );
+        let mut app = App::new(context);
+        app.active_tab = app.tabs.iter().position(|t| *t == Tab::Search).unwrap();
+        app.is_searching = true;
+        app.search_query = "ban".into();
+        app.handle_key(KeyEvent::from(KeyCode::Enter));
+        for _ in 0..100 {
+            app.tick();
+            if app.toast.is_some() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let toast = app.toast.expect("search error should be shown");
+        assert_eq!(toast.message, "Search failed; see the log for details");
+        assert_eq!(toast.severity, Severity::Persistent);
+        assert!(toast.detail.unwrap().contains("connection refused"));
+    }
+
     #[tokio::test]
     async fn tab_numbers_jump_correctly() {
         let provider = Arc::new(MockProvider);
@@ -2096,7 +4240,7 @@ mod tests {
             provider_id: "filesystem".into(),
             profile: Some("home".into()),
         };
-        let dirs = tunez_core::AppDirs::discover().expect("failed to discover dirs");
+        let (_tmp, dirs) = test_dirs();
         let context = UiContext::new(provider, provider_selection, None, Theme::default(), dirs);
         let mut app = App::new(context);
         app.jump_to_tab('3');
@@ -2104,4 +4248,675 @@ mod tests {
         app.jump_to_tab('9'); // out of range ignored
         assert_eq!(app.active_tab, 2);
     }
+
+    #[tokio::test]
+    async fn open_playlists_load_their_next_page_of_tracks() {
+        let provider = Arc::new(MockProvider);
+        let provider_selection = ProviderSelection {
+            provider_id: "mock".into(),
+            profile: None,
+        };
+        let (_tmp, dirs) = test_dirs();
+        let context = UiContext::new(provider, provider_selection, None, Theme::default(), dirs);
+        let mut app = App::new(context);
+        app.active_tab = app.tabs.iter().position(|t| *t == Tab::Playlists).unwrap();
+        app.viewing_album_tracks = true;
+        app.album_tracks_source = Some(TrackListSource::Playlist(tunez_core::PlaylistId::new(
+            "mix",
+        )));
+        app.album_tracks_paging.first(2);
+        app.album_tracks_paging.receive(
+            &mut app.album_tracks,
+            tunez_core::Page {
+                items: vec![album_track(None, None), album_track(None, None)],
+                next: Some(tunez_core::PageCursor::from_offset(2)),
+            },
+        );
+
+        app.album_tracks_state.select(Some(1));
+        app.load_more();
+        assert!(app.album_tracks_request.is_pending());
+    }
+
+    #[tokio::test]
+    async fn playing_from_a_long_album_queues_all_of_it() {
+        let provider = Arc::new(MockProvider);
+        let provider_selection = ProviderSelection {
+            provider_id: "mock".into(),
+            profile: None,
+        };
+        let (_tmp, dirs) = test_dirs();
+        let context = UiContext::new(provider, provider_selection, None, Theme::default(), dirs);
+        let mut app = App::new(context);
+        app.active_tab = app.tabs.iter().position(|t| *t == Tab::Library).unwrap();
+        app.load_album_tracks(tunez_core::AlbumId::new("long"), "Long".into());
+        for _ in 0..100 {
+            app.tick();
+            if !app.album_tracks.is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(app.album_tracks.len(), PAGE_SIZE as usize);
+
+        app.play_from_track_list(3);
+        for _ in 0..100 {
+            app.tick();
+            if app.track_list_play_rx.is_none() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(app.player.queue().len(), 120);
+        assert_eq!(app.player.current().unwrap().track.title, "Track 3");
+    }
+
+    #[tokio::test]
+    async fn leaving_a_tab_drops_its_load() {
+        let provider = Arc::new(MockProvider);
+        let provider_selection = ProviderSelection {
+            provider_id: "filesystem".into(),
+            profile: None,
+        };
+        let (_tmp, dirs) = test_dirs();
+        let context = UiContext::new(provider, provider_selection, None, Theme::default(), dirs);
+        let mut app = App::new(context);
+        app.active_tab = app.tabs.iter().position(|t| *t == Tab::Library).unwrap();
+        app.library_push(LibraryView::Album {
+            id: tunez_core::AlbumId::new("album"),
+            title: "Record".into(),
+        });
+        assert!(app.album_tracks_request.is_pending());
+
+        // Switch before the album's tracks have been applied.
+        app.active_tab = app.tabs.iter().position(|t| *t == Tab::Playlists).unwrap();
+        app.on_tab_changed();
+        assert!(!app.album_tracks_request.is_pending());
+
+        for _ in 0..20 {
+            app.tick();
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(!app.viewing_album_tracks);
+        assert_eq!(app.current_album_name, None);
+        assert!(app.album_tracks_paging.more().is_none());
+    }
+
+    #[test]
+    fn progress_formats_times_and_clamps_ratio() {
+        assert_eq!(
+            format_progress(Duration::from_secs(75), Some(300)),
+            ("01:15 / 05:00".to_string(), 25)
+        );
+        assert_eq!(
+            format_progress(Duration::from_millis(299_500), Some(300)).1,
+            99
+        );
+        assert_eq!(
+            format_progress(Duration::from_secs(400), Some(300)),
+            ("05:00 / 05:00".to_string(), 100)
+        );
+        assert_eq!(
+            format_progress(Duration::from_secs(3_725), Some(7_200)).0,
+            "1:02:05 / 2:00:00"
+        );
+    }
+
+    fn album_track(disc: Option<u32>, seconds: Option<u32>) -> tunez_core::Track {
+        tunez_core::Track {
+            id: tunez_core::TrackId::new("t"),
+            provider_id: "mock".into(),
+            title: "Song".into(),
+            artist: "Band".into(),
+            album: Some("Album".into()),
+            duration_seconds: seconds,
+            track_number: None,
+            disc_number: disc,
+            year: None,
+            genre: None,
+            musicbrainz_id: None,
+            replay_gain: Default::default(),
+        }
+    }
+
+    #[test]
+    fn album_summary_totals_known_durations_across_discs() {
+        let single = [
+            album_track(Some(1), Some(200)),
+            album_track(Some(1), Some(100)),
+        ];
+        let summary = album_summary(&single, true);
+        assert_eq!(
+            summary,
+            AlbumSummary {
+                tracks: 2,
+                total_seconds: 300,
+                unknown_durations: 0,
+                multi_disc: false,
+                partial: false,
+            }
+        );
+        assert_eq!(summary.label(), "2 tracks · 05:00");
+
+        let double = [
+            album_track(Some(1), Some(3_000)),
+            album_track(None, None),
+            album_track(Some(2), Some(900)),
+            album_track(Some(2), None),
+        ];
+        let summary = album_summary(&double, true);
+        assert_eq!(summary.total_seconds, 3_900);
+        assert_eq!(summary.unknown_durations, 2);
+        assert!(summary.multi_disc);
+        assert_eq!(
+            summary.label(),
+            "4 tracks · 1:05:00 (+2 unknown) · multi-disc"
+        );
+
+        let summary = album_summary(&[album_track(None, None)], true);
+        assert!(!summary.multi_disc);
+        assert_eq!(summary.label(), "1 track · 00:00 (+1 unknown)");
+
+        // More pages to come: say the totals are only what's loaded so far.
+        let summary = album_summary(&single, false);
+        assert_eq!(summary.label(), "2+ tracks · 05:00 so far");
+    }
+
+    #[test]
+    fn progress_without_duration_is_indeterminate() {
+        assert_eq!(
+            format_progress(Duration::from_secs(42), None),
+            ("00:42 / --:--".to_string(), 0)
+        );
+        assert_eq!(
+            format_progress(Duration::from_secs(42), Some(0)),
+            ("00:42 / --:--".to_string(), 0)
+        );
+    }
+
+    #[test]
+    fn click_in_nav_maps_to_tab() {
+        let nav = Rect::new(0, 3, 18, 12);
+        let tabs = Tab::all().len();
+        assert_eq!(tab_at(nav, tabs, 5, 4), Some(0));
+        assert_eq!(tab_at(nav, tabs, 5, 6), Some(2));
+        assert_eq!(tab_at(nav, tabs, 16, 11), Some(7));
+        // Borders and rows past the last tab are not tabs.
+        assert_eq!(tab_at(nav, tabs, 5, 3), None);
+        assert_eq!(tab_at(nav, tabs, 0, 5), None);
+        assert_eq!(tab_at(nav, tabs, 5, 12), None);
+        assert_eq!(tab_at(nav, tabs, 30, 5), None);
+    }
+
+    #[test]
+    fn list_clicks_account_for_scroll_and_gauge_clicks_seek() {
+        let list = Rect::new(20, 5, 40, 10);
+        assert_eq!(list_row_at(list, 0, 25, 6), Some(0));
+        assert_eq!(list_row_at(list, 10, 25, 8), Some(12));
+
+        let gauge = Rect::new(20, 20, 13, 3);
+        assert_eq!(seek_fraction(gauge, 21, 21), Some(0.0));
+        assert_eq!(seek_fraction(gauge, 31, 21), Some(1.0));
+        assert_eq!(seek_fraction(gauge, 26, 21), Some(0.5));
+        assert_eq!(seek_fraction(gauge, 26, 20), None);
+    }
+
+    #[tokio::test]
+    async fn library_push_and_pop_restore_levels() {
+        let provider = Arc::new(MockProvider);
+        let provider_selection = ProviderSelection {
+            provider_id: "filesystem".into(),
+            profile: None,
+        };
+        let (_tmp, dirs) = test_dirs();
+        let context = UiContext::new(provider, provider_selection, None, Theme::default(), dirs);
+        let mut app = App::new(context);
+        app.library_items = vec![
+            tunez_core::CollectionItem::Artist {
+                name: "First".into(),
+                provider_id: "mock".into(),
+            },
+            tunez_core::CollectionItem::Artist {
+                name: "Band".into(),
+                provider_id: "mock".into(),
+            },
+        ];
+        app.library_state.select(Some(1));
+        assert!(!app.library_pop());
+
+        app.library_push(LibraryView::Artist("Band".into()));
+        assert!(app.library_items.is_empty());
+        assert_eq!(app.library_state.selected(), None);
+        assert_eq!(app.library_breadcrumb(), "Library › Band");
+
+        app.library_items = vec![tunez_core::CollectionItem::Album(tunez_core::Album {
+            id: tunez_core::AlbumId::new("album"),
+            provider_id: "mock".into(),
+            title: "Record".into(),
+            artist: "Band".into(),
+            track_count: None,
+            duration_seconds: None,
+            year: None,
+            added_at: None,
+        })];
+        app.library_state.select(Some(0));
+        app.library_push(LibraryView::Album {
+            id: tunez_core::AlbumId::new("album"),
+            title: "Record".into(),
+        });
+        assert!(app.viewing_album_tracks);
+        assert_eq!(app.library_breadcrumb(), "Library › Band › Record");
+
+        assert!(app.library_pop());
+        assert!(!app.viewing_album_tracks);
+        assert_eq!(app.library_view, LibraryView::Artist("Band".into()));
+        assert_eq!(app.library_items.len(), 1);
+        assert_eq!(app.library_state.selected(), Some(0));
+
+        assert!(app.library_pop());
+        assert_eq!(app.library_view, LibraryView::Root);
+        assert_eq!(app.library_items.len(), 2);
+        assert_eq!(app.library_state.selected(), Some(1));
+        assert_eq!(app.library_breadcrumb(), "Library");
+    }
+
+    #[tokio::test]
+    async fn sort_key_reloads_the_library_in_the_next_order() {
+        let provider = Arc::new(MockProvider);
+        let provider_selection = ProviderSelection {
+            provider_id: "filesystem".into(),
+            profile: None,
+        };
+        let (_tmp, dirs) = test_dirs();
+        let context = UiContext::new(provider, provider_selection, None, Theme::default(), dirs);
+        let mut app = App::new(context);
+        app.active_tab = app.tabs.iter().position(|t| *t == Tab::Library).unwrap();
+        app.library_push(LibraryView::Artist("Band".into()));
+
+        for (step, (sort, expected)) in [
+            (tunez_core::SortOrder::Title, ["Alpha", "Zulu"]),
+            (tunez_core::SortOrder::Artist, ["Alpha", "Zulu"]),
+            (tunez_core::SortOrder::Year, ["Zulu", "Alpha"]),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            if step > 0 {
+                app.handle_key(KeyEvent::from(KeyCode::Char('o')));
+            }
+            assert!(app.library_paging.loading_first());
+            for _ in 0..100 {
+                app.tick();
+                if !app.library_paging.loading_first() {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+            let titles: Vec<_> = app.library_items.iter().map(|item| item.name()).collect();
+            assert_eq!(app.library_sort, sort);
+            assert_eq!(titles, expected);
+        }
+        assert_eq!(app.library_breadcrumb(), "Library › Band");
+    }
+
+    #[tokio::test]
+    async fn recently_played_row_leads_the_library() {
+        let provider = Arc::new(MockProvider);
+        let provider_selection = ProviderSelection {
+            provider_id: "filesystem".into(),
+            profile: None,
+        };
+        let (_tmp, dirs) = test_dirs();
+        let context = UiContext::new(provider, provider_selection, None, Theme::default(), dirs);
+        let mut app = App::new(context);
+        app.active_tab = app.tabs.iter().position(|t| *t == Tab::Library).unwrap();
+        app.library_items = vec![tunez_core::CollectionItem::Artist {
+            name: "Band".into(),
+            provider_id: "mock".into(),
+        }];
+        assert_eq!(
+            app.library_shortcuts(),
+            [LibraryView::RecentlyPlayed, LibraryView::MostPlayed]
+        );
+        assert_eq!(app.active_list().map(|(_, len)| len), Some(3));
+        app.library_state.select(Some(0));
+        app.handle_key(KeyEvent::from(KeyCode::Char('j')));
+        assert_eq!(app.library_state.selected(), Some(1));
+
+        app.library_push(LibraryView::RecentlyPlayed);
+        assert!(app.viewing_album_tracks);
+        assert!(app.library_shortcuts().is_empty());
+        assert_eq!(app.library_breadcrumb(), "Library › Recently Played");
+        for _ in 0..100 {
+            app.tick();
+            if app.library_status.is_some() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(app.library_status.as_deref(), Some("Nothing played yet"));
+
+        assert!(app.library_pop());
+        assert!(!app.viewing_album_tracks);
+        assert_eq!(app.library_items.len(), 1);
+        assert_eq!(app.library_shortcuts().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn an_open_playlist_shows_and_selects_its_tracks() {
+        let provider = Arc::new(MockProvider);
+        let provider_selection = ProviderSelection {
+            provider_id: "mock".into(),
+            profile: None,
+        };
+        let (_tmp, dirs) = test_dirs();
+        let context = UiContext::new(provider, provider_selection, None, Theme::default(), dirs);
+        let mut app = App::new(context);
+        app.active_tab = app.tabs.iter().position(|t| *t == Tab::Playlists).unwrap();
+        app.playlist_items = vec![tunez_core::Playlist {
+            id: tunez_core::PlaylistId::new("other"),
+            provider_id: "mock".into(),
+            name: "Other list".into(),
+            description: None,
+            track_count: None,
+        }];
+        app.viewing_album_tracks = true;
+        app.current_album_name = Some("Road trip".into());
+        app.album_tracks = ["First", "Second"]
+            .into_iter()
+            .map(|title| tunez_core::Track {
+                title: title.into(),
+                ..album_track(None, None)
+            })
+            .collect();
+        app.album_tracks_state.select(Some(0));
+
+        app.scroll_selection(1);
+        assert_eq!(app.album_tracks_state.selected(), Some(1));
+        assert_eq!(app.playlist_state.selected(), None);
+
+        let mut terminal =
+            ratatui::Terminal::new(ratatui::backend::TestBackend::new(80, 24)).unwrap();
+        terminal.draw(|frame| app.render(frame)).unwrap();
+        let screen: String = terminal
+            .backend()
+            .buffer()
+            .content
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect();
+        assert!(screen.contains("Band - Second"), "{screen}");
+        assert!(screen.contains("Road trip"), "{screen}");
+        assert!(!screen.contains("Other list"), "{screen}");
+    }
+
+    #[tokio::test]
+    async fn the_header_shows_whether_scrobbling_is_on() {
+        let (_tmp, dirs) = test_dirs();
+        let scrobbler = Arc::new(tunez_core::FileScrobbler::new(
+            "file",
+            dirs.data_dir().join("scrobbles.jsonl"),
+            10,
+            "Tunez",
+            None,
+        ));
+        let provider_selection = ProviderSelection {
+            provider_id: "filesystem".into(),
+            profile: None,
+        };
+        let context = UiContext::new(
+            Arc::new(MockProvider),
+            provider_selection,
+            Some(scrobbler),
+            Theme::default(),
+            dirs,
+        );
+        let mut app = App::new(context);
+
+        let header = |app: &mut App| {
+            let mut terminal =
+                ratatui::Terminal::new(ratatui::backend::TestBackend::new(120, 24)).unwrap();
+            terminal.draw(|frame| app.render(frame)).unwrap();
+            terminal.backend().buffer().content[..360]
+                .iter()
+                .map(|cell| cell.symbol())
+                .collect::<String>()
+        };
+        assert!(header(&mut app).contains("Scrobble: ON"));
+        app.scrobbler_manager.set_enabled(false);
+        assert!(header(&mut app).contains("Scrobble: OFF"));
+    }
+
+    #[tokio::test]
+    async fn scrobbling_toggle_without_a_scrobbler_saves_nothing() {
+        let (_tmp, dirs) = test_dirs();
+        let config_path = tunez_core::Config::config_path(&dirs);
+        let mut context = UiContext::new(
+            Arc::new(MockProvider),
+            ProviderSelection {
+                provider_id: "mock".into(),
+                profile: None,
+            },
+            None,
+            Theme::default(),
+            dirs,
+        );
+        context.config = Some(tunez_core::Config::default());
+        let mut app = App::new(context);
+
+        app.toggle_scrobbling();
+        assert!(!app.scrobbler_manager.is_active());
+        assert!(app
+            .config_error
+            .as_deref()
+            .is_some_and(|message| message.starts_with("No scrobbler configured")));
+        assert!(!config_path.exists());
+    }
+
+    #[tokio::test]
+    async fn small_terminals_get_the_compact_layout() {
+        let provider = Arc::new(MockProvider);
+        let provider_selection = ProviderSelection {
+            provider_id: "filesystem".into(),
+            profile: None,
+        };
+        let (_tmp, dirs) = test_dirs();
+        let context = UiContext::new(provider, provider_selection, None, Theme::default(), dirs);
+        let mut app = App::new(context);
+        app.active_tab = app.tabs.iter().position(|t| *t == Tab::Library).unwrap();
+        app.library_items = vec![tunez_core::CollectionItem::Artist {
+            name: "Band".into(),
+            provider_id: "mock".into(),
+        }];
+
+        let draw = |app: &mut App, width, height| {
+            let mut terminal =
+                ratatui::Terminal::new(ratatui::backend::TestBackend::new(width, height)).unwrap();
+            terminal.draw(|frame| app.render(frame)).unwrap();
+            let buffer = terminal.backend().buffer();
+            buffer
+                .content
+                .chunks(usize::from(width))
+                .map(|row| row.iter().map(|cell| cell.symbol()).collect::<String>())
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+
+        let compact = draw(&mut app, 44, 14);
+        assert!(!compact.contains("Resize"), "{compact}");
+        assert!(compact.contains("Library (3/8)"), "{compact}");
+        assert!(compact.contains("Band"), "{compact}");
+        assert!(!compact.contains("Tabs"), "{compact}");
+
+        let full = draw(&mut app, 80, 24);
+        assert!(full.contains("Tabs") && full.contains("Band"), "{full}");
+
+        let tiny = draw(&mut app, 30, 14);
+        assert!(tiny.contains("Resize") && tiny.contains("40x12"), "{tiny}");
+        app.layout.min_width = 30;
+        assert!(draw(&mut app, 30, 14).contains("Band"));
+    }
+
+    #[tokio::test]
+    async fn slash_filters_the_queue_in_place() {
+        let provider = Arc::new(MockProvider);
+        let provider_selection = ProviderSelection {
+            provider_id: "filesystem".into(),
+            profile: None,
+        };
+        let (_tmp, dirs) = test_dirs();
+        let context = UiContext::new(provider, provider_selection, None, Theme::default(), dirs);
+        let mut app = App::new(context);
+        app.player.queue_mut().clear();
+        for title in ["Abba", "Blur", "Cabaret", "Dio"] {
+            app.player.queue_mut().enqueue_back(tunez_core::Track {
+                id: tunez_core::TrackId::new(title),
+                provider_id: "mock".into(),
+                title: title.into(),
+                artist: "Band".into(),
+                album: None,
+                duration_seconds: None,
+                track_number: None,
+                disc_number: None,
+                year: None,
+                genre: None,
+                musicbrainz_id: None,
+                replay_gain: Default::default(),
+            });
+        }
+        let queue = app.tabs.iter().position(|t| *t == Tab::Queue).unwrap();
+        app.active_tab = queue;
+        app.queue_state.select(Some(1));
+        let type_keys = |app: &mut App, keys: &str| {
+            for c in keys.chars() {
+                app.handle_key(KeyEvent::from(KeyCode::Char(c)));
+            }
+        };
+
+        type_keys(&mut app, "/ca");
+        assert_eq!(app.active_tab, queue);
+        assert_eq!(app.filtered_rows(), Some(vec![2]));
+        assert_eq!(app.queue_state.selected(), Some(2));
+        assert_eq!(app.focused_track().unwrap().title, "Cabaret");
+
+        // Applied filters keep narrowing navigation; Esc shows everything
+        // with the selection left in place.
+        app.handle_key(KeyEvent::from(KeyCode::Backspace));
+        app.handle_key(KeyEvent::from(KeyCode::Backspace));
+        type_keys(&mut app, "ab");
+        app.handle_key(KeyEvent::from(KeyCode::Enter));
+        assert_eq!(app.filtered_rows(), Some(vec![0, 2]));
+        assert_eq!(app.queue_state.selected(), Some(2));
+        app.handle_key(KeyEvent::from(KeyCode::Char('j')));
+        assert_eq!(app.queue_state.selected(), Some(0));
+        app.handle_key(KeyEvent::from(KeyCode::Esc));
+        assert!(app.list_filter.is_none());
+        assert_eq!(app.queue_state.selected(), Some(0));
+
+        // Nothing matching clears the selection until the filter closes.
+        type_keys(&mut app, "/zz");
+        assert_eq!(app.queue_state.selected(), None);
+        app.handle_key(KeyEvent::from(KeyCode::Esc));
+        assert_eq!(app.queue_state.selected(), Some(0));
+        assert_eq!(app.active_tab, queue);
+    }
+
+    #[tokio::test]
+    async fn artist_search_opens_the_artist_in_the_library() {
+        let provider = Arc::new(MockProvider);
+        let provider_selection = ProviderSelection {
+            provider_id: "filesystem".into(),
+            profile: None,
+        };
+        let (_tmp, dirs) = test_dirs();
+        let context = UiContext::new(provider, provider_selection, None, Theme::default(), dirs);
+        let mut app = App::new(context);
+        app.active_tab = app.tabs.iter().position(|t| *t == Tab::Search).unwrap();
+        app.is_searching = true;
+        app.search_query = "ban".into();
+        app.handle_key(KeyEvent::from(KeyCode::Tab));
+        app.handle_key(KeyEvent::from(KeyCode::Tab));
+        assert_eq!(app.search_scope, SearchScope::Artists);
+        app.handle_key(KeyEvent::from(KeyCode::Enter));
+        for _ in 0..100 {
+            app.tick();
+            if !app.search_collections.is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(app.active_list().map(|(_, len)| len), Some(1));
+        assert!(app.focused_track().is_none());
+
+        app.handle_key(KeyEvent::from(KeyCode::Enter));
+        assert_eq!(app.tabs[app.active_tab], Tab::Library);
+        assert_eq!(app.library_view, LibraryView::Artist("Band".into()));
+    }
+
+    #[tokio::test]
+    async fn a_slow_search_does_not_overwrite_a_newer_one() {
+        let provider = Arc::new(MockProvider);
+        let provider_selection = ProviderSelection {
+            provider_id: "filesystem".into(),
+            profile: None,
+        };
+        let (_tmp, dirs) = test_dirs();
+        let context = UiContext::new(provider, provider_selection, None, Theme::default(), dirs);
+        let mut app = App::new(context);
+        app.search_query = "slow band".into();
+        app.perform_search();
+        app.search_query = "band".into();
+        app.perform_search();
+
+        for _ in 0..100 {
+            app.tick();
+            if !app.search_results.is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(app.search_results.len(), 1);
+        assert_eq!(app.search_results[0].title, "band");
+
+        // The first search answers later and is dropped.
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        app.tick();
+        assert_eq!(app.search_results.len(), 1);
+        assert_eq!(app.search_results[0].title, "band");
+    }
+
+    #[tokio::test]
+    async fn enter_in_an_album_queues_the_whole_album() {
+        let provider = Arc::new(MockProvider);
+        let provider_selection = ProviderSelection {
+            provider_id: "filesystem".into(),
+            profile: None,
+        };
+        let (_tmp, dirs) = test_dirs();
+        let context = UiContext::new(provider, provider_selection, None, Theme::default(), dirs);
+        let mut app = App::new(context);
+        app.player.queue_mut().clear();
+        app.player.queue_mut().enqueue_back(album_track(None, None));
+        app.active_tab = app.tabs.iter().position(|t| *t == Tab::Library).unwrap();
+        app.viewing_album_tracks = true;
+        app.album_tracks_source = Some(TrackListSource::Album(tunez_core::AlbumId::new("a")));
+        app.album_tracks = ["One", "Two", "Three"]
+            .map(|title| tunez_core::Track {
+                title: title.into(),
+                ..album_track(Some(1), Some(60))
+            })
+            .to_vec();
+        app.album_tracks_state.select(Some(1));
+
+        app.handle_key(KeyEvent::from(KeyCode::Enter));
+        let titles: Vec<_> = app
+            .player
+            .queue()
+            .items()
+            .iter()
+            .map(|item| item.track.title.as_str())
+            .collect();
+        assert_eq!(titles, ["One", "Two", "Three"]);
+        assert_eq!(app.player.current().unwrap().track.title, "Two");
+    }
 }
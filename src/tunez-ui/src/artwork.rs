@@ -0,0 +1,162 @@
+//! Cover art drawn with Unicode half-blocks.
+//!
+//! Each terminal cell shows two vertically stacked pixels: `▀` with the upper
+//! pixel as foreground and the lower one as background. Cells are about twice
+//! as tall as they are wide, so the pixels come out roughly square.
+
+use image::imageops::FilterType;
+use image::RgbImage;
+use ratatui::style::{Color, Style};
+use ratatui::text::{Line, Span};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ArtworkError {
+    #[error("failed to decode artwork: {0}")]
+    Decode(#[from] image::ImageError),
+}
+
+/// Cover art converted to a grid of colored half-block cells.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArtCells {
+    pub width: u16,
+    pub height: u16,
+    lines: Vec<Line<'static>>,
+}
+
+impl ArtCells {
+    /// Decode `data` and scale it to fit within `max_width` x `max_height`
+    /// cells, keeping its aspect ratio.
+    pub fn from_image_bytes(
+        data: &[u8],
+        max_width: u16,
+        max_height: u16,
+    ) -> Result<Self, ArtworkError> {
+        let image = image::load_from_memory(data)?;
+        let resized = image
+            .resize(
+                u32::from(max_width.max(1)),
+                u32::from(max_height.max(1)) * 2,
+                FilterType::Triangle,
+            )
+            .to_rgb8();
+        Ok(Self::from_pixels(&resized))
+    }
+
+    fn from_pixels(pixels: &RgbImage) -> Self {
+        let rgb = |x, y| {
+            let [r, g, b] = pixels.get_pixel(x, y).0;
+            Color::Rgb(r, g, b)
+        };
+        let lines: Vec<Line<'static>> = (0..pixels.height())
+            .step_by(2)
+            .map(|y| {
+                let spans: Vec<Span<'static>> = (0..pixels.width())
+                    .map(|x| {
+                        let mut style = Style::default().fg(rgb(x, y));
+                        // An odd final row leaves the lower half empty.
+                        if y + 1 < pixels.height() {
+                            style = style.bg(rgb(x, y + 1));
+                        }
+                        Span::styled("▀", style)
+                    })
+                    .collect();
+                Line::from(spans)
+            })
+            .collect();
+        Self {
+            width: pixels.width() as u16,
+            height: lines.len() as u16,
+            lines,
+        }
+    }
+
+    pub fn lines(&self) -> &[Line<'static>] {
+        &self.lines
+    }
+}
+
+/// The most recently rendered cover, so it is only decoded again when the
+/// album or the available space changes.
+#[derive(Debug, Default)]
+pub struct ArtworkCache {
+    entry: Option<(String, u16, u16, Option<ArtCells>)>,
+}
+
+impl ArtworkCache {
+    /// Cells for `album_key` at `width` x `height`, decoding `data` on a miss.
+    /// Undecodable artwork is cached as `None` so it is not retried each frame.
+    pub fn get_or_render(
+        &mut self,
+        album_key: &str,
+        width: u16,
+        height: u16,
+        data: &[u8],
+    ) -> Option<&ArtCells> {
+        let hit = matches!(
+            &self.entry,
+            Some((key, w, h, _)) if key == album_key && *w == width && *h == height
+        );
+        if !hit {
+            let cells = ArtCells::from_image_bytes(data, width, height)
+                .map_err(|e| tracing::debug!("Cannot show artwork: {}", e))
+                .ok();
+            self.entry = Some((album_key.to_string(), width, height, cells));
+        }
+        self.entry.as_ref().and_then(|(.., cells)| cells.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageFormat, Rgb};
+    use std::io::Cursor;
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let image = RgbImage::from_fn(width, height, |x, y| {
+            if y < height / 2 {
+                Rgb([255, 0, 0])
+            } else {
+                Rgb([0, 0, 255 - x as u8])
+            }
+        });
+        let mut bytes = Cursor::new(Vec::new());
+        image.write_to(&mut bytes, ImageFormat::Png).unwrap();
+        bytes.into_inner()
+    }
+
+    #[test]
+    fn tiny_png_fills_expected_cells() {
+        let cells = ArtCells::from_image_bytes(&png(4, 4), 4, 2).unwrap();
+        assert_eq!((cells.width, cells.height), (4, 2));
+        assert_eq!(cells.lines().len(), 2);
+        assert!(cells.lines().iter().all(|line| line.spans.len() == 4));
+        assert_eq!(
+            cells.lines()[0].spans[0].style,
+            Style::default()
+                .fg(Color::Rgb(255, 0, 0))
+                .bg(Color::Rgb(255, 0, 0))
+        );
+        assert_eq!(cells.lines()[1].spans[0].content, "▀");
+    }
+
+    #[test]
+    fn wide_image_keeps_aspect_ratio() {
+        // 8x2 pixels into at most 4x4 cells scales to 4x1 pixels: one cell row.
+        let cells = ArtCells::from_image_bytes(&png(8, 2), 4, 4).unwrap();
+        assert_eq!((cells.width, cells.height), (4, 1));
+        assert_eq!(cells.lines()[0].spans[0].style.bg, None);
+    }
+
+    #[test]
+    fn cache_rerenders_only_when_key_or_size_changes() {
+        let mut cache = ArtworkCache::default();
+        let data = png(4, 4);
+        assert_eq!(cache.get_or_render("album", 4, 2, &data).unwrap().width, 4);
+        // Same key and size: the cached cells are used, not the new bytes.
+        assert!(cache.get_or_render("album", 4, 2, b"garbage").is_some());
+        assert_eq!(cache.get_or_render("album", 2, 1, &data).unwrap().width, 2);
+        assert!(cache.get_or_render("other", 2, 1, b"garbage").is_none());
+    }
+}
@@ -0,0 +1,195 @@
+//! Type-ahead filtering over the rows a list has already loaded.
+//!
+//! Unlike the Search tab nothing is sent to the provider: the rows shown are
+//! narrowed to those whose name contains the typed text. Selections stay in
+//! terms of the full list, so actions on the selected row work unchanged.
+
+use ratatui::widgets::ListState;
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ListFilter {
+    query: String,
+    /// Whether keys still go to the query rather than the list.
+    typing: bool,
+    /// Selection to come back to once a wider query shows it again.
+    anchor: Option<usize>,
+    /// Scroll offset of the narrowed list.
+    offset: usize,
+}
+
+impl ListFilter {
+    /// An empty filter taking keystrokes.
+    pub(crate) fn new() -> Self {
+        Self {
+            typing: true,
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn query(&self) -> &str {
+        &self.query
+    }
+
+    pub(crate) fn is_typing(&self) -> bool {
+        self.typing
+    }
+
+    /// Send keys to the query, or keep it and hand them back to the list.
+    pub(crate) fn set_typing(&mut self, typing: bool) {
+        self.typing = typing;
+    }
+
+    pub(crate) fn push(&mut self, c: char) {
+        self.query.push(c);
+    }
+
+    pub(crate) fn pop(&mut self) {
+        self.query.pop();
+    }
+
+    /// Whether a row called `name` is shown: a case-insensitive substring
+    /// match, so an empty query shows everything.
+    pub(crate) fn matches(&self, name: &str) -> bool {
+        name.to_lowercase().contains(&self.query.to_lowercase())
+    }
+
+    /// Indices of the shown rows among `names`.
+    pub(crate) fn visible<S: AsRef<str>>(&self, names: &[S]) -> Vec<usize> {
+        (0..names.len())
+            .filter(|&i| self.matches(names[i].as_ref()))
+            .collect()
+    }
+
+    /// Re-point `state` after the query changed: the selected row if it's
+    /// still shown, else the next shown row after it, else the last one.
+    pub(crate) fn reselect(&mut self, state: &mut ListState, visible: &[usize]) {
+        let wanted = self.anchor.or(state.selected());
+        let selected = match wanted {
+            Some(wanted) => visible
+                .iter()
+                .find(|&&i| i >= wanted)
+                .or(visible.last())
+                .copied(),
+            None => visible.first().copied(),
+        };
+        // Narrowed away from it; a wider query may bring it back.
+        self.anchor = if selected == wanted { None } else { wanted };
+        state.select(selected);
+    }
+
+    /// Move the selection `delta` shown rows, wrapping round or stopping at
+    /// the ends.
+    pub(crate) fn step(
+        &mut self,
+        state: &mut ListState,
+        visible: &[usize],
+        delta: isize,
+        wrap: bool,
+    ) {
+        if visible.is_empty() {
+            return;
+        }
+        let position = state
+            .selected()
+            .and_then(|s| visible.iter().position(|&i| i == s));
+        let last = visible.len() - 1;
+        let next = match position {
+            Some(p) if wrap => (p as isize + delta).rem_euclid(visible.len() as isize) as usize,
+            Some(p) => p.saturating_add_signed(delta).min(last),
+            None => 0,
+        };
+        state.select(Some(visible[next]));
+        self.anchor = None;
+    }
+
+    /// The selection to keep once the filter is closed.
+    pub(crate) fn restore(&self, state: &mut ListState) {
+        if state.selected().is_none() {
+            state.select(self.anchor);
+        }
+    }
+
+    /// State for drawing the narrowed list: its scroll offset and the
+    /// selected row's position among `visible`.
+    pub(crate) fn display_state(&self, state: &ListState, visible: &[usize]) -> ListState {
+        let selected = state
+            .selected()
+            .and_then(|s| visible.iter().position(|&i| i == s));
+        ListState::default()
+            .with_offset(self.offset)
+            .with_selected(selected)
+    }
+
+    /// Keep the scroll offset the narrowed list was drawn with.
+    pub(crate) fn set_offset(&mut self, offset: usize) {
+        self.offset = offset;
+    }
+
+    pub(crate) fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NAMES: [&str; 5] = ["Abba", "Blur", "Cabaret", "Dio", "Queen"];
+
+    fn typed(query: &str) -> ListFilter {
+        let mut filter = ListFilter::new();
+        query.chars().for_each(|c| filter.push(c));
+        filter
+    }
+
+    #[test]
+    fn rows_match_on_any_case_substring() {
+        assert_eq!(typed("").visible(&NAMES), [0, 1, 2, 3, 4]);
+        assert_eq!(typed("ab").visible(&NAMES), [0, 2]);
+        assert_eq!(typed("AB").visible(&NAMES), [0, 2]);
+        assert_eq!(typed("een").visible(&NAMES), [4]);
+        assert!(typed("zz").visible(&NAMES).is_empty());
+    }
+
+    #[test]
+    fn selection_survives_narrowing_and_widening() {
+        let mut filter = ListFilter::new();
+        let mut state = ListState::default().with_selected(Some(2));
+
+        // Still shown: kept.
+        filter.push('a');
+        filter.reselect(&mut state, &filter.visible(&NAMES));
+        assert_eq!(state.selected(), Some(2));
+
+        // Hidden: the next shown row is selected instead...
+        filter.pop();
+        filter.push('u');
+        filter.reselect(&mut state, &filter.visible(&NAMES));
+        assert_eq!(state.selected(), Some(4));
+        filter.push('z');
+        filter.reselect(&mut state, &filter.visible(&NAMES));
+        assert_eq!(state.selected(), None);
+
+        // ...until widening shows the original again.
+        filter.pop();
+        filter.pop();
+        filter.reselect(&mut state, &filter.visible(&NAMES));
+        assert_eq!(state.selected(), Some(2));
+
+        // Moving through the narrowed rows skips hidden ones and wraps.
+        filter.push('e');
+        let visible = filter.visible(&NAMES);
+        assert_eq!(visible, [2, 4]);
+        filter.step(&mut state, &visible, 1, true);
+        assert_eq!(state.selected(), Some(4));
+        filter.step(&mut state, &visible, 1, false);
+        assert_eq!(state.selected(), Some(4));
+        filter.step(&mut state, &visible, 1, true);
+        assert_eq!(state.selected(), Some(2));
+        assert_eq!(filter.display_state(&state, &visible).selected(), Some(0));
+
+        // Closing keeps the row chosen while filtered.
+        filter.restore(&mut state);
+        assert_eq!(state.selected(), Some(2));
+    }
+}
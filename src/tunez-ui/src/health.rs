@@ -0,0 +1,164 @@
+//! Background reachability checks behind the header's `Net:` indicator.
+//!
+//! The provider's `health_check` runs off the UI thread every
+//! [`CHECK_INTERVAL`]. A single failure only marks the connection degraded;
+//! it takes [`FAILURE_THRESHOLD`] in a row to report it offline, so one
+//! dropped request doesn't make the indicator flap.
+
+use std::sync::{mpsc, Arc};
+use std::time::{Duration, Instant};
+use tunez_core::{Provider, ProviderResult};
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(15);
+const FAILURE_THRESHOLD: u32 = 3;
+
+type Check = Arc<dyn Fn() -> ProviderResult<()> + Send + Sync>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum NetStatus {
+    Ok,
+    Degraded,
+    Offline,
+}
+
+impl NetStatus {
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            NetStatus::Ok => "OK",
+            NetStatus::Degraded => "Degraded",
+            NetStatus::Offline => "Offline",
+        }
+    }
+}
+
+pub(crate) struct HealthMonitor {
+    check: Check,
+    interval: Duration,
+    threshold: u32,
+    failures: u32,
+    next_check: Instant,
+    rx: Option<mpsc::Receiver<ProviderResult<()>>>,
+}
+
+impl HealthMonitor {
+    /// Check `provider`, starting on the first `poll`.
+    pub(crate) fn new(provider: Arc<dyn Provider>) -> Self {
+        Self::with_check(
+            move || provider.health_check(),
+            CHECK_INTERVAL,
+            FAILURE_THRESHOLD,
+        )
+    }
+
+    fn with_check(
+        check: impl Fn() -> ProviderResult<()> + Send + Sync + 'static,
+        interval: Duration,
+        threshold: u32,
+    ) -> Self {
+        Self {
+            check: Arc::new(check),
+            interval,
+            threshold: threshold.max(1),
+            failures: 0,
+            next_check: Instant::now(),
+            rx: None,
+        }
+    }
+
+    pub(crate) fn status(&self) -> NetStatus {
+        match self.failures {
+            0 => NetStatus::Ok,
+            n if n < self.threshold => NetStatus::Degraded,
+            _ => NetStatus::Offline,
+        }
+    }
+
+    /// Take a finished check's result and start the next one when it's due.
+    /// Must be called from within a tokio runtime.
+    pub(crate) fn poll(&mut self, now: Instant) {
+        if let Some(rx) = &self.rx {
+            match rx.try_recv() {
+                Ok(result) => self.record(result),
+                Err(mpsc::TryRecvError::Empty) => return,
+                // The check panicked; try again next interval.
+                Err(mpsc::TryRecvError::Disconnected) => {}
+            }
+            self.rx = None;
+            self.next_check = now + self.interval;
+        }
+        if self.rx.is_none() && now >= self.next_check {
+            let check = self.check.clone();
+            let (tx, rx) = mpsc::channel();
+            self.rx = Some(rx);
+            tokio::task::spawn_blocking(move || {
+                let _ = tx.send(check());
+            });
+        }
+    }
+
+    fn record(&mut self, result: ProviderResult<()>) {
+        match result {
+            Ok(()) => self.failures = 0,
+            Err(e) => {
+                self.failures = self.failures.saturating_add(1);
+                tracing::debug!(
+                    failures = self.failures,
+                    "provider health check failed: {e}"
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use tunez_core::ProviderError;
+
+    /// Start a check at `now` and poll until its result is recorded.
+    fn run_check(monitor: &mut HealthMonitor, now: Instant) {
+        monitor.poll(now);
+        while monitor.rx.is_some() {
+            std::thread::sleep(Duration::from_millis(5));
+            monitor.poll(now);
+        }
+    }
+
+    #[tokio::test]
+    async fn repeated_failures_go_offline_and_recover() {
+        let failing = Arc::new(AtomicBool::new(true));
+        let flag = failing.clone();
+        let mut monitor = HealthMonitor::with_check(
+            move || {
+                if flag.load(Ordering::SeqCst) {
+                    Err(ProviderError::NetworkError {
+                        message: "connection refused".into(),
+                    })
+                } else {
+                    Ok(())
+                }
+            },
+            Duration::from_secs(60),
+            3,
+        );
+        let start = Instant::now();
+        let minute = |n: u32| start + Duration::from_secs(60) * n;
+        assert_eq!(monitor.status(), NetStatus::Ok);
+
+        run_check(&mut monitor, minute(0));
+        assert_eq!(monitor.status(), NetStatus::Degraded);
+        // Not due yet: nothing is sent.
+        monitor.poll(minute(0));
+        assert!(monitor.rx.is_none());
+
+        run_check(&mut monitor, minute(1));
+        assert_eq!(monitor.status(), NetStatus::Degraded);
+        run_check(&mut monitor, minute(2));
+        assert_eq!(monitor.status(), NetStatus::Offline);
+
+        failing.store(false, Ordering::SeqCst);
+        run_check(&mut monitor, minute(3));
+        assert_eq!(monitor.status(), NetStatus::Ok);
+    }
+}
@@ -0,0 +1,308 @@
+//! Configurable key bindings for global actions.
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::collections::BTreeMap;
+use thiserror::Error;
+
+/// A global action that can be bound to keys in `[keybindings]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Quit,
+    Help,
+    PlayPause,
+    Next,
+    Prev,
+    Search,
+    CycleViz,
+    CycleTheme,
+    CycleRepeat,
+    ToggleShuffle,
+    SeekForward,
+    SeekBack,
+    SeekStart,
+    SeekEnd,
+    NextChapter,
+    PrevChapter,
+    ToggleFavorite,
+}
+
+// Config validation and the key map must agree on every action name.
+const _: () = assert!(Action::ALL.len() == tunez_core::KEYBINDING_ACTIONS.len());
+
+impl Action {
+    pub const ALL: [Action; 17] = [
+        Action::Quit,
+        Action::Help,
+        Action::PlayPause,
+        Action::Next,
+        Action::Prev,
+        Action::Search,
+        Action::CycleViz,
+        Action::CycleTheme,
+        Action::CycleRepeat,
+        Action::ToggleShuffle,
+        Action::SeekForward,
+        Action::SeekBack,
+        Action::SeekStart,
+        Action::SeekEnd,
+        Action::NextChapter,
+        Action::PrevChapter,
+        Action::ToggleFavorite,
+    ];
+
+    /// The name used in config files: its entry in
+    /// [`tunez_core::KEYBINDING_ACTIONS`], which lists them in the order the
+    /// actions are declared.
+    pub fn name(self) -> &'static str {
+        tunez_core::KEYBINDING_ACTIONS[self as usize]
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|action| action.name() == name)
+    }
+
+    fn default_keys(self) -> &'static str {
+        match self {
+            Action::Quit => "q esc",
+            Action::Help => "?",
+            Action::PlayPause => "space",
+            Action::Next => "n",
+            Action::Prev => "p",
+            Action::Search => "/",
+            Action::CycleViz => "v",
+            Action::CycleTheme => "t",
+            Action::CycleRepeat => "r",
+            Action::ToggleShuffle => "s",
+            Action::SeekForward => "]",
+            Action::SeekBack => "[",
+            Action::SeekStart => "g",
+            Action::SeekEnd => "G",
+            Action::NextChapter => "}",
+            Action::PrevChapter => "{",
+            Action::ToggleFavorite => "f",
+        }
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum KeyMapError {
+    #[error("unknown keybinding action '{0}'")]
+    UnknownAction(String),
+    #[error("invalid key '{key}' for action '{action}'")]
+    InvalidKey { action: String, key: String },
+}
+
+/// Maps key presses to [`Action`]s.
+#[derive(Debug, Clone)]
+pub struct KeyMap {
+    bindings: Vec<(KeyCode, KeyModifiers, Action)>,
+}
+
+impl Default for KeyMap {
+    fn default() -> Self {
+        let mut map = Self {
+            bindings: Vec::new(),
+        };
+        for action in Action::ALL {
+            for key in action.default_keys().split_whitespace() {
+                let key = parse_key(key).expect("default key bindings are valid");
+                map.bind(key, action);
+            }
+        }
+        map
+    }
+}
+
+impl KeyMap {
+    /// The defaults with `bindings` (action name to whitespace-separated key
+    /// strings) applied on top. A configured action loses its default keys.
+    pub fn from_config(bindings: &BTreeMap<String, String>) -> Result<Self, KeyMapError> {
+        let mut map = Self::default();
+        for (name, keys) in bindings {
+            let action =
+                Action::from_name(name).ok_or_else(|| KeyMapError::UnknownAction(name.clone()))?;
+            let keys = keys
+                .split_whitespace()
+                .map(|key| {
+                    parse_key(key).ok_or_else(|| KeyMapError::InvalidKey {
+                        action: name.clone(),
+                        key: key.to_string(),
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            map.bindings.retain(|(_, _, bound)| *bound != action);
+            for key in keys {
+                map.bind(key, action);
+            }
+        }
+        Ok(map)
+    }
+
+    /// The action bound to `key`, if any.
+    pub fn action(&self, key: &KeyEvent) -> Option<Action> {
+        let (code, modifiers) = normalize(key.code, key.modifiers);
+        self.bindings
+            .iter()
+            .find(|(c, m, _)| *c == code && *m == modifiers)
+            .map(|(_, _, action)| *action)
+    }
+
+    /// Bind `key` to `action`, replacing whatever it was bound to before.
+    fn bind(&mut self, key: KeyEvent, action: Action) {
+        let (code, modifiers) = normalize(key.code, key.modifiers);
+        self.bindings
+            .retain(|(c, m, _)| !(*c == code && *m == modifiers));
+        self.bindings.push((code, modifiers, action));
+    }
+}
+
+/// Shifted characters arrive as the shifted char, with or without SHIFT set
+/// depending on the terminal, so SHIFT is dropped for them.
+fn normalize(code: KeyCode, modifiers: KeyModifiers) -> (KeyCode, KeyModifiers) {
+    match code {
+        KeyCode::Char(_) => (code, modifiers - KeyModifiers::SHIFT),
+        _ => (code, modifiers),
+    }
+}
+
+/// Parse a key string such as `n`, `space`, `ctrl+n` or `alt+shift+left`.
+pub fn parse_key(input: &str) -> Option<KeyEvent> {
+    // A trailing "+" is the plus key itself, as in "ctrl++".
+    let (mods, key) = match input.strip_suffix('+') {
+        Some(rest) if rest.is_empty() || rest.ends_with('+') => {
+            (rest.strip_suffix('+').unwrap_or(""), "+")
+        }
+        _ => input.rsplit_once('+').unwrap_or(("", input)),
+    };
+
+    let mut modifiers = KeyModifiers::NONE;
+    for part in mods.split('+').filter(|p| !p.is_empty()) {
+        modifiers |= match part.to_ascii_lowercase().as_str() {
+            "ctrl" | "control" => KeyModifiers::CONTROL,
+            "alt" => KeyModifiers::ALT,
+            "shift" => KeyModifiers::SHIFT,
+            _ => return None,
+        };
+    }
+
+    let mut chars = key.chars();
+    let code = match (chars.next(), chars.next()) {
+        (Some(c), None) if modifiers.contains(KeyModifiers::SHIFT) => {
+            KeyCode::Char(c.to_ascii_uppercase())
+        }
+        (Some(c), None) => KeyCode::Char(c),
+        _ => match key.to_ascii_lowercase().as_str() {
+            "space" => KeyCode::Char(' '),
+            "enter" | "return" => KeyCode::Enter,
+            "esc" | "escape" => KeyCode::Esc,
+            "tab" => KeyCode::Tab,
+            "backtab" => KeyCode::BackTab,
+            "backspace" => KeyCode::Backspace,
+            "delete" | "del" => KeyCode::Delete,
+            "insert" | "ins" => KeyCode::Insert,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            f if f.starts_with('f') => {
+                KeyCode::F(f[1..].parse().ok().filter(|n| (1..=12).contains(n))?)
+            }
+            _ => return None,
+        },
+    };
+    Some(KeyEvent::new(code, modifiers))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_key_strings() {
+        assert_eq!(
+            parse_key("ctrl+n"),
+            Some(KeyEvent::new(KeyCode::Char('n'), KeyModifiers::CONTROL))
+        );
+        assert_eq!(
+            parse_key("alt+shift+left"),
+            Some(KeyEvent::new(
+                KeyCode::Left,
+                KeyModifiers::ALT | KeyModifiers::SHIFT
+            ))
+        );
+        assert_eq!(
+            parse_key("space"),
+            Some(KeyEvent::new(KeyCode::Char(' '), KeyModifiers::NONE))
+        );
+        assert_eq!(
+            parse_key("ctrl++"),
+            Some(KeyEvent::new(KeyCode::Char('+'), KeyModifiers::CONTROL))
+        );
+        assert_eq!(
+            parse_key("F5"),
+            Some(KeyEvent::new(KeyCode::F(5), KeyModifiers::NONE))
+        );
+        assert_eq!(parse_key("hyper+n"), None);
+        assert_eq!(parse_key("f13"), None);
+        assert_eq!(parse_key("nope"), None);
+    }
+
+    #[test]
+    fn defaults_apply_without_config() {
+        let map = KeyMap::from_config(&BTreeMap::new()).unwrap();
+        let key = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE);
+        assert_eq!(map.action(&key('n')), Some(Action::Next));
+        assert_eq!(map.action(&key(' ')), Some(Action::PlayPause));
+        assert_eq!(
+            map.action(&KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE)),
+            Some(Action::Quit)
+        );
+        // Terminals may report SHIFT alongside shifted characters.
+        assert_eq!(
+            map.action(&KeyEvent::new(KeyCode::Char('?'), KeyModifiers::SHIFT)),
+            Some(Action::Help)
+        );
+        assert_eq!(map.action(&key('x')), None);
+    }
+
+    #[test]
+    fn configured_binding_replaces_default() {
+        let bindings = BTreeMap::from([("next".to_string(), "ctrl+n".to_string())]);
+        let map = KeyMap::from_config(&bindings).unwrap();
+        assert_eq!(
+            map.action(&KeyEvent::new(KeyCode::Char('n'), KeyModifiers::CONTROL)),
+            Some(Action::Next)
+        );
+        assert_eq!(
+            map.action(&KeyEvent::new(KeyCode::Char('n'), KeyModifiers::NONE)),
+            None
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_actions_and_keys() {
+        let bindings = BTreeMap::from([("fly".to_string(), "f".to_string())]);
+        assert_eq!(
+            KeyMap::from_config(&bindings).unwrap_err(),
+            KeyMapError::UnknownAction("fly".into())
+        );
+        let bindings = BTreeMap::from([("next".to_string(), "ctrl+".to_string())]);
+        assert!(matches!(
+            KeyMap::from_config(&bindings),
+            Err(KeyMapError::InvalidKey { .. })
+        ));
+    }
+
+    #[test]
+    fn actions_are_listed_in_declaration_order() {
+        for (index, action) in Action::ALL.into_iter().enumerate() {
+            assert_eq!(action as usize, index);
+            assert_eq!(Action::from_name(action.name()), Some(action));
+        }
+        assert_eq!(Action::SeekBack.name(), "seek_back");
+    }
+}
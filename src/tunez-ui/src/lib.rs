@@ -1,5 +1,18 @@
 pub mod app;
+pub mod artwork;
+mod filter;
+mod health;
 pub mod help;
+pub mod keymap;
+#[cfg(feature = "mpris")]
+mod mpris;
+mod paging;
+mod requests;
+pub mod resolve;
+mod text;
 pub mod theme;
-pub use app::{run_ui, UiContext};
-pub use theme::Theme;
+mod toast;
+mod unavailable;
+pub use app::{run_ui, ProviderConnector, UiContext};
+pub use keymap::{KeyMap, KeyMapError};
+pub use theme::{ColorDepth, Theme, ThemeError};
@@ -0,0 +1,415 @@
+//! MPRIS over D-Bus, so desktop media keys and sound applets can see and
+//! control what's playing.
+//!
+//! The D-Bus connection lives on its own thread. Method calls arrive there
+//! and are passed to the UI as [`MprisCommand`]s, which `App::tick` runs
+//! against the player like the matching key actions; the UI sends back a
+//! [`MprisState`] whenever what applets should show has changed.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Duration;
+use tunez_core::Track;
+use tunez_player::{Player, PlayerState, QueueId};
+use zbus::zvariant::{ObjectPath, OwnedValue, Value};
+
+const BUS_NAME: &str = "org.mpris.MediaPlayer2.tunez";
+const OBJECT_PATH: &str = "/org/mpris/MediaPlayer2";
+const PLAYER_INTERFACE: &str = "org.mpris.MediaPlayer2.Player";
+/// Prefix of the `mpris:trackid` object path; the queue entry id follows.
+const TRACK_PATH: &str = "/org/tunez/track";
+
+/// A request from a D-Bus client, for the UI to carry out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum MprisCommand {
+    Play,
+    Pause,
+    PlayPause,
+    Stop,
+    Next,
+    Previous,
+    /// Move by this many microseconds, backwards when negative.
+    Seek(i64),
+    /// Jump to a position in the given queue entry, if it's still playing.
+    SetPosition(QueueId, Duration),
+}
+
+/// What D-Bus clients are shown.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct MprisState {
+    status: &'static str,
+    current: Option<(QueueId, Track)>,
+}
+
+impl Default for MprisState {
+    fn default() -> Self {
+        Self {
+            status: "Stopped",
+            current: None,
+        }
+    }
+}
+
+impl MprisState {
+    pub(crate) fn of(player: &Player) -> Self {
+        let status = match player.state() {
+            PlayerState::Playing { .. } | PlayerState::Buffering { .. } => "Playing",
+            PlayerState::Paused { .. } => "Paused",
+            _ => "Stopped",
+        };
+        let current = player
+            .current()
+            .filter(|_| status != "Stopped")
+            .map(|item| (item.id, item.track.clone()));
+        Self { status, current }
+    }
+}
+
+/// The `Metadata` property for `track` playing as queue entry `entry`.
+pub(crate) fn metadata(entry: QueueId, track: &Track) -> HashMap<String, OwnedValue> {
+    let mut map = HashMap::new();
+    let mut insert = |key: &str, value: Value<'_>| {
+        if let Ok(value) = value.try_to_owned() {
+            map.insert(key.to_string(), value);
+        }
+    };
+    insert("mpris:trackid", Value::from(track_path(entry)));
+    insert("xesam:title", Value::from(track.title.as_str()));
+    insert("xesam:artist", Value::from(vec![track.artist.as_str()]));
+    if let Some(album) = &track.album {
+        insert("xesam:album", Value::from(album.as_str()));
+    }
+    if let Some(seconds) = track.duration_seconds {
+        insert("mpris:length", Value::from(i64::from(seconds) * 1_000_000));
+    }
+    if let Some(number) = track.track_number {
+        insert("xesam:trackNumber", Value::from(number as i32));
+    }
+    if let Some(number) = track.disc_number {
+        insert("xesam:discNumber", Value::from(number as i32));
+    }
+    if let Some(genre) = &track.genre {
+        insert("xesam:genre", Value::from(vec![genre.as_str()]));
+    }
+    if let Some(year) = track.year {
+        insert("xesam:contentCreated", Value::from(year.to_string()));
+    }
+    map
+}
+
+fn track_path(entry: QueueId) -> ObjectPath<'static> {
+    ObjectPath::from_string_unchecked(format!("{TRACK_PATH}/{}", entry.0))
+}
+
+/// The queue entry a `mpris:trackid` refers to.
+fn entry_of(path: &ObjectPath<'_>) -> Option<QueueId> {
+    let id = path.as_str().strip_prefix(TRACK_PATH)?.strip_prefix('/')?;
+    id.parse().ok().map(QueueId)
+}
+
+/// The UI's end of the service.
+pub(crate) struct Mpris {
+    commands: mpsc::Receiver<MprisCommand>,
+    updates: mpsc::Sender<MprisState>,
+    position_us: Arc<AtomicI64>,
+    last: Option<MprisState>,
+}
+
+impl Mpris {
+    /// Start serving on the session bus from a background thread. Failing to
+    /// connect is logged there; the controls just never receive anything.
+    pub(crate) fn start() -> Self {
+        let (command_tx, commands) = mpsc::channel();
+        let (updates, update_rx) = mpsc::channel();
+        let position_us = Arc::new(AtomicI64::new(0));
+        let position = position_us.clone();
+        let spawned = std::thread::Builder::new()
+            .name("mpris".into())
+            .spawn(move || {
+                if let Err(err) = serve(command_tx, update_rx, position) {
+                    tracing::warn!(error = %err, "MPRIS service unavailable");
+                }
+            });
+        if let Err(err) = spawned {
+            tracing::warn!(error = %err, "failed to start the MPRIS thread");
+        }
+        Self {
+            commands,
+            updates,
+            position_us,
+            last: None,
+        }
+    }
+
+    /// Requests received since the last call.
+    pub(crate) fn commands(&self) -> impl Iterator<Item = MprisCommand> + '_ {
+        self.commands.try_iter()
+    }
+
+    /// Publish the player's state, notifying clients if it changed.
+    pub(crate) fn update(&mut self, player: &Player) {
+        let micros = i64::try_from(player.position().as_micros()).unwrap_or(i64::MAX);
+        self.position_us.store(micros, Ordering::Relaxed);
+        let state = MprisState::of(player);
+        if self.last.as_ref() != Some(&state) {
+            self.last = Some(state.clone());
+            let _ = self.updates.send(state);
+        }
+    }
+}
+
+/// Own the connection until the UI side is dropped.
+fn serve(
+    commands: mpsc::Sender<MprisCommand>,
+    updates: mpsc::Receiver<MprisState>,
+    position_us: Arc<AtomicI64>,
+) -> zbus::Result<()> {
+    let state = Arc::new(Mutex::new(MprisState::default()));
+    let connection = zbus::blocking::connection::Builder::session()?
+        .name(BUS_NAME)?
+        .serve_at(OBJECT_PATH, RootInterface)?
+        .serve_at(
+            OBJECT_PATH,
+            PlayerInterface {
+                commands,
+                state: state.clone(),
+                position_us,
+            },
+        )?
+        .build()?;
+
+    for update in updates {
+        let changed = HashMap::from([
+            ("PlaybackStatus", Value::from(update.status)),
+            (
+                "Metadata",
+                Value::from(
+                    update
+                        .current
+                        .as_ref()
+                        .map_or_else(HashMap::new, |(id, track)| metadata(*id, track)),
+                ),
+            ),
+            ("CanPlay", Value::from(update.current.is_some())),
+            ("CanPause", Value::from(update.current.is_some())),
+            ("CanSeek", Value::from(update.current.is_some())),
+        ]);
+        *state.lock().unwrap_or_else(|e| e.into_inner()) = update;
+        connection.emit_signal(
+            None::<()>,
+            OBJECT_PATH,
+            "org.freedesktop.DBus.Properties",
+            "PropertiesChanged",
+            &(PLAYER_INTERFACE, changed, Vec::<&str>::new()),
+        )?;
+    }
+    Ok(())
+}
+
+struct RootInterface;
+
+#[zbus::interface(name = "org.mpris.MediaPlayer2")]
+impl RootInterface {
+    fn raise(&self) {}
+
+    fn quit(&self) {}
+
+    #[zbus(property)]
+    fn can_quit(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn can_raise(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn has_track_list(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn identity(&self) -> &str {
+        "Tunez"
+    }
+
+    #[zbus(property)]
+    fn supported_uri_schemes(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    #[zbus(property)]
+    fn supported_mime_types(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+struct PlayerInterface {
+    commands: mpsc::Sender<MprisCommand>,
+    state: Arc<Mutex<MprisState>>,
+    position_us: Arc<AtomicI64>,
+}
+
+impl PlayerInterface {
+    fn send(&self, command: MprisCommand) {
+        let _ = self.commands.send(command);
+    }
+
+    fn state(&self) -> MprisState {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+#[zbus::interface(name = "org.mpris.MediaPlayer2.Player")]
+impl PlayerInterface {
+    fn next(&self) {
+        self.send(MprisCommand::Next);
+    }
+
+    fn previous(&self) {
+        self.send(MprisCommand::Previous);
+    }
+
+    fn pause(&self) {
+        self.send(MprisCommand::Pause);
+    }
+
+    fn play_pause(&self) {
+        self.send(MprisCommand::PlayPause);
+    }
+
+    fn stop(&self) {
+        self.send(MprisCommand::Stop);
+    }
+
+    fn play(&self) {
+        self.send(MprisCommand::Play);
+    }
+
+    fn seek(&self, offset: i64) {
+        self.send(MprisCommand::Seek(offset));
+    }
+
+    fn set_position(&self, track_id: ObjectPath<'_>, position: i64) {
+        if let (Some(entry), Ok(micros)) = (entry_of(&track_id), u64::try_from(position)) {
+            self.send(MprisCommand::SetPosition(
+                entry,
+                Duration::from_micros(micros),
+            ));
+        }
+    }
+
+    fn open_uri(&self, _uri: &str) {}
+
+    #[zbus(property)]
+    fn playback_status(&self) -> &'static str {
+        self.state().status
+    }
+
+    #[zbus(property)]
+    fn rate(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn minimum_rate(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn maximum_rate(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn volume(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn metadata(&self) -> HashMap<String, OwnedValue> {
+        match &self.state().current {
+            Some((entry, track)) => metadata(*entry, track),
+            None => HashMap::new(),
+        }
+    }
+
+    #[zbus(property(emits_changed_signal = "false"))]
+    fn position(&self) -> i64 {
+        self.position_us.load(Ordering::Relaxed)
+    }
+
+    #[zbus(property)]
+    fn can_go_next(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_go_previous(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_play(&self) -> bool {
+        self.state().current.is_some()
+    }
+
+    #[zbus(property)]
+    fn can_pause(&self) -> bool {
+        self.state().current.is_some()
+    }
+
+    #[zbus(property)]
+    fn can_seek(&self) -> bool {
+        self.state().current.is_some()
+    }
+
+    #[zbus(property(emits_changed_signal = "const"))]
+    fn can_control(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tunez_core::TrackId;
+
+    #[test]
+    fn metadata_maps_track_fields_to_mpris_keys() {
+        let track = Track {
+            id: TrackId::new("t1"),
+            provider_id: "test".into(),
+            title: "Song".into(),
+            artist: "Band".into(),
+            album: Some("Album".into()),
+            duration_seconds: Some(245),
+            track_number: Some(3),
+            disc_number: None,
+            year: Some(1999),
+            genre: Some("Rock".into()),
+            musicbrainz_id: None,
+            replay_gain: Default::default(),
+        };
+        let map = metadata(QueueId(42), &track);
+
+        let expect = |key: &str, value: Value<'_>| {
+            assert_eq!(map.get(key), Some(&value.try_to_owned().unwrap()), "{key}");
+        };
+        let path = ObjectPath::try_from("/org/tunez/track/42").unwrap();
+        expect("mpris:trackid", Value::from(path.clone()));
+        expect("mpris:length", Value::from(245_000_000i64));
+        expect("xesam:title", Value::from("Song"));
+        expect("xesam:artist", Value::from(vec!["Band"]));
+        expect("xesam:album", Value::from("Album"));
+        expect("xesam:trackNumber", Value::from(3i32));
+        expect("xesam:genre", Value::from(vec!["Rock"]));
+        expect("xesam:contentCreated", Value::from("1999"));
+        assert_eq!(map.len(), 8);
+
+        assert_eq!(entry_of(&path), Some(QueueId(42)));
+        assert_eq!(entry_of(&ObjectPath::try_from("/other/42").unwrap()), None);
+    }
+}
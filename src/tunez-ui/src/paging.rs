@@ -0,0 +1,126 @@
+//! Cursor bookkeeping for lists that load one page at a time.
+//!
+//! A list asks for its first page, then for the next one whenever the
+//! selection reaches the last loaded row. Providers that never return a
+//! `next` cursor simply end after the first page.
+
+use tunez_core::{Page, PageCursor, PageRequest};
+
+/// Rows requested per page.
+pub(crate) const PAGE_SIZE: u32 = 50;
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ListPaging {
+    next: Option<PageCursor>,
+    /// Set while a request is in flight: `true` when its page extends the
+    /// list, `false` when it replaces it.
+    pending: Option<bool>,
+    limit: u32,
+}
+
+impl ListPaging {
+    /// Forget the loaded pages and return the request for the first one.
+    pub(crate) fn first(&mut self, limit: u32) -> PageRequest {
+        self.next = None;
+        self.pending = Some(false);
+        self.limit = limit;
+        PageRequest::first_page(limit)
+    }
+
+    /// The request for the page after the loaded ones, or `None` while a
+    /// request is in flight or once the provider reported no more.
+    pub(crate) fn more(&mut self) -> Option<PageRequest> {
+        if self.pending.is_some() {
+            return None;
+        }
+        let paging = PageRequest::from_cursor(self.next.as_ref()?, self.limit)?;
+        self.pending = Some(true);
+        Some(paging)
+    }
+
+    /// Put a received page into `items`: the first page replaces them, later
+    /// ones are appended.
+    pub(crate) fn receive<T>(&mut self, items: &mut Vec<T>, page: Page<T>) {
+        if self.pending.take() == Some(true) {
+            items.extend(page.items);
+        } else {
+            *items = page.items;
+        }
+        self.next = page.next;
+    }
+
+    /// The request in flight was abandoned; a later `more` may send it again.
+    pub(crate) fn cancel(&mut self) {
+        self.pending = None;
+    }
+
+    /// The request in flight failed. The list ends here rather than
+    /// retrying on every tick.
+    pub(crate) fn fail(&mut self) {
+        self.pending = None;
+        self.next = None;
+    }
+
+    /// Whether every page has arrived, so the loaded items are the whole list.
+    pub(crate) fn is_complete(&self) -> bool {
+        self.pending.is_none() && self.next.is_none()
+    }
+
+    /// Whether the page in flight is the first one.
+    pub(crate) fn loading_first(&self) -> bool {
+        self.pending == Some(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(items: &[u32], next: Option<u32>) -> Page<u32> {
+        Page {
+            items: items.to_vec(),
+            next: next.map(PageCursor::from_offset),
+        }
+    }
+
+    #[test]
+    fn pages_append_until_the_cursor_runs_out() {
+        let mut paging = ListPaging::default();
+        let mut items = vec![99];
+        assert_eq!(paging.more(), None);
+
+        assert_eq!(paging.first(2), PageRequest::first_page(2));
+        assert!(paging.loading_first());
+        paging.receive(&mut items, page(&[1, 2], Some(2)));
+        assert_eq!(items, [1, 2]);
+
+        let request = paging.more().unwrap();
+        assert_eq!((request.offset, request.limit), (2, 2));
+        // Only one request at a time.
+        assert_eq!(paging.more(), None);
+        paging.receive(&mut items, page(&[3, 4], Some(4)));
+        assert_eq!(items, [1, 2, 3, 4]);
+
+        // An abandoned request is sent again.
+        assert!(paging.more().is_some());
+        paging.cancel();
+        assert_eq!(paging.more().map(|r| r.offset), Some(4));
+        paging.receive(&mut items, page(&[5], None));
+        assert_eq!(items, [1, 2, 3, 4, 5]);
+        assert_eq!(paging.more(), None);
+        assert!(paging.is_complete());
+
+        // A failed one ends the list.
+        paging.first(2);
+        paging.receive(&mut items, page(&[1, 2], Some(2)));
+        assert!(!paging.is_complete());
+        assert!(paging.more().is_some());
+        paging.fail();
+        assert_eq!(paging.more(), None);
+
+        // Starting over replaces the list.
+        paging.first(2);
+        paging.receive(&mut items, page(&[7], None));
+        assert_eq!(items, [7]);
+    }
+}
@@ -0,0 +1,183 @@
+//! Background loads where only the newest answer matters.
+//!
+//! Each request is tagged with a generation, and starting another one bumps
+//! it, so a slow answer to an earlier search or page load is dropped when it
+//! arrives rather than overwriting the newer one. Asking again for exactly
+//! the request already in flight sends nothing and waits for that one.
+//!
+//! The generation doubles as the request's cancellation token: a fetch still
+//! queued for a blocking thread when it's cancelled never calls the provider.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Arc};
+use tokio::task::JoinHandle;
+
+pub(crate) struct LatestRequest<K, T> {
+    generation: u64,
+    /// `generation`, as seen by the fetches waiting to run.
+    current: Arc<AtomicU64>,
+    /// What the newest request asked for, while it's running.
+    in_flight: Option<(K, JoinHandle<()>)>,
+    tx: mpsc::Sender<(u64, T)>,
+    rx: mpsc::Receiver<(u64, T)>,
+}
+
+impl<K: PartialEq, T: Send + 'static> LatestRequest<K, T> {
+    pub(crate) fn new() -> Self {
+        let (tx, rx) = mpsc::channel();
+        Self {
+            generation: 0,
+            current: Arc::new(AtomicU64::new(0)),
+            in_flight: None,
+            tx,
+            rx,
+        }
+    }
+
+    /// Run `fetch` off the UI thread for `key`, superseding any earlier
+    /// request, unless the same request is still running. Returns whether a
+    /// request was sent. Must be called from within a tokio runtime.
+    pub(crate) fn start(&mut self, key: K, fetch: impl FnOnce() -> T + Send + 'static) -> bool {
+        if let Some((running, handle)) = &self.in_flight {
+            // A finished handle with nothing received means the fetch panicked.
+            if *running == key && !handle.is_finished() {
+                return false;
+            }
+        }
+        self.supersede();
+        let generation = self.generation;
+        let current = self.current.clone();
+        let tx = self.tx.clone();
+        let handle = tokio::task::spawn_blocking(move || {
+            if current.load(Ordering::Acquire) != generation {
+                return;
+            }
+            let _ = tx.send((generation, fetch()));
+        });
+        self.in_flight = Some((key, handle));
+        true
+    }
+
+    /// Abandon the request in flight; its answer is dropped when it arrives.
+    pub(crate) fn cancel(&mut self) {
+        self.supersede();
+        self.in_flight = None;
+    }
+
+    /// Whether a request was sent and its answer hasn't been taken yet.
+    pub(crate) fn is_pending(&self) -> bool {
+        self.in_flight.is_some()
+    }
+
+    fn supersede(&mut self) {
+        self.generation += 1;
+        self.current.store(self.generation, Ordering::Release);
+    }
+
+    /// The newest request's answer once it has arrived.
+    pub(crate) fn poll(&mut self) -> Option<T> {
+        while let Ok((generation, result)) = self.rx.try_recv() {
+            if generation == self.generation {
+                self.in_flight = None;
+                return Some(result);
+            }
+            tracing::debug!(generation, "dropping a superseded response");
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+    use std::time::Duration;
+
+    /// Poll until an answer arrives or it's clear none will.
+    fn wait<K: PartialEq, T: Send + 'static>(request: &mut LatestRequest<K, T>) -> Option<T> {
+        for _ in 0..200 {
+            if let Some(result) = request.poll() {
+                return Some(result);
+            }
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        None
+    }
+
+    #[tokio::test]
+    async fn only_the_latest_answer_is_applied() {
+        let mut search = LatestRequest::new();
+        let (release, gate) = mpsc::channel::<()>();
+        search.start("ab", move || {
+            // Answers after the newer search has.
+            let _ = gate.recv_timeout(Duration::from_secs(5));
+            "results for ab"
+        });
+        search.start("abba", || "results for abba");
+
+        assert_eq!(wait(&mut search), Some("results for abba"));
+        // Fails when "ab" was superseded before it started and never ran.
+        let _ = release.send(());
+        assert_eq!(wait(&mut search), None);
+    }
+
+    #[tokio::test]
+    async fn identical_requests_in_flight_are_coalesced() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let fetch = |calls: &Arc<AtomicUsize>, gate: Option<mpsc::Receiver<()>>| {
+            let calls = calls.clone();
+            move || {
+                if let Some(gate) = gate {
+                    let _ = gate.recv_timeout(Duration::from_secs(5));
+                }
+                calls.fetch_add(1, Ordering::SeqCst)
+            }
+        };
+
+        let mut playlists = LatestRequest::new();
+        let (release, gate) = mpsc::channel();
+        assert!(playlists.start(0, fetch(&calls, Some(gate))));
+        assert!(!playlists.start(0, fetch(&calls, None)));
+        release.send(()).unwrap();
+        assert_eq!(wait(&mut playlists), Some(0));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // Once answered, asking again fetches again.
+        assert!(playlists.start(0, fetch(&calls, None)));
+        assert_eq!(wait(&mut playlists), Some(1));
+
+        // A cancelled request's answer never shows up.
+        assert!(playlists.start(1, fetch(&calls, None)));
+        playlists.cancel();
+        assert_eq!(wait(&mut playlists), None);
+    }
+
+    #[test]
+    fn a_request_cancelled_before_it_runs_never_fetches() {
+        // One blocking thread, so the second fetch queues behind the first.
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .max_blocking_threads(1)
+            .build()
+            .unwrap();
+        let _guard = runtime.enter();
+
+        let mut busy = LatestRequest::new();
+        let (release, gate) = mpsc::channel::<()>();
+        busy.start((), move || {
+            let _ = gate.recv_timeout(Duration::from_secs(5));
+        });
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mut tracks = LatestRequest::new();
+        let counted = calls.clone();
+        assert!(tracks.start("album", move || counted.fetch_add(1, Ordering::SeqCst)));
+        assert!(tracks.is_pending());
+        tracks.cancel();
+        assert!(!tracks.is_pending());
+
+        release.send(()).unwrap();
+        assert_eq!(wait(&mut busy), Some(()));
+        assert_eq!(wait(&mut tracks), None);
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+    }
+}
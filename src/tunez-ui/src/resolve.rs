@@ -0,0 +1,396 @@
+//! Resolving a `tunez play` selector to the tracks to queue.
+//!
+//! Resolution only uses the [`Provider`] trait, so every provider gets the
+//! same behaviour. Name matches are case-insensitive; where several items
+//! match, the provider's own ordering decides.
+
+use thiserror::Error;
+use tunez_core::models::PlaySelector;
+use tunez_core::{
+    BrowseKind, CollectionItem, Page, PageRequest, Provider, ProviderError, ProviderResult, Track,
+    TrackId, TrackSearchFilters,
+};
+
+const PAGE_SIZE: u32 = 100;
+/// Stop paging after this many items so a huge library can't stall startup.
+const MAX_ITEMS: usize = 5000;
+
+#[derive(Debug, Error)]
+pub enum ResolveError {
+    #[error("{0} not found")]
+    NotFound(String),
+    #[error(transparent)]
+    Provider(#[from] ProviderError),
+}
+
+/// The tracks `selector` refers to, in play order. Never empty.
+pub fn resolve_selector(
+    provider: &dyn Provider,
+    selector: &PlaySelector,
+) -> Result<Vec<Track>, ResolveError> {
+    let not_found = || ResolveError::NotFound(selector.describe());
+    let tracks = match selector {
+        PlaySelector::Id { id } => match provider.get_track(&TrackId::new(id.as_str())) {
+            Ok(track) => vec![track],
+            Err(ProviderError::NotFound { .. }) => return Err(not_found()),
+            Err(e) => return Err(e.into()),
+        },
+        PlaySelector::TrackSearch {
+            track,
+            artist,
+            album,
+        } => {
+            let filters = TrackSearchFilters {
+                artist: artist.clone(),
+                album: album.clone(),
+                year: None,
+            };
+            let mut matches: Vec<Track> =
+                collect_pages(|paging| provider.search_tracks(track, filters.clone(), paging))?
+                    .into_iter()
+                    .filter(|t| {
+                        artist.as_deref().is_none_or(|a| same_name(&t.artist, a))
+                            && album
+                                .as_deref()
+                                .is_none_or(|a| t.album.as_deref().is_some_and(|b| same_name(b, a)))
+                    })
+                    .collect();
+            // An exact title beats a partial match.
+            if let Some(pos) = matches.iter().position(|t| same_name(&t.title, track)) {
+                matches.swap(0, pos);
+            }
+            matches.into_iter().take(1).collect()
+        }
+        PlaySelector::AlbumSearch { album, artist } => {
+            let albums = collect_pages(|paging| provider.browse(BrowseKind::Albums, paging))?;
+            let found = albums.into_iter().find_map(|item| match item {
+                CollectionItem::Album(a)
+                    if same_name(&a.title, album)
+                        && artist
+                            .as_deref()
+                            .is_none_or(|name| same_name(&a.artist, name)) =>
+                {
+                    Some(a)
+                }
+                _ => None,
+            });
+            match found {
+                Some(album) => {
+                    collect_pages(|paging| provider.list_album_tracks(&album.id, paging))?
+                }
+                None => Vec::new(),
+            }
+        }
+        PlaySelector::ArtistSearch { artist } => {
+            let filters = TrackSearchFilters {
+                artist: Some(artist.clone()),
+                ..Default::default()
+            };
+            let mut tracks: Vec<Track> =
+                collect_pages(|paging| provider.search_tracks(artist, filters.clone(), paging))?
+                    .into_iter()
+                    .filter(|t| same_name(&t.artist, artist))
+                    .collect();
+            tracks.sort_by(|a, b| a.album.cmp(&b.album).then_with(|| a.album_order(b)));
+            tracks
+        }
+        PlaySelector::Playlist { name } => {
+            let playlists = collect_pages(|paging| provider.list_playlists(paging))?;
+            let found = playlists
+                .iter()
+                .find(|p| p.name == *name)
+                .or_else(|| playlists.iter().find(|p| same_name(&p.name, name)));
+            match found {
+                Some(playlist) => {
+                    collect_pages(|paging| provider.list_playlist_tracks(&playlist.id, paging))?
+                }
+                None => Vec::new(),
+            }
+        }
+    };
+    if tracks.is_empty() {
+        Err(not_found())
+    } else {
+        Ok(tracks)
+    }
+}
+
+fn same_name(a: &str, b: &str) -> bool {
+    a.trim().to_lowercase() == b.trim().to_lowercase()
+}
+
+/// Follow page cursors until the listing ends or [`MAX_ITEMS`] is reached.
+pub(crate) fn collect_pages<T>(
+    mut fetch: impl FnMut(PageRequest) -> ProviderResult<Page<T>>,
+) -> ProviderResult<Vec<T>> {
+    let mut items = Vec::new();
+    let mut paging = PageRequest::first_page(PAGE_SIZE);
+    loop {
+        let page = fetch(paging)?;
+        items.extend(page.items);
+        let next = page
+            .next
+            .and_then(|cursor| PageRequest::from_cursor(&cursor, PAGE_SIZE));
+        match next {
+            Some(next) if items.len() < MAX_ITEMS => paging = next,
+            _ => break,
+        }
+    }
+    items.truncate(MAX_ITEMS);
+    Ok(items)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tunez_core::{
+        Album, AlbumId, PageCursor, Playlist, PlaylistId, ProviderCapabilities, StreamUrl,
+    };
+
+    fn track(id: &str, title: &str, artist: &str, album: &str, number: u32) -> Track {
+        Track {
+            id: TrackId::new(id),
+            provider_id: "fake".into(),
+            title: title.into(),
+            artist: artist.into(),
+            album: Some(album.into()),
+            duration_seconds: None,
+            track_number: Some(number),
+            disc_number: None,
+            year: None,
+            genre: None,
+            musicbrainz_id: None,
+            replay_gain: Default::default(),
+        }
+    }
+
+    /// A small in-memory library that pages one item at a time, so the
+    /// resolver has to follow cursors.
+    struct FakeProvider {
+        tracks: Vec<Track>,
+    }
+
+    impl FakeProvider {
+        fn new() -> Self {
+            Self {
+                tracks: vec![
+                    track("1", "Intro", "Alpha", "First", 1),
+                    track("2", "Home", "Alpha", "First", 2),
+                    track("3", "Home Again", "Beta", "Second", 1),
+                    track("4", "Home", "Beta", "Second", 2),
+                ],
+            }
+        }
+
+        fn page<T: Clone>(items: &[T], paging: PageRequest) -> ProviderResult<Page<T>> {
+            let start = paging.offset as usize;
+            let end = (start + 1).min(items.len());
+            Ok(Page {
+                items: items[start.min(end)..end].to_vec(),
+                next: (end < items.len()).then(|| PageCursor(end.to_string())),
+            })
+        }
+
+        fn album_tracks(&self, album: &str) -> Vec<Track> {
+            let mut tracks: Vec<Track> = self
+                .tracks
+                .iter()
+                .filter(|t| t.album.as_deref() == Some(album))
+                .cloned()
+                .collect();
+            tracks.sort_by(Track::album_order);
+            tracks
+        }
+    }
+
+    impl Provider for FakeProvider {
+        fn id(&self) -> &str {
+            "fake"
+        }
+        fn name(&self) -> &str {
+            "Fake"
+        }
+        fn capabilities(&self) -> ProviderCapabilities {
+            ProviderCapabilities::default()
+        }
+        fn search_tracks(
+            &self,
+            query: &str,
+            _filters: TrackSearchFilters,
+            paging: PageRequest,
+        ) -> ProviderResult<Page<Track>> {
+            let q = query.to_lowercase();
+            let hits: Vec<Track> = self
+                .tracks
+                .iter()
+                .filter(|t| {
+                    t.title.to_lowercase().contains(&q) || t.artist.to_lowercase().contains(&q)
+                })
+                .cloned()
+                .collect();
+            Self::page(&hits, paging)
+        }
+        fn browse(
+            &self,
+            kind: BrowseKind,
+            paging: PageRequest,
+        ) -> ProviderResult<Page<CollectionItem>> {
+            if kind != BrowseKind::Albums {
+                return Err(ProviderError::NotSupported {
+                    operation: "browse".into(),
+                });
+            }
+            let albums: Vec<CollectionItem> = [("First", "Alpha"), ("Second", "Beta")]
+                .into_iter()
+                .map(|(title, artist)| {
+                    CollectionItem::Album(Album {
+                        id: AlbumId::new(title),
+                        provider_id: "fake".into(),
+                        title: title.into(),
+                        artist: artist.into(),
+                        track_count: None,
+                        duration_seconds: None,
+                        year: None,
+                        added_at: None,
+                    })
+                })
+                .collect();
+            Self::page(&albums, paging)
+        }
+        fn list_playlists(&self, paging: PageRequest) -> ProviderResult<Page<Playlist>> {
+            let playlists = vec![Playlist {
+                id: PlaylistId::new("mix"),
+                name: "Road Trip".into(),
+                ..Default::default()
+            }];
+            Self::page(&playlists, paging)
+        }
+        fn search_playlists(
+            &self,
+            _query: &str,
+            paging: PageRequest,
+        ) -> ProviderResult<Page<Playlist>> {
+            self.list_playlists(paging)
+        }
+        fn get_playlist(&self, _playlist_id: &PlaylistId) -> ProviderResult<Playlist> {
+            unimplemented!()
+        }
+        fn list_playlist_tracks(
+            &self,
+            _playlist_id: &PlaylistId,
+            paging: PageRequest,
+        ) -> ProviderResult<Page<Track>> {
+            let mix = vec![self.tracks[3].clone(), self.tracks[0].clone()];
+            Self::page(&mix, paging)
+        }
+        fn get_album(&self, _album_id: &AlbumId) -> ProviderResult<Album> {
+            unimplemented!()
+        }
+        fn list_album_tracks(
+            &self,
+            album_id: &AlbumId,
+            paging: PageRequest,
+        ) -> ProviderResult<Page<Track>> {
+            Self::page(&self.album_tracks(&album_id.0), paging)
+        }
+        fn get_track(&self, track_id: &TrackId) -> ProviderResult<Track> {
+            self.tracks
+                .iter()
+                .find(|t| t.id == *track_id)
+                .cloned()
+                .ok_or_else(|| ProviderError::NotFound {
+                    entity: track_id.0.clone(),
+                })
+        }
+        fn get_stream_url(&self, _track_id: &TrackId) -> ProviderResult<StreamUrl> {
+            unimplemented!()
+        }
+    }
+
+    fn ids(tracks: Vec<Track>) -> Vec<String> {
+        tracks.into_iter().map(|t| t.id.0).collect()
+    }
+
+    #[test]
+    fn id_resolves_via_get_track() {
+        let provider = FakeProvider::new();
+        let selector = PlaySelector::Id { id: "3".into() };
+        assert_eq!(ids(resolve_selector(&provider, &selector).unwrap()), ["3"]);
+
+        let missing = PlaySelector::Id { id: "99".into() };
+        assert!(matches!(
+            resolve_selector(&provider, &missing),
+            Err(ResolveError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn track_search_prefers_exact_title_and_honours_artist() {
+        let provider = FakeProvider::new();
+        let selector = PlaySelector::TrackSearch {
+            track: "home".into(),
+            artist: Some("beta".into()),
+            album: None,
+        };
+        assert_eq!(ids(resolve_selector(&provider, &selector).unwrap()), ["4"]);
+
+        let selector = PlaySelector::TrackSearch {
+            track: "home".into(),
+            artist: Some("Gamma".into()),
+            album: None,
+        };
+        assert!(matches!(
+            resolve_selector(&provider, &selector),
+            Err(ResolveError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn album_search_queues_album_in_order() {
+        let provider = FakeProvider::new();
+        let selector = PlaySelector::AlbumSearch {
+            album: "first".into(),
+            artist: Some("Alpha".into()),
+        };
+        assert_eq!(
+            ids(resolve_selector(&provider, &selector).unwrap()),
+            ["1", "2"]
+        );
+
+        let wrong_artist = PlaySelector::AlbumSearch {
+            album: "first".into(),
+            artist: Some("Beta".into()),
+        };
+        assert!(resolve_selector(&provider, &wrong_artist).is_err());
+    }
+
+    #[test]
+    fn artist_search_queues_all_artist_tracks() {
+        let provider = FakeProvider::new();
+        let selector = PlaySelector::ArtistSearch {
+            artist: "BETA".into(),
+        };
+        assert_eq!(
+            ids(resolve_selector(&provider, &selector).unwrap()),
+            ["3", "4"]
+        );
+    }
+
+    #[test]
+    fn playlist_resolves_by_name() {
+        let provider = FakeProvider::new();
+        let selector = PlaySelector::Playlist {
+            name: "road trip".into(),
+        };
+        assert_eq!(
+            ids(resolve_selector(&provider, &selector).unwrap()),
+            ["4", "1"]
+        );
+
+        let missing = PlaySelector::Playlist {
+            name: "Nope".into(),
+        };
+        let err = resolve_selector(&provider, &missing).unwrap_err();
+        assert_eq!(err.to_string(), "playlist=\"Nope\" not found");
+    }
+}
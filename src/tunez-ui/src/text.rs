@@ -0,0 +1,134 @@
+//! Fitting text into terminal cells.
+//!
+//! Rows are measured in cells rather than chars: CJK characters and most
+//! emoji take two, combining marks none. Cutting only between graphemes
+//! keeps an accent with its letter and never splits a wide character,
+//! which ratatui would otherwise do when it clips a row at the border.
+
+use std::borrow::Cow;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+const ELLIPSIS: &str = "…";
+
+/// Gap between the end of a scrolling text and its start coming round again.
+const MARQUEE_GAP: &str = "   ";
+
+/// `text` as it fits in `width` cells: unchanged when it already does,
+/// otherwise cut on a grapheme boundary and ended with an ellipsis.
+pub(crate) fn truncate(text: &str, width: usize) -> Cow<'_, str> {
+    if text.width() <= width {
+        return Cow::Borrowed(text);
+    }
+    let Some(budget) = width.checked_sub(ELLIPSIS.width()) else {
+        return Cow::Borrowed("");
+    };
+    let mut used = 0;
+    let mut end = 0;
+    for grapheme in text.graphemes(true) {
+        let cells = grapheme.width();
+        if used + cells > budget {
+            break;
+        }
+        used += cells;
+        end += grapheme.len();
+    }
+    Cow::Owned(format!("{}{ELLIPSIS}", &text[..end]))
+}
+
+/// The `width` cells of `text` to show at scroll position `step`. Text that
+/// fits is returned as is; longer text scrolls one grapheme per step and
+/// wraps round after `MARQUEE_GAP`. A wide character that would straddle
+/// the right edge is left for the next step and its cell padded.
+pub(crate) fn marquee(text: &str, width: usize, step: usize) -> String {
+    if text.width() <= width {
+        return text.to_string();
+    }
+    let graphemes: Vec<&str> = text
+        .graphemes(true)
+        .chain(MARQUEE_GAP.graphemes(true))
+        .collect();
+    let mut shown = String::new();
+    let mut used = 0;
+    for grapheme in graphemes.iter().cycle().skip(step % graphemes.len()) {
+        let cells = grapheme.width();
+        if used + cells > width {
+            break;
+        }
+        used += cells;
+        shown.push_str(grapheme);
+    }
+    shown.extend(std::iter::repeat_n(' ', width - used));
+    shown
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ascii_is_cut_to_the_budget_with_an_ellipsis() {
+        assert_eq!(truncate("Hello world", 11), "Hello world");
+        assert!(matches!(truncate("Hello world", 20), Cow::Borrowed(_)));
+        assert_eq!(truncate("Hello world", 6), "Hello…");
+        assert_eq!(truncate("Hello world", 1), "…");
+        assert_eq!(truncate("Hello world", 0), "");
+    }
+
+    #[test]
+    fn double_width_characters_are_never_split() {
+        // Eight cells.
+        let title = "東京事変";
+        assert_eq!(truncate(title, 8), title);
+        assert_eq!(truncate(title, 7), "東京事…");
+        // Half of 事 would fit, but it isn't shown.
+        assert_eq!(truncate(title, 6), "東京…");
+        assert_eq!(truncate(title, 2), "…");
+        for width in 0..10 {
+            assert!(truncate(title, width).width() <= width, "{width}");
+        }
+    }
+
+    #[test]
+    fn emoji_and_combining_marks_stay_whole() {
+        let emoji = "🎵🎶🎸 Live";
+        assert_eq!(truncate(emoji, 4), "🎵…");
+        assert_eq!(truncate(emoji, 5), "🎵🎶…");
+        assert_eq!(truncate(emoji, 9), "🎵🎶🎸 L…");
+
+        // "e" and a combining acute accent are one grapheme in one cell.
+        let accented = "Cafe\u{301} del Mar";
+        assert_eq!(truncate(accented, 5), "Cafe\u{301}…");
+        assert_eq!(truncate(accented, 4), "Caf…");
+    }
+
+    #[test]
+    fn marquee_scrolls_only_text_that_does_not_fit() {
+        assert_eq!(marquee("Band - Song", 20, 7), "Band - Song");
+        assert_eq!(marquee("Band - Song", 11, 7), "Band - Song");
+
+        assert_eq!(marquee("abcdef", 4, 0), "abcd");
+        assert_eq!(marquee("abcdef", 4, 2), "cdef");
+        // Across the seam the gap shows, then the start comes round again.
+        assert_eq!(marquee("abcdef", 4, 4), "ef  ");
+        assert_eq!(marquee("abcdef", 4, 7), "  ab");
+        // One full cycle is the text plus the gap.
+        assert_eq!(marquee("abcdef", 4, 9), marquee("abcdef", 4, 0));
+        assert_eq!(marquee("ébc★", 2, 3), "★ ");
+    }
+
+    #[test]
+    fn marquee_steps_by_grapheme_and_fills_its_cells() {
+        let title = "東京事変 Live";
+        assert_eq!(marquee(title, 5, 0), "東京 ");
+        assert_eq!(marquee(title, 5, 1), "京事 ");
+        assert_eq!(marquee(title, 5, 3), "変 Li");
+        for step in 0..20 {
+            assert_eq!(marquee(title, 5, step).width(), 5, "{step}");
+        }
+
+        // The accent moves with its letter.
+        let accented = "Cafe\u{301} del Mar";
+        assert_eq!(marquee(accented, 4, 3), "e\u{301} de");
+    }
+}
@@ -1,8 +1,55 @@
 use ratatui::style::Color;
 use std::env;
+use thiserror::Error;
+use tunez_core::{CustomThemeColors, ThemeSetting};
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ThemeError {
+    #[error("invalid color '{value}' for theme.custom.{field}")]
+    InvalidColor { field: &'static str, value: String },
+}
+
+/// How many colors the terminal can show, from fewest to most.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ColorDepth {
+    /// No color at all: `NO_COLOR` is set or the terminal is `dumb`.
+    Mono,
+    /// The 16 ANSI colors.
+    Ansi16,
+    /// The xterm 256-color palette.
+    Ansi256,
+    /// 24-bit RGB.
+    TrueColor,
+}
+
+impl ColorDepth {
+    /// Detect the depth from `NO_COLOR`, `COLORTERM` and `TERM`.
+    pub fn detect() -> Self {
+        Self::from_env(
+            env::var_os("NO_COLOR").is_some(),
+            env::var("COLORTERM").ok().as_deref(),
+            env::var("TERM").ok().as_deref(),
+        )
+    }
+
+    fn from_env(no_color: bool, colorterm: Option<&str>, term: Option<&str>) -> Self {
+        let term = term.unwrap_or_default();
+        if no_color || term == "dumb" {
+            ColorDepth::Mono
+        } else if matches!(colorterm, Some("truecolor" | "24bit")) || term.contains("direct") {
+            ColorDepth::TrueColor
+        } else if term.contains("256color") {
+            ColorDepth::Ansi256
+        } else {
+            ColorDepth::Ansi16
+        }
+    }
+}
 
 #[derive(Debug, Clone, Copy)]
 pub struct Theme {
+    /// Name used in config and when cycling themes.
+    pub name: &'static str,
     pub primary: Color,
     pub secondary: Color,
     pub accent: Color,
@@ -16,6 +63,7 @@ pub struct Theme {
 impl Default for Theme {
     fn default() -> Self {
         Self {
+            name: "default",
             primary: Color::Cyan,
             secondary: Color::Gray,
             accent: Color::Magenta,
@@ -31,6 +79,7 @@ impl Default for Theme {
 impl Theme {
     pub fn monochrome() -> Self {
         Self {
+            name: "monochrome",
             primary: Color::White,
             secondary: Color::Gray,
             accent: Color::White, // No color differentiation
@@ -44,6 +93,7 @@ impl Theme {
 
     pub fn afterdark() -> Self {
         Self {
+            name: "afterdark",
             primary: Color::LightMagenta,
             secondary: Color::DarkGray,
             accent: Color::LightCyan,
@@ -57,6 +107,7 @@ impl Theme {
 
     pub fn solarized() -> Self {
         Self {
+            name: "solarized",
             primary: Color::Cyan,
             secondary: Color::Yellow,
             accent: Color::Magenta,
@@ -68,6 +119,45 @@ impl Theme {
         }
     }
 
+    /// Build the `custom` theme from config colors on top of the default theme.
+    pub fn custom(colors: &CustomThemeColors) -> Result<Self, ThemeError> {
+        let base = Self::default();
+        let pick = |field, value: &Option<String>, fallback| match value {
+            Some(value) => parse_color(value).ok_or_else(|| ThemeError::InvalidColor {
+                field,
+                value: value.clone(),
+            }),
+            None => Ok(fallback),
+        };
+        Ok(Self {
+            name: "custom",
+            primary: pick("primary", &colors.primary, base.primary)?,
+            secondary: pick("secondary", &colors.secondary, base.secondary)?,
+            accent: pick("accent", &colors.accent, base.accent)?,
+            error: pick("error", &colors.error, base.error)?,
+            success: pick("success", &colors.success, base.success)?,
+            text: pick("text", &colors.text, base.text)?,
+            ..base
+        })
+    }
+
+    /// The theme selected by config, plus the custom theme if one is defined
+    /// so it can join the theme rotation.
+    pub fn from_settings(
+        setting: Option<&ThemeSetting>,
+    ) -> Result<(Self, Option<Self>), ThemeError> {
+        let custom = setting
+            .and_then(ThemeSetting::custom)
+            .map(Self::custom)
+            .transpose()?;
+        let name = setting.and_then(ThemeSetting::name);
+        let theme = match (name, custom) {
+            (Some("custom"), Some(custom)) if env::var("NO_COLOR").is_err() => custom,
+            _ => Self::from_config(name),
+        };
+        Ok((theme, custom))
+    }
+
     pub fn from_config(name: Option<&str>) -> Self {
         // Enforce NO_COLOR standard (see no-color.org)
         if env::var("NO_COLOR").is_ok() {
@@ -101,6 +191,137 @@ impl Theme {
     pub fn available_themes() -> &'static [&'static str] {
         &["default", "monochrome", "afterdark", "solarized"]
     }
+
+    /// This theme with its colors reduced to what a terminal of `depth` can
+    /// show. Without color it falls back to the monochrome palette.
+    pub fn for_depth(self, depth: ColorDepth) -> Self {
+        if depth == ColorDepth::Mono {
+            return Self {
+                name: self.name,
+                ..Self::monochrome()
+            };
+        }
+        let fit = |color| fit_color(color, depth);
+        Self {
+            primary: fit(self.primary),
+            secondary: fit(self.secondary),
+            accent: fit(self.accent),
+            error: fit(self.error),
+            success: fit(self.success),
+            background: fit(self.background),
+            text: fit(self.text),
+            ..self
+        }
+    }
+
+    /// The theme after this one in the rotation of built-ins, followed by
+    /// `custom` when one is configured.
+    pub fn next(&self, custom: Option<&Theme>) -> Self {
+        let mut rotation: Vec<Theme> = Self::available_themes()
+            .iter()
+            .filter_map(|name| Self::parse(name))
+            .collect();
+        rotation.extend(custom.copied());
+        let idx = rotation
+            .iter()
+            .position(|theme| theme.name == self.name)
+            .map_or(0, |idx| (idx + 1) % rotation.len());
+        rotation[idx]
+    }
+}
+
+/// The 16 ANSI colors with their usual xterm RGB values.
+const ANSI16: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::Red, (205, 0, 0)),
+    (Color::Green, (0, 205, 0)),
+    (Color::Yellow, (205, 205, 0)),
+    (Color::Blue, (0, 0, 238)),
+    (Color::Magenta, (205, 0, 205)),
+    (Color::Cyan, (0, 205, 205)),
+    (Color::Gray, (229, 229, 229)),
+    (Color::DarkGray, (127, 127, 127)),
+    (Color::LightRed, (255, 0, 0)),
+    (Color::LightGreen, (0, 255, 0)),
+    (Color::LightYellow, (255, 255, 0)),
+    (Color::LightBlue, (92, 92, 255)),
+    (Color::LightMagenta, (255, 0, 255)),
+    (Color::LightCyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+/// Channel levels of the 6x6x6 cube in the 256-color palette.
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// `color` as the nearest one a terminal of `depth` can show. Named ANSI
+/// colors and `Reset` are left alone.
+pub fn fit_color(color: Color, depth: ColorDepth) -> Color {
+    let rgb = match color {
+        Color::Rgb(r, g, b) => (r, g, b),
+        Color::Indexed(index) if index >= 16 => indexed_rgb(index),
+        Color::Indexed(index) => return ANSI16[usize::from(index)].0,
+        other => return other,
+    };
+    match depth {
+        ColorDepth::TrueColor => color,
+        ColorDepth::Ansi256 if matches!(color, Color::Indexed(_)) => color,
+        ColorDepth::Ansi256 => Color::Indexed(nearest_256(rgb)),
+        ColorDepth::Ansi16 | ColorDepth::Mono => nearest_ansi16(rgb),
+    }
+}
+
+fn distance((r1, g1, b1): (u8, u8, u8), (r2, g2, b2): (u8, u8, u8)) -> u32 {
+    let d = |a: u8, b: u8| (i32::from(a) - i32::from(b)).unsigned_abs().pow(2);
+    d(r1, r2) + d(g1, g2) + d(b1, b2)
+}
+
+fn nearest_ansi16(rgb: (u8, u8, u8)) -> Color {
+    ANSI16
+        .iter()
+        .min_by_key(|(_, ansi)| distance(rgb, *ansi))
+        .map_or(Color::Reset, |(color, _)| *color)
+}
+
+/// Nearest entry in the cube (16-231) or the grey ramp (232-255).
+fn nearest_256((r, g, b): (u8, u8, u8)) -> u8 {
+    let level = |c: u8| {
+        (0..CUBE_LEVELS.len())
+            .min_by_key(|&i| CUBE_LEVELS[i].abs_diff(c))
+            .unwrap_or(0)
+    };
+    let (ri, gi, bi) = (level(r), level(g), level(b));
+    let cube = (16 + 36 * ri + 6 * gi + bi) as u8;
+    let average = ((u16::from(r) + u16::from(g) + u16::from(b)) / 3) as u8;
+    let grey = 232 + (average.saturating_sub(3) / 10).min(23);
+    if distance((r, g, b), indexed_rgb(grey)) < distance((r, g, b), indexed_rgb(cube)) {
+        grey
+    } else {
+        cube
+    }
+}
+
+/// RGB of a 256-color palette entry from 16 up.
+fn indexed_rgb(index: u8) -> (u8, u8, u8) {
+    if index >= 232 {
+        let level = 8 + (index - 232) * 10;
+        return (level, level, level);
+    }
+    let cube = usize::from(index.saturating_sub(16));
+    (
+        CUBE_LEVELS[cube / 36],
+        CUBE_LEVELS[(cube / 6) % 6],
+        CUBE_LEVELS[cube % 6],
+    )
+}
+
+/// Parse `#rrggbb` hex or a color name such as `cyan` or `light-red`.
+pub fn parse_color(value: &str) -> Option<Color> {
+    let value = value.trim();
+    // Indexed colors ("42") are accepted by ratatui but not documented here.
+    if value.starts_with('#') && value.len() != 7 {
+        return None;
+    }
+    value.parse().ok()
 }
 
 #[cfg(test)]
@@ -146,6 +367,123 @@ mod tests {
         assert!(Theme::parse("unknown").is_none());
     }
 
+    #[test]
+    fn parses_hex_and_named_colors() {
+        assert_eq!(parse_color("#ff8800"), Some(Color::Rgb(0xff, 0x88, 0x00)));
+        assert_eq!(parse_color(" #00FF7f "), Some(Color::Rgb(0, 0xff, 0x7f)));
+        assert_eq!(parse_color("light-red"), Some(Color::LightRed));
+        assert_eq!(parse_color("Cyan"), Some(Color::Cyan));
+    }
+
+    #[test]
+    fn rejects_malformed_colors() {
+        assert_eq!(parse_color("#ff880"), None);
+        assert_eq!(parse_color("#gg0000"), None);
+        assert_eq!(parse_color("ff8800"), None);
+        assert_eq!(parse_color("not-a-color"), None);
+        let colors = CustomThemeColors {
+            success: Some("#12345".into()),
+            ..Default::default()
+        };
+        assert_eq!(
+            Theme::custom(&colors).unwrap_err(),
+            ThemeError::InvalidColor {
+                field: "success",
+                value: "#12345".into()
+            }
+        );
+    }
+
+    #[test]
+    fn custom_theme_fills_unset_colors_from_default() {
+        let colors = CustomThemeColors {
+            primary: Some("#102030".into()),
+            text: Some("white".into()),
+            ..Default::default()
+        };
+        let theme = Theme::custom(&colors).unwrap();
+        assert_eq!(theme.name, "custom");
+        assert_eq!(theme.primary, Color::Rgb(0x10, 0x20, 0x30));
+        assert_eq!(theme.text, Color::White);
+        assert_eq!(theme.secondary, Theme::default().secondary);
+    }
+
+    #[test]
+    fn cycling_includes_custom_theme_by_name() {
+        let custom = Theme::custom(&CustomThemeColors::default()).unwrap();
+        let mut names = Vec::new();
+        let mut theme = Theme::default();
+        for _ in 0..5 {
+            theme = theme.next(Some(&custom));
+            names.push(theme.name);
+        }
+        assert_eq!(
+            names,
+            ["monochrome", "afterdark", "solarized", "custom", "default"]
+        );
+        assert_eq!(Theme::solarized().next(None).name, "default");
+    }
+
+    #[test]
+    fn truecolor_maps_to_nearest_ansi16() {
+        let orange = Color::Rgb(0xff, 0x88, 0x00);
+        assert_eq!(fit_color(orange, ColorDepth::Ansi16), Color::Yellow);
+        assert_eq!(
+            fit_color(Color::Rgb(250, 10, 10), ColorDepth::Ansi16),
+            Color::LightRed
+        );
+        assert_eq!(
+            fit_color(Color::Rgb(0x10, 0x20, 0x30), ColorDepth::Ansi16),
+            Color::Black
+        );
+        assert_eq!(
+            fit_color(Color::Indexed(196), ColorDepth::Ansi16),
+            Color::LightRed
+        );
+        // Named colors are already in range; truecolor is kept when supported.
+        assert_eq!(fit_color(Color::Cyan, ColorDepth::Ansi16), Color::Cyan);
+        assert_eq!(fit_color(orange, ColorDepth::TrueColor), orange);
+        assert_eq!(fit_color(orange, ColorDepth::Ansi256), Color::Indexed(208));
+        assert_eq!(
+            fit_color(Color::Rgb(128, 128, 128), ColorDepth::Ansi256),
+            Color::Indexed(244)
+        );
+    }
+
+    #[test]
+    fn themes_follow_the_detected_depth() {
+        assert_eq!(
+            ColorDepth::from_env(false, Some("truecolor"), Some("xterm-256color")),
+            ColorDepth::TrueColor
+        );
+        assert_eq!(
+            ColorDepth::from_env(false, None, Some("xterm-256color")),
+            ColorDepth::Ansi256
+        );
+        assert_eq!(
+            ColorDepth::from_env(false, None, Some("xterm")),
+            ColorDepth::Ansi16
+        );
+        assert_eq!(
+            ColorDepth::from_env(true, Some("truecolor"), None),
+            ColorDepth::Mono
+        );
+        assert_eq!(
+            ColorDepth::from_env(false, None, Some("dumb")),
+            ColorDepth::Mono
+        );
+
+        let custom = Theme::custom(&CustomThemeColors {
+            primary: Some("#ff8800".into()),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(custom.for_depth(ColorDepth::Ansi16).primary, Color::Yellow);
+        let mono = custom.for_depth(ColorDepth::Mono);
+        assert_eq!(mono.name, "custom");
+        assert!(!mono.is_color);
+    }
+
     #[test]
     fn test_available_themes() {
         let themes = Theme::available_themes();
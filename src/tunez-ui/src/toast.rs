@@ -0,0 +1,237 @@
+//! Short messages shown in the player bar, and the wording used when an
+//! error reaches the user.
+//!
+//! Errors are phrased as what happened and what to do about it; the
+//! technical detail (`detail`) goes to the log instead of the screen.
+
+use std::time::Duration;
+use tunez_audio::AudioError;
+use tunez_core::{redact_error, ProviderError, ScrobblerError};
+
+/// How a toast is styled and how long it stays up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Severity {
+    /// Confirmation of something the user did.
+    Info,
+    /// A failure that may go away by itself, like a dropped connection.
+    Transient,
+    /// A failure that needs the user to change something.
+    Persistent,
+}
+
+impl Severity {
+    pub(crate) fn duration(self) -> Duration {
+        match self {
+            Severity::Info => Duration::from_secs(3),
+            Severity::Transient => Duration::from_secs(5),
+            Severity::Persistent => Duration::from_secs(10),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Toast {
+    pub(crate) message: String,
+    pub(crate) severity: Severity,
+    /// The underlying error, for the log.
+    pub(crate) detail: Option<String>,
+}
+
+impl Toast {
+    pub(crate) fn info(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            severity: Severity::Info,
+            detail: None,
+        }
+    }
+
+    /// A failure the user can't act on beyond reading the log.
+    pub(crate) fn failure(message: impl Into<String>, error: impl std::fmt::Display) -> Self {
+        Self::error(message, Severity::Persistent, error)
+    }
+
+    fn error(
+        message: impl Into<String>,
+        severity: Severity,
+        error: impl std::fmt::Display,
+    ) -> Self {
+        Self {
+            message: message.into(),
+            severity,
+            detail: Some(redact_error(error)),
+        }
+    }
+
+    /// `subject` names what was being loaded or played ("Track", "Search").
+    pub(crate) fn provider(subject: &str, error: &ProviderError) -> Self {
+        let (message, severity) = match error {
+            ProviderError::NetworkError { .. } => (
+                format!("{subject}: can't reach the server; check your connection"),
+                Severity::Transient,
+            ),
+            ProviderError::AuthenticationError { .. } => (
+                "Sign in expired; check credentials".to_string(),
+                Severity::Persistent,
+            ),
+            ProviderError::NotFound { .. } => (
+                format!("{subject} no longer available"),
+                Severity::Persistent,
+            ),
+            ProviderError::NotSupported { .. } => (
+                format!("{subject}: not supported by this provider"),
+                Severity::Persistent,
+            ),
+            ProviderError::RateLimited {
+                retry_after: Some(delay),
+            } => (
+                format!(
+                    "{subject}: the server is busy; try again in {}s",
+                    delay.as_secs().max(1)
+                ),
+                Severity::Transient,
+            ),
+            ProviderError::RateLimited { retry_after: None } => (
+                format!("{subject}: the server is busy; try again shortly"),
+                Severity::Transient,
+            ),
+            ProviderError::Other { .. } => (
+                format!("{subject} failed; see the log for details"),
+                Severity::Persistent,
+            ),
+        };
+        Self::error(message, severity, error)
+    }
+
+    pub(crate) fn scrobbler(error: &ScrobblerError) -> Self {
+        let (message, severity) = match error {
+            ScrobblerError::NotConfigured => (
+                "Scrobbling isn't set up; check the scrobbling settings",
+                Severity::Persistent,
+            ),
+            ScrobblerError::Network { .. } => (
+                "Scrobble failed: can't reach the scrobbling service",
+                Severity::Transient,
+            ),
+            ScrobblerError::Authentication { .. } => (
+                "Scrobbling sign in expired; check credentials",
+                Severity::Persistent,
+            ),
+            ScrobblerError::RateLimited { .. } => {
+                ("Scrobble skipped: the service is busy", Severity::Transient)
+            }
+            ScrobblerError::Other { .. } => (
+                "Scrobble failed; see the log for details",
+                Severity::Persistent,
+            ),
+        };
+        Self::error(message, severity, error)
+    }
+
+    pub(crate) fn audio(error: &AudioError) -> Self {
+        let (message, severity) = match error {
+            AudioError::Backend(_) => (
+                "No audio output; check your sound device",
+                Severity::Persistent,
+            ),
+            AudioError::UnsupportedSource(_) => {
+                ("Can't play this track's format", Severity::Persistent)
+            }
+            AudioError::Io(_) => (
+                "Couldn't read the track; it may have moved",
+                Severity::Transient,
+            ),
+            AudioError::Other(_) => (
+                "Playback failed; see the log for details",
+                Severity::Persistent,
+            ),
+        };
+        Self::error(message, severity, error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn provider_errors_read_as_actions() {
+        let auth = Toast::provider(
+            "Search",
+            &ProviderError::AuthenticationError {
+                message: "401 Unauthorized".into(),
+            },
+        );
+        assert_eq!(auth.message, "Sign in expired; check credentials");
+        assert_eq!(auth.severity, Severity::Persistent);
+        assert_eq!(
+            auth.detail.as_deref(),
+            Some("authentication error: 401 Unauthorized")
+        );
+
+        let missing = Toast::provider(
+            "Track",
+            &ProviderError::NotFound {
+                entity: "track 42".into(),
+            },
+        );
+        assert_eq!(missing.message, "Track no longer available");
+
+        let offline = Toast::provider(
+            "Search",
+            &ProviderError::NetworkError {
+                message: "connection refused".into(),
+            },
+        );
+        assert_eq!(
+            offline.message,
+            "Search: can't reach the server; check your connection"
+        );
+        assert_eq!(offline.severity, Severity::Transient);
+        assert!(!offline.message.contains("connection refused"));
+
+        let busy = Toast::provider(
+            "Library",
+            &ProviderError::RateLimited {
+                retry_after: Some(std::time::Duration::from_secs(30)),
+            },
+        );
+        assert_eq!(
+            busy.message,
+            "Library: the server is busy; try again in 30s"
+        );
+        assert_eq!(busy.severity, Severity::Transient);
+    }
+
+    #[test]
+    fn scrobbler_and_audio_errors_are_worded_for_the_user() {
+        let limited = Toast::scrobbler(&ScrobblerError::RateLimited {
+            message: "429".into(),
+        });
+        assert_eq!(limited.message, "Scrobble skipped: the service is busy");
+        assert_eq!(limited.severity, Severity::Transient);
+
+        let auth = Toast::scrobbler(&ScrobblerError::Authentication {
+            message: "bad session key".into(),
+        });
+        assert_eq!(
+            auth.message,
+            "Scrobbling sign in expired; check credentials"
+        );
+        assert_eq!(auth.severity, Severity::Persistent);
+
+        let device = Toast::audio(&AudioError::Backend("no default output device".into()));
+        assert_eq!(device.message, "No audio output; check your sound device");
+        assert_eq!(device.severity, Severity::Persistent);
+        assert_eq!(
+            device.detail.as_deref(),
+            Some("audio backend unavailable: no default output device")
+        );
+    }
+
+    #[test]
+    fn persistent_toasts_stay_up_longer() {
+        assert!(Severity::Info.duration() < Severity::Transient.duration());
+        assert!(Severity::Transient.duration() < Severity::Persistent.duration());
+    }
+}
@@ -0,0 +1,107 @@
+//! Stand-in for a provider that failed to start, so the UI can still run.
+
+use tunez_core::{
+    Album, AlbumId, BrowseKind, CollectionItem, Page, PageRequest, Playlist, PlaylistId, Provider,
+    ProviderCapabilities, ProviderError, ProviderResult, StreamUrl, Track, TrackId,
+    TrackSearchFilters,
+};
+
+/// Answers every request with the error that kept the provider from starting.
+pub(crate) struct UnavailableProvider {
+    id: String,
+    reason: String,
+}
+
+impl UnavailableProvider {
+    pub(crate) fn new(id: impl Into<String>, reason: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            reason: reason.into(),
+        }
+    }
+
+    fn fail<T>(&self) -> ProviderResult<T> {
+        Err(ProviderError::Other {
+            message: format!("{} is unavailable: {}", self.id, self.reason),
+        })
+    }
+}
+
+impl Provider for UnavailableProvider {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn name(&self) -> &str {
+        "Unavailable"
+    }
+
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities::default()
+    }
+
+    fn search_tracks(
+        &self,
+        _query: &str,
+        _filters: TrackSearchFilters,
+        _paging: PageRequest,
+    ) -> ProviderResult<Page<Track>> {
+        self.fail()
+    }
+
+    fn browse(
+        &self,
+        _kind: BrowseKind,
+        _paging: PageRequest,
+    ) -> ProviderResult<Page<CollectionItem>> {
+        self.fail()
+    }
+
+    fn list_playlists(&self, _paging: PageRequest) -> ProviderResult<Page<Playlist>> {
+        self.fail()
+    }
+
+    fn search_playlists(
+        &self,
+        _query: &str,
+        _paging: PageRequest,
+    ) -> ProviderResult<Page<Playlist>> {
+        self.fail()
+    }
+
+    fn get_playlist(&self, _playlist_id: &PlaylistId) -> ProviderResult<Playlist> {
+        self.fail()
+    }
+
+    fn list_playlist_tracks(
+        &self,
+        _playlist_id: &PlaylistId,
+        _paging: PageRequest,
+    ) -> ProviderResult<Page<Track>> {
+        self.fail()
+    }
+
+    fn get_album(&self, _album_id: &AlbumId) -> ProviderResult<Album> {
+        self.fail()
+    }
+
+    fn list_album_tracks(
+        &self,
+        _album_id: &AlbumId,
+        _paging: PageRequest,
+    ) -> ProviderResult<Page<Track>> {
+        self.fail()
+    }
+
+    fn get_track(&self, _track_id: &TrackId) -> ProviderResult<Track> {
+        self.fail()
+    }
+
+    fn get_stream_url(&self, _track_id: &TrackId) -> ProviderResult<StreamUrl> {
+        self.fail()
+    }
+
+    fn health_check(&self) -> ProviderResult<()> {
+        self.fail()
+    }
+}
@@ -1,6 +1,9 @@
 //! Visualization system for Tunez music player.
 //!
 //! Provides multiple visualization modes and FFT computation for audio analysis.
+//! The analysis is also available without rendering, for headless tools:
+//! feed samples with [`Visualizer::add_samples`] and read
+//! [`Visualizer::spectrum_magnitudes`] or [`Visualizer::rms`].
 
 use ratatui::{
     style::Style,
@@ -12,6 +15,21 @@ use std::collections::VecDeque;
 use std::sync::{Arc, Mutex};
 use tunez_core::models::Track;
 
+/// Points per FFT window.
+const FFT_SIZE: usize = 1024;
+/// Bars drawn by the spectrum analyzer.
+const SPECTRUM_BARS: usize = 64;
+/// Frequency range the spectrum bars span, log-spaced.
+const SPECTRUM_MIN_HZ: f32 = 20.0;
+const SPECTRUM_MAX_HZ: f32 = 20_000.0;
+/// Samples kept for analysis unless [`Visualizer::with_buffer_capacity`] says
+/// otherwise.
+const DEFAULT_BUFFER_CAPACITY: usize = 2048;
+/// Assumed until playback reports the real rate.
+const DEFAULT_SAMPLE_RATE: u32 = 44_100;
+/// Samples drawn by the oscilloscope.
+const WAVEFORM_LEN: usize = 256;
+
 /// Different visualization modes available in Tunez
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum VizMode {
@@ -50,45 +68,106 @@ impl VizMode {
 pub struct Visualizer {
     /// Audio sample buffer (wrapped for thread safety)
     sample_buffer: Arc<Mutex<VecDeque<f32>>>,
+    /// Most samples `sample_buffer` holds; older ones are dropped first.
+    capacity: usize,
     /// Current visualization mode
     mode: VizMode,
     /// Current track for context
     current_track: Option<Track>,
     /// Animation phase for particle effects
     phase: f32,
+    /// Calls to `update_animation` so far.
+    frame: u64,
     /// FFT processor
     fft: Arc<dyn Fft<f32>>,
     /// Pre-computed Hann window
     window: Vec<f32>,
     /// Scratch buffer for FFT computation
     scratch: Arc<Mutex<Vec<Complex<f32>>>>,
+    /// Rate of the samples being fed in, which sets the Hz of each FFT bin.
+    sample_rate: u32,
+    /// Start the oscilloscope on a rising zero crossing.
+    trigger: bool,
 }
 
 impl Visualizer {
     pub fn new() -> Self {
+        Self::with_buffer_capacity(DEFAULT_BUFFER_CAPACITY)
+    }
+
+    /// A visualizer keeping the latest `capacity` samples (at least one).
+    /// Below the FFT size the spectrum is computed over the samples it has,
+    /// padded with silence.
+    pub fn with_buffer_capacity(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
         let mut planner = FftPlanner::new();
-        let fft = planner.plan_fft_forward(1024);
+        let fft = planner.plan_fft_forward(FFT_SIZE);
 
         // Pre-compute Hann window
-        let window: Vec<f32> = (0..1024)
-            .map(|i| {
-                let n = i as f32;
-                let len = 1024.0;
-                0.5 * (1.0 - (2.0 * std::f32::consts::PI * n / (len - 1.0)).cos())
-            })
-            .collect();
+        let window: Vec<f32> = (0..FFT_SIZE).map(|i| hann(i, FFT_SIZE)).collect();
 
         Self {
-            sample_buffer: Arc::new(Mutex::new(VecDeque::with_capacity(2048))),
+            sample_buffer: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+            capacity,
             mode: VizMode::Spectrum,
             current_track: None,
             phase: 0.0,
+            frame: 0,
             fft,
             window,
-            scratch: Arc::new(Mutex::new(vec![Complex::zero(); 1024])),
+            scratch: Arc::new(Mutex::new(vec![Complex::zero(); FFT_SIZE])),
+            sample_rate: DEFAULT_SAMPLE_RATE,
+            trigger: true,
         }
     }
 
+    /// Set the rate of the samples passed to `add_samples`, as reported by
+    /// the audio output when playback starts. Zero is ignored.
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        if sample_rate > 0 {
+            self.sample_rate = sample_rate;
+        }
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Samples in each FFT window; [`Visualizer::spectrum_magnitudes`] has
+    /// half as many bins.
+    pub fn fft_size(&self) -> usize {
+        FFT_SIZE
+    }
+
+    /// Width of each magnitude bin: `sample_rate / fft_size` Hz.
+    pub fn bin_hz(&self) -> f32 {
+        self.sample_rate as f32 / FFT_SIZE as f32
+    }
+
+    /// Lower and upper edge in Hz of each spectrum bar, log-spaced from 20 Hz
+    /// to 20 kHz or the Nyquist frequency, whichever is lower.
+    pub fn spectrum_bands(&self) -> Vec<(f32, f32)> {
+        let max = SPECTRUM_MAX_HZ.min(self.sample_rate as f32 / 2.0);
+        let ratio = (max / SPECTRUM_MIN_HZ).powf(1.0 / SPECTRUM_BARS as f32);
+        (0..SPECTRUM_BARS)
+            .map(|i| {
+                let lo = SPECTRUM_MIN_HZ * ratio.powi(i as i32);
+                (lo, lo * ratio)
+            })
+            .collect()
+    }
+
+    /// Start each oscilloscope frame where the signal next rises through
+    /// zero, so a steady tone stands still instead of drifting sideways.
+    /// On by default; frames without a crossing start at the oldest sample.
+    pub fn set_trigger_enabled(&mut self, enabled: bool) {
+        self.trigger = enabled;
+    }
+
+    pub fn trigger_enabled(&self) -> bool {
+        self.trigger
+    }
+
     /// Set the current visualization mode
     pub fn set_mode(&mut self, mode: VizMode) {
         self.mode = mode;
@@ -99,15 +178,18 @@ impl Visualizer {
         self.mode
     }
 
-    /// Add audio samples for visualization (thread-safe)
+    /// Add audio samples for visualization (thread-safe). Only the newest
+    /// `capacity` samples are kept, however large the chunk.
     pub fn add_samples(&self, samples: &[f32]) {
         let mut buffer = self.sample_buffer.lock().unwrap();
-        for &sample in samples {
-            if buffer.len() >= 2048 {
-                buffer.pop_front();
-            }
-            buffer.push_back(sample);
+        if samples.len() >= self.capacity {
+            buffer.clear();
+            buffer.extend(&samples[samples.len() - self.capacity..]);
+            return;
         }
+        buffer.extend(samples);
+        let excess = buffer.len().saturating_sub(self.capacity);
+        buffer.drain(..excess);
     }
 
     /// Set the current track for context
@@ -117,12 +199,18 @@ impl Visualizer {
 
     /// Update animation phase (called on each tick)
     pub fn update_animation(&mut self) {
+        self.frame = self.frame.wrapping_add(1);
         self.phase += 0.1;
         if self.phase > std::f32::consts::TAU {
             self.phase -= std::f32::consts::TAU;
         }
     }
 
+    /// Animation frames elapsed, for other widgets that animate in step.
+    pub fn animation_frame(&self) -> u64 {
+        self.frame
+    }
+
     /// Check if visualization should render based on terminal capabilities
     /// Returns true if visualization should be rendered, false if it should be skipped
     pub fn should_render(&self, width: u16, height: u16) -> bool {
@@ -160,55 +248,79 @@ impl Visualizer {
         }
     }
 
-    fn compute_spectrum(&self) -> VisualizationData {
-        let buffer_lock = self.sample_buffer.lock().unwrap();
-        // Take latest 1024 samples
-        let len = buffer_lock.len();
-        let skip = len.saturating_sub(1024);
+    /// Magnitude of each FFT bin over the latest [`fft_size`] samples, after a
+    /// Hann window, with fewer samples windowed over their own length and
+    /// then padded with silence. There are
+    /// `fft_size / 2` bins from 0 Hz up to Nyquist; bin `i` is centred on
+    /// `i * sample_rate / fft_size` Hz (see [`bin_hz`]), so at 48 kHz each
+    /// bin is 46.875 Hz wide. Needs no terminal to compute.
+    ///
+    /// [`fft_size`]: Visualizer::fft_size
+    /// [`bin_hz`]: Visualizer::bin_hz
+    pub fn spectrum_magnitudes(&self) -> Vec<f32> {
+        let mut input = self.windowed_input();
+        let mut scratch = self.scratch.lock().unwrap();
+        self.fft.process_with_scratch(&mut input, &mut scratch);
+
+        // The upper half mirrors the lower for real input.
+        input.iter().take(FFT_SIZE / 2).map(|c| c.norm()).collect()
+    }
 
-        let mut input: Vec<Complex<f32>> = buffer_lock
+    /// The latest samples after the Hann window, padded to the FFT size.
+    fn windowed_input(&self) -> Vec<Complex<f32>> {
+        let buffer = self.sample_buffer.lock().unwrap();
+        let skip = buffer.len().saturating_sub(FFT_SIZE);
+        let count = buffer.len() - skip;
+
+        // A window over all FFT_SIZE points would cut a shorter buffer off
+        // mid-slope at the padding, smearing its spectrum.
+        let mut input: Vec<Complex<f32>> = buffer
             .iter()
             .skip(skip)
-            .zip(self.window.iter())
-            .map(|(&s, &w)| Complex::new(s * w, 0.0))
+            .enumerate()
+            .map(|(i, &s)| {
+                let w = if count == FFT_SIZE {
+                    self.window[i]
+                } else {
+                    hann(i, count)
+                };
+                Complex::new(s * w, 0.0)
+            })
             .collect();
+        input.resize(FFT_SIZE, Complex::zero());
+        input
+    }
 
-        // Pad with zeros if not enough samples
-        while input.len() < 1024 {
-            input.push(Complex::zero());
+    /// Root mean square level of the latest [`fft_size`] samples, or 0.0
+    /// before any arrive. A full-scale sine reads about 0.707.
+    ///
+    /// [`fft_size`]: Visualizer::fft_size
+    pub fn rms(&self) -> f32 {
+        let buffer = self.sample_buffer.lock().unwrap();
+        let skip = buffer.len().saturating_sub(FFT_SIZE);
+        let count = buffer.len() - skip;
+        if count == 0 {
+            return 0.0;
         }
+        let sum: f32 = buffer.iter().skip(skip).map(|&s| s * s).sum();
+        (sum / count as f32).sqrt()
+    }
 
-        // Drop lock before expensive FFT
-        drop(buffer_lock);
-
-        // Run FFT
-        let mut scratch = self.scratch.lock().unwrap();
-        // Fft::process takes buffer as slice of Complex.
-        // It processes in-place or out-of-place depending on implementation,
-        // but rustfft `process` generally takes `&mut [Complex]`.
-        // We reuse the scratch buffer if needed, but here `input` is our proper buffer.
-        // `process` takes `input` and `scratch`.
-        let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
-            // Just safeguard against partial inputs, though we padded.
-        }));
-
-        self.fft.process_with_scratch(&mut input, &mut scratch);
-
-        // Compute magnitudes (first half is enough, symmetric)
-        // 512 bins from 0 to Nyquist.
-        // Map to 64 bars typically.
-        let magnitudes: Vec<f32> = input.iter().take(512).map(|c| c.norm()).collect();
-
-        // Map 512 bins to ~64 display bars
-        // Simple linear grouping for MVP, or log
-        // Let's do a simple grouping: 512 / 8 = 64
-        let bars: Vec<u64> = magnitudes
-            .chunks(8)
-            .map(|chunk| {
-                let sum: f32 = chunk.iter().sum();
+    fn compute_spectrum(&self) -> VisualizationData {
+        let magnitudes = self.spectrum_magnitudes();
+
+        // Each bar shows the loudest bin in its band. Low bands narrower than
+        // a bin still get the bin they fall in.
+        let bin_hz = self.bin_hz();
+        let bars: Vec<u64> = self
+            .spectrum_bands()
+            .into_iter()
+            .map(|(lo, hi)| {
+                let start = ((lo / bin_hz) as usize).min(magnitudes.len() - 1);
+                let end = ((hi / bin_hz).ceil() as usize).clamp(start + 1, magnitudes.len());
+                let peak = magnitudes[start..end].iter().copied().fold(0.0, f32::max);
                 // Scale for visual
-                let val = (sum * 2.0).min(100.0);
-                val as u64
+                (peak * 2.0).min(100.0) as u64
             })
             .collect();
 
@@ -217,9 +329,15 @@ impl Visualizer {
 
     fn compute_oscilloscope(&self) -> VisualizationData {
         let buffer = self.sample_buffer.lock().unwrap();
+        let start = if self.trigger {
+            rising_edge(&buffer).unwrap_or(0)
+        } else {
+            0
+        };
         let samples: Vec<u64> = buffer
             .iter()
-            .take(256) // Take a reasonable number of samples for waveform
+            .skip(start)
+            .take(WAVEFORM_LEN)
             .map(|&s| {
                 // Scale to 0-100 range for visualization
                 let scaled = (s + 1.0) * 50.0; // From [-1,1] to [0,100]
@@ -375,6 +493,21 @@ pub enum VisualizationData {
     Particles(Vec<(u16, u16, u8)>),
 }
 
+/// Point `i` of a Hann window `len` points long.
+fn hann(i: usize, len: usize) -> f32 {
+    if len < 2 {
+        return 1.0;
+    }
+    0.5 * (1.0 - (std::f32::consts::TAU * i as f32 / (len - 1) as f32).cos())
+}
+
+/// Index of the first rising zero crossing that leaves a whole waveform
+/// after it, if any.
+fn rising_edge(buffer: &VecDeque<f32>) -> Option<usize> {
+    let last_start = buffer.len().checked_sub(WAVEFORM_LEN)?;
+    (1..=last_start).find(|&i| buffer[i - 1] < 0.0 && buffer[i] >= 0.0)
+}
+
 impl Default for Visualizer {
     fn default() -> Self {
         Self::new()
@@ -413,52 +546,195 @@ mod tests {
     }
 
     #[test]
-    fn spectrum_detects_sine_wave() {
-        // Generate a sine wave at ~2200Hz (approx bin 50 of 1024 points at 44.1kHz)
-        // 1024 points.
-        // We just need a pure tone.
-        // If we have 1024 samples, and the sine wave completes N cycles in that window.
-        // frequency bin k = cycles * (SampleRate / N) ? No,
-        // bin k corresponds to k cycles per window.
-        // So if we inject a sine wave with 32 cycles in 1024 samples.
-        // Bin 32 should peak.
-        // 32 cycles in 1024 samples.
-        let hz = 32.0;
-        let samples: Vec<f32> = (0..2048) // Fill more than enough
-            .map(|i| {
-                let t = i as f32;
-                (t * hz * std::f32::consts::TAU / 1024.0).sin()
-            })
-            .collect();
-
+    fn large_chunks_keep_only_the_newest_samples() {
         let viz = Visualizer::new();
-        viz.add_samples(&samples);
+        viz.add_samples(&[0.25; 100]);
+        let chunk: Vec<f32> = (0..10_000).map(|i| i as f32).collect();
+        viz.add_samples(&chunk);
+        let buffer = viz.sample_buffer.lock().unwrap();
+        assert_eq!(buffer.len(), DEFAULT_BUFFER_CAPACITY);
+        assert!(buffer.iter().eq(&chunk[10_000 - DEFAULT_BUFFER_CAPACITY..]));
+        drop(buffer);
+
+        // Small chunks top up and push out the oldest.
+        viz.add_samples(&[-1.0, -2.0]);
+        let buffer = viz.sample_buffer.lock().unwrap();
+        assert_eq!(buffer.len(), DEFAULT_BUFFER_CAPACITY);
+        let oldest = (10_000 - DEFAULT_BUFFER_CAPACITY + 2) as f32;
+        assert_eq!(buffer.front(), Some(&oldest));
+        assert_eq!(buffer.back(), Some(&-2.0));
+    }
 
-        // Compute
-        let data = viz.compute();
-        match data {
+    #[test]
+    fn capacity_below_fft_size_is_zero_padded() {
+        let mut viz = Visualizer::with_buffer_capacity(512);
+        viz.set_sample_rate(48_000);
+        let hz = 100.0 * viz.bin_hz();
+        viz.add_samples(&tone(hz, 48_000));
+        assert_eq!(viz.sample_buffer.lock().unwrap().len(), 512);
+
+        let magnitudes = viz.spectrum_magnitudes();
+        assert_eq!(magnitudes.len(), viz.fft_size() / 2);
+        let peak = magnitudes
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .map(|(i, _)| i)
+            .unwrap();
+        assert!(peak.abs_diff(100) <= 1, "peak at bin {peak}");
+    }
+
+    /// Bar with the highest value in a spectrum.
+    fn peak_bar(viz: &Visualizer) -> usize {
+        match viz.compute() {
             VisualizationData::Spectrum(bars) => {
-                // We bucket 512 bins into 64 bars. 8 bins per bar.
-                // Bin 32 is in bar range [32/8] = 4.
-                // So bar 4 should have high value.
-                assert!(bars.len() >= 5);
-                // Check if peak is roughly where expected
-                // Note: Windowing might spread energy slightly, but 4 should be high.
-                let peak_idx = bars
-                    .iter()
+                assert_eq!(bars.len(), SPECTRUM_BARS);
+                bars.iter()
                     .enumerate()
                     .max_by_key(|(_, &v)| v)
                     .map(|(i, _)| i)
-                    .unwrap();
-                println!("Peak index: {}", peak_idx);
-                // Allow +/- 1 bar tolerance
-                assert!(
-                    (peak_idx as i32 - 4).abs() <= 1,
-                    "Expected peak around bar 4, got {}",
-                    peak_idx
-                );
+                    .unwrap()
             }
             _ => panic!("Wrong mode"),
         }
     }
+
+    /// Bar whose band contains `hz`.
+    fn bar_for(viz: &Visualizer, hz: f32) -> usize {
+        viz.spectrum_bands()
+            .iter()
+            .position(|&(lo, hi)| lo <= hz && hz < hi)
+            .unwrap()
+    }
+
+    fn waveform(viz: &Visualizer) -> Vec<u64> {
+        match viz.compute() {
+            VisualizationData::Waveform(samples) => samples,
+            _ => panic!("expected a waveform"),
+        }
+    }
+
+    #[test]
+    fn triggered_frames_start_at_the_same_phase() {
+        // 441 Hz at 44.1 kHz: a period of 100 samples.
+        let sine = |i: usize| (i as f32 * 441.0 * std::f32::consts::TAU / 44_100.0).sin();
+        let mut viz = Visualizer::new();
+        viz.set_mode(VizMode::Oscilloscope);
+        viz.add_samples(&(0..2048).map(|i| sine(i + 25)).collect::<Vec<_>>());
+        let first = waveform(&viz);
+        // A third of a period later, as the next frame would arrive.
+        viz.add_samples(&(2048..2081).map(|i| sine(i + 25)).collect::<Vec<_>>());
+        let second = waveform(&viz);
+
+        assert_eq!(first.len(), WAVEFORM_LEN);
+        assert_eq!(second.len(), WAVEFORM_LEN);
+        for (a, b) in first.iter().zip(&second) {
+            assert!(a.abs_diff(*b) <= 1, "{first:?}\n{second:?}");
+        }
+        // Rising from the middle line.
+        assert!(
+            first[0].abs_diff(50) <= 4 && first[10] > first[0],
+            "{first:?}"
+        );
+
+        viz.set_trigger_enabled(false);
+        let untriggered = waveform(&viz);
+        assert!(untriggered[0].abs_diff(second[0]) > 10, "{untriggered:?}");
+    }
+
+    #[test]
+    fn frames_without_a_crossing_start_at_the_oldest_sample() {
+        let mut viz = Visualizer::new();
+        viz.set_mode(VizMode::Oscilloscope);
+        let ramp: Vec<f32> = (0..300).map(|i| i as f32 / 300.0).collect();
+        viz.add_samples(&ramp);
+        assert_eq!(waveform(&viz)[0], 50);
+    }
+
+    fn tone(hz: f32, sample_rate: u32) -> Vec<f32> {
+        (0..2048)
+            .map(|i| (i as f32 * hz * std::f32::consts::TAU / sample_rate as f32).sin())
+            .collect()
+    }
+
+    #[test]
+    fn spectrum_detects_sine_wave() {
+        // 2 kHz at 48 kHz is bin 42.67; windowing spreads it a little.
+        let mut viz = Visualizer::new();
+        viz.set_sample_rate(48_000);
+        viz.add_samples(&tone(2_000.0, 48_000));
+        let expected = bar_for(&viz, 2_000.0);
+        let peak = peak_bar(&viz);
+        assert!(
+            peak.abs_diff(expected) <= 1,
+            "Expected peak around bar {expected}, got {peak}"
+        );
+    }
+
+    #[test]
+    fn magnitudes_peak_at_the_sine_bin() {
+        let mut viz = Visualizer::new();
+        viz.set_sample_rate(48_000);
+        // Centred on bin 100: 100 * 48000 / 1024 Hz.
+        let hz = 100.0 * viz.bin_hz();
+        viz.add_samples(&tone(hz, 48_000));
+
+        let magnitudes = viz.spectrum_magnitudes();
+        assert_eq!(magnitudes.len(), viz.fft_size() / 2);
+        let peak = magnitudes
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .map(|(i, _)| i)
+            .unwrap();
+        assert_eq!(peak, (hz / viz.bin_hz()).round() as usize);
+    }
+
+    #[test]
+    fn short_buffers_are_windowed_over_their_own_length() {
+        let viz = Visualizer::new();
+        viz.add_samples(&[1.0; 301]);
+
+        let input = viz.windowed_input();
+        assert_eq!(input.len(), viz.fft_size());
+        // Tapered to silence at both ends, full height in the middle.
+        assert!(input[0].re.abs() < 1e-6);
+        assert!((input[150].re - 1.0).abs() < 1e-6, "{}", input[150].re);
+        assert!(input[300].re.abs() < 1e-6, "{}", input[300].re);
+        assert!(input[301..].iter().all(|c| *c == Complex::zero()));
+    }
+
+    #[test]
+    fn rms_of_a_constant_signal_is_its_level() {
+        let viz = Visualizer::new();
+        assert_eq!(viz.rms(), 0.0);
+        // sqrt(mean(0.5²)) = 0.5, including when the buffer holds more than
+        // one window.
+        viz.add_samples(&[-0.5; 3000]);
+        assert!((viz.rms() - 0.5).abs() < 1e-6, "{}", viz.rms());
+    }
+
+    #[test]
+    fn sample_rate_sets_the_frequency_of_each_bar() {
+        // The same samples read as twice the rate are an octave higher.
+        let samples = tone(2_000.0, 44_100);
+        let mut viz = Visualizer::new();
+        viz.add_samples(&samples);
+        let at_44k = peak_bar(&viz);
+        viz.set_sample_rate(88_200);
+        let at_88k = peak_bar(&viz);
+        assert!(at_88k > at_44k, "{at_88k} should be above {at_44k}");
+        assert!(at_88k.abs_diff(bar_for(&viz, 4_000.0)) <= 1);
+
+        // A fixed tone lands in the same bar at any rate.
+        for rate in [44_100, 48_000, 96_000] {
+            let mut viz = Visualizer::new();
+            viz.set_sample_rate(rate);
+            viz.add_samples(&tone(4_000.0, rate));
+            assert!(
+                peak_bar(&viz).abs_diff(bar_for(&viz, 4_000.0)) <= 1,
+                "rate {rate}"
+            );
+        }
+    }
 }